@@ -25,5 +25,5 @@ python script/headless.py reftest
 python script/headless.py rawtest
 python script/headless.py test_invalidation
 CXX=clang++ cargo run ${CARGOFLAGS} --release --features=software -- \
-  --software --headless reftest
+  --headless-sw reftest
 popd
@@ -199,6 +199,7 @@ impl Example for App {
                             }
                         ],
                         colors: vec![],
+                        clip_regions: vec![],
                     },
                 );
                 txn.generate_frame(0, RenderReasons::empty());
@@ -3311,6 +3311,7 @@ pub fn ast_to_hir(state: &mut State, tu: &syntax::TranslationUnit) -> Translatio
     );
     declare_function(state, "abs", None, Type::new(Vec2), vec![Type::new(Vec2)]);
     declare_function(state, "abs", None, Type::new(Vec3), vec![Type::new(Vec3)]);
+    declare_function(state, "abs", None, Type::new(Vec4), vec![Type::new(Vec4)]);
     declare_function(state, "abs", None, Type::new(Float), vec![Type::new(Float)]);
     declare_function(state, "sign", None, Type::new(Vec2), vec![Type::new(Vec2)]);
     declare_function(state, "sign", None, Type::new(Vec3), vec![Type::new(Vec3)]);
@@ -3530,6 +3531,13 @@ pub fn ast_to_hir(state: &mut State, tu: &syntax::TranslationUnit) -> Translatio
         Type::new(Float),
         vec![Type::new(Vec2)],
     );
+    declare_function(
+        state,
+        "length",
+        None,
+        Type::new(Float),
+        vec![Type::new(Vec3)],
+    );
     declare_function(state, "pow", None, Type::new(Vec3), vec![Type::new(Vec3)]);
     declare_function(state, "pow", None, Type::new(Float), vec![Type::new(Float)]);
     declare_function(state, "exp", None, Type::new(Float), vec![Type::new(Float)]);
@@ -3798,6 +3806,13 @@ pub fn ast_to_hir(state: &mut State, tu: &syntax::TranslationUnit) -> Translatio
         Type::new(Vec2),
         vec![Type::new(Vec2)],
     );
+    declare_function(
+        state,
+        "normalize",
+        None,
+        Type::new(Vec3),
+        vec![Type::new(Vec3)],
+    );
     state.declare(
         "gl_FragCoord",
         SymDecl::Global(StorageClass::In, None, Type::new(Vec4), RunClass::Vector),
@@ -82,6 +82,15 @@ WINBASEAPI BOOL WINAPI QueryPerformanceFrequency(LARGE_INTEGER* lpFrequency);
 
 #define FALLTHROUGH [[fallthrough]]
 
+// Rasterizer state that changes while drawing is kept per thread, so that the
+// bands of a draw call may be rasterized in parallel. The initial-exec model
+// avoids the cost of dynamic TLS lookups within the span loops.
+#ifdef _WIN32
+#  define THREAD_LOCAL thread_local
+#else
+#  define THREAD_LOCAL thread_local __attribute__((tls_model("initial-exec")))
+#endif
+
 #if defined(MOZILLA_CLIENT) && defined(MOZ_CLANG_PLUGIN)
 #  define IMPLICIT __attribute__((annotate("moz_implicit")))
 #else
@@ -740,6 +749,13 @@ struct ObjectStore {
   O** end() const { return &objects[size]; }
 };
 
+// Runs run_band(job, band) for every band in [0, num_bands) on the workers,
+// returning only once all bands have been rasterized.
+typedef void (*RasterizeBandsFunc)(void* workers, int num_bands,
+                                   void (*run_band)(void* job, int band),
+                                   void* job);
+typedef void (*ReleaseRasterizeWorkersFunc)(void* workers);
+
 struct Context {
   int32_t references = 1;
 
@@ -780,6 +796,13 @@ struct Context {
   int shaded_rows = 0;
   int shaded_pixels = 0;
 
+  // Optional pool of workers supplied by the embedder to rasterize the bands
+  // of large draw calls in parallel. See SetRasterizeWorkers.
+  RasterizeBandsFunc rasterize_bands = nullptr;
+  ReleaseRasterizeWorkersFunc release_rasterize_workers = nullptr;
+  void* rasterize_workers = nullptr;
+  int max_raster_bands = 1;
+
   struct TextureUnit {
     GLuint texture_2d_binding = 0;
     GLuint texture_rectangle_binding = 0;
@@ -860,9 +883,23 @@ struct Context {
   }
 };
 static Context* ctx = nullptr;
-static VertexShaderImpl* vertex_shader = nullptr;
-static FragmentShaderImpl* fragment_shader = nullptr;
-static BlendKey blend_key = BLEND_KEY_NONE;
+static THREAD_LOCAL VertexShaderImpl* vertex_shader = nullptr;
+static THREAD_LOCAL FragmentShaderImpl* fragment_shader = nullptr;
+static THREAD_LOCAL BlendKey blend_key = BLEND_KEY_NONE;
+
+// The rows of the color buffer the current thread is rasterizing. Unless a
+// draw call is split into bands, this covers the whole buffer.
+static THREAD_LOCAL IntRange raster_band = {INT32_MIN, INT32_MAX};
+
+// Statistics gathered by the current thread while rasterizing a draw call,
+// which are only accumulated into the context once the draw call completes.
+struct RasterStats {
+  int shaded_rows = 0;
+  int shaded_pixels = 0;
+  // The number of rows of the color buffer whose delayed clear was resolved.
+  int cleared_rows = 0;
+};
+static THREAD_LOCAL RasterStats raster_stats;
 
 static void prepare_texture(Texture& t, const IntRect* skip = nullptr);
 
@@ -2693,8 +2730,138 @@ void VertexArray::validate() {
   max_attrib = last_enabled;
 }
 
+// Draw the primitives of a draw call with the current vertex and fragment
+// shaders, within the current band of rows.
+static void draw_primitives(GLenum mode, GLsizei count, GLenum type,
+                            GLintptr offset, GLsizei instancecount,
+                            VertexArray& v, Texture& colortex,
+                            Texture& depthtex) {
+  switch (type) {
+    case GL_UNSIGNED_SHORT:
+      assert(mode == GL_TRIANGLES);
+      draw_elements<uint16_t>(count, instancecount, offset, v, colortex,
+                              depthtex);
+      break;
+    case GL_UNSIGNED_INT:
+      assert(mode == GL_TRIANGLES);
+      draw_elements<uint32_t>(count, instancecount, offset, v, colortex,
+                              depthtex);
+      break;
+    case GL_NONE:
+      // Non-standard GL extension - if element type is GL_NONE, then we don't
+      // use any element buffer and behave as if DrawArrays was called instead.
+      for (GLsizei instance = 0; instance < instancecount; instance++) {
+        switch (mode) {
+          case GL_LINES:
+            for (GLsizei i = 0; i + 2 <= count; i += 2) {
+              vertex_shader->load_attribs(v.attribs, offset + i, instance, 2);
+              draw_quad(2, colortex, depthtex);
+            }
+            break;
+          case GL_TRIANGLES:
+            for (GLsizei i = 0; i + 3 <= count; i += 3) {
+              vertex_shader->load_attribs(v.attribs, offset + i, instance, 3);
+              draw_quad(3, colortex, depthtex);
+            }
+            break;
+          default:
+            assert(false);
+            break;
+        }
+      }
+      break;
+    default:
+      assert(false);
+      break;
+  }
+}
+
+// The maximum number of bands a draw call may be split into.
+constexpr int MAX_RASTER_BANDS = 16;
+// The minimum number of rows of the color buffer worth rasterizing as a band.
+constexpr int MIN_RASTER_BAND_HEIGHT = 64;
+
+// A draw call whose bands are being rasterized in parallel by the workers.
+struct RasterizeJob {
+  GLenum mode;
+  GLsizei count;
+  GLenum type;
+  GLintptr offset;
+  GLsizei instancecount;
+  VertexArray* v;
+  Texture* colortex;
+  Texture* depthtex;
+  ProgramImpl* program;
+  // The rows of the color buffer that may be drawn to.
+  IntRange rows;
+  int num_bands;
+  RasterStats stats[MAX_RASTER_BANDS];
+
+  // Get the rows of the color buffer covered by a band. The boundaries are
+  // aligned to 32 rows so that bands never share a word of the mask of rows
+  // pending delayed clear.
+  IntRange band_rows(int band) const {
+    auto boundary = [&](int i) {
+      if (i <= 0) return INT32_MIN;
+      if (i >= num_bands) return INT32_MAX;
+      return (rows.start + rows.len() * i / num_bands) & ~31;
+    };
+    return {boundary(band), boundary(band + 1)};
+  }
+};
+
+// Decide how many bands to split a draw call into. Every band runs the vertex
+// shader for all of the primitives, so draw calls are only split if workers
+// are available and the target is tall enough to be worth the overhead.
+static int num_raster_bands(const IntRect& bounds) {
+  if (!ctx->rasterize_bands || ctx->max_raster_bands <= 1) {
+    return 1;
+  }
+  return clamp(bounds.height() / MIN_RASTER_BAND_HEIGHT, 1,
+               min(ctx->max_raster_bands, MAX_RASTER_BANDS));
+}
+
+// Rasterize a band of a draw call on a worker. Only the rows within the band
+// are drawn, while the primitives are otherwise processed exactly as they
+// would be without bands, so that the output doesn't depend on the number of
+// bands.
+static void rasterize_band(void* data, int band) {
+  RasterizeJob& job = *(RasterizeJob*)data;
+  // Shaders hold the state of the primitive being rasterized, so each band
+  // needs its own copy of the program.
+  ProgramImpl* program = job.program->clone();
+  VertexShaderImpl* prev_vertex_shader = vertex_shader;
+  FragmentShaderImpl* prev_fragment_shader = fragment_shader;
+  vertex_shader = program->get_vertex_shader();
+  fragment_shader = program->get_fragment_shader();
+  raster_band = job.band_rows(band);
+  raster_stats = RasterStats();
+
+  draw_primitives(job.mode, job.count, job.type, job.offset,
+                  job.instancecount, *job.v, *job.colortex, *job.depthtex);
+
+  job.stats[band] = raster_stats;
+  raster_band = {INT32_MIN, INT32_MAX};
+  vertex_shader = prev_vertex_shader;
+  fragment_shader = prev_fragment_shader;
+  delete program;
+}
+
 extern "C" {
 
+void SetRasterizeWorkers(RasterizeBandsFunc rasterize_bands,
+                         ReleaseRasterizeWorkersFunc release, void* workers,
+                         int max_bands) {
+  if (ctx->release_rasterize_workers) {
+    ctx->release_rasterize_workers(ctx->rasterize_workers);
+  }
+  ctx->rasterize_bands = rasterize_bands;
+  ctx->release_rasterize_workers = release;
+  ctx->rasterize_workers = workers;
+  ctx->max_raster_bands = max_bands;
+}
+
+
 void DrawElementsInstanced(GLenum mode, GLsizei count, GLenum type,
                            GLintptr offset, GLsizei instancecount) {
   if (offset < 0 || count <= 0 || instancecount <= 0 || !vertex_shader ||
@@ -2733,50 +2900,41 @@ void DrawElementsInstanced(GLenum mode, GLsizei count, GLenum type,
   uint64_t start = get_time_value();
 #endif
 
-  ctx->shaded_rows = 0;
-  ctx->shaded_pixels = 0;
+  raster_stats = RasterStats();
 
   vertex_shader->init_batch();
 
-  switch (type) {
-    case GL_UNSIGNED_SHORT:
-      assert(mode == GL_TRIANGLES);
-      draw_elements<uint16_t>(count, instancecount, offset, v, colortex,
-                              depthtex);
-      break;
-    case GL_UNSIGNED_INT:
-      assert(mode == GL_TRIANGLES);
-      draw_elements<uint32_t>(count, instancecount, offset, v, colortex,
-                              depthtex);
-      break;
-    case GL_NONE:
-      // Non-standard GL extension - if element type is GL_NONE, then we don't
-      // use any element buffer and behave as if DrawArrays was called instead.
-      for (GLsizei instance = 0; instance < instancecount; instance++) {
-        switch (mode) {
-          case GL_LINES:
-            for (GLsizei i = 0; i + 2 <= count; i += 2) {
-              vertex_shader->load_attribs(v.attribs, offset + i, instance, 2);
-              draw_quad(2, colortex, depthtex);
-            }
-            break;
-          case GL_TRIANGLES:
-            for (GLsizei i = 0; i + 3 <= count; i += 3) {
-              vertex_shader->load_attribs(v.attribs, offset + i, instance, 3);
-              draw_quad(3, colortex, depthtex);
-            }
-            break;
-          default:
-            assert(false);
-            break;
-        }
-      }
-      break;
-    default:
-      assert(false);
-      break;
+  IntRect bounds = ctx->apply_scissor(colortex);
+  int num_bands = num_raster_bands(bounds);
+  if (num_bands > 1) {
+    RasterizeJob job = {mode,
+                        count,
+                        type,
+                        offset,
+                        instancecount,
+                        &v,
+                        &colortex,
+                        &depthtex,
+                        ctx->programs[ctx->current_program].impl,
+                        {bounds.y0, bounds.y1},
+                        num_bands,
+                        {}};
+    ctx->rasterize_bands(ctx->rasterize_workers, num_bands, rasterize_band,
+                         &job);
+    for (int band = 0; band < num_bands; band++) {
+      raster_stats.shaded_rows += job.stats[band].shaded_rows;
+      raster_stats.shaded_pixels += job.stats[band].shaded_pixels;
+      raster_stats.cleared_rows += job.stats[band].cleared_rows;
+    }
+  } else {
+    draw_primitives(mode, count, type, offset, instancecount, v, colortex,
+                    depthtex);
   }
 
+  ctx->shaded_rows = raster_stats.shaded_rows;
+  ctx->shaded_pixels = raster_stats.shaded_pixels;
+  colortex.delay_clear -= raster_stats.cleared_rows;
+
   if (ctx->samples_passed_query) {
     Query& q = ctx->queries[ctx->samples_passed_query];
     q.value += ctx->shaded_pixels;
@@ -2830,6 +2988,9 @@ void DestroyContext(Context* c) {
   if (ctx == c) {
     MakeCurrent(nullptr);
   }
+  if (c->release_rasterize_workers) {
+    c->release_rasterize_workers(c->rasterize_workers);
+  }
   delete c;
 }
 
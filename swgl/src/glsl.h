@@ -1701,6 +1701,21 @@ struct vec4_scalar {
         UNREACHABLE;
     }
   }
+  float& operator[](int index) {
+    switch (index) {
+      case 0:
+        return x;
+      case 1:
+        return y;
+      case 2:
+        return z;
+      case 3:
+        return w;
+      default:
+        UNREACHABLE;
+    }
+  }
+
   float& sel(XYZW c1) { return select(c1); }
   vec2_scalar sel(XYZW c1, XYZW c2) {
     return vec2_scalar{select(c1), select(c2)};
@@ -2131,11 +2146,36 @@ SI vec4_scalar clamp(vec4_scalar a, float minVal, float maxVal) {
                      clamp(a.z, minVal, maxVal), clamp(a.w, minVal, maxVal)};
 }
 
+SI vec4 min(vec4 a, vec4 b) {
+  return vec4(min(a.x, b.x), min(a.y, b.y), min(a.z, b.z), min(a.w, b.w));
+}
+SI vec4_scalar min(vec4_scalar a, vec4_scalar b) {
+  return vec4_scalar{min(a.x, b.x), min(a.y, b.y), min(a.z, b.z),
+                     min(a.w, b.w)};
+}
+
+SI vec4 max(vec4 a, vec4 b) {
+  return vec4(max(a.x, b.x), max(a.y, b.y), max(a.z, b.z), max(a.w, b.w));
+}
+SI vec4_scalar max(vec4_scalar a, vec4_scalar b) {
+  return vec4_scalar{max(a.x, b.x), max(a.y, b.y), max(a.z, b.z),
+                     max(a.w, b.w)};
+}
+
+vec4 floor(vec4 v) {
+  return vec4(floor(v.x), floor(v.y), floor(v.z), floor(v.w));
+}
+
 vec4 step(vec4 edge, vec4 x) {
   return vec4(step(edge.x, x.x), step(edge.y, x.y), step(edge.z, x.z),
               step(edge.w, x.w));
 }
 
+vec4 step(Float edge, vec4 x) {
+  return vec4(step(edge, x.x), step(edge, x.y), step(edge, x.z),
+              step(edge, x.w));
+}
+
 vec4_scalar step(vec4_scalar edge, vec4_scalar x) {
   return vec4_scalar(step(edge.x, x.x), step(edge.y, x.y), step(edge.z, x.z),
                      step(edge.w, x.w));
@@ -2785,6 +2825,10 @@ float dot(vec3_scalar a, vec3_scalar b) {
 
 Float dot(vec3 a, vec3 b) { return a.x * b.x + a.y * b.y + a.z * b.z; }
 
+float length(vec3_scalar a) { return sqrtf(dot(a, a)); }
+
+Float length(vec3 a) { return sqrt(dot(a, a)); }
+
 float dot(vec2_scalar a, vec2_scalar b) { return a.x * b.x + a.y * b.y; }
 
 Float dot(vec2 a, vec2 b) { return a.x * b.x + a.y * b.y; }
@@ -2873,6 +2917,12 @@ vec2 mod(vec2 a, vec2 b) { return vec2(mod(a.x, b.x), mod(a.y, b.y)); }
 
 vec3 abs(vec3 v) { return vec3(abs(v.x), abs(v.y), abs(v.z)); }
 
+vec4 abs(vec4 v) { return vec4(abs(v.x), abs(v.y), abs(v.z), abs(v.w)); }
+
+vec4_scalar abs(vec4_scalar v) {
+  return vec4_scalar{fabsf(v.x), fabsf(v.y), fabsf(v.z), fabsf(v.w)};
+}
+
 vec3 sign(vec3 v) { return vec3(sign(v.x), sign(v.y), sign(v.z)); }
 
 mat2 inverse(mat2 v) {
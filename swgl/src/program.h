@@ -30,6 +30,10 @@ struct ProgramImpl {
   virtual VertexShaderImpl* get_vertex_shader() = 0;
   virtual FragmentShaderImpl* get_fragment_shader() = 0;
   virtual const char* get_name() const = 0;
+  // Create a copy of the program with the same uniforms and bound textures,
+  // to rasterize with on another thread. The copy shares the sampler state of
+  // the original, which must outlive it.
+  virtual ProgramImpl* clone() const = 0;
 };
 
 typedef ProgramImpl* (*ProgramLoader)();
@@ -406,6 +406,10 @@ struct ClipRect {
   explicit ClipRect(const IntRect& i)
       : x0(i.x0), y0(i.y0), x1(i.x1), y1(i.y1) {}
   explicit ClipRect(const Texture& t) : ClipRect(ctx->apply_scissor(t)) {
+    // Stop at the end of the band of rows being rasterized. The start of the
+    // band is instead handled while walking the rows, as the edges must still
+    // be stepped through the rows above it.
+    y1 = min(y1, float(raster_band.end));
     // If blending is enabled, set blend_key to reflect the resolved blend
     // state for the currently drawn primitive.
     if (ctx->blend) {
@@ -721,7 +725,9 @@ static inline void prepare_row(Texture& colortex, int y, int startx, int endx,
   uint32_t& mask = colortex.cleared_rows[y / 32];
   if ((mask & (1 << (y & 31))) == 0) {
     mask |= 1 << (y & 31);
-    colortex.delay_clear--;
+    // The count of rows pending clear is only updated once the draw call
+    // completes, as the rows may be resolved concurrently by several bands.
+    raster_stats.cleared_rows++;
     if (blend_key || use_discard) {
       // If depth test, blending, or discard is used, old color values
       // might be sampled, so we need to clear the entire row to fill it.
@@ -943,16 +949,20 @@ static inline void draw_quad_spans(int nump, Point2D p[4], uint32_t z,
       checkY = min(ceil(min(l1.y, r1.y) - aaRound), clipRect.y1);
     }
 
-    // Calculate a potentially AA'd span and check if it is non-empty.
-    IntRange span = aa_span(fbuf, left, right, clipSpan);
+    // Calculate a potentially AA'd span and check if it is non-empty. Rows
+    // above the current band are skipped, but the edges are still stepped
+    // through them so that the edges match those of the other bands exactly.
+    IntRange span = int(y) >= raster_band.start
+                        ? aa_span(fbuf, left, right, clipSpan)
+                        : IntRange{0, 0};
     if (span.len() > 0) {
       // If user clip planes are enabled, use them to bound the current span.
       if (vertex_shader->use_clip_distance()) {
         span = span.intersect(clip_distance_range(left, right));
         if (span.len() <= 0) goto next_span;
       }
-      ctx->shaded_rows++;
-      ctx->shaded_pixels += span.len();
+      raster_stats.shaded_rows++;
+      raster_stats.shaded_pixels += span.len();
       // Advance color/depth buffer pointers to the start of the span.
       P* buf = fbuf + span.start;
       // Check if we will need to use depth-buffer or discard on this span.
@@ -1193,16 +1203,20 @@ static inline void draw_perspective_spans(int nump, Point3D* p,
       checkY = min(ceil(min(l1.y, r1.y) - aaRound), clipRect.y1);
     }
 
-    // Calculate a potentially AA'd span and check if it is non-empty.
-    IntRange span = aa_span(fbuf, left, right, clipSpan);
+    // Calculate a potentially AA'd span and check if it is non-empty. Rows
+    // above the current band are skipped, but the edges are still stepped
+    // through them so that the edges match those of the other bands exactly.
+    IntRange span = int(y) >= raster_band.start
+                        ? aa_span(fbuf, left, right, clipSpan)
+                        : IntRange{0, 0};
     if (span.len() > 0) {
       // If user clip planes are enabled, use them to bound the current span.
       if (vertex_shader->use_clip_distance()) {
         span = span.intersect(clip_distance_range(left, right));
         if (span.len() <= 0) goto next_span;
       }
-      ctx->shaded_rows++;
-      ctx->shaded_pixels += span.len();
+      raster_stats.shaded_rows++;
+      raster_stats.shaded_pixels += span.len();
       // Advance color/depth buffer pointers to the start of the span.
       P* buf = fbuf + span.start;
       // Check if the we will need to use depth-buffer or discard on this span.
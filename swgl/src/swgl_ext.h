@@ -1806,9 +1806,9 @@ static bool commitRadialGradient(sampler2D sampler, int address, float size,
 // origin. The bounding box specifies the rectangle relative to the clip mask's
 // origin that constrains sampling within the clip mask. Blending must be
 // enabled for this to work.
-static sampler2D swgl_ClipMask = nullptr;
-static IntPoint swgl_ClipMaskOffset = {0, 0};
-static IntRect swgl_ClipMaskBounds = {0, 0, 0, 0};
+static THREAD_LOCAL sampler2D swgl_ClipMask = nullptr;
+static THREAD_LOCAL IntPoint swgl_ClipMaskOffset = {0, 0};
+static THREAD_LOCAL IntRect swgl_ClipMaskBounds = {0, 0, 0, 0};
 #define swgl_clipMask(mask, offset, bb_origin, bb_size)        \
   do {                                                         \
     if (bb_size != vec2_scalar(0.0f, 0.0f)) {                  \
@@ -1822,7 +1822,7 @@ static IntRect swgl_ClipMaskBounds = {0, 0, 0, 0};
 
 // Extension to enable anti-aliasing for the given edges of a quad.
 // Blending must be enable for this to work.
-static int swgl_AAEdgeMask = 0;
+static THREAD_LOCAL int swgl_AAEdgeMask = 0;
 
 static ALWAYS_INLINE int calcAAEdgeMask(bool on) { return on ? 0xF : 0; }
 static ALWAYS_INLINE int calcAAEdgeMask(int mask) { return mask; }
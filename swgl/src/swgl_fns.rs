@@ -319,6 +319,44 @@ extern "C" {
     fn DestroyContext(ctx: *mut c_void);
     fn MakeCurrent(ctx: *mut c_void);
     fn ReportMemory(ctx: *mut c_void, size_of_op: unsafe extern "C" fn(ptr: *const c_void) -> usize) -> usize;
+    fn SetRasterizeWorkers(
+        rasterize_bands: Option<RasterizeBandsFunc>,
+        release: Option<unsafe extern "C" fn(workers: *mut c_void)>,
+        workers: *mut c_void,
+        max_bands: c_int,
+    );
+}
+
+type RunBandFunc = unsafe extern "C" fn(job: *mut c_void, band: c_int);
+type RasterizeBandsFunc =
+    unsafe extern "C" fn(workers: *mut c_void, num_bands: c_int, run_band: RunBandFunc, job: *mut c_void);
+
+/// A pool of workers on which draw calls may rasterize horizontal bands of
+/// their output in parallel. The output is the same as if the draw calls were
+/// rasterized on a single thread.
+pub trait RasterizeWorkers: Send + Sync {
+    /// The maximum number of bands a draw call should be split into.
+    fn max_bands(&self) -> usize;
+
+    /// Call `band` for each band index in `0..num_bands`, only returning once
+    /// all the calls have returned.
+    fn run_bands(&self, num_bands: usize, band: &(dyn Fn(usize) + Sync));
+}
+
+unsafe extern "C" fn rasterize_bands(
+    workers: *mut c_void,
+    num_bands: c_int,
+    run_band: RunBandFunc,
+    job: *mut c_void,
+) {
+    let workers = &*(workers as *const Box<dyn RasterizeWorkers>);
+    // The job is only accessed by the bands until run_bands returns.
+    let job = job as usize;
+    workers.run_bands(num_bands as usize, &|band| run_band(job as *mut c_void, band as c_int));
+}
+
+unsafe extern "C" fn release_rasterize_workers(workers: *mut c_void) {
+    drop(Box::from_raw(workers as *mut Box<dyn RasterizeWorkers>));
 }
 
 #[derive(Clone, Copy)]
@@ -456,6 +494,26 @@ impl Context {
     pub fn report_memory(&self, size_of_op: unsafe extern "C" fn(ptr: *const c_void) -> usize) -> usize {
         unsafe { ReportMemory(self.0, size_of_op) }
     }
+
+    /// Sets the workers on which draw calls rasterize bands of their output
+    /// in parallel, or rasterize on the calling thread only if `None`. This
+    /// context must be current.
+    pub fn set_rasterize_workers(&self, workers: Option<Box<dyn RasterizeWorkers>>) {
+        unsafe {
+            match workers {
+                Some(workers) => {
+                    let max_bands = workers.max_bands() as c_int;
+                    SetRasterizeWorkers(
+                        Some(rasterize_bands),
+                        Some(release_rasterize_workers),
+                        Box::into_raw(Box::new(workers)) as *mut c_void,
+                        max_bands,
+                    );
+                }
+                None => SetRasterizeWorkers(None, None, ptr::null_mut(), 1),
+            }
+        }
+    }
 }
 
 impl From<*mut c_void> for Context {
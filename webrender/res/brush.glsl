@@ -86,6 +86,8 @@ void text_shader_main(
 #define BRUSH_FLAG_SEGMENT_NINEPATCH_MIDDLE    64
 #define BRUSH_FLAG_TEXEL_RECT                 128
 #define BRUSH_FLAG_FORCE_AA                   256
+#define BRUSH_FLAG_TONE_MAP_CLAMP             512
+#define BRUSH_FLAG_TONE_MAP_REINHARD         1024
 
 #define INVALID_SEGMENT_INDEX                   0xffff
 
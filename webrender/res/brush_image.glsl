@@ -4,7 +4,7 @@
 
 #define VECS_PER_SPECIFIC_BRUSH 3
 
-#include shared,prim_shared,brush
+#include shared,prim_shared,brush,color_space
 
 // Interpolated UV coordinates to sample.
 varying vec2 v_uv;
@@ -21,9 +21,20 @@ flat varying vec4 v_uv_bounds;
 // sampling artifacts.
 flat varying vec4 v_uv_sample_bounds;
 
-// Flag to allow perspective interpolation of UV.
+// Flag to allow perspective interpolation of UV, and the tone mapping
+// operator to apply to the sampled colors.
 // Packed in to vector to work around bug 1630356.
-flat varying vec2 v_perspective;
+flat varying vec2 v_perspective_tone_mapping;
+#define v_perspective v_perspective_tone_mapping.x
+#define v_tone_mapping v_perspective_tone_mapping.y
+
+// The color space of the image and the color space to convert it to.
+flat varying ivec2 v_color_spaces;
+
+// Must match the ToneMapping enum.
+#define TONE_MAPPING_NONE       0.0
+#define TONE_MAPPING_CLAMP      1.0
+#define TONE_MAPPING_REINHARD   2.0
 
 #ifdef WR_VERTEX_SHADER
 
@@ -164,7 +175,17 @@ void brush_vs(
     }
 
     float perspective_interpolate = (brush_flags & BRUSH_FLAG_PERSPECTIVE_INTERPOLATION) != 0 ? 1.0 : 0.0;
-    v_perspective.x = perspective_interpolate;
+    v_perspective = perspective_interpolate;
+
+    if ((brush_flags & BRUSH_FLAG_TONE_MAP_CLAMP) != 0) {
+        v_tone_mapping = TONE_MAPPING_CLAMP;
+    } else if ((brush_flags & BRUSH_FLAG_TONE_MAP_REINHARD) != 0) {
+        v_tone_mapping = TONE_MAPPING_REINHARD;
+    } else {
+        v_tone_mapping = TONE_MAPPING_NONE;
+    }
+
+    v_color_spaces = ivec2(prim_user_data.w & 0xff, prim_user_data.w >> 8);
 
     // Handle case where the UV coords are inverted (e.g. from an
     // external image).
@@ -280,6 +301,25 @@ void brush_vs(
 
 #ifdef WR_FRAGMENT_SHADER
 
+// Maps premultiplied colors sampled from high bit depth images into the range
+// of 8 bit targets. This must match tone_map in high_bit_depth.rs.
+vec4 tone_map(vec4 color, float tone_mapping) {
+    if (tone_mapping == TONE_MAPPING_NONE) {
+        return color;
+    }
+
+    float alpha = clamp(color.a, 0.0, 1.0);
+    if (tone_mapping == TONE_MAPPING_CLAMP) {
+        return vec4(clamp(color.rgb, vec3(0.0), vec3(alpha)), alpha);
+    }
+
+    if (alpha <= 0.0) {
+        return vec4(0.0);
+    }
+    vec3 unpremultiplied = max(color.rgb / alpha, vec3(0.0));
+    return vec4(unpremultiplied / (vec3(1.0) + unpremultiplied) * alpha, alpha);
+}
+
 vec2 compute_repeated_uvs(float perspective_divisor) {
 #ifdef WR_FEATURE_REPETITION
     vec2 uv_size = v_uv_bounds.zw - v_uv_bounds.xy;
@@ -313,13 +353,14 @@ vec2 compute_repeated_uvs(float perspective_divisor) {
 }
 
 Fragment brush_fs() {
-    float perspective_divisor = mix(gl_FragCoord.w, 1.0, v_perspective.x);
+    float perspective_divisor = mix(gl_FragCoord.w, 1.0, v_perspective);
     vec2 repeated_uv = compute_repeated_uvs(perspective_divisor);
 
     // Clamp the uvs to avoid sampling artifacts.
     vec2 uv = clamp(repeated_uv, v_uv_sample_bounds.xy, v_uv_sample_bounds.zw);
 
-    vec4 texel = TEX_SAMPLE(sColor0, uv);
+    vec4 texel = tone_map(TEX_SAMPLE(sColor0, uv), v_tone_mapping);
+    texel = convert_color_space(texel, v_color_spaces.x, v_color_spaces.y);
 
     Fragment frag;
 
@@ -352,13 +393,18 @@ void swgl_drawSpanRGBA8() {
         return;
     }
 
+    // Color space conversions are only done by brush_fs.
+    if (v_color_spaces.x != v_color_spaces.y) {
+        return;
+    }
+
     #ifdef WR_FEATURE_ALPHA_PASS
         if (v_mask_swizzle != vec2(1.0, 0.0)) {
             return;
         }
     #endif
 
-    float perspective_divisor = mix(swgl_forceScalar(gl_FragCoord.w), 1.0, v_perspective.x);
+    float perspective_divisor = mix(swgl_forceScalar(gl_FragCoord.w), 1.0, v_perspective);
 
     #ifdef WR_FEATURE_REPETITION
         // Get the UVs before any repetition, scaling, or offsetting has occurred...
@@ -17,6 +17,7 @@ struct Gradient {
     vec4 start_end_point;
     int extend_mode;
     vec2 stretch_size;
+    int color_space;
 };
 
 Gradient fetch_gradient(int address) {
@@ -24,7 +25,8 @@ Gradient fetch_gradient(int address) {
     return Gradient(
         data[0],
         int(data[1].x),
-        data[1].yz
+        data[1].yz,
+        int(data[1].w)
     );
 }
 
@@ -50,7 +52,8 @@ void brush_vs(
         brush_flags,
         texel_rect,
         gradient.extend_mode,
-        gradient.stretch_size
+        gradient.stretch_size,
+        gradient.color_space
     );
 
     vec2 start_point = gradient.start_end_point.xy;
@@ -82,6 +85,9 @@ Fragment brush_fs() {
 
 #ifdef SWGL_DRAW_SPAN
 void swgl_drawSpanRGBA8() {
+    if (!gradient_is_srgb()) {
+        return;
+    }
   int address = swgl_validateGradient(sGpuCache, get_gpu_cache_uv(v_gradient_address.x), int(GRADIENT_ENTRIES + 2.0));
     if (address < 0) {
         return;
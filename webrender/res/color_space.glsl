@@ -0,0 +1,180 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// Conversions between RGB color spaces. This must match color_space.rs.
+
+// Must match the RgbColorSpace enum.
+#define RGB_COLOR_SPACE_SRGB        0
+#define RGB_COLOR_SPACE_DISPLAY_P3  1
+#define RGB_COLOR_SPACE_REC2020     2
+#define RGB_COLOR_SPACE_LINEAR_SRGB 3
+
+#ifdef WR_FRAGMENT_SHADER
+
+// The matrices are stored in column-major order and use the D65 white point.
+const mat3 XyzFromSrgb = mat3(
+    0.4123908, 0.2126390, 0.0193308,
+    0.3575843, 0.7151687, 0.1191948,
+    0.1804808, 0.0721923, 0.9505322
+);
+const mat3 SrgbFromXyz = mat3(
+     3.2409699, -0.9692436,  0.0556301,
+    -1.5373832,  1.8759675, -0.2039770,
+    -0.4986108,  0.0415551,  1.0569715
+);
+const mat3 XyzFromDisplayP3 = mat3(
+    0.4865709, 0.2289746, 0.0000000,
+    0.2656677, 0.6917385, 0.0451134,
+    0.1982173, 0.0792869, 1.0439444
+);
+const mat3 DisplayP3FromXyz = mat3(
+     2.4934969, -0.8294890,  0.0358458,
+    -0.9313836,  1.7626641, -0.0761724,
+    -0.4027108,  0.0236247,  0.9568845
+);
+const mat3 XyzFromRec2020 = mat3(
+    0.6369580, 0.2627002, 0.0000000,
+    0.1446169, 0.6779981, 0.0280727,
+    0.1688810, 0.0593017, 1.0609851
+);
+const mat3 Rec2020FromXyz = mat3(
+     1.7166512, -0.6666844,  0.0176399,
+    -0.3556708,  1.6164812, -0.0427706,
+    -0.2533663,  0.0157685,  0.9421031
+);
+
+vec3 decode_transfer(vec3 c, int color_space) {
+    if (color_space == RGB_COLOR_SPACE_REC2020) {
+        return mix(
+            pow((c + 0.0993) / 1.0993, vec3(1.0 / 0.45)),
+            c / 4.5,
+            lessThanEqual(c, vec3(0.08145))
+        );
+    }
+    if (color_space == RGB_COLOR_SPACE_LINEAR_SRGB) {
+        return c;
+    }
+    return mix(
+        pow((c + 0.055) / 1.055, vec3(2.4)),
+        c / 12.92,
+        lessThanEqual(c, vec3(0.04045))
+    );
+}
+
+vec3 encode_transfer(vec3 c, int color_space) {
+    if (color_space == RGB_COLOR_SPACE_REC2020) {
+        return mix(
+            1.0993 * pow(c, vec3(0.45)) - 0.0993,
+            c * 4.5,
+            lessThanEqual(c, vec3(0.0181))
+        );
+    }
+    if (color_space == RGB_COLOR_SPACE_LINEAR_SRGB) {
+        return c;
+    }
+    return mix(
+        1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055,
+        c * 12.92,
+        lessThanEqual(c, vec3(0.0031308))
+    );
+}
+
+vec3 rgb_to_xyz(vec3 c, int color_space) {
+    if (color_space == RGB_COLOR_SPACE_DISPLAY_P3) {
+        return XyzFromDisplayP3 * c;
+    }
+    if (color_space == RGB_COLOR_SPACE_REC2020) {
+        return XyzFromRec2020 * c;
+    }
+    return XyzFromSrgb * c;
+}
+
+vec3 xyz_to_rgb(vec3 c, int color_space) {
+    if (color_space == RGB_COLOR_SPACE_DISPLAY_P3) {
+        return DisplayP3FromXyz * c;
+    }
+    if (color_space == RGB_COLOR_SPACE_REC2020) {
+        return Rec2020FromXyz * c;
+    }
+    return SrgbFromXyz * c;
+}
+
+// Converts a premultiplied color between two color spaces. Colors outside of
+// the destination gamut are clamped per channel.
+vec4 convert_color_space(vec4 color, int src, int dst) {
+    if (src == dst) {
+        return color;
+    }
+    if (color.a <= 0.0) {
+        return color;
+    }
+
+    vec3 rgb = clamp(color.rgb / color.a, vec3(0.0), vec3(1.0));
+    rgb = rgb_to_xyz(decode_transfer(rgb, src), src);
+    rgb = clamp(xyz_to_rgb(rgb, dst), vec3(0.0), vec3(1.0));
+    return vec4(encode_transfer(rgb, dst) * color.a, color.a);
+}
+
+// Must match the GradientColorSpace enum.
+#define GRADIENT_COLOR_SPACE_SRGB        0
+#define GRADIENT_COLOR_SPACE_SRGB_LINEAR 1
+#define GRADIENT_COLOR_SPACE_OKLAB       2
+#define GRADIENT_COLOR_SPACE_OKLCH       3
+#define GRADIENT_COLOR_SPACE_HSL         4
+
+// https://bottosson.github.io/posts/oklab/
+const mat3 LmsFromOklab = mat3(
+    1.0000000000,  1.0000000000,  1.0000000000,
+    0.3963377774, -0.1055613458, -0.0894841775,
+    0.2158037573, -0.0638541728, -1.2914855480
+);
+const mat3 LinearSrgbFromLms = mat3(
+     4.0767416621, -1.2684380046, -0.0041960863,
+    -3.3077115913,  2.6097574011, -0.7034186147,
+     0.2309699292, -0.3413193965,  1.7076147010
+);
+
+vec3 oklab_to_linear_srgb(vec3 lab) {
+    vec3 lms = LmsFromOklab * lab;
+    return LinearSrgbFromLms * (lms * lms * lms);
+}
+
+float hsl_channel(vec3 hsl, float n) {
+    float k = mod(n + hsl.x / 30.0, 12.0);
+    float a = hsl.y * min(hsl.z, 1.0 - hsl.z);
+    return hsl.z - a * clamp(min(k - 3.0, 9.0 - k), -1.0, 1.0);
+}
+
+// Converts an interpolated gradient color back to premultiplied sRGB. All
+// channels but the hue are premultiplied, the hue is in degrees. This must
+// match interpolation_colors in prim_store/gradient/mod.rs.
+vec4 gradient_color_to_srgb(vec4 color, int color_space) {
+    if (color_space == GRADIENT_COLOR_SPACE_SRGB) {
+        return color;
+    }
+    // Transparent colors come out as transparent black from the final
+    // premultiplication, they only need to avoid dividing by zero.
+    float inv_alpha = color.a > 0.0 ? 1.0 / color.a : 0.0;
+
+    vec3 rgb;
+    if (color_space == GRADIENT_COLOR_SPACE_HSL) {
+        vec3 hsl = vec3(color.x, color.y * inv_alpha, color.z * inv_alpha);
+        rgb = vec3(hsl_channel(hsl, 0.0), hsl_channel(hsl, 8.0), hsl_channel(hsl, 4.0));
+    } else {
+        vec3 c = color.rgb * inv_alpha;
+        if (color_space == GRADIENT_COLOR_SPACE_OKLCH) {
+            // Degrees to radians.
+            float hue = color.z * 0.017453292519943295;
+            c = vec3(c.x, c.y * cos(hue), c.y * sin(hue));
+        }
+        if (color_space != GRADIENT_COLOR_SPACE_SRGB_LINEAR) {
+            c = oklab_to_linear_srgb(c);
+        }
+        rgb = encode_transfer(clamp(c, vec3(0.0), vec3(1.0)), RGB_COLOR_SPACE_SRGB);
+    }
+
+    return vec4(clamp(rgb, vec3(0.0), vec3(1.0)) * color.a, color.a);
+}
+
+#endif
@@ -0,0 +1,112 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#include shared,clip_shared
+
+varying highp vec4 vLocalPos;
+// xy = GPU cache address of the vertices, z = vertex count, w = fill rule.
+flat varying highp ivec4 vPolygonParams;
+
+#define FILL_RULE_NONZERO   1
+#define FILL_RULE_EVENODD   2
+
+#ifdef WR_VERTEX_SHADER
+
+PER_INSTANCE in vec4 aClipLocalRect;
+PER_INSTANCE in ivec2 aClipPolygonAddress;
+PER_INSTANCE in ivec2 aClipPolygonParams;
+
+struct ClipMaskInstancePolygon {
+    ClipMaskInstanceCommon base;
+    RectWithEndpoint local_rect;
+    ivec2 polygon_address;
+    int vertex_count;
+    int fill_rule;
+};
+
+ClipMaskInstancePolygon fetch_clip_item() {
+    ClipMaskInstancePolygon cmi;
+
+    cmi.base = fetch_clip_item_common();
+    cmi.local_rect = RectWithEndpoint(aClipLocalRect.xy, aClipLocalRect.zw);
+    cmi.polygon_address = aClipPolygonAddress;
+    cmi.vertex_count = aClipPolygonParams.x;
+    cmi.fill_rule = aClipPolygonParams.y;
+
+    return cmi;
+}
+
+void main(void) {
+    ClipMaskInstancePolygon cmi = fetch_clip_item();
+    Transform clip_transform = fetch_transform(cmi.base.clip_transform_id);
+    Transform prim_transform = fetch_transform(cmi.base.prim_transform_id);
+
+    ClipVertexInfo vi = write_clip_tile_vertex(
+        cmi.local_rect,
+        prim_transform,
+        clip_transform,
+        cmi.base.sub_rect,
+        cmi.base.task_origin,
+        cmi.base.screen_origin,
+        cmi.base.device_pixel_scale
+    );
+
+    vLocalPos = vi.local_pos;
+    vPolygonParams = ivec4(cmi.polygon_address, cmi.vertex_count, cmi.fill_rule);
+}
+#endif
+
+#ifdef WR_FRAGMENT_SHADER
+
+// Vertices are packed two per GPU cache block, and a polygon never spans
+// more than a single row of the GPU cache texture.
+vec2 fetch_polygon_vertex(int index) {
+    vec4 block = fetch_from_gpu_cache_1_direct(vPolygonParams.xy + ivec2(index / 2, 0));
+    return (index % 2) == 0 ? block.xy : block.zw;
+}
+
+void main(void) {
+    vec2 local_pos = vLocalPos.xy / vLocalPos.w;
+    float aa_range = compute_aa_range(local_pos);
+
+    int vertex_count = vPolygonParams.z;
+    int winding = 0;
+    float min_dist_sq = 1.0e20;
+
+    vec2 prev = fetch_polygon_vertex(vertex_count - 1);
+    for (int i = 0; i < vertex_count; i++) {
+        vec2 cur = fetch_polygon_vertex(i);
+
+        // Squared distance to the edge segment, used for anti-aliasing.
+        vec2 edge = cur - prev;
+        vec2 to_pos = local_pos - prev;
+        float t = clamp(dot(to_pos, edge) / max(dot(edge, edge), 1.0e-6), 0.0, 1.0);
+        vec2 delta = to_pos - edge * t;
+        min_dist_sq = min(min_dist_sq, dot(delta, delta));
+
+        // Winding number contribution of this edge, counting upward crossings
+        // to the left of the point and downward crossings to the right.
+        float side = edge.x * to_pos.y - edge.y * to_pos.x;
+        if (local_pos.y >= prev.y) {
+            if (local_pos.y < cur.y && side > 0.0) {
+                winding += 1;
+            }
+        } else if (local_pos.y >= cur.y && side < 0.0) {
+            winding -= 1;
+        }
+
+        prev = cur;
+    }
+
+    bool inside = vPolygonParams.w == FILL_RULE_EVENODD ?
+        (winding & 1) != 0 :
+        winding != 0;
+
+    float dist = sqrt(min_dist_sq);
+    float alpha = distance_aa(aa_range, inside ? -dist : dist);
+
+    float final_alpha = vLocalPos.w > 0.0 ? alpha : 0.0;
+    oFragColor = vec4(final_alpha, 0.0, 0.0, 1.0);
+}
+#endif
@@ -28,6 +28,7 @@ PER_INSTANCE in float aStartOffset;
 PER_INSTANCE in float aEndOffset;
 PER_INSTANCE in float aAngle;
 PER_INSTANCE in int aExtendMode;
+PER_INSTANCE in int aColorSpace;
 PER_INSTANCE in int aGradientStopsAddress;
 
 void main(void) {
@@ -48,6 +49,7 @@ void main(void) {
     v_pos = (aTaskRect.zw - aTaskRect.xy) * aPosition.xy * v_offset_scale * aScale;
 
     v_gradient_repeat.x = float(aExtendMode == EXTEND_MODE_REPEAT);
+    v_gradient_repeat.y = float(aColorSpace);
     v_gradient_address.x = aGradientStopsAddress;
 }
 #endif
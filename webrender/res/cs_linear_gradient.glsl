@@ -20,6 +20,7 @@ PER_INSTANCE in vec2 aStartPoint;
 PER_INSTANCE in vec2 aEndPoint;
 PER_INSTANCE in vec2 aScale;
 PER_INSTANCE in int aExtendMode;
+PER_INSTANCE in int aColorSpace;
 PER_INSTANCE in int aGradientStopsAddress;
 
 void main(void) {
@@ -37,6 +38,7 @@ void main(void) {
     v_scale_dir *= (aTaskRect.zw - aTaskRect.xy);
 
     v_gradient_repeat.x = float(aExtendMode == EXTEND_MODE_REPEAT);
+    v_gradient_repeat.y = float(aColorSpace);
     v_gradient_address.x = aGradientStopsAddress;
 }
 #endif
@@ -54,6 +56,9 @@ void main(void) {
 
 #ifdef SWGL_DRAW_SPAN
 void swgl_drawSpanRGBA8() {
+    if (!gradient_is_srgb()) {
+        return;
+    }
     int address = swgl_validateGradient(sGpuCache, get_gpu_cache_uv(v_gradient_address.x), int(GRADIENT_ENTRIES + 2.0));
     if (address < 0) {
         return;
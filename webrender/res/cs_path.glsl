@@ -0,0 +1,87 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#include shared,rect,gpu_cache
+
+#define FILL_RULE_NONZERO   1
+#define FILL_RULE_EVENODD   2
+
+// Position of the fragment in task pixels, relative to the task origin.
+varying highp vec2 vPos;
+// xy = GPU cache address of the edges, z = edge count, w = fill rule.
+flat varying highp ivec4 vPathParams;
+
+#ifdef WR_VERTEX_SHADER
+
+PER_INSTANCE in vec4 aTaskRect;
+PER_INSTANCE in ivec2 aEdgesAddress;
+PER_INSTANCE in ivec2 aPathParams;
+
+void main(void) {
+    vPos = aPosition.xy * (aTaskRect.zw - aTaskRect.xy);
+    vPathParams = ivec4(aEdgesAddress, aPathParams.x, aPathParams.y);
+
+    gl_Position = uTransform * vec4(mix(aTaskRect.xy, aTaskRect.zw, aPosition.xy), 0.0, 1.0);
+}
+#endif
+
+#ifdef WR_FRAGMENT_SHADER
+
+// Coverage is computed from the winding number of a 4x4 grid of samples
+// within the pixel. Unlike distance based anti-aliasing, this is correct for
+// the overlapping contours that strokes are made of.
+#define SAMPLE_OFFSETS vec4(-0.375, -0.125, 0.125, 0.375)
+
+void main(void) {
+    vec2 pixel = floor(vPos) + 0.5;
+    vec4 sample_x = vec4(pixel.x) + SAMPLE_OFFSETS;
+    vec4 sample_y = vec4(pixel.y) + SAMPLE_OFFSETS;
+
+    // The winding number of each sample, one row of samples per vector.
+    vec4 winding0 = vec4(0.0);
+    vec4 winding1 = vec4(0.0);
+    vec4 winding2 = vec4(0.0);
+    vec4 winding3 = vec4(0.0);
+
+    // Edges are stored one per block, and never span more than a single row
+    // of the GPU cache texture.
+    int edge_count = vPathParams.z;
+    for (int i = 0; i < edge_count; i++) {
+        vec4 edge = fetch_from_gpu_cache_1_direct(vPathParams.xy + ivec2(i, 0));
+
+        // +1 for rows the edge crosses downwards, -1 upwards, 0 otherwise.
+        // There is no early out for edges that cross no rows, as a continue
+        // under a varying condition can't be expressed by swgl.
+        vec4 crossing = step(edge.y, sample_y) - step(edge.w, sample_y);
+
+        float dy = edge.w - edge.y;
+        float inv_slope = dy != 0.0 ? (edge.z - edge.x) / dy : 0.0;
+        vec4 cross_x = vec4(edge.x) + (sample_y - vec4(edge.y)) * inv_slope;
+
+        // Samples to the right of the crossing point are wound by the edge.
+        winding0 += crossing.x * step(cross_x.x, sample_x);
+        winding1 += crossing.y * step(cross_x.y, sample_x);
+        winding2 += crossing.z * step(cross_x.z, sample_x);
+        winding3 += crossing.w * step(cross_x.w, sample_x);
+    }
+
+    vec4 inside0, inside1, inside2, inside3;
+    if (vPathParams.w == FILL_RULE_EVENODD) {
+        inside0 = winding0 - 2.0 * floor(winding0 * 0.5);
+        inside1 = winding1 - 2.0 * floor(winding1 * 0.5);
+        inside2 = winding2 - 2.0 * floor(winding2 * 0.5);
+        inside3 = winding3 - 2.0 * floor(winding3 * 0.5);
+    } else {
+        inside0 = step(0.5, winding0 * winding0);
+        inside1 = step(0.5, winding1 * winding1);
+        inside2 = step(0.5, winding2 * winding2);
+        inside3 = step(0.5, winding3 * winding3);
+    }
+
+    vec4 inside = inside0 + inside1 + inside2 + inside3;
+    float coverage = (inside.x + inside.y + inside.z + inside.w) * (1.0 / 16.0);
+
+    oFragColor = vec4(coverage);
+}
+#endif
@@ -20,6 +20,7 @@ PER_INSTANCE in float aStartRadius;
 PER_INSTANCE in float aEndRadius;
 PER_INSTANCE in float aXYRatio;
 PER_INSTANCE in int aExtendMode;
+PER_INSTANCE in int aColorSpace;
 PER_INSTANCE in int aGradientStopsAddress;
 
 void main(void) {
@@ -42,6 +43,7 @@ void main(void) {
     v_pos.y *= aXYRatio;
 
     v_gradient_repeat.x = float(aExtendMode == EXTEND_MODE_REPEAT);
+    v_gradient_repeat.y = float(aColorSpace);
     v_gradient_address.x = aGradientStopsAddress;
 }
 #endif
@@ -58,6 +60,9 @@ void main(void) {
 
 #ifdef SWGL_DRAW_SPAN
 void swgl_drawSpanRGBA8() {
+    if (!gradient_is_srgb()) {
+        return;
+    }
     int address = swgl_validateGradient(sGpuCache, get_gpu_cache_uv(v_gradient_address.x),
                                         int(GRADIENT_ENTRIES + 2.0));
     if (address < 0) {
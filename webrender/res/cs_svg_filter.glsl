@@ -8,6 +8,8 @@
 
 varying vec2 vInput1Uv;
 varying vec2 vInput2Uv;
+// The position of the fragment in device pixels, relative to the task origin.
+varying vec2 vTaskPos;
 flat varying vec4 vInput1UvRect;
 flat varying vec4 vInput2UvRect;
 flat varying ivec4 vData;
@@ -36,6 +38,14 @@ flat varying ivec4 vFuncs;
 #define FILTER_COMPONENT_TRANSFER   8
 #define FILTER_IDENTITY             9
 #define FILTER_COMPOSITE            10
+#define FILTER_TURBULENCE           11
+#define FILTER_MORPHOLOGY           12
+#define FILTER_CONVOLVE_MATRIX      13
+#define FILTER_DISPLACEMENT_MAP     14
+#define FILTER_DIFFUSE_LIGHTING     15
+#define FILTER_SPECULAR_LIGHTING    16
+#define FILTER_TILE                 17
+#define FILTER_IMAGE                18
 
 #define COMPOSITE_OVER       0
 #define COMPOSITE_IN         1
@@ -89,6 +99,7 @@ void main(void) {
     RectWithEndpoint target_rect = filter_task.task_rect;
 
     vec2 pos = mix(target_rect.p0, target_rect.p1, aPosition.xy);
+    vTaskPos = pos - target_rect.p0;
 
     RectWithEndpoint input_1_task;
     if (aFilterInputCount > 0) {
@@ -157,6 +168,26 @@ void main(void) {
               vFilterData0 = fetch_from_gpu_cache_1_direct(aFilterExtraDataAddress);
             }
             break;
+        case FILTER_TURBULENCE:
+        case FILTER_CONVOLVE_MATRIX:
+        case FILTER_DIFFUSE_LIGHTING:
+        case FILTER_SPECULAR_LIGHTING:
+            vData = ivec4(aFilterExtraDataAddress, 0, 0);
+            break;
+        case FILTER_MORPHOLOGY:
+            vData = ivec4(aFilterGenericInt, 0, 0, 0);
+            vFilterData0 = vec4(filter_task.user_data.xy, vec2(0.0));
+            break;
+        case FILTER_DISPLACEMENT_MAP:
+            vData = ivec4(aFilterGenericInt & 0xf, (aFilterGenericInt >> 4) & 0xf, 0, 0);
+            vFloat0.x = filter_task.user_data.x;
+            break;
+        case FILTER_TILE:
+        case FILTER_IMAGE:
+            vFilterData0 = fetch_from_gpu_cache_1_direct(aFilterExtraDataAddress);
+            vec2 input_size = vec2(TEX_SIZE(sColor0).xy);
+            vFilterData1 = vec4(input_1_task.p0, input_1_task.p1) / input_size.xyxy;
+            break;
         default:
             break;
     }
@@ -515,6 +546,281 @@ vec4 sampleInUvRect(sampler2D sampler, vec2 uv, vec4 uvRect) {
     return texture(sampler, clamped);
 }
 
+// Samples the input, returning transparent black outside of its task rect.
+vec4 sampleInUvRectOrTransparent(sampler2D sampler, vec2 uv, vec4 uvRect, vec2 texel) {
+    vec2 half_texel = 0.5 * texel;
+    return sampleInUvRect(sampler, uv, uvRect) *
+        point_inside_rect(uv, uvRect.xy - half_texel, uvRect.zw + half_texel);
+}
+
+// Turbulence Filter
+//
+// This is a port of the reference implementation in
+// https://drafts.fxtf.org/filter-effects/#feTurbulenceElement
+// The lattice selector and gradient tables follow the first three blocks of
+// the filter data, see push_turbulence_tables.
+
+#define TURBULENCE_PERLIN_N 4096.0
+#define TURBULENCE_BM 255
+#define TURBULENCE_LATTICE_OFFSET 3
+#define TURBULENCE_GRADIENT_OFFSET 67
+
+int turbulence_lattice(int index) {
+    index = index & TURBULENCE_BM;
+    vec4 texel = fetch_from_gpu_cache_1_direct(vData.xy + ivec2(TURBULENCE_LATTICE_OFFSET + index / 4, 0));
+    return int(texel[index % 4]);
+}
+
+vec2 turbulence_gradient(int channel, int index) {
+    int offset = channel * 256 + index;
+    vec4 texel = fetch_from_gpu_cache_1_direct(vData.xy + ivec2(TURBULENCE_GRADIENT_OFFSET + offset / 2, 0));
+    return (offset % 2) == 0 ? texel.xy : texel.zw;
+}
+
+float turbulence_s_curve(float t) {
+    return t * t * (3.0 - 2.0 * t);
+}
+
+// Returns the noise of the four color channels at the given point.
+vec4 turbulence_noise2(vec2 point, bool stitch, vec4 stitch_info) {
+    vec2 t = point + vec2(TURBULENCE_PERLIN_N);
+    ivec2 b0 = ivec2(t);
+    ivec2 b1 = b0 + ivec2(1);
+    vec2 r0 = t - vec2(b0);
+    vec2 r1 = r0 - vec2(1.0);
+
+    if (stitch) {
+        ivec2 size = ivec2(stitch_info.xy);
+        ivec2 wrap = ivec2(stitch_info.zw);
+        if (b0.x >= wrap.x) { b0.x -= size.x; }
+        if (b1.x >= wrap.x) { b1.x -= size.x; }
+        if (b0.y >= wrap.y) { b0.y -= size.y; }
+        if (b1.y >= wrap.y) { b1.y -= size.y; }
+    }
+
+    int i = turbulence_lattice(b0.x);
+    int j = turbulence_lattice(b1.x);
+    int b00 = turbulence_lattice(i + (b0.y & TURBULENCE_BM));
+    int b10 = turbulence_lattice(j + (b0.y & TURBULENCE_BM));
+    int b01 = turbulence_lattice(i + (b1.y & TURBULENCE_BM));
+    int b11 = turbulence_lattice(j + (b1.y & TURBULENCE_BM));
+
+    float sx = turbulence_s_curve(r0.x);
+    float sy = turbulence_s_curve(r0.y);
+
+    // Dynamically indexing a vector is buggy on some devices, so use a temporary array.
+    float[4] result;
+    for (int channel = 0; channel < 4; channel++) {
+        float u = dot(r0, turbulence_gradient(channel, b00));
+        float v = dot(vec2(r1.x, r0.y), turbulence_gradient(channel, b10));
+        float a = mix(u, v, sx);
+        u = dot(vec2(r0.x, r1.y), turbulence_gradient(channel, b01));
+        v = dot(r1, turbulence_gradient(channel, b11));
+        float b = mix(u, v, sx);
+        result[channel] = mix(a, b, sy);
+    }
+    return vec4(result[0], result[1], result[2], result[3]);
+}
+
+vec4 turbulence() {
+    vec4 params = fetch_from_gpu_cache_1_direct(vData.xy);
+    vec4 origin = fetch_from_gpu_cache_1_direct(vData.xy + ivec2(1, 0));
+    vec4 stitch_info = fetch_from_gpu_cache_1_direct(vData.xy + ivec2(2, 0));
+
+    int num_octaves = int(params.z);
+    int flags = int(params.w);
+    bool fractal_sum = (flags & 1) != 0;
+    bool stitch = (flags & 2) != 0;
+
+    // Noise is evaluated in the local space of the filter.
+    vec2 point = (vTaskPos + origin.xy) * origin.z * params.xy;
+
+    vec4 sum = vec4(0.0);
+    float ratio = 1.0;
+    for (int octave = 0; octave < num_octaves; octave++) {
+        vec4 noise = turbulence_noise2(point, stitch, stitch_info);
+        sum += (fractal_sum ? noise : abs(noise)) / ratio;
+        point *= 2.0;
+        ratio *= 2.0;
+        stitch_info.xy *= 2.0;
+        stitch_info.zw = 2.0 * stitch_info.zw - vec2(TURBULENCE_PERLIN_N);
+    }
+
+    if (fractal_sum) {
+        sum = (sum + vec4(1.0)) * 0.5;
+    }
+    return clamp(sum, vec4(0.0), vec4(1.0));
+}
+
+// Morphology Filter
+//
+// Each task applies the operator along a single axis, the radius along the
+// other axis is zero.
+
+#define MORPHOLOGY_DILATE 1
+
+vec4 morphology(vec2 texel) {
+    vec2 radius = vFilterData0.xy;
+    vec2 step = radius.x > 0.0 ? vec2(texel.x, 0.0) : vec2(0.0, texel.y);
+    int count = int(max(radius.x, radius.y));
+    bool dilate = vData.x == MORPHOLOGY_DILATE;
+
+    vec4 result = dilate ? vec4(0.0) : vec4(1.0);
+    for (int i = -count; i <= count; i++) {
+        vec4 texel_color = sampleInUvRectOrTransparent(sColor0, vInput1Uv + step * float(i), vInput1UvRect, texel);
+        result = dilate ? max(result, texel_color) : min(result, texel_color);
+    }
+    return result;
+}
+
+// Convolve Matrix Filter
+
+#define EDGE_MODE_DUPLICATE 0
+#define EDGE_MODE_WRAP 1
+#define EDGE_MODE_NONE 2
+#define MAX_CONVOLVE_MATRIX_ORDER 5
+
+vec4 sampleWithEdgeMode(vec2 uv, vec2 texel, int edge_mode) {
+    vec4 uvRect = vInput1UvRect;
+    if (edge_mode == EDGE_MODE_WRAP) {
+        vec2 origin = uvRect.xy - 0.5 * texel;
+        vec2 size = uvRect.zw + 0.5 * texel - origin;
+        uv = origin + mod(uv - origin, size);
+    } else if (edge_mode == EDGE_MODE_NONE) {
+        return sampleInUvRectOrTransparent(sColor0, uv, uvRect, texel);
+    }
+    return sampleInUvRect(sColor0, uv, uvRect);
+}
+
+vec4 convolve_matrix(vec2 texel) {
+    vec4 order_target = fetch_from_gpu_cache_1_direct(vData.xy);
+    vec4 params = fetch_from_gpu_cache_1_direct(vData.xy + ivec2(1, 0));
+
+    ivec2 order = ivec2(order_target.xy);
+    vec2 target = order_target.zw;
+    float divisor = params.x;
+    float bias = params.y;
+    int edge_mode = int(params.z);
+    bool preserve_alpha = params.w != 0.0;
+
+    vec4 sum = vec4(0.0);
+    for (int y = 0; y < MAX_CONVOLVE_MATRIX_ORDER; y++) {
+        if (y >= order.y) {
+            break;
+        }
+        for (int x = 0; x < MAX_CONVOLVE_MATRIX_ORDER; x++) {
+            if (x >= order.x) {
+                break;
+            }
+            int k = (order.x - x - 1) + (order.y - y - 1) * order.x;
+            vec4 kernel = fetch_from_gpu_cache_1_direct(vData.xy + ivec2(2 + k / 4, 0));
+            vec2 uv = vInput1Uv + (vec2(x, y) - target) * texel;
+            vec4 color = sampleWithEdgeMode(uv, texel, edge_mode);
+            if (preserve_alpha) {
+                if (color.a != 0.0) {
+                    color.rgb /= color.a;
+                }
+            }
+            sum += color * kernel[k % 4];
+        }
+    }
+
+    vec4 result = sum / divisor;
+    if (preserve_alpha) {
+        vec4 color = sampleInUvRect(sColor0, vInput1Uv, vInput1UvRect);
+        result.rgb = clamp(result.rgb + vec3(bias), vec3(0.0), vec3(1.0)) * color.a;
+        result.a = color.a;
+    } else {
+        result = clamp(result + vec4(bias), vec4(0.0), vec4(1.0));
+        result.rgb = min(result.rgb, vec3(result.a));
+    }
+    return result;
+}
+
+// Displacement Map Filter
+
+vec4 displacement_map(vec4 map, vec2 texel) {
+    // Dynamically indexing a vector is buggy on some devices, so use a temporary array.
+    float[4] channels;
+    channels[0] = map.r;
+    channels[1] = map.g;
+    channels[2] = map.b;
+    channels[3] = map.a;
+    vec2 offset = vFloat0.x * (vec2(channels[vData.x], channels[vData.y]) - vec2(0.5));
+    return sampleInUvRectOrTransparent(sColor0, vInput1Uv + offset * texel, vInput1UvRect, texel);
+}
+
+// Lighting Filters
+//
+// The filter data is laid out as
+// [color.rgb, surface scale], [constant, specular exponent, light type, 0]
+// followed by two blocks describing the light.
+
+#define LIGHT_DISTANT 0
+#define LIGHT_POINT 1
+#define LIGHT_SPOT 2
+
+float sampleAlpha(vec2 offset, vec2 texel) {
+    return sampleInUvRect(sColor0, vInput1Uv + offset * texel, vInput1UvRect).a;
+}
+
+vec4 lighting(vec2 texel, bool specular) {
+    vec4 color_scale = fetch_from_gpu_cache_1_direct(vData.xy);
+    vec4 params = fetch_from_gpu_cache_1_direct(vData.xy + ivec2(1, 0));
+    vec4 light0 = fetch_from_gpu_cache_1_direct(vData.xy + ivec2(2, 0));
+    vec4 light1 = fetch_from_gpu_cache_1_direct(vData.xy + ivec2(3, 0));
+
+    float surface_scale = color_scale.w;
+    int light_type = int(params.z);
+
+    // Surface normal from the Sobel gradient of the alpha channel.
+    float tl = sampleAlpha(vec2(-1.0, -1.0), texel);
+    float t  = sampleAlpha(vec2( 0.0, -1.0), texel);
+    float tr = sampleAlpha(vec2( 1.0, -1.0), texel);
+    float l  = sampleAlpha(vec2(-1.0,  0.0), texel);
+    float c  = sampleAlpha(vec2( 0.0,  0.0), texel);
+    float r  = sampleAlpha(vec2( 1.0,  0.0), texel);
+    float bl = sampleAlpha(vec2(-1.0,  1.0), texel);
+    float b  = sampleAlpha(vec2( 0.0,  1.0), texel);
+    float br = sampleAlpha(vec2( 1.0,  1.0), texel);
+    vec2 gradient = vec2(
+        (tr + 2.0 * r + br) - (tl + 2.0 * l + bl),
+        (bl + 2.0 * b + br) - (tl + 2.0 * t + tr)
+    );
+    vec3 normal = normalize(vec3(-surface_scale * 0.25 * gradient, 1.0));
+
+    vec3 light_color = color_scale.rgb;
+    vec3 light_vector;
+    if (light_type == LIGHT_DISTANT) {
+        light_vector = light0.xyz;
+    } else {
+        vec3 surface = vec3(vTaskPos, surface_scale * c);
+        light_vector = normalize(light0.xyz - surface);
+        if (light_type == LIGHT_SPOT) {
+            float minus_l_dot_s = -dot(light_vector, light1.xyz);
+            if (minus_l_dot_s < light1.w) {
+                light_color = vec3(0.0);
+            } else {
+                light_color *= pow(max(minus_l_dot_s, 0.0), light0.w);
+            }
+        }
+    }
+
+    vec4 result;
+    if (specular) {
+        vec3 halfway = normalize(light_vector + vec3(0.0, 0.0, 1.0));
+        float n_dot_h = max(dot(normal, halfway), 0.0);
+        result.rgb = params.x * pow(n_dot_h, params.y) * light_color;
+        result.rgb = clamp(result.rgb, vec3(0.0), vec3(1.0));
+        result.a = max(result.r, max(result.g, result.b));
+    } else {
+        float n_dot_l = max(dot(normal, light_vector), 0.0);
+        result.rgb = clamp(params.x * n_dot_l * light_color, vec3(0.0), vec3(1.0));
+        result.a = 1.0;
+    }
+    return result;
+}
+
 void main(void) {
     vec4 Ca = vec4(0.0, 0.0, 0.0, 0.0);
     vec4 Cb = vec4(0.0, 0.0, 0.0, 0.0);
@@ -532,6 +838,7 @@ void main(void) {
     }
 
     vec4 result = vec4(1.0, 0.0, 0.0, 1.0);
+    vec2 texel = vec2(1.0) / vec2(TEX_SIZE(sColor0).xy);
 
     bool needsPremul = true;
 
@@ -581,6 +888,43 @@ void main(void) {
         case FILTER_COMPOSITE:
             result = composite(Ca, Cb, vData.x);
             needsPremul = false;
+            break;
+        case FILTER_TURBULENCE:
+            result = turbulence();
+            break;
+        case FILTER_MORPHOLOGY:
+            result = morphology(texel);
+            needsPremul = false;
+            break;
+        case FILTER_CONVOLVE_MATRIX:
+            result = convolve_matrix(texel);
+            needsPremul = false;
+            break;
+        case FILTER_DISPLACEMENT_MAP:
+            result = displacement_map(Cb, texel);
+            needsPremul = false;
+            break;
+        case FILTER_DIFFUSE_LIGHTING:
+            result = lighting(texel, false);
+            break;
+        case FILTER_SPECULAR_LIGHTING:
+            result = lighting(texel, true);
+            needsPremul = false;
+            break;
+        case FILTER_TILE:
+            vec2 tile_origin = vFilterData0.xy;
+            vec2 tile_size = vFilterData0.zw - vFilterData0.xy;
+            vec2 tile_pos = tile_origin + mod(vTaskPos - tile_origin, tile_size);
+            result = sampleInUvRect(sColor0, vFilterData1.xy + tile_pos * texel, vInput1UvRect);
+            needsPremul = false;
+            break;
+        case FILTER_IMAGE:
+            vec2 image_pos = (vTaskPos - vFilterData0.xy) / (vFilterData0.zw - vFilterData0.xy);
+            vec2 image_uv = mix(vFilterData1.xy, vFilterData1.zw, image_pos);
+            result = sampleInUvRect(sColor0, image_uv, vInput1UvRect);
+            result *= point_inside_rect(vTaskPos, vFilterData0.xy, vFilterData0.zw);
+            needsPremul = false;
+            break;
         default:
             break;
     }
@@ -2,10 +2,13 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+#include color_space
+
 // Gradient GPU cache address.
 // Packed in to a vector to work around bug 1630356.
 flat varying HIGHP_FS_ADDRESS ivec2 v_gradient_address;
-// Repetition along the gradient stops.
+// Repetition along the gradient stops in x, and the interpolation color space
+// of the gradient in the low byte of y, with the output color space above it.
 // Packed in to a vector to work around bug 1630356.
 flat varying vec2 v_gradient_repeat;
 
@@ -56,8 +59,18 @@ vec4 sample_gradient(float offset) {
     // Fetch the start and end color. There is a [start, end] color per entry.
     vec4 texels[2] = fetch_from_gpu_cache_2(v_gradient_address.x + 2 * int(entry_index));
 
-    // Finally interpolate and apply dithering
-    return dither(texels[0] + texels[1] * entry_fract);
+    // Finally interpolate, convert to sRGB, then to the output color space,
+    // and apply dithering
+    int color_spaces = int(v_gradient_repeat.y);
+    vec4 color = texels[0] + texels[1] * entry_fract;
+    color = gradient_color_to_srgb(color, color_spaces & 0xff);
+    return dither(convert_color_space(color, RGB_COLOR_SPACE_SRGB, color_spaces >> 8));
+}
+
+// The SWGL span functions sample the gradient ramp as sRGB colors, which is
+// only correct when both the interpolation and output color spaces are sRGB.
+bool gradient_is_srgb() {
+    return int(v_gradient_repeat.y) == 0;
 }
 
 #endif //WR_FRAGMENT_SHADER
@@ -24,7 +24,8 @@ void write_gradient_vertex(
     int brush_flags,
     vec4 texel_rect,
     int extend_mode,
-    vec2 stretch_size
+    vec2 stretch_size,
+    int color_space
 ) {
     if ((brush_flags & BRUSH_FLAG_SEGMENT_RELATIVE) != 0) {
         v_pos = (vi.local_pos - segment_rect.p0) / rect_size(segment_rect);
@@ -44,6 +45,7 @@ void write_gradient_vertex(
 
     // Whether to repeat the gradient along the line instead of clamping.
     v_gradient_repeat.x = float(extend_mode == EXTEND_MODE_REPEAT);
+    v_gradient_repeat.y = float(color_space);
 
 #ifdef WR_FEATURE_ALPHA_PASS
     v_tile_repeat = tile_repeat;
@@ -2,10 +2,12 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-#include shared,prim_shared
+#include shared,prim_shared,color_space
 
 flat varying vec4 v_color;
 flat varying vec3 v_mask_swizzle;
+// The color space that color bitmap glyphs are converted to from sRGB.
+flat varying int v_output_color_space;
 // Normalized bounds of the source image in the texture.
 flat varying vec4 v_uv_bounds;
 
@@ -234,6 +236,8 @@ void main() {
 
     write_clip(vi.world_pos, clip_area, task);
 
+    v_output_color_space = RGB_COLOR_SPACE_SRGB;
+
     switch (color_mode) {
         case COLOR_MODE_ALPHA:
             v_mask_swizzle = vec3(0.0, 1.0, 1.0);
@@ -253,9 +257,13 @@ void main() {
             v_mask_swizzle = vec3(1.0, 0.0, 0.0);
             v_color = text.color;
             break;
+        case COLOR_MODE_COLOR_BITMAP:
+            v_output_color_space = ph.user_data.y;
+            v_mask_swizzle = vec3(1.0, 0.0, 0.0);
+            v_color = vec4(text.color.a);
+            break;
         case COLOR_MODE_SUBPX_CONST_COLOR:
         case COLOR_MODE_SUBPX_BG_PASS0:
-        case COLOR_MODE_COLOR_BITMAP:
             v_mask_swizzle = vec3(1.0, 0.0, 0.0);
             v_color = vec4(text.color.a);
             break;
@@ -298,6 +306,7 @@ Fragment text_fs(void) {
     // v_mask_swizzle.z != 0 means we are using an R8 texture as alpha,
     // and therefore must swizzle from the r channel to all channels.
     mask = mix(mask, mask.rrrr, bvec4(v_mask_swizzle.z != 0.0));
+    mask = convert_color_space(mask, RGB_COLOR_SPACE_SRGB, v_output_color_space);
     #ifndef WR_FEATURE_DUAL_SOURCE_BLENDING
         mask.rgb = mask.rgb * v_mask_swizzle.x + mask.aaa * v_mask_swizzle.y;
     #endif
@@ -339,6 +348,10 @@ void swgl_drawSpanRGBA8() {
     if (v_mask_swizzle.x != 0.0 && v_mask_swizzle.x != 1.0) {
         return;
     }
+    // Color bitmap glyphs that need converting take the slow path.
+    if (v_output_color_space != RGB_COLOR_SPACE_SRGB) {
+        return;
+    }
 
     #ifdef WR_FEATURE_DUAL_SOURCE_BLENDING
         swgl_commitTextureLinearRGBA8(sColor0, v_uv, v_uv_bounds);
@@ -0,0 +1,667 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Evaluation of the keyframe animations registered with
+//! `Transaction::add_animation`.
+//!
+//! Each document owns an `AnimationStore`. Every time a frame is requested,
+//! the render backend samples the store with the current time and feeds the
+//! resulting values to the document's `SceneProperties`, where they override
+//! the dynamic properties supplied by the embedder. While any animation is
+//! playing, the render backend also builds frames on its own, so animations
+//! don't depend on the embedder requesting frames. The timing model follows
+//! the CSS animations and easing functions specifications.
+//!
+//! Times are in nanoseconds from the monotonic `precise_time_ns` clock.
+
+use api::{AnimatedProperty, AnimationDirection, AnimationId, ColorF, DynamicProperties};
+use api::{Keyframe, KeyframeAnimation, NotificationRequest, StepPosition, TimingFunction};
+use api::units::LayoutTransform;
+
+/// The keyframe animations of a document.
+pub struct AnimationStore {
+    /// Animations in the order they were added. When several animations target
+    /// the same property binding, the last one wins.
+    animations: Vec<Animation>,
+}
+
+struct Animation {
+    desc: KeyframeAnimation,
+    notification: Option<NotificationRequest>,
+    /// The time of the first frame built with this animation.
+    start_time: Option<u64>,
+    finished: bool,
+}
+
+impl AnimationStore {
+    pub fn new() -> Self {
+        AnimationStore {
+            animations: Vec::new(),
+        }
+    }
+
+    /// Add an animation, replacing any animation with the same id.
+    pub fn add(&mut self, desc: KeyframeAnimation, notification: Option<NotificationRequest>) {
+        self.remove(desc.id);
+        self.animations.push(Animation {
+            desc,
+            notification,
+            start_time: None,
+            finished: false,
+        });
+    }
+
+    /// Remove an animation. Its pending notification request, if any, is
+    /// notified with `Checkpoint::TransactionDropped`.
+    pub fn remove(&mut self, id: AnimationId) {
+        self.animations.retain(|animation| animation.desc.id != id);
+    }
+
+    /// Returns true if any animation hasn't finished playing, in which case
+    /// new frames are needed to show it.
+    pub fn is_active(&self) -> bool {
+        self.animations.iter().any(|animation| !animation.finished)
+    }
+
+    /// Evaluate the animations at the given time.
+    ///
+    /// Animations that finish playing have their notification request moved
+    /// to `finished`, and are dropped unless they fill forwards.
+    pub fn sample(
+        &mut self,
+        time: u64,
+        finished: &mut Vec<NotificationRequest>,
+    ) -> DynamicProperties {
+        let mut properties = DynamicProperties::default();
+
+        for animation in &mut self.animations {
+            let start_time = *animation.start_time.get_or_insert(time);
+            let elapsed_ms = time.saturating_sub(start_time) as f64 / 1_000_000.0;
+
+            let sample = sample_timing(&animation.desc, elapsed_ms);
+
+            if let Some(progress) = sample.progress {
+                match animation.desc.property {
+                    AnimatedProperty::Transform(key, ref keyframes) => {
+                        if let Some(value) = sample_keyframes(keyframes, progress, interpolate_transform) {
+                            properties.transforms.push(key.with(value));
+                        }
+                    }
+                    AnimatedProperty::Float(key, ref keyframes) => {
+                        if let Some(value) = sample_keyframes(keyframes, progress, interpolate_float) {
+                            properties.floats.push(key.with(value));
+                        }
+                    }
+                    AnimatedProperty::Color(key, ref keyframes) => {
+                        if let Some(value) = sample_keyframes(keyframes, progress, interpolate_color) {
+                            properties.colors.push(key.with(value));
+                        }
+                    }
+                }
+            }
+
+            animation.finished = sample.finished;
+            if sample.finished {
+                if let Some(notification) = animation.notification.take() {
+                    finished.push(notification);
+                }
+            }
+        }
+
+        self.animations.retain(|animation| {
+            !animation.finished || animation.desc.fill_mode.fills_forwards()
+        });
+
+        properties
+    }
+}
+
+/// The state of an animation at a given time.
+#[derive(Debug, PartialEq)]
+struct TimingSample {
+    /// The directed progress within the current iteration, or None if the
+    /// animation doesn't apply a value.
+    progress: Option<f32>,
+    /// True once all iterations have been played.
+    finished: bool,
+}
+
+/// Computes the directed progress of an animation, `elapsed_ms` after it
+/// started.
+fn sample_timing(desc: &KeyframeAnimation, elapsed_ms: f64) -> TimingSample {
+    let duration = desc.duration_ms.max(0.0) as f64;
+    let iterations = desc.iterations.max(0.0) as f64;
+    let active_duration = if duration == 0.0 { 0.0 } else { duration * iterations };
+    let local_time = elapsed_ms - desc.delay_ms as f64;
+
+    let (overall_progress, finished) = if local_time < 0.0 {
+        if !desc.fill_mode.fills_backwards() {
+            return TimingSample { progress: None, finished: false };
+        }
+        (0.0, false)
+    } else if local_time >= active_duration {
+        if !desc.fill_mode.fills_forwards() {
+            return TimingSample { progress: None, finished: true };
+        }
+        (iterations, true)
+    } else {
+        (local_time / duration, false)
+    };
+
+    let (mut iteration_progress, mut current_iteration) = if overall_progress.is_finite() {
+        (overall_progress.fract(), overall_progress.floor())
+    } else {
+        (1.0, 0.0)
+    };
+
+    // The end of the last iteration is reached with a progress of 1 rather
+    // than at the start of the next iteration.
+    if finished && iteration_progress == 0.0 && iterations > 0.0 {
+        iteration_progress = 1.0;
+        current_iteration -= 1.0;
+    }
+
+    let forwards = match desc.direction {
+        AnimationDirection::Normal => true,
+        AnimationDirection::Reverse => false,
+        AnimationDirection::Alternate => current_iteration % 2.0 == 0.0,
+        AnimationDirection::AlternateReverse => current_iteration % 2.0 != 0.0,
+    };
+
+    let progress = if forwards { iteration_progress } else { 1.0 - iteration_progress };
+
+    TimingSample {
+        progress: Some(progress as f32),
+        finished,
+    }
+}
+
+/// Evaluates a set of keyframes at the given progress.
+fn sample_keyframes<T: Copy>(
+    keyframes: &[Keyframe<T>],
+    progress: f32,
+    interpolate: fn(&T, &T, f32) -> T,
+) -> Option<T> {
+    let first = keyframes.first()?;
+    let last = keyframes.last()?;
+
+    if progress < first.offset {
+        return Some(first.value);
+    }
+    if progress >= last.offset {
+        return Some(last.value);
+    }
+
+    let segment = keyframes.windows(2).find(|pair| progress < pair[1].offset)?;
+    let (from, to) = (&segment[0], &segment[1]);
+    let t = (progress - from.offset) / (to.offset - from.offset);
+
+    Some(interpolate(&from.value, &to.value, ease(from.easing, t)))
+}
+
+/// Applies an easing function to an input progress in [0, 1].
+fn ease(timing_function: TimingFunction, t: f32) -> f32 {
+    match timing_function {
+        TimingFunction::Linear => t,
+        TimingFunction::Ease => cubic_bezier(0.25, 0.1, 0.25, 1.0, t),
+        TimingFunction::EaseIn => cubic_bezier(0.42, 0.0, 1.0, 1.0, t),
+        TimingFunction::EaseOut => cubic_bezier(0.0, 0.0, 0.58, 1.0, t),
+        TimingFunction::EaseInOut => cubic_bezier(0.42, 0.0, 0.58, 1.0, t),
+        TimingFunction::CubicBezier(x1, y1, x2, y2) => cubic_bezier(x1, y1, x2, y2, t),
+        TimingFunction::Steps(steps, position) => step(steps, position, t),
+    }
+}
+
+fn cubic_bezier(x1: f32, y1: f32, x2: f32, y2: f32, x: f32) -> f32 {
+    if x <= 0.0 || x >= 1.0 {
+        return x.clamp(0.0, 1.0);
+    }
+
+    // Evaluates one coordinate of the curve, whose end points are 0 and 1.
+    let curve = |p1: f32, p2: f32, t: f32| {
+        let u = 1.0 - t;
+        3.0 * u * u * t * p1 + 3.0 * u * t * t * p2 + t * t * t
+    };
+    let derivative = |p1: f32, p2: f32, t: f32| {
+        let u = 1.0 - t;
+        3.0 * u * u * p1 + 6.0 * u * t * (p2 - p1) + 3.0 * t * t * (1.0 - p2)
+    };
+
+    // Find the parameter of the curve at x with Newton's method, falling back
+    // to bisection when it doesn't converge.
+    let mut t = x;
+    for _ in 0 .. 8 {
+        let error = curve(x1, x2, t) - x;
+        if error.abs() < 1e-6 {
+            return curve(y1, y2, t);
+        }
+        let slope = derivative(x1, x2, t);
+        if slope.abs() < 1e-6 {
+            break;
+        }
+        t -= error / slope;
+    }
+
+    let (mut low, mut high) = (0.0, 1.0);
+    t = x;
+    for _ in 0 .. 32 {
+        let value = curve(x1, x2, t);
+        if (value - x).abs() < 1e-6 {
+            break;
+        }
+        if value < x {
+            low = t;
+        } else {
+            high = t;
+        }
+        t = (low + high) * 0.5;
+    }
+
+    curve(y1, y2, t)
+}
+
+fn step(steps: u32, position: StepPosition, t: f32) -> f32 {
+    let steps = match position {
+        StepPosition::JumpNone => steps.max(2),
+        _ => steps.max(1),
+    } as f32;
+
+    let mut current_step = (t * steps).floor();
+    if let StepPosition::JumpStart | StepPosition::JumpBoth = position {
+        current_step += 1.0;
+    }
+
+    let jumps = match position {
+        StepPosition::JumpStart | StepPosition::JumpEnd => steps,
+        StepPosition::JumpNone => steps - 1.0,
+        StepPosition::JumpBoth => steps + 1.0,
+    };
+
+    current_step.clamp(0.0, jumps) / jumps
+}
+
+fn interpolate_float(from: &f32, to: &f32, t: f32) -> f32 {
+    from + (to - from) * t
+}
+
+/// Interpolates colors in premultiplied space, so that fading in from a
+/// transparent color doesn't go through that color's components.
+fn interpolate_color(from: &ColorF, to: &ColorF, t: f32) -> ColorF {
+    let from = from.premultiplied();
+    let to = to.premultiplied();
+    let a = interpolate_float(&from.a, &to.a, t).clamp(0.0, 1.0);
+    if a <= 0.0 {
+        return ColorF::TRANSPARENT;
+    }
+
+    ColorF::new(
+        interpolate_float(&from.r, &to.r, t) / a,
+        interpolate_float(&from.g, &to.g, t) / a,
+        interpolate_float(&from.b, &to.b, t) / a,
+        a,
+    )
+}
+
+/// Interpolates transforms by decomposing them into translation, scale, skew
+/// and rotation, as CSS does. Transforms that can't be decomposed switch
+/// from one to the other half-way through.
+fn interpolate_transform(from: &LayoutTransform, to: &LayoutTransform, t: f32) -> LayoutTransform {
+    match (DecomposedTransform::new(from), DecomposedTransform::new(to)) {
+        (Some(from), Some(to)) => from.interpolate(&to, t).recompose(),
+        _ => if t < 0.5 { *from } else { *to },
+    }
+}
+
+type Vec3 = [f32; 3];
+
+fn dot(a: Vec3, b: Vec3) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+/// a + b * s
+fn add_scaled(a: Vec3, b: Vec3, s: f32) -> Vec3 {
+    [a[0] + b[0] * s, a[1] + b[1] * s, a[2] + b[2] * s]
+}
+
+fn scale(a: Vec3, s: f32) -> Vec3 {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+/// An affine transform, split such that its upper 3x3 matrix is
+/// `diag(scale) * skew * rotation` where skew is lower triangular. Matrices
+/// apply to row vectors.
+#[derive(Clone, Copy, Debug)]
+struct DecomposedTransform {
+    translation: Vec3,
+    scale: Vec3,
+    /// The xy, xz and yz shear factors.
+    skew: Vec3,
+    /// A unit quaternion (x, y, z, w).
+    quaternion: [f32; 4],
+}
+
+impl DecomposedTransform {
+    /// Returns None for transforms with a perspective component or a
+    /// singular upper 3x3 matrix.
+    fn new(transform: &LayoutTransform) -> Option<Self> {
+        let m = transform.to_array();
+        let w = m[15];
+        if m[3] != 0.0 || m[7] != 0.0 || m[11] != 0.0 || w == 0.0 {
+            return None;
+        }
+
+        let mut rows = [
+            scale([m[0], m[1], m[2]], 1.0 / w),
+            scale([m[4], m[5], m[6]], 1.0 / w),
+            scale([m[8], m[9], m[10]], 1.0 / w),
+        ];
+        let translation = scale([m[12], m[13], m[14]], 1.0 / w);
+
+        // Gram-Schmidt orthonormalization of the rows, keeping track of the
+        // projections as the skew factors.
+        let mut scales = [0.0; 3];
+        let mut skew = [0.0; 3];
+
+        scales[0] = dot(rows[0], rows[0]).sqrt();
+        if scales[0] == 0.0 {
+            return None;
+        }
+        rows[0] = scale(rows[0], 1.0 / scales[0]);
+
+        skew[0] = dot(rows[0], rows[1]);
+        rows[1] = add_scaled(rows[1], rows[0], -skew[0]);
+        scales[1] = dot(rows[1], rows[1]).sqrt();
+        if scales[1] == 0.0 {
+            return None;
+        }
+        rows[1] = scale(rows[1], 1.0 / scales[1]);
+        skew[0] /= scales[1];
+
+        skew[1] = dot(rows[0], rows[2]);
+        rows[2] = add_scaled(rows[2], rows[0], -skew[1]);
+        skew[2] = dot(rows[1], rows[2]);
+        rows[2] = add_scaled(rows[2], rows[1], -skew[2]);
+        scales[2] = dot(rows[2], rows[2]).sqrt();
+        if scales[2] == 0.0 {
+            return None;
+        }
+        rows[2] = scale(rows[2], 1.0 / scales[2]);
+        skew[1] /= scales[2];
+        skew[2] /= scales[2];
+
+        // Keep the rotation proper by moving reflections into the scale.
+        if dot(rows[0], cross(rows[1], rows[2])) < 0.0 {
+            for i in 0 .. 3 {
+                scales[i] = -scales[i];
+                rows[i] = scale(rows[i], -1.0);
+            }
+        }
+
+        Some(DecomposedTransform {
+            translation,
+            scale: scales,
+            skew,
+            quaternion: quaternion_from_rotation(&rows),
+        })
+    }
+
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        let lerp = |a: Vec3, b: Vec3| add_scaled(scale(a, 1.0 - t), b, t);
+        DecomposedTransform {
+            translation: lerp(self.translation, other.translation),
+            scale: lerp(self.scale, other.scale),
+            skew: lerp(self.skew, other.skew),
+            quaternion: slerp(self.quaternion, other.quaternion, t),
+        }
+    }
+
+    fn recompose(&self) -> LayoutTransform {
+        let r = rotation_from_quaternion(self.quaternion);
+        let [sx, sy, sz] = self.scale;
+        let [xy, xz, yz] = self.skew;
+
+        let row0 = scale(r[0], sx);
+        let row1 = scale(add_scaled(r[1], r[0], xy), sy);
+        let row2 = scale(add_scaled(add_scaled(r[2], r[1], yz), r[0], xz), sz);
+        let [tx, ty, tz] = self.translation;
+
+        LayoutTransform::new(
+            row0[0], row0[1], row0[2], 0.0,
+            row1[0], row1[1], row1[2], 0.0,
+            row2[0], row2[1], row2[2], 0.0,
+            tx, ty, tz, 1.0,
+        )
+    }
+}
+
+/// Converts a unit quaternion into the rows of a rotation matrix.
+fn rotation_from_quaternion(q: [f32; 4]) -> [Vec3; 3] {
+    let [x, y, z, w] = q;
+    [
+        [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - z * w), 2.0 * (x * z + y * w)],
+        [2.0 * (x * y + z * w), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - x * w)],
+        [2.0 * (x * z - y * w), 2.0 * (y * z + x * w), 1.0 - 2.0 * (x * x + y * y)],
+    ]
+}
+
+/// The inverse of `rotation_from_quaternion`, picking the largest component
+/// first for numerical stability.
+fn quaternion_from_rotation(r: &[Vec3; 3]) -> [f32; 4] {
+    let trace = r[0][0] + r[1][1] + r[2][2];
+    if trace > 0.0 {
+        let s = (trace + 1.0).sqrt() * 2.0;
+        [(r[2][1] - r[1][2]) / s, (r[0][2] - r[2][0]) / s, (r[1][0] - r[0][1]) / s, s * 0.25]
+    } else if r[0][0] > r[1][1] && r[0][0] > r[2][2] {
+        let s = (1.0 + r[0][0] - r[1][1] - r[2][2]).sqrt() * 2.0;
+        [s * 0.25, (r[0][1] + r[1][0]) / s, (r[0][2] + r[2][0]) / s, (r[2][1] - r[1][2]) / s]
+    } else if r[1][1] > r[2][2] {
+        let s = (1.0 + r[1][1] - r[0][0] - r[2][2]).sqrt() * 2.0;
+        [(r[0][1] + r[1][0]) / s, s * 0.25, (r[1][2] + r[2][1]) / s, (r[0][2] - r[2][0]) / s]
+    } else {
+        let s = (1.0 + r[2][2] - r[0][0] - r[1][1]).sqrt() * 2.0;
+        [(r[0][2] + r[2][0]) / s, (r[1][2] + r[2][1]) / s, s * 0.25, (r[1][0] - r[0][1]) / s]
+    }
+}
+
+/// Spherical linear interpolation between unit quaternions, along the
+/// shortest path.
+fn slerp(from: [f32; 4], to: [f32; 4], t: f32) -> [f32; 4] {
+    let mut to = to;
+    let mut cos_theta = from.iter().zip(to.iter()).map(|(a, b)| a * b).sum::<f32>();
+    if cos_theta < 0.0 {
+        for c in &mut to {
+            *c = -*c;
+        }
+        cos_theta = -cos_theta;
+    }
+
+    let (a, b) = if cos_theta > 0.9995 {
+        // The quaternions are almost the same, lerp to avoid dividing by ~0.
+        (1.0 - t, t)
+    } else {
+        let theta = cos_theta.min(1.0).acos();
+        let sin_theta = theta.sin();
+        (((1.0 - t) * theta).sin() / sin_theta, (t * theta).sin() / sin_theta)
+    };
+
+    let mut q = [0.0; 4];
+    for i in 0 .. 4 {
+        q[i] = from[i] * a + to[i] * b;
+    }
+    let length = q.iter().map(|c| c * c).sum::<f32>().sqrt();
+    for c in &mut q {
+        *c /= length;
+    }
+
+    q
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use api::{AnimationFillMode, IdNamespace, PropertyBindingKey};
+
+    fn animation(duration_ms: f32, iterations: f32) -> KeyframeAnimation {
+        let key = PropertyBindingKey::new(1);
+        let keyframes = vec![
+            Keyframe::new(0.0, 0.0, TimingFunction::Linear),
+            Keyframe::new(1.0, 1.0, TimingFunction::Linear),
+        ];
+        let id = AnimationId::new(IdNamespace(0), 0);
+        let mut desc = KeyframeAnimation::new(id, AnimatedProperty::Float(key, keyframes), duration_ms);
+        desc.iterations = iterations;
+        desc
+    }
+
+    fn progress(desc: &KeyframeAnimation, elapsed_ms: f64) -> Option<f32> {
+        sample_timing(desc, elapsed_ms).progress
+    }
+
+    fn assert_transforms_eq(a: &LayoutTransform, b: &LayoutTransform) {
+        for (a, b) in a.to_array().iter().zip(b.to_array().iter()) {
+            assert!((a - b).abs() < 1e-4, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn timing_functions() {
+        assert_eq!(ease(TimingFunction::Linear, 0.3), 0.3);
+        assert_eq!(ease(TimingFunction::Ease, 0.0), 0.0);
+        assert_eq!(ease(TimingFunction::Ease, 1.0), 1.0);
+        assert!((ease(TimingFunction::Ease, 0.5) - 0.8024).abs() < 1e-3);
+        assert!((ease(TimingFunction::EaseInOut, 0.5) - 0.5).abs() < 1e-4);
+        assert!((ease(TimingFunction::EaseIn, 0.25) - 0.0935).abs() < 1e-3);
+
+        assert_eq!(ease(TimingFunction::Steps(4, StepPosition::JumpEnd), 0.3), 0.25);
+        assert_eq!(ease(TimingFunction::Steps(4, StepPosition::JumpEnd), 1.0), 1.0);
+        assert_eq!(ease(TimingFunction::Steps(4, StepPosition::JumpStart), 0.0), 0.25);
+        assert_eq!(ease(TimingFunction::Steps(3, StepPosition::JumpNone), 0.5), 0.5);
+        assert_eq!(ease(TimingFunction::Steps(3, StepPosition::JumpBoth), 0.0), 0.25);
+        assert_eq!(ease(TimingFunction::Steps(3, StepPosition::JumpBoth), 0.99), 0.75);
+    }
+
+    #[test]
+    fn timing_phases() {
+        let mut desc = animation(100.0, 2.0);
+        desc.delay_ms = 50.0;
+        assert_eq!(progress(&desc, 0.0), None);
+        assert_eq!(progress(&desc, 75.0), Some(0.25));
+        assert_eq!(progress(&desc, 175.0), Some(0.25));
+        assert_eq!(sample_timing(&desc, 250.0), TimingSample { progress: None, finished: true });
+
+        desc.fill_mode = AnimationFillMode::Both;
+        assert_eq!(progress(&desc, 0.0), Some(0.0));
+        assert_eq!(sample_timing(&desc, 250.0), TimingSample { progress: Some(1.0), finished: true });
+
+        desc.iterations = 1.5;
+        assert_eq!(progress(&desc, 1000.0), Some(0.5));
+
+        desc.iterations = f32::INFINITY;
+        assert_eq!(sample_timing(&desc, 1e9 + 75.0), TimingSample { progress: Some(0.25), finished: false });
+    }
+
+    #[test]
+    fn timing_directions() {
+        let mut desc = animation(100.0, 2.0);
+        desc.fill_mode = AnimationFillMode::Forwards;
+
+        desc.direction = AnimationDirection::Reverse;
+        assert_eq!(progress(&desc, 25.0), Some(0.75));
+        assert_eq!(progress(&desc, 125.0), Some(0.75));
+        assert_eq!(progress(&desc, 200.0), Some(0.0));
+
+        desc.direction = AnimationDirection::Alternate;
+        assert_eq!(progress(&desc, 25.0), Some(0.25));
+        assert_eq!(progress(&desc, 125.0), Some(0.75));
+        assert_eq!(progress(&desc, 200.0), Some(0.0));
+
+        desc.direction = AnimationDirection::AlternateReverse;
+        assert_eq!(progress(&desc, 25.0), Some(0.75));
+        assert_eq!(progress(&desc, 125.0), Some(0.25));
+        assert_eq!(progress(&desc, 200.0), Some(1.0));
+    }
+
+    #[test]
+    fn keyframe_segments() {
+        let keyframes = [
+            Keyframe::new(0.2, 10.0, TimingFunction::Linear),
+            Keyframe::new(0.6, 20.0, TimingFunction::Steps(2, StepPosition::JumpEnd)),
+            Keyframe::new(0.8, 40.0, TimingFunction::Linear),
+        ];
+        let value = |progress| sample_keyframes(&keyframes, progress, interpolate_float);
+
+        assert_eq!(value(0.0), Some(10.0));
+        assert_eq!(value(0.4), Some(15.0));
+        assert_eq!(value(0.65), Some(20.0));
+        assert_eq!(value(0.75), Some(30.0));
+        assert_eq!(value(1.0), Some(40.0));
+        assert_eq!(sample_keyframes(&[], 0.5, interpolate_float), None);
+    }
+
+    #[test]
+    fn color_interpolation() {
+        let from = ColorF::new(1.0, 0.0, 0.0, 0.0);
+        let to = ColorF::new(0.0, 0.0, 1.0, 1.0);
+        // The transparent color doesn't bleed into the result.
+        assert_eq!(interpolate_color(&from, &to, 0.5), ColorF::new(0.0, 0.0, 1.0, 0.5));
+        assert_eq!(interpolate_color(&from, &from, 0.5), ColorF::TRANSPARENT);
+    }
+
+    #[test]
+    fn transform_decomposition() {
+        let transform = DecomposedTransform {
+            translation: [10.0, -20.0, 5.0],
+            scale: [2.0, -0.5, 3.0],
+            skew: [0.3, -0.2, 0.1],
+            quaternion: slerp([0.0, 0.0, 0.0, 1.0], [0.48, -0.6, 0.64, 0.0], 0.3),
+        }.recompose();
+
+        let decomposed = DecomposedTransform::new(&transform).unwrap();
+        assert_transforms_eq(&decomposed.recompose(), &transform);
+
+        // Perspective transforms can't be decomposed.
+        let mut m = transform.to_array();
+        m[11] = -0.01;
+        let perspective = LayoutTransform::new(
+            m[0], m[1], m[2], m[3],
+            m[4], m[5], m[6], m[7],
+            m[8], m[9], m[10], m[11],
+            m[12], m[13], m[14], m[15],
+        );
+        assert!(DecomposedTransform::new(&perspective).is_none());
+        assert_transforms_eq(&interpolate_transform(&transform, &perspective, 0.4), &transform);
+        assert_transforms_eq(&interpolate_transform(&transform, &perspective, 0.6), &perspective);
+    }
+
+    #[test]
+    fn transform_interpolation() {
+        let rotation = |degrees: f32, tx: f32| {
+            let (sin, cos) = degrees.to_radians().sin_cos();
+            LayoutTransform::new(
+                cos, sin, 0.0, 0.0,
+                -sin, cos, 0.0, 0.0,
+                0.0, 0.0, 1.0, 0.0,
+                tx, 0.0, 0.0, 1.0,
+            )
+        };
+
+        // Rotations are interpolated by angle rather than component-wise.
+        assert_transforms_eq(
+            &interpolate_transform(&rotation(0.0, 0.0), &rotation(90.0, 100.0), 0.5),
+            &rotation(45.0, 50.0),
+        );
+        assert_transforms_eq(
+            &interpolate_transform(&rotation(-60.0, 0.0), &rotation(60.0, 0.0), 0.25),
+            &rotation(-30.0, 0.0),
+        );
+    }
+}
@@ -2,10 +2,11 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use api::{AlphaType, ClipMode, ImageRendering, ImageBufferKind};
+use api::{AlphaType, ClipMode, ImageKey, ImageRendering, ImageBufferKind, RgbColorSpace};
 use api::{FontInstanceFlags, YuvColorSpace, YuvFormat, ColorDepth, ColorRange, PremultipliedColorF};
 use api::units::*;
 use crate::clip::{ClipNodeFlags, ClipNodeRange, ClipItemKind, ClipStore};
+use crate::frame_builder::ToneMapping;
 use crate::spatial_tree::{SpatialTree, SpatialNodeIndex, CoordinateSystemId};
 use crate::glyph_rasterizer::{GlyphFormat, SubpixelDirection};
 use crate::gpu_cache::{GpuBlockData, GpuCache, GpuCacheAddress};
@@ -15,6 +16,7 @@ use crate::gpu_types::{PrimitiveInstanceData, RasterizationSpace, GlyphInstance}
 use crate::gpu_types::{PrimitiveHeader, PrimitiveHeaderIndex, TransformPaletteId, TransformPalette};
 use crate::gpu_types::{ImageBrushData, get_shader_opacity, BoxShadowData};
 use crate::gpu_types::{ClipMaskInstanceCommon, ClipMaskInstanceImage, ClipMaskInstanceRect, ClipMaskInstanceBoxShadow};
+use crate::gpu_types::ClipMaskInstancePolygon;
 use crate::internal_types::{FastHashMap, Swizzle, TextureSource, Filter};
 use crate::picture::{Picture3DContext, PictureCompositeMode, TileKey};
 use crate::prim_store::{PrimitiveInstanceKind, ClipData, PrimitiveInstanceIndex};
@@ -23,7 +25,7 @@ use crate::prim_store::{BrushSegment, ClipMaskKind, ClipTaskIndex};
 use crate::prim_store::VECS_PER_SEGMENT;
 use crate::render_target::RenderTargetContext;
 use crate::render_task_graph::{RenderTaskId, RenderTaskGraph};
-use crate::render_task::RenderTaskAddress;
+use crate::render_task::{RenderTaskAddress, RenderTaskKind};
 use crate::renderer::{BlendMode, ShaderColorMode};
 use crate::renderer::MAX_VERTEX_TEXTURE_WIDTH;
 use crate::resource_cache::{GlyphFetchResult, ImageProperties, ImageRequest};
@@ -1053,12 +1055,14 @@ impl BatchBuilder {
                 };
 
                 let glyph_keys = &ctx.scratch.glyph_keys[run.glyph_keys_range];
+                // Color bitmap glyphs are rasterized in sRGB, the text shader
+                // converts them to the output color space.
                 let prim_header_index = prim_headers.push(
                     &prim_header,
                     z_id,
                     [
                         (run.raster_scale * 65535.0).round() as i32,
-                        0,
+                        ctx.output_color_space as i32,
                         0,
                         0,
                     ],
@@ -1349,6 +1353,68 @@ impl BatchBuilder {
                     specific_resource_address,
                 );
             }
+            PrimitiveInstanceKind::Path { data_handle, ref render_task, .. } => {
+                // A path is drawn as an image brush sampling its cached coverage
+                // mask, tinted by the color stored in the template. The tint was
+                // converted to the output color space during scene building, and
+                // the mask is not a color, so the shader doesn't convert either.
+                let task_id = match render_task {
+                    Some(task_id) => *task_id,
+                    None => return,
+                };
+
+                let common_data = &ctx.data_stores.path[data_handle].common;
+                let prim_cache_address = gpu_cache.get_address(&common_data.gpu_cache_handle);
+
+                let (clip_task_address, clip_mask_texture_id) = ctx.get_prim_clip_task_and_texture(
+                    prim_info.clip_task_index,
+                    render_tasks,
+                ).unwrap();
+
+                let (uv_rect_address, texture) = render_tasks.resolve_location(task_id, gpu_cache).unwrap();
+                let textures = BatchTextures::prim_textured(
+                    texture,
+                    clip_mask_texture_id,
+                );
+                let prim_user_data = ImageBrushData {
+                    color_mode: ShaderColorMode::Image,
+                    alpha_type: AlphaType::PremultipliedAlpha,
+                    raster_space: RasterizationSpace::Local,
+                    opacity: 1.0,
+                }.encode();
+
+                let prim_header = PrimitiveHeader {
+                    local_rect: prim_rect,
+                    local_clip_rect: prim_info.combined_local_clip_rect,
+                    specific_prim_address: prim_cache_address,
+                    transform_id,
+                };
+
+                let prim_header_index = prim_headers.push(
+                    &prim_header,
+                    z_id,
+                    prim_user_data,
+                );
+
+                let batch_key = BatchKey {
+                    blend_mode: BlendMode::PremultipliedAlpha,
+                    kind: BatchKind::Brush(BrushBatchKind::Image(texture.image_buffer_kind())),
+                    textures,
+                };
+
+                self.add_brush_instance_to_batches(
+                    batch_key,
+                    batch_features,
+                    bounding_rect,
+                    z_id,
+                    INVALID_SEGMENT_INDEX,
+                    EdgeAaSegmentMask::all(),
+                    clip_task_address,
+                    brush_flags | BrushFlags::PERSPECTIVE_INTERPOLATION,
+                    prim_header_index,
+                    uv_rect_address.as_int(),
+                );
+            }
             PrimitiveInstanceKind::Picture { pic_index, segment_instance_index, .. } => {
                 let picture = &ctx.prim_store.pictures[pic_index.0];
                 let blend_mode = BlendMode::PremultipliedAlpha;
@@ -1635,16 +1701,16 @@ impl BatchBuilder {
 
                                         let user_data = match filter {
                                             Filter::Identity => 0x10000i32, // matches `Contrast(1)`
-                                            Filter::Contrast(amount) |
-                                            Filter::Grayscale(amount) |
-                                            Filter::Invert(amount) |
-                                            Filter::Saturate(amount) |
-                                            Filter::Sepia(amount) |
-                                            Filter::Brightness(amount) => {
+                                            Filter::Contrast(_, amount) |
+                                            Filter::Grayscale(_, amount) |
+                                            Filter::Invert(_, amount) |
+                                            Filter::Saturate(_, amount) |
+                                            Filter::Sepia(_, amount) |
+                                            Filter::Brightness(_, amount) => {
                                                 (amount * 65536.0) as i32
                                             }
                                             Filter::SrgbToLinear | Filter::LinearToSrgb => 0,
-                                            Filter::HueRotate(angle) => {
+                                            Filter::HueRotate(_, angle) => {
                                                 (0.01745329251 * angle * 65536.0) as i32
                                             }
                                             Filter::ColorMatrix(_) => {
@@ -2329,12 +2395,13 @@ impl BatchBuilder {
                 let image_data = &ctx.data_stores.image[data_handle].kind;
                 let common_data = &ctx.data_stores.image[data_handle].common;
                 let image_instance = &ctx.prim_store.images[image_instance_index];
-                let prim_user_data = ImageBrushData {
+                let mut prim_user_data = ImageBrushData {
                     color_mode: ShaderColorMode::Image,
                     alpha_type: image_data.alpha_type,
                     raster_space: RasterizationSpace::Local,
                     opacity: 1.0,
                 }.encode();
+                prim_user_data[3] = ctx.get_image_color_spaces(image_data.key);
 
                 let blend_mode = if !common_data.opacity.is_opaque ||
                     prim_info.clip_task_index != ClipTaskIndex::INVALID ||
@@ -2349,6 +2416,20 @@ impl BatchBuilder {
                     BlendMode::None
                 };
 
+                // Only tone map images sampled straight from the texture cache.
+                // The render tasks that copy or downscale them are drawn in
+                // 8-bit targets, which already clamped their colors.
+                let samples_texture_cache = match image_instance.src_color {
+                    Some(task_id) => matches!(render_tasks[task_id].kind, RenderTaskKind::Image(..)),
+                    // Each tile of a tiled image is sampled from the texture cache.
+                    None => true,
+                };
+                let brush_flags = if samples_texture_cache {
+                    brush_flags | ctx.get_image_tone_mapping_flags(image_data.key)
+                } else {
+                    brush_flags
+                };
+
                 if image_instance.visible_tiles.is_empty() {
                     if cfg!(debug_assertions) {
                         match ctx.resource_cache.get_image_properties(image_data.key) {
@@ -3159,6 +3240,8 @@ pub struct ClipBatchList {
     /// Rectangle draws fill up the rectangles with rounded corners.
     pub slow_rectangles: Vec<ClipMaskInstanceRect>,
     pub fast_rectangles: Vec<ClipMaskInstanceRect>,
+    /// Polygon draws evaluate the polygon winding analytically.
+    pub polygons: Vec<ClipMaskInstancePolygon>,
     /// Image draws apply the image masking.
     pub images: FastHashMap<(TextureSource, Option<DeviceIntRect>), Vec<ClipMaskInstanceImage>>,
     pub box_shadows: FastHashMap<TextureSource, Vec<ClipMaskInstanceBoxShadow>>,
@@ -3169,6 +3252,7 @@ impl ClipBatchList {
         ClipBatchList {
             slow_rectangles: Vec::new(),
             fast_rectangles: Vec::new(),
+            polygons: Vec::new(),
             images: FastHashMap::default(),
             box_shadows: FastHashMap::default(),
         }
@@ -3564,6 +3648,21 @@ impl ClipBatcher {
                         true
                     }
                 }
+                ClipItemKind::Polygon { rect, polygon_handle, ref gpu_cache_handle } => {
+                    let polygon = &ctx.data_stores.polygon[polygon_handle];
+
+                    self.get_batch_list(is_first_clip)
+                        .polygons
+                        .push(ClipMaskInstancePolygon {
+                            common,
+                            local_rect: rect,
+                            polygon_address: gpu_cache.get_address(gpu_cache_handle),
+                            vertex_count: polygon.points.len() as i32,
+                            fill_rule: u8::from(polygon.fill_rule) as i32,
+                        });
+
+                    true
+                }
                 ClipItemKind::RoundedRectangle { rect, ref radius, mode, .. } => {
                     let batch_list = self.get_batch_list(is_first_clip);
                     let instance = ClipMaskInstanceRect {
@@ -3631,6 +3730,36 @@ impl<'a, 'rc> RenderTargetContext<'a, 'rc> {
             render_tasks,
         )
     }
+
+    /// Returns the brush flags selecting the tone mapping to apply to an
+    /// image, if it is stored in a high bit depth format.
+    fn get_image_tone_mapping_flags(&self, image_key: ImageKey) -> BrushFlags {
+        let tone_mapping = match self.hdr_tone_mapping {
+            Some(tone_mapping) => tone_mapping,
+            None => return BrushFlags::empty(),
+        };
+
+        match self.resource_cache.get_image_properties(image_key) {
+            Some(ImageProperties { descriptor, .. }) if descriptor.format.is_high_bit_depth_color() => {
+                match tone_mapping {
+                    ToneMapping::Clamp => BrushFlags::TONE_MAP_CLAMP,
+                    ToneMapping::Reinhard => BrushFlags::TONE_MAP_REINHARD,
+                }
+            }
+            _ => BrushFlags::empty(),
+        }
+    }
+
+    /// Returns the color space of an image and the output color space, packed
+    /// for the image shader which converts between them when they differ.
+    fn get_image_color_spaces(&self, image_key: ImageKey) -> i32 {
+        let color_space = match self.resource_cache.get_image_properties(image_key) {
+            Some(ImageProperties { descriptor, .. }) => descriptor.color_space,
+            None => RgbColorSpace::Srgb,
+        };
+
+        color_space as i32 | ((self.output_color_space as i32) << 8)
+    }
 }
 
 // A tightly packed command stored in a command buffer
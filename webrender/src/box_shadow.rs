@@ -3,7 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use api::{BorderRadius, BoxShadowClipMode, ClipMode, ColorF, PrimitiveKeyKind};
-use api::PropertyBinding;
+use api::{PropertyBinding, RgbColorSpace};
 use api::units::*;
 use crate::clip::{ClipItemKey, ClipItemKeyKind, ClipChainId};
 use crate::scene_building::SceneBuilder;
@@ -87,6 +87,8 @@ impl<'a> SceneBuilder<'a> {
             return;
         }
 
+        let color = self.output_color(color);
+
         // Inset shadows get smaller as spread radius increases.
         let (spread_amount, prim_clip_mode) = match clip_mode {
             BoxShadowClipMode::Outset => (spread_radius, ClipMode::ClipOut),
@@ -168,6 +170,7 @@ impl<'a> SceneBuilder<'a> {
                 clips,
                 PrimitiveKeyKind::Rectangle {
                     color: PropertyBinding::Value(color.into()),
+                    color_space: RgbColorSpace::Srgb,
                 },
             );
         } else {
@@ -194,6 +197,7 @@ impl<'a> SceneBuilder<'a> {
             // clip mask item.
             let prim = PrimitiveKeyKind::Rectangle {
                 color: PropertyBinding::Value(color.into()),
+                color_space: RgbColorSpace::Srgb,
             };
 
             // Create the box-shadow clip item.
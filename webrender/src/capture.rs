@@ -228,6 +228,11 @@ impl CaptureConfig {
         use std::io::BufWriter;
         use std::borrow::Cow;
 
+        if format.is_compressed() {
+            error!("Unable to save PNG of compressed {:?} data", format);
+            return;
+        }
+
         // `png` expects
         let data = match stride {
             Some(stride) if stride != format.bytes_per_pixel() * size.width => {
@@ -94,20 +94,23 @@
 
 use api::{BorderRadius, ClipMode, ComplexClipRegion, ImageMask};
 use api::{BoxShadowClipMode, ClipId, FillRule, ImageKey, ImageRendering, PipelineId};
+use api::PropertyBindingId;
 use api::units::*;
 use crate::image_tiling::{self, Repetition};
 use crate::border::{ensure_no_corner_overlap, BorderRadiusAu};
 use crate::box_shadow::{BLUR_SAMPLE_SCALE, BoxShadowClipSource, BoxShadowCacheKey};
 use crate::spatial_tree::{SpatialTree, SpatialNodeIndex, CoordinateSystemId};
 use crate::ellipse::Ellipse;
-use crate::gpu_cache::GpuCache;
+use crate::gpu_cache::{GpuCache, GpuCacheHandle};
 use crate::gpu_types::{BoxShadowStretchMode};
 use crate::intern::{self, ItemUid};
 use crate::internal_types::{FastHashMap, FastHashSet};
 use crate::prim_store::{VisibleMaskImageTile};
-use crate::prim_store::{PointKey, SizeKey, RectangleKey, PolygonKey};
+use crate::prim_store::{PointKey, SizeKey, RectangleKey, PolygonKey, VectorKey};
 use crate::render_task_cache::to_cache_size;
+use crate::renderer::MAX_VERTEX_TEXTURE_WIDTH;
 use crate::resource_cache::{ImageRequest, ResourceCache};
+use crate::scene::SceneProperties;
 use crate::space::SpaceMapper;
 use crate::util::{clamp_to_scale_factor, MaxRect, extract_inner_rect_safe, project_rect, ScaleOffset, VecHelper};
 use euclid::approxeq::ApproxEq;
@@ -387,12 +390,31 @@ enum ClipResult {
 #[derive(MallocSizeOf)]
 pub struct ClipNode {
     pub item: ClipItem,
+    /// Set if the region of a rounded rectangle clip is animated.
+    pub animation: Option<ClipRegionAnimation>,
+}
+
+/// The property binding that drives an animated rounded rectangle clip.
+#[derive(Debug)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+#[derive(MallocSizeOf)]
+pub struct ClipRegionAnimation {
+    pub id: PropertyBindingId,
+    /// Offset between the coordinates of the animated values and the
+    /// spatial node of the clip.
+    pub offset: LayoutVector2D,
+    /// The region used when the property has no value.
+    pub default_rect: LayoutRect,
+    pub default_radius: BorderRadius,
 }
 
 // Convert from an interning key for a clip item
 // to a clip node, which is cached in the document.
 impl From<ClipItemKey> for ClipNode {
     fn from(item: ClipItemKey) -> Self {
+        let mut animation = None;
+
         let kind = match item.kind {
             ClipItemKeyKind::Rectangle(rect, mode) => {
                 ClipItemKind::Rectangle { rect: rect.into(), mode }
@@ -404,6 +426,21 @@ impl From<ClipItemKey> for ClipNode {
                     mode,
                 }
             }
+            ClipItemKeyKind::AnimatedRoundedRectangle(rect, radius, mode, id, offset) => {
+                let rect: LayoutRect = rect.into();
+                let radius: BorderRadius = radius.into();
+                animation = Some(ClipRegionAnimation {
+                    id,
+                    offset: offset.into(),
+                    default_rect: rect,
+                    default_radius: radius,
+                });
+                ClipItemKind::RoundedRectangle {
+                    rect,
+                    radius,
+                    mode,
+                }
+            }
             ClipItemKeyKind::ImageMask(rect, image, repeat, polygon_handle) => {
                 ClipItemKind::Image {
                     image,
@@ -412,6 +449,13 @@ impl From<ClipItemKey> for ClipNode {
                     polygon_handle,
                 }
             }
+            ClipItemKeyKind::Polygon(rect, polygon_handle) => {
+                ClipItemKind::Polygon {
+                    rect: rect.into(),
+                    polygon_handle,
+                    gpu_cache_handle: GpuCacheHandle::new(),
+                }
+            }
             ClipItemKeyKind::BoxShadow(shadow_rect_fract_offset, shadow_rect_size, shadow_radius, prim_shadow_rect, blur_radius, clip_mode) => {
                 ClipItemKind::new_box_shadow(
                     shadow_rect_fract_offset.into(),
@@ -429,6 +473,7 @@ impl From<ClipItemKey> for ClipNode {
                 kind,
                 spatial_node_index: item.spatial_node_index,
             },
+            animation,
         }
     }
 }
@@ -691,8 +736,29 @@ impl ClipNodeInfo {
 }
 
 impl ClipNode {
+    /// Apply the current value of an animated clip region.
+    pub fn update_animation(&mut self, scene_properties: &SceneProperties) {
+        let animation = match self.animation {
+            Some(ref animation) => animation,
+            None => return,
+        };
+
+        let (new_rect, mut new_radius) = match scene_properties.clip_region_properties().get(&animation.id) {
+            Some(region) => (region.rect.translate(animation.offset), region.radii),
+            None => (animation.default_rect, animation.default_radius),
+        };
+        ensure_no_corner_overlap(&mut new_radius, new_rect.size());
+
+        if let ClipItemKind::RoundedRectangle { ref mut rect, ref mut radius, .. } = self.item.kind {
+            *rect = new_rect;
+            *radius = new_radius;
+        }
+    }
+
     pub fn update(
         &mut self,
+        gpu_cache: &mut GpuCache,
+        polygon_data_store: &PolygonDataStore,
         device_pixel_scale: DevicePixelScale,
     ) {
         match self.item.kind {
@@ -700,6 +766,19 @@ impl ClipNode {
             ClipItemKind::Rectangle { .. } |
             ClipItemKind::RoundedRectangle { .. } => {}
 
+            ClipItemKind::Polygon { rect, polygon_handle, ref mut gpu_cache_handle } => {
+                // The vertices are packed two per GPU block, in the local
+                // space of the clip, for the cs_clip_polygon shader to walk.
+                if let Some(mut request) = gpu_cache.request(gpu_cache_handle) {
+                    let polygon = &polygon_data_store[polygon_handle];
+                    for pair in polygon.points.chunks(2) {
+                        let p0 = LayoutPoint::from(pair[0]) + rect.min.to_vector();
+                        let p1 = pair.get(1).map_or(p0, |p| LayoutPoint::from(*p) + rect.min.to_vector());
+                        request.push([p0.x, p0.y, p1.x, p1.y]);
+                    }
+                }
+            }
+
             ClipItemKind::BoxShadow { ref mut source } => {
                 // Quote from https://drafts.csswg.org/css-backgrounds-3/#shadow-blur
                 // "the image that would be generated by applying to the shadow a
@@ -775,6 +854,9 @@ pub struct ClipStore {
     /// context) is defined.
     #[ignore_malloc_size_of = "range missing"]
     chain_builder_stack: Vec<ClipChainBuilder>,
+
+    /// The clip nodes whose region is animated, updated before each frame.
+    pub animated_clips: Vec<ClipDataHandle>,
 }
 
 // A clip chain instance is what gets built for a given clip
@@ -1016,6 +1098,7 @@ impl ClipStore {
             templates,
             instances: Vec::with_capacity(stats.instances_capacity),
             chain_builder_stack: Vec::new(),
+            animated_clips: Vec::new(),
         }
     }
 
@@ -1248,6 +1331,7 @@ impl ClipStore {
                 ClipItemKind::Rectangle { mode: ClipMode::ClipOut, .. } |
                 ClipItemKind::Image { .. } |
                 ClipItemKind::BoxShadow { .. } |
+                ClipItemKind::Polygon { .. } |
                 ClipItemKind::RoundedRectangle { mode: ClipMode::ClipOut, .. } => {
                     return None;
                 }
@@ -1293,6 +1377,7 @@ impl ClipStore {
         device_pixel_scale: DevicePixelScale,
         world_rect: &WorldRect,
         clip_data_store: &mut ClipDataStore,
+        polygon_data_store: &PolygonDataStore,
         request_resources: bool,
         is_chased: bool,
     ) -> Option<ClipChainInstance> {
@@ -1358,7 +1443,7 @@ impl ClipStore {
                     // Needs a mask -> add to clip node indices
 
                     // TODO(gw): Ensure this only runs once on each node per frame?
-                    node.update(device_pixel_scale);
+                    node.update(gpu_cache, polygon_data_store, device_pixel_scale);
 
                     // Create the clip node instance for this clip node
                     if let Some(instance) = node_info.create_instance(
@@ -1380,6 +1465,7 @@ impl ClipStore {
                             ClipItemKind::Rectangle { mode: ClipMode::ClipOut, .. } |
                             ClipItemKind::RoundedRectangle { .. } |
                             ClipItemKind::Image { .. } |
+                            ClipItemKind::Polygon { .. } |
                             ClipItemKind::BoxShadow { .. } => {
                                 true
                             }
@@ -1430,6 +1516,17 @@ impl ClipStore {
         self.mask_tiles.clear();
     }
 
+    /// Apply the current values of the animated clip regions.
+    pub fn update_animated_clips(
+        &self,
+        clip_data_store: &mut ClipDataStore,
+        scene_properties: &SceneProperties,
+    ) {
+        for handle in &self.animated_clips {
+            clip_data_store[*handle].update_animation(scene_properties);
+        }
+    }
+
     pub fn end_frame(&mut self, scratch: &mut ClipStoreScratchBuffer) {
         mem::swap(&mut self.clip_node_instances, &mut scratch.clip_node_instances);
         mem::swap(&mut self.mask_tiles, &mut scratch.mask_tiles);
@@ -1474,7 +1571,12 @@ impl<I: Iterator<Item = ComplexClipRegion>> Iterator for ComplexTranslateIter<I>
 pub enum ClipItemKeyKind {
     Rectangle(RectangleKey, ClipMode),
     RoundedRectangle(RectangleKey, BorderRadiusAu, ClipMode),
+    /// A rounded rectangle whose region is animated by a property binding. The
+    /// rect and radius are the default region, and the vector is the offset
+    /// applied to the animated values.
+    AnimatedRoundedRectangle(RectangleKey, BorderRadiusAu, ClipMode, PropertyBindingId, VectorKey),
     ImageMask(RectangleKey, ImageKey, bool, Option<PolygonDataHandle>),
+    Polygon(RectangleKey, PolygonDataHandle),
     BoxShadow(PointKey, SizeKey, BorderRadiusAu, RectangleKey, Au, BoxShadowClipMode),
 }
 
@@ -1496,6 +1598,23 @@ impl ClipItemKeyKind {
         }
     }
 
+    pub fn animated_rounded_rect(
+        rect: LayoutRect,
+        mut radii: BorderRadius,
+        mode: ClipMode,
+        id: PropertyBindingId,
+        offset: LayoutVector2D,
+    ) -> Self {
+        ensure_no_corner_overlap(&mut radii, rect.size());
+        ClipItemKeyKind::AnimatedRoundedRectangle(
+            rect.into(),
+            radii.into(),
+            mode,
+            id,
+            offset.into(),
+        )
+    }
+
     pub fn image_mask(image_mask: &ImageMask, mask_rect: LayoutRect,
                       polygon_handle: Option<PolygonDataHandle>) -> Self {
         ClipItemKeyKind::ImageMask(
@@ -1506,6 +1625,10 @@ impl ClipItemKeyKind {
         )
     }
 
+    pub fn polygon(bounds: LayoutRect, polygon_handle: PolygonDataHandle) -> Self {
+        ClipItemKeyKind::Polygon(bounds.into(), polygon_handle)
+    }
+
     pub fn box_shadow(
         shadow_rect: LayoutRect,
         shadow_radius: BorderRadius,
@@ -1536,7 +1659,9 @@ impl ClipItemKeyKind {
 
             ClipItemKeyKind::Rectangle(_, ClipMode::ClipOut) |
             ClipItemKeyKind::RoundedRectangle(..) |
+            ClipItemKeyKind::AnimatedRoundedRectangle(..) |
             ClipItemKeyKind::ImageMask(..) |
+            ClipItemKeyKind::Polygon(..) |
             ClipItemKeyKind::BoxShadow(..) => ClipNodeKind::Complex,
         }
     }
@@ -1588,6 +1713,13 @@ pub enum ClipItemKind {
         repeat: bool,
         polygon_handle: Option<PolygonDataHandle>,
     },
+    Polygon {
+        /// The bounding rect of the polygon.
+        rect: LayoutRect,
+        polygon_handle: PolygonDataHandle,
+        /// The vertices, uploaded for the clip mask shader.
+        gpu_cache_handle: GpuCacheHandle,
+    },
     BoxShadow {
         source: BoxShadowClipSource,
     },
@@ -1764,6 +1896,7 @@ impl ClipItemKind {
         match *self {
             ClipItemKind::Rectangle { .. } |
             ClipItemKind::Image { .. } |
+            ClipItemKind::Polygon { .. } |
             ClipItemKind::BoxShadow { .. } => {
                 false
             }
@@ -1792,6 +1925,7 @@ impl ClipItemKind {
                     Some(rect)
                 }
             }
+            ClipItemKind::Polygon { rect, .. } => Some(rect),
             ClipItemKind::BoxShadow { .. } => None,
         }
     }
@@ -1815,7 +1949,8 @@ impl ClipItemKind {
                 let inner_clip_rect = extract_inner_rect_safe(&rect, radius);
                 (rect, inner_clip_rect, mode)
             }
-            ClipItemKind::Image { rect, repeat: false, .. } => {
+            ClipItemKind::Image { rect, repeat: false, .. } |
+            ClipItemKind::Polygon { rect, .. } => {
                 (rect, None, ClipMode::Clip)
             }
             ClipItemKind::Image { repeat: true, .. } |
@@ -1937,6 +2072,16 @@ impl ClipItemKind {
                     }
                 }
             }
+            ClipItemKind::Polygon { rect, .. } => {
+                match rect.intersection(prim_rect) {
+                    Some(..) => {
+                        ClipResult::Partial
+                    }
+                    None => {
+                        ClipResult::Reject
+                    }
+                }
+            }
             ClipItemKind::BoxShadow { .. } => {
                 ClipResult::Partial
             }
@@ -2071,7 +2216,7 @@ pub fn polygon_contains_point(
     // Calculate a winding number for this point.
     let mut winding_number: i32 = 0;
 
-    let count = polygon.point_count as usize;
+    let count = polygon.points.len();
 
     for i in 0..count {
         let p0 = polygon.points[i];
@@ -2218,8 +2363,12 @@ fn add_clip_node_to_current_chain(
 
 #[cfg(test)]
 mod tests {
-    use super::projected_rect_contains;
-    use euclid::{Transform3D, rect};
+    use super::{projected_rect_contains, ClipItemKey, ClipItemKeyKind, ClipItemKind, ClipNode};
+    use api::{BorderRadius, ClipMode, ComplexClipRegion, DynamicProperties, PropertyBindingKey};
+    use api::units::LayoutRect;
+    use crate::scene::SceneProperties;
+    use crate::spatial_tree::SpatialNodeIndex;
+    use euclid::{Transform3D, point2, rect, size2, vec2};
 
     #[test]
     fn test_empty_projected_rect() {
@@ -2233,6 +2382,57 @@ mod tests {
             "Empty rectangle is considered to include a non-empty!"
         );
     }
+
+    #[test]
+    fn test_animated_rounded_rect() {
+        let key = PropertyBindingKey::<ComplexClipRegion>::new(1);
+        let mut node = ClipNode::from(ClipItemKey {
+            kind: ClipItemKeyKind::animated_rounded_rect(
+                LayoutRect::from_origin_and_size(point2(10.0, 10.0), size2(50.0, 50.0)),
+                BorderRadius::uniform(4.0),
+                ClipMode::Clip,
+                key.id,
+                vec2(5.0, 5.0),
+            ),
+            spatial_node_index: SpatialNodeIndex::new(0),
+        });
+
+        let region = |node: &ClipNode| match node.item.kind {
+            ClipItemKind::RoundedRectangle { rect, radius, .. } => (rect, radius),
+            _ => panic!("unexpected clip kind"),
+        };
+
+        // The region is read from the property and translated by the offset,
+        // with overlapping corners scaled down.
+        let mut properties = SceneProperties::new();
+        properties.add_properties(DynamicProperties {
+            clip_regions: vec![key.with(ComplexClipRegion::new(
+                LayoutRect::from_origin_and_size(point2(0.0, 0.0), size2(20.0, 20.0)),
+                BorderRadius::uniform(20.0),
+                ClipMode::Clip,
+            ))],
+            ..Default::default()
+        });
+        properties.flush_pending_updates();
+        node.update_animation(&properties);
+        assert_eq!(
+            region(&node),
+            (
+                LayoutRect::from_origin_and_size(point2(5.0, 5.0), size2(20.0, 20.0)),
+                BorderRadius::uniform(10.0),
+            ),
+        );
+
+        // Without a value, the default region is used.
+        node.update_animation(&SceneProperties::new());
+        assert_eq!(
+            region(&node),
+            (
+                LayoutRect::from_origin_and_size(point2(10.0, 10.0), size2(50.0, 50.0)),
+                BorderRadius::uniform(4.0),
+            ),
+        );
+    }
 }
 
 /// PolygonKeys get interned, because it's a convenient way to move the data
@@ -2245,8 +2445,13 @@ mod tests {
 #[cfg_attr(any(feature = "serde"), derive(Deserialize, Serialize))]
 pub enum PolygonIntern {}
 
+pub type PolygonDataStore = intern::DataStore<PolygonIntern>;
 pub type PolygonDataHandle = intern::Handle<PolygonIntern>;
 
+/// The maximum number of vertices of a polygon clip. The vertices are stored
+/// two per block in a single GPU cache allocation, which can't exceed a row.
+pub const MAX_POLYGON_CLIP_VERTICES: usize = 2 * MAX_VERTEX_TEXTURE_WIDTH;
+
 impl intern::InternDebug for PolygonKey {}
 
 impl intern::Internable for PolygonIntern {
@@ -0,0 +1,379 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Conversions between RGB color spaces.
+//!
+//! Solid colors are converted to the output color space of the document on
+//! the CPU: colors given by value during scene building, and animated
+//! rectangle colors when they are resolved during frame building. Images and
+//! color bitmap glyphs are converted by their shaders, see color_space.glsl,
+//! which must be kept in sync with this module.
+//!
+//! Gradient stops are converted to the interpolation color space of their
+//! gradient when building the gradient ramp, and the gradient shaders convert
+//! the interpolated colors back to sRGB and then to the output color space.
+
+#![cfg_attr(feature = "cargo-clippy", allow(clippy::excessive_precision))]
+
+use api::{ColorF, GradientColorSpace, RgbColorSpace};
+
+type Matrix = [[f32; 3]; 3];
+
+// All matrices below use the D65 white point, so no chromatic adaptation is
+// needed between them.
+
+const SRGB_TO_XYZ: Matrix = [
+    [0.4123908, 0.3575843, 0.1804808],
+    [0.2126390, 0.7151687, 0.0721923],
+    [0.0193308, 0.1191948, 0.9505322],
+];
+
+const XYZ_TO_SRGB: Matrix = [
+    [3.2409699, -1.5373832, -0.4986108],
+    [-0.9692436, 1.8759675, 0.0415551],
+    [0.0556301, -0.2039770, 1.0569715],
+];
+
+const DISPLAY_P3_TO_XYZ: Matrix = [
+    [0.4865709, 0.2656677, 0.1982173],
+    [0.2289746, 0.6917385, 0.0792869],
+    [0.0000000, 0.0451134, 1.0439444],
+];
+
+const XYZ_TO_DISPLAY_P3: Matrix = [
+    [2.4934969, -0.9313836, -0.4027108],
+    [-0.8294890, 1.7626641, 0.0236247],
+    [0.0358458, -0.0761724, 0.9568845],
+];
+
+const REC2020_TO_XYZ: Matrix = [
+    [0.6369580, 0.1446169, 0.1688810],
+    [0.2627002, 0.6779981, 0.0593017],
+    [0.0000000, 0.0280727, 1.0609851],
+];
+
+const XYZ_TO_REC2020: Matrix = [
+    [1.7166512, -0.3556708, -0.2533663],
+    [-0.6666844, 1.6164812, 0.0157685],
+    [0.0176399, -0.0427706, 0.9421031],
+];
+
+// https://bottosson.github.io/posts/oklab/
+const LINEAR_SRGB_TO_LMS: Matrix = [
+    [0.4122214708, 0.5363325363, 0.0514459929],
+    [0.2119034982, 0.6806995451, 0.1073969566],
+    [0.0883024619, 0.2817188376, 0.6299787005],
+];
+
+const LMS_TO_OKLAB: Matrix = [
+    [0.2104542553, 0.7936177850, -0.0040720468],
+    [1.9779984951, -2.4285922050, 0.4505937099],
+    [0.0259040371, 0.7827717662, -0.8086757660],
+];
+
+fn to_xyz(color_space: RgbColorSpace) -> &'static Matrix {
+    match color_space {
+        RgbColorSpace::Srgb | RgbColorSpace::LinearSrgb => &SRGB_TO_XYZ,
+        RgbColorSpace::DisplayP3 => &DISPLAY_P3_TO_XYZ,
+        RgbColorSpace::Rec2020 => &REC2020_TO_XYZ,
+    }
+}
+
+fn from_xyz(color_space: RgbColorSpace) -> &'static Matrix {
+    match color_space {
+        RgbColorSpace::Srgb | RgbColorSpace::LinearSrgb => &XYZ_TO_SRGB,
+        RgbColorSpace::DisplayP3 => &XYZ_TO_DISPLAY_P3,
+        RgbColorSpace::Rec2020 => &XYZ_TO_REC2020,
+    }
+}
+
+fn transform(m: &Matrix, v: [f32; 3]) -> [f32; 3] {
+    let row = |r: &[f32; 3]| r[0] * v[0] + r[1] * v[1] + r[2] * v[2];
+    [row(&m[0]), row(&m[1]), row(&m[2])]
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn rec2020_to_linear(c: f32) -> f32 {
+    if c <= 0.08145 {
+        c / 4.5
+    } else {
+        ((c + 0.0993) / 1.0993).powf(1.0 / 0.45)
+    }
+}
+
+fn linear_to_rec2020(c: f32) -> f32 {
+    if c <= 0.0181 {
+        c * 4.5
+    } else {
+        1.0993 * c.powf(0.45) - 0.0993
+    }
+}
+
+fn decode(c: f32, color_space: RgbColorSpace) -> f32 {
+    match color_space {
+        RgbColorSpace::Srgb | RgbColorSpace::DisplayP3 => srgb_to_linear(c),
+        RgbColorSpace::Rec2020 => rec2020_to_linear(c),
+        RgbColorSpace::LinearSrgb => c,
+    }
+}
+
+fn encode(c: f32, color_space: RgbColorSpace) -> f32 {
+    match color_space {
+        RgbColorSpace::Srgb | RgbColorSpace::DisplayP3 => linear_to_srgb(c),
+        RgbColorSpace::Rec2020 => linear_to_rec2020(c),
+        RgbColorSpace::LinearSrgb => c,
+    }
+}
+
+/// Converts a non-premultiplied color from one color space to another.
+///
+/// Colors that fall outside of the gamut of the destination color space are
+/// clamped per channel.
+pub fn convert_color(color: ColorF, from: RgbColorSpace, to: RgbColorSpace) -> ColorF {
+    if from == to {
+        return color;
+    }
+
+    let linear = [
+        decode(color.r.clamp(0.0, 1.0), from),
+        decode(color.g.clamp(0.0, 1.0), from),
+        decode(color.b.clamp(0.0, 1.0), from),
+    ];
+    let xyz = transform(to_xyz(from), linear);
+    let rgb = transform(from_xyz(to), xyz);
+
+    ColorF::new(
+        encode(rgb[0].clamp(0.0, 1.0), to),
+        encode(rgb[1].clamp(0.0, 1.0), to),
+        encode(rgb[2].clamp(0.0, 1.0), to),
+        color.a,
+    )
+}
+
+fn srgb_to_oklab(rgb: [f32; 3]) -> [f32; 3] {
+    let linear = [srgb_to_linear(rgb[0]), srgb_to_linear(rgb[1]), srgb_to_linear(rgb[2])];
+    let lms = transform(&LINEAR_SRGB_TO_LMS, linear);
+    transform(&LMS_TO_OKLAB, [lms[0].cbrt(), lms[1].cbrt(), lms[2].cbrt()])
+}
+
+fn srgb_to_hsl(rgb: [f32; 3]) -> [f32; 3] {
+    let [r, g, b] = rgb;
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) * 0.5;
+    let d = max - min;
+    if d <= 0.0 {
+        return [0.0, 0.0, lightness];
+    }
+
+    let saturation = d / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == r {
+        ((g - b) / d).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / d + 2.0
+    } else {
+        (r - g) / d + 4.0
+    };
+
+    [hue * 60.0, saturation, lightness]
+}
+
+/// Converts the RGB channels of a non-premultiplied sRGB color to a gradient
+/// interpolation color space.
+///
+/// Hues are expressed in degrees in the [0, 360) range. OKLCH colors are
+/// stored as (L, C, H) and HSL colors as (H, S, L), see `gradient_hue_index`.
+pub fn to_gradient_color_space(color: ColorF, color_space: GradientColorSpace) -> [f32; 3] {
+    let rgb = [
+        color.r.clamp(0.0, 1.0),
+        color.g.clamp(0.0, 1.0),
+        color.b.clamp(0.0, 1.0),
+    ];
+
+    match color_space {
+        GradientColorSpace::Srgb => rgb,
+        GradientColorSpace::SrgbLinear => {
+            [srgb_to_linear(rgb[0]), srgb_to_linear(rgb[1]), srgb_to_linear(rgb[2])]
+        }
+        GradientColorSpace::Oklab => srgb_to_oklab(rgb),
+        GradientColorSpace::Oklch => {
+            let [l, a, b] = srgb_to_oklab(rgb);
+            let hue = b.atan2(a).to_degrees().rem_euclid(360.0);
+            [l, a.hypot(b), hue]
+        }
+        GradientColorSpace::Hsl => srgb_to_hsl(rgb),
+    }
+}
+
+/// Packs the interpolation and output color spaces of a gradient into the
+/// color space parameter of the gradient shaders, see `sample_gradient`.
+pub fn pack_gradient_color_spaces(interpolation: GradientColorSpace, output: RgbColorSpace) -> i32 {
+    interpolation as i32 | ((output as i32) << 8)
+}
+
+/// Returns the index of the hue channel of a polar gradient color space.
+pub fn gradient_hue_index(color_space: GradientColorSpace) -> Option<usize> {
+    match color_space {
+        GradientColorSpace::Oklch => Some(2),
+        GradientColorSpace::Hsl => Some(0),
+        GradientColorSpace::Srgb |
+        GradientColorSpace::SrgbLinear |
+        GradientColorSpace::Oklab => None,
+    }
+}
+
+/// Whether the hue of a color converted by `to_gradient_color_space` is
+/// powerless, i.e. whether the color is achromatic.
+pub fn has_powerless_hue(color: [f32; 3], color_space: GradientColorSpace) -> bool {
+    match color_space {
+        GradientColorSpace::Oklch => color[1] < 1e-4,
+        GradientColorSpace::Hsl => color[1] <= 0.0,
+        GradientColorSpace::Srgb |
+        GradientColorSpace::SrgbLinear |
+        GradientColorSpace::Oklab => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: ColorF, b: ColorF) {
+        let eps = 0.002;
+        assert!(
+            (a.r - b.r).abs() < eps && (a.g - b.g).abs() < eps &&
+            (a.b - b.b).abs() < eps && (a.a - b.a).abs() < eps,
+            "{:?} != {:?}", a, b,
+        );
+    }
+
+    #[test]
+    fn identity() {
+        let color = ColorF::new(0.2, 0.4, 0.6, 0.5);
+        assert_eq!(convert_color(color, RgbColorSpace::DisplayP3, RgbColorSpace::DisplayP3), color);
+    }
+
+    #[test]
+    fn srgb_round_trips() {
+        let color = ColorF::new(0.2, 0.4, 0.6, 0.5);
+        for &space in &[RgbColorSpace::DisplayP3, RgbColorSpace::Rec2020, RgbColorSpace::LinearSrgb] {
+            let converted = convert_color(color, RgbColorSpace::Srgb, space);
+            assert_close(convert_color(converted, space, RgbColorSpace::Srgb), color);
+        }
+    }
+
+    #[test]
+    fn white_is_preserved() {
+        for &from in &[RgbColorSpace::DisplayP3, RgbColorSpace::Rec2020, RgbColorSpace::LinearSrgb] {
+            assert_close(convert_color(ColorF::WHITE, from, RgbColorSpace::Srgb), ColorF::WHITE);
+        }
+    }
+
+    #[test]
+    fn wide_gamut_to_srgb() {
+        // Display-P3 red is outside of the sRGB gamut and gets clamped.
+        let red = convert_color(ColorF::new(1.0, 0.0, 0.0, 1.0), RgbColorSpace::DisplayP3, RgbColorSpace::Srgb);
+        assert_close(red, ColorF::new(1.0, 0.0, 0.0, 1.0));
+
+        // sRGB red is less saturated once expressed in Display-P3.
+        let red = convert_color(ColorF::new(1.0, 0.0, 0.0, 1.0), RgbColorSpace::Srgb, RgbColorSpace::DisplayP3);
+        assert_close(red, ColorF::new(0.9175, 0.2003, 0.1386, 1.0));
+
+        let mid = convert_color(ColorF::new(0.5, 0.5, 0.5, 1.0), RgbColorSpace::Srgb, RgbColorSpace::LinearSrgb);
+        assert_close(mid, ColorF::new(0.2140, 0.2140, 0.2140, 1.0));
+    }
+
+    // The inverse conversions are only done on the GPU, these mirror
+    // color_space.glsl.
+    fn oklab_to_srgb(lab: [f32; 3]) -> [f32; 3] {
+        let [l, a, b] = lab;
+        let lms = [
+            (l + 0.3963377774 * a + 0.2158037573 * b).powi(3),
+            (l - 0.1055613458 * a - 0.0638541728 * b).powi(3),
+            (l - 0.0894841775 * a - 1.2914855480 * b).powi(3),
+        ];
+        let rgb = transform(&[
+            [4.0767416621, -3.3077115913, 0.2309699292],
+            [-1.2684380046, 2.6097574011, -0.3413193965],
+            [-0.0041960863, -0.7034186147, 1.7076147010],
+        ], lms);
+        [linear_to_srgb(rgb[0]), linear_to_srgb(rgb[1]), linear_to_srgb(rgb[2])]
+    }
+
+    fn hsl_to_srgb(hsl: [f32; 3]) -> [f32; 3] {
+        let [h, s, l] = hsl;
+        let a = s * l.min(1.0 - l);
+        let f = |n: f32| {
+            let k = (n + h / 30.0).rem_euclid(12.0);
+            l - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+        };
+        [f(0.0), f(8.0), f(4.0)]
+    }
+
+    fn assert_close3(a: [f32; 3], b: [f32; 3]) {
+        let eps = 0.002;
+        assert!(
+            a.iter().zip(b.iter()).all(|(a, b)| (a - b).abs() < eps),
+            "{:?} != {:?}", a, b,
+        );
+    }
+
+    #[test]
+    fn gradient_color_spaces() {
+        let white = to_gradient_color_space(ColorF::WHITE, GradientColorSpace::Oklab);
+        assert_close3(white, [1.0, 0.0, 0.0]);
+
+        let red = ColorF::new(1.0, 0.0, 0.0, 1.0);
+        assert_close3(to_gradient_color_space(red, GradientColorSpace::Oklab), [0.6279, 0.2249, 0.1258]);
+        assert_close3(to_gradient_color_space(red, GradientColorSpace::Oklch), [0.6280, 0.2577, 29.234]);
+        assert_close3(to_gradient_color_space(red, GradientColorSpace::Hsl), [0.0, 1.0, 0.5]);
+
+        let blue = ColorF::new(0.0, 0.0, 1.0, 1.0);
+        assert_close3(to_gradient_color_space(blue, GradientColorSpace::Hsl), [240.0, 1.0, 0.5]);
+
+        let mid = to_gradient_color_space(ColorF::new(0.5, 0.5, 0.5, 1.0), GradientColorSpace::SrgbLinear);
+        assert_close3(mid, [0.2140, 0.2140, 0.2140]);
+
+        assert!(has_powerless_hue(
+            to_gradient_color_space(ColorF::new(0.3, 0.3, 0.3, 1.0), GradientColorSpace::Oklch),
+            GradientColorSpace::Oklch,
+        ));
+        assert!(!has_powerless_hue(
+            to_gradient_color_space(red, GradientColorSpace::Hsl),
+            GradientColorSpace::Hsl,
+        ));
+    }
+
+    #[test]
+    fn gradient_color_spaces_round_trip() {
+        for &rgb in &[[0.2, 0.4, 0.6], [1.0, 0.5, 0.0], [0.9, 0.1, 0.7], [0.3, 0.3, 0.3]] {
+            let color = ColorF::new(rgb[0], rgb[1], rgb[2], 1.0);
+
+            let lab = to_gradient_color_space(color, GradientColorSpace::Oklab);
+            assert_close3(oklab_to_srgb(lab), rgb);
+
+            let [l, c, h] = to_gradient_color_space(color, GradientColorSpace::Oklch);
+            let h = h.to_radians();
+            assert_close3(oklab_to_srgb([l, c * h.cos(), c * h.sin()]), rgb);
+
+            let hsl = to_gradient_color_space(color, GradientColorSpace::Hsl);
+            assert_close3(hsl_to_srgb(hsl), rgb);
+        }
+    }
+}
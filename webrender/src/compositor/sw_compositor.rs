@@ -3,6 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use gleam::{gl, gl::Gl};
+use rayon::ThreadPool;
 use std::cell::{Cell, UnsafeCell};
 use std::collections::{hash_map::HashMap, VecDeque};
 use std::ops::{Deref, DerefMut, Range};
@@ -171,7 +172,7 @@ impl SwSurface {
 fn image_rendering_to_gl_filter(filter: ImageRendering) -> gl::GLenum {
     match filter {
         ImageRendering::Pixelated => gl::NEAREST,
-        ImageRendering::Auto | ImageRendering::CrispEdges => gl::LINEAR,
+        ImageRendering::Auto | ImageRendering::CrispEdges | ImageRendering::HighQuality => gl::LINEAR,
     }
 }
 
@@ -701,6 +702,29 @@ impl SwCompositeThread {
     }
 }
 
+/// Rasterizes the bands of SWGL draw calls on the WebRender worker pool.
+pub struct SwRasterizeWorkers(Arc<ThreadPool>);
+
+impl SwRasterizeWorkers {
+    pub fn new(workers: Arc<ThreadPool>) -> Self {
+        SwRasterizeWorkers(workers)
+    }
+}
+
+impl swgl::RasterizeWorkers for SwRasterizeWorkers {
+    fn max_bands(&self) -> usize {
+        self.0.current_num_threads()
+    }
+
+    fn run_bands(&self, num_bands: usize, band: &(dyn Fn(usize) + Sync)) {
+        self.0.scope(|scope| {
+            for index in 0..num_bands {
+                scope.spawn(move |_| band(index));
+            }
+        });
+    }
+}
+
 /// Parameters describing how to composite a surface within a frame
 type FrameSurface = (
     NativeSurfaceId,
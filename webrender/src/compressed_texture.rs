@@ -0,0 +1,1553 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! CPU decoders for block-compressed image formats.
+//!
+//! Images in a compressed format are normally uploaded as-is and decoded by
+//! the GPU when sampled. When the device doesn't support a format (including
+//! the software rasterizer, which supports none of them), the resource cache
+//! decodes the image to RGBA8 up front using the functions in this module.
+
+use api::{ImageDescriptor, ImageFormat};
+
+/// The color produced for blocks that are malformed or use features that
+/// aren't supported, matching the ASTC error color.
+const ERROR_COLOR: [u8; 4] = [255, 0, 255, 255];
+
+/// Width and height in pixels of the blocks of every supported format.
+const BLOCK_SIZE: usize = 4;
+
+type Texels = [[u8; 4]; BLOCK_SIZE * BLOCK_SIZE];
+
+/// Decodes block-compressed image data into tightly packed RGBA8 pixels.
+///
+/// Blocks that are missing from a truncated buffer decode to the error color.
+pub fn decompress_to_rgba8(descriptor: &ImageDescriptor, data: &[u8]) -> Vec<u8> {
+    let format = descriptor.format;
+    assert_eq!(format.block_size() as usize, BLOCK_SIZE);
+
+    let width = descriptor.size.width.max(0) as usize;
+    let height = descriptor.size.height.max(0) as usize;
+    let stride = descriptor.compute_stride() as usize;
+    let block_bytes = format.bytes_per_block() as usize;
+    let blocks_x = (width + BLOCK_SIZE - 1) / BLOCK_SIZE;
+    let blocks_y = (height + BLOCK_SIZE - 1) / BLOCK_SIZE;
+
+    let mut pixels = vec![0; width * height * 4];
+    let mut texels = [[0; 4]; BLOCK_SIZE * BLOCK_SIZE];
+
+    for by in 0 .. blocks_y {
+        let row_start = descriptor.offset as usize + by * stride;
+        for bx in 0 .. blocks_x {
+            let start = row_start + bx * block_bytes;
+            match data.get(start .. start + block_bytes) {
+                Some(block) => decode_block(format, block, &mut texels),
+                None => texels = [ERROR_COLOR; BLOCK_SIZE * BLOCK_SIZE],
+            }
+
+            for y in 0 .. BLOCK_SIZE.min(height - by * BLOCK_SIZE) {
+                for x in 0 .. BLOCK_SIZE.min(width - bx * BLOCK_SIZE) {
+                    let offset = ((by * BLOCK_SIZE + y) * width + bx * BLOCK_SIZE + x) * 4;
+                    pixels[offset .. offset + 4].copy_from_slice(&texels[y * BLOCK_SIZE + x]);
+                }
+            }
+        }
+    }
+
+    pixels
+}
+
+/// Decodes a single block into row-major RGBA8 texels.
+fn decode_block(format: ImageFormat, block: &[u8], texels: &mut Texels) {
+    match format {
+        ImageFormat::BC1 => decode_bc1_color(block, true, texels),
+        ImageFormat::BC3 => {
+            decode_bc1_color(&block[8 ..], false, texels);
+            decode_bc3_alpha(&block[.. 8], texels);
+        }
+        ImageFormat::BC7 => decode_bc7(block, texels),
+        ImageFormat::ETC2RGB8 => decode_etc2_color(read_u64_be(block), texels),
+        ImageFormat::ETC2RGBA8 => {
+            decode_etc2_color(read_u64_be(&block[8 ..]), texels);
+            decode_eac_alpha(read_u64_be(&block[.. 8]), texels);
+        }
+        ImageFormat::ASTC4X4 => decode_astc(block, texels),
+        _ => panic!("{:?} is not a block-compressed format", format),
+    }
+}
+
+fn read_u64_be(bytes: &[u8]) -> u64 {
+    let mut word = 0;
+    for byte in &bytes[.. 8] {
+        word = (word << 8) | *byte as u64;
+    }
+    word
+}
+
+fn read_u128_le(bytes: &[u8]) -> u128 {
+    let mut word = 0;
+    for byte in bytes[.. 16].iter().rev() {
+        word = (word << 8) | *byte as u128;
+    }
+    word
+}
+
+/// Reads bits from a 128-bit block, starting at the least significant bit.
+struct BitReader {
+    bits: u128,
+    position: u32,
+}
+
+impl BitReader {
+    fn new(bits: u128) -> Self {
+        BitReader { bits, position: 0 }
+    }
+
+    fn read(&mut self, count: u32) -> u32 {
+        let value = extract_bits(self.bits, self.position, count);
+        self.position += count;
+        value
+    }
+}
+
+fn extract_bits(bits: u128, start: u32, count: u32) -> u32 {
+    if count == 0 || start >= 128 {
+        return 0;
+    }
+    ((bits >> start) & ((1u128 << count) - 1)) as u32
+}
+
+/// Expands the low `bits` bits of `value` to 8 bits by replicating its high bits.
+fn expand_to_u8(value: u32, bits: u32) -> u8 {
+    let mut result = value << (8 - bits);
+    let mut shift = bits;
+    while shift < 8 {
+        result |= result >> shift;
+        shift *= 2;
+    }
+    result as u8
+}
+
+fn clamp_u8(value: i32) -> u8 {
+    value.clamp(0, 255) as u8
+}
+
+// BC1 - BC3
+
+fn decode_bc1_color(block: &[u8], allow_transparent: bool, texels: &mut Texels) {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+
+    let rgb565 = |c: u16| -> [u32; 3] {
+        [
+            expand_to_u8((c >> 11) as u32 & 0x1f, 5) as u32,
+            expand_to_u8((c >> 5) as u32 & 0x3f, 6) as u32,
+            expand_to_u8(c as u32 & 0x1f, 5) as u32,
+        ]
+    };
+    let e0 = rgb565(c0);
+    let e1 = rgb565(c1);
+
+    let mut palette = [[0u8; 4]; 4];
+    for i in 0 .. 3 {
+        palette[0][i] = e0[i] as u8;
+        palette[1][i] = e1[i] as u8;
+        if c0 > c1 || !allow_transparent {
+            palette[2][i] = ((2 * e0[i] + e1[i]) / 3) as u8;
+            palette[3][i] = ((e0[i] + 2 * e1[i]) / 3) as u8;
+        } else {
+            palette[2][i] = ((e0[i] + e1[i]) / 2) as u8;
+        }
+    }
+    palette[0][3] = 255;
+    palette[1][3] = 255;
+    palette[2][3] = 255;
+    palette[3][3] = if c0 > c1 || !allow_transparent { 255 } else { 0 };
+
+    for (i, texel) in texels.iter_mut().enumerate() {
+        *texel = palette[(indices >> (2 * i)) as usize & 3];
+    }
+}
+
+fn decode_bc3_alpha(block: &[u8], texels: &mut Texels) {
+    let a0 = block[0] as u32;
+    let a1 = block[1] as u32;
+    let mut palette = [a0, a1, 0, 0, 0, 0, 0, 255];
+    if a0 > a1 {
+        for i in 1 .. 7 {
+            palette[i + 1] = ((7 - i as u32) * a0 + i as u32 * a1) / 7;
+        }
+    } else {
+        for i in 1 .. 5 {
+            palette[i + 1] = ((5 - i as u32) * a0 + i as u32 * a1) / 5;
+        }
+    }
+
+    let mut indices = 0u64;
+    for byte in block[2 .. 8].iter().rev() {
+        indices = (indices << 8) | *byte as u64;
+    }
+    for (i, texel) in texels.iter_mut().enumerate() {
+        texel[3] = palette[(indices >> (3 * i)) as usize & 7] as u8;
+    }
+}
+
+// BC7
+
+struct Bc7Mode {
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    endpoint_pbits: bool,
+    shared_pbits: bool,
+    index_bits: u32,
+    index_bits2: u32,
+}
+
+const fn bc7_mode(
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    endpoint_pbits: bool,
+    shared_pbits: bool,
+    index_bits: u32,
+    index_bits2: u32,
+) -> Bc7Mode {
+    Bc7Mode {
+        subsets,
+        partition_bits,
+        rotation_bits,
+        index_selection_bits,
+        color_bits,
+        alpha_bits,
+        endpoint_pbits,
+        shared_pbits,
+        index_bits,
+        index_bits2,
+    }
+}
+
+const BC7_MODES: [Bc7Mode; 8] = [
+    bc7_mode(3, 4, 0, 0, 4, 0, true, false, 3, 0),
+    bc7_mode(2, 6, 0, 0, 6, 0, false, true, 3, 0),
+    bc7_mode(3, 6, 0, 0, 5, 0, false, false, 2, 0),
+    bc7_mode(2, 6, 0, 0, 7, 0, true, false, 2, 0),
+    bc7_mode(1, 0, 2, 1, 5, 6, false, false, 2, 3),
+    bc7_mode(1, 0, 2, 0, 7, 8, false, false, 2, 2),
+    bc7_mode(1, 0, 0, 0, 7, 7, true, false, 4, 0),
+    bc7_mode(2, 6, 0, 0, 5, 5, true, false, 2, 0),
+];
+
+const BC7_WEIGHTS2: [u32; 4] = [0, 21, 43, 64];
+const BC7_WEIGHTS3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const BC7_WEIGHTS4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+/// Subset masks of the two-subset partitions: bit `i` is set when pixel `i`
+/// belongs to the second subset.
+const BC7_PARTITIONS2: [u16; 64] = [
+    0xcccc, 0x8888, 0xeeee, 0xecc8, 0xc880, 0xfeec, 0xfec8, 0xec80,
+    0xc800, 0xffec, 0xfe80, 0xe800, 0xffe8, 0xff00, 0xfff0, 0xf000,
+    0xf710, 0x008e, 0x7100, 0x08ce, 0x008c, 0x7310, 0x3100, 0x8cce,
+    0x088c, 0x3110, 0x6666, 0x366c, 0x17e8, 0x0ff0, 0x718e, 0x399c,
+    0xaaaa, 0xf0f0, 0x5a5a, 0x33cc, 0x3c3c, 0x55aa, 0x9696, 0xa55a,
+    0x73ce, 0x13c8, 0x324c, 0x3bdc, 0x6996, 0xc33c, 0x9966, 0x0660,
+    0x0272, 0x04e4, 0x4e40, 0x2720, 0xc936, 0x936c, 0x39c6, 0x639c,
+    0x9336, 0x9cc6, 0x817e, 0xe718, 0xccf0, 0x0fcc, 0x7744, 0xee22,
+];
+
+const BC7_PARTITIONS3: [[u8; 16]; 64] = [
+    [0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 1, 2, 2, 2, 2],
+    [0, 0, 0, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 2, 0, 0, 1, 2, 2, 1, 1, 2, 2, 1, 1],
+    [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 1, 0, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2],
+    [0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 2, 2],
+    [0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 1, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2],
+    [0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2],
+    [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2],
+    [0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2],
+    [0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2],
+    [0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2, 1, 2, 2, 2],
+    [0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0, 2, 2, 2, 0],
+    [0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2],
+    [0, 1, 1, 1, 0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0],
+    [0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2],
+    [0, 0, 2, 2, 0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1],
+    [0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2, 0, 2, 2, 2],
+    [0, 0, 0, 1, 0, 0, 0, 1, 2, 2, 2, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2],
+    [0, 0, 0, 0, 1, 1, 0, 0, 2, 2, 1, 0, 2, 2, 1, 0],
+    [0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1, 0, 0, 0, 0],
+    [0, 0, 1, 2, 0, 0, 1, 2, 1, 1, 2, 2, 2, 2, 2, 2],
+    [0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1, 0, 1, 1, 0],
+    [0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1],
+    [0, 0, 2, 2, 1, 1, 0, 2, 1, 1, 0, 2, 0, 0, 2, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 2, 0, 0, 2, 2, 2, 2, 2],
+    [0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1],
+    [0, 0, 0, 0, 2, 0, 0, 0, 2, 2, 1, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 2, 2, 2],
+    [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 2, 0, 0, 1, 1],
+    [0, 0, 1, 1, 0, 0, 1, 2, 0, 0, 2, 2, 0, 2, 2, 2],
+    [0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0],
+    [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0],
+    [0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0],
+    [0, 1, 2, 0, 2, 0, 1, 2, 1, 2, 0, 1, 0, 1, 2, 0],
+    [0, 0, 1, 1, 2, 2, 0, 0, 1, 1, 2, 2, 0, 0, 1, 1],
+    [0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0, 1, 1],
+    [0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1],
+    [0, 0, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2, 1, 1, 2, 2],
+    [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 1, 1],
+    [0, 2, 2, 0, 1, 2, 2, 1, 0, 2, 2, 0, 1, 2, 2, 1],
+    [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 0, 1, 0, 1],
+    [0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1],
+    [0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2],
+    [0, 2, 2, 2, 0, 1, 1, 1, 0, 2, 2, 2, 0, 1, 1, 1],
+    [0, 0, 0, 2, 1, 1, 1, 2, 0, 0, 0, 2, 1, 1, 1, 2],
+    [0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2],
+    [0, 2, 2, 2, 0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2],
+    [0, 0, 0, 2, 1, 1, 1, 2, 1, 1, 1, 2, 0, 0, 0, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2],
+    [0, 0, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2],
+    [0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 1],
+    [0, 2, 2, 2, 1, 2, 2, 2, 0, 2, 2, 2, 1, 2, 2, 2],
+    [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 1, 1, 1, 2, 0, 1, 1, 2, 2, 0, 1, 2, 2, 2, 0],
+];
+
+/// Index of the anchor pixel of the second subset of the two-subset partitions.
+const BC7_ANCHORS2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15,
+    15, 2, 8, 2, 2, 8, 8, 15, 2, 8, 2, 2, 8, 8, 2, 2,
+    15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6,
+    6, 2, 6, 8, 15, 15, 2, 2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+/// Index of the anchor pixel of the second subset of the three-subset partitions.
+const BC7_ANCHORS3_SECOND: [u8; 64] = [
+    3, 3, 15, 15, 8, 3, 15, 15, 8, 8, 6, 6, 6, 5, 3, 3,
+    3, 3, 8, 15, 3, 3, 6, 10, 5, 8, 8, 6, 8, 5, 15, 15,
+    8, 15, 3, 5, 6, 10, 8, 15, 15, 3, 15, 5, 15, 15, 15, 15,
+    3, 15, 5, 5, 5, 8, 5, 10, 5, 10, 8, 13, 15, 12, 3, 3,
+];
+
+/// Index of the anchor pixel of the third subset of the three-subset partitions.
+const BC7_ANCHORS3_THIRD: [u8; 64] = [
+    15, 8, 8, 3, 15, 15, 3, 8, 15, 15, 15, 15, 15, 15, 15, 8,
+    15, 8, 15, 3, 15, 8, 15, 8, 3, 15, 6, 10, 15, 15, 10, 8,
+    15, 3, 15, 10, 10, 8, 9, 10, 6, 15, 8, 15, 3, 6, 6, 8,
+    15, 3, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 3, 15, 15, 8,
+];
+
+fn bc7_subset(subsets: usize, partition: usize, pixel: usize) -> usize {
+    match subsets {
+        1 => 0,
+        2 => (BC7_PARTITIONS2[partition] >> pixel) as usize & 1,
+        _ => BC7_PARTITIONS3[partition][pixel] as usize,
+    }
+}
+
+fn bc7_is_anchor(subsets: usize, partition: usize, pixel: usize) -> bool {
+    pixel == 0 || match subsets {
+        1 => false,
+        2 => BC7_ANCHORS2[partition] as usize == pixel,
+        _ => {
+            BC7_ANCHORS3_SECOND[partition] as usize == pixel ||
+            BC7_ANCHORS3_THIRD[partition] as usize == pixel
+        }
+    }
+}
+
+fn bc7_weight(index_bits: u32, index: u32) -> u32 {
+    match index_bits {
+        2 => BC7_WEIGHTS2[index as usize],
+        3 => BC7_WEIGHTS3[index as usize],
+        _ => BC7_WEIGHTS4[index as usize],
+    }
+}
+
+fn bc7_interpolate(e0: u8, e1: u8, weight: u32) -> u8 {
+    (((64 - weight) * e0 as u32 + weight * e1 as u32 + 32) >> 6) as u8
+}
+
+fn decode_bc7(block: &[u8], texels: &mut Texels) {
+    let bits = read_u128_le(block);
+    if bits & 0xff == 0 {
+        // Reserved mode.
+        *texels = [[0; 4]; 16];
+        return;
+    }
+
+    let mode_index = (bits & 0xff).trailing_zeros();
+    let mode = &BC7_MODES[mode_index as usize];
+    let mut reader = BitReader::new(bits);
+    reader.read(mode_index + 1);
+
+    let partition = reader.read(mode.partition_bits) as usize;
+    let rotation = reader.read(mode.rotation_bits);
+    let index_selection = reader.read(mode.index_selection_bits);
+
+    // Two endpoints per subset, each with up to four channels.
+    let mut endpoints = [[0u32; 4]; 6];
+    let endpoint_count = mode.subsets * 2;
+    for channel in 0 .. 3 {
+        for endpoint in &mut endpoints[.. endpoint_count] {
+            endpoint[channel] = reader.read(mode.color_bits);
+        }
+    }
+    for endpoint in &mut endpoints[.. endpoint_count] {
+        endpoint[3] = reader.read(mode.alpha_bits);
+    }
+
+    let mut color_bits = mode.color_bits;
+    let mut alpha_bits = mode.alpha_bits;
+    if mode.endpoint_pbits || mode.shared_pbits {
+        let mut pbits = [0; 6];
+        if mode.endpoint_pbits {
+            for pbit in &mut pbits[.. endpoint_count] {
+                *pbit = reader.read(1);
+            }
+        } else {
+            for subset in 0 .. mode.subsets {
+                let pbit = reader.read(1);
+                pbits[subset * 2] = pbit;
+                pbits[subset * 2 + 1] = pbit;
+            }
+        }
+        for (endpoint, pbit) in endpoints[.. endpoint_count].iter_mut().zip(pbits.iter()) {
+            for channel in endpoint.iter_mut() {
+                *channel = (*channel << 1) | pbit;
+            }
+        }
+        color_bits += 1;
+        if alpha_bits > 0 {
+            alpha_bits += 1;
+        }
+    }
+
+    let mut colors = [[0u8; 4]; 6];
+    for (color, endpoint) in colors.iter_mut().zip(endpoints.iter()).take(endpoint_count) {
+        for channel in 0 .. 3 {
+            color[channel] = expand_to_u8(endpoint[channel], color_bits);
+        }
+        color[3] = if alpha_bits > 0 { expand_to_u8(endpoint[3], alpha_bits) } else { 255 };
+    }
+
+    let mut indices = [0u32; 16];
+    for (pixel, index) in indices.iter_mut().enumerate() {
+        let anchor = bc7_is_anchor(mode.subsets, partition, pixel);
+        *index = reader.read(mode.index_bits - anchor as u32);
+    }
+    let mut indices2 = [0u32; 16];
+    if mode.index_bits2 > 0 {
+        for (pixel, index) in indices2.iter_mut().enumerate() {
+            *index = reader.read(mode.index_bits2 - (pixel == 0) as u32);
+        }
+    }
+
+    for (pixel, texel) in texels.iter_mut().enumerate() {
+        let subset = bc7_subset(mode.subsets, partition, pixel);
+        let e0 = colors[subset * 2];
+        let e1 = colors[subset * 2 + 1];
+
+        let (color_weight, alpha_weight) = if mode.index_bits2 == 0 {
+            let weight = bc7_weight(mode.index_bits, indices[pixel]);
+            (weight, weight)
+        } else if index_selection == 0 {
+            (bc7_weight(mode.index_bits, indices[pixel]), bc7_weight(mode.index_bits2, indices2[pixel]))
+        } else {
+            (bc7_weight(mode.index_bits2, indices2[pixel]), bc7_weight(mode.index_bits, indices[pixel]))
+        };
+
+        for channel in 0 .. 3 {
+            texel[channel] = bc7_interpolate(e0[channel], e1[channel], color_weight);
+        }
+        texel[3] = bc7_interpolate(e0[3], e1[3], alpha_weight);
+
+        if rotation > 0 {
+            texel.swap(3, rotation as usize - 1);
+        }
+    }
+}
+
+// ETC2 and EAC
+
+const ETC_MODIFIERS: [[i32; 2]; 8] = [
+    [2, 8], [5, 17], [9, 29], [13, 42], [18, 60], [24, 80], [33, 106], [47, 183],
+];
+
+const ETC_DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14],
+    [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12],
+    [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11],
+    [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10],
+    [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9],
+    [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9],
+    [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9],
+    [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8],
+    [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+fn etc_bits(word: u64, high: u32, low: u32) -> i32 {
+    ((word >> low) & ((1 << (high - low + 1)) - 1)) as i32
+}
+
+/// Returns the 2-bit index of the pixel at (x, y), whose bits are stored
+/// column-major in the low 32 bits of the block.
+fn etc_pixel_index(word: u64, x: usize, y: usize) -> usize {
+    let i = x * 4 + y;
+    let msb = (word >> (16 + i)) & 1;
+    let lsb = (word >> i) & 1;
+    ((msb << 1) | lsb) as usize
+}
+
+fn etc_add(color: [i32; 3], delta: i32) -> [u8; 4] {
+    [clamp_u8(color[0] + delta), clamp_u8(color[1] + delta), clamp_u8(color[2] + delta), 255]
+}
+
+fn decode_etc2_color(word: u64, texels: &mut Texels) {
+    if word & (1 << 33) == 0 {
+        let base0 = [etc_bits(word, 63, 60), etc_bits(word, 55, 52), etc_bits(word, 47, 44)];
+        let base1 = [etc_bits(word, 59, 56), etc_bits(word, 51, 48), etc_bits(word, 43, 40)];
+        let expand = |c: [i32; 3]| [c[0] * 17, c[1] * 17, c[2] * 17];
+        decode_etc_subblocks(word, expand(base0), expand(base1), texels);
+        return;
+    }
+
+    let r = etc_bits(word, 63, 59);
+    let g = etc_bits(word, 55, 51);
+    let b = etc_bits(word, 47, 43);
+    let sign_extend = |d: i32| if d >= 4 { d - 8 } else { d };
+    let dr = sign_extend(etc_bits(word, 58, 56));
+    let dg = sign_extend(etc_bits(word, 50, 48));
+    let db = sign_extend(etc_bits(word, 42, 40));
+
+    if !(0 ..= 31).contains(&(r + dr)) {
+        decode_etc2_t_mode(word, texels);
+    } else if !(0 ..= 31).contains(&(g + dg)) {
+        decode_etc2_h_mode(word, texels);
+    } else if !(0 ..= 31).contains(&(b + db)) {
+        decode_etc2_planar_mode(word, texels);
+    } else {
+        let expand = |c: [i32; 3]| [
+            expand_to_u8(c[0] as u32, 5) as i32,
+            expand_to_u8(c[1] as u32, 5) as i32,
+            expand_to_u8(c[2] as u32, 5) as i32,
+        ];
+        decode_etc_subblocks(word, expand([r, g, b]), expand([r + dr, g + dg, b + db]), texels);
+    }
+}
+
+/// Decodes the individual and differential modes, which split the block into
+/// two 2x4 or 4x2 halves with their own base color and modifier table.
+fn decode_etc_subblocks(word: u64, base0: [i32; 3], base1: [i32; 3], texels: &mut Texels) {
+    let flip = word & (1 << 32) != 0;
+    let tables = [etc_bits(word, 39, 37) as usize, etc_bits(word, 36, 34) as usize];
+
+    for y in 0 .. 4 {
+        for x in 0 .. 4 {
+            let second = if flip { y >= 2 } else { x >= 2 };
+            let (base, table) = if second { (base1, tables[1]) } else { (base0, tables[0]) };
+            let modifier = ETC_MODIFIERS[table];
+            let delta = match etc_pixel_index(word, x, y) {
+                0 => modifier[0],
+                1 => modifier[1],
+                2 => -modifier[0],
+                _ => -modifier[1],
+            };
+            texels[y * 4 + x] = etc_add(base, delta);
+        }
+    }
+}
+
+fn etc_paint(word: u64, paint: [[u8; 4]; 4], texels: &mut Texels) {
+    for y in 0 .. 4 {
+        for x in 0 .. 4 {
+            texels[y * 4 + x] = paint[etc_pixel_index(word, x, y)];
+        }
+    }
+}
+
+fn decode_etc2_t_mode(word: u64, texels: &mut Texels) {
+    let c1 = [
+        (etc_bits(word, 60, 59) << 2 | etc_bits(word, 57, 56)) * 17,
+        etc_bits(word, 55, 52) * 17,
+        etc_bits(word, 51, 48) * 17,
+    ];
+    let c2 = [etc_bits(word, 47, 44) * 17, etc_bits(word, 43, 40) * 17, etc_bits(word, 39, 36) * 17];
+    let distance = ETC_DISTANCES[(etc_bits(word, 35, 34) << 1 | etc_bits(word, 32, 32)) as usize];
+
+    let paint = [etc_add(c1, 0), etc_add(c2, distance), etc_add(c2, 0), etc_add(c2, -distance)];
+    etc_paint(word, paint, texels);
+}
+
+fn decode_etc2_h_mode(word: u64, texels: &mut Texels) {
+    let r1 = etc_bits(word, 62, 59);
+    let g1 = etc_bits(word, 58, 56) << 1 | etc_bits(word, 52, 52);
+    let b1 = etc_bits(word, 51, 51) << 3 | etc_bits(word, 49, 47);
+    let r2 = etc_bits(word, 46, 43);
+    let g2 = etc_bits(word, 42, 39);
+    let b2 = etc_bits(word, 38, 35);
+
+    let packed1 = r1 << 8 | g1 << 4 | b1;
+    let packed2 = r2 << 8 | g2 << 4 | b2;
+    let index = etc_bits(word, 34, 34) << 2 | etc_bits(word, 32, 32) << 1 | (packed1 >= packed2) as i32;
+    let distance = ETC_DISTANCES[index as usize];
+
+    let c1 = [r1 * 17, g1 * 17, b1 * 17];
+    let c2 = [r2 * 17, g2 * 17, b2 * 17];
+    let paint = [etc_add(c1, distance), etc_add(c1, -distance), etc_add(c2, distance), etc_add(c2, -distance)];
+    etc_paint(word, paint, texels);
+}
+
+fn decode_etc2_planar_mode(word: u64, texels: &mut Texels) {
+    let expand6 = |c: i32| expand_to_u8(c as u32, 6) as i32;
+    let expand7 = |c: i32| expand_to_u8(c as u32, 7) as i32;
+
+    let origin = [
+        expand6(etc_bits(word, 62, 57)),
+        expand7(etc_bits(word, 56, 56) << 6 | etc_bits(word, 54, 49)),
+        expand6(etc_bits(word, 48, 48) << 5 | etc_bits(word, 44, 43) << 3 | etc_bits(word, 41, 39)),
+    ];
+    let horizontal = [
+        expand6(etc_bits(word, 38, 34) << 1 | etc_bits(word, 32, 32)),
+        expand7(etc_bits(word, 31, 25)),
+        expand6(etc_bits(word, 24, 19)),
+    ];
+    let vertical = [
+        expand6(etc_bits(word, 18, 13)),
+        expand7(etc_bits(word, 12, 6)),
+        expand6(etc_bits(word, 5, 0)),
+    ];
+
+    for y in 0 .. 4 {
+        for x in 0 .. 4 {
+            let texel = &mut texels[y * 4 + x];
+            for c in 0 .. 3 {
+                let value = x as i32 * (horizontal[c] - origin[c]) +
+                    y as i32 * (vertical[c] - origin[c]) +
+                    4 * origin[c] + 2;
+                texel[c] = clamp_u8(value >> 2);
+            }
+            texel[3] = 255;
+        }
+    }
+}
+
+fn decode_eac_alpha(word: u64, texels: &mut Texels) {
+    let base = etc_bits(word, 63, 56);
+    let multiplier = etc_bits(word, 55, 52);
+    let modifiers = EAC_MODIFIERS[etc_bits(word, 51, 48) as usize];
+
+    for y in 0 .. 4 {
+        for x in 0 .. 4 {
+            let i = x * 4 + y;
+            let index = ((word >> (45 - 3 * i)) & 7) as usize;
+            texels[y * 4 + x][3] = clamp_u8(base + modifiers[index] * multiplier);
+        }
+    }
+}
+
+// ASTC
+
+#[derive(Clone, Copy, PartialEq)]
+enum IseEncoding {
+    Bits,
+    Trit,
+    Quint,
+}
+
+/// A quantization range for integer sequence encoded values: the number of
+/// low bits stored per value, and whether each value has a trit or quint on
+/// top of them.
+#[derive(Clone, Copy)]
+struct IseRange {
+    encoding: IseEncoding,
+    bits: u32,
+}
+
+impl IseRange {
+    const fn new(encoding: IseEncoding, bits: u32) -> Self {
+        IseRange { encoding, bits }
+    }
+
+    fn levels(&self) -> u32 {
+        let base = match self.encoding {
+            IseEncoding::Bits => 1,
+            IseEncoding::Trit => 3,
+            IseEncoding::Quint => 5,
+        };
+        base << self.bits
+    }
+
+    /// Number of bits needed to store `count` values in this range.
+    fn bit_count(&self, count: u32) -> u32 {
+        count * self.bits + match self.encoding {
+            IseEncoding::Bits => 0,
+            IseEncoding::Trit => (8 * count + 4) / 5,
+            IseEncoding::Quint => (7 * count + 2) / 3,
+        }
+    }
+}
+
+/// All ranges endpoint values may be encoded with, from smallest to largest.
+const ASTC_COLOR_RANGES: [IseRange; 21] = [
+    IseRange::new(IseEncoding::Bits, 1),
+    IseRange::new(IseEncoding::Trit, 0),
+    IseRange::new(IseEncoding::Bits, 2),
+    IseRange::new(IseEncoding::Quint, 0),
+    IseRange::new(IseEncoding::Trit, 1),
+    IseRange::new(IseEncoding::Bits, 3),
+    IseRange::new(IseEncoding::Quint, 1),
+    IseRange::new(IseEncoding::Trit, 2),
+    IseRange::new(IseEncoding::Bits, 4),
+    IseRange::new(IseEncoding::Quint, 2),
+    IseRange::new(IseEncoding::Trit, 3),
+    IseRange::new(IseEncoding::Bits, 5),
+    IseRange::new(IseEncoding::Quint, 3),
+    IseRange::new(IseEncoding::Trit, 4),
+    IseRange::new(IseEncoding::Bits, 6),
+    IseRange::new(IseEncoding::Quint, 4),
+    IseRange::new(IseEncoding::Trit, 5),
+    IseRange::new(IseEncoding::Bits, 7),
+    IseRange::new(IseEncoding::Quint, 5),
+    IseRange::new(IseEncoding::Trit, 6),
+    IseRange::new(IseEncoding::Bits, 8),
+];
+
+/// Weight ranges, indexed by the range bits of the block mode, for low and
+/// high precision.
+const ASTC_WEIGHT_RANGES: [[Option<IseRange>; 8]; 2] = [
+    [
+        None,
+        None,
+        Some(IseRange::new(IseEncoding::Bits, 1)),
+        Some(IseRange::new(IseEncoding::Trit, 0)),
+        Some(IseRange::new(IseEncoding::Bits, 2)),
+        Some(IseRange::new(IseEncoding::Quint, 0)),
+        Some(IseRange::new(IseEncoding::Trit, 1)),
+        Some(IseRange::new(IseEncoding::Bits, 3)),
+    ],
+    [
+        None,
+        None,
+        Some(IseRange::new(IseEncoding::Quint, 1)),
+        Some(IseRange::new(IseEncoding::Trit, 2)),
+        Some(IseRange::new(IseEncoding::Bits, 4)),
+        Some(IseRange::new(IseEncoding::Quint, 2)),
+        Some(IseRange::new(IseEncoding::Trit, 3)),
+        Some(IseRange::new(IseEncoding::Bits, 5)),
+    ],
+];
+
+fn decode_trits(t: u32) -> [u32; 5] {
+    let bit = |n: u32| (t >> n) & 1;
+    let (c, t4, t3);
+    if (t >> 2) & 7 == 7 {
+        c = ((t >> 5) & 7) << 2 | (t & 3);
+        t4 = 2;
+        t3 = 2;
+    } else {
+        c = t & 0x1f;
+        if (t >> 5) & 3 == 3 {
+            t4 = 2;
+            t3 = bit(7);
+        } else {
+            t4 = bit(7);
+            t3 = (t >> 5) & 3;
+        }
+    }
+
+    let cbit = |n: u32| (c >> n) & 1;
+    let (t2, t1, t0);
+    if c & 3 == 3 {
+        t2 = 2;
+        t1 = cbit(4);
+        t0 = cbit(3) << 1 | (cbit(2) & !cbit(3) & 1);
+    } else if (c >> 2) & 3 == 3 {
+        t2 = 2;
+        t1 = 2;
+        t0 = c & 3;
+    } else {
+        t2 = cbit(4);
+        t1 = (c >> 2) & 3;
+        t0 = cbit(1) << 1 | (cbit(0) & !cbit(1) & 1);
+    }
+
+    [t0, t1, t2, t3, t4]
+}
+
+fn decode_quints(q: u32) -> [u32; 3] {
+    let bit = |n: u32| (q >> n) & 1;
+    if (q >> 1) & 3 == 3 && (q >> 5) & 3 == 0 {
+        let q2 = bit(0) << 2 | (bit(4) & !bit(0) & 1) << 1 | (bit(3) & !bit(0) & 1);
+        return [4, 4, q2];
+    }
+
+    let (q2, c);
+    if (q >> 1) & 3 == 3 {
+        q2 = 4;
+        c = ((q >> 3) & 3) << 3 | (!(q >> 5) & 3) << 1 | bit(0);
+    } else {
+        q2 = (q >> 5) & 3;
+        c = q & 0x1f;
+    }
+
+    if c & 7 == 5 {
+        [(c >> 3) & 3, 4, q2]
+    } else {
+        [c & 7, (c >> 3) & 3, q2]
+    }
+}
+
+/// Decodes `count` integer sequence encoded values starting at `start`,
+/// returning each value's trit or quint and its low bits.
+fn decode_ise(bits: u128, start: u32, range: IseRange, count: usize) -> Vec<(u32, u32)> {
+    let mut reader = BitReader::new(bits);
+    reader.position = start;
+    let mut values = Vec::with_capacity(count);
+
+    match range.encoding {
+        IseEncoding::Bits => {
+            for _ in 0 .. count {
+                values.push((0, reader.read(range.bits)));
+            }
+        }
+        IseEncoding::Trit => {
+            // Each group of five values stores an 8-bit packed trit block,
+            // interleaved with the values' low bits.
+            const TRIT_BITS: [u32; 5] = [2, 2, 1, 2, 1];
+            while values.len() < count {
+                let mut low = [0; 5];
+                let mut packed = 0;
+                let mut shift = 0;
+                for i in 0 .. 5 {
+                    low[i] = reader.read(range.bits);
+                    packed |= reader.read(TRIT_BITS[i]) << shift;
+                    shift += TRIT_BITS[i];
+                }
+                let trits = decode_trits(packed);
+                for i in 0 .. 5 {
+                    values.push((trits[i], low[i]));
+                }
+            }
+        }
+        IseEncoding::Quint => {
+            const QUINT_BITS: [u32; 3] = [3, 2, 2];
+            while values.len() < count {
+                let mut low = [0; 3];
+                let mut packed = 0;
+                let mut shift = 0;
+                for i in 0 .. 3 {
+                    low[i] = reader.read(range.bits);
+                    packed |= reader.read(QUINT_BITS[i]) << shift;
+                    shift += QUINT_BITS[i];
+                }
+                let quints = decode_quints(packed);
+                for i in 0 .. 3 {
+                    values.push((quints[i], low[i]));
+                }
+            }
+        }
+    }
+
+    values.truncate(count);
+    values
+}
+
+/// Unquantizes an endpoint value to 0..=255.
+fn unquantize_color(range: IseRange, (d, m): (u32, u32)) -> u8 {
+    if range.encoding == IseEncoding::Bits {
+        return expand_to_u8(m, range.bits);
+    }
+
+    let bit = |n: u32| (m >> n) & 1;
+    let a = if m & 1 != 0 { 0x1ff } else { 0 };
+    let (b, c) = match (range.encoding, range.bits) {
+        (IseEncoding::Trit, 1) => (0, 204),
+        (IseEncoding::Trit, 2) => {
+            let b = bit(1);
+            (b << 8 | b << 4 | b << 2 | b << 1, 93)
+        }
+        (IseEncoding::Trit, 3) => {
+            let (b, c) = (bit(1), bit(2));
+            (c << 8 | b << 7 | c << 3 | b << 2 | c << 1 | b, 44)
+        }
+        (IseEncoding::Trit, 4) => {
+            let cb = (m >> 1) & 7;
+            (cb << 6 | cb, 22)
+        }
+        (IseEncoding::Trit, 5) => {
+            let cb = (m >> 1) & 0xf;
+            (cb << 5 | cb >> 2, 11)
+        }
+        (IseEncoding::Trit, _) => {
+            let cb = (m >> 1) & 0x1f;
+            (cb << 4 | cb >> 4, 5)
+        }
+        (IseEncoding::Quint, 1) => (0, 113),
+        (IseEncoding::Quint, 2) => {
+            let b = bit(1);
+            (b << 8 | b << 3 | b << 2, 54)
+        }
+        (IseEncoding::Quint, 3) => {
+            let cb = (m >> 1) & 3;
+            (cb << 7 | cb << 1 | cb >> 1, 26)
+        }
+        (IseEncoding::Quint, 4) => {
+            let cb = (m >> 1) & 7;
+            (cb << 6 | cb >> 1, 13)
+        }
+        (IseEncoding::Quint, _) => {
+            let cb = (m >> 1) & 0xf;
+            (cb << 5 | cb >> 3, 6)
+        }
+        (IseEncoding::Bits, _) => unreachable!(),
+    };
+
+    let t = (d * c + b) ^ a;
+    ((a & 0x80) | (t >> 2)) as u8
+}
+
+/// Unquantizes a weight to 0..=64.
+fn unquantize_weight(range: IseRange, (d, m): (u32, u32)) -> u32 {
+    let value = match (range.encoding, range.bits) {
+        (IseEncoding::Bits, bits) => expand_to_u8(m, bits) as u32 >> 2,
+        (IseEncoding::Trit, 0) => [0, 32, 63][d as usize],
+        (IseEncoding::Quint, 0) => [0, 16, 32, 47, 63][d as usize],
+        (encoding, bits) => {
+            let bit = |n: u32| (m >> n) & 1;
+            let a = if m & 1 != 0 { 0x7f } else { 0 };
+            let (b, c) = match (encoding, bits) {
+                (IseEncoding::Trit, 1) => (0, 50),
+                (IseEncoding::Quint, 1) => (0, 28),
+                (IseEncoding::Trit, 2) => {
+                    let b = bit(1);
+                    (b << 6 | b << 2 | b, 23)
+                }
+                (IseEncoding::Quint, 2) => {
+                    let b = bit(1);
+                    (b << 6 | b << 1, 13)
+                }
+                _ => {
+                    let cb = (m >> 1) & 3;
+                    (cb << 5 | cb, 11)
+                }
+            };
+            let t = (d * c + b) ^ a;
+            (a & 0x20) | (t >> 2)
+        }
+    };
+
+    if value > 32 { value + 1 } else { value }
+}
+
+fn astc_hash52(mut p: u32) -> u32 {
+    p ^= p >> 15;
+    p = p.wrapping_sub(p << 17);
+    p = p.wrapping_add(p << 7);
+    p = p.wrapping_add(p << 4);
+    p ^= p >> 5;
+    p = p.wrapping_add(p << 16);
+    p ^= p >> 7;
+    p ^= p >> 3;
+    p ^= p << 6;
+    p ^= p >> 17;
+    p
+}
+
+/// Returns the partition of the texel at (x, y) of a 4x4 block.
+fn astc_select_partition(seed: u32, x: u32, y: u32, partition_count: u32) -> usize {
+    // Small blocks sample the partition pattern at double scale.
+    let (x, y) = (x << 1, y << 1);
+    let seed = seed + (partition_count - 1) * 1024;
+    let rnum = astc_hash52(seed);
+
+    let mut seeds = [0u32; 8];
+    for (i, s) in seeds.iter_mut().enumerate() {
+        let value = (rnum >> (4 * i)) & 0xf;
+        *s = value * value;
+    }
+
+    let (sh1, sh2) = if seed & 1 != 0 {
+        (if seed & 2 != 0 { 4 } else { 5 }, if partition_count == 3 { 6 } else { 5 })
+    } else {
+        (if partition_count == 3 { 6 } else { 5 }, if seed & 2 != 0 { 4 } else { 5 })
+    };
+
+    let a = (seeds[0] >> sh1) * x + (seeds[1] >> sh2) * y + (rnum >> 14);
+    let b = (seeds[2] >> sh1) * x + (seeds[3] >> sh2) * y + (rnum >> 10);
+    let c = (seeds[4] >> sh1) * x + (seeds[5] >> sh2) * y + (rnum >> 6);
+    let d = (seeds[6] >> sh1) * x + (seeds[7] >> sh2) * y + (rnum >> 2);
+
+    let a = a & 0x3f;
+    let b = b & 0x3f;
+    let c = if partition_count >= 3 { c & 0x3f } else { 0 };
+    let d = if partition_count >= 4 { d & 0x3f } else { 0 };
+
+    if a >= b && a >= c && a >= d {
+        0
+    } else if b >= c && b >= d {
+        1
+    } else if c >= d {
+        2
+    } else {
+        3
+    }
+}
+
+fn bit_transfer_signed(a: &mut i32, b: &mut i32) {
+    *b = (*b >> 1) | (*a & 0x80);
+    *a = (*a >> 1) & 0x3f;
+    if *a & 0x20 != 0 {
+        *a -= 0x40;
+    }
+}
+
+fn blue_contract(r: i32, g: i32, b: i32, a: i32) -> [i32; 4] {
+    [(r + b) >> 1, (g + b) >> 1, b, a]
+}
+
+/// Decodes a pair of LDR endpoints, or returns `None` for HDR modes.
+fn decode_astc_endpoints(mode: u32, v: &[i32]) -> Option<[[u8; 4]; 2]> {
+    let (e0, e1) = match mode {
+        0 => ([v[0], v[0], v[0], 255], [v[1], v[1], v[1], 255]),
+        1 => {
+            let l0 = (v[0] >> 2) | (v[1] & 0xc0);
+            let l1 = (l0 + (v[1] & 0x3f)).min(255);
+            ([l0, l0, l0, 255], [l1, l1, l1, 255])
+        }
+        4 => ([v[0], v[0], v[0], v[2]], [v[1], v[1], v[1], v[3]]),
+        5 => {
+            let (mut v0, mut v1, mut v2, mut v3) = (v[0], v[1], v[2], v[3]);
+            bit_transfer_signed(&mut v1, &mut v0);
+            bit_transfer_signed(&mut v3, &mut v2);
+            ([v0, v0, v0, v2], [v0 + v1, v0 + v1, v0 + v1, v2 + v3])
+        }
+        6 => (
+            [(v[0] * v[3]) >> 8, (v[1] * v[3]) >> 8, (v[2] * v[3]) >> 8, 255],
+            [v[0], v[1], v[2], 255],
+        ),
+        8 | 12 => {
+            let (a0, a1) = if mode == 12 { (v[6], v[7]) } else { (255, 255) };
+            if v[1] + v[3] + v[5] >= v[0] + v[2] + v[4] {
+                ([v[0], v[2], v[4], a0], [v[1], v[3], v[5], a1])
+            } else {
+                (blue_contract(v[1], v[3], v[5], a1), blue_contract(v[0], v[2], v[4], a0))
+            }
+        }
+        9 | 13 => {
+            let (mut v0, mut v1, mut v2, mut v3, mut v4, mut v5) = (v[0], v[1], v[2], v[3], v[4], v[5]);
+            let (mut v6, mut v7) = if mode == 13 { (v[6], v[7]) } else { (255, 0) };
+            bit_transfer_signed(&mut v1, &mut v0);
+            bit_transfer_signed(&mut v3, &mut v2);
+            bit_transfer_signed(&mut v5, &mut v4);
+            if mode == 13 {
+                bit_transfer_signed(&mut v7, &mut v6);
+            }
+            if v1 + v3 + v5 >= 0 {
+                ([v0, v2, v4, v6], [v0 + v1, v2 + v3, v4 + v5, v6 + v7])
+            } else {
+                (blue_contract(v0 + v1, v2 + v3, v4 + v5, v6 + v7), blue_contract(v0, v2, v4, v6))
+            }
+        }
+        10 => (
+            [(v[0] * v[3]) >> 8, (v[1] * v[3]) >> 8, (v[2] * v[3]) >> 8, v[4]],
+            [v[0], v[1], v[2], v[5]],
+        ),
+        _ => return None,
+    };
+
+    let clamp = |e: [i32; 4]| [clamp_u8(e[0]), clamp_u8(e[1]), clamp_u8(e[2]), clamp_u8(e[3])];
+    Some([clamp(e0), clamp(e1)])
+}
+
+fn decode_astc(block: &[u8], texels: &mut Texels) {
+    *texels = decode_astc_block(read_u128_le(block)).unwrap_or([ERROR_COLOR; 16]);
+}
+
+/// Decodes an ASTC 4x4 block, returning `None` for blocks that are invalid
+/// or use HDR endpoints.
+fn decode_astc_block(bits: u128) -> Option<Texels> {
+    let block_mode = extract_bits(bits, 0, 11);
+
+    if block_mode & 0x1ff == 0x1fc {
+        // Void-extent block: a single constant color.
+        if block_mode & 0x200 != 0 {
+            return None;
+        }
+        let mut color = [0; 4];
+        for (c, channel) in color.iter_mut().enumerate() {
+            *channel = (extract_bits(bits, 64 + 16 * c as u32, 16) >> 8) as u8;
+        }
+        return Some([color; 16]);
+    }
+
+    let bit = |n: u32| (block_mode >> n) & 1;
+    let (grid_width, grid_height, range_bits);
+    let high_precision;
+    let dual_plane;
+    if block_mode & 3 != 0 {
+        range_bits = bit(4) | (block_mode & 3) << 1;
+        high_precision = bit(9);
+        dual_plane = bit(10) != 0;
+        let a = (block_mode >> 5) & 3;
+        let b = (block_mode >> 7) & 3;
+        let (w, h) = match (block_mode >> 2) & 3 {
+            0 => (b + 4, a + 2),
+            1 => (b + 8, a + 2),
+            2 => (a + 2, b + 8),
+            _ if bit(8) == 0 => (a + 2, (b & 1) + 6),
+            _ => ((b & 1) + 2, a + 2),
+        };
+        grid_width = w;
+        grid_height = h;
+    } else {
+        range_bits = bit(4) | ((block_mode >> 2) & 3) << 1;
+        let a = (block_mode >> 5) & 3;
+        let (w, h, high, dual) = match (block_mode >> 7) & 3 {
+            0 => (12, a + 2, bit(9), bit(10)),
+            1 => (a + 2, 12, bit(9), bit(10)),
+            2 => (a + 6, ((block_mode >> 9) & 3) + 6, 0, 0),
+            _ => match a {
+                0 => (6, 10, bit(9), bit(10)),
+                1 => (10, 6, bit(9), bit(10)),
+                _ => return None,
+            },
+        };
+        grid_width = w;
+        grid_height = h;
+        high_precision = high;
+        dual_plane = dual != 0;
+    }
+
+    // Weight grids can't be larger than the block.
+    if grid_width > 4 || grid_height > 4 {
+        return None;
+    }
+    let weight_range = ASTC_WEIGHT_RANGES[high_precision as usize][range_bits as usize]?;
+    let plane_count = if dual_plane { 2 } else { 1 };
+    let weight_count = grid_width * grid_height * plane_count;
+    let weight_bits = weight_range.bit_count(weight_count);
+    if !(24 ..= 96).contains(&weight_bits) {
+        return None;
+    }
+
+    let partition_count = extract_bits(bits, 11, 2) + 1;
+    if dual_plane && partition_count == 4 {
+        return None;
+    }
+
+    let mut below_weights = 128 - weight_bits;
+    let mut endpoint_modes = [0u32; 4];
+    let (partition_seed, color_start);
+    if partition_count == 1 {
+        endpoint_modes[0] = extract_bits(bits, 13, 4);
+        partition_seed = 0;
+        color_start = 17;
+    } else {
+        partition_seed = extract_bits(bits, 13, 10);
+        color_start = 29;
+        let field = extract_bits(bits, 23, 6);
+        if field & 3 == 0 {
+            for mode in &mut endpoint_modes[.. partition_count as usize] {
+                *mode = field >> 2;
+            }
+        } else {
+            // The remaining mode bits are stored just below the weights.
+            let extra_bits = 3 * partition_count - 4;
+            below_weights -= extra_bits;
+            let encoded = field | extract_bits(bits, below_weights, extra_bits) << 6;
+            let base_class = (encoded & 3) - 1;
+            for (i, mode) in endpoint_modes[.. partition_count as usize].iter_mut().enumerate() {
+                let class = base_class + ((encoded >> (2 + i)) & 1);
+                let low = (encoded >> (2 + partition_count + 2 * i as u32)) & 3;
+                *mode = class << 2 | low;
+            }
+        }
+    }
+
+    let plane2_component = if dual_plane {
+        below_weights -= 2;
+        Some(extract_bits(bits, below_weights, 2) as usize)
+    } else {
+        None
+    };
+
+    let value_count: u32 = endpoint_modes[.. partition_count as usize]
+        .iter()
+        .map(|mode| ((mode >> 2) + 1) * 2)
+        .sum();
+    if value_count > 18 || below_weights < color_start {
+        return None;
+    }
+
+    // Endpoints use the largest range that fits in the remaining bits.
+    let color_bits = below_weights - color_start;
+    let color_range = ASTC_COLOR_RANGES
+        .iter()
+        .rev()
+        .find(|range| range.bit_count(value_count) <= color_bits)?;
+    if color_range.levels() < 6 {
+        return None;
+    }
+
+    let values: Vec<i32> = decode_ise(bits, color_start, *color_range, value_count as usize)
+        .into_iter()
+        .map(|value| unquantize_color(*color_range, value) as i32)
+        .collect();
+
+    let mut endpoints = [[[0u8; 4]; 2]; 4];
+    let mut offset = 0;
+    for (endpoint, mode) in endpoints.iter_mut().zip(endpoint_modes.iter()).take(partition_count as usize) {
+        let count = (((mode >> 2) + 1) * 2) as usize;
+        *endpoint = decode_astc_endpoints(*mode, &values[offset .. offset + count])?;
+        offset += count;
+    }
+
+    // Weights are stored from the top of the block with their bits reversed.
+    let weights: Vec<u32> = decode_ise(bits.reverse_bits(), 0, weight_range, weight_count as usize)
+        .into_iter()
+        .map(|value| unquantize_weight(weight_range, value))
+        .collect();
+
+    let mut texels = [[0; 4]; 16];
+    for y in 0 .. 4 {
+        for x in 0 .. 4 {
+            let partition = if partition_count > 1 {
+                astc_select_partition(partition_seed, x, y, partition_count)
+            } else {
+                0
+            };
+            let [e0, e1] = endpoints[partition];
+
+            let plane_weights = [
+                astc_infill_weight(&weights, grid_width, grid_height, plane_count, 0, x, y),
+                astc_infill_weight(&weights, grid_width, grid_height, plane_count, 1, x, y),
+            ];
+
+            let texel = &mut texels[(y * 4 + x) as usize];
+            for c in 0 .. 4 {
+                let weight = if plane2_component == Some(c) { plane_weights[1] } else { plane_weights[0] };
+                let c0 = e0[c] as u32 * 257;
+                let c1 = e1[c] as u32 * 257;
+                let value = (c0 * (64 - weight) + c1 * weight + 32) >> 6;
+                texel[c] = (value >> 8) as u8;
+            }
+        }
+    }
+
+    Some(texels)
+}
+
+/// Bilinearly interpolates the weight grid at texel (x, y) of a 4x4 block.
+fn astc_infill_weight(
+    weights: &[u32],
+    grid_width: u32,
+    grid_height: u32,
+    plane_count: u32,
+    plane: u32,
+    x: u32,
+    y: u32,
+) -> u32 {
+    if plane >= plane_count {
+        return 0;
+    }
+
+    // (1024 + block_size / 2) / (block_size - 1)
+    const SCALE: u32 = 342;
+    let gs = (SCALE * x * (grid_width - 1) + 32) >> 6;
+    let gt = (SCALE * y * (grid_height - 1) + 32) >> 6;
+    let (js, fs) = (gs >> 4, gs & 0xf);
+    let (jt, ft) = (gt >> 4, gt & 0xf);
+
+    let weight_at = |s: u32, t: u32| -> u32 {
+        let s = s.min(grid_width - 1);
+        let t = t.min(grid_height - 1);
+        weights[((t * grid_width + s) * plane_count + plane) as usize]
+    };
+
+    let w11 = (fs * ft + 8) >> 4;
+    let w10 = ft - w11;
+    let w01 = fs - w11;
+    let w00 = 16 - fs - ft + w11;
+
+    (weight_at(js, jt) * w00 +
+        weight_at(js + 1, jt) * w01 +
+        weight_at(js, jt + 1) * w10 +
+        weight_at(js + 1, jt + 1) * w11 +
+        8) >> 4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use api::ImageDescriptorFlags;
+    use std::collections::HashSet;
+
+    /// Packs fields into a 128-bit block, starting at the least significant bit.
+    struct BitWriter {
+        bits: u128,
+        position: u32,
+    }
+
+    impl BitWriter {
+        fn new() -> Self {
+            BitWriter { bits: 0, position: 0 }
+        }
+
+        fn write(&mut self, value: u32, count: u32) {
+            self.bits |= ((value as u128) & ((1 << count) - 1)) << self.position;
+            self.position += count;
+        }
+
+        fn bytes(&self) -> [u8; 16] {
+            self.bits.to_le_bytes()
+        }
+    }
+
+    fn decode(format: ImageFormat, block: &[u8]) -> Texels {
+        let mut texels = [[0; 4]; 16];
+        decode_block(format, block, &mut texels);
+        texels
+    }
+
+    #[test]
+    fn bc1_modes() {
+        // Opaque mode: white and black endpoints with two interpolated colors.
+        let block = [0xff, 0xff, 0x00, 0x00, 0b11_10_01_00, 0, 0, 0];
+        let texels = decode(ImageFormat::BC1, &block);
+        assert_eq!(texels[0], [255, 255, 255, 255]);
+        assert_eq!(texels[1], [0, 0, 0, 255]);
+        assert_eq!(texels[2], [170, 170, 170, 255]);
+        assert_eq!(texels[3], [85, 85, 85, 255]);
+
+        // When the first endpoint is smaller, the last index is transparent.
+        let block = [0x00, 0x00, 0xff, 0xff, 0b11_10_01_00, 0, 0, 0];
+        let texels = decode(ImageFormat::BC1, &block);
+        assert_eq!(texels[2], [127, 127, 127, 255]);
+        assert_eq!(texels[3], [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn bc3_alpha() {
+        let mut block = [0u8; 16];
+        block[0] = 255;
+        block[1] = 0;
+        // Pixel 0 uses index 0, pixel 1 index 2 and the rest index 1.
+        let mut indices = 0u64;
+        for i in 0 .. 16 {
+            let index = match i { 0 => 0, 1 => 2, _ => 1 };
+            indices |= index << (3 * i);
+        }
+        block[2 .. 8].copy_from_slice(&indices.to_le_bytes()[.. 6]);
+
+        let texels = decode(ImageFormat::BC3, &block);
+        assert_eq!(texels[0][3], 255);
+        assert_eq!(texels[1][3], 218);
+        assert_eq!(texels[2][3], 0);
+    }
+
+    #[test]
+    fn bc7_mode6() {
+        let mut writer = BitWriter::new();
+        writer.write(1 << 6, 7);
+        for _ in 0 .. 3 {
+            writer.write(0, 7);
+            writer.write(127, 7);
+        }
+        writer.write(127, 7);
+        writer.write(127, 7);
+        // P-bits.
+        writer.write(0, 1);
+        writer.write(1, 1);
+        // The anchor index has one bit less.
+        writer.write(0, 3);
+        writer.write(8, 4);
+        for _ in 2 .. 16 {
+            writer.write(15, 4);
+        }
+        assert_eq!(writer.position, 128);
+
+        let texels = decode(ImageFormat::BC7, &writer.bytes());
+        assert_eq!(texels[0], [0, 0, 0, 254]);
+        assert_eq!(texels[1], [135, 135, 135, 255]);
+        assert_eq!(texels[15], [255, 255, 255, 255]);
+
+        // Mode bits of zero are reserved, and decode as transparent black.
+        assert_eq!(decode(ImageFormat::BC7, &[0; 16])[0], [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn bc7_partition_anchors() {
+        // Each anchor pixel must belong to the subset it is the anchor of.
+        for partition in 0 .. 64 {
+            assert_eq!(bc7_subset(2, partition, 0), 0);
+            assert_eq!(bc7_subset(2, partition, BC7_ANCHORS2[partition] as usize), 1);
+            assert_eq!(bc7_subset(3, partition, 0), 0);
+            assert_eq!(bc7_subset(3, partition, BC7_ANCHORS3_SECOND[partition] as usize), 1);
+            assert_eq!(bc7_subset(3, partition, BC7_ANCHORS3_THIRD[partition] as usize), 2);
+        }
+    }
+
+    #[test]
+    fn etc2_individual_mode() {
+        let mut word = 0u64;
+        // Base colors 0x888 and 0x444, with modifier tables 0 and 1.
+        word |= 0x84 << 56 | 0x84 << 48 | 0x84 << 40;
+        word |= 1 << 34;
+        // Pixel (3, 0) uses index 3.
+        word |= 1 << (16 + 12) | 1 << 12;
+
+        let texels = decode(ImageFormat::ETC2RGB8, &word.to_be_bytes());
+        assert_eq!(texels[0], [138, 138, 138, 255]);
+        assert_eq!(texels[2], [73, 73, 73, 255]);
+        assert_eq!(texels[3], [51, 51, 51, 255]);
+    }
+
+    #[test]
+    fn etc2_planar_mode() {
+        // A differential block whose blue channel overflows selects planar mode.
+        let word = 1u64 << 33 | 0b11111 << 43 | 0b001 << 40;
+
+        let texels = decode(ImageFormat::ETC2RGB8, &word.to_be_bytes());
+        assert_eq!(texels[0], [0, 0, 105, 255]);
+        assert_eq!(texels[1], [0, 0, 79, 255]);
+        assert_eq!(texels[15], [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn eac_alpha() {
+        let mut word = 128u64 << 56 | 2 << 52 | 13 << 48;
+        for i in 0 .. 16 {
+            let index = if i == 4 { 3 } else { 7 };
+            word |= index << (45 - 3 * i);
+        }
+        let mut block = [0u8; 16];
+        block[.. 8].copy_from_slice(&word.to_be_bytes());
+
+        let texels = decode(ImageFormat::ETC2RGBA8, &block);
+        assert_eq!(texels[0][3], 146);
+        assert_eq!(texels[1][3], 108);
+    }
+
+    #[test]
+    fn ise_packed_blocks() {
+        // Every combination of trits and quints must have an encoding.
+        let trits: HashSet<_> = (0 .. 256).map(decode_trits).collect();
+        assert_eq!(trits.len(), 243);
+        let quints: HashSet<_> = (0 .. 128).map(decode_quints).collect();
+        assert_eq!(quints.len(), 125);
+    }
+
+    #[test]
+    fn astc_unquantization_range() {
+        // Values aren't ordered by their digits, but every range must span
+        // the full output range.
+        fn all_values(range: IseRange) -> Vec<(u32, u32)> {
+            let digits = range.levels() >> range.bits;
+            (0 .. digits).flat_map(|d| (0 .. 1 << range.bits).map(move |m| (d, m))).collect()
+        }
+        for range in &ASTC_COLOR_RANGES[4 ..] {
+            let values: Vec<u8> = all_values(*range).into_iter().map(|v| unquantize_color(*range, v)).collect();
+            assert_eq!(values.iter().min(), Some(&0));
+            assert_eq!(values.iter().max(), Some(&255));
+        }
+        for range in ASTC_WEIGHT_RANGES.iter().flatten().flatten() {
+            let values: Vec<u32> = all_values(*range).into_iter().map(|v| unquantize_weight(*range, v)).collect();
+            assert_eq!(values.iter().min(), Some(&0));
+            assert_eq!(values.iter().max(), Some(&64));
+        }
+    }
+
+    #[test]
+    fn astc_void_extent() {
+        let mut writer = BitWriter::new();
+        writer.write(0xdfc, 12);
+        writer.write(0xfffff, 20);
+        writer.write(0xffffffff, 32);
+        for value in &[0xff00, 0x8000, 0x0000, 0xffff] {
+            writer.write(*value, 16);
+        }
+
+        let texels = decode(ImageFormat::ASTC4X4, &writer.bytes());
+        assert_eq!(texels[0], [255, 128, 0, 255]);
+        assert_eq!(texels[15], [255, 128, 0, 255]);
+    }
+
+    #[test]
+    fn astc_single_partition() {
+        let mut writer = BitWriter::new();
+        // A 4x4 grid of 2-bit weights, one partition using RGB direct endpoints.
+        writer.write(0x042, 11);
+        writer.write(0, 2);
+        writer.write(8, 4);
+        for value in &[0, 255, 0, 255, 0, 255] {
+            writer.write(*value, 8);
+        }
+        let mut bits = writer.bits;
+        // Weights are stored bit-reversed from the top of the block.
+        for i in 0 .. 16 {
+            let weight = [3, 1, 0, 2][i % 4] as u128;
+            bits |= (weight & 1) << (127 - 2 * i);
+            bits |= (weight >> 1) << (126 - 2 * i);
+        }
+
+        let texels = decode(ImageFormat::ASTC4X4, &bits.to_le_bytes());
+        assert_eq!(texels[0], [255, 255, 255, 255]);
+        assert_eq!(texels[1], [84, 84, 84, 255]);
+        assert_eq!(texels[2], [0, 0, 0, 255]);
+        assert_eq!(texels[3], [171, 171, 171, 255]);
+    }
+
+    #[test]
+    fn astc_invalid_block() {
+        // A block mode with no valid weight range decodes to the error color.
+        let texels = decode(ImageFormat::ASTC4X4, &[0; 16]);
+        assert_eq!(texels[0], ERROR_COLOR);
+    }
+
+    #[test]
+    fn decompress_partial_blocks() {
+        let descriptor = ImageDescriptor::new(5, 3, ImageFormat::BC1, ImageDescriptorFlags::empty());
+        assert_eq!(descriptor.compute_stride(), 16);
+        assert_eq!(descriptor.compute_total_size(), 16);
+
+        let mut data = vec![0u8; 16];
+        // The second block is solid red.
+        data[8 .. 12].copy_from_slice(&[0x00, 0xf8, 0x00, 0xf8]);
+        let pixels = decompress_to_rgba8(&descriptor, &data);
+        assert_eq!(pixels.len(), 5 * 3 * 4);
+        assert_eq!(&pixels[0 .. 4], &[0, 0, 0, 255]);
+        assert_eq!(&pixels[16 .. 20], &[255, 0, 0, 255]);
+        assert_eq!(&pixels[(2 * 5 + 4) * 4 .. (2 * 5 + 5) * 4], &[255, 0, 0, 255]);
+
+        // Truncated data decodes to the error color.
+        let pixels = decompress_to_rgba8(&descriptor, &data[.. 8]);
+        assert_eq!(&pixels[16 .. 20], &ERROR_COLOR);
+    }
+}
@@ -512,10 +512,9 @@ impl Texture {
     /// Returns the number of bytes (generally in GPU memory) that this texture
     /// consumes.
     pub fn size_in_bytes(&self) -> usize {
-        let bpp = self.format.bytes_per_pixel() as usize;
-        let w = self.size.width as usize;
-        let h = self.size.height as usize;
-        bpp * w * h
+        let row_bytes = self.format.bytes_per_block_row(self.size.width) as usize;
+        let rows = self.format.block_rows(self.size.height) as usize;
+        row_bytes * rows
     }
 
     #[cfg(feature = "replay")]
@@ -990,6 +989,16 @@ pub struct Capabilities {
     pub supports_image_external_essl3: bool,
     /// Whether the VAO must be rebound after an attached VBO has been orphaned.
     pub requires_vao_rebind_after_orphaning: bool,
+    /// Whether BC1 and BC3 (S3TC) compressed textures can be sampled.
+    pub supports_texture_compression_s3tc: bool,
+    /// Whether BC7 (BPTC) compressed textures can be sampled.
+    pub supports_texture_compression_bptc: bool,
+    /// Whether ETC2 and EAC compressed textures can be sampled.
+    pub supports_texture_compression_etc2: bool,
+    /// Whether LDR ASTC compressed textures can be sampled.
+    pub supports_texture_compression_astc: bool,
+    /// Whether RGBA16F and RGB10A2 textures can be created and sampled.
+    pub supports_high_bit_depth_textures: bool,
     /// The name of the renderer, as reported by GL
     pub renderer_name: String,
 }
@@ -1051,6 +1060,23 @@ impl StrideAlignment {
 // precision problems.
 const RESERVE_DEPTH_BITS: i32 = 2;
 
+// Internal formats from the texture compression extensions.
+const COMPRESSED_RGBA_S3TC_DXT1_EXT: gl::GLenum = 0x83F1;
+const COMPRESSED_RGBA_S3TC_DXT5_EXT: gl::GLenum = 0x83F3;
+const COMPRESSED_RGBA_BPTC_UNORM: gl::GLenum = 0x8E8C;
+const COMPRESSED_RGB8_ETC2: gl::GLenum = 0x9274;
+const COMPRESSED_RGBA8_ETC2_EAC: gl::GLenum = 0x9278;
+const COMPRESSED_RGBA_ASTC_4X4_KHR: gl::GLenum = 0x93B0;
+
+fn compressed_format_desc(internal: gl::GLenum) -> FormatDesc {
+    FormatDesc {
+        internal,
+        external: internal,
+        read: gl::NONE,
+        pixel_type: gl::NONE,
+    }
+}
+
 pub struct Device {
     gl: Rc<dyn gl::Gl>,
 
@@ -1772,6 +1798,28 @@ impl Device {
         // an attached buffer has been orphaned.
         let requires_vao_rebind_after_orphaning = is_adreno_3xx;
 
+        // Images in compressed formats the device can't sample from are
+        // decoded on the CPU instead. swgl doesn't support any of them.
+        let supports_texture_compression_s3tc = !is_software_webrender &&
+            supports_extension(&extensions, "GL_EXT_texture_compression_s3tc");
+        let supports_texture_compression_bptc = !is_software_webrender && match gl.get_type() {
+            gl::GlType::Gl => gl_version >= [4, 2] ||
+                supports_extension(&extensions, "GL_ARB_texture_compression_bptc"),
+            gl::GlType::Gles => supports_extension(&extensions, "GL_EXT_texture_compression_bptc"),
+        };
+        let supports_texture_compression_etc2 = !is_software_webrender && match gl.get_type() {
+            gl::GlType::Gl => gl_version >= [4, 3] ||
+                supports_extension(&extensions, "GL_ARB_ES3_compatibility"),
+            gl::GlType::Gles => true,
+        };
+        let supports_texture_compression_astc = !is_software_webrender &&
+            supports_extension(&extensions, "GL_KHR_texture_compression_astc_ldr");
+
+        // Half float and 10 bit textures are core in GL 3 and GLES 3, but swgl
+        // only implements 8 bit color formats. Images in those formats are
+        // converted to RGBA8 on the CPU instead.
+        let supports_high_bit_depth_textures = !is_software_webrender;
+
         Device {
             gl,
             base_gl: None,
@@ -1808,6 +1856,11 @@ impl Device {
                 uses_native_antialiasing,
                 supports_image_external_essl3,
                 requires_vao_rebind_after_orphaning,
+                supports_texture_compression_s3tc,
+                supports_texture_compression_bptc,
+                supports_texture_compression_etc2,
+                supports_texture_compression_astc,
+                supports_high_bit_depth_textures,
                 renderer_name,
             },
 
@@ -1908,6 +1961,34 @@ impl Device {
         self.color_formats.clone()
     }
 
+    /// Returns the block-compressed formats that textures can be created with.
+    pub fn supported_compressed_formats(&self) -> Vec<ImageFormat> {
+        let mut formats = Vec::new();
+        if self.capabilities.supports_texture_compression_s3tc {
+            formats.extend_from_slice(&[ImageFormat::BC1, ImageFormat::BC3]);
+        }
+        if self.capabilities.supports_texture_compression_bptc {
+            formats.push(ImageFormat::BC7);
+        }
+        if self.capabilities.supports_texture_compression_etc2 {
+            formats.extend_from_slice(&[ImageFormat::ETC2RGB8, ImageFormat::ETC2RGBA8]);
+        }
+        if self.capabilities.supports_texture_compression_astc {
+            formats.push(ImageFormat::ASTC4X4);
+        }
+        formats
+    }
+
+    /// Returns the image formats storing more than 8 bits per color channel
+    /// that textures can be created with.
+    pub fn supported_high_bit_depth_formats(&self) -> Vec<ImageFormat> {
+        if self.capabilities.supports_high_bit_depth_textures {
+            vec![ImageFormat::RGBA16F, ImageFormat::RGB10A2]
+        } else {
+            Vec::new()
+        }
+    }
+
     pub fn swizzle_settings(&self) -> Option<SwizzleSettings> {
         if self.capabilities.supports_texture_swizzle {
             Some(self.swizzle_settings)
@@ -2524,7 +2605,20 @@ impl Device {
             TexStorageUsage::NonBGRA8 => texture.format != ImageFormat::BGRA8,
             TexStorageUsage::Never => false,
         };
-        if use_texture_storage {
+        if format.is_compressed() && !use_texture_storage {
+            // Compressed formats have no external format to allocate with, so
+            // the storage has to be specified with (zeroed) data.
+            let size = format.bytes_per_block_row(width) * format.block_rows(height);
+            self.gl.compressed_tex_image_2d(
+                texture.target,
+                0,
+                desc.internal,
+                texture.size.width as gl::GLint,
+                texture.size.height as gl::GLint,
+                0,
+                &vec![0; size as usize],
+            );
+        } else if use_texture_storage {
             self.gl.tex_storage_2d(
                 texture.target,
                 mipmap_levels,
@@ -2557,6 +2651,57 @@ impl Device {
         texture
     }
 
+    /// Uploads block-compressed data to a region of a texture. The region must
+    /// be aligned to the format's blocks, except where it meets the right or
+    /// bottom edge of the texture. Returns the number of bytes uploaded.
+    pub fn upload_compressed_texture(
+        &mut self,
+        texture: &Texture,
+        rect: DeviceIntRect,
+        stride: Option<i32>,
+        data: &[u8],
+    ) -> usize {
+        debug_assert!(self.inside_frame);
+        debug_assert!(texture.format.is_compressed());
+        if rect.is_empty() {
+            return 0;
+        }
+
+        let format = texture.format;
+        let row_bytes = format.bytes_per_block_row(rect.width()) as usize;
+        let rows = format.block_rows(rect.height()) as usize;
+        let src_stride = stride.map_or(row_bytes, |stride| stride as usize);
+        assert!((rows - 1) * src_stride + row_bytes <= data.len());
+
+        // There is no unpack row length for compressed uploads, so rows of
+        // blocks that aren't contiguous have to be repacked.
+        let packed;
+        let data = if src_stride == row_bytes {
+            &data[.. rows * row_bytes]
+        } else {
+            packed = (0 .. rows)
+                .flat_map(|row| &data[row * src_stride .. row * src_stride + row_bytes])
+                .cloned()
+                .collect::<Vec<u8>>();
+            &packed[..]
+        };
+
+        self.bind_texture(DEFAULT_TEXTURE, texture, Swizzle::default());
+        self.gl.bind_buffer(gl::PIXEL_UNPACK_BUFFER, 0);
+        self.gl.compressed_tex_sub_image_2d(
+            texture.target,
+            0,
+            rect.min.x,
+            rect.min.y,
+            rect.width(),
+            rect.height(),
+            self.gl_describe_format(format).internal,
+            data,
+        );
+
+        data.len()
+    }
+
     fn set_texture_parameters(&mut self, target: gl::GLuint, filter: TextureFilter) {
         let mag_filter = match filter {
             TextureFilter::Nearest => gl::NEAREST,
@@ -3164,6 +3309,16 @@ impl Device {
         texture: &Texture,
         pixels: &[T]
     ) {
+        if texture.format.is_compressed() {
+            self.upload_compressed_texture(
+                texture,
+                DeviceIntRect::from_size(texture.size),
+                None,
+                texels_to_u8_slice(pixels),
+            );
+            return;
+        }
+
         self.bind_texture(DEFAULT_TEXTURE, texture, Swizzle::default());
         let desc = self.gl_describe_format(texture.format);
         self.gl.tex_sub_image_2d(
@@ -4036,6 +4191,26 @@ impl Device {
                 read: gl::RG,
                 pixel_type: gl::UNSIGNED_SHORT,
             },
+            ImageFormat::RGBA16F => FormatDesc {
+                internal: gl::RGBA16F,
+                external: gl::RGBA,
+                read: gl::RGBA,
+                pixel_type: gl::HALF_FLOAT,
+            },
+            ImageFormat::RGB10A2 => FormatDesc {
+                internal: gl::RGB10_A2,
+                external: gl::RGBA,
+                read: gl::RGBA,
+                pixel_type: gl::UNSIGNED_INT_2_10_10_10_REV,
+            },
+            // Compressed formats are only ever uploaded with their internal
+            // format, and can't be read back.
+            ImageFormat::BC1 => compressed_format_desc(COMPRESSED_RGBA_S3TC_DXT1_EXT),
+            ImageFormat::BC3 => compressed_format_desc(COMPRESSED_RGBA_S3TC_DXT5_EXT),
+            ImageFormat::BC7 => compressed_format_desc(COMPRESSED_RGBA_BPTC_UNORM),
+            ImageFormat::ETC2RGB8 => compressed_format_desc(COMPRESSED_RGB8_ETC2),
+            ImageFormat::ETC2RGBA8 => compressed_format_desc(COMPRESSED_RGBA8_ETC2_EAC),
+            ImageFormat::ASTC4X4 => compressed_format_desc(COMPRESSED_RGBA_ASTC_4X4_KHR),
         }
     }
 
@@ -4677,6 +4852,16 @@ impl<'a> TextureUploader<'a> {
             ImageFormat::RG16 => (gl::RG, 4, gl::UNSIGNED_SHORT),
             ImageFormat::RGBAF32 => (gl::RGBA, 16, gl::FLOAT),
             ImageFormat::RGBAI32 => (gl::RGBA_INTEGER, 16, gl::INT),
+            ImageFormat::RGBA16F => (gl::RGBA, 8, gl::HALF_FLOAT),
+            ImageFormat::RGB10A2 => (gl::RGBA, 4, gl::UNSIGNED_INT_2_10_10_10_REV),
+            ImageFormat::BC1 |
+            ImageFormat::BC3 |
+            ImageFormat::BC7 |
+            ImageFormat::ETC2RGB8 |
+            ImageFormat::ETC2RGBA8 |
+            ImageFormat::ASTC4X4 => {
+                panic!("Compressed {:?} data must be uploaded with upload_compressed_texture", format)
+            }
         };
 
         let row_length = match chunk.stride {
@@ -217,3 +217,71 @@ fn push_component_transfer_data(
         }
     }
 }
+
+const TURBULENCE_TABLE_SIZE: usize = 256;
+
+/// Pushes the lattice selector and gradient tables used by the turbulence
+/// filter, generated from `seed` as described in
+/// https://drafts.fxtf.org/filter-effects/#feTurbulenceElement
+///
+/// The 256 lattice entries are packed four per block, followed by the
+/// gradients of the four color channels, packed two per block.
+pub fn push_turbulence_tables(
+    request: &mut GpuDataRequest,
+    seed: i32,
+) {
+    const RAND_M: i64 = 2147483647;
+    const RAND_A: i64 = 16807;
+    const RAND_Q: i64 = 127773;
+    const RAND_R: i64 = 2836;
+
+    fn random(seed: i64) -> i64 {
+        let result = RAND_A * (seed % RAND_Q) - RAND_R * (seed / RAND_Q);
+        if result <= 0 { result + RAND_M } else { result }
+    }
+
+    let mut seed = seed as i64;
+    if seed <= 0 {
+        seed = -(seed % (RAND_M - 1)) + 1;
+    }
+    if seed > RAND_M - 1 {
+        seed = RAND_M - 1;
+    }
+
+    let size = TURBULENCE_TABLE_SIZE as i64;
+    let mut lattice = [0usize; TURBULENCE_TABLE_SIZE];
+    let mut gradients = [[[0.0f32; 2]; TURBULENCE_TABLE_SIZE]; 4];
+    for channel in gradients.iter_mut() {
+        for (i, gradient) in channel.iter_mut().enumerate() {
+            lattice[i] = i;
+            for value in gradient.iter_mut() {
+                seed = random(seed);
+                *value = ((seed % (size + size)) - size) as f32 / size as f32;
+            }
+            let length = (gradient[0] * gradient[0] + gradient[1] * gradient[1]).sqrt();
+            if length > 0.0 {
+                gradient[0] /= length;
+                gradient[1] /= length;
+            }
+        }
+    }
+
+    for i in (1 .. TURBULENCE_TABLE_SIZE).rev() {
+        seed = random(seed);
+        lattice.swap(i, (seed % size) as usize);
+    }
+
+    for values in lattice.chunks(4) {
+        request.push([
+            values[0] as f32,
+            values[1] as f32,
+            values[2] as f32,
+            values[3] as f32,
+        ]);
+    }
+    for channel in &gradients {
+        for values in channel.chunks(2) {
+            request.push([values[0][0], values[0][1], values[1][0], values[1][1]]);
+        }
+    }
+}
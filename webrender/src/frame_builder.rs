@@ -2,7 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use api::{ColorF, DebugFlags, FontRenderMode, PremultipliedColorF};
+use api::{ColorF, DebugFlags, FontRenderMode, PremultipliedColorF, RgbColorSpace};
 use api::units::*;
 use crate::batch::{BatchBuilder, AlphaBatchBuilder, AlphaBatchContainer, CommandBufferList};
 use crate::clip::{ClipStore, ClipChainStack};
@@ -52,6 +52,19 @@ impl Default for ChasePrimitive {
     }
 }
 
+/// How colors outside of the displayable range in high bit depth images
+/// (`RGBA16F`, `RGB10A2`) are mapped when they are drawn into 8 bit targets.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub enum ToneMapping {
+    /// Clamp each premultiplied color channel to the 0..alpha range.
+    Clamp,
+    /// Compress the unpremultiplied color channels with the Reinhard
+    /// operator, `c / (1 + c)`.
+    Reinhard,
+}
+
 #[derive(Clone, Copy, Debug)]
 #[cfg_attr(feature = "capture", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
@@ -79,6 +92,8 @@ pub struct FrameBuilderConfig {
     pub force_invalidation: bool,
     pub is_software: bool,
     pub low_quality_pinch_zoom: bool,
+    /// The tone mapping applied to high bit depth images, if any.
+    pub hdr_tone_mapping: Option<ToneMapping>,
 }
 
 /// A set of common / global resources that are retained between
@@ -168,6 +183,8 @@ pub struct FrameBuildingContext<'a> {
     pub debug_flags: DebugFlags,
     pub fb_config: &'a FrameBuilderConfig,
     pub root_spatial_node_index: SpatialNodeIndex,
+    /// The color space that the document is composited in.
+    pub output_color_space: RgbColorSpace,
 }
 
 pub struct FrameBuildingState<'a> {
@@ -277,6 +294,7 @@ impl FrameBuilder {
             debug_flags,
             fb_config: &scene.config,
             root_spatial_node_index,
+            output_color_space: scene.output_color_space,
         };
 
         scene.picture_graph.build_update_passes(
@@ -310,6 +328,7 @@ impl FrameBuilder {
                 scene_properties,
                 config: scene.config,
                 root_spatial_node_index,
+                output_color_space: scene.output_color_space,
             };
 
             for pic_index in scene.tile_cache_pictures.iter().rev() {
@@ -506,6 +525,7 @@ impl FrameBuilder {
         spatial_tree.update_tree(scene_properties);
         let mut transform_palette = spatial_tree.build_transform_palette();
         scene.clip_store.begin_frame(&mut scratch.clip_store);
+        scene.clip_store.update_animated_clips(&mut data_stores.clip, scene_properties);
 
         rg_builder.begin_frame(stamp.frame_id());
 
@@ -587,6 +607,8 @@ impl FrameBuilder {
                     globals: &self.globals,
                     tile_caches,
                     root_spatial_node_index: spatial_tree.root_reference_frame_index(),
+                    hdr_tone_mapping: scene.config.hdr_tone_mapping,
+                    output_color_space: scene.output_color_space,
                 };
 
                 let pass = build_render_pass(
@@ -626,6 +648,8 @@ impl FrameBuilder {
                 globals: &self.globals,
                 tile_caches,
                 root_spatial_node_index: spatial_tree.root_reference_frame_index(),
+                hdr_tone_mapping: scene.config.hdr_tone_mapping,
+                output_color_space: scene.output_color_space,
             };
 
             self.build_composite_pass(
@@ -5,11 +5,11 @@
 use api::{FontInstanceData, FontInstanceFlags, FontInstanceKey};
 use api::{FontInstanceOptions, FontInstancePlatformOptions};
 use api::{FontKey, FontRenderMode, FontSize, FontTemplate, FontVariation};
-use api::{ColorU, GlyphIndex, GlyphDimensions, SyntheticItalics};
+use api::{ColorU, GlyphIndex, GlyphDimensions, GlyphOrientation, SyntheticItalics};
 use api::{IdNamespace, BlobImageResources};
 use api::channel::crossbeam::{unbounded, Receiver, Sender};
 use api::units::*;
-use api::{ImageDescriptor, ImageDescriptorFlags, ImageFormat, DirtyRect};
+use api::{ImageDescriptor, ImageDescriptorFlags, ImageFormat, DirtyRect, RgbColorSpace};
 use crate::internal_types::ResourceCacheError;
 use crate::platform::font::FontContext;
 use crate::device::TextureFilter;
@@ -26,6 +26,7 @@ use rayon::prelude::*;
 use euclid::approxeq::ApproxEq;
 use euclid::size2;
 use smallvec::SmallVec;
+use std::borrow::Cow;
 use std::cmp;
 use std::cell::Cell;
 use std::hash::{Hash, Hasher};
@@ -150,10 +151,17 @@ impl GlyphRasterizer {
         let process_glyph = move |key: &GlyphKey| -> GlyphRasterJob {
             profile_scope!("glyph-raster");
             let mut context = font_contexts.lock_current_context();
+            // The job keeps the requested font, since the glyph is cached for
+            // it under a key that carries the orientation, but the glyph is
+            // rasterized and processed with the font it is actually drawn with.
+            let raster_font = match key.orientation() {
+                GlyphOrientation::Upright => Cow::Borrowed(&*font),
+                GlyphOrientation::Sideways => Cow::Owned(font.sideways()),
+            };
             let mut job = GlyphRasterJob {
                 font: Arc::clone(&font),
                 key: key.clone(),
-                result: context.rasterize_glyph(&font, key),
+                result: context.rasterize_glyph(&raster_font, key),
             };
 
             if let Ok(ref mut glyph) = job.result {
@@ -182,7 +190,7 @@ impl GlyphRasterizer {
                 assert_eq!((glyph.left.fract(), glyph.top.fract()), (0.0, 0.0));
 
                 // Check if the glyph has a bitmap that needs to be downscaled.
-                glyph.downscale_bitmap_if_required(&font);
+                glyph.downscale_bitmap_if_required(&raster_font);
 
                 // Convert from BGRA8 to R8 if required. In the future we can make it the
                 // backends' responsibility to output glyphs in the desired format,
@@ -290,6 +298,7 @@ impl GlyphRasterizer {
                             format: glyph.format.image_format(self.can_use_r8_format),
                             flags: ImageDescriptorFlags::empty(),
                             offset: 0,
+                            color_space: RgbColorSpace::Srgb,
                         },
                         TextureFilter::Linear,
                         Some(CachedImageData::Raw(Arc::new(glyph.bytes))),
@@ -1032,6 +1041,20 @@ impl FontInstance {
         if self.use_transform_glyphs() { GlyphFormat::TransformedSubpixel } else { GlyphFormat::Subpixel }
     }
 
+    /// Returns the font that sideways glyphs of this font are rasterized with.
+    /// A clockwise rotation, i.e. a transpose followed by a horizontal flip, is
+    /// applied on top of any transpose and flips the font already has.
+    pub fn sideways(&self) -> FontInstance {
+        let mut font = self.clone();
+        font.flags.set(FontInstanceFlags::FLIP_X, !self.flags.contains(FontInstanceFlags::FLIP_Y));
+        font.flags.set(FontInstanceFlags::FLIP_Y, self.flags.contains(FontInstanceFlags::FLIP_X));
+        font.flags.toggle(FontInstanceFlags::TRANSPOSE);
+        // A sideways glyph runs along the other axis, so synthetic italics
+        // need to skew it the other way.
+        font.flags.toggle(FontInstanceFlags::VERTICAL);
+        font
+    }
+
     pub fn disable_subpixel_aa(&mut self) {
         self.render_mode = self.render_mode.limit_by(FontRenderMode::Alpha);
     }
@@ -1182,6 +1205,7 @@ impl GlyphKey {
         index: u32,
         point: DevicePoint,
         subpx_dir: SubpixelDirection,
+        orientation: GlyphOrientation,
     ) -> Self {
         let (dx, dy) = match subpx_dir {
             SubpixelDirection::None => (0.0, 0.0),
@@ -1191,13 +1215,25 @@ impl GlyphKey {
         };
         let sox = SubpixelOffset::quantize(dx);
         let soy = SubpixelOffset::quantize(dy);
-        assert_eq!(0, index & 0xF0000000);
+        assert_eq!(0, index & 0xF8000000);
+        let sideways = match orientation {
+            GlyphOrientation::Upright => 0,
+            GlyphOrientation::Sideways => 1 << 27,
+        };
 
-        GlyphKey(index | (sox as u32) << 28 | (soy as u32) << 30)
+        GlyphKey(index | sideways | (sox as u32) << 28 | (soy as u32) << 30)
     }
 
     pub fn index(&self) -> GlyphIndex {
-        self.0 & 0x0FFFFFFF
+        self.0 & 0x07FFFFFF
+    }
+
+    pub fn orientation(&self) -> GlyphOrientation {
+        if self.0 & (1 << 27) != 0 {
+            GlyphOrientation::Sideways
+        } else {
+            GlyphOrientation::Upright
+        }
     }
 
     fn subpixel_offset(&self) -> (SubpixelOffset, SubpixelOffset) {
@@ -1450,13 +1486,82 @@ impl RasterizedGlyph {
     }
 }
 
+/// The maximum number of glyph inks kept by the font contexts.
+const MAX_CACHED_GLYPH_INKS: usize = 4096;
+
+/// The horizontal extent of the ink in each row of a rasterized glyph, which
+/// lets text decorations skip the parts of a line that cross glyph outlines.
+#[derive(Clone, Debug)]
+pub struct GlyphInk {
+    /// The position of the top left corner of the bitmap relative to the glyph
+    /// origin, with y pointing down.
+    origin: (f32, f32),
+    /// The size of a pixel of the bitmap.
+    scale: f32,
+    /// The first and last columns with ink in each row, if any.
+    rows: Vec<Option<(i32, i32)>>,
+}
+
+impl GlyphInk {
+    pub fn new(glyph: &RasterizedGlyph) -> Self {
+        let width = glyph.width.max(0) as usize;
+        let height = glyph.height.max(0) as usize;
+        let bytes_per_pixel = if width * height > 0 {
+            glyph.bytes.len() / (width * height)
+        } else {
+            0
+        };
+
+        let rows = (0 .. height).map(|y| {
+            let row = &glyph.bytes[y * width * bytes_per_pixel .. (y + 1) * width * bytes_per_pixel];
+            let has_ink = |x: &usize| {
+                row[x * bytes_per_pixel .. (x + 1) * bytes_per_pixel].iter().any(|&c| c != 0)
+            };
+            let first = (0 .. width).find(has_ink)?;
+            let last = (0 .. width).rev().find(has_ink)?;
+            Some((first as i32, last as i32))
+        }).collect();
+
+        GlyphInk {
+            origin: (glyph.left, -glyph.top),
+            scale: glyph.scale,
+            rows,
+        }
+    }
+
+    /// Returns the horizontal range covered by ink between `top` and `bottom`,
+    /// relative to the glyph origin with y pointing down.
+    pub fn horizontal_extent(&self, top: f32, bottom: f32) -> Option<(f32, f32)> {
+        let first_row = ((top - self.origin.1) / self.scale).floor().max(0.0) as usize;
+        let end_row = ((bottom - self.origin.1) / self.scale).ceil().max(0.0) as usize;
+
+        let (first, last) = self.rows
+            .get(first_row .. end_row.min(self.rows.len()))?
+            .iter()
+            .flatten()
+            .fold(None, |extent: Option<(i32, i32)>, &(first, last)| match extent {
+                Some((min, max)) => Some((min.min(first), max.max(last))),
+                None => Some((first, last)),
+            })?;
+
+        Some((
+            self.origin.0 + first as f32 * self.scale,
+            self.origin.0 + (last + 1) as f32 * self.scale,
+        ))
+    }
+}
+
 pub struct FontContexts {
     // These worker are mostly accessed from their corresponding worker threads.
     // The goal is that there should be no noticeable contention on the mutexes.
     worker_contexts: Vec<Mutex<FontContext>>,
     // This worker should be accessed by threads that don't belong to the thread pool
-    // (in theory that's only the render backend thread so no contention expected either).
+    // (in theory that's only the render backend thread, and the scene builder thread
+    // for text decorations that skip ink, so no noticeable contention expected either).
     shared_context: Mutex<FontContext>,
+    // The ink of the glyphs looked up by scene building, which outlives any
+    // single scene build. Keyed by the normalized font and glyph index.
+    glyph_inks: Mutex<FastHashMap<(FontInstance, GlyphIndex), Option<Arc<GlyphInk>>>>,
     // Stored here as a convenience to get the current thread index.
     #[allow(dead_code)]
     workers: Arc<ThreadPool>,
@@ -1487,6 +1592,69 @@ impl FontContexts {
     pub fn num_worker_contexts(&self) -> usize {
         self.worker_contexts.len()
     }
+
+    /// Rasterize a glyph with the shared context to find its ink, at the size of
+    /// the font and without transform. Unlike glyph rasterization for drawing,
+    /// this is called during scene building, which may happen before the render
+    /// backend adds the font to the contexts, so the font template is added here
+    /// if needed. The ink is cached across scene builds, regardless of the color
+    /// and transform of the font.
+    pub fn get_glyph_ink(
+        &self,
+        font: &FontInstance,
+        template: &FontTemplate,
+        glyph_index: GlyphIndex,
+        orientation: GlyphOrientation,
+    ) -> Option<Arc<GlyphInk>> {
+        let mut font = match orientation {
+            GlyphOrientation::Upright => font.clone(),
+            GlyphOrientation::Sideways => font.sideways(),
+        };
+        font.color = ColorU::new(0xFF, 0xFF, 0xFF, 0xFF);
+        font.disable_subpixel_aa();
+        font.disable_subpixel_position();
+        font.transform = FontTransform::identity();
+        FontContext::prepare_font(&mut font);
+
+        let key = (font, glyph_index);
+        if let Some(ink) = self.glyph_inks.lock().unwrap().get(&key) {
+            return ink.clone();
+        }
+        let font = &key.0;
+
+        let glyph_key = GlyphKey::new(
+            glyph_index,
+            DevicePoint::zero(),
+            SubpixelDirection::None,
+            GlyphOrientation::Upright,
+        );
+
+        let ink = {
+            let mut context = self.lock_shared_context();
+            context.add_font(&font.font_key, template);
+            context
+                .rasterize_glyph(font, &glyph_key)
+                .ok()
+                .map(|glyph| Arc::new(GlyphInk::new(&glyph)))
+        };
+
+        let mut glyph_inks = self.glyph_inks.lock().unwrap();
+        // The cache is only a shortcut, so rather than tracking which inks are
+        // still in use, start over once it grows too large.
+        if glyph_inks.len() >= MAX_CACHED_GLYPH_INKS {
+            glyph_inks.clear();
+        }
+        glyph_inks.insert(key, ink.clone());
+        ink
+    }
+
+    /// Drop the cached inks of deleted fonts and font instances.
+    fn remove_glyph_inks(&self, font_keys: &[FontKey], instances: &[FontInstance]) {
+        self.glyph_inks.lock().unwrap().retain(|(font, _), _| {
+            !font_keys.contains(&font.font_key) &&
+            !instances.iter().any(|instance| instance.instance_key == font.instance_key)
+        });
+    }
 }
 
 pub trait AsyncForEach<T> {
@@ -1581,6 +1749,7 @@ impl GlyphRasterizer {
         let font_context = FontContexts {
                 worker_contexts: contexts,
                 shared_context: Mutex::new(shared_context),
+                glyph_inks: Mutex::new(FastHashMap::default()),
                 workers: Arc::clone(&workers),
                 locked_mutex: Mutex::new(false),
                 locked_cond: Condvar::new(),
@@ -1635,6 +1804,11 @@ impl GlyphRasterizer {
         self.font_contexts.lock_shared_context().has_font(&font_key)
     }
 
+    /// The font contexts, shared with the scene builder to find glyph ink.
+    pub fn font_contexts(&self) -> Arc<FontContexts> {
+        Arc::clone(&self.font_contexts)
+    }
+
     pub fn get_glyph_dimensions(
         &mut self,
         font: &FontInstance,
@@ -1644,6 +1818,7 @@ impl GlyphRasterizer {
             glyph_index,
             DevicePoint::zero(),
             SubpixelDirection::None,
+            GlyphOrientation::Upright,
         );
 
         self.font_contexts
@@ -1665,6 +1840,7 @@ impl GlyphRasterizer {
         profile_scope!("remove_dead_fonts");
         let fonts_to_remove = mem::replace(&mut self.fonts_to_remove, Vec::new());
         let font_instances_to_remove = mem::replace(& mut self.font_instances_to_remove, Vec::new());
+        self.font_contexts.remove_glyph_inks(&fonts_to_remove, &font_instances_to_remove);
         self.font_contexts.async_for_each(move |mut context| {
             for font_key in &fonts_to_remove {
                 context.delete_font(font_key);
@@ -1711,6 +1887,7 @@ pub(in crate::glyph_rasterizer) struct GlyphRasterJob {
 }
 
 #[allow(dead_code)]
+#[derive(Debug)]
 pub enum GlyphRasterError {
     LoadFailed,
 }
@@ -1739,7 +1916,7 @@ mod test_glyph_rasterizer {
         use crate::glyph_cache::GlyphCache;
         use crate::gpu_cache::GpuCache;
         use crate::profiler::TransactionProfile;
-        use api::{FontKey, FontInstanceKey, FontTemplate, IdNamespace};
+        use api::{FontKey, FontInstanceKey, FontTemplate, GlyphOrientation, IdNamespace};
         use api::units::DevicePoint;
         use std::sync::Arc;
         use crate::glyph_rasterizer::{FontInstance, BaseFontInstance, GlyphKey, GlyphRasterizer};
@@ -1779,6 +1956,7 @@ mod test_glyph_rasterizer {
                 i,
                 DevicePoint::zero(),
                 subpx_dir,
+                GlyphOrientation::Upright,
             ));
         }
 
@@ -1813,7 +1991,7 @@ mod test_glyph_rasterizer {
         use crate::glyph_cache::GlyphCache;
         use crate::gpu_cache::GpuCache;
         use crate::profiler::TransactionProfile;
-        use api::{FontKey, FontInstanceKey, FontTemplate, IdNamespace};
+        use api::{FontKey, FontInstanceKey, FontTemplate, GlyphOrientation, IdNamespace};
         use api::units::DevicePoint;
         use std::sync::Arc;
         use crate::glyph_rasterizer::{FontInstance, BaseFontInstance, GlyphKey, GlyphRasterizer};
@@ -1853,6 +2031,7 @@ mod test_glyph_rasterizer {
                 i,
                 DevicePoint::zero(),
                 subpx_dir,
+                GlyphOrientation::Upright,
             ));
         }
 
@@ -1874,6 +2053,120 @@ mod test_glyph_rasterizer {
         );
     }
 
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn rasterize_sideways_glyphs() {
+        // This test rasterizes a few glyphs both upright and sideways with FreeType, and
+        // checks that the sideways bitmaps are the upright ones rotated clockwise about
+        // the glyph origin.
+        use std::fs::File;
+        use std::io::Read;
+        use api::{FontHinting, FontInstancePlatformOptions, FontKey, FontInstanceKey};
+        use api::{FontLCDFilter, FontTemplate, GlyphOrientation, IdNamespace};
+        use api::units::DevicePoint;
+        use std::sync::Arc;
+        use crate::glyph_rasterizer::{AddFont, FontInstance, BaseFontInstance, GlyphKey};
+        use crate::glyph_rasterizer::SubpixelDirection;
+        use crate::platform::font::FontContext;
+
+        let mut font_file =
+            File::open("../wrench/reftests/text/VeraBd.ttf").expect("Couldn't open font file");
+        let mut font_data = vec![];
+        font_file
+            .read_to_end(&mut font_data)
+            .expect("failed to read font file");
+
+        let font_key = FontKey::new(IdNamespace(0), 0);
+        let mut context = FontContext::new().unwrap();
+        context.add_font(&font_key, &FontTemplate::Raw(Arc::new(font_data), 0));
+
+        // Hinting is disabled so that the outlines are rasterized the same way in
+        // either orientation, up to the rounding of the coverage.
+        let mut font = FontInstance::from_base(Arc::new(BaseFontInstance::new(
+            FontInstanceKey::new(IdNamespace(0), 0),
+            font_key,
+            32.0,
+            None,
+            Some(FontInstancePlatformOptions {
+                lcd_filter: FontLCDFilter::None,
+                hinting: FontHinting::None,
+            }),
+            Vec::new(),
+        )));
+        font.disable_subpixel_aa();
+        FontContext::prepare_font(&mut font);
+        let sideways_font = font.sideways();
+
+        for index in 36 .. 46 {
+            let upright_key = GlyphKey::new(
+                index,
+                DevicePoint::zero(),
+                SubpixelDirection::None,
+                GlyphOrientation::Upright,
+            );
+            let sideways_key = GlyphKey::new(
+                index,
+                DevicePoint::zero(),
+                SubpixelDirection::None,
+                GlyphOrientation::Sideways,
+            );
+            assert_eq!(upright_key.index(), sideways_key.index());
+            assert_eq!(sideways_key.orientation(), GlyphOrientation::Sideways);
+
+            let upright = context.rasterize_glyph(&font, &upright_key).unwrap();
+            let sideways = context.rasterize_glyph(&sideways_font, &sideways_key).unwrap();
+
+            assert_eq!((sideways.width, sideways.height), (upright.height, upright.width));
+            assert_eq!(sideways.left, upright.top - upright.height as f32);
+            assert_eq!(sideways.top, -upright.left);
+
+            // Row y of the sideways bitmap is column y of the upright one, read
+            // from the bottom up.
+            let width = upright.width as usize;
+            let height = upright.height as usize;
+            for y in 0 .. width {
+                for x in 0 .. height {
+                    let sideways_pixel = (y * height + x) * 4;
+                    let upright_pixel = ((height - 1 - x) * width + y) * 4;
+                    for c in 0 .. 4 {
+                        let a = sideways.bytes[sideways_pixel + c] as i32;
+                        let b = upright.bytes[upright_pixel + c] as i32;
+                        assert!((a - b).abs() <= 3, "glyph {} differs at ({}, {})", index, x, y);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn glyph_ink() {
+        use crate::glyph_rasterizer::{GlyphFormat, GlyphInk, RasterizedGlyph};
+
+        // A 4x4 glyph at 2x scale, 4 pixels above the baseline, with ink in
+        // the second column of the first two rows and in the last two
+        // columns of the last row.
+        let mut bytes = vec![0u8; 4 * 4 * 4];
+        for &(x, y) in &[(1, 0), (1, 1), (2, 3), (3, 3)] {
+            bytes[(y * 4 + x) * 4 + 3] = 0xff;
+        }
+        let ink = GlyphInk::new(&RasterizedGlyph {
+            top: 4.0,
+            left: 1.0,
+            width: 4,
+            height: 4,
+            scale: 2.0,
+            format: GlyphFormat::Alpha,
+            bytes,
+        });
+
+        assert_eq!(ink.horizontal_extent(-4.0, -2.0), Some((3.0, 5.0)));
+        assert_eq!(ink.horizontal_extent(-4.0, 4.0), Some((3.0, 9.0)));
+        assert_eq!(ink.horizontal_extent(2.5, 3.0), Some((5.0, 9.0)));
+        assert_eq!(ink.horizontal_extent(0.0, 2.0), None);
+        assert_eq!(ink.horizontal_extent(4.0, 10.0), None);
+        assert_eq!(ink.horizontal_extent(-10.0, -4.0), None);
+    }
+
     #[test]
     fn test_subpx_quantize() {
         use crate::glyph_rasterizer::SubpixelOffset;
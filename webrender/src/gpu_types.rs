@@ -191,6 +191,18 @@ pub struct ClipMaskInstanceRect {
     pub clip_data: ClipData,
 }
 
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+#[repr(C)]
+pub struct ClipMaskInstancePolygon {
+    pub common: ClipMaskInstanceCommon,
+    pub local_rect: LayoutRect,
+    pub polygon_address: GpuCacheAddress,
+    pub vertex_count: i32,
+    pub fill_rule: i32,
+}
+
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "capture", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
@@ -574,6 +586,11 @@ bitflags! {
         /// Whether to force the anti-aliasing when the primitive
         /// is axis-aligned.
         const FORCE_AA = 256;
+        /// Clamp the colors sampled from a high bit depth image.
+        const TONE_MAP_CLAMP = 512;
+        /// Tone map the colors sampled from a high bit depth image with the
+        /// Reinhard operator.
+        const TONE_MAP_REINHARD = 1024;
     }
 }
 
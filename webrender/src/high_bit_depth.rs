@@ -0,0 +1,185 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! CPU conversion of high bit depth image formats.
+//!
+//! `RGBA16F` and `RGB10A2` images are normally uploaded as-is and tone mapped
+//! by the image shader when they are drawn. When the device can't create
+//! textures in those formats (e.g. the software rasterizer), the resource
+//! cache converts the image to RGBA8 up front, applying the same tone mapping
+//! as the shader would.
+
+use api::{ImageDescriptor, ImageFormat};
+use crate::frame_builder::ToneMapping;
+
+/// Converts `RGBA16F` or `RGB10A2` image data into tightly packed RGBA8
+/// pixels.
+///
+/// Pixels that are missing from a truncated buffer are left transparent.
+pub fn convert_to_rgba8(
+    descriptor: &ImageDescriptor,
+    data: &[u8],
+    tone_mapping: Option<ToneMapping>,
+) -> Vec<u8> {
+    let format = descriptor.format;
+    assert!(format.is_high_bit_depth_color());
+
+    let width = descriptor.size.width.max(0) as usize;
+    let height = descriptor.size.height.max(0) as usize;
+    let stride = descriptor.compute_stride() as usize;
+    let bpp = format.bytes_per_pixel() as usize;
+
+    let mut pixels = vec![0; width * height * 4];
+
+    for y in 0 .. height {
+        let row_start = descriptor.offset as usize + y * stride;
+        for x in 0 .. width {
+            let start = row_start + x * bpp;
+            let texel = match data.get(start .. start + bpp) {
+                Some(texel) => texel,
+                None => continue,
+            };
+
+            let color = match format {
+                ImageFormat::RGBA16F => decode_rgba16f(texel),
+                _ => decode_rgb10a2(texel),
+            };
+            let color = tone_map(color, tone_mapping);
+
+            let offset = (y * width + x) * 4;
+            for (dst, value) in pixels[offset .. offset + 4].iter_mut().zip(color.iter()) {
+                // NaNs saturate to zero.
+                *dst = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+            }
+        }
+    }
+
+    pixels
+}
+
+/// Applies a tone mapping operator to a premultiplied color. This must match
+/// `tone_map` in brush_image.glsl.
+pub fn tone_map(color: [f32; 4], tone_mapping: Option<ToneMapping>) -> [f32; 4] {
+    let [r, g, b, a] = color;
+    let a = a.clamp(0.0, 1.0);
+    match tone_mapping {
+        None => [r, g, b, a],
+        Some(ToneMapping::Clamp) => [
+            r.max(0.0).min(a),
+            g.max(0.0).min(a),
+            b.max(0.0).min(a),
+            a,
+        ],
+        Some(ToneMapping::Reinhard) => {
+            if a <= 0.0 {
+                return [0.0; 4];
+            }
+            let reinhard = |c: f32| {
+                let c = (c / a).max(0.0);
+                c / (1.0 + c) * a
+            };
+            [reinhard(r), reinhard(g), reinhard(b), a]
+        }
+    }
+}
+
+fn decode_rgba16f(texel: &[u8]) -> [f32; 4] {
+    let channel = |i: usize| half_to_f32(u16::from_ne_bytes([texel[i * 2], texel[i * 2 + 1]]));
+    [channel(0), channel(1), channel(2), channel(3)]
+}
+
+/// Decodes a texel in the GL_UNSIGNED_INT_2_10_10_10_REV layout, with red in
+/// the least significant bits.
+fn decode_rgb10a2(texel: &[u8]) -> [f32; 4] {
+    let bits = u32::from_ne_bytes([texel[0], texel[1], texel[2], texel[3]]);
+    [
+        (bits & 0x3ff) as f32 / 1023.0,
+        ((bits >> 10) & 0x3ff) as f32 / 1023.0,
+        ((bits >> 20) & 0x3ff) as f32 / 1023.0,
+        (bits >> 30) as f32 / 3.0,
+    ]
+}
+
+/// Converts an IEEE 754 binary16 value to an f32.
+pub fn half_to_f32(half: u16) -> f32 {
+    let sign = ((half as u32) & 0x8000) << 16;
+    let exponent = ((half >> 10) & 0x1f) as u32;
+    let mantissa = (half & 0x3ff) as u32;
+
+    let magnitude = match exponent {
+        // Zero and subnormals.
+        0 => mantissa as f32 * (1.0 / (1 << 24) as f32),
+        // Infinity and NaN.
+        0x1f => f32::from_bits(0x7f80_0000 | (mantissa << 13)),
+        _ => f32::from_bits(((exponent + 112) << 23) | (mantissa << 13)),
+    };
+
+    f32::from_bits(magnitude.to_bits() | sign)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use api::ImageDescriptorFlags;
+
+    #[test]
+    fn half_float_values() {
+        assert_eq!(half_to_f32(0x0000), 0.0);
+        assert_eq!(half_to_f32(0x3c00), 1.0);
+        assert_eq!(half_to_f32(0x4000), 2.0);
+        assert_eq!(half_to_f32(0x3800), 0.5);
+        assert_eq!(half_to_f32(0xc000), -2.0);
+        assert_eq!(half_to_f32(0x7bff), 65504.0);
+        assert_eq!(half_to_f32(0x0001), 1.0 / (1 << 24) as f32);
+        assert_eq!(half_to_f32(0x7c00), f32::INFINITY);
+        assert!(half_to_f32(0x7e00).is_nan());
+    }
+
+    #[test]
+    fn tone_mapping_operators() {
+        let color = [2.0, 0.5, -1.0, 0.5];
+        assert_eq!(tone_map(color, None), color);
+        assert_eq!(tone_map(color, Some(ToneMapping::Clamp)), [0.5, 0.5, 0.0, 0.5]);
+        assert_eq!(tone_map(color, Some(ToneMapping::Reinhard)), [0.4, 0.25, 0.0, 0.5]);
+        assert_eq!(tone_map([1.0, 1.0, 1.0, 0.0], Some(ToneMapping::Reinhard)), [0.0; 4]);
+    }
+
+    #[test]
+    fn convert_rgba16f() {
+        let descriptor = ImageDescriptor::new(2, 1, ImageFormat::RGBA16F, ImageDescriptorFlags::empty());
+        let mut data = Vec::new();
+        for half in &[0x3c00u16, 0x3800, 0x0000, 0x3c00, 0x4000, 0x3c00, 0x3c00, 0x3c00] {
+            data.extend_from_slice(&half.to_ne_bytes());
+        }
+
+        assert_eq!(
+            convert_to_rgba8(&descriptor, &data, None),
+            vec![255, 128, 0, 255, 255, 255, 255, 255],
+        );
+        assert_eq!(
+            convert_to_rgba8(&descriptor, &data, Some(ToneMapping::Reinhard)),
+            vec![128, 85, 0, 255, 170, 128, 128, 255],
+        );
+    }
+
+    #[test]
+    fn convert_rgb10a2() {
+        let mut descriptor = ImageDescriptor::new(1, 2, ImageFormat::RGB10A2, ImageDescriptorFlags::empty());
+        descriptor.stride = Some(8);
+        let mut data = Vec::new();
+        data.extend_from_slice(&(1023u32 | (512 << 20) | (3 << 30)).to_ne_bytes());
+        data.extend_from_slice(&[0xff; 4]);
+        data.extend_from_slice(&(1u32 << 30).to_ne_bytes());
+
+        assert_eq!(
+            convert_to_rgba8(&descriptor, &data, None),
+            vec![255, 0, 128, 255, 0, 0, 0, 85],
+        );
+
+        // The second row is cut off.
+        let truncated = convert_to_rgba8(&descriptor, &data[.. 4], None);
+        assert_eq!(truncated.len(), 8);
+        assert_eq!(&truncated[4 ..], &[0, 0, 0, 0]);
+    }
+}
@@ -3,7 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use api::{BorderRadius, ClipMode, HitTestItem, HitTestResult, ItemTag, PrimitiveFlags};
-use api::{PipelineId, ApiHitTester, ClipId};
+use api::{PipelineId, ApiHitTester, ClipId, HitTestArea, HitTestMode, HitTestQuery, HitTestShape};
 use api::units::*;
 use crate::clip::{ClipItemKind, ClipStore, ClipNode, rounded_rectangle_contains_point};
 use crate::clip::{polygon_contains_point};
@@ -11,9 +11,10 @@ use crate::prim_store::PolygonKey;
 use crate::scene_builder_thread::Interners;
 use crate::spatial_tree::{SpatialNodeIndex, SpatialTree, get_external_scroll_offset};
 use crate::internal_types::{FastHashMap, FastHashSet, LayoutPrimitiveInfo};
+use std::cmp::Ordering;
 use std::ops;
 use std::sync::{Arc, Mutex};
-use crate::util::{LayoutToWorldFastTransform, VecHelper};
+use crate::util::{LayoutToWorldFastTransform, ScaleOffset, VecHelper};
 
 pub struct SharedHitTester {
     // We don't really need a mutex here. We could do with some sort of
@@ -42,13 +43,28 @@ impl SharedHitTester {
 }
 
 impl ApiHitTester for SharedHitTester {
-    fn hit_test(&self,
-        point: WorldPoint,
+    fn hit_test_query(&self,
+        query: &HitTestQuery,
     ) -> HitTestResult {
-        self.get_ref().hit_test(HitTest::new(point))
+        self.get_ref().hit_test(HitTest::new(query.clone()))
     }
 }
 
+/// The number of probes along each axis of the part of an item overlapping
+/// the area of an area query, when the item or one of its clips isn't
+/// axis-aligned in world space and the point of the item closest to the
+/// center of the area is clipped out.
+const AREA_PROBE_COUNT: usize = 8;
+
+/// How far points on the boundary of a region are moved off of it on either
+/// side, in world space, to find one inside of all the regions of a hit test.
+const BOUNDARY_NUDGE: f32 = 0.01;
+
+/// The number of steps around an ellipse over which the points closest to a
+/// point and the crossings with other ellipses are searched for, before they
+/// are refined.
+const ELLIPSE_STEP_COUNT: usize = 128;
+
 /// A copy of important spatial node data to use during hit testing. This a copy of
 /// data from the SpatialTree that will persist as a new frame is under construction,
 /// allowing hit tests consistent with the currently rendered frame.
@@ -93,11 +109,15 @@ impl HitTestClipNode {
                 if let Some(handle) = polygon_handle {
                     // Retrieve the polygon data from the interner.
                     let polygon = &interners.polygon[handle];
-                    HitTestRegion::Polygon(rect, *polygon)
+                    HitTestRegion::Polygon(rect, polygon.clone())
                 } else {
                     HitTestRegion::Rectangle(rect, ClipMode::Clip)
                 }
             }
+            ClipItemKind::Polygon { rect, polygon_handle, .. } => {
+                let polygon = &interners.polygon[polygon_handle];
+                HitTestRegion::Polygon(rect, polygon.clone())
+            }
             ClipItemKind::BoxShadow { .. } => HitTestRegion::Invalid,
         };
 
@@ -112,6 +132,9 @@ impl HitTestClipNode {
 struct HitTestingItem {
     rect: LayoutRect,
     clip_rect: LayoutRect,
+    /// The hit-testable region within the rect, if it isn't the whole rect.
+    shape: Option<HitTestRegion>,
+    pass_through: bool,
     tag: ItemTag,
     is_backface_visible: bool,
     spatial_node_index: SpatialNodeIndex,
@@ -123,18 +146,54 @@ impl HitTestingItem {
     fn new(
         tag: ItemTag,
         info: &LayoutPrimitiveInfo,
+        shape: &HitTestShape,
+        points: &[LayoutPoint],
+        pass_through: bool,
         spatial_node_index: SpatialNodeIndex,
         clip_nodes_range: ops::Range<ClipNodeIndex>,
     ) -> HitTestingItem {
+        let shape = match *shape {
+            HitTestShape::Rect => None,
+            HitTestShape::RoundedRect(radius) => {
+                Some(HitTestRegion::RoundedRectangle(info.rect, radius, ClipMode::Clip))
+            }
+            HitTestShape::Ellipse => {
+                let radius = BorderRadius::uniform_size(info.rect.size() * 0.5);
+                Some(HitTestRegion::RoundedRectangle(info.rect, radius, ClipMode::Clip))
+            }
+            // A polygon needs at least three vertices to enclose any area.
+            HitTestShape::Polygon(..) if points.len() < 3 => {
+                Some(HitTestRegion::Rectangle(LayoutRect::zero(), ClipMode::Clip))
+            }
+            HitTestShape::Polygon(fill_rule) => {
+                // The polygon key stores points relative to the origin of
+                // the bounds, like polygon clips do.
+                let bounds = LayoutRect::from_points(points);
+                let relative_points: Vec<LayoutPoint> = points
+                    .iter()
+                    .map(|point| *point - bounds.min.to_vector())
+                    .collect();
+                Some(HitTestRegion::Polygon(bounds, PolygonKey::new(&relative_points, fill_rule)))
+            }
+        };
+
         HitTestingItem {
             rect: info.rect,
             clip_rect: info.clip_rect,
+            shape,
+            pass_through,
             tag,
             is_backface_visible: info.flags.contains(PrimitiveFlags::IS_BACKFACE_VISIBLE),
             spatial_node_index,
             clip_nodes_range,
         }
     }
+
+    /// Returns true if the item's shape contains the given point, in the
+    /// local space of the item.
+    fn shape_contains(&self, point: LayoutPoint) -> bool {
+        self.shape.as_ref().map_or(true, |shape| shape.contains(&point))
+    }
 }
 
 /// Statistics about allocation sizes of current hit tester,
@@ -206,11 +265,16 @@ impl HitTestingScene {
         }
     }
 
-    /// Add a hit testing primitive.
+    /// Add a hit testing primitive, whose hit-testable region has the given
+    /// shape within its rect. Polygon points are in the local space of the
+    /// spatial node.
     pub fn add_item(
         &mut self,
         tag: ItemTag,
         info: &LayoutPrimitiveInfo,
+        shape: &HitTestShape,
+        points: &[LayoutPoint],
+        pass_through: bool,
         spatial_node_index: SpatialNodeIndex,
         clip_id: ClipId,
         clip_store: &ClipStore,
@@ -262,6 +326,9 @@ impl HitTestingScene {
         let item = HitTestingItem::new(
             tag,
             info,
+            shape,
+            points,
+            pass_through,
             spatial_node_index,
             clip_range,
         );
@@ -291,7 +358,7 @@ impl HitTestingScene {
     }
 }
 
-#[derive(MallocSizeOf)]
+#[derive(Clone, MallocSizeOf)]
 enum HitTestRegion {
     Invalid,
     Rectangle(LayoutRect, ClipMode),
@@ -310,13 +377,246 @@ impl HitTestRegion {
                 rounded_rectangle_contains_point(point, &rect, &radii),
             HitTestRegion::RoundedRectangle(rect, radii, ClipMode::ClipOut) =>
                 !rounded_rectangle_contains_point(point, &rect, &radii),
-            HitTestRegion::Polygon(rect, polygon) =>
-                polygon_contains_point(point, &rect, &polygon),
+            HitTestRegion::Polygon(ref rect, ref polygon) =>
+                polygon_contains_point(point, rect, polygon),
             HitTestRegion::Invalid => true,
         }
     }
 }
 
+/// A piece of the boundary of a region in an area hit test, in world space.
+#[derive(Clone, Copy)]
+enum Boundary {
+    /// A line segment, between two points.
+    Segment(WorldPoint, WorldPoint),
+    /// A whole axis-aligned ellipse, with a center and radii. Rounded corners
+    /// only follow a quarter of it, but points on the rest are just rejected.
+    Ellipse(WorldPoint, WorldVector2D),
+}
+
+impl Boundary {
+    fn add_rect(rect: &WorldRect, boundaries: &mut Vec<Boundary>) {
+        let corners = [rect.top_left(), rect.top_right(), rect.bottom_right(), rect.bottom_left()];
+        Boundary::add_polygon(&corners, boundaries);
+    }
+
+    fn add_polygon(points: &[WorldPoint], boundaries: &mut Vec<Boundary>) {
+        for (i, point) in points.iter().enumerate() {
+            boundaries.push(Boundary::Segment(*point, points[(i + 1) % points.len()]));
+        }
+    }
+
+    fn add_region(region: &HitTestRegion, transform: &ScaleOffset, boundaries: &mut Vec<Boundary>) {
+        match *region {
+            HitTestRegion::Invalid => {}
+            HitTestRegion::Rectangle(ref rect, _) => {
+                Boundary::add_rect(&transform.map_rect(rect), boundaries);
+            }
+            HitTestRegion::RoundedRectangle(ref rect, ref radii, _) => {
+                Boundary::add_rect(&transform.map_rect(rect), boundaries);
+
+                let corners = [
+                    (rect.top_left() + radii.top_left.to_vector(), radii.top_left),
+                    (
+                        rect.top_right() + LayoutVector2D::new(-radii.top_right.width, radii.top_right.height),
+                        radii.top_right,
+                    ),
+                    (rect.bottom_right() - radii.bottom_right.to_vector(), radii.bottom_right),
+                    (
+                        rect.bottom_left() + LayoutVector2D::new(radii.bottom_left.width, -radii.bottom_left.height),
+                        radii.bottom_left,
+                    ),
+                ];
+                for &(center, radius) in &corners {
+                    if radius.width <= 0.0 || radius.height <= 0.0 {
+                        continue;
+                    }
+                    let radius: WorldVector2D = transform.map_vector(&radius.to_vector());
+                    boundaries.push(Boundary::Ellipse(
+                        transform.map_point(&center),
+                        WorldVector2D::new(radius.x.abs(), radius.y.abs()),
+                    ));
+                }
+            }
+            HitTestRegion::Polygon(ref rect, ref polygon) => {
+                let points: Vec<WorldPoint> = polygon.points
+                    .iter()
+                    .map(|point| transform.map_point(&(rect.min + LayoutVector2D::new(point.x, point.y))))
+                    .collect();
+                Boundary::add_polygon(&points, boundaries);
+            }
+        }
+    }
+
+    /// Add the points of the boundary that are locally closest to the target.
+    fn add_closest_points(&self, target: WorldPoint, points: &mut Vec<BoundaryPoint>) {
+        match *self {
+            Boundary::Segment(from, to) => {
+                let direction = to - from;
+                let length = direction.square_length();
+                let t = if length > 0.0 {
+                    ((target - from).dot(direction) / length).max(0.0).min(1.0)
+                } else {
+                    0.0
+                };
+                points.push(BoundaryPoint::new(from + direction * t, direction, direction));
+            }
+            Boundary::Ellipse(center, radius) => {
+                let distance = |angle: f32| (ellipse_point(center, radius, angle) - target).square_length();
+                let step = 2.0 * std::f32::consts::PI / ELLIPSE_STEP_COUNT as f32;
+                for i in 0 .. ELLIPSE_STEP_COUNT {
+                    let angle = i as f32 * step;
+                    let d = distance(angle);
+                    if d > distance(angle - step) || d > distance(angle + step) {
+                        continue;
+                    }
+
+                    // Narrow the minimum down with a ternary search.
+                    let (mut low, mut high) = (angle - step, angle + step);
+                    for _ in 0 .. 32 {
+                        let a = low + (high - low) / 3.0;
+                        let b = high - (high - low) / 3.0;
+                        if distance(a) < distance(b) {
+                            high = b;
+                        } else {
+                            low = a;
+                        }
+                    }
+                    let angle = (low + high) * 0.5;
+                    let tangent = ellipse_tangent(radius, angle);
+                    points.push(BoundaryPoint::new(ellipse_point(center, radius, angle), tangent, tangent));
+                }
+            }
+        }
+    }
+
+    /// Add the points where the boundary crosses or touches another one.
+    fn add_crossings(&self, other: &Boundary, points: &mut Vec<BoundaryPoint>) {
+        // Allow for rounding at the ends of segments, so that polygons and
+        // rects are found to meet at their corners.
+        const END_TOLERANCE: f32 = 1e-4;
+        let on_segment = |t: f32| t >= -END_TOLERANCE && t <= 1.0 + END_TOLERANCE;
+
+        match (*self, *other) {
+            (Boundary::Segment(a0, a1), Boundary::Segment(b0, b1)) => {
+                let (a, b) = (a1 - a0, b1 - b0);
+                let denominator = a.cross(b);
+                if denominator == 0.0 {
+                    // Parallel segments only overlap at points that are
+                    // already found as their ends.
+                    return;
+                }
+                let offset = b0 - a0;
+                let t = offset.cross(b) / denominator;
+                let u = offset.cross(a) / denominator;
+                if on_segment(t) && on_segment(u) {
+                    points.push(BoundaryPoint::new(a0 + a * t, a, b));
+                }
+            }
+            (Boundary::Segment(from, to), Boundary::Ellipse(center, radius)) |
+            (Boundary::Ellipse(center, radius), Boundary::Segment(from, to)) => {
+                // Scale the ellipse into the unit circle, and solve for where
+                // the segment is at distance one from its center.
+                let start = WorldVector2D::new((from.x - center.x) / radius.x, (from.y - center.y) / radius.y);
+                let direction = WorldVector2D::new((to.x - from.x) / radius.x, (to.y - from.y) / radius.y);
+                let a = direction.square_length();
+                let b = 2.0 * start.dot(direction);
+                let c = start.square_length() - 1.0;
+                let discriminant = b * b - 4.0 * a * c;
+                if a == 0.0 || discriminant < 0.0 {
+                    return;
+                }
+                for &sign in &[-1.0, 1.0] {
+                    let t = (-b + sign * discriminant.sqrt()) / (2.0 * a);
+                    if !on_segment(t) {
+                        continue;
+                    }
+                    let point = from + (to - from) * t;
+                    let angle = ((point.y - center.y) / radius.y).atan2((point.x - center.x) / radius.x);
+                    points.push(BoundaryPoint::new(point, to - from, ellipse_tangent(radius, angle)));
+                }
+            }
+            (Boundary::Ellipse(center, radius), Boundary::Ellipse(other_center, other_radius)) => {
+                // Step around the first ellipse to find where it goes in or
+                // out of the second one, and bisect each of those steps.
+                let outside = |angle: f32| {
+                    let point = ellipse_point(center, radius, angle);
+                    let x = (point.x - other_center.x) / other_radius.x;
+                    let y = (point.y - other_center.y) / other_radius.y;
+                    x * x + y * y > 1.0
+                };
+                let step = 2.0 * std::f32::consts::PI / ELLIPSE_STEP_COUNT as f32;
+                for i in 0 .. ELLIPSE_STEP_COUNT {
+                    let (mut low, mut high) = (i as f32 * step, (i + 1) as f32 * step);
+                    let low_outside = outside(low);
+                    if low_outside == outside(high) {
+                        continue;
+                    }
+                    for _ in 0 .. 24 {
+                        let middle = (low + high) * 0.5;
+                        if outside(middle) == low_outside {
+                            low = middle;
+                        } else {
+                            high = middle;
+                        }
+                    }
+                    let angle = (low + high) * 0.5;
+                    let point = ellipse_point(center, radius, angle);
+                    let other_angle = ((point.y - other_center.y) / other_radius.y)
+                        .atan2((point.x - other_center.x) / other_radius.x);
+                    points.push(BoundaryPoint::new(
+                        point,
+                        ellipse_tangent(radius, angle),
+                        ellipse_tangent(other_radius, other_angle),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+fn ellipse_point(center: WorldPoint, radius: WorldVector2D, angle: f32) -> WorldPoint {
+    center + WorldVector2D::new(radius.x * angle.cos(), radius.y * angle.sin())
+}
+
+fn ellipse_tangent(radius: WorldVector2D, angle: f32) -> WorldVector2D {
+    WorldVector2D::new(-radius.x * angle.sin(), radius.y * angle.cos())
+}
+
+/// A point on one or two boundaries, with their tangents at that point.
+struct BoundaryPoint {
+    point: WorldPoint,
+    tangents: [WorldVector2D; 2],
+}
+
+impl BoundaryPoint {
+    fn new(point: WorldPoint, tangent: WorldVector2D, other_tangent: WorldVector2D) -> Self {
+        BoundaryPoint {
+            point,
+            tangents: [tangent, other_tangent],
+        }
+    }
+
+    /// Add the point, and the points just off of it across and between its
+    /// boundaries, one of which is inside of all the regions if any point
+    /// close to it is.
+    fn add_nudged_points(&self, points: &mut Vec<WorldPoint>) {
+        points.push(self.point);
+
+        let [t0, t1] = self.tangents;
+        let n0 = WorldVector2D::new(-t0.y, t0.x);
+        let n1 = WorldVector2D::new(-t1.y, t1.x);
+        let t0 = t0.try_normalize().unwrap_or_else(WorldVector2D::zero);
+        let t1 = t1.try_normalize().unwrap_or_else(WorldVector2D::zero);
+        let directions = [n0, -n0, n1, -n1, t0 + t1, t0 - t1, t1 - t0, -t0 - t1];
+        for direction in directions.iter() {
+            if let Some(direction) = direction.try_normalize() {
+                points.push(self.point + direction * BOUNDARY_NUDGE);
+            }
+        }
+    }
+}
+
 #[derive(MallocSizeOf)]
 pub struct HitTester {
     #[ignore_malloc_size_of = "Arc"]
@@ -372,89 +672,279 @@ impl HitTester {
     pub fn hit_test(&self, test: HitTest) -> HitTestResult {
         let mut result = HitTestResult::default();
 
+        let area_center = test.area.center();
+        let area_bounds = test.area.bounds();
+
         let mut current_spatial_node_index = SpatialNodeIndex::INVALID;
-        let mut point_in_layer = None;
+        let mut center_in_layer = None;
+        let mut bounds_in_layer = None;
 
         // For each hit test primitive
         for item in self.scene.items.iter().rev() {
+            if let Some(ref tag_range) = test.tag_range {
+                if !tag_range.contains(&item.tag) {
+                    continue;
+                }
+            }
+
             let scroll_node = &self.spatial_nodes[&item.spatial_node_index];
             let pipeline_id = scroll_node.pipeline_id;
 
-            // Update the cached point in layer space, if the spatial node
+            // Update the cached area in layer space, if the spatial node
             // changed since last primitive.
             if item.spatial_node_index != current_spatial_node_index {
-                point_in_layer = scroll_node
-                    .world_content_transform
-                    .inverse()
-                    .and_then(|inverted| inverted.transform_point2d(test.point));
+                let inverted = scroll_node.world_content_transform.inverse();
+                center_in_layer = inverted
+                    .as_ref()
+                    .and_then(|inverted| inverted.transform_point2d(area_center));
+                bounds_in_layer = match test.area {
+                    HitTestArea::Point(..) => None,
+                    HitTestArea::Rect(..) | HitTestArea::Circle { .. } => inverted
+                        .as_ref()
+                        .and_then(|inverted| inverted.to_transform().outer_transformed_box2d(&area_bounds)),
+                };
                 current_spatial_node_index = item.spatial_node_index;
             }
 
             // Only consider hit tests on transformable layers.
-            let point_in_layer = match point_in_layer {
+            let center_in_layer = match center_in_layer {
                 Some(p) => p,
                 None => continue,
             };
 
-            // If the item's rect or clip rect don't contain this point, it's
-            // not a valid hit.
-            if !item.rect.contains(point_in_layer) {
-                continue;
-            }
-
-            if !item.clip_rect.contains(point_in_layer) {
-                continue;
-            }
-
-            // See if any of the clips for this primitive cull out the item.
-            let clip_nodes = &self.scene.clip_nodes[item.clip_nodes_range.start.0 as usize .. item.clip_nodes_range.end.0 as usize];
-            let is_valid = clip_nodes.iter().all(|clip_node| {
-                let transform = self
-                    .spatial_nodes[&clip_node.spatial_node_index]
-                    .world_content_transform;
-                let transformed_point = match transform
-                    .inverse()
-                    .and_then(|inverted| inverted.transform_point2d(test.point))
-                {
-                    Some(point) => point,
-                    // XXX This `return true` is a bit sketchy, but matches
-                    // pre-existing behavior.
-                    None => return true,
-                };
-                clip_node.region.contains(&transformed_point)
-            });
-
-            if !is_valid {
-                continue;
-            }
-
             // Don't hit items with backface-visibility:hidden if they are facing the back.
             if !item.is_backface_visible && scroll_node.world_content_transform.is_backface_visible() {
                 continue;
             }
 
+            let point_in_layer = match test.area {
+                HitTestArea::Point(point) => {
+                    // If the item's rect or clip rect don't contain this point,
+                    // it's not a valid hit.
+                    if !item.rect.contains(center_in_layer) ||
+                       !item.clip_rect.contains(center_in_layer) ||
+                       !item.shape_contains(center_in_layer) ||
+                       !self.clips_contain(item, point) {
+                        continue;
+                    }
+                    center_in_layer
+                }
+                HitTestArea::Rect(..) | HitTestArea::Circle { .. } => {
+                    let overlap = bounds_in_layer
+                        .and_then(|bounds| bounds.intersection(&item.rect))
+                        .and_then(|overlap| overlap.intersection(&item.clip_rect));
+                    let overlap = match overlap {
+                        Some(overlap) => overlap,
+                        None => continue,
+                    };
+
+                    let point = match self.area_boundaries(item, &test.area, &overlap) {
+                        Some(boundaries) => self.intersect_area(item, &test.area, &overlap, &boundaries),
+                        None => self.probe_area(item, &test.area, &overlap, center_in_layer),
+                    };
+                    match point {
+                        Some(point) => point,
+                        None => continue,
+                    }
+                }
+            };
+
+            let item_rect = scroll_node
+                .world_content_transform
+                .to_transform()
+                .outer_transformed_box2d(&item.rect)
+                .unwrap_or_else(WorldRect::zero);
+
             result.items.push(HitTestItem {
                 pipeline: pipeline_id,
                 tag: item.tag,
+                point_in_local_space: point_in_layer,
+                item_rect,
             });
+
+            if test.mode == HitTestMode::Topmost && !item.pass_through {
+                break;
+            }
         }
 
-        result.items.dedup();
+        result.items.dedup_by(|a, b| a.pipeline == b.pipeline && a.tag == b.tag);
         result
     }
+
+    /// Find the point of the item within the given part of its local rect
+    /// that is inside the area and not clipped out, preferring the one closest
+    /// to the center of the area. This is approximate: past the nearest point,
+    /// only a grid of `AREA_PROBE_COUNT` points per axis is tested, so it is
+    /// only used when `intersect_area` can't be.
+    fn probe_area(
+        &self,
+        item: &HitTestingItem,
+        area: &HitTestArea,
+        overlap: &LayoutRect,
+        center_in_layer: LayoutPoint,
+    ) -> Option<LayoutPoint> {
+        let transform = &self.spatial_nodes[&item.spatial_node_index].world_content_transform;
+        let is_hit = |point: LayoutPoint| {
+            overlap.contains(point) &&
+            item.shape_contains(point) &&
+            transform.transform_point2d(point).map_or(false, |world_point| {
+                area.contains(world_point) && self.clips_contain(item, world_point)
+            })
+        };
+
+        // The point of the overlap closest to the center of the area. The
+        // rect is half-open, so stay just inside of its far edges.
+        let max = overlap.max - LayoutVector2D::new(0.001, 0.001);
+        let nearest = center_in_layer.max(overlap.min).min(max);
+        if is_hit(nearest) {
+            return Some(nearest);
+        }
+
+        let step = overlap.size() / AREA_PROBE_COUNT as f32;
+        let mut best: Option<(f32, LayoutPoint)> = None;
+        for y in 0 .. AREA_PROBE_COUNT {
+            for x in 0 .. AREA_PROBE_COUNT {
+                let point = overlap.min + LayoutVector2D::new(
+                    (x as f32 + 0.5) * step.width,
+                    (y as f32 + 0.5) * step.height,
+                );
+                let distance = (point - center_in_layer).square_length();
+                if best.map_or(true, |(best_distance, _)| distance < best_distance) && is_hit(point) {
+                    best = Some((distance, point));
+                }
+            }
+        }
+
+        best.map(|(_, point)| point)
+    }
+
+    /// The boundaries in world space of the area, of the given part of the
+    /// local rect of the item, and of the shape and clips of the item, or
+    /// None if the item or any of its clips isn't axis-aligned in world space.
+    fn area_boundaries(
+        &self,
+        item: &HitTestingItem,
+        area: &HitTestArea,
+        overlap: &LayoutRect,
+    ) -> Option<Vec<Boundary>> {
+        let transform = self.scale_offset(item.spatial_node_index)?;
+
+        let mut boundaries = Vec::new();
+        match *area {
+            HitTestArea::Point(..) => {}
+            HitTestArea::Rect(ref rect) => Boundary::add_rect(rect, &mut boundaries),
+            HitTestArea::Circle { center, radius } => {
+                boundaries.push(Boundary::Ellipse(center, WorldVector2D::new(radius, radius)));
+            }
+        }
+        Boundary::add_rect(&transform.map_rect(overlap), &mut boundaries);
+        if let Some(ref shape) = item.shape {
+            Boundary::add_region(shape, &transform, &mut boundaries);
+        }
+        for clip_node in self.clip_nodes(item) {
+            let transform = self.scale_offset(clip_node.spatial_node_index)?;
+            Boundary::add_region(&clip_node.region, &transform, &mut boundaries);
+        }
+
+        Some(boundaries)
+    }
+
+    /// Find the point of the item within the given part of its local rect
+    /// that is inside the area and not clipped out, closest to the center of
+    /// the area, given the boundaries returned by `area_boundaries`.
+    fn intersect_area(
+        &self,
+        item: &HitTestingItem,
+        area: &HitTestArea,
+        overlap: &LayoutRect,
+        boundaries: &[Boundary],
+    ) -> Option<LayoutPoint> {
+        let inverse = self.spatial_nodes[&item.spatial_node_index].world_content_transform.inverse()?;
+        let is_hit = |world_point: WorldPoint| {
+            area.contains(world_point) &&
+            inverse.transform_point2d(world_point).map_or(false, |point| {
+                overlap.contains(point) && item.shape_contains(point)
+            }) &&
+            self.clips_contain(item, world_point)
+        };
+
+        // If the intersection of all the regions isn't empty, its point
+        // closest to the center is either the center itself, or a point of
+        // one of the boundaries that is either locally closest to the center
+        // or where it crosses another boundary. The regions are half-open, so
+        // also try points just off of each of those, on every side.
+        let center = area.center();
+        let mut candidates = vec![BoundaryPoint::new(center, WorldVector2D::zero(), WorldVector2D::zero())];
+        for (i, boundary) in boundaries.iter().enumerate() {
+            boundary.add_closest_points(center, &mut candidates);
+            for other in &boundaries[i + 1 ..] {
+                boundary.add_crossings(other, &mut candidates);
+            }
+        }
+
+        let mut points = Vec::with_capacity(candidates.len() * 9);
+        for candidate in &candidates {
+            candidate.add_nudged_points(&mut points);
+        }
+        points.sort_by(|a, b| {
+            (*a - center).square_length()
+                .partial_cmp(&(*b - center).square_length())
+                .unwrap_or(Ordering::Equal)
+        });
+
+        points
+            .into_iter()
+            .find(|point| is_hit(*point))
+            .and_then(|point| inverse.transform_point2d(point))
+    }
+
+    /// The transform of the spatial node to world space, if it only scales
+    /// and offsets, without collapsing either axis.
+    fn scale_offset(&self, spatial_node_index: SpatialNodeIndex) -> Option<ScaleOffset> {
+        let transform = &self.spatial_nodes[&spatial_node_index].world_content_transform;
+        ScaleOffset::from_transform(&*transform.to_transform())
+            .filter(|scale_offset| scale_offset.scale.x != 0.0 && scale_offset.scale.y != 0.0)
+    }
+
+    fn clip_nodes(&self, item: &HitTestingItem) -> &[HitTestClipNode] {
+        &self.scene.clip_nodes[item.clip_nodes_range.start.0 as usize .. item.clip_nodes_range.end.0 as usize]
+    }
+
+    /// Returns true if none of the clips of the item cull out the given point.
+    fn clips_contain(&self, item: &HitTestingItem, point: WorldPoint) -> bool {
+        let clip_nodes = self.clip_nodes(item);
+        clip_nodes.iter().all(|clip_node| {
+            let transform = self
+                .spatial_nodes[&clip_node.spatial_node_index]
+                .world_content_transform;
+            let transformed_point = match transform
+                .inverse()
+                .and_then(|inverted| inverted.transform_point2d(point))
+            {
+                Some(point) => point,
+                // XXX This `return true` is a bit sketchy, but matches
+                // pre-existing behavior.
+                None => return true,
+            };
+            clip_node.region.contains(&transformed_point)
+        })
+    }
 }
 
-#[derive(MallocSizeOf)]
 pub struct HitTest {
-    point: WorldPoint,
+    area: HitTestArea,
+    mode: HitTestMode,
+    tag_range: Option<ops::RangeInclusive<ItemTag>>,
 }
 
 impl HitTest {
     pub fn new(
-        point: WorldPoint,
+        query: HitTestQuery,
     ) -> HitTest {
         HitTest {
-            point,
+            area: query.area,
+            mode: query.mode,
+            tag_range: query.tag_range,
         }
     }
 }
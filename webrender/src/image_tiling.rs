@@ -2,7 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use crate::api::TileSize;
+use crate::api::{ImageFormat, RepeatMode, TileSize};
 use crate::api::units::*;
 use crate::segment::EdgeAaSegmentMask;
 use euclid::{point2, size2};
@@ -31,6 +31,77 @@ pub fn simplify_repeated_primitive(
     }
 }
 
+/// Resolve the repeat modes of a repeated image into the stretch size, tile
+/// spacing and primitive rect, following the CSS `background-repeat` keywords.
+/// `Round` scales the tiles and `Space` spreads them out so that a whole number
+/// of them fits in the primitive rect, with the given tile spacing as the
+/// minimum spacing. A single spaced tile is centered.
+///
+/// Returns false if no tile fits, in which case nothing should be drawn.
+pub fn apply_repeat_modes(
+    repeat_horizontal: RepeatMode,
+    repeat_vertical: RepeatMode,
+    stretch_size: &mut LayoutSize,
+    tile_spacing: &mut LayoutSize,
+    prim_rect: &mut LayoutRect,
+) -> bool {
+    apply_repeat_mode_1d(
+        repeat_horizontal,
+        &mut stretch_size.width,
+        &mut tile_spacing.width,
+        &mut prim_rect.min.x,
+        &mut prim_rect.max.x,
+    ) && apply_repeat_mode_1d(
+        repeat_vertical,
+        &mut stretch_size.height,
+        &mut tile_spacing.height,
+        &mut prim_rect.min.y,
+        &mut prim_rect.max.y,
+    )
+}
+
+fn apply_repeat_mode_1d(
+    mode: RepeatMode,
+    stretch_size: &mut f32,
+    tile_spacing: &mut f32,
+    prim_start: &mut f32,
+    prim_end: &mut f32,
+) -> bool {
+    let extent = *prim_end - *prim_start;
+    if extent <= 0.0 || *stretch_size <= 0.0 {
+        return true;
+    }
+
+    match mode {
+        RepeatMode::Repeat => {}
+        RepeatMode::Stretch => {
+            *stretch_size = extent;
+            *tile_spacing = 0.0;
+        }
+        RepeatMode::Round => {
+            let stride = *stretch_size + *tile_spacing;
+            let count = ((extent + *tile_spacing) / stride).round().max(1.0);
+            *stretch_size = (extent + *tile_spacing) / count - *tile_spacing;
+        }
+        RepeatMode::Space => {
+            let stride = *stretch_size + *tile_spacing;
+            let count = ((extent + *tile_spacing) / stride).floor();
+            if count < 1.0 {
+                return false;
+            }
+            if count < 2.0 {
+                *prim_start += (extent - *stretch_size) * 0.5;
+                *prim_end = *prim_start + *stretch_size;
+                *tile_spacing = 0.0;
+            } else {
+                *tile_spacing = (extent - count * *stretch_size) / (count - 1.0);
+            }
+        }
+    }
+
+    true
+}
+
 pub struct Repetition {
     pub origin: LayoutPoint,
     pub edge_flags: EdgeAaSegmentMask,
@@ -635,6 +706,41 @@ pub fn compute_valid_tiles_if_bounds_change(
     })
 }
 
+/// Rounds a tile size down to a whole number of the format's blocks, so that
+/// every tile other than the last in each row and column starts on a block.
+pub fn align_tile_size_to_blocks(tile_size: TileSize, format: ImageFormat) -> TileSize {
+    let block_size = format.block_size() as TileSize;
+    (tile_size / block_size * block_size).max(block_size)
+}
+
+/// Expands a rect outwards to the edges of the format's blocks, since
+/// block-compressed data can only be updated a whole block at a time.
+pub fn align_rect_to_blocks(rect: DeviceIntRect, format: ImageFormat) -> DeviceIntRect {
+    let block_size = format.block_size();
+    let round_down = |v: i32| v - v.rem_euclid(block_size);
+    let round_up = |v: i32| round_down(v + block_size - 1);
+    DeviceIntRect::new(
+        point2(round_down(rect.min.x), round_down(rect.min.y)),
+        point2(round_up(rect.max.x), round_up(rect.max.y)),
+    )
+}
+
+/// Computes the offset in bytes of the data of a tile within the data of an
+/// image stored as one large buffer. For block-compressed formats, `stride`
+/// is the distance between rows of blocks.
+pub fn compute_tile_data_offset(
+    format: ImageFormat,
+    stride: i32,
+    regular_tile_size: TileSize,
+    tile: TileOffset,
+) -> i32 {
+    let block_size = format.block_size();
+    let tile_size = regular_tile_size as i32;
+    debug_assert_eq!(tile_size % block_size, 0);
+    tile.y * tile_size / block_size * stride +
+        tile.x * tile_size / block_size * format.bytes_per_block()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -820,4 +926,51 @@ mod tests {
 
         assert_eq!(r, rect(20, 20, 80, 80).to_box2d());
     }
+
+    #[test]
+    fn block_aligned_tiles() {
+        assert_eq!(align_tile_size_to_blocks(17, ImageFormat::RGBA8), 17);
+        assert_eq!(align_tile_size_to_blocks(17, ImageFormat::BC7), 16);
+        assert_eq!(align_tile_size_to_blocks(512, ImageFormat::BC1), 512);
+
+        assert_eq!(
+            align_rect_to_blocks(rect(5, 3, 6, 2).to_box2d(), ImageFormat::BC1),
+            rect(4, 0, 8, 8).to_box2d(),
+        );
+        assert_eq!(
+            align_rect_to_blocks(rect(5, 3, 6, 2).to_box2d(), ImageFormat::R8),
+            rect(5, 3, 6, 2).to_box2d(),
+        );
+
+        // 1000 pixels of BC1 blocks make rows of 2000 bytes, covering 4 rows of pixels.
+        assert_eq!(compute_tile_data_offset(ImageFormat::BC1, 2000, 256, point2(2, 1)), 64 * 2000 + 128 * 8);
+        assert_eq!(compute_tile_data_offset(ImageFormat::RGBA8, 4000, 256, point2(2, 1)), 256 * 4000 + 512 * 4);
+    }
+
+    #[test]
+    fn repeat_modes() {
+        let apply = |mode, stretch, spacing, start, end| {
+            let (mut stretch, mut spacing, mut start, mut end) = (stretch, spacing, start, end);
+            let visible = apply_repeat_mode_1d(mode, &mut stretch, &mut spacing, &mut start, &mut end);
+            (visible, stretch, spacing, start, end)
+        };
+
+        assert_eq!(apply(RepeatMode::Repeat, 30.0, 0.0, 0.0, 100.0), (true, 30.0, 0.0, 0.0, 100.0));
+        assert_eq!(apply(RepeatMode::Stretch, 30.0, 5.0, 0.0, 100.0), (true, 100.0, 0.0, 0.0, 100.0));
+
+        // 100 / 45 rounds to 2 tiles, and 140 / 30 to 5 tiles with spacing.
+        assert_eq!(apply(RepeatMode::Round, 30.0, 0.0, 0.0, 120.0), (true, 30.0, 0.0, 0.0, 120.0));
+        assert_eq!(apply(RepeatMode::Round, 45.0, 0.0, 0.0, 100.0), (true, 50.0, 0.0, 0.0, 100.0));
+        assert_eq!(apply(RepeatMode::Round, 20.0, 10.0, 0.0, 130.0), (true, 18.0, 10.0, 0.0, 130.0));
+        // At least one tile is drawn.
+        assert_eq!(apply(RepeatMode::Round, 300.0, 0.0, 0.0, 100.0), (true, 100.0, 0.0, 0.0, 100.0));
+
+        // Three 30 pixel tiles fit in 100 pixels, leaving two 5 pixel gaps.
+        assert_eq!(apply(RepeatMode::Space, 30.0, 0.0, 0.0, 100.0), (true, 30.0, 5.0, 0.0, 100.0));
+        // The tile spacing is the minimum spacing, so only two fit here.
+        assert_eq!(apply(RepeatMode::Space, 30.0, 6.0, 0.0, 100.0), (true, 30.0, 40.0, 0.0, 100.0));
+        // A single tile is centered, and none are drawn if it doesn't fit.
+        assert_eq!(apply(RepeatMode::Space, 60.0, 0.0, 10.0, 110.0), (true, 60.0, 0.0, 30.0, 90.0));
+        assert_eq!(apply(RepeatMode::Space, 120.0, 0.0, 0.0, 100.0), (false, 120.0, 0.0, 0.0, 100.0));
+    }
 }
@@ -396,6 +396,7 @@ macro_rules! enumerate_interners {
             filter_data: FilterDataIntern,
             backdrop: Backdrop,
             polygon: PolygonIntern,
+            path: Path,
         }
     }
 }
@@ -208,6 +208,10 @@ pub struct PlaneSplitterIndex(pub usize);
 const OPACITY_EPSILON: f32 = 0.001;
 
 /// Equivalent to api::FilterOp with added internal information
+///
+/// Arguments that can be animated are stored along with their value for the
+/// current frame, which is updated when the picture resolves its scene
+/// properties.
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "capture", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
@@ -217,15 +221,17 @@ pub enum Filter {
         width: f32,
         height: f32,
         should_inflate: bool,
+        width_binding: api::PropertyBinding<f32>,
+        height_binding: api::PropertyBinding<f32>,
     },
-    Brightness(f32),
-    Contrast(f32),
-    Grayscale(f32),
-    HueRotate(f32),
-    Invert(f32),
+    Brightness(api::PropertyBinding<f32>, f32),
+    Contrast(api::PropertyBinding<f32>, f32),
+    Grayscale(api::PropertyBinding<f32>, f32),
+    HueRotate(api::PropertyBinding<f32>, f32),
+    Invert(api::PropertyBinding<f32>, f32),
     Opacity(api::PropertyBinding<f32>, f32),
-    Saturate(f32),
-    Sepia(f32),
+    Saturate(api::PropertyBinding<f32>, f32),
+    Sepia(api::PropertyBinding<f32>, f32),
     DropShadows(SmallVec<[Shadow; 1]>),
     ColorMatrix(Box<[f32; 20]>),
     SrgbToLinear,
@@ -235,6 +241,17 @@ pub enum Filter {
 }
 
 impl Filter {
+    /// Construct a blur filter with fixed radii.
+    pub fn blur(width: f32, height: f32, should_inflate: bool) -> Self {
+        Filter::Blur {
+            width,
+            height,
+            should_inflate,
+            width_binding: api::PropertyBinding::Value(width),
+            height_binding: api::PropertyBinding::Value(height),
+        }
+    }
+
     pub fn is_visible(&self) -> bool {
         match *self {
             Filter::Identity |
@@ -261,17 +278,21 @@ impl Filter {
     }
 
     pub fn is_noop(&self) -> bool {
+        use api::PropertyBinding::Value;
+
         match *self {
             Filter::Identity => false, // this is intentional
-            Filter::Blur { width, height, .. } => width == 0.0 && height == 0.0,
-            Filter::Brightness(amount) => amount == 1.0,
-            Filter::Contrast(amount) => amount == 1.0,
-            Filter::Grayscale(amount) => amount == 0.0,
-            Filter::HueRotate(amount) => amount == 0.0,
-            Filter::Invert(amount) => amount == 0.0,
-            Filter::Opacity(api::PropertyBinding::Value(amount), _) => amount >= 1.0,
-            Filter::Saturate(amount) => amount == 1.0,
-            Filter::Sepia(amount) => amount == 0.0,
+            Filter::Blur { width_binding: Value(width), height_binding: Value(height), .. } => {
+                width == 0.0 && height == 0.0
+            }
+            Filter::Brightness(Value(amount), _) => amount == 1.0,
+            Filter::Contrast(Value(amount), _) => amount == 1.0,
+            Filter::Grayscale(Value(amount), _) => amount == 0.0,
+            Filter::HueRotate(Value(amount), _) => amount == 0.0,
+            Filter::Invert(Value(amount), _) => amount == 0.0,
+            Filter::Opacity(Value(amount), _) => amount >= 1.0,
+            Filter::Saturate(Value(amount), _) => amount == 1.0,
+            Filter::Sepia(Value(amount), _) => amount == 0.0,
             Filter::DropShadows(ref shadows) => {
                 for shadow in shadows {
                     if shadow.offset.x != 0.0 || shadow.offset.y != 0.0 || shadow.blur_radius != 0.0 {
@@ -290,7 +311,16 @@ impl Filter {
                     0.0, 0.0, 0.0, 0.0
                 ]
             }
-            Filter::Opacity(api::PropertyBinding::Binding(..), _) |
+            // Animated filters may stop being a no-op at any frame.
+            Filter::Blur { .. } |
+            Filter::Brightness(..) |
+            Filter::Contrast(..) |
+            Filter::Grayscale(..) |
+            Filter::HueRotate(..) |
+            Filter::Invert(..) |
+            Filter::Opacity(..) |
+            Filter::Saturate(..) |
+            Filter::Sepia(..) |
             Filter::SrgbToLinear |
             Filter::LinearToSrgb |
             Filter::ComponentTransfer |
@@ -298,6 +328,30 @@ impl Filter {
         }
     }
 
+    /// Returns the property bindings the arguments of this filter are read
+    /// from, whether they are animated or not.
+    pub fn bindings(&self) -> SmallVec<[api::PropertyBinding<f32>; 2]> {
+        match *self {
+            Filter::Blur { width_binding, height_binding, .. } => {
+                smallvec![width_binding, height_binding]
+            }
+            Filter::Brightness(binding, _) |
+            Filter::Contrast(binding, _) |
+            Filter::Grayscale(binding, _) |
+            Filter::HueRotate(binding, _) |
+            Filter::Invert(binding, _) |
+            Filter::Opacity(binding, _) |
+            Filter::Saturate(binding, _) |
+            Filter::Sepia(binding, _) => smallvec![binding],
+            Filter::Identity |
+            Filter::DropShadows(..) |
+            Filter::ColorMatrix(..) |
+            Filter::SrgbToLinear |
+            Filter::LinearToSrgb |
+            Filter::ComponentTransfer |
+            Filter::Flood(..) => SmallVec::new(),
+        }
+    }
 
     pub fn as_int(&self) -> i32 {
         // Must be kept in sync with brush_blend.glsl
@@ -322,19 +376,34 @@ impl Filter {
     }
 }
 
+/// Returns the value of a property binding before any animated value has
+/// been supplied.
+fn default_value(binding: api::PropertyBinding<f32>) -> f32 {
+    match binding {
+        api::PropertyBinding::Value(value) |
+        api::PropertyBinding::Binding(_, value) => value,
+    }
+}
+
 impl From<FilterOp> for Filter {
     fn from(op: FilterOp) -> Self {
         match op {
             FilterOp::Identity => Filter::Identity,
-            FilterOp::Blur(width, height) => Filter::Blur { width, height, should_inflate: true },
-            FilterOp::Brightness(b) => Filter::Brightness(b),
-            FilterOp::Contrast(c) => Filter::Contrast(c),
-            FilterOp::Grayscale(g) => Filter::Grayscale(g),
-            FilterOp::HueRotate(h) => Filter::HueRotate(h),
-            FilterOp::Invert(i) => Filter::Invert(i),
+            FilterOp::Blur(width, height) => Filter::Blur {
+                width: default_value(width),
+                height: default_value(height),
+                should_inflate: true,
+                width_binding: width,
+                height_binding: height,
+            },
+            FilterOp::Brightness(b) => Filter::Brightness(b, default_value(b)),
+            FilterOp::Contrast(c) => Filter::Contrast(c, default_value(c)),
+            FilterOp::Grayscale(g) => Filter::Grayscale(g, default_value(g)),
+            FilterOp::HueRotate(h) => Filter::HueRotate(h, default_value(h)),
+            FilterOp::Invert(i) => Filter::Invert(i, default_value(i)),
             FilterOp::Opacity(binding, opacity) => Filter::Opacity(binding, opacity),
-            FilterOp::Saturate(s) => Filter::Saturate(s),
-            FilterOp::Sepia(s) => Filter::Sepia(s),
+            FilterOp::Saturate(s) => Filter::Saturate(s, default_value(s)),
+            FilterOp::Sepia(s) => Filter::Sepia(s, default_value(s)),
             FilterOp::ColorMatrix(mat) => Filter::ColorMatrix(Box::new(mat)),
             FilterOp::SrgbToLinear => Filter::SrgbToLinear,
             FilterOp::LinearToSrgb => Filter::LinearToSrgb,
@@ -75,15 +75,18 @@ extern crate svg_fmt;
 #[macro_use]
 mod profiler;
 
+mod animation;
 mod batch;
 mod border;
 mod box_shadow;
 #[cfg(any(feature = "capture", feature = "replay"))]
 mod capture;
 mod clip;
+mod color_space;
 mod space;
 mod spatial_tree;
 mod composite;
+mod compressed_texture;
 mod compositor;
 mod debug_colors;
 mod debug_font_data;
@@ -99,9 +102,11 @@ mod glyph_cache;
 mod glyph_rasterizer;
 mod gpu_cache;
 mod gpu_types;
+mod high_bit_depth;
 mod hit_test;
 mod internal_types;
 mod lru_cache;
+mod path;
 mod picture;
 mod picture_graph;
 mod prepare;
@@ -118,6 +123,7 @@ mod scene;
 mod scene_builder_thread;
 mod scene_building;
 mod screen_capture;
+mod scroll_physics;
 mod segment;
 mod spatial_node;
 mod surface;
@@ -126,6 +132,8 @@ mod texture_cache;
 mod tile_cache;
 mod util;
 mod visibility;
+#[cfg(feature = "capture")]
+mod yaml_frame_writer;
 mod api_resources;
 mod image_tiling;
 mod image_source;
@@ -206,7 +214,7 @@ pub use crate::composite::{MappableCompositor, MappedTileInfo, SWGLCompositeSurf
 pub use crate::device::{UploadMethod, VertexUsageHint, get_gl_target, get_unoptimized_shader_source};
 pub use crate::device::{ProgramBinary, ProgramCache, ProgramCacheObserver, FormatDesc};
 pub use crate::device::Device;
-pub use crate::frame_builder::ChasePrimitive;
+pub use crate::frame_builder::{ChasePrimitive, ToneMapping};
 pub use crate::prim_store::PrimitiveDebugId;
 pub use crate::profiler::{ProfilerHooks, set_profiler_hooks};
 pub use crate::renderer::{
@@ -0,0 +1,557 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Flattening and stroking of vector paths.
+//!
+//! Paths are rasterized by the `cs_path` shader, which computes the winding
+//! number of each sample against a flat list of line edges. This module turns
+//! the curves of a path into those edges. Strokes are converted into a set of
+//! consistently oriented convex polygons (one per segment, join and cap), so
+//! that filling them with the nonzero rule produces their union.
+
+use api::{LineCap, LineJoin, PathSegment, StrokeStyle};
+use api::units::*;
+use crate::renderer::MAX_VERTEX_TEXTURE_WIDTH;
+use std::f32::consts::PI;
+
+/// A line edge, stored as `[x0, y0, x1, y1]`.
+pub type PathEdge = [f32; 4];
+
+/// The maximum number of edges of a path. Edges are stored one per GPU cache
+/// block, and a GPU cache allocation can't span more than a single row.
+pub const MAX_PATH_EDGES: usize = MAX_VERTEX_TEXTURE_WIDTH;
+
+/// The maximum distance, in device pixels, between a curve and the line
+/// segments approximating it.
+const PATH_TOLERANCE: f32 = 0.25;
+
+/// The number of times the tolerance is doubled when a path has too many
+/// edges, before giving up and dropping the extra contours.
+const MAX_TOLERANCE_STEPS: usize = 6;
+
+const MAX_CURVE_SUBDIVISIONS: usize = 64;
+const MAX_CIRCLE_SEGMENTS: usize = 64;
+
+/// A flattened sub-path.
+#[derive(Debug)]
+struct Polyline {
+    points: Vec<LayoutPoint>,
+    closed: bool,
+}
+
+/// Build the edges of a path, in the local space of the path scaled by
+/// `scale`. The edges of a stroked path must be filled with the nonzero rule.
+pub fn build_path_edges(
+    segments: &[PathSegment],
+    stroke: Option<&StrokeStyle>,
+    scale: (f32, f32),
+) -> Vec<PathEdge> {
+    let mut tolerance = PATH_TOLERANCE / scale.0.max(scale.1).max(0.001);
+    let mut steps = 0;
+
+    loop {
+        let polylines = flatten_path(segments, tolerance);
+        let mut builder = EdgeBuilder::new(scale);
+
+        match stroke {
+            Some(stroke) => stroke_polylines(&polylines, stroke, tolerance, &mut builder),
+            None => {
+                for polyline in &polylines {
+                    builder.add_polygon(&polyline.points);
+                }
+            }
+        }
+
+        if builder.edges.len() <= MAX_PATH_EDGES {
+            return builder.edges;
+        }
+
+        steps += 1;
+        if steps > MAX_TOLERANCE_STEPS {
+            warn!("Path has more than {} edges, dropping the extra contours", MAX_PATH_EDGES);
+            return builder.truncate(MAX_PATH_EDGES);
+        }
+
+        tolerance *= 2.0;
+    }
+}
+
+/// Accumulates closed contours as edges, keeping track of where each contour
+/// starts so that whole contours can be dropped.
+struct EdgeBuilder {
+    edges: Vec<PathEdge>,
+    contour_starts: Vec<usize>,
+    scale: (f32, f32),
+}
+
+impl EdgeBuilder {
+    fn new(scale: (f32, f32)) -> Self {
+        EdgeBuilder {
+            edges: Vec::new(),
+            contour_starts: Vec::new(),
+            scale,
+        }
+    }
+
+    /// Add a closed contour. The contour is closed implicitly.
+    fn add_polygon(&mut self, points: &[LayoutPoint]) {
+        if points.len() < 3 {
+            return;
+        }
+
+        self.contour_starts.push(self.edges.len());
+        let mut prev = points[points.len() - 1];
+        for &point in points {
+            if prev != point {
+                self.edges.push([
+                    prev.x * self.scale.0,
+                    prev.y * self.scale.1,
+                    point.x * self.scale.0,
+                    point.y * self.scale.1,
+                ]);
+            }
+            prev = point;
+        }
+    }
+
+    /// Add a convex polygon, oriented so that its winding number is positive.
+    fn add_convex_polygon(&mut self, points: &mut [LayoutPoint]) {
+        let area = signed_area(points);
+        if area.abs() < 1.0e-6 {
+            return;
+        }
+        // Edges going down add to the winding number of the points to their
+        // right, so in y-down space the polygon must be counter-clockwise.
+        if area > 0.0 {
+            points.reverse();
+        }
+        self.add_polygon(points);
+    }
+
+    fn add_circle(&mut self, center: LayoutPoint, radius: f32, tolerance: f32) {
+        let count = circle_segment_count(radius, tolerance);
+        let mut points: Vec<LayoutPoint> = (0 .. count)
+            .map(|i| {
+                let angle = 2.0 * PI * i as f32 / count as f32;
+                center + LayoutVector2D::new(angle.cos(), angle.sin()) * radius
+            })
+            .collect();
+        self.add_convex_polygon(&mut points);
+    }
+
+    fn truncate(mut self, max_edges: usize) -> Vec<PathEdge> {
+        let end = self.contour_starts
+            .iter()
+            .rev()
+            .find(|&&start| start <= max_edges)
+            .cloned()
+            .unwrap_or(0);
+        self.edges.truncate(end);
+        self.edges
+    }
+}
+
+fn signed_area(points: &[LayoutPoint]) -> f32 {
+    let mut area = 0.0;
+    let mut prev = points[points.len() - 1];
+    for &point in points {
+        area += prev.x * point.y - point.x * prev.y;
+        prev = point;
+    }
+    area * 0.5
+}
+
+fn circle_segment_count(radius: f32, tolerance: f32) -> usize {
+    if radius <= tolerance {
+        return 4;
+    }
+    let step = 2.0 * (1.0 - tolerance / radius).acos();
+    ((2.0 * PI / step).ceil() as usize).max(4).min(MAX_CIRCLE_SEGMENTS)
+}
+
+/// Convert the segments of a path into polylines, approximating curves with
+/// line segments no further than `tolerance` from the curve.
+fn flatten_path(segments: &[PathSegment], tolerance: f32) -> Vec<Polyline> {
+    let mut polylines = Vec::new();
+    let mut current: Option<Polyline> = None;
+    let mut subpath_start = LayoutPoint::zero();
+    let mut last = LayoutPoint::zero();
+
+    fn push_point(current: &mut Option<Polyline>, from: LayoutPoint, to: LayoutPoint) {
+        let polyline = current.get_or_insert_with(|| Polyline {
+            points: vec![from],
+            closed: false,
+        });
+        if polyline.points.last() != Some(&to) {
+            polyline.points.push(to);
+        }
+    }
+
+    for segment in segments {
+        match *segment {
+            PathSegment::MoveTo(to) => {
+                polylines.extend(current.take());
+                subpath_start = to;
+                last = to;
+            }
+            PathSegment::LineTo(to) => {
+                push_point(&mut current, last, to);
+                last = to;
+            }
+            PathSegment::QuadraticTo(ctrl, to) => {
+                let dd = (last.to_vector() - ctrl.to_vector() * 2.0 + to.to_vector()).length();
+                let count = subdivision_count(dd / (4.0 * tolerance));
+                for i in 1 ..= count {
+                    let t = i as f32 / count as f32;
+                    let mt = 1.0 - t;
+                    let point = (
+                        last.to_vector() * (mt * mt) +
+                        ctrl.to_vector() * (2.0 * mt * t) +
+                        to.to_vector() * (t * t)
+                    ).to_point();
+                    push_point(&mut current, last, point);
+                }
+                last = to;
+            }
+            PathSegment::CubicTo(ctrl1, ctrl2, to) => {
+                let dd1 = (last.to_vector() - ctrl1.to_vector() * 2.0 + ctrl2.to_vector()).length();
+                let dd2 = (ctrl1.to_vector() - ctrl2.to_vector() * 2.0 + to.to_vector()).length();
+                let count = subdivision_count(3.0 * dd1.max(dd2) / (4.0 * tolerance));
+                let from = last;
+                for i in 1 ..= count {
+                    let t = i as f32 / count as f32;
+                    let mt = 1.0 - t;
+                    let point = (
+                        from.to_vector() * (mt * mt * mt) +
+                        ctrl1.to_vector() * (3.0 * mt * mt * t) +
+                        ctrl2.to_vector() * (3.0 * mt * t * t) +
+                        to.to_vector() * (t * t * t)
+                    ).to_point();
+                    push_point(&mut current, from, point);
+                }
+                last = to;
+            }
+            PathSegment::Close => {
+                if let Some(mut polyline) = current.take() {
+                    if polyline.points.len() > 1 && polyline.points.last() == Some(&subpath_start) {
+                        polyline.points.pop();
+                    }
+                    polyline.closed = true;
+                    polylines.push(polyline);
+                }
+                last = subpath_start;
+            }
+        }
+    }
+
+    polylines.extend(current.take());
+    polylines
+}
+
+fn subdivision_count(squared: f32) -> usize {
+    (squared.sqrt().ceil() as usize).max(1).min(MAX_CURVE_SUBDIVISIONS)
+}
+
+/// Split polylines into the dashes described by the stroke style.
+fn dash_polylines(polylines: &[Polyline], stroke: &StrokeStyle) -> Vec<Polyline> {
+    let dashes = stroke.dashes();
+    let period: f32 = dashes.iter().sum();
+    if period <= 0.0 || dashes.iter().any(|dash| *dash < 0.0) {
+        return Vec::new();
+    }
+
+    let mut result = Vec::new();
+    for polyline in polylines {
+        // Find where in the dash pattern the polyline starts.
+        let mut index = 0;
+        let mut remaining = dashes[0];
+        let mut offset = stroke.dash_offset % period;
+        if offset < 0.0 {
+            offset += period;
+        }
+        while offset > 0.0 {
+            if offset < remaining {
+                remaining -= offset;
+                break;
+            }
+            offset -= remaining;
+            index = (index + 1) % dashes.len();
+            remaining = dashes[index];
+        }
+
+        let mut points = polyline.points.clone();
+        if polyline.closed {
+            points.push(points[0]);
+        }
+
+        let mut current = if index % 2 == 0 { Some(vec![points[0]]) } else { None };
+        for pair in points.windows(2) {
+            let (mut from, to) = (pair[0], pair[1]);
+            let mut length = (to - from).length();
+            while length > 0.0 {
+                let step = remaining.min(length);
+                let point = from + (to - from) * (step / length);
+                if let Some(ref mut dash) = current {
+                    dash.push(point);
+                }
+                from = point;
+                length -= step;
+                remaining -= step;
+
+                if remaining <= 0.0 {
+                    index = (index + 1) % dashes.len();
+                    remaining = dashes[index];
+                    if index % 2 == 0 {
+                        current = Some(vec![from]);
+                    } else if let Some(dash) = current.take() {
+                        result.push(Polyline { points: dash, closed: false });
+                    }
+                }
+            }
+        }
+        if let Some(dash) = current {
+            if dash.len() > 1 {
+                result.push(Polyline { points: dash, closed: false });
+            }
+        }
+    }
+
+    result
+}
+
+/// Emit the polygons covering the stroke of the given polylines.
+fn stroke_polylines(
+    polylines: &[Polyline],
+    stroke: &StrokeStyle,
+    tolerance: f32,
+    builder: &mut EdgeBuilder,
+) {
+    if stroke.width <= 0.0 {
+        return;
+    }
+
+    let dashed;
+    let polylines = if stroke.dashes().is_empty() {
+        polylines
+    } else {
+        dashed = dash_polylines(polylines, stroke);
+        &dashed[..]
+    };
+
+    let half_width = stroke.width * 0.5;
+
+    for polyline in polylines {
+        let mut points = polyline.points.clone();
+        points.dedup();
+        if polyline.closed && points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+
+        // Zero length sub-paths only get caps.
+        if points.len() < 2 {
+            if let Some(&point) = points.first() {
+                match stroke.line_cap {
+                    LineCap::Butt => {}
+                    LineCap::Round => builder.add_circle(point, half_width, tolerance),
+                    LineCap::Square => {
+                        let half = LayoutVector2D::new(half_width, half_width);
+                        let mut square = [
+                            point - half,
+                            point + LayoutVector2D::new(half_width, -half_width),
+                            point + half,
+                            point + LayoutVector2D::new(-half_width, half_width),
+                        ];
+                        builder.add_convex_polygon(&mut square);
+                    }
+                }
+            }
+            continue;
+        }
+
+        let segment_count = if polyline.closed { points.len() } else { points.len() - 1 };
+        let direction = |i: usize| {
+            let from = points[i % points.len()];
+            let to = points[(i + 1) % points.len()];
+            (to - from).normalize()
+        };
+
+        for i in 0 .. segment_count {
+            let from = points[i];
+            let to = points[(i + 1) % points.len()];
+            let dir = direction(i);
+            let normal = LayoutVector2D::new(-dir.y, dir.x) * half_width;
+            let mut quad = [from + normal, to + normal, to - normal, from - normal];
+            builder.add_convex_polygon(&mut quad);
+
+            // Join with the next segment.
+            if i + 1 < segment_count || polyline.closed {
+                add_join(to, dir, direction(i + 1), stroke, half_width, tolerance, builder);
+            }
+        }
+
+        if !polyline.closed {
+            let first_dir = direction(0);
+            let last_dir = direction(points.len() - 2);
+            add_cap(points[0], -first_dir, stroke.line_cap, half_width, tolerance, builder);
+            add_cap(points[points.len() - 1], last_dir, stroke.line_cap, half_width, tolerance, builder);
+        }
+    }
+}
+
+fn add_join(
+    point: LayoutPoint,
+    dir0: LayoutVector2D,
+    dir1: LayoutVector2D,
+    stroke: &StrokeStyle,
+    half_width: f32,
+    tolerance: f32,
+    builder: &mut EdgeBuilder,
+) {
+    let cross = dir0.cross(dir1);
+    let dot = dir0.dot(dir1);
+    if cross.abs() < 1.0e-6 && dot > 0.0 {
+        return;
+    }
+
+    // The outer side of the join is opposite to the direction of the turn.
+    let side = if cross > 0.0 { -half_width } else { half_width };
+    let outer0 = point + LayoutVector2D::new(-dir0.y, dir0.x) * side;
+    let outer1 = point + LayoutVector2D::new(-dir1.y, dir1.x) * side;
+
+    match stroke.line_join {
+        LineJoin::Round => {
+            builder.add_circle(point, half_width, tolerance);
+        }
+        LineJoin::Miter => {
+            // The ratio of the miter length to the stroke width.
+            let ratio = 1.0 / ((1.0 + dot) * 0.5).max(0.0).sqrt();
+            if ratio <= stroke.miter_limit {
+                let bisector = ((outer0 - point) + (outer1 - point)).normalize();
+                let tip = point + bisector * (half_width * ratio);
+                builder.add_convex_polygon(&mut [point, outer0, tip, outer1]);
+            } else {
+                builder.add_convex_polygon(&mut [point, outer0, outer1]);
+            }
+        }
+        LineJoin::Bevel => {
+            builder.add_convex_polygon(&mut [point, outer0, outer1]);
+        }
+    }
+}
+
+fn add_cap(
+    point: LayoutPoint,
+    dir: LayoutVector2D,
+    cap: LineCap,
+    half_width: f32,
+    tolerance: f32,
+    builder: &mut EdgeBuilder,
+) {
+    match cap {
+        LineCap::Butt => {}
+        LineCap::Round => {
+            builder.add_circle(point, half_width, tolerance);
+        }
+        LineCap::Square => {
+            let normal = LayoutVector2D::new(-dir.y, dir.x) * half_width;
+            let extent = dir * half_width;
+            builder.add_convex_polygon(&mut [
+                point + normal,
+                point + normal + extent,
+                point - normal + extent,
+                point - normal,
+            ]);
+        }
+    }
+}
+
+#[cfg(test)]
+fn winding_number(edges: &[PathEdge], x: f32, y: f32) -> i32 {
+    let mut winding = 0;
+    for edge in edges {
+        let (y0, y1) = (edge[1], edge[3]);
+        if (y0 <= y) != (y1 <= y) {
+            let t = (y - y0) / (y1 - y0);
+            let cross_x = edge[0] + (edge[2] - edge[0]) * t;
+            if cross_x <= x {
+                winding += if y1 > y0 { 1 } else { -1 };
+            }
+        }
+    }
+    winding
+}
+
+#[test]
+fn fill_rect() {
+    let segments = [
+        PathSegment::MoveTo(LayoutPoint::new(10.0, 10.0)),
+        PathSegment::LineTo(LayoutPoint::new(50.0, 10.0)),
+        PathSegment::LineTo(LayoutPoint::new(50.0, 30.0)),
+        PathSegment::LineTo(LayoutPoint::new(10.0, 30.0)),
+    ];
+    let edges = build_path_edges(&segments, None, (2.0, 2.0));
+
+    // The sub-path is closed implicitly.
+    assert_eq!(edges.len(), 4);
+    assert_ne!(winding_number(&edges, 60.0, 40.0), 0);
+    assert_eq!(winding_number(&edges, 10.0, 40.0), 0);
+    assert_eq!(winding_number(&edges, 110.0, 40.0), 0);
+}
+
+#[test]
+fn flatten_curves() {
+    let segments = [
+        PathSegment::MoveTo(LayoutPoint::new(0.0, 0.0)),
+        PathSegment::QuadraticTo(LayoutPoint::new(50.0, 100.0), LayoutPoint::new(100.0, 0.0)),
+        PathSegment::CubicTo(
+            LayoutPoint::new(100.0, -100.0),
+            LayoutPoint::new(0.0, -100.0),
+            LayoutPoint::new(0.0, 0.0),
+        ),
+        PathSegment::Close,
+    ];
+    let polylines = flatten_path(&segments, 0.25);
+    assert_eq!(polylines.len(), 1);
+    assert!(polylines[0].closed);
+    assert!(polylines[0].points.len() > 8);
+
+    // Every flattened point of the quadratic lies on the curve's hull.
+    for point in &polylines[0].points {
+        assert!(point.y <= 50.0 && point.y >= -75.0);
+    }
+}
+
+#[test]
+fn stroke_is_union() {
+    // A self-overlapping stroke must not cancel itself out.
+    let segments = [
+        PathSegment::MoveTo(LayoutPoint::new(0.0, 50.0)),
+        PathSegment::LineTo(LayoutPoint::new(100.0, 50.0)),
+        PathSegment::LineTo(LayoutPoint::new(50.0, 0.0)),
+        PathSegment::LineTo(LayoutPoint::new(50.0, 100.0)),
+    ];
+    let stroke = StrokeStyle::new(10.0, LineJoin::Round, LineCap::Butt);
+    let edges = build_path_edges(&segments, Some(&stroke), (1.0, 1.0));
+
+    assert!(winding_number(&edges, 50.0, 50.0) > 0);
+    assert!(winding_number(&edges, 20.0, 52.0) > 0);
+    assert_eq!(winding_number(&edges, 20.0, 60.0), 0);
+    assert_eq!(winding_number(&edges, -2.0, 50.0), 0);
+}
+
+#[test]
+fn stroke_dashes() {
+    let segments = [
+        PathSegment::MoveTo(LayoutPoint::new(0.0, 10.0)),
+        PathSegment::LineTo(LayoutPoint::new(100.0, 10.0)),
+    ];
+    let stroke = StrokeStyle::new(4.0, LineJoin::Miter, LineCap::Butt)
+        .with_dashes(&[10.0], 0.0);
+    let edges = build_path_edges(&segments, Some(&stroke), (1.0, 1.0));
+
+    // Five dashes of four edges each.
+    assert_eq!(edges.len(), 20);
+    assert!(winding_number(&edges, 5.0, 10.0) > 0);
+    assert_eq!(winding_number(&edges, 15.0, 10.0), 0);
+}
@@ -45,7 +45,7 @@
 //! - primitives
 //! - clips
 //! - image keys
-//! - opacity bindings
+//! - float bindings (opacity and other animated filter arguments)
 //! - transforms
 //!
 //! These dependency lists are built each frame and compared to the previous frame to
@@ -94,9 +94,9 @@
 //! blend the overlay tile (this is not always optimal right now, but will be
 //! improved as a follow up).
 
-use api::{MixBlendMode, PremultipliedColorF, FilterPrimitiveKind};
+use api::{MixBlendMode, PremultipliedColorF, FilterPrimitiveKind, MorphologyOperator};
 use api::{PropertyBinding, PropertyBindingId, FilterPrimitive, RasterSpace};
-use api::{DebugFlags, ImageKey, ColorF, ColorU, PrimitiveFlags};
+use api::{DebugFlags, ImageKey, ColorF, ColorU, PrimitiveFlags, ComplexClipRegion};
 use api::{ImageRendering, ColorDepth, YuvRangedColorSpace, YuvFormat, AlphaType};
 use api::units::*;
 use crate::box_shadow::BLUR_SAMPLE_SCALE;
@@ -116,7 +116,7 @@ use crate::frame_builder::{FrameBuildingContext, FrameBuildingState, PictureStat
 use crate::gpu_cache::{GpuCache, GpuCacheAddress, GpuCacheHandle};
 use crate::gpu_types::{UvRectKind, ZBufferId};
 use plane_split::{Clipper, Polygon, Splitter};
-use crate::prim_store::{PrimitiveTemplateKind, PictureIndex, PrimitiveInstance, PrimitiveInstanceKind};
+use crate::prim_store::{PrimitiveTemplateKind, resolve_rectangle_color, PictureIndex, PrimitiveInstance, PrimitiveInstanceKind};
 use crate::prim_store::{ColorBindingStorage, ColorBindingIndex, PrimitiveScratchBuffer};
 use crate::print_tree::{PrintTree, PrintTreePrinter};
 use crate::render_backend::DataStores;
@@ -333,12 +333,15 @@ impl<T> From<PropertyBinding<T>> for Binding<T> {
     }
 }
 
-pub type OpacityBinding = Binding<f32>;
-pub type OpacityBindingInfo = BindingInfo<f32>;
+pub type FloatBinding = Binding<f32>;
+pub type FloatBindingInfo = BindingInfo<f32>;
 
 pub type ColorBinding = Binding<ColorU>;
 pub type ColorBindingInfo = BindingInfo<ColorU>;
 
+pub type ClipRegionBinding = Binding<ComplexClipRegion>;
+pub type ClipRegionBindingInfo = BindingInfo<ComplexClipRegion>;
+
 /// A dependency for a transform is defined by the spatial node index + frame it was used
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 #[cfg_attr(feature = "capture", derive(Serialize))]
@@ -471,12 +474,15 @@ struct TilePostUpdateContext<'a> {
     /// The calculated backdrop information for this cache instance.
     backdrop: Option<BackdropInfo>,
 
-    /// Information about opacity bindings from the picture cache.
-    opacity_bindings: &'a FastHashMap<PropertyBindingId, OpacityBindingInfo>,
+    /// Information about float bindings from the picture cache.
+    float_bindings: &'a FastHashMap<PropertyBindingId, FloatBindingInfo>,
 
     /// Information about color bindings from the picture cache.
     color_bindings: &'a FastHashMap<PropertyBindingId, ColorBindingInfo>,
 
+    /// Information about clip region bindings from the picture cache.
+    clip_region_bindings: &'a FastHashMap<PropertyBindingId, ClipRegionBindingInfo>,
+
     /// Current size in device pixels of tiles for this cache
     current_tile_size: DeviceIntSize,
 
@@ -517,11 +523,14 @@ struct PrimitiveDependencyInfo {
     /// Image keys this primitive depends on.
     images: SmallVec<[ImageDependency; 8]>,
 
-    /// Opacity bindings this primitive depends on.
-    opacity_bindings: SmallVec<[OpacityBinding; 4]>,
+    /// Float bindings, such as opacities, this primitive depends on.
+    float_bindings: SmallVec<[FloatBinding; 4]>,
 
-    /// Color binding this primitive depends on.
-    color_binding: Option<ColorBinding>,
+    /// Color bindings this primitive depends on.
+    color_bindings: SmallVec<[ColorBinding; 4]>,
+
+    /// Animated clip regions this primitive depends on.
+    clip_region_bindings: SmallVec<[ClipRegionBinding; 2]>,
 
     /// Clips that this primitive depends on.
     clips: SmallVec<[ItemUid; 8]>,
@@ -539,8 +548,9 @@ impl PrimitiveDependencyInfo {
         PrimitiveDependencyInfo {
             prim_uid,
             images: SmallVec::new(),
-            opacity_bindings: SmallVec::new(),
-            color_binding: None,
+            float_bindings: SmallVec::new(),
+            color_bindings: SmallVec::new(),
+            clip_region_bindings: SmallVec::new(),
             prim_clip_box,
             clips: SmallVec::new(),
             spatial_nodes: SmallVec::new(),
@@ -694,10 +704,12 @@ pub enum PrimitiveCompareResult {
     Transform,
     /// An image dependency was dirty
     Image,
-    /// The value of an opacity binding changed
-    OpacityBinding,
+    /// The value of a float binding changed
+    FloatBinding,
     /// The value of a color binding changed
     ColorBinding,
+    /// The value of an animated clip region changed
+    ClipRegionBinding,
 }
 
 /// Debugging information about why a tile was invalidated
@@ -830,8 +842,9 @@ impl Tile {
             &self.current_descriptor,
             state.resource_cache,
             state.spatial_node_comparer,
-            ctx.opacity_bindings,
+            ctx.float_bindings,
             ctx.color_bindings,
+            ctx.clip_region_bindings,
         );
 
         let mut dirty_rect = PictureBox2D::zero();
@@ -982,8 +995,8 @@ impl Tile {
         // Include any image keys this tile depends on.
         self.current_descriptor.images.extend_from_slice(&info.images);
 
-        // Include any opacity bindings this primitive depends on.
-        self.current_descriptor.opacity_bindings.extend_from_slice(&info.opacity_bindings);
+        // Include any float bindings this primitive depends on.
+        self.current_descriptor.float_bindings.extend_from_slice(&info.float_bindings);
 
         // Include any clip nodes that this primitive depends on.
         self.current_descriptor.clips.extend_from_slice(&info.clips);
@@ -999,10 +1012,10 @@ impl Tile {
         }
 
         // Include any color bindings this primitive depends on.
-        if info.color_binding.is_some() {
-            self.current_descriptor.color_bindings.insert(
-                self.current_descriptor.color_bindings.len(), info.color_binding.unwrap());
-        }
+        self.current_descriptor.color_bindings.extend_from_slice(&info.color_bindings);
+
+        // Include any animated clip regions this primitive depends on.
+        self.current_descriptor.clip_region_bindings.extend_from_slice(&info.clip_region_bindings);
 
         // TODO(gw): The prim_clip_rect can be impacted by the clip rect of the display port,
         //           which can cause invalidations when a new display list with changed
@@ -1039,7 +1052,9 @@ impl Tile {
         debug_assert!(info.spatial_nodes.len() <= MAX_PRIM_SUB_DEPS);
         debug_assert!(info.clips.len() <= MAX_PRIM_SUB_DEPS);
         debug_assert!(info.images.len() <= MAX_PRIM_SUB_DEPS);
-        debug_assert!(info.opacity_bindings.len() <= MAX_PRIM_SUB_DEPS);
+        debug_assert!(info.float_bindings.len() <= MAX_PRIM_SUB_DEPS);
+        debug_assert!(info.color_bindings.len() <= MAX_PRIM_SUB_DEPS);
+        debug_assert!(info.clip_region_bindings.len() <= MAX_PRIM_SUB_DEPS);
 
         self.current_descriptor.prims.push(PrimitiveDescriptor {
             prim_uid: info.prim_uid,
@@ -1047,8 +1062,9 @@ impl Tile {
             transform_dep_count: info.spatial_nodes.len()  as u8,
             clip_dep_count: info.clips.len() as u8,
             image_dep_count: info.images.len() as u8,
-            opacity_binding_dep_count: info.opacity_bindings.len() as u8,
-            color_binding_dep_count: if info.color_binding.is_some() { 1 } else { 0 } as u8,
+            float_binding_dep_count: info.float_bindings.len() as u8,
+            color_binding_dep_count: info.color_bindings.len() as u8,
+            clip_region_binding_dep_count: info.clip_region_bindings.len() as u8,
         });
 
         // Add this primitive to the dirty rect quadtree.
@@ -1293,9 +1309,10 @@ pub struct PrimitiveDescriptor {
     /// The number of extra dependencies that this primitive has.
     transform_dep_count: u8,
     image_dep_count: u8,
-    opacity_binding_dep_count: u8,
+    float_binding_dep_count: u8,
     clip_dep_count: u8,
     color_binding_dep_count: u8,
+    clip_region_binding_dep_count: u8,
 }
 
 impl PartialEq for PrimitiveDescriptor {
@@ -1416,9 +1433,9 @@ pub struct TileDescriptor {
     /// List of image keys that this tile depends on.
     images: Vec<ImageDependency>,
 
-    /// The set of opacity bindings that this tile depends on.
-    // TODO(gw): Ugh, get rid of all opacity binding support!
-    opacity_bindings: Vec<OpacityBinding>,
+    /// The set of float bindings, such as animated opacities and filter
+    /// arguments, that this tile depends on.
+    float_bindings: Vec<FloatBinding>,
 
     /// List of the effects of transforms that we care about
     /// tracking for this tile.
@@ -1430,6 +1447,10 @@ pub struct TileDescriptor {
     /// List of the effects of color that we care about
     /// tracking for this tile.
     color_bindings: Vec<ColorBinding>,
+
+    /// List of the animated clip regions that we care about
+    /// tracking for this tile.
+    clip_region_bindings: Vec<ClipRegionBinding>,
 }
 
 impl TileDescriptor {
@@ -1437,11 +1458,12 @@ impl TileDescriptor {
         TileDescriptor {
             prims: Vec::new(),
             clips: Vec::new(),
-            opacity_bindings: Vec::new(),
+            float_bindings: Vec::new(),
             images: Vec::new(),
             transforms: Vec::new(),
             local_valid_rect: PictureRect::zero(),
             color_bindings: Vec::new(),
+            clip_region_bindings: Vec::new(),
         }
     }
 
@@ -1458,12 +1480,13 @@ impl TileDescriptor {
                 prim.prim_clip_box.max.x,
                 prim.prim_clip_box.max.y,
             ));
-            pt.add_item(format!("deps: t={} i={} o={} c={} color={}",
+            pt.add_item(format!("deps: t={} i={} f={} c={} color={} clip_region={}",
                 prim.transform_dep_count,
                 prim.image_dep_count,
-                prim.opacity_binding_dep_count,
+                prim.float_binding_dep_count,
                 prim.clip_dep_count,
                 prim.color_binding_dep_count,
+                prim.clip_region_binding_dep_count,
             ));
             pt.end_level();
         }
@@ -1488,10 +1511,10 @@ impl TileDescriptor {
             pt.end_level();
         }
 
-        if !self.opacity_bindings.is_empty() {
-            pt.new_level("opacity_bindings".to_string());
-            for opacity_binding in &self.opacity_bindings {
-                pt.new_level(format!("binding={:?}", opacity_binding));
+        if !self.float_bindings.is_empty() {
+            pt.new_level("float_bindings".to_string());
+            for float_binding in &self.float_bindings {
+                pt.new_level(format!("binding={:?}", float_binding));
                 pt.end_level();
             }
             pt.end_level();
@@ -1515,6 +1538,15 @@ impl TileDescriptor {
             pt.end_level();
         }
 
+        if !self.clip_region_bindings.is_empty() {
+            pt.new_level("clip_region_bindings".to_string());
+            for clip_region_binding in &self.clip_region_bindings {
+                pt.new_level(format!("binding={:?}", clip_region_binding));
+                pt.end_level();
+            }
+            pt.end_level();
+        }
+
         pt.end_level();
     }
 
@@ -1523,11 +1555,12 @@ impl TileDescriptor {
     fn clear(&mut self) {
         self.prims.clear();
         self.clips.clear();
-        self.opacity_bindings.clear();
+        self.float_bindings.clear();
         self.images.clear();
         self.transforms.clear();
         self.local_valid_rect = PictureRect::zero();
         self.color_bindings.clear();
+        self.clip_region_bindings.clear();
     }
 }
 
@@ -1810,11 +1843,11 @@ pub struct TileCacheInstance {
     pub sub_slices: Vec<SubSlice>,
     /// The positioning node for this tile cache.
     pub spatial_node_index: SpatialNodeIndex,
-    /// List of opacity bindings, with some extra information
+    /// List of float bindings, with some extra information
     /// about whether they changed since last frame.
-    opacity_bindings: FastHashMap<PropertyBindingId, OpacityBindingInfo>,
+    float_bindings: FastHashMap<PropertyBindingId, FloatBindingInfo>,
     /// Switch back and forth between old and new bindings hashmaps to avoid re-allocating.
-    old_opacity_bindings: FastHashMap<PropertyBindingId, OpacityBindingInfo>,
+    old_float_bindings: FastHashMap<PropertyBindingId, FloatBindingInfo>,
     /// A helper to compare transforms between previous and current frame.
     spatial_node_comparer: SpatialNodeComparer,
     /// List of color bindings, with some extra information
@@ -1822,6 +1855,11 @@ pub struct TileCacheInstance {
     color_bindings: FastHashMap<PropertyBindingId, ColorBindingInfo>,
     /// Switch back and forth between old and new bindings hashmaps to avoid re-allocating.
     old_color_bindings: FastHashMap<PropertyBindingId, ColorBindingInfo>,
+    /// List of animated clip regions, with some extra information
+    /// about whether they changed since last frame.
+    clip_region_bindings: FastHashMap<PropertyBindingId, ClipRegionBindingInfo>,
+    /// Switch back and forth between old and new bindings hashmaps to avoid re-allocating.
+    old_clip_region_bindings: FastHashMap<PropertyBindingId, ClipRegionBindingInfo>,
     /// The current dirty region tracker for this picture.
     pub dirty_region: DirtyRegion,
     /// Current size of tiles in picture units.
@@ -1911,11 +1949,13 @@ impl TileCacheInstance {
             slice_flags: params.slice_flags,
             spatial_node_index: params.spatial_node_index,
             sub_slices,
-            opacity_bindings: FastHashMap::default(),
-            old_opacity_bindings: FastHashMap::default(),
+            float_bindings: FastHashMap::default(),
+            old_float_bindings: FastHashMap::default(),
             spatial_node_comparer: SpatialNodeComparer::new(),
             color_bindings: FastHashMap::default(),
             old_color_bindings: FastHashMap::default(),
+            clip_region_bindings: FastHashMap::default(),
+            old_clip_region_bindings: FastHashMap::default(),
             dirty_region: DirtyRegion::new(params.spatial_node_index),
             tile_size: PictureSize::zero(),
             tile_rect: TileRect::zero(),
@@ -2118,6 +2158,7 @@ impl TileCacheInstance {
                 frame_context.global_device_pixel_scale,
                 &frame_context.global_screen_world_rect,
                 &mut frame_state.data_stores.clip,
+                &frame_state.data_stores.polygon,
                 true,
                 false,
             );
@@ -2236,18 +2277,18 @@ impl TileCacheInstance {
         self.local_to_surface = local_to_surface;
         self.invalidate_all_tiles = surface_scale_changed || frame_context.config.force_invalidation;
 
-        // Do a hacky diff of opacity binding values from the last frame. This is
+        // Do a hacky diff of float binding values from the last frame. This is
         // used later on during tile invalidation tests.
         let current_properties = frame_context.scene_properties.float_properties();
-        mem::swap(&mut self.opacity_bindings, &mut self.old_opacity_bindings);
+        mem::swap(&mut self.float_bindings, &mut self.old_float_bindings);
 
-        self.opacity_bindings.clear();
+        self.float_bindings.clear();
         for (id, value) in current_properties {
-            let changed = match self.old_opacity_bindings.get(id) {
+            let changed = match self.old_float_bindings.get(id) {
                 Some(old_property) => !old_property.value.approx_eq(value),
                 None => true,
             };
-            self.opacity_bindings.insert(*id, OpacityBindingInfo {
+            self.float_bindings.insert(*id, FloatBindingInfo {
                 value: *value,
                 changed,
             });
@@ -2270,6 +2311,22 @@ impl TileCacheInstance {
             });
         }
 
+        // Same for the animated clip regions.
+        let current_properties = frame_context.scene_properties.clip_region_properties();
+        mem::swap(&mut self.clip_region_bindings, &mut self.old_clip_region_bindings);
+
+        self.clip_region_bindings.clear();
+        for (id, value) in current_properties {
+            let changed = match self.old_clip_region_bindings.get(id) {
+                Some(old_property) => old_property.value != *value,
+                None => true,
+            };
+            self.clip_region_bindings.insert(*id, ClipRegionBindingInfo {
+                value: *value,
+                changed,
+            });
+        }
+
         let world_tile_size = WorldSize::new(
             self.current_tile_size.width as f32 / frame_context.global_device_pixel_scale.0,
             self.current_tile_size.height as f32 / frame_context.global_device_pixel_scale.0,
@@ -3029,6 +3086,10 @@ impl TileCacheInstance {
                 && !prim_info.spatial_nodes.contains(&clip.item.spatial_node_index) {
                 prim_info.spatial_nodes.push(clip.item.spatial_node_index);
             }
+
+            if let Some(ref animation) = clip.animation {
+                prim_info.clip_region_bindings.push(ClipRegionBinding::Binding(animation.id));
+            }
         }
 
         // Certain primitives may select themselves to be a backdrop candidate, which is
@@ -3048,10 +3109,13 @@ impl TileCacheInstance {
         //           pass) so that we can calculate these correctly.
         match prim_instance.kind {
             PrimitiveInstanceKind::Picture { pic_index,.. } => {
-                // Pictures can depend on animated opacity bindings.
+                // Pictures can depend on animated opacities and other animated
+                // filter arguments, which are all float bindings.
                 let pic = &pictures[pic_index.0];
-                if let Some(PictureCompositeMode::Filter(Filter::Opacity(binding, _))) = pic.composite_mode {
-                    prim_info.opacity_bindings.push(binding.into());
+                if let Some(PictureCompositeMode::Filter(ref filter)) = pic.composite_mode {
+                    prim_info.float_bindings.extend(
+                        filter.bindings().into_iter().map(FloatBinding::from)
+                    );
                 }
             }
             PrimitiveInstanceKind::Rectangle { data_handle, color_binding_index, .. } => {
@@ -3059,8 +3123,13 @@ impl TileCacheInstance {
                 // TODO(gw): We could resolve the opacity binding here, but the common
                 //           case for background rects is that they don't have animated opacity.
                 let color = match data_stores.prim[data_handle].kind {
-                    PrimitiveTemplateKind::Rectangle { color, .. } => {
-                        frame_context.scene_properties.resolve_color(&color)
+                    PrimitiveTemplateKind::Rectangle { ref color, color_space } => {
+                        resolve_rectangle_color(
+                            color,
+                            color_space,
+                            frame_context.scene_properties,
+                            frame_context.output_color_space,
+                        )
                     }
                     _ => unreachable!(),
                 };
@@ -3072,7 +3141,7 @@ impl TileCacheInstance {
                 }
 
                 if color_binding_index != ColorBindingIndex::INVALID {
-                    prim_info.color_binding = Some(color_bindings[color_binding_index].into());
+                    prim_info.color_bindings.push(color_bindings[color_binding_index].into());
                 }
             }
             PrimitiveInstanceKind::Image { data_handle, ref mut is_compositor_surface, .. } => {
@@ -3236,6 +3305,7 @@ impl TileCacheInstance {
             PrimitiveInstanceKind::LinearGradient { data_handle, .. }
             | PrimitiveInstanceKind::CachedLinearGradient { data_handle, .. } => {
                 let gradient_data = &data_stores.linear_grad[data_handle];
+                prim_info.color_bindings.extend(gradient_data.stop_bindings.color_bindings());
                 if gradient_data.stops_opacity.is_opaque
                     && gradient_data.tile_spacing == LayoutSize::zero()
                 {
@@ -3247,6 +3317,7 @@ impl TileCacheInstance {
             }
             PrimitiveInstanceKind::ConicGradient { data_handle, .. } => {
                 let gradient_data = &data_stores.conic_grad[data_handle];
+                prim_info.color_bindings.extend(gradient_data.stop_bindings.color_bindings());
                 if gradient_data.stops_opacity.is_opaque
                     && gradient_data.tile_spacing == LayoutSize::zero()
                 {
@@ -3258,6 +3329,7 @@ impl TileCacheInstance {
             }
             PrimitiveInstanceKind::RadialGradient { data_handle, .. } => {
                 let gradient_data = &data_stores.radial_grad[data_handle];
+                prim_info.color_bindings.extend(gradient_data.stop_bindings.color_bindings());
                 if gradient_data.stops_opacity.is_opaque
                     && gradient_data.tile_spacing == LayoutSize::zero()
                 {
@@ -3268,6 +3340,7 @@ impl TileCacheInstance {
                 }
             }
             PrimitiveInstanceKind::LineDecoration { .. } |
+            PrimitiveInstanceKind::Path { .. } |
             PrimitiveInstanceKind::NormalBorder { .. } |
             PrimitiveInstanceKind::TextRun { .. } |
             PrimitiveInstanceKind::Backdrop { .. } => {
@@ -3386,7 +3459,9 @@ impl TileCacheInstance {
         // Truncate the lengths of dependency arrays to the max size we can handle.
         // Any arrays this size or longer will invalidate every frame.
         prim_info.clips.truncate(MAX_PRIM_SUB_DEPS);
-        prim_info.opacity_bindings.truncate(MAX_PRIM_SUB_DEPS);
+        prim_info.float_bindings.truncate(MAX_PRIM_SUB_DEPS);
+        prim_info.color_bindings.truncate(MAX_PRIM_SUB_DEPS);
+        prim_info.clip_region_bindings.truncate(MAX_PRIM_SUB_DEPS);
         prim_info.spatial_nodes.truncate(MAX_PRIM_SUB_DEPS);
         prim_info.images.truncate(MAX_PRIM_SUB_DEPS);
 
@@ -3524,8 +3599,9 @@ impl TileCacheInstance {
             global_device_pixel_scale: frame_context.global_device_pixel_scale,
             local_clip_rect: self.local_clip_rect,
             backdrop: None,
-            opacity_bindings: &self.opacity_bindings,
+            float_bindings: &self.float_bindings,
             color_bindings: &self.color_bindings,
+            clip_region_bindings: &self.clip_region_bindings,
             current_tile_size: self.current_tile_size,
             local_rect: self.local_rect,
             z_id: ZBufferId::invalid(),
@@ -3867,7 +3943,7 @@ impl PictureCompositeMode {
         };
 
         match self {
-            PictureCompositeMode::Filter(Filter::Blur { width, height, should_inflate }) => {
+            PictureCompositeMode::Filter(Filter::Blur { width, height, should_inflate, .. }) => {
                 if *should_inflate {
                     let (width_factor, height_factor) = surface.clamp_blur_radius(*width, *height);
 
@@ -3933,7 +4009,34 @@ impl PictureCompositeMode {
                             input_rect.translate(primitive.offset * Scale::new(1.0))
                         },
 
-                        FilterPrimitiveKind::Flood(..) => surface_rect,
+                        FilterPrimitiveKind::Morphology(ref primitive) => {
+                            let input_rect = primitive.input.to_index(cur_index).map(|index| output_rects[index]).unwrap_or(surface_rect);
+                            match primitive.operator {
+                                MorphologyOperator::Dilate => input_rect.inflate(primitive.radius_x, primitive.radius_y),
+                                MorphologyOperator::Erode => input_rect,
+                            }
+                        }
+                        FilterPrimitiveKind::ConvolveMatrix(ref primitive) => {
+                            let input_rect = primitive.input.to_index(cur_index).map(|index| output_rects[index]).unwrap_or(surface_rect);
+                            input_rect.inflate(primitive.order_x as f32, primitive.order_y as f32)
+                        }
+                        FilterPrimitiveKind::DisplacementMap(ref primitive) => {
+                            let input_rect = primitive.input1.to_index(cur_index).map(|index| output_rects[index]).unwrap_or(surface_rect);
+                            let inflation_factor = primitive.scale.abs() * 0.5;
+                            input_rect.inflate(inflation_factor, inflation_factor)
+                        }
+                        FilterPrimitiveKind::Merge(ref primitive) => {
+                            primitive.inputs().iter().fold(LayoutRect::zero(), |rect, input| {
+                                rect.union(&input.to_index(cur_index).map(|index| output_rects[index]).unwrap_or(surface_rect))
+                            })
+                        }
+
+                        FilterPrimitiveKind::Flood(..) |
+                        FilterPrimitiveKind::Turbulence(..) |
+                        FilterPrimitiveKind::DiffuseLighting(..) |
+                        FilterPrimitiveKind::SpecularLighting(..) |
+                        FilterPrimitiveKind::Tile(..) |
+                        FilterPrimitiveKind::Image(..) => surface_rect,
                     };
                     output_rects.push(output_rect);
                     result_rect = result_rect.union(&output_rect);
@@ -3957,7 +4060,7 @@ impl PictureCompositeMode {
         };
 
         match self {
-            PictureCompositeMode::Filter(Filter::Blur { width, height, should_inflate }) => {
+            PictureCompositeMode::Filter(Filter::Blur { width, height, should_inflate, .. }) => {
                 if *should_inflate {
                     let (width_factor, height_factor) = surface.clamp_blur_radius(*width, *height);
 
@@ -4028,7 +4131,34 @@ impl PictureCompositeMode {
                             input_rect.translate(primitive.offset * Scale::new(1.0))
                         },
 
-                        FilterPrimitiveKind::Flood(..) => surface_rect,
+                        FilterPrimitiveKind::Morphology(ref primitive) => {
+                            let input_rect = primitive.input.to_index(cur_index).map(|index| output_rects[index]).unwrap_or(surface_rect);
+                            match primitive.operator {
+                                MorphologyOperator::Dilate => input_rect.inflate(primitive.radius_x, primitive.radius_y),
+                                MorphologyOperator::Erode => input_rect,
+                            }
+                        }
+                        FilterPrimitiveKind::ConvolveMatrix(ref primitive) => {
+                            let input_rect = primitive.input.to_index(cur_index).map(|index| output_rects[index]).unwrap_or(surface_rect);
+                            input_rect.inflate(primitive.order_x as f32, primitive.order_y as f32)
+                        }
+                        FilterPrimitiveKind::DisplacementMap(ref primitive) => {
+                            let input_rect = primitive.input1.to_index(cur_index).map(|index| output_rects[index]).unwrap_or(surface_rect);
+                            let inflation_factor = primitive.scale.abs() * 0.5;
+                            input_rect.inflate(inflation_factor, inflation_factor)
+                        }
+                        FilterPrimitiveKind::Merge(ref primitive) => {
+                            primitive.inputs().iter().fold(LayoutRect::zero(), |rect, input| {
+                                rect.union(&input.to_index(cur_index).map(|index| output_rects[index]).unwrap_or(surface_rect))
+                            })
+                        }
+
+                        FilterPrimitiveKind::Flood(..) |
+                        FilterPrimitiveKind::Turbulence(..) |
+                        FilterPrimitiveKind::DiffuseLighting(..) |
+                        FilterPrimitiveKind::SpecularLighting(..) |
+                        FilterPrimitiveKind::Tile(..) |
+                        FilterPrimitiveKind::Image(..) => surface_rect,
                     };
                     output_rects.push(output_rect);
                     result_rect = result_rect.union(&output_rect);
@@ -4362,7 +4492,18 @@ impl PicturePrimitive {
         match self.composite_mode {
             Some(PictureCompositeMode::Filter(ref mut filter)) => {
                 match *filter {
-                    Filter::Opacity(ref binding, ref mut value) => {
+                    Filter::Blur { ref width_binding, ref mut width, ref height_binding, ref mut height, .. } => {
+                        *width = properties.resolve_float(width_binding);
+                        *height = properties.resolve_float(height_binding);
+                    }
+                    Filter::Brightness(ref binding, ref mut value) |
+                    Filter::Contrast(ref binding, ref mut value) |
+                    Filter::Grayscale(ref binding, ref mut value) |
+                    Filter::HueRotate(ref binding, ref mut value) |
+                    Filter::Invert(ref binding, ref mut value) |
+                    Filter::Opacity(ref binding, ref mut value) |
+                    Filter::Saturate(ref binding, ref mut value) |
+                    Filter::Sepia(ref binding, ref mut value) => {
                         *value = properties.resolve_float(binding);
                     }
                     _ => {}
@@ -5314,7 +5455,10 @@ impl PicturePrimitive {
                             primitives,
                             filter_datas,
                             frame_state.rg_builder,
+                            frame_state.resource_cache,
+                            frame_state.gpu_cache,
                             surface_rects.clipped.size().to_i32(),
+                            surface_rects.clipped.min,
                             surface_rects.uv_rect_kind,
                             picture_task_id,
                             device_pixel_scale,
@@ -5991,12 +6135,14 @@ struct PrimitiveComparer<'a> {
     clip_comparer: CompareHelper<'a, ItemUid>,
     transform_comparer: CompareHelper<'a, SpatialNodeKey>,
     image_comparer: CompareHelper<'a, ImageDependency>,
-    opacity_comparer: CompareHelper<'a, OpacityBinding>,
+    float_comparer: CompareHelper<'a, FloatBinding>,
     color_comparer: CompareHelper<'a, ColorBinding>,
+    clip_region_comparer: CompareHelper<'a, ClipRegionBinding>,
     resource_cache: &'a ResourceCache,
     spatial_node_comparer: &'a mut SpatialNodeComparer,
-    opacity_bindings: &'a FastHashMap<PropertyBindingId, OpacityBindingInfo>,
+    float_bindings: &'a FastHashMap<PropertyBindingId, FloatBindingInfo>,
     color_bindings: &'a FastHashMap<PropertyBindingId, ColorBindingInfo>,
+    clip_region_bindings: &'a FastHashMap<PropertyBindingId, ClipRegionBindingInfo>,
 }
 
 impl<'a> PrimitiveComparer<'a> {
@@ -6005,8 +6151,9 @@ impl<'a> PrimitiveComparer<'a> {
         curr: &'a TileDescriptor,
         resource_cache: &'a ResourceCache,
         spatial_node_comparer: &'a mut SpatialNodeComparer,
-        opacity_bindings: &'a FastHashMap<PropertyBindingId, OpacityBindingInfo>,
+        float_bindings: &'a FastHashMap<PropertyBindingId, FloatBindingInfo>,
         color_bindings: &'a FastHashMap<PropertyBindingId, ColorBindingInfo>,
+        clip_region_bindings: &'a FastHashMap<PropertyBindingId, ClipRegionBindingInfo>,
     ) -> Self {
         let clip_comparer = CompareHelper::new(
             &prev.clips,
@@ -6023,9 +6170,9 @@ impl<'a> PrimitiveComparer<'a> {
             &curr.images,
         );
 
-        let opacity_comparer = CompareHelper::new(
-            &prev.opacity_bindings,
-            &curr.opacity_bindings,
+        let float_comparer = CompareHelper::new(
+            &prev.float_bindings,
+            &curr.float_bindings,
         );
 
         let color_comparer = CompareHelper::new(
@@ -6033,16 +6180,23 @@ impl<'a> PrimitiveComparer<'a> {
             &curr.color_bindings,
         );
 
+        let clip_region_comparer = CompareHelper::new(
+            &prev.clip_region_bindings,
+            &curr.clip_region_bindings,
+        );
+
         PrimitiveComparer {
             clip_comparer,
             transform_comparer,
             image_comparer,
-            opacity_comparer,
+            float_comparer,
             color_comparer,
+            clip_region_comparer,
             resource_cache,
             spatial_node_comparer,
-            opacity_bindings,
+            float_bindings,
             color_bindings,
+            clip_region_bindings,
         }
     }
 
@@ -6050,24 +6204,27 @@ impl<'a> PrimitiveComparer<'a> {
         self.clip_comparer.reset();
         self.transform_comparer.reset();
         self.image_comparer.reset();
-        self.opacity_comparer.reset();
+        self.float_comparer.reset();
         self.color_comparer.reset();
+        self.clip_region_comparer.reset();
     }
 
     fn advance_prev(&mut self, prim: &PrimitiveDescriptor) {
         self.clip_comparer.advance_prev(prim.clip_dep_count);
         self.transform_comparer.advance_prev(prim.transform_dep_count);
         self.image_comparer.advance_prev(prim.image_dep_count);
-        self.opacity_comparer.advance_prev(prim.opacity_binding_dep_count);
+        self.float_comparer.advance_prev(prim.float_binding_dep_count);
         self.color_comparer.advance_prev(prim.color_binding_dep_count);
+        self.clip_region_comparer.advance_prev(prim.clip_region_binding_dep_count);
     }
 
     fn advance_curr(&mut self, prim: &PrimitiveDescriptor) {
         self.clip_comparer.advance_curr(prim.clip_dep_count);
         self.transform_comparer.advance_curr(prim.transform_dep_count);
         self.image_comparer.advance_curr(prim.image_dep_count);
-        self.opacity_comparer.advance_curr(prim.opacity_binding_dep_count);
+        self.float_comparer.advance_curr(prim.float_binding_dep_count);
         self.color_comparer.advance_curr(prim.color_binding_dep_count);
+        self.clip_region_comparer.advance_curr(prim.clip_region_binding_dep_count);
     }
 
     /// Check if two primitive descriptors are the same.
@@ -6078,8 +6235,9 @@ impl<'a> PrimitiveComparer<'a> {
     ) -> PrimitiveCompareResult {
         let resource_cache = self.resource_cache;
         let spatial_node_comparer = &mut self.spatial_node_comparer;
-        let opacity_bindings = self.opacity_bindings;
+        let float_bindings = self.float_bindings;
         let color_bindings = self.color_bindings;
+        let clip_region_bindings = self.clip_region_bindings;
 
         // Check equality of the PrimitiveDescriptor
         if prev != curr {
@@ -6120,17 +6278,17 @@ impl<'a> PrimitiveComparer<'a> {
             return PrimitiveCompareResult::Image;
         }
 
-        // Check if any of the opacity bindings this prim has are different.
-        if !self.opacity_comparer.is_same(
-            prev.opacity_binding_dep_count,
-            curr.opacity_binding_dep_count,
+        // Check if any of the float bindings this prim has are different.
+        if !self.float_comparer.is_same(
+            prev.float_binding_dep_count,
+            curr.float_binding_dep_count,
             |prev, curr| {
                 if prev != curr {
                     return false;
                 }
 
-                if let OpacityBinding::Binding(id) = curr {
-                    if opacity_bindings
+                if let FloatBinding::Binding(id) = curr {
+                    if float_bindings
                         .get(id)
                         .map_or(true, |info| info.changed) {
                         return false;
@@ -6140,7 +6298,7 @@ impl<'a> PrimitiveComparer<'a> {
                 true
             },
         ) {
-            return PrimitiveCompareResult::OpacityBinding;
+            return PrimitiveCompareResult::FloatBinding;
         }
 
         // Check if any of the color bindings this prim has are different.
@@ -6166,6 +6324,29 @@ impl<'a> PrimitiveComparer<'a> {
             return PrimitiveCompareResult::ColorBinding;
         }
 
+        // Check if any of the animated clip regions this prim has are different.
+        if !self.clip_region_comparer.is_same(
+            prev.clip_region_binding_dep_count,
+            curr.clip_region_binding_dep_count,
+            |prev, curr| {
+                if prev != curr {
+                    return false;
+                }
+
+                if let ClipRegionBinding::Binding(id) = curr {
+                    if clip_region_bindings
+                        .get(id)
+                        .map_or(true, |info| info.changed) {
+                        return false;
+                    }
+                }
+
+                true
+            },
+        ) {
+            return PrimitiveCompareResult::ClipRegionBinding;
+        }
+
         PrimitiveCompareResult::Equal
     }
 }
@@ -8,7 +8,7 @@
 
 use std::cmp;
 use api::{PremultipliedColorF, PropertyBinding};
-use api::{BoxShadowClipMode, BorderStyle, ClipMode};
+use api::{BoxShadowClipMode, BorderStyle, ClipMode, FillRule, StrokeStyle};
 use api::units::*;
 use euclid::Scale;
 use smallvec::SmallVec;
@@ -23,7 +23,9 @@ use crate::gpu_types::{BrushFlags};
 use crate::internal_types::{FastHashMap, PlaneSplitAnchor};
 use crate::picture::{PicturePrimitive, SliceId, ClusterFlags};
 use crate::picture::{PrimitiveList, PrimitiveCluster, SurfaceIndex, TileCacheInstance, SubpixelMode, Picture3DContext};
+use crate::path::build_path_edges;
 use crate::prim_store::line_dec::MAX_LINE_DECORATION_RESOLUTION;
+use crate::prim_store::path::MAX_PATH_RESOLUTION;
 use crate::prim_store::*;
 use crate::render_backend::DataStores;
 use crate::render_task_graph::RenderTaskId;
@@ -327,6 +329,76 @@ fn prepare_interned_prim_for_render(
                 ));
             }
         }
+        PrimitiveInstanceKind::Path { data_handle, ref mut render_task, .. } => {
+            profile_scope!("Path");
+            let prim_data = &mut data_stores.path[*data_handle];
+            let common_data = &mut prim_data.common;
+            let path_data = &mut prim_data.kind;
+
+            path_data.update(common_data, frame_state);
+
+            if is_chased {
+                info!("\tpath key={:?}", path_data.cache_key);
+            }
+
+            // The mask is rasterized in local space, scaled by the same power of
+            // two friendly scale factors as line decorations, so that it only
+            // needs to be redrawn when the scale changes significantly.
+            let scale = frame_context
+                .spatial_tree
+                .get_world_transform(prim_spatial_node_index)
+                .scale_factors();
+            let scale_width = clamp_to_scale_factor(scale.0, false);
+            let scale_height = clamp_to_scale_factor(scale.1, false);
+            let world_scale = LayoutToWorldScale::new(scale_width.max(scale_height));
+
+            let cache_key = &path_data.cache_key;
+            let local_size = LayoutSize::from_au(cache_key.size);
+            let scale_factor = world_scale * Scale::new(1.0);
+            let mut task_size = (local_size * scale_factor).ceil().to_i32();
+            if task_size.width > MAX_PATH_RESOLUTION as i32 ||
+               task_size.height > MAX_PATH_RESOLUTION as i32 {
+                let max_extent = cmp::max(task_size.width, task_size.height);
+                let task_scale_factor = Scale::new(MAX_PATH_RESOLUTION as f32 / max_extent as f32);
+                task_size = (local_size * scale_factor * task_scale_factor).ceil().to_i32();
+            }
+
+            *render_task = Some(frame_state.resource_cache.request_render_task(
+                RenderTaskCacheKey {
+                    size: task_size,
+                    kind: RenderTaskCacheKeyKind::Path(cache_key.clone()),
+                },
+                frame_state.gpu_cache,
+                frame_state.rg_builder,
+                None,
+                false,
+                RenderTaskParent::Surface(pic_context.surface_index),
+                &mut frame_state.surface_builder,
+                |rg_builder| {
+                    // Map the local path onto the task, which is rounded up to
+                    // whole device pixels.
+                    let task_scale = (
+                        task_size.width as f32 / local_size.width,
+                        task_size.height as f32 / local_size.height,
+                    );
+                    let stroke = cache_key.stroke.as_ref().map(StrokeStyle::from);
+                    let edges = build_path_edges(
+                        &cache_key.segments(),
+                        stroke.as_ref(),
+                        task_scale,
+                    );
+                    let fill_rule = match stroke {
+                        Some(..) => FillRule::Nonzero,
+                        None => cache_key.fill_rule,
+                    };
+
+                    rg_builder.add().init(RenderTask::new_dynamic(
+                        task_size,
+                        RenderTaskKind::new_path(edges, fill_rule),
+                    ))
+                }
+            ));
+        }
         PrimitiveInstanceKind::TextRun { run_index, data_handle, .. } => {
             profile_scope!("TextRun");
             let prim_data = &mut data_stores.text_run[*data_handle];
@@ -401,7 +473,11 @@ fn prepare_interned_prim_for_render(
 
             // Update the template this instane references, which may refresh the GPU
             // cache with any shared template data.
-            prim_data.update(frame_state, frame_context.scene_properties);
+            prim_data.update(
+                frame_state,
+                frame_context.scene_properties,
+                frame_context.output_color_space,
+            );
         }
         PrimitiveInstanceKind::NormalBorder { data_handle, ref mut render_task_ids, .. } => {
             profile_scope!("NormalBorder");
@@ -531,6 +607,7 @@ fn prepare_interned_prim_for_render(
             prim_data.update(
                 frame_state,
                 frame_context.scene_properties,
+                frame_context.output_color_space,
             );
 
             write_segment(
@@ -542,6 +619,7 @@ fn prepare_interned_prim_for_render(
                     prim_data.kind.write_prim_gpu_blocks(
                         request,
                         frame_context.scene_properties,
+                        frame_context.output_color_space,
                     );
                 }
             );
@@ -604,7 +682,11 @@ fn prepare_interned_prim_for_render(
 
             // Update the template this instane references, which may refresh the GPU
             // cache with any shared template data.
-            prim_data.update(frame_state, pic_context.surface_index);
+            prim_data.update(
+                frame_state,
+                pic_context.surface_index,
+                frame_context.scene_properties,
+            );
 
             if prim_data.stretch_size.width >= prim_data.common.prim_rect.width() &&
                 prim_data.stretch_size.height >= prim_data.common.prim_rect.height() {
@@ -658,7 +740,11 @@ fn prepare_interned_prim_for_render(
 
             // Update the template this instance references, which may refresh the GPU
             // cache with any shared template data.
-            prim_data.update(frame_state, pic_context.surface_index);
+            prim_data.update(
+                frame_state,
+                pic_context.surface_index,
+                frame_context.scene_properties,
+            );
 
             if prim_data.tile_spacing != LayoutSize::zero() {
                 prim_data.common.may_need_repetition = false;
@@ -689,7 +775,11 @@ fn prepare_interned_prim_for_render(
 
             // Update the template this instane references, which may refresh the GPU
             // cache with any shared template data.
-            prim_data.update(frame_state, pic_context.surface_index);
+            prim_data.update(
+                frame_state,
+                pic_context.surface_index,
+                frame_context.scene_properties,
+            );
 
             if prim_data.tile_spacing != LayoutSize::zero() {
                 prim_data.common.may_need_repetition = false;
@@ -723,7 +813,11 @@ fn prepare_interned_prim_for_render(
 
             // Update the template this instane references, which may refresh the GPU
             // cache with any shared template data.
-            prim_data.update(frame_state, pic_context.surface_index);
+            prim_data.update(
+                frame_state,
+                pic_context.surface_index,
+                frame_context.scene_properties,
+            );
 
             if prim_data.tile_spacing != LayoutSize::zero() {
                 prim_data.common.may_need_repetition = false;
@@ -909,6 +1003,7 @@ fn update_clip_task_for_brush(
         PrimitiveInstanceKind::TextRun { .. } |
         PrimitiveInstanceKind::Clear { .. } |
         PrimitiveInstanceKind::LineDecoration { .. } |
+        PrimitiveInstanceKind::Path { .. } |
         PrimitiveInstanceKind::Backdrop { .. } => {
             return None;
         }
@@ -1052,6 +1147,7 @@ fn update_clip_task_for_brush(
                     device_pixel_scale,
                     &dirty_world_rect,
                     &mut data_stores.clip,
+                    &data_stores.polygon,
                     false,
                     instance.is_chased(),
                 );
@@ -1294,8 +1390,8 @@ fn write_brush_segment_description(
 
                 continue;
             }
-            ClipItemKind::Image { .. } => {
-                // If we encounter an image mask, bail out from segment building.
+            ClipItemKind::Image { .. } | ClipItemKind::Polygon { .. } => {
+                // If we encounter an image mask or a polygon clip, bail out from segment building.
                 // It's not possible to know which parts of the primitive are affected
                 // by the mask (without inspecting the pixels). We could do something
                 // better here in the future if it ever shows up as a performance issue
@@ -1377,6 +1473,7 @@ fn build_segments_if_needed(
         PrimitiveInstanceKind::RadialGradient { .. } |
         PrimitiveInstanceKind::ConicGradient { .. } |
         PrimitiveInstanceKind::LineDecoration { .. } |
+        PrimitiveInstanceKind::Path { .. } |
         PrimitiveInstanceKind::Backdrop { .. } => {
             // These primitives don't support / need segments.
             return;
@@ -9,8 +9,9 @@
 //! Conic gradients are rendered via cached render tasks and composited with the image brush.
 
 use euclid::vec2;
-use api::{ExtendMode, GradientStop, PremultipliedColorF};
+use api::{ExtendMode, GradientColorSpace, GradientInterpolation, GradientStop, PremultipliedColorF, RgbColorSpace};
 use api::units::*;
+use crate::color_space::pack_gradient_color_spaces;
 use crate::scene_building::IsVisible;
 use crate::frame_builder::FrameBuildingState;
 use crate::gpu_cache::{GpuCache, GpuCacheHandle};
@@ -26,7 +27,8 @@ use crate::render_task_cache::{RenderTaskCacheKeyKind, RenderTaskCacheKey, Rende
 use crate::picture::{SurfaceIndex};
 
 use std::{hash, ops::{Deref, DerefMut}};
-use super::{stops_and_min_alpha, GradientStopKey, GradientGpuBlockBuilder};
+use crate::scene::SceneProperties;
+use super::{stops_and_min_alpha, GradientStopKey, GradientStopBindings, GradientGpuBlockBuilder};
 
 /// Hashable conic gradient parameters, for use during prim interning.
 #[cfg_attr(feature = "capture", derive(Serialize))]
@@ -55,6 +57,8 @@ impl hash::Hash for ConicGradientParams {
 pub struct ConicGradientKey {
     pub common: PrimKeyCommonData,
     pub extend_mode: ExtendMode,
+    pub interpolation: GradientInterpolation,
+    pub output_color_space: RgbColorSpace,
     pub center: PointKey,
     pub params: ConicGradientParams,
     pub stretch_size: SizeKey,
@@ -71,6 +75,8 @@ impl ConicGradientKey {
         ConicGradientKey {
             common: info.into(),
             extend_mode: conic_grad.extend_mode,
+            interpolation: conic_grad.interpolation,
+            output_color_space: conic_grad.output_color_space,
             center: conic_grad.center,
             params: conic_grad.params,
             stretch_size: conic_grad.stretch_size,
@@ -89,6 +95,8 @@ impl InternDebug for ConicGradientKey {}
 pub struct ConicGradientTemplate {
     pub common: PrimTemplateCommonData,
     pub extend_mode: ExtendMode,
+    pub interpolation: GradientInterpolation,
+    pub output_color_space: RgbColorSpace,
     pub center: DevicePoint,
     pub params: ConicGradientParams,
     pub task_size: DeviceIntSize,
@@ -98,6 +106,7 @@ pub struct ConicGradientTemplate {
     pub brush_segments: Vec<BrushSegment>,
     pub stops_opacity: PrimitiveOpacity,
     pub stops: Vec<GradientStop>,
+    pub stop_bindings: GradientStopBindings,
     pub stops_handle: GpuCacheHandle,
     pub src_color: Option<RenderTaskId>,
 }
@@ -193,6 +202,8 @@ impl From<ConicGradientKey> for ConicGradientTemplate {
             common,
             center: DevicePoint::new(item.center.x, item.center.y),
             extend_mode: item.extend_mode,
+            interpolation: item.interpolation,
+            output_color_space: item.output_color_space,
             params: item.params,
             stretch_size,
             task_size: task_size.ceil().to_i32(),
@@ -200,6 +211,7 @@ impl From<ConicGradientKey> for ConicGradientTemplate {
             tile_spacing: item.tile_spacing.into(),
             brush_segments,
             stops_opacity,
+            stop_bindings: GradientStopBindings::new(&stops),
             stops,
             stops_handle: GpuCacheHandle::new(),
             src_color: None,
@@ -216,7 +228,12 @@ impl ConicGradientTemplate {
         &mut self,
         frame_state: &mut FrameBuildingState,
         parent_surface: SurfaceIndex,
+        scene_properties: &SceneProperties,
     ) {
+        if self.stop_bindings.resolve(&mut self.stops, scene_properties) {
+            frame_state.gpu_cache.invalidate(&self.stops_handle);
+        }
+
         if let Some(mut request) =
             frame_state.gpu_cache.request(&mut self.common.gpu_cache_handle) {
             // write_prim_gpu_blocks
@@ -244,6 +261,7 @@ impl ConicGradientTemplate {
                 false,
                 &mut request,
                 &self.stops,
+                self.interpolation,
             );
         }
 
@@ -255,6 +273,8 @@ impl ConicGradientTemplate {
             end_offset: FloatKey(self.params.end_offset),
             angle: FloatKey(self.params.angle),
             extend_mode: self.extend_mode,
+            interpolation: self.interpolation,
+            output_color_space: self.output_color_space,
             stops: self.stops.iter().map(|stop| (*stop).into()).collect(),
         };
 
@@ -274,6 +294,8 @@ impl ConicGradientTemplate {
                     self.task_size,
                     RenderTaskKind::ConicGradient(ConicGradientTask {
                         extend_mode: self.extend_mode,
+                        color_space: self.interpolation.color_space,
+                        output_color_space: self.output_color_space,
                         scale: self.scale,
                         center: self.center,
                         params: self.params.clone(),
@@ -300,6 +322,8 @@ pub type ConicGradientDataHandle = InternHandle<ConicGradient>;
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub struct ConicGradient {
     pub extend_mode: ExtendMode,
+    pub interpolation: GradientInterpolation,
+    pub output_color_space: RgbColorSpace,
     pub center: PointKey,
     pub params: ConicGradientParams,
     pub stretch_size: SizeKey,
@@ -347,6 +371,8 @@ impl IsVisible for ConicGradient {
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub struct ConicGradientTask {
     pub extend_mode: ExtendMode,
+    pub color_space: GradientColorSpace,
+    pub output_color_space: RgbColorSpace,
     pub center: DevicePoint,
     pub scale: DeviceVector2D,
     pub params: ConicGradientParams,
@@ -363,6 +389,7 @@ impl ConicGradientTask {
             end_offset: self.params.end_offset,
             angle: self.params.angle,
             extend_mode: self.extend_mode as i32,
+            color_space: pack_gradient_color_spaces(self.color_space, self.output_color_space),
             gradient_stops_address: self.stops.as_int(gpu_cache),
         }
     }
@@ -383,6 +410,7 @@ pub struct ConicGradientInstance {
     pub end_offset: f32,
     pub angle: f32,
     pub extend_mode: i32,
+    pub color_space: i32,
     pub gradient_stops_address: i32,
 }
 
@@ -397,6 +425,8 @@ pub struct ConicGradientCacheKey {
     pub end_offset: FloatKey,
     pub angle: FloatKey,
     pub extend_mode: ExtendMode,
+    pub interpolation: GradientInterpolation,
+    pub output_color_space: RgbColorSpace,
     pub stops: Vec<GradientStopKey>,
 }
 
@@ -10,8 +10,10 @@
 
 use euclid::approxeq::ApproxEq;
 use euclid::{point2, vec2, size2};
-use api::{ExtendMode, GradientStop, LineOrientation, PremultipliedColorF, ColorF, ColorU};
+use api::{ExtendMode, GradientColorSpace, GradientInterpolation, GradientStop, LineOrientation};
+use api::{PremultipliedColorF, ColorF, ColorU, RgbColorSpace};
 use api::units::*;
+use crate::color_space::pack_gradient_color_spaces;
 use crate::scene_building::IsVisible;
 use crate::frame_builder::FrameBuildingState;
 use crate::gpu_cache::{GpuCache, GpuCacheHandle};
@@ -26,8 +28,10 @@ use crate::render_task::{RenderTask, RenderTaskKind};
 use crate::render_task_graph::RenderTaskId;
 use crate::render_task_cache::{RenderTaskCacheKeyKind, RenderTaskCacheKey, RenderTaskParent};
 use crate::picture::{SurfaceIndex};
+use crate::scene::SceneProperties;
 use crate::util::pack_as_float;
-use super::{stops_and_min_alpha, GradientStopKey, GradientGpuBlockBuilder, apply_gradient_local_clip};
+use super::{stops_and_min_alpha, GradientStopKey, GradientStopBindings, GradientGpuBlockBuilder};
+use super::{apply_gradient_local_clip, has_animated_stops};
 use std::ops::{Deref, DerefMut};
 use std::mem::swap;
 
@@ -40,6 +44,8 @@ pub const MAX_CACHED_SIZE: f32 = 1024.0;
 pub struct LinearGradientKey {
     pub common: PrimKeyCommonData,
     pub extend_mode: ExtendMode,
+    pub interpolation: GradientInterpolation,
+    pub output_color_space: RgbColorSpace,
     pub start_point: PointKey,
     pub end_point: PointKey,
     pub stretch_size: SizeKey,
@@ -58,6 +64,8 @@ impl LinearGradientKey {
         LinearGradientKey {
             common: info.into(),
             extend_mode: linear_grad.extend_mode,
+            interpolation: linear_grad.interpolation,
+            output_color_space: linear_grad.output_color_space,
             start_point: linear_grad.start_point,
             end_point: linear_grad.end_point,
             stretch_size: linear_grad.stretch_size,
@@ -78,6 +86,8 @@ impl InternDebug for LinearGradientKey {}
 pub struct LinearGradientTemplate {
     pub common: PrimTemplateCommonData,
     pub extend_mode: ExtendMode,
+    pub interpolation: GradientInterpolation,
+    pub output_color_space: RgbColorSpace,
     pub start_point: DevicePoint,
     pub end_point: DevicePoint,
     pub task_size: DeviceIntSize,
@@ -86,6 +96,7 @@ pub struct LinearGradientTemplate {
     pub tile_spacing: LayoutSize,
     pub stops_opacity: PrimitiveOpacity,
     pub stops: Vec<GradientStop>,
+    pub stop_bindings: GradientStopBindings,
     pub brush_segments: Vec<BrushSegment>,
     pub reverse_stops: bool,
     pub is_fast_path: bool,
@@ -165,7 +176,7 @@ pub fn optimize_linear_gradient(
     // decomposing the gradient into multiple gradients with only two
     // gradient stops per segment to get a faster shader.
 
-    if extend_mode != ExtendMode::Clamp || stops.is_empty() {
+    if extend_mode != ExtendMode::Clamp || stops.is_empty() || has_animated_stops(stops) {
         return false;
     }
 
@@ -347,9 +358,13 @@ impl From<LinearGradientKey> for LinearGradientTemplate {
         }
 
         // See if we can render the gradient using a special fast-path shader.
-        // The fast path path only works with two gradient stops.
+        // The fast path path only works with two gradient stops interpolated
+        // and output in sRGB.
+        let mut stop_bindings = GradientStopBindings::new(&stops);
         let mut is_fast_path = false;
-        if item.cached && stops.len() == 2 && brush_segments.is_empty() {
+        if item.cached && stops.len() == 2 && brush_segments.is_empty()
+            && item.interpolation.color_space == GradientColorSpace::Srgb
+            && item.output_color_space == RgbColorSpace::Srgb {
             if horizontal
                 && stretch_size.width >= common.prim_rect.width()
                 && start_point.x.approx_eq(&0.0)
@@ -365,7 +380,7 @@ impl From<LinearGradientKey> for LinearGradientTemplate {
                 task_size.height = task_size.height.min(256.0);
             }
 
-            if stops[0].color == stops[1].color {
+            if stops[0].color == stops[1].color && !stop_bindings.is_animated() {
                 is_fast_path = true;
                 task_size = size2(1.0, 1.0);
             }
@@ -374,6 +389,7 @@ impl From<LinearGradientKey> for LinearGradientTemplate {
                 // The fast path doesn't use the gradient gpu blocks builder so handle
                 // reversed stops here.
                 stops.swap(0, 1);
+                stop_bindings.swap(0, 1);
             }
         }
 
@@ -396,6 +412,8 @@ impl From<LinearGradientKey> for LinearGradientTemplate {
         LinearGradientTemplate {
             common,
             extend_mode: item.extend_mode,
+            interpolation: item.interpolation,
+            output_color_space: item.output_color_space,
             start_point,
             end_point,
             task_size: task_size.ceil().to_i32(),
@@ -404,6 +422,7 @@ impl From<LinearGradientKey> for LinearGradientTemplate {
             tile_spacing,
             stops_opacity,
             stops,
+            stop_bindings,
             brush_segments,
             reverse_stops: item.reverse_stops,
             is_fast_path,
@@ -423,7 +442,12 @@ impl LinearGradientTemplate {
         &mut self,
         frame_state: &mut FrameBuildingState,
         parent_surface: SurfaceIndex,
+        scene_properties: &SceneProperties,
     ) {
+        if self.stop_bindings.resolve(&mut self.stops, scene_properties) {
+            frame_state.gpu_cache.invalidate(&self.stops_handle);
+        }
+
         if let Some(mut request) = frame_state.gpu_cache.request(
             &mut self.common.gpu_cache_handle
         ) {
@@ -451,7 +475,10 @@ impl LinearGradientTemplate {
                     pack_as_float(self.extend_mode as u32),
                     self.stretch_size.width,
                     self.stretch_size.height,
-                    0.0,
+                    pack_as_float(pack_gradient_color_spaces(
+                        self.interpolation.color_space,
+                        self.output_color_space,
+                    ) as u32),
                 ]);
             }
 
@@ -472,6 +499,7 @@ impl LinearGradientTemplate {
                     self.reverse_stops,
                     &mut request,
                     &self.stops,
+                    self.interpolation,
                 );
             }
         }
@@ -524,6 +552,8 @@ impl LinearGradientTemplate {
                 end: PointKey { x: self.end_point.x, y: self.end_point.y },
                 scale: PointKey { x: self.scale.x, y: self.scale.y },
                 extend_mode: self.extend_mode,
+                interpolation: self.interpolation,
+                output_color_space: self.output_color_space,
                 stops: self.stops.iter().map(|stop| (*stop).into()).collect(),
                 reversed_stops: self.reverse_stops,
             };
@@ -547,6 +577,8 @@ impl LinearGradientTemplate {
                             end: self.end_point,
                             scale: self.scale,
                             extend_mode: self.extend_mode,
+                            color_space: self.interpolation.color_space,
+                            output_color_space: self.output_color_space,
                             stops: self.stops_handle,
                         }),
                     ))
@@ -565,6 +597,8 @@ pub type LinearGradientDataHandle = InternHandle<LinearGradient>;
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub struct LinearGradient {
     pub extend_mode: ExtendMode,
+    pub interpolation: GradientInterpolation,
+    pub output_color_space: RgbColorSpace,
     pub start_point: PointKey,
     pub end_point: PointKey,
     pub stretch_size: SizeKey,
@@ -678,6 +712,8 @@ pub struct LinearGradientTask {
     pub end: DevicePoint,
     pub scale: DeviceVector2D,
     pub extend_mode: ExtendMode,
+    pub color_space: GradientColorSpace,
+    pub output_color_space: RgbColorSpace,
     pub stops: GpuCacheHandle,
 }
 
@@ -689,6 +725,7 @@ impl LinearGradientTask {
             end: self.end,
             scale: self.scale,
             extend_mode: self.extend_mode as i32,
+            color_space: pack_gradient_color_spaces(self.color_space, self.output_color_space),
             gradient_stops_address: self.stops.as_int(gpu_cache),
         }
     }
@@ -707,6 +744,7 @@ pub struct LinearGradientInstance {
     pub end: DevicePoint,
     pub scale: DeviceVector2D,
     pub extend_mode: i32,
+    pub color_space: i32,
     pub gradient_stops_address: i32,
 }
 
@@ -719,6 +757,8 @@ pub struct LinearGradientCacheKey {
     pub end: PointKey,
     pub scale: PointKey,
     pub extend_mode: ExtendMode,
+    pub interpolation: GradientInterpolation,
+    pub output_color_space: RgbColorSpace,
     pub stops: Vec<GradientStopKey>,
     pub reversed_stops: bool,
 }
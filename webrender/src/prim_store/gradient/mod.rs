@@ -2,9 +2,13 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use api::{ColorF, ColorU, GradientStop, PremultipliedColorF};
+use api::{ColorF, ColorU, GradientColorSpace, GradientInterpolation, GradientStop};
+use api::{HueInterpolationMethod, PremultipliedColorF, PropertyBinding, PropertyBindingKey};
 use api::units::{LayoutRect, LayoutSize, LayoutVector2D};
+use crate::color_space::{gradient_hue_index, has_powerless_hue, to_gradient_color_space};
 use crate::gpu_cache::GpuDataRequest;
+use crate::picture::ColorBinding;
+use crate::scene::SceneProperties;
 use std::hash;
 
 mod linear;
@@ -24,6 +28,7 @@ pub use conic::*;
 pub struct GradientStopKey {
     pub offset: f32,
     pub color: ColorU,
+    pub color_binding: Option<PropertyBindingKey<ColorF>>,
 }
 
 impl GradientStopKey {
@@ -31,6 +36,7 @@ impl GradientStopKey {
         GradientStopKey {
             offset: 0.0,
             color: ColorU::new(0, 0, 0, 0),
+            color_binding: None,
         }
     }
 }
@@ -40,10 +46,16 @@ impl Into<GradientStopKey> for GradientStop {
         GradientStopKey {
             offset: self.offset,
             color: self.color.into(),
+            color_binding: self.color_binding,
         }
     }
 }
 
+/// Returns true if the color of any of the stops is animated.
+pub fn has_animated_stops(stop_keys: &[GradientStopKey]) -> bool {
+    stop_keys.iter().any(|stop_key| stop_key.color_binding.is_some())
+}
+
 // Convert `stop_keys` into a vector of `GradientStop`s, which is a more
 // convenient representation for the current gradient builder. Compute the
 // minimum stop alpha along the way.
@@ -56,18 +68,93 @@ fn stops_and_min_alpha(stop_keys: &[GradientStopKey]) -> (Vec<GradientStop>, f32
         GradientStop {
             offset: stop_key.offset,
             color,
+            color_binding: stop_key.color_binding,
         }
     }).collect();
 
+    // The alpha of animated stops can't be known ahead of time.
+    if has_animated_stops(stop_keys) {
+        min_alpha = 0.0;
+    }
+
     (stops, min_alpha)
 }
 
+/// The animated stop colors of a gradient template.
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+#[derive(Debug, MallocSizeOf)]
+pub struct GradientStopBindings {
+    /// One binding per stop, or none if no stop color is animated.
+    bindings: Vec<PropertyBinding<ColorF>>,
+}
+
+impl GradientStopBindings {
+    pub fn new(stops: &[GradientStop]) -> Self {
+        let bindings = if stops.iter().any(|stop| stop.color_binding.is_some()) {
+            stops.iter().map(|stop| {
+                match stop.color_binding {
+                    Some(key) => PropertyBinding::Binding(key, stop.color),
+                    None => PropertyBinding::Value(stop.color),
+                }
+            }).collect()
+        } else {
+            Vec::new()
+        };
+
+        GradientStopBindings { bindings }
+    }
+
+    pub fn is_animated(&self) -> bool {
+        !self.bindings.is_empty()
+    }
+
+    /// Must be called when the corresponding stops are swapped.
+    pub fn swap(&mut self, a: usize, b: usize) {
+        if self.is_animated() {
+            self.bindings.swap(a, b);
+        }
+    }
+
+    /// Write the current value of the animated colors to `stops`. Returns
+    /// true if any of them changed.
+    pub fn resolve(
+        &self,
+        stops: &mut [GradientStop],
+        scene_properties: &SceneProperties,
+    ) -> bool {
+        let mut changed = false;
+        for (stop, binding) in stops.iter_mut().zip(self.bindings.iter()) {
+            let color = scene_properties.resolve_color(binding);
+            if color != stop.color {
+                stop.color = color;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    /// The bindings that picture cache tiles depend on.
+    pub fn color_bindings(&self) -> impl Iterator<Item = ColorBinding> + '_ {
+        self.bindings.iter().filter_map(|binding| {
+            match *binding {
+                PropertyBinding::Binding(..) => {
+                    Some(ColorBinding::from(PropertyBinding::<ColorU>::from(*binding)))
+                }
+                PropertyBinding::Value(..) => None,
+            }
+        })
+    }
+}
+
 impl Eq for GradientStopKey {}
 
 impl hash::Hash for GradientStopKey {
     fn hash<H: hash::Hasher>(&self, state: &mut H) {
         self.offset.to_bits().hash(state);
         self.color.hash(state);
+        self.color_binding.map(|key| key.id).hash(state);
     }
 }
 
@@ -104,6 +191,63 @@ impl GradientDataEntry {
     }
 }
 
+/// Returns how much the hue changes between two gradient stops, in degrees.
+///
+/// See https://drafts.csswg.org/css-color-4/#hue-interpolation
+fn hue_delta(from: f32, to: f32, method: HueInterpolationMethod) -> f32 {
+    let delta = (to - from).rem_euclid(360.0);
+    match method {
+        HueInterpolationMethod::Shorter if delta > 180.0 => delta - 360.0,
+        HueInterpolationMethod::Longer if delta == 0.0 => 360.0,
+        HueInterpolationMethod::Longer if delta < 180.0 => delta - 360.0,
+        HueInterpolationMethod::Decreasing if delta > 0.0 => delta - 360.0,
+        _ => delta,
+    }
+}
+
+/// Converts the colors of two consecutive gradient stops to the interpolation
+/// color space of the gradient.
+///
+/// All channels but the hue are premultiplied, and the hue of the second color
+/// is adjusted so that interpolating linearly between the two colors follows
+/// the hue interpolation method. Colors in any other space than sRGB must be
+/// converted back by `sample_gradient` in gradient.glsl.
+fn interpolation_colors(
+    from: &ColorF,
+    to: &ColorF,
+    interpolation: GradientInterpolation,
+) -> (PremultipliedColorF, PremultipliedColorF) {
+    let color_space = interpolation.color_space;
+    if color_space == GradientColorSpace::Srgb {
+        return (from.premultiplied(), to.premultiplied());
+    }
+
+    let mut c0 = to_gradient_color_space(*from, color_space);
+    let mut c1 = to_gradient_color_space(*to, color_space);
+    let hue_index = gradient_hue_index(color_space);
+
+    if let Some(hue) = hue_index {
+        // An achromatic color takes the hue of the other stop.
+        match (has_powerless_hue(c0, color_space), has_powerless_hue(c1, color_space)) {
+            (true, false) => c0[hue] = c1[hue],
+            (false, true) => c1[hue] = c0[hue],
+            _ => {}
+        }
+        c1[hue] = c0[hue] + hue_delta(c0[hue], c1[hue], interpolation.hue);
+    }
+
+    let premultiply = |mut c: [f32; 3], a: f32| {
+        for (i, channel) in c.iter_mut().enumerate() {
+            if Some(i) != hue_index {
+                *channel *= a;
+            }
+        }
+        PremultipliedColorF { r: c[0], g: c[1], b: c[2], a }
+    };
+
+    (premultiply(c0, from.a), premultiply(c1, to.a))
+}
+
 // TODO(gw): Tidy this up to be a free function / module?
 struct GradientGpuBlockBuilder {}
 
@@ -169,22 +313,24 @@ impl GradientGpuBlockBuilder {
         reverse_stops: bool,
         request: &mut GpuDataRequest,
         src_stops: &[GradientStop],
+        interpolation: GradientInterpolation,
     ) {
         // Preconditions (should be ensured by DisplayListBuilder):
         // * we have at least two stops
         // * first stop has offset 0.0
         // * last stop has offset 1.0
         let mut src_stops = src_stops.into_iter();
-        let mut cur_color = match src_stops.next() {
+        let mut cur_stop_color = match src_stops.next() {
             Some(stop) => {
                 debug_assert_eq!(stop.offset, 0.0);
-                stop.color.premultiplied()
+                stop.color
             }
             None => {
                 error!("Zero gradient stops found!");
-                PremultipliedColorF::BLACK
+                ColorF::BLACK
             }
         };
+        let cur_color = interpolation_colors(&cur_stop_color, &cur_stop_color, interpolation).0;
 
         // A table of gradient entries, with two colors per entry, that specify the start and end color
         // within the segment of the gradient space represented by that entry. To lookup a gradient result,
@@ -195,7 +341,8 @@ impl GradientGpuBlockBuilder {
         // color of an entry actually differs from the start color of the next entry, even though they fall
         // at the same edge offset in the gradient space. Instead, the two-color-per-entry layout preserves
         // hard stops, as the end color for a given entry can differ from the start color for the following
-        // entry.
+        // entry. For the same reason, colors in polar interpolation color spaces can have a different hue
+        // on each side of a stop.
         // Colors are stored in RGBA32F format (in the GPU cache). This table requires the gradient color
         // stops to be normalized to the range [0, 1]. The first and last entries hold the first and last
         // color stop colors respectively, while the entries in between hold the interpolated color stop
@@ -232,7 +379,7 @@ impl GradientGpuBlockBuilder {
             // loop will then fill indices in [GRADIENT_DATA_TABLE_BEGIN, GRADIENT_DATA_TABLE_END).
             let mut cur_idx = GRADIENT_DATA_TABLE_END;
             for next in src_stops {
-                let next_color = next.color.premultiplied();
+                let (start_color, next_color) = interpolation_colors(&cur_stop_color, &next.color, interpolation);
                 let next_idx = Self::get_index(1.0 - next.offset);
 
                 if next_idx < cur_idx {
@@ -240,20 +387,21 @@ impl GradientGpuBlockBuilder {
                         next_idx,
                         cur_idx,
                         &next_color,
-                        &cur_color,
+                        &start_color,
                         &mut entries,
                         &prev_step,
                     );
                     cur_idx = next_idx;
                 }
 
-                cur_color = next_color;
+                cur_stop_color = next.color;
             }
             if cur_idx != GRADIENT_DATA_TABLE_BEGIN {
                 error!("Gradient stops abruptly at {}, auto-completing to white", cur_idx);
             }
 
             // Fill in the last entry (for reversed stops) with the last color stop
+            let cur_color = interpolation_colors(&cur_stop_color, &cur_stop_color, interpolation).0;
             GradientGpuBlockBuilder::fill_colors(
                 GRADIENT_DATA_FIRST_STOP,
                 GRADIENT_DATA_FIRST_STOP + 1,
@@ -278,14 +426,14 @@ impl GradientGpuBlockBuilder {
             // loop will then fill indices in [GRADIENT_DATA_TABLE_BEGIN, GRADIENT_DATA_TABLE_END).
             let mut cur_idx = GRADIENT_DATA_TABLE_BEGIN;
             for next in src_stops {
-                let next_color = next.color.premultiplied();
+                let (start_color, next_color) = interpolation_colors(&cur_stop_color, &next.color, interpolation);
                 let next_idx = Self::get_index(next.offset);
 
                 if next_idx > cur_idx {
                     prev_step = GradientGpuBlockBuilder::fill_colors(
                         cur_idx,
                         next_idx,
-                        &cur_color,
+                        &start_color,
                         &next_color,
                         &mut entries,
                         &prev_step,
@@ -293,13 +441,14 @@ impl GradientGpuBlockBuilder {
                     cur_idx = next_idx;
                 }
 
-                cur_color = next_color;
+                cur_stop_color = next.color;
             }
             if cur_idx != GRADIENT_DATA_TABLE_END {
                 error!("Gradient stops abruptly at {}, auto-completing to white", cur_idx);
             }
 
             // Fill in the last entry with the last color stop
+            let cur_color = interpolation_colors(&cur_stop_color, &cur_stop_color, interpolation).0;
             GradientGpuBlockBuilder::fill_colors(
                 GRADIENT_DATA_LAST_STOP,
                 GRADIENT_DATA_LAST_STOP + 1,
@@ -375,14 +524,40 @@ fn test_struct_sizes() {
     // (b) You made a structure larger. This is not necessarily a problem, but should only
     //     be done with care, and after checking if talos performance regresses badly.
     assert_eq!(mem::size_of::<LinearGradient>(), 72, "LinearGradient size changed");
-    assert_eq!(mem::size_of::<LinearGradientTemplate>(), 152, "LinearGradientTemplate size changed");
-    assert_eq!(mem::size_of::<LinearGradientKey>(), 88, "LinearGradientKey size changed");
+    assert_eq!(mem::size_of::<LinearGradientTemplate>(), 176, "LinearGradientTemplate size changed");
+    assert_eq!(mem::size_of::<LinearGradientKey>(), 96, "LinearGradientKey size changed");
 
     assert_eq!(mem::size_of::<RadialGradient>(), 72, "RadialGradient size changed");
-    assert_eq!(mem::size_of::<RadialGradientTemplate>(), 152, "RadialGradientTemplate size changed");
+    assert_eq!(mem::size_of::<RadialGradientTemplate>(), 176, "RadialGradientTemplate size changed");
     assert_eq!(mem::size_of::<RadialGradientKey>(), 96, "RadialGradientKey size changed");
 
     assert_eq!(mem::size_of::<ConicGradient>(), 72, "ConicGradient size changed");
-    assert_eq!(mem::size_of::<ConicGradientTemplate>(), 152, "ConicGradientTemplate size changed");
+    assert_eq!(mem::size_of::<ConicGradientTemplate>(), 176, "ConicGradientTemplate size changed");
     assert_eq!(mem::size_of::<ConicGradientKey>(), 96, "ConicGradientKey size changed");
 }
+
+#[test]
+fn test_hue_interpolation() {
+    use HueInterpolationMethod::*;
+
+    assert_eq!(hue_delta(30.0, 90.0, Shorter), 60.0);
+    assert_eq!(hue_delta(0.0, 240.0, Shorter), -120.0);
+    assert_eq!(hue_delta(0.0, 240.0, Longer), 240.0);
+    assert_eq!(hue_delta(30.0, 90.0, Longer), -300.0);
+    assert_eq!(hue_delta(90.0, 90.0, Longer), 360.0);
+    assert_eq!(hue_delta(90.0, 30.0, Increasing), 300.0);
+    assert_eq!(hue_delta(30.0, 90.0, Decreasing), -300.0);
+    assert_eq!(hue_delta(90.0, 90.0, Decreasing), 0.0);
+
+    // Gray has no hue and takes the hue of blue, so only the lightness and
+    // saturation change.
+    let interpolation = GradientInterpolation {
+        color_space: GradientColorSpace::Hsl,
+        hue: Shorter,
+    };
+    let gray = ColorF::new(0.5, 0.5, 0.5, 0.5);
+    let blue = ColorF::new(0.0, 0.0, 1.0, 1.0);
+    let (start, end) = interpolation_colors(&gray, &blue, interpolation);
+    assert_eq!(start, PremultipliedColorF { r: 240.0, g: 0.0, b: 0.25, a: 0.5 });
+    assert_eq!(end, PremultipliedColorF { r: 240.0, g: 1.0, b: 0.5, a: 1.0 });
+}
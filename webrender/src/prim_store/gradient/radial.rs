@@ -9,8 +9,9 @@
 //! Radial gradients are rendered via cached render tasks and composited with the image brush.
 
 use euclid::{vec2, size2};
-use api::{ExtendMode, GradientStop, PremultipliedColorF, ColorU};
+use api::{ExtendMode, GradientColorSpace, GradientInterpolation, GradientStop, PremultipliedColorF, ColorU, RgbColorSpace};
 use api::units::*;
+use crate::color_space::pack_gradient_color_spaces;
 use crate::scene_building::IsVisible;
 use crate::frame_builder::FrameBuildingState;
 use crate::gpu_cache::{GpuCache, GpuCacheHandle};
@@ -26,9 +27,10 @@ use crate::render_task_cache::{RenderTaskCacheKeyKind, RenderTaskCacheKey, Rende
 use crate::picture::{SurfaceIndex};
 
 use std::{hash, ops::{Deref, DerefMut}};
+use crate::scene::SceneProperties;
 use super::{
-    stops_and_min_alpha, GradientStopKey, GradientGpuBlockBuilder,
-    apply_gradient_local_clip,
+    stops_and_min_alpha, GradientStopKey, GradientStopBindings, GradientGpuBlockBuilder,
+    apply_gradient_local_clip, has_animated_stops,
 };
 
 /// Hashable radial gradient parameters, for use during prim interning.
@@ -58,6 +60,8 @@ impl hash::Hash for RadialGradientParams {
 pub struct RadialGradientKey {
     pub common: PrimKeyCommonData,
     pub extend_mode: ExtendMode,
+    pub interpolation: GradientInterpolation,
+    pub output_color_space: RgbColorSpace,
     pub center: PointKey,
     pub params: RadialGradientParams,
     pub stretch_size: SizeKey,
@@ -74,6 +78,8 @@ impl RadialGradientKey {
         RadialGradientKey {
             common: info.into(),
             extend_mode: radial_grad.extend_mode,
+            interpolation: radial_grad.interpolation,
+            output_color_space: radial_grad.output_color_space,
             center: radial_grad.center,
             params: radial_grad.params,
             stretch_size: radial_grad.stretch_size,
@@ -93,6 +99,8 @@ impl InternDebug for RadialGradientKey {}
 pub struct RadialGradientTemplate {
     pub common: PrimTemplateCommonData,
     pub extend_mode: ExtendMode,
+    pub interpolation: GradientInterpolation,
+    pub output_color_space: RgbColorSpace,
     pub params: RadialGradientParams,
     pub center: DevicePoint,
     pub task_size: DeviceIntSize,
@@ -102,6 +110,7 @@ pub struct RadialGradientTemplate {
     pub brush_segments: Vec<BrushSegment>,
     pub stops_opacity: PrimitiveOpacity,
     pub stops: Vec<GradientStop>,
+    pub stop_bindings: GradientStopBindings,
     pub stops_handle: GpuCacheHandle,
     pub src_color: Option<RenderTaskId>,
 }
@@ -158,6 +167,8 @@ impl From<RadialGradientKey> for RadialGradientTemplate {
             common,
             center: DevicePoint::new(item.center.x, item.center.y),
             extend_mode: item.extend_mode,
+            interpolation: item.interpolation,
+            output_color_space: item.output_color_space,
             params: item.params,
             stretch_size,
             task_size: task_size.ceil().to_i32(),
@@ -165,6 +176,7 @@ impl From<RadialGradientKey> for RadialGradientTemplate {
             tile_spacing: item.tile_spacing.into(),
             brush_segments,
             stops_opacity,
+            stop_bindings: GradientStopBindings::new(&stops),
             stops,
             stops_handle: GpuCacheHandle::new(),
             src_color: None,
@@ -181,7 +193,12 @@ impl RadialGradientTemplate {
         &mut self,
         frame_state: &mut FrameBuildingState,
         parent_surface: SurfaceIndex,
+        scene_properties: &SceneProperties,
     ) {
+        if self.stop_bindings.resolve(&mut self.stops, scene_properties) {
+            frame_state.gpu_cache.invalidate(&self.stops_handle);
+        }
+
         if let Some(mut request) =
             frame_state.gpu_cache.request(&mut self.common.gpu_cache_handle) {
             // write_prim_gpu_blocks
@@ -209,6 +226,7 @@ impl RadialGradientTemplate {
                 false,
                 &mut request,
                 &self.stops,
+                self.interpolation,
             );
         }
 
@@ -221,6 +239,8 @@ impl RadialGradientTemplate {
             end_radius: FloatKey(self.params.end_radius),
             ratio_xy: FloatKey(self.params.ratio_xy),
             extend_mode: self.extend_mode,
+            interpolation: self.interpolation,
+            output_color_space: self.output_color_space,
             stops: self.stops.iter().map(|stop| (*stop).into()).collect(),
         };
 
@@ -240,6 +260,8 @@ impl RadialGradientTemplate {
                     task_size,
                     RenderTaskKind::RadialGradient(RadialGradientTask {
                         extend_mode: self.extend_mode,
+                        color_space: self.interpolation.color_space,
+                        output_color_space: self.output_color_space,
                         center: self.center,
                         scale: self.scale,
                         params: self.params.clone(),
@@ -266,6 +288,8 @@ pub type RadialGradientDataHandle = InternHandle<RadialGradient>;
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub struct RadialGradient {
     pub extend_mode: ExtendMode,
+    pub interpolation: GradientInterpolation,
+    pub output_color_space: RgbColorSpace,
     pub center: PointKey,
     pub params: RadialGradientParams,
     pub stretch_size: SizeKey,
@@ -313,6 +337,8 @@ impl IsVisible for RadialGradient {
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 pub struct RadialGradientTask {
     pub extend_mode: ExtendMode,
+    pub color_space: GradientColorSpace,
+    pub output_color_space: RgbColorSpace,
     pub center: DevicePoint,
     pub scale: DeviceVector2D,
     pub params: RadialGradientParams,
@@ -329,6 +355,7 @@ impl RadialGradientTask {
             end_radius: self.params.end_radius,
             ratio_xy: self.params.ratio_xy,
             extend_mode: self.extend_mode as i32,
+            color_space: pack_gradient_color_spaces(self.color_space, self.output_color_space),
             gradient_stops_address: self.stops.as_int(gpu_cache),
         }
     }
@@ -349,6 +376,7 @@ pub struct RadialGradientInstance {
     pub end_radius: f32,
     pub ratio_xy: f32,
     pub extend_mode: i32,
+    pub color_space: i32,
     pub gradient_stops_address: i32,
 }
 
@@ -363,6 +391,8 @@ pub struct RadialGradientCacheKey {
     pub end_radius: FloatKey,
     pub ratio_xy: FloatKey,
     pub extend_mode: ExtendMode,
+    pub interpolation: GradientInterpolation,
+    pub output_color_space: RgbColorSpace,
     pub stops: Vec<GradientStopKey>,
 }
 
@@ -399,7 +429,7 @@ pub fn optimize_radial_gradient(
 
     *center += offset;
 
-    if extend_mode != ExtendMode::Clamp || stops.is_empty() {
+    if extend_mode != ExtendMode::Clamp || stops.is_empty() || has_animated_stops(stops) {
         return;
     }
 
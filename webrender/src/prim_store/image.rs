@@ -3,7 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use api::{
-    AlphaType, ColorDepth, ColorF, ColorU, ExternalImageData, ExternalImageType,
+    AlphaType, ColorDepth, ColorF, ColorU, ExternalImageData, ExternalImageType, ImageFormat,
     ImageKey as ApiImageKey, ImageBufferKind, ImageRendering, PremultipliedColorF,
     RasterSpace, Shadow, YuvColorSpace, ColorRange, YuvFormat,
 };
@@ -52,6 +52,22 @@ pub struct ImageCacheKey {
     pub texel_rect: Option<DeviceIntRect>,
 }
 
+// Key that identifies an image downscaled for `ImageRendering::HighQuality`,
+// or the source rect of an image, in the render task cache. The size of the
+// cache key is the size of the downscaled image, so each level of the mip
+// chain gets its own entry, and the source rect copy is the first level.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct ScaledImageCacheKey {
+    pub image: ApiImageKey,
+    /// The part of the image that is downscaled, if not all of it.
+    pub texel_rect: Option<DeviceIntRect>,
+    /// The generation of the image template, since updating an image
+    /// doesn't invalidate the render task cache.
+    pub generation: u32,
+}
+
 /// Instance specific fields for an image primitive. These are
 /// currently stored in a separate array to avoid bloating the
 /// size of PrimitiveInstance. In the future, we should be able
@@ -83,6 +99,7 @@ pub struct Image {
     pub color: ColorU,
     pub image_rendering: ImageRendering,
     pub alpha_type: AlphaType,
+    pub source_rect: Option<DeviceIntRect>,
 }
 
 pub type ImageKey = PrimKey<Image>;
@@ -111,6 +128,9 @@ pub struct ImageData {
     pub color: ColorF,
     pub image_rendering: ImageRendering,
     pub alpha_type: AlphaType,
+    /// The part of the image that is drawn, in texels relative to the
+    /// visible rect of the image.
+    pub source_rect: Option<DeviceIntRect>,
 }
 
 impl From<Image> for ImageData {
@@ -122,6 +142,7 @@ impl From<Image> for ImageData {
             tile_spacing: image.tile_spacing.into(),
             image_rendering: image.image_rendering,
             alpha_type: image.alpha_type,
+            source_rect: image.source_rect,
         }
     }
 }
@@ -177,6 +198,13 @@ impl ImageData {
                     frame_state.gpu_cache,
                 );
 
+                // Only single channel 8-bit images are rendered as alpha;
+                // every other format, including compressed ones, has color.
+                let target_kind = match descriptor.format {
+                    ImageFormat::R8 => RenderTargetKind::Alpha,
+                    _ => RenderTargetKind::Color,
+                };
+
                 let orig_task_id = frame_state.rg_builder.add().init(
                     RenderTask::new_image(size, request)
                 );
@@ -194,12 +222,6 @@ impl ImageData {
                         })
                     )
                 {
-                    let target_kind = if descriptor.format.bytes_per_pixel() == 1 {
-                        RenderTargetKind::Alpha
-                    } else {
-                        RenderTargetKind::Color
-                    };
-
                     let task_id = RenderTask::new_scaling(
                         orig_task_id,
                         frame_state.rg_builder,
@@ -217,6 +239,85 @@ impl ImageData {
                     orig_task_id
                 };
 
+                // The part of the image that is drawn, if not all of it. It is
+                // copied to its own task, so that the rest of this path can
+                // treat it as the image. The copy is kept in the render task
+                // cache, since sprites are usually drawn from the same atlas
+                // image frame after frame.
+                let texel_rect = match self.source_rect {
+                    Some(source_rect) => match source_rect.intersection(&DeviceIntRect::from_size(size)) {
+                        Some(rect) if rect.size() == size => None,
+                        Some(rect) => Some(rect),
+                        None => {
+                            image_instance.src_color = None;
+                            visibility.reset();
+                            return;
+                        }
+                    },
+                    None => None,
+                };
+
+                let task_id = match texel_rect {
+                    // External images may change without a new generation,
+                    // so their source rect is copied every frame.
+                    Some(rect) if external_image.is_some() => {
+                        size = rect.size();
+
+                        let task_id = RenderTask::new_scaling_from_rect(
+                            task_id,
+                            frame_state.rg_builder,
+                            target_kind,
+                            size,
+                            rect,
+                        );
+
+                        frame_state.surface_builder.add_child_render_task(
+                            task_id,
+                            frame_state.rg_builder,
+                        );
+
+                        task_id
+                    }
+                    Some(rect) => {
+                        size = rect.size();
+
+                        let cache_key = ScaledImageCacheKey {
+                            image: self.key,
+                            texel_rect,
+                            generation: frame_state.resource_cache.get_image_generation(self.key).0,
+                        };
+
+                        frame_state.resource_cache.request_render_task(
+                            RenderTaskCacheKey {
+                                size,
+                                kind: RenderTaskCacheKeyKind::ScaledImage(cache_key),
+                            },
+                            frame_state.gpu_cache,
+                            frame_state.rg_builder,
+                            None,
+                            descriptor.is_opaque(),
+                            RenderTaskParent::Surface(parent_surface),
+                            &mut frame_state.surface_builder,
+                            |rg_builder| {
+                                let copy_task_id = RenderTask::new_scaling_from_rect(
+                                    task_id,
+                                    rg_builder,
+                                    target_kind,
+                                    size,
+                                    rect,
+                                );
+
+                                RenderTask::new_blit(
+                                    size,
+                                    copy_task_id,
+                                    rg_builder,
+                                )
+                            }
+                        )
+                    }
+                    None => task_id,
+                };
+
                 // Every frame, for cached items, we need to request the render
                 // task cache item. The closure will be invoked on the first
                 // time through, and any time the render task output has been
@@ -224,6 +325,73 @@ impl ImageData {
                 if self.tile_spacing == LayoutSize::zero() {
                     // Most common case.
                     image_instance.src_color = Some(task_id);
+
+                    // External images may change without a new generation,
+                    // so they are never cached downscaled.
+                    if self.image_rendering == ImageRendering::HighQuality && external_image.is_none() {
+                        // As for borders, the scale is based on the world
+                        // transform of the primitive.
+                        let (scale_x, scale_y) = frame_context
+                            .spatial_tree
+                            .get_world_transform(prim_spatial_node_index)
+                            .scale_factors();
+                        let device_pixel_scale = frame_state.surfaces[parent_surface.0].device_pixel_scale;
+                        let target_size = DeviceSize::new(
+                            self.stretch_size.width * scale_x * device_pixel_scale.0,
+                            self.stretch_size.height * scale_y * device_pixel_scale.0,
+                        );
+
+                        let mut level_size = size;
+                        while let Some(next_size) = next_mip_level_size(level_size, target_size) {
+                            level_size = next_size;
+                        }
+
+                        if level_size != size {
+                            let cache_key = ScaledImageCacheKey {
+                                image: self.key,
+                                texel_rect,
+                                generation: frame_state.resource_cache.get_image_generation(self.key).0,
+                            };
+
+                            let cached_task_handle = frame_state.resource_cache.request_render_task(
+                                RenderTaskCacheKey {
+                                    size: level_size,
+                                    kind: RenderTaskCacheKeyKind::ScaledImage(cache_key),
+                                },
+                                frame_state.gpu_cache,
+                                frame_state.rg_builder,
+                                None,
+                                descriptor.is_opaque(),
+                                RenderTaskParent::Surface(parent_surface),
+                                &mut frame_state.surface_builder,
+                                |rg_builder| {
+                                    // Halve the image until it reaches the size of
+                                    // the cache entry. Each level is sampled at the
+                                    // corners of the texels of the previous one,
+                                    // so that bilinear filtering averages 2x2 texels.
+                                    let mut level_task_id = task_id;
+                                    let mut level_size = size;
+                                    while let Some(next_size) = next_mip_level_size(level_size, target_size) {
+                                        level_task_id = RenderTask::new_scaling(
+                                            level_task_id,
+                                            rg_builder,
+                                            target_kind,
+                                            next_size,
+                                        );
+                                        level_size = next_size;
+                                    }
+
+                                    RenderTask::new_blit(
+                                        level_size,
+                                        level_task_id,
+                                        rg_builder,
+                                    )
+                                }
+                            );
+
+                            image_instance.src_color = Some(cached_task_handle);
+                        }
+                    }
                 } else {
                     let padding = DeviceIntSideOffsets::new(
                         0,
@@ -241,12 +409,7 @@ impl ImageData {
 
                     let image_cache_key = ImageCacheKey {
                         request,
-                        texel_rect: None,
-                    };
-                    let target_kind = if descriptor.format.bytes_per_pixel() == 1 {
-                        RenderTargetKind::Alpha
-                    } else {
-                        RenderTargetKind::Color
+                        texel_rect,
                     };
 
                     // Request a pre-rendered image task.
@@ -315,6 +478,19 @@ impl ImageData {
 
                 let base_edge_flags = edge_flags_for_tile_spacing(&self.tile_spacing);
 
+                // The part of the image that is repeated, in the same space
+                // as the active rect.
+                let source_rect = match self.source_rect {
+                    Some(rect) => match rect.translate(active_rect.min.to_vector()).intersection(&active_rect) {
+                        Some(rect) => Some(rect),
+                        None => {
+                            visibility.reset();
+                            return;
+                        }
+                    },
+                    None => None,
+                };
+
                 let stride = self.stretch_size + self.tile_spacing;
 
                 // We are performing the decomposition on the CPU here, no need to
@@ -335,9 +511,40 @@ impl ImageData {
                         self.stretch_size,
                     );
 
+                    // With a source rect, the whole image is laid out so that
+                    // the source rect covers the repetition, and the tiles are
+                    // clipped to the repetition.
+                    let (layout_tiled_rect, tile_visible_rect, tile_clip_rect) = match source_rect {
+                        Some(source_rect) => {
+                            let scale = LayoutSize::new(
+                                self.stretch_size.width / source_rect.width() as f32,
+                                self.stretch_size.height / source_rect.height() as f32,
+                            );
+                            let offset = (source_rect.min - active_rect.min).to_f32();
+                            let layout_tiled_rect = LayoutRect::from_origin_and_size(
+                                LayoutPoint::new(
+                                    origin.x - offset.x * scale.width,
+                                    origin.y - offset.y * scale.height,
+                                ),
+                                LayoutSize::new(
+                                    active_rect.width() as f32 * scale.width,
+                                    active_rect.height() as f32 * scale.height,
+                                ),
+                            );
+
+                            let tile_visible_rect = visible_rect.intersection(&layout_image_rect);
+                            let tile_clip_rect = tight_clip_rect.intersection(&layout_image_rect);
+                            match (tile_visible_rect, tile_clip_rect) {
+                                (Some(visible), Some(clip)) => (layout_tiled_rect, visible, clip),
+                                _ => continue,
+                            }
+                        }
+                        None => (layout_image_rect, visible_rect, tight_clip_rect),
+                    };
+
                     let tiles = image_tiling::tiles(
-                        &layout_image_rect,
-                        &visible_rect,
+                        &layout_tiled_rect,
+                        &tile_visible_rect,
                         &active_rect,
                         tile_size as i32,
                     );
@@ -357,7 +564,7 @@ impl ImageData {
                             src_color: task_id,
                             edge_flags: tile.edge_flags & edge_flags,
                             local_rect: tile.rect,
-                            local_clip_rect: tight_clip_rect,
+                            local_clip_rect: tile_clip_rect,
                         });
                     }
                 }
@@ -405,6 +612,30 @@ fn edge_flags_for_tile_spacing(tile_spacing: &LayoutSize) -> EdgeAaSegmentMask {
     flags
 }
 
+/// Returns the size of the next level of the mip chain of an image level of
+/// `size`, drawn at `target_size`. Only the dimensions that are at least twice
+/// as large as the target are halved, and None is returned when there are none.
+fn next_mip_level_size(size: DeviceIntSize, target_size: DeviceSize) -> Option<DeviceIntSize> {
+    let halve = |size: i32, target_size: f32| {
+        if size as f32 >= 2.0 * target_size.max(1.0) {
+            (size + 1) / 2
+        } else {
+            size
+        }
+    };
+
+    let next_size = DeviceIntSize::new(
+        halve(size.width, target_size.width),
+        halve(size.height, target_size.height),
+    );
+
+    if next_size == size {
+        None
+    } else {
+        Some(next_size)
+    }
+}
+
 pub type ImageTemplate = PrimTemplate<ImageData>;
 
 impl From<ImageKey> for ImageTemplate {
@@ -472,6 +703,7 @@ impl CreateShadow for Image {
             image_rendering: self.image_rendering,
             alpha_type: self.alpha_type,
             color: shadow.color.into(),
+            source_rect: self.source_rect,
         }
     }
 }
@@ -671,10 +903,41 @@ fn test_struct_sizes() {
     //     test expectations and move on.
     // (b) You made a structure larger. This is not necessarily a problem, but should only
     //     be done with care, and after checking if talos performance regresses badly.
-    assert_eq!(mem::size_of::<Image>(), 32, "Image size changed");
-    assert_eq!(mem::size_of::<ImageTemplate>(), 72, "ImageTemplate size changed");
-    assert_eq!(mem::size_of::<ImageKey>(), 52, "ImageKey size changed");
+    assert_eq!(mem::size_of::<Image>(), 52, "Image size changed");
+    assert_eq!(mem::size_of::<ImageTemplate>(), 92, "ImageTemplate size changed");
+    assert_eq!(mem::size_of::<ImageKey>(), 72, "ImageKey size changed");
     assert_eq!(mem::size_of::<YuvImage>(), 32, "YuvImage size changed");
     assert_eq!(mem::size_of::<YuvImageTemplate>(), 84, "YuvImageTemplate size changed");
     assert_eq!(mem::size_of::<YuvImageKey>(), 52, "YuvImageKey size changed");
 }
+
+#[test]
+fn mip_level_sizes() {
+    let levels = |size: DeviceIntSize, target_size: DeviceSize| {
+        let mut levels = Vec::new();
+        let mut size = size;
+        while let Some(next_size) = next_mip_level_size(size, target_size) {
+            levels.push(next_size);
+            size = next_size;
+        }
+        levels
+    };
+
+    // Not minified enough to need downscaling.
+    assert_eq!(levels(DeviceIntSize::new(256, 256), DeviceSize::new(129.0, 200.0)), vec![]);
+    // Stops at the last level that is larger than the target.
+    assert_eq!(
+        levels(DeviceIntSize::new(256, 256), DeviceSize::new(40.0, 40.0)),
+        vec![DeviceIntSize::new(128, 128), DeviceIntSize::new(64, 64)],
+    );
+    // Each dimension is halved independently, rounding up.
+    assert_eq!(
+        levels(DeviceIntSize::new(101, 20), DeviceSize::new(25.0, 20.0)),
+        vec![DeviceIntSize::new(51, 20), DeviceIntSize::new(26, 20)],
+    );
+    // Empty targets stop at a single texel.
+    assert_eq!(
+        levels(DeviceIntSize::new(3, 1), DeviceSize::zero()),
+        vec![DeviceIntSize::new(2, 1), DeviceIntSize::new(1, 1)],
+    );
+}
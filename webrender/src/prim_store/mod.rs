@@ -5,13 +5,14 @@
 use api::{BorderRadius, ClipMode, ColorF, ColorU, RasterSpace};
 use api::{ImageRendering, RepeatMode, PrimitiveFlags};
 use api::{PremultipliedColorF, PropertyBinding, Shadow};
-use api::{PrimitiveKeyKind, FillRule, POLYGON_CLIP_VERTEX_MAX};
+use api::{PrimitiveKeyKind, FillRule, RgbColorSpace};
 use api::units::*;
 use euclid::{SideOffsets2D, Size2D};
 use malloc_size_of::MallocSizeOf;
 use crate::segment::EdgeAaSegmentMask;
 use crate::border::BorderSegmentCacheKey;
 use crate::clip::{ClipChainId, ClipSet};
+use crate::color_space::convert_color;
 use crate::debug_item::{DebugItem, DebugMessage};
 use crate::debug_colors;
 use crate::scene_building::{CreateShadow, IsVisible};
@@ -38,6 +39,7 @@ pub mod borders;
 pub mod gradient;
 pub mod image;
 pub mod line_dec;
+pub mod path;
 pub mod picture;
 pub mod text_run;
 pub mod interned;
@@ -49,6 +51,7 @@ use borders::{ImageBorderDataHandle, NormalBorderDataHandle};
 use gradient::{LinearGradientPrimitive, LinearGradientDataHandle, RadialGradientDataHandle, ConicGradientDataHandle};
 use image::{ImageDataHandle, ImageInstance, YuvImageDataHandle};
 use line_dec::LineDecorationDataHandle;
+use path::PathDataHandle;
 use picture::PictureDataHandle;
 use text_run::{TextRunDataHandle, TextRunPrimitive};
 
@@ -205,16 +208,14 @@ impl From<WorldRect> for RectangleKey {
     }
 }
 
-/// To create a fixed-size representation of a polygon, we use a fixed
-/// number of points. Our initialization method restricts us to values
-/// <= 32. If our constant POLYGON_CLIP_VERTEX_MAX is > 32, the Rust
-/// compiler will complain.
+/// A hashable representation of a polygon, used both for the hit-testing
+/// polygons attached to image mask clips and for analytic polygon clips.
+/// The points are relative to the origin of the clip rect they belong to.
 #[cfg_attr(feature = "capture", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
-#[derive(Copy, Debug, Clone, Hash, MallocSizeOf, PartialEq)]
+#[derive(Debug, Clone, Hash, MallocSizeOf, PartialEq)]
 pub struct PolygonKey {
-    pub point_count: u8,
-    pub points: [PointKey; POLYGON_CLIP_VERTEX_MAX],
+    pub points: Vec<PointKey>,
     pub fill_rule: FillRule,
 }
 
@@ -223,21 +224,8 @@ impl PolygonKey {
         points_layout: &Vec<LayoutPoint>,
         fill_rule: FillRule,
     ) -> Self {
-        // We have to fill fixed-size arrays with data from a Vec.
-        // We'll do this by initializing the arrays to known-good
-        // values then overwriting those values as long as our
-        // iterator provides values.
-        let mut points: [PointKey; POLYGON_CLIP_VERTEX_MAX] = [PointKey { x: 0.0, y: 0.0}; POLYGON_CLIP_VERTEX_MAX];
-
-        let mut point_count: u8 = 0;
-        for (src, dest) in points_layout.iter().zip(points.iter_mut()) {
-            *dest = (*src as LayoutPoint).into();
-            point_count = point_count + 1;
-        }
-
         PolygonKey {
-            point_count,
-            points,
+            points: points_layout.iter().map(|point| (*point).into()).collect(),
             fill_rule,
         }
     }
@@ -491,6 +479,7 @@ impl intern::InternDebug for PrimitiveKey {}
 pub enum PrimitiveTemplateKind {
     Rectangle {
         color: PropertyBinding<ColorF>,
+        color_space: RgbColorSpace,
     },
     Clear,
 }
@@ -501,19 +490,40 @@ impl PrimitiveTemplateKind {
         &self,
         request: &mut GpuDataRequest,
         scene_properties: &SceneProperties,
+        output_color_space: RgbColorSpace,
     ) {
         match *self {
             PrimitiveTemplateKind::Clear => {
                 // Opaque black with operator dest out
                 request.push(PremultipliedColorF::BLACK);
             }
-            PrimitiveTemplateKind::Rectangle { ref color, .. } => {
-                request.push(scene_properties.resolve_color(color).premultiplied())
+            PrimitiveTemplateKind::Rectangle { ref color, color_space } => {
+                let color = resolve_rectangle_color(color, color_space, scene_properties, output_color_space);
+                request.push(color.premultiplied())
             }
         }
     }
 }
 
+/// Resolve the color of a rectangle primitive. Colors given by value were
+/// converted to the output color space during scene building, while animated
+/// colors are converted once resolved.
+pub fn resolve_rectangle_color(
+    color: &PropertyBinding<ColorF>,
+    color_space: RgbColorSpace,
+    scene_properties: &SceneProperties,
+    output_color_space: RgbColorSpace,
+) -> ColorF {
+    match *color {
+        PropertyBinding::Value(value) => value,
+        PropertyBinding::Binding(..) => convert_color(
+            scene_properties.resolve_color(color),
+            color_space,
+            output_color_space,
+        ),
+    }
+}
+
 /// Construct the primitive template data from a primitive key. This
 /// is invoked when a primitive key is created and the interner
 /// doesn't currently contain a primitive with this key.
@@ -523,9 +533,10 @@ impl From<PrimitiveKeyKind> for PrimitiveTemplateKind {
             PrimitiveKeyKind::Clear => {
                 PrimitiveTemplateKind::Clear
             }
-            PrimitiveKeyKind::Rectangle { color, .. } => {
+            PrimitiveKeyKind::Rectangle { color, color_space } => {
                 PrimitiveTemplateKind::Rectangle {
                     color: color.into(),
+                    color_space,
                 }
             }
         }
@@ -607,9 +618,10 @@ impl PrimitiveTemplate {
         &mut self,
         frame_state: &mut FrameBuildingState,
         scene_properties: &SceneProperties,
+        output_color_space: RgbColorSpace,
     ) {
         if let Some(mut request) = frame_state.gpu_cache.request(&mut self.common.gpu_cache_handle) {
-            self.kind.write_prim_gpu_blocks(&mut request, scene_properties);
+            self.kind.write_prim_gpu_blocks(&mut request, scene_properties, output_color_space);
         }
 
         self.opacity = match self.kind {
@@ -950,6 +962,7 @@ impl CreateShadow for PrimitiveKeyKind {
             PrimitiveKeyKind::Rectangle { .. } => {
                 PrimitiveKeyKind::Rectangle {
                     color: PropertyBinding::Value(shadow.color.into()),
+                    color_space: RgbColorSpace::Srgb,
                 }
             }
             PrimitiveKeyKind::Clear => {
@@ -996,6 +1009,12 @@ pub enum PrimitiveInstanceKind {
         //           but also the opacity, clip_task_id etc below.
         render_task: Option<RenderTaskId>,
     },
+    /// A vector path, drawn from a cached coverage mask render task.
+    Path {
+        /// Handle to the common interned data for this primitive.
+        data_handle: PathDataHandle,
+        render_task: Option<RenderTaskId>,
+    },
     NormalBorder {
         /// Handle to the common interned data for this primitive.
         data_handle: NormalBorderDataHandle,
@@ -1161,6 +1180,9 @@ impl PrimitiveInstance {
             PrimitiveInstanceKind::LineDecoration { data_handle, .. } => {
                 data_handle.uid()
             }
+            PrimitiveInstanceKind::Path { data_handle, .. } => {
+                data_handle.uid()
+            }
             PrimitiveInstanceKind::LinearGradient { data_handle, .. } => {
                 data_handle.uid()
             }
@@ -1477,8 +1499,8 @@ fn test_struct_sizes() {
     //     be done with care, and after checking if talos performance regresses badly.
     assert_eq!(mem::size_of::<PrimitiveInstance>(), 152, "PrimitiveInstance size changed");
     assert_eq!(mem::size_of::<PrimitiveInstanceKind>(), 24, "PrimitiveInstanceKind size changed");
-    assert_eq!(mem::size_of::<PrimitiveTemplate>(), 56, "PrimitiveTemplate size changed");
-    assert_eq!(mem::size_of::<PrimitiveTemplateKind>(), 28, "PrimitiveTemplateKind size changed");
-    assert_eq!(mem::size_of::<PrimitiveKey>(), 36, "PrimitiveKey size changed");
-    assert_eq!(mem::size_of::<PrimitiveKeyKind>(), 16, "PrimitiveKeyKind size changed");
+    assert_eq!(mem::size_of::<PrimitiveTemplate>(), 60, "PrimitiveTemplate size changed");
+    assert_eq!(mem::size_of::<PrimitiveTemplateKind>(), 32, "PrimitiveTemplateKind size changed");
+    assert_eq!(mem::size_of::<PrimitiveKey>(), 40, "PrimitiveKey size changed");
+    assert_eq!(mem::size_of::<PrimitiveKeyKind>(), 20, "PrimitiveKeyKind size changed");
 }
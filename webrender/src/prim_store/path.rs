@@ -0,0 +1,267 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use api::{ColorF, ColorU, FillRule, LineCap, LineJoin, PathSegment, PremultipliedColorF, StrokeStyle};
+use api::units::*;
+use crate::scene_building::IsVisible;
+use crate::frame_builder::FrameBuildingState;
+use crate::gpu_cache::GpuDataRequest;
+use crate::intern;
+use crate::internal_types::LayoutPrimitiveInfo;
+use crate::prim_store::{
+    PointKey, PrimKey, PrimTemplate, PrimTemplateCommonData,
+    InternablePrimitive, PrimitiveStore,
+};
+use crate::prim_store::PrimitiveInstanceKind;
+use std::hash;
+
+/// Maximum resolution in device pixels at which paths are rasterized.
+pub const MAX_PATH_RESOLUTION: u32 = 4096;
+
+/// A hashable path segment. Points are relative to the origin of the
+/// primitive, so that translated copies of a path share the same mask.
+#[derive(Clone, Copy, Debug, Hash, MallocSizeOf, PartialEq, Eq)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub enum PathSegmentKey {
+    MoveTo(PointKey),
+    LineTo(PointKey),
+    QuadraticTo(PointKey, PointKey),
+    CubicTo(PointKey, PointKey, PointKey),
+    Close,
+}
+
+impl PathSegmentKey {
+    pub fn new(segment: &PathSegment, origin: LayoutPoint) -> Self {
+        let key = |p: LayoutPoint| PointKey::from(p - origin.to_vector());
+        match *segment {
+            PathSegment::MoveTo(to) => PathSegmentKey::MoveTo(key(to)),
+            PathSegment::LineTo(to) => PathSegmentKey::LineTo(key(to)),
+            PathSegment::QuadraticTo(ctrl, to) => PathSegmentKey::QuadraticTo(key(ctrl), key(to)),
+            PathSegment::CubicTo(ctrl1, ctrl2, to) => {
+                PathSegmentKey::CubicTo(key(ctrl1), key(ctrl2), key(to))
+            }
+            PathSegment::Close => PathSegmentKey::Close,
+        }
+    }
+}
+
+impl From<PathSegmentKey> for PathSegment {
+    fn from(key: PathSegmentKey) -> PathSegment {
+        match key {
+            PathSegmentKey::MoveTo(to) => PathSegment::MoveTo(to.into()),
+            PathSegmentKey::LineTo(to) => PathSegment::LineTo(to.into()),
+            PathSegmentKey::QuadraticTo(ctrl, to) => PathSegment::QuadraticTo(ctrl.into(), to.into()),
+            PathSegmentKey::CubicTo(ctrl1, ctrl2, to) => {
+                PathSegment::CubicTo(ctrl1.into(), ctrl2.into(), to.into())
+            }
+            PathSegmentKey::Close => PathSegment::Close,
+        }
+    }
+}
+
+/// A hashable stroke style.
+#[derive(Clone, Debug, MallocSizeOf, PartialEq)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct StrokeKey {
+    pub width: f32,
+    pub line_join: LineJoin,
+    pub line_cap: LineCap,
+    pub miter_limit: f32,
+    pub dash_offset: f32,
+    pub dashes: Vec<f32>,
+}
+
+impl Eq for StrokeKey {}
+
+impl hash::Hash for StrokeKey {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.width.to_bits().hash(state);
+        self.line_join.hash(state);
+        self.line_cap.hash(state);
+        self.miter_limit.to_bits().hash(state);
+        self.dash_offset.to_bits().hash(state);
+        for dash in &self.dashes {
+            dash.to_bits().hash(state);
+        }
+    }
+}
+
+impl From<StrokeStyle> for StrokeKey {
+    fn from(stroke: StrokeStyle) -> StrokeKey {
+        StrokeKey {
+            width: stroke.width,
+            line_join: stroke.line_join,
+            line_cap: stroke.line_cap,
+            miter_limit: stroke.miter_limit,
+            dash_offset: stroke.dash_offset,
+            dashes: stroke.dashes().to_vec(),
+        }
+    }
+}
+
+impl From<&StrokeKey> for StrokeStyle {
+    fn from(key: &StrokeKey) -> StrokeStyle {
+        let stroke = StrokeStyle::new(key.width, key.line_join, key.line_cap);
+        StrokeStyle {
+            miter_limit: key.miter_limit,
+            ..stroke.with_dashes(&key.dashes, key.dash_offset)
+        }
+    }
+}
+
+/// Identifies the coverage mask of a path, independently of its color.
+#[derive(Clone, Debug, Hash, MallocSizeOf, PartialEq, Eq)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct PathCacheKey {
+    pub segments: Vec<PathSegmentKey>,
+    pub fill_rule: FillRule,
+    pub stroke: Option<StrokeKey>,
+    pub size: LayoutSizeAu,
+}
+
+impl PathCacheKey {
+    pub fn segments(&self) -> Vec<PathSegment> {
+        self.segments.iter().map(|segment| (*segment).into()).collect()
+    }
+}
+
+/// Identifying key for a path.
+#[derive(Clone, Debug, Hash, MallocSizeOf, PartialEq, Eq)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct Path {
+    pub cache_key: PathCacheKey,
+    pub color: ColorU,
+}
+
+pub type PathKey = PrimKey<Path>;
+
+impl PathKey {
+    pub fn new(
+        info: &LayoutPrimitiveInfo,
+        path: Path,
+    ) -> Self {
+        PathKey {
+            common: info.into(),
+            kind: path,
+        }
+    }
+}
+
+impl intern::InternDebug for PathKey {}
+
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+#[derive(MallocSizeOf)]
+pub struct PathData {
+    pub cache_key: PathCacheKey,
+    pub color: ColorF,
+}
+
+impl PathData {
+    /// Update the GPU cache for a given primitive template. This may be called multiple
+    /// times per frame, by each primitive reference that refers to this interned
+    /// template. The initial request call to the GPU cache ensures that work is only
+    /// done if the cache entry is invalid (due to first use or eviction).
+    pub fn update(
+        &mut self,
+        common: &mut PrimTemplateCommonData,
+        frame_state: &mut FrameBuildingState,
+    ) {
+        if let Some(ref mut request) = frame_state.gpu_cache.request(&mut common.gpu_cache_handle) {
+            self.write_prim_gpu_blocks(request);
+        }
+    }
+
+    fn write_prim_gpu_blocks(
+        &self,
+        request: &mut GpuDataRequest
+    ) {
+        // The coverage mask is drawn as a white image brush, tinted by the color.
+        request.push(self.color.premultiplied());
+        request.push(PremultipliedColorF::WHITE);
+        request.push([
+            self.cache_key.size.width.to_f32_px(),
+            self.cache_key.size.height.to_f32_px(),
+            0.0,
+            0.0,
+        ]);
+    }
+}
+
+pub type PathTemplate = PrimTemplate<PathData>;
+
+impl From<PathKey> for PathTemplate {
+    fn from(path: PathKey) -> Self {
+        let common = PrimTemplateCommonData::with_key_common(path.common);
+        PathTemplate {
+            common,
+            kind: PathData {
+                cache_key: path.kind.cache_key,
+                color: path.kind.color.into(),
+            }
+        }
+    }
+}
+
+pub type PathDataHandle = intern::Handle<Path>;
+
+impl intern::Internable for Path {
+    type Key = PathKey;
+    type StoreData = PathTemplate;
+    type InternData = ();
+    const PROFILE_COUNTER: usize = crate::profiler::INTERNED_PATHS;
+}
+
+impl InternablePrimitive for Path {
+    fn into_key(
+        self,
+        info: &LayoutPrimitiveInfo,
+    ) -> PathKey {
+        PathKey::new(
+            info,
+            self,
+        )
+    }
+
+    fn make_instance_kind(
+        _key: PathKey,
+        data_handle: PathDataHandle,
+        _: &mut PrimitiveStore,
+        _reference_frame_relative_offset: LayoutVector2D,
+    ) -> PrimitiveInstanceKind {
+        PrimitiveInstanceKind::Path {
+            data_handle,
+            render_task: None,
+        }
+    }
+}
+
+impl IsVisible for Path {
+    fn is_visible(&self) -> bool {
+        self.color.a > 0 && !self.cache_key.segments.is_empty()
+    }
+}
+
+#[test]
+fn path_segment_key_round_trip() {
+    let origin = LayoutPoint::new(10.0, 20.0);
+    let segment = PathSegment::CubicTo(
+        LayoutPoint::new(15.0, 20.0),
+        LayoutPoint::new(20.0, 25.0),
+        LayoutPoint::new(20.0, 30.0),
+    );
+    let key = PathSegmentKey::new(&segment, origin);
+    assert_eq!(
+        PathSegment::from(key),
+        PathSegment::CubicTo(
+            LayoutPoint::new(5.0, 0.0),
+            LayoutPoint::new(10.0, 5.0),
+            LayoutPoint::new(10.0, 10.0),
+        ),
+    );
+}
@@ -5,6 +5,8 @@
 use api::{
     ColorU, MixBlendMode, FilterPrimitiveInput, FilterPrimitiveKind, ColorSpace,
     PropertyBinding, PropertyBindingId, CompositeOperator, RasterSpace,
+    TurbulenceNoiseType, MorphologyOperator, EdgeMode, ColorChannel, LightSource,
+    ImageKey, ImageRendering,
 };
 use api::units::{Au, LayoutVector2D};
 use crate::scene_building::IsVisible;
@@ -14,8 +16,8 @@ use crate::intern::{Internable, InternDebug, Handle as InternHandle};
 use crate::internal_types::{LayoutPrimitiveInfo, Filter};
 use crate::picture::PictureCompositeMode;
 use crate::prim_store::{
-    PrimitiveInstanceKind, PrimitiveStore, VectorKey,
-    InternablePrimitive,
+    PrimitiveInstanceKind, PrimitiveStore, VectorKey, RectangleKey,
+    InternablePrimitive, FloatKey,
 };
 
 #[cfg_attr(feature = "capture", derive(Serialize))]
@@ -53,6 +55,44 @@ impl From<CompositeOperator> for CompositeOperatorKey {
     }
 }
 
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+#[derive(Debug, Clone, MallocSizeOf, PartialEq, Hash, Eq)]
+pub enum LightSourceKey {
+    Distant(FloatKey, FloatKey),
+    Point([FloatKey; 3]),
+    Spot([FloatKey; 3], [FloatKey; 3], FloatKey, FloatKey),
+}
+
+impl From<LightSource> for LightSourceKey {
+    fn from(light: LightSource) -> Self {
+        match light {
+            LightSource::Distant(light) => {
+                LightSourceKey::Distant(FloatKey(light.azimuth), FloatKey(light.elevation))
+            }
+            LightSource::Point(light) => {
+                LightSourceKey::Point([
+                    FloatKey(light.x),
+                    FloatKey(light.y),
+                    FloatKey(light.z),
+                ])
+            }
+            LightSource::Spot(light) => {
+                LightSourceKey::Spot(
+                    [FloatKey(light.x), FloatKey(light.y), FloatKey(light.z)],
+                    [
+                        FloatKey(light.points_at_x),
+                        FloatKey(light.points_at_y),
+                        FloatKey(light.points_at_z),
+                    ],
+                    FloatKey(light.specular_exponent),
+                    FloatKey(light.limiting_cone_angle),
+                )
+            }
+        }
+    }
+}
+
 #[cfg_attr(feature = "capture", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 #[derive(Debug, Clone, MallocSizeOf, PartialEq, Hash, Eq)]
@@ -67,6 +107,39 @@ pub enum FilterPrimitiveKey {
     ComponentTransfer(ColorSpace, FilterPrimitiveInput, Vec<SFilterData>),
     Offset(ColorSpace, FilterPrimitiveInput, VectorKey),
     Composite(ColorSpace, FilterPrimitiveInput, FilterPrimitiveInput, CompositeOperatorKey),
+    Turbulence(ColorSpace, (FloatKey, FloatKey), u32, i32, Option<RectangleKey>, TurbulenceNoiseType),
+    Morphology(ColorSpace, MorphologyOperator, Au, Au, FilterPrimitiveInput),
+    ConvolveMatrix(ColorSpace, FilterPrimitiveInput, (u32, u32), Vec<FloatKey>, (FloatKey, FloatKey), (u32, u32), EdgeMode, bool),
+    DisplacementMap(ColorSpace, FilterPrimitiveInput, FilterPrimitiveInput, Au, ColorChannel, ColorChannel),
+    DiffuseLighting(ColorSpace, FilterPrimitiveInput, FloatKey, FloatKey, ColorU, LightSourceKey),
+    SpecularLighting(ColorSpace, FilterPrimitiveInput, FloatKey, FloatKey, FloatKey, ColorU, LightSourceKey),
+    Tile(ColorSpace, FilterPrimitiveInput, RectangleKey),
+    Image(ColorSpace, ImageKey, ImageRendering, RectangleKey),
+    Merge(ColorSpace, Vec<FilterPrimitiveInput>),
+}
+
+/// A filter argument in a picture composite key. Animated arguments are
+/// identified by their binding rather than their current value, so that
+/// the picture doesn't need to be interned again when they change.
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+#[derive(Debug, Clone, Copy, MallocSizeOf, PartialEq, Hash, Eq)]
+pub enum FilterArgKey {
+    Value(Au),
+    Binding(PropertyBindingId, Au),
+}
+
+impl From<PropertyBinding<f32>> for FilterArgKey {
+    fn from(binding: PropertyBinding<f32>) -> Self {
+        match binding {
+            PropertyBinding::Value(value) => {
+                FilterArgKey::Value(Au::from_f32_px(value))
+            }
+            PropertyBinding::Binding(key, default) => {
+                FilterArgKey::Binding(key.id, Au::from_f32_px(default))
+            }
+        }
+    }
 }
 
 /// Represents a hashable description of how a picture primitive
@@ -79,16 +152,16 @@ pub enum PictureCompositeKey {
     Identity,
 
     // FilterOp
-    Blur(Au, Au, bool),
-    Brightness(Au),
-    Contrast(Au),
-    Grayscale(Au),
-    HueRotate(Au),
-    Invert(Au),
+    Blur(FilterArgKey, FilterArgKey, bool),
+    Brightness(FilterArgKey),
+    Contrast(FilterArgKey),
+    Grayscale(FilterArgKey),
+    HueRotate(FilterArgKey),
+    Invert(FilterArgKey),
     Opacity(Au),
     OpacityBinding(PropertyBindingId, Au),
-    Saturate(Au),
-    Sepia(Au),
+    Saturate(FilterArgKey),
+    Sepia(FilterArgKey),
     DropShadows(Vec<(VectorKey, Au, ColorU)>),
     ColorMatrix([Au; 20]),
     SrgbToLinear,
@@ -142,15 +215,15 @@ impl From<Option<PictureCompositeMode>> for PictureCompositeKey {
             }
             Some(PictureCompositeMode::Filter(op)) => {
                 match op {
-                    Filter::Blur { width, height, should_inflate } =>
-                        PictureCompositeKey::Blur(Au::from_f32_px(width), Au::from_f32_px(height), should_inflate),
-                    Filter::Brightness(value) => PictureCompositeKey::Brightness(Au::from_f32_px(value)),
-                    Filter::Contrast(value) => PictureCompositeKey::Contrast(Au::from_f32_px(value)),
-                    Filter::Grayscale(value) => PictureCompositeKey::Grayscale(Au::from_f32_px(value)),
-                    Filter::HueRotate(value) => PictureCompositeKey::HueRotate(Au::from_f32_px(value)),
-                    Filter::Invert(value) => PictureCompositeKey::Invert(Au::from_f32_px(value)),
-                    Filter::Saturate(value) => PictureCompositeKey::Saturate(Au::from_f32_px(value)),
-                    Filter::Sepia(value) => PictureCompositeKey::Sepia(Au::from_f32_px(value)),
+                    Filter::Blur { width_binding, height_binding, should_inflate, .. } =>
+                        PictureCompositeKey::Blur(width_binding.into(), height_binding.into(), should_inflate),
+                    Filter::Brightness(binding, _) => PictureCompositeKey::Brightness(binding.into()),
+                    Filter::Contrast(binding, _) => PictureCompositeKey::Contrast(binding.into()),
+                    Filter::Grayscale(binding, _) => PictureCompositeKey::Grayscale(binding.into()),
+                    Filter::HueRotate(binding, _) => PictureCompositeKey::HueRotate(binding.into()),
+                    Filter::Invert(binding, _) => PictureCompositeKey::Invert(binding.into()),
+                    Filter::Saturate(binding, _) => PictureCompositeKey::Saturate(binding.into()),
+                    Filter::Sepia(binding, _) => PictureCompositeKey::Sepia(binding.into()),
                     Filter::SrgbToLinear => PictureCompositeKey::SrgbToLinear,
                     Filter::LinearToSrgb => PictureCompositeKey::LinearToSrgb,
                     Filter::Identity => PictureCompositeKey::Identity,
@@ -219,6 +292,70 @@ impl From<Option<PictureCompositeMode>> for PictureCompositeKey {
                             FilterPrimitiveKey::Offset(primitive.color_space, info.input, info.offset.into()),
                         FilterPrimitiveKind::Composite(info) =>
                             FilterPrimitiveKey::Composite(primitive.color_space, info.input1, info.input2, info.operator.into()),
+                        FilterPrimitiveKind::Turbulence(info) =>
+                            FilterPrimitiveKey::Turbulence(
+                                primitive.color_space,
+                                (FloatKey(info.base_frequency_x), FloatKey(info.base_frequency_y)),
+                                info.num_octaves,
+                                info.seed,
+                                if info.stitch_tiles { Some(info.stitch_rect.into()) } else { None },
+                                info.noise_type,
+                            ),
+                        FilterPrimitiveKind::Morphology(info) =>
+                            FilterPrimitiveKey::Morphology(
+                                primitive.color_space,
+                                info.operator,
+                                Au::from_f32_px(info.radius_x),
+                                Au::from_f32_px(info.radius_y),
+                                info.input,
+                            ),
+                        FilterPrimitiveKind::ConvolveMatrix(info) => {
+                            let kernel_size = (info.order_x * info.order_y) as usize;
+                            FilterPrimitiveKey::ConvolveMatrix(
+                                primitive.color_space,
+                                info.input,
+                                (info.order_x, info.order_y),
+                                info.kernel[.. kernel_size].iter().map(|value| FloatKey(*value)).collect(),
+                                (FloatKey(info.divisor), FloatKey(info.bias)),
+                                (info.target_x, info.target_y),
+                                info.edge_mode,
+                                info.preserve_alpha,
+                            )
+                        }
+                        FilterPrimitiveKind::DisplacementMap(info) =>
+                            FilterPrimitiveKey::DisplacementMap(
+                                primitive.color_space,
+                                info.input1,
+                                info.input2,
+                                Au::from_f32_px(info.scale),
+                                info.x_channel,
+                                info.y_channel,
+                            ),
+                        FilterPrimitiveKind::DiffuseLighting(info) =>
+                            FilterPrimitiveKey::DiffuseLighting(
+                                primitive.color_space,
+                                info.input,
+                                FloatKey(info.surface_scale),
+                                FloatKey(info.diffuse_constant),
+                                info.lighting_color.into(),
+                                info.light.into(),
+                            ),
+                        FilterPrimitiveKind::SpecularLighting(info) =>
+                            FilterPrimitiveKey::SpecularLighting(
+                                primitive.color_space,
+                                info.input,
+                                FloatKey(info.surface_scale),
+                                FloatKey(info.specular_constant),
+                                FloatKey(info.specular_exponent),
+                                info.lighting_color.into(),
+                                info.light.into(),
+                            ),
+                        FilterPrimitiveKind::Tile(info) =>
+                            FilterPrimitiveKey::Tile(primitive.color_space, info.input, info.source_rect.into()),
+                        FilterPrimitiveKind::Image(info) =>
+                            FilterPrimitiveKey::Image(primitive.color_space, info.image_key, info.rendering, info.rect.into()),
+                        FilterPrimitiveKind::Merge(info) =>
+                            FilterPrimitiveKey::Merge(primitive.color_space, info.inputs().to_vec()),
                     }
                 }).collect())
             }
@@ -475,7 +475,7 @@ impl TextRunPrimitive {
                 glyphs.iter().map(|src| {
                     let src_point = src.point + prim_offset;
                     let device_offset = transform.transform(&src_point);
-                    GlyphKey::new(src.index, device_offset, subpx_dir)
+                    GlyphKey::new(src.glyph_index(), device_offset, subpx_dir, src.orientation())
                 }));
         }
 
@@ -18,9 +18,17 @@
 //! - An empty token to insert a bit of vertical space.
 //! - A '|' token to start a new column.
 //! - A '_' token to start a new row.
+//!
+//! ## Trace export
+//!
+//! While `DebugFlags::PROFILER_TRACE` is set, the counter values and timings
+//! of the last frames are kept in a ring buffer. They can be written in the
+//! Chrome trace event format with `DebugCommand::SaveProfilerTrace`, to be
+//! loaded in chrome://tracing or Perfetto.
 
 use api::{ColorF, ColorU};
 use crate::renderer::DebugRenderer;
+use crate::device::GpuFrameId;
 use crate::device::query::GpuTimer;
 use euclid::{Point2D, Rect, Size2D, vec2, default};
 use crate::internal_types::FastHashMap;
@@ -49,6 +57,8 @@ const PROFILE_PADDING: f32 = 10.0;
 const BACKGROUND_COLOR: ColorU = ColorU { r: 20, g: 20, b: 20, a: 220 };
 
 const ONE_SECOND_NS: u64 = 1_000_000_000;
+/// The number of frames kept for trace exports.
+const TRACE_FRAME_COUNT: usize = 600;
 
 /// Profiler UI string presets. Defined in the profiler UI string syntax, can contain other presets.
 static PROFILER_PRESETS: &'static[(&'static str, &'static str)] = &[
@@ -70,9 +80,9 @@ static PROFILER_PRESETS: &'static[(&'static str, &'static str)] = &[
     // Texture cache allocation stats.
     (&"Texture cache stats", &"Atlas textures mem, Standalone textures mem, Picture tiles mem, Render targets mem, Depth targets mem, Atlas items mem,
         Texture cache standalone pressure, Texture cache eviction count, Texture cache youngest evicted, ,
-        Atlas RGBA8 linear pixels, Atlas RGBA8 glyphs pixels, Atlas A8 glyphs pixels, Atlas A8 pixels, Atlas A16 pixels, Atlas RGBA8 nearest pixels,
-        Atlas RGBA8 linear textures, Atlas RGBA8 glyphs textures, Atlas A8 glyphs textures, Atlas A8 textures, Atlas A16 textures, Atlas RGBA8 nearest textures,
-        Atlas RGBA8 linear pressure, Atlas RGBA8 glyphs pressure, Atlas A8 glyphs pressure, Atlas A8 pressure, Atlas A16 pressure, Atlas RGBA8 nearest pressure,"
+        Atlas RGBA8 linear pixels, Atlas RGBA8 glyphs pixels, Atlas A8 glyphs pixels, Atlas A8 pixels, Atlas A16 pixels, Atlas RGBA8 nearest pixels, Atlas RGBA16F pixels, Atlas RGB10A2 pixels,
+        Atlas RGBA8 linear textures, Atlas RGBA8 glyphs textures, Atlas A8 glyphs textures, Atlas A8 textures, Atlas A16 textures, Atlas RGBA8 nearest textures, Atlas RGBA16F textures, Atlas RGB10A2 textures,
+        Atlas RGBA8 linear pressure, Atlas RGBA8 glyphs pressure, Atlas A8 glyphs pressure, Atlas A8 pressure, Atlas A16 pressure, Atlas RGBA8 nearest pressure, Atlas RGBA16F pressure, Atlas RGB10A2 pressure,"
     ),
     // Graphs to investigate driver overhead of texture cache updates.
     (&"Texture upload perf", &"#Texture cache update,#Texture cache upload, ,#Staging CPU allocation,#Staging GPU allocation,#Staging CPU copy,#Staging GPU copy,#Upload time, ,#Upload copy batches,#Rasterized glyphs, ,#Cache texture creation,#Cache texture deletion"),
@@ -89,7 +99,7 @@ static PROFILER_PRESETS: &'static[(&'static str, &'static str)] = &[
     // Misc:
 
     (&"Memory", &"Image templates,Image templates mem,Font templates,Font templates mem,DisplayList mem,Picture tiles mem"),
-    (&"Interners", "Interned primitives,Interned clips,Interned pictures,Interned text runs,Interned normal borders,Interned image borders,Interned images,Interned YUV images,Interned line decorations,Interned linear gradients,Interned radial gradients,Interned conic gradients,Interned filter data,Interned backdrops"),
+    (&"Interners", "Interned primitives,Interned clips,Interned pictures,Interned text runs,Interned normal borders,Interned image borders,Interned images,Interned YUV images,Interned line decorations,Interned linear gradients,Interned radial gradients,Interned conic gradients,Interned filter data,Interned backdrops,Interned paths"),
     // Gpu sampler queries (need the pref gfx.webrender.debug.gpu-sampler-queries).
     (&"GPU samplers", &"Alpha targets samplers,Transparent pass samplers,Opaque pass samplers,Total samplers"),
 
@@ -227,31 +237,39 @@ pub const INTERNED_PICTURES: usize = 96;
 pub const INTERNED_FILTER_DATA: usize = 97;
 pub const INTERNED_BACKDROPS: usize = 98;
 pub const INTERNED_POLYGONS: usize = 99;
-
-pub const DEPTH_TARGETS_MEM: usize = 100;
-
-pub const SHADER_BUILD_TIME: usize = 101;
-
-pub const RENDER_REASON_FIRST: usize = 102;
-pub const RENDER_REASON_SCENE: usize = 102;
-pub const RENDER_REASON_ANIMATED_PROPERTY: usize = 103;
-pub const RENDER_REASON_RESOURCE_UPDATE: usize = 104;
-pub const RENDER_REASON_ASYNC_IMAGE: usize = 105;
-pub const RENDER_REASON_CLEAR_RESOURCES: usize = 106;
-pub const RENDER_REASON_APZ: usize = 107;
-pub const RENDER_REASON_RESIZE: usize = 108;
-pub const RENDER_REASON_WIDGET: usize = 109;
-pub const RENDER_REASON_TEXTURE_CACHE_FLUSH: usize = 110;
-pub const RENDER_REASON_SNAPSHOT: usize = 111;
-pub const RENDER_REASON_POST_RESOURCE_UPDATE_HOOKS: usize = 112;
-pub const RENDER_REASON_CONFIG_CHANGE: usize = 113;
-pub const RENDER_REASON_CONTENT_SYNC: usize = 114;
-pub const RENDER_REASON_FLUSH: usize = 115;
-pub const RENDER_REASON_TESTING: usize = 116;
-pub const RENDER_REASON_OTHER: usize = 117;
-pub const RENDER_REASON_VSYNC: usize = 118;
-
-pub const NUM_PROFILER_EVENTS: usize = 119;
+pub const INTERNED_PATHS: usize = 100;
+
+pub const DEPTH_TARGETS_MEM: usize = 101;
+
+pub const SHADER_BUILD_TIME: usize = 102;
+
+pub const RENDER_REASON_FIRST: usize = 103;
+pub const RENDER_REASON_SCENE: usize = 103;
+pub const RENDER_REASON_ANIMATED_PROPERTY: usize = 104;
+pub const RENDER_REASON_RESOURCE_UPDATE: usize = 105;
+pub const RENDER_REASON_ASYNC_IMAGE: usize = 106;
+pub const RENDER_REASON_CLEAR_RESOURCES: usize = 107;
+pub const RENDER_REASON_APZ: usize = 108;
+pub const RENDER_REASON_RESIZE: usize = 109;
+pub const RENDER_REASON_WIDGET: usize = 110;
+pub const RENDER_REASON_TEXTURE_CACHE_FLUSH: usize = 111;
+pub const RENDER_REASON_SNAPSHOT: usize = 112;
+pub const RENDER_REASON_POST_RESOURCE_UPDATE_HOOKS: usize = 113;
+pub const RENDER_REASON_CONFIG_CHANGE: usize = 114;
+pub const RENDER_REASON_CONTENT_SYNC: usize = 115;
+pub const RENDER_REASON_FLUSH: usize = 116;
+pub const RENDER_REASON_TESTING: usize = 117;
+pub const RENDER_REASON_OTHER: usize = 118;
+pub const RENDER_REASON_VSYNC: usize = 119;
+
+pub const ATLAS_RGBA16F_PIXELS: usize = 120;
+pub const ATLAS_RGBA16F_TEXTURES: usize = 121;
+pub const ATLAS_RGB10A2_PIXELS: usize = 122;
+pub const ATLAS_RGB10A2_TEXTURES: usize = 123;
+pub const ATLAS_COLOR16F_PRESSURE: usize = 124;
+pub const ATLAS_COLOR10_PRESSURE: usize = 125;
+
+pub const NUM_PROFILER_EVENTS: usize = 126;
 
 pub struct Profiler {
     counters: Vec<Counter>,
@@ -266,6 +284,11 @@ pub struct Profiler {
     frame_timestamps_within_last_second: Vec<u64>,
 
     ui: Vec<Item>,
+
+    /// Whether the frames are recorded for trace exports.
+    tracing: bool,
+    /// The last frames, for trace exports.
+    trace_frames: VecDeque<TraceFrame>,
 }
 
 impl Profiler {
@@ -403,6 +426,7 @@ impl Profiler {
             int("Interned filter data", "", INTERNED_FILTER_DATA, Expected::none()),
             int("Interned backdrops", "", INTERNED_BACKDROPS, Expected::none()),
             int("Interned polygons", "", INTERNED_POLYGONS, Expected::none()),
+            int("Interned paths", "", INTERNED_PATHS, Expected::none()),
 
             float("Depth targets mem", "MB", DEPTH_TARGETS_MEM, Expected::none()),
             float("Shader build time", "ms", SHADER_BUILD_TIME, Expected::none()),
@@ -425,6 +449,13 @@ impl Profiler {
             float("Reason other", "", RENDER_REASON_OTHER, expected(0.0..0.01)),
             float("On vsync", "", RENDER_REASON_VSYNC, expected(0.0..0.01)),
 
+            int("Atlas RGBA16F pixels", "px", ATLAS_RGBA16F_PIXELS, expected(0..2_000_000)),
+            int("Atlas RGBA16F textures", "", ATLAS_RGBA16F_TEXTURES, expected(0..2)),
+            int("Atlas RGB10A2 pixels", "px", ATLAS_RGB10A2_PIXELS, expected(0..2_000_000)),
+            int("Atlas RGB10A2 textures", "", ATLAS_RGB10A2_TEXTURES, expected(0..2)),
+            float("Atlas RGBA16F pressure", "", ATLAS_COLOR16F_PRESSURE, expected(0.0..1.0)),
+            float("Atlas RGB10A2 pressure", "", ATLAS_COLOR10_PRESSURE, expected(0.0..1.0)),
+
         ];
 
         let mut counters = Vec::with_capacity(profile_counters.len());
@@ -445,6 +476,9 @@ impl Profiler {
             num_graph_samples: 500, // Would it be useful to control this via a pref?
             frame_timestamps_within_last_second: Vec::new(),
             ui: Vec::new(),
+
+            tracing: false,
+            trace_frames: VecDeque::new(),
         }
     }
 
@@ -466,7 +500,7 @@ impl Profiler {
     }
 
     // Call at the end of every frame, after setting the counter values and before drawing the counters.
-    pub fn update(&mut self) {
+    pub fn update(&mut self, gpu_frame_id: GpuFrameId) {
         let now = precise_time_ns();
         let update_avg = (now - self.start) > self.avg_over_period;
         if update_avg {
@@ -487,23 +521,77 @@ impl Profiler {
             80.0
         );
 
+        if self.tracing {
+            self.record_trace_frame(now, gpu_frame_id);
+        }
+
         for counter in &mut self.counters {
             counter.update(update_avg);
         }
     }
 
+    /// Start or stop recording the frames for trace exports.
+    ///
+    /// The frames recorded previously are discarded when recording starts.
+    pub fn set_tracing(&mut self, enabled: bool) {
+        if enabled && !self.tracing {
+            self.trace_frames.clear();
+            self.trace_frames.reserve(TRACE_FRAME_COUNT);
+        }
+        self.tracing = enabled;
+    }
+
+    fn record_trace_frame(&mut self, end_ns: u64, gpu_frame_id: GpuFrameId) {
+        // Reuse the allocations of the oldest frame once the buffer is full.
+        let mut frame = if self.trace_frames.len() == TRACE_FRAME_COUNT {
+            self.trace_frames.pop_front().unwrap()
+        } else {
+            TraceFrame {
+                end_ns: 0,
+                gpu_frame_id,
+                counters: Vec::new(),
+                gpu_samples: Vec::new(),
+            }
+        };
+
+        frame.end_ns = end_ns;
+        frame.gpu_frame_id = gpu_frame_id;
+        frame.counters.clear();
+        frame.counters.extend(
+            self.counters
+                .iter()
+                .enumerate()
+                .filter(|(_, counter)| counter.value.is_finite())
+                .map(|(index, counter)| (index, counter.value))
+        );
+        // The GPU timings of a frame are only known a few frames later.
+        frame.gpu_samples.clear();
+
+        self.trace_frames.push_back(frame);
+    }
+
     pub fn update_frame_stats(&mut self, stats: FullFrameStats) {
         if stats.gecko_display_list_time != 0.0 {
           self.frame_stats.push(stats.into());
         }
     }
 
-    pub fn set_gpu_time_queries(&mut self, gpu_queries: Vec<GpuTimer>) {
+    pub fn set_gpu_time_queries(&mut self, gpu_frame_id: GpuFrameId, gpu_queries: Vec<GpuTimer>) {
         let mut gpu_time_ns = 0;
         for sample in &gpu_queries {
             gpu_time_ns += sample.time_ns;
         }
 
+        if self.tracing {
+            let frame = self.trace_frames
+                .iter_mut()
+                .rev()
+                .find(|frame| frame.gpu_frame_id == gpu_frame_id);
+            if let Some(frame) = frame {
+                frame.gpu_samples.extend_from_slice(&gpu_queries);
+            }
+        }
+
         self.gpu_frames.push(ProfilerFrame {
           total_time: gpu_time_ns,
           samples: gpu_queries
@@ -1205,6 +1293,89 @@ impl Profiler {
         }
     }
 
+    /// Write the counter values and timings of the last frames in the Chrome
+    /// trace event format.
+    ///
+    /// Counters are written as counter events. The timings are written as
+    /// spans on one track per thread, ending at the time the frame was
+    /// rendered. The individual steps aren't timestamped, so the spans only
+    /// have real durations: they are laid out back to back, each thread's
+    /// work preceding the work of the next one in the pipeline, and are
+    /// marked with the "synthetic" category.
+    pub fn write_chrome_trace(&self, sink: &mut dyn std::io::Write) -> std::io::Result<()> {
+        write!(sink, "{{\"displayTimeUnit\":\"ms\",\"traceEvents\":[")?;
+        write!(
+            sink,
+            "{{\"name\":\"process_name\",\"ph\":\"M\",\"pid\":{},\"args\":{{\"name\":\"WebRender\"}}}}",
+            TRACE_PID,
+        )?;
+        for &(tid, name) in TRACE_THREADS {
+            write!(
+                sink,
+                ",{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":{},\"tid\":{},\"args\":{{\"name\":\"{}\"}}}}",
+                TRACE_PID,
+                tid,
+                name,
+            )?;
+        }
+
+        for frame in &self.trace_frames {
+            let value = |index: usize| {
+                frame.counters
+                    .iter()
+                    .find(|(i, _)| *i == index)
+                    .map_or(0.0, |(_, value)| *value)
+            };
+
+            for &(index, value) in &frame.counters {
+                let counter = &self.counters[index];
+                write!(
+                    sink,
+                    ",{{\"name\":{},\"ph\":\"C\",\"ts\":{:.3},\"pid\":{},\"args\":{{{}:{}}}}}",
+                    JsonStr(counter.name),
+                    ns_to_us(frame.end_ns),
+                    TRACE_PID,
+                    JsonStr(if counter.unit.is_empty() { "value" } else { counter.unit }),
+                    value,
+                )?;
+            }
+
+            // Walk back from the end of the frame, one thread at a time.
+            let mut end_ms = ns_to_ms(frame.end_ns);
+
+            let gpu_ms: f64 = frame.gpu_samples.iter().map(|sample| ns_to_ms(sample.time_ns)).sum();
+            let mut start_ms = end_ms - gpu_ms;
+            for sample in &frame.gpu_samples {
+                let duration_ms = ns_to_ms(sample.time_ns);
+                write_trace_span(sink, TRACE_TID_GPU, sample.tag.label, start_ms, duration_ms)?;
+                start_ms += duration_ms;
+            }
+
+            for &(tid, parent, children) in TRACE_CPU_SPANS {
+                let duration_ms = value(parent);
+                if duration_ms <= 0.0 {
+                    continue;
+                }
+
+                let start_ms = end_ms - duration_ms;
+                write_trace_span(sink, tid, self.counters[parent].name, start_ms, duration_ms)?;
+
+                let mut child_start_ms = start_ms;
+                for &child in children {
+                    let child_duration_ms = value(child).min(end_ms - child_start_ms);
+                    if child_duration_ms > 0.0 {
+                        write_trace_span(sink, tid, self.counters[child].name, child_start_ms, child_duration_ms)?;
+                        child_start_ms += child_duration_ms;
+                    }
+                }
+
+                end_ms = start_ms;
+            }
+        }
+
+        writeln!(sink, "]}}")
+    }
+
     #[cfg(feature = "capture")]
     pub fn dump_stats(&self, sink: &mut dyn std::io::Write) -> std::io::Result<()> {
         for counter in &self.counters {
@@ -1217,6 +1388,80 @@ impl Profiler {
     }
 }
 
+const TRACE_PID: u32 = 1;
+const TRACE_TID_SCENE_BUILDER: u32 = 1;
+const TRACE_TID_RENDER_BACKEND: u32 = 2;
+const TRACE_TID_RENDERER: u32 = 3;
+const TRACE_TID_GPU: u32 = 4;
+
+const TRACE_THREADS: &[(u32, &str)] = &[
+    (TRACE_TID_SCENE_BUILDER, "Scene builder"),
+    (TRACE_TID_RENDER_BACKEND, "Render backend"),
+    (TRACE_TID_RENDERER, "Renderer"),
+    (TRACE_TID_GPU, "GPU"),
+];
+
+/// The timing counters written as spans in trace exports, from the last
+/// step of a frame to the first one, along with the counters of the steps
+/// they are made of.
+const TRACE_CPU_SPANS: &[(u32, usize, &[usize])] = &[
+    (TRACE_TID_RENDERER, RENDERER_TIME, &[]),
+    (
+        TRACE_TID_RENDER_BACKEND,
+        FRAME_BUILDING_TIME,
+        &[FRAME_VISIBILITY_TIME, FRAME_PREPARE_TIME, FRAME_BATCHING_TIME],
+    ),
+    (TRACE_TID_SCENE_BUILDER, SCENE_BUILD_TIME, &[]),
+];
+
+fn write_trace_span(
+    sink: &mut dyn std::io::Write,
+    tid: u32,
+    name: &str,
+    start_ms: f64,
+    duration_ms: f64,
+) -> std::io::Result<()> {
+    write!(
+        sink,
+        ",{{\"name\":{},\"cat\":\"synthetic\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\"pid\":{},\"tid\":{}}}",
+        JsonStr(name),
+        start_ms * 1000.0,
+        duration_ms * 1000.0,
+        TRACE_PID,
+        tid,
+    )
+}
+
+/// Formats a string as a JSON string literal.
+struct JsonStr<'a>(&'a str);
+
+impl<'a> std::fmt::Display for JsonStr<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+                c => f.write_char(c)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+/// The counter values and GPU timings of a frame, kept for trace exports.
+struct TraceFrame {
+    /// The time at which the frame was rendered.
+    end_ns: u64,
+    /// The frame the GPU timer samples are reported for.
+    gpu_frame_id: GpuFrameId,
+    /// The counters that were set during the frame.
+    counters: Vec<(usize, f64)>,
+    /// The GPU timer samples of the frame, once they are available.
+    gpu_samples: Vec<GpuTimer>,
+}
+
 /// Defines the interface for hooking up an external profiler to WR.
 pub trait ProfilerHooks : Send + Sync {
     /// Register a thread with the profiler.
@@ -1805,6 +2050,10 @@ pub fn ns_to_ms(ns: u64) -> f64 {
     ns as f64 / 1_000_000.0
 }
 
+pub fn ns_to_us(ns: u64) -> f64 {
+    ns as f64 / 1_000.0
+}
+
 pub fn ms_to_ns(ms: f64) -> u64 {
   (ms * 1_000_000.0) as u64
 }
@@ -1828,3 +2077,64 @@ enum Item {
     Row,
 }
 
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn chrome_trace_is_valid_json() {
+        let mut profiler = Profiler::new();
+        profiler.set_tracing(true);
+
+        for frame in 0..3 {
+            profiler.counters[RENDERER_TIME].set(2.0);
+            profiler.counters[FRAME_BUILDING_TIME].set(3.0);
+            profiler.counters[FRAME_PREPARE_TIME].set(1.0);
+            profiler.update(GpuFrameId::new(frame));
+        }
+
+        // The GPU timings arrive a few frames late, and are reported for the
+        // frame they were measured in.
+        profiler.set_gpu_time_queries(
+            GpuFrameId::new(1),
+            vec![GpuTimer {
+                tag: GpuProfileTag { label: "\"quoted\" \\ label", color: ColorF::WHITE },
+                time_ns: 500_000,
+            }],
+        );
+
+        let mut output = Vec::new();
+        profiler.write_chrome_trace(&mut output).unwrap();
+
+        let trace: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        let events = trace["traceEvents"].as_array().unwrap();
+
+        for event in events {
+            assert!(event["name"].is_string());
+            assert!(event["pid"].is_u64());
+            match event["ph"].as_str().unwrap() {
+                "M" => {}
+                "C" => {
+                    assert!(event["ts"].is_f64());
+                    assert!(event["args"].is_object());
+                }
+                "X" => {
+                    assert_eq!(event["cat"], "synthetic");
+                    assert!(event["ts"].is_f64());
+                    assert!(event["dur"].as_f64().unwrap() > 0.0);
+                    assert!(event["tid"].is_u64());
+                }
+                ph => panic!("unexpected event phase {}", ph),
+            }
+        }
+
+        let gpu_spans: Vec<_> = events
+            .iter()
+            .filter(|event| event["tid"] == TRACE_TID_GPU)
+            .filter(|event| event["ph"] == "X")
+            .collect();
+        assert_eq!(gpu_spans.len(), 1);
+        assert_eq!(gpu_spans[0]["name"], "\"quoted\" \\ label");
+    }
+}
@@ -18,8 +18,9 @@ use crate::api::{FontKey, FontInstanceKey, NativeFontHandle};
 use crate::api::{BlobImageData, BlobImageKey, ImageData, ImageDescriptor, ImageKey, Epoch, QualitySettings};
 use crate::api::{BlobImageParams, BlobImageRequest, BlobImageResult, AsyncBlobImageRasterizer, BlobImageHandler};
 use crate::api::{DocumentId, PipelineId, PropertyBindingId, PropertyBindingKey, ExternalEvent};
-use crate::api::{HitTestResult, HitTesterRequest, ApiHitTester, PropertyValue, DynamicProperties};
-use crate::api::{SampledScrollOffset, TileSize, NotificationRequest, DebugFlags};
+use crate::api::{RgbColorSpace, AnimationId, KeyframeAnimation};
+use crate::api::{HitTestQuery, HitTestResult, HitTesterRequest, ApiHitTester, PropertyValue, DynamicProperties};
+use crate::api::{SampledScrollOffset, ScrollEventPhase, ScrollLocation, TileSize, NotificationRequest, DebugFlags};
 use crate::api::{GlyphDimensionRequest, GlyphIndexRequest, GlyphIndex, GlyphDimensions};
 use crate::api::{FontInstanceOptions, FontInstancePlatformOptions, FontVariation, RenderReasons};
 use crate::api::DEFAULT_TILE_SIZE;
@@ -45,6 +46,8 @@ pub enum ResourceUpdate {
     AddImage(AddImage),
     /// See `UpdateImage`.
     UpdateImage(UpdateImage),
+    /// See `AddAnimatedImage`.
+    AddAnimatedImage(AddAnimatedImage),
     /// Delete an existing image resource.
     ///
     /// It is invalid to continue referring to the image key in any display list
@@ -88,6 +91,11 @@ impl fmt::Debug for ResourceUpdate {
                 "ResourceUpdate::UpdateImage size({:?})",
                 &i.descriptor.size
             )),
+            ResourceUpdate::AddAnimatedImage(ref i) => f.write_fmt(format_args!(
+                "ResourceUpdate::AddAnimatedImage size({:?}) frames({})",
+                &i.descriptor.size,
+                i.frames.len(),
+            )),
             ResourceUpdate::AddBlobImage(ref i) => f.write_fmt(format_args!(
                 "ResourceUFpdate::AddBlobImage size({:?})",
                 &i.descriptor.size
@@ -326,6 +334,16 @@ impl Transaction {
         );
     }
 
+    /// Set the color space that the document is composited in. Colors and
+    /// images in other color spaces are converted to it. Defaults to sRGB.
+    pub fn set_output_color_space(&mut self, output_color_space: RgbColorSpace) {
+        self.scene_ops.push(
+            SceneMsg::SetOutputColorSpace {
+                output_color_space,
+            },
+        );
+    }
+
     /// Set multiple scroll offsets with generations to the node identified by
     /// the given external scroll id, the scroll offsets are relative to the
     /// pre-scrolled offset for the scrolling layer.
@@ -337,6 +355,24 @@ impl Transaction {
         self.frame_ops.push(FrameMsg::SetScrollOffsets(id, sampled_scroll_offsets));
     }
 
+    /// Scroll the node identified by the given external scroll id in response
+    /// to an input event, letting the render backend apply momentum, snapping
+    /// and overscroll as described by the scroll frame's `ScrollPhysics`.
+    ///
+    /// A positive delta moves the content towards the bottom right, i.e.
+    /// scrolls back towards the start of the content. The motion that follows
+    /// the event advances every time a frame is generated, and the render
+    /// backend builds frames on its own until it has settled. Setting the
+    /// scroll offsets of the node cancels it.
+    pub fn scroll(
+        &mut self,
+        id: ExternalScrollId,
+        location: ScrollLocation,
+        phase: ScrollEventPhase,
+    ) {
+        self.frame_ops.push(FrameMsg::Scroll(id, location, phase));
+    }
+
     /// Set the current quality / performance settings for this document.
     pub fn set_quality_settings(&mut self, settings: QualitySettings) {
         self.scene_ops.push(SceneMsg::SetQualitySettings { settings });
@@ -390,6 +426,30 @@ impl Transaction {
         self.frame_ops.push(FrameMsg::AppendDynamicTransformProperties(transforms));
     }
 
+    /// Register a keyframe animation that the render backend evaluates every
+    /// time it builds a frame, so that the animated property binding doesn't
+    /// need to be updated through `append_dynamic_properties`. While it plays,
+    /// the render backend builds frames on its own if the embedder doesn't
+    /// request them. Adding an animation with the id of an existing one
+    /// replaces it.
+    ///
+    /// The optional notification request is notified with
+    /// `Checkpoint::AnimationFinished` once the animation has played all of its
+    /// iterations.
+    pub fn add_animation(
+        &mut self,
+        animation: KeyframeAnimation,
+        notification: Option<NotificationRequest>,
+    ) {
+        self.frame_ops.push(FrameMsg::AddAnimation(animation, notification));
+    }
+
+    /// Stop and remove a keyframe animation. The animated property binding
+    /// falls back to its dynamic property or default value.
+    pub fn remove_animation(&mut self, id: AnimationId) {
+        self.frame_ops.push(FrameMsg::RemoveAnimation(id));
+    }
+
     /// Consumes this object and just returns the frame ops.
     pub fn get_frame_ops(self) -> Vec<FrameMsg> {
         self.frame_ops
@@ -446,6 +506,23 @@ impl Transaction {
         }));
     }
 
+    /// See `ResourceUpdate::AddAnimatedImage`.
+    pub fn add_animated_image(
+        &mut self,
+        key: ImageKey,
+        descriptor: ImageDescriptor,
+        frames: Vec<AnimatedImageFrame>,
+        loop_count: Option<u32>,
+    ) {
+        assert!(!frames.is_empty(), "Animated images need at least one frame");
+        self.resource_updates.push(ResourceUpdate::AddAnimatedImage(AddAnimatedImage {
+            key,
+            descriptor,
+            frames,
+            loop_count,
+        }));
+    }
+
     /// See `ResourceUpdate::DeleteImage`.
     pub fn delete_image(&mut self, key: ImageKey) {
         self.resource_updates.push(ResourceUpdate::DeleteImage(key));
@@ -672,6 +749,42 @@ pub struct UpdateImage {
     pub dirty_rect: ImageDirtyRect,
 }
 
+/// A single frame of an animated image.
+#[derive(Clone)]
+#[cfg_attr(any(feature = "serde"), derive(Deserialize, Serialize))]
+pub struct AnimatedImageFrame {
+    /// The pixels of the frame. All frames share the descriptor of the image.
+    pub data: ImageData,
+    /// How long the frame is displayed for, in milliseconds.
+    pub duration_ms: u32,
+}
+
+/// Creates an animated image resource, made of a sequence of frames.
+///
+/// The frame to display is selected from the time at which each frame is
+/// built, and the render backend builds frames on its own while the image
+/// animates. Only the frames that end up being displayed are uploaded to the
+/// GPU, and they stay in the texture cache while the image animates. The
+/// image is referenced by display items like any other image.
+///
+/// An `UpdateImage` for the key stops the animation and replaces its frames
+/// with the new still image.
+///
+/// Must be matched with a `DeleteImage` at some point to prevent memory leaks.
+#[derive(Clone)]
+#[cfg_attr(any(feature = "serde"), derive(Deserialize, Serialize))]
+pub struct AddAnimatedImage {
+    /// A key to identify the image resource.
+    pub key: ImageKey,
+    /// Properties of the image, shared by all frames.
+    pub descriptor: ImageDescriptor,
+    /// The frames of the animation, in display order.
+    pub frames: Vec<AnimatedImageFrame>,
+    /// How many times the animation is played before it stops on its last
+    /// frame. `None` loops forever.
+    pub loop_count: Option<u32>,
+}
+
 /// Creates a blob-image resource with provided parameters.
 ///
 /// Must be matched with a `DeleteImage` at some point to prevent memory leaks.
@@ -778,6 +891,11 @@ pub enum SceneMsg {
         ///
         device_rect: DeviceIntRect,
     },
+    /// Set the color space that the document is composited in.
+    SetOutputColorSpace {
+        ///
+        output_color_space: RgbColorSpace,
+    },
     /// Set the current quality / performance configuration for this document.
     SetQualitySettings {
         /// The set of available quality / performance config values.
@@ -790,12 +908,14 @@ pub enum FrameMsg {
     ///
     UpdateEpoch(PipelineId, Epoch),
     ///
-    HitTest(WorldPoint, Sender<HitTestResult>),
+    HitTest(HitTestQuery, Sender<HitTestResult>),
     ///
     RequestHitTester(Sender<Arc<dyn ApiHitTester>>),
     ///
     SetScrollOffsets(ExternalScrollId, Vec<SampledScrollOffset>),
     ///
+    Scroll(ExternalScrollId, ScrollLocation, ScrollEventPhase),
+    ///
     ResetDynamicProperties,
     ///
     AppendDynamicProperties(DynamicProperties),
@@ -803,6 +923,10 @@ pub enum FrameMsg {
     AppendDynamicTransformProperties(Vec<PropertyValue<LayoutTransform>>),
     ///
     SetIsTransformAsyncZooming(bool, PropertyBindingId),
+    ///
+    AddAnimation(KeyframeAnimation, Option<NotificationRequest>),
+    ///
+    RemoveAnimation(AnimationId),
 }
 
 impl fmt::Debug for SceneMsg {
@@ -812,6 +936,7 @@ impl fmt::Debug for SceneMsg {
             SceneMsg::SetDisplayList { .. } => "SceneMsg::SetDisplayList",
             SceneMsg::RemovePipeline(..) => "SceneMsg::RemovePipeline",
             SceneMsg::SetDocumentView { .. } => "SceneMsg::SetDocumentView",
+            SceneMsg::SetOutputColorSpace { .. } => "SceneMsg::SetOutputColorSpace",
             SceneMsg::SetRootPipeline(..) => "SceneMsg::SetRootPipeline",
             SceneMsg::SetQualitySettings { .. } => "SceneMsg::SetQualitySettings",
         })
@@ -825,10 +950,13 @@ impl fmt::Debug for FrameMsg {
             FrameMsg::HitTest(..) => "FrameMsg::HitTest",
             FrameMsg::RequestHitTester(..) => "FrameMsg::RequestHitTester",
             FrameMsg::SetScrollOffsets(..) => "FrameMsg::SetScrollOffsets",
+            FrameMsg::Scroll(..) => "FrameMsg::Scroll",
             FrameMsg::ResetDynamicProperties => "FrameMsg::ResetDynamicProperties",
             FrameMsg::AppendDynamicProperties(..) => "FrameMsg::AppendDynamicProperties",
             FrameMsg::AppendDynamicTransformProperties(..) => "FrameMsg::AppendDynamicTransformProperties",
             FrameMsg::SetIsTransformAsyncZooming(..) => "FrameMsg::SetIsTransformAsyncZooming",
+            FrameMsg::AddAnimation(..) => "FrameMsg::AddAnimation",
+            FrameMsg::RemoveAnimation(..) => "FrameMsg::RemoveAnimation",
         })
     }
 }
@@ -845,6 +973,28 @@ bitflags!{
         const TILE_CACHE = 0x4;
         ///
         const EXTERNAL_RESOURCES = 0x8;
+        /// Also write the scene as YAML that wrench can replay.
+        const YAML = 0x10;
+    }
+}
+
+/// File format of the render task graphs written by
+/// `DebugCommand::DumpRenderTaskGraphs`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderTaskGraphFormat {
+    /// A Graphviz DOT description of the tasks, passes and target allocations.
+    Dot,
+    /// An SVG drawing of the tasks in each pass and their dependencies.
+    Svg,
+}
+
+impl RenderTaskGraphFormat {
+    /// The file extension used for this format.
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            RenderTaskGraphFormat::Dot => "dot",
+            RenderTaskGraphFormat::Svg => "svg",
+        }
     }
 }
 
@@ -906,6 +1056,13 @@ pub enum DebugCommand {
     SetPictureTileSize(Option<DeviceIntSize>),
     /// Set an override for max off-screen surface size
     SetMaximumSurfaceSize(Option<usize>),
+    /// Write the profiler counters and timings of the frames recorded while
+    /// `DebugFlags::PROFILER_TRACE` was set to a file, in the Chrome trace
+    /// event format.
+    SaveProfilerTrace(PathBuf),
+    /// Write the render task graph of each frame built from now on into the
+    /// given directory, or stop doing so if `None`.
+    DumpRenderTaskGraphs(Option<(PathBuf, RenderTaskGraphFormat)>),
 }
 
 /// Message sent by the `RenderApi` to the render backend thread.
@@ -1193,6 +1350,12 @@ impl RenderApi {
         }
     }
 
+    /// Creates an `AnimationId`.
+    pub fn generate_animation_id(&self) -> AnimationId {
+        let new_id = self.next_unique_id();
+        AnimationId::new(self.namespace_id, new_id)
+    }
+
     #[inline]
     fn next_unique_id(&self) -> u32 {
         let ResourceId(id) = self.next_id.get();
@@ -1268,12 +1431,21 @@ impl RenderApi {
     pub fn hit_test(&self,
         document_id: DocumentId,
         point: WorldPoint,
+    ) -> HitTestResult {
+        self.hit_test_query(document_id, HitTestQuery::point(point))
+    }
+
+    /// Does a hit test on display items in the specified document, as described
+    /// by the query. The vector of hit results is ordered from front to back.
+    pub fn hit_test_query(&self,
+        document_id: DocumentId,
+        query: HitTestQuery,
     ) -> HitTestResult {
         let (tx, rx) = single_msg_channel();
 
         self.send_frame_msg(
             document_id,
-            FrameMsg::HitTest(point, tx)
+            FrameMsg::HitTest(query, tx)
         );
         rx.recv().unwrap()
     }
@@ -11,15 +11,18 @@
 use api::{DebugFlags, Parameter, BoolParameter};
 use api::{DocumentId, ExternalScrollId, HitTestResult};
 use api::{IdNamespace, PipelineId, RenderNotifier, SampledScrollOffset};
-use api::{NotificationRequest, Checkpoint, QualitySettings};
+use api::{NotificationRequest, Checkpoint, QualitySettings, RgbColorSpace};
 use api::{PrimitiveKeyKind, RenderReasons};
 use api::units::*;
-use api::channel::{single_msg_channel, Sender, Receiver};
+use api::channel::{single_msg_channel, Sender, Receiver, RecvTimeoutError};
 #[cfg(any(feature = "capture", feature = "replay"))]
 use crate::render_api::CaptureBits;
 #[cfg(feature = "replay")]
 use crate::render_api::CapturedDocument;
+#[cfg(feature = "capture")]
+use crate::render_api::RenderTaskGraphFormat;
 use crate::render_api::{MemoryReport, TransactionMsg, ResourceUpdate, ApiMsg, FrameMsg, ClearCache, DebugCommand};
+use crate::animation::AnimationStore;
 use crate::clip::{ClipIntern, PolygonIntern, ClipStoreScratchBuffer};
 use crate::filterdata::FilterDataIntern;
 #[cfg(any(feature = "capture", feature = "replay"))]
@@ -41,6 +44,8 @@ use crate::prim_store::{PrimitiveInstanceKind, PrimTemplateCommonData};
 use crate::prim_store::interned::*;
 use crate::profiler::{self, TransactionProfile};
 use crate::render_task_graph::RenderTaskGraphBuilder;
+#[cfg(feature = "capture")]
+use crate::render_task_graph::dump_render_tasks;
 use crate::renderer::{AsyncPropertySampler, FullFrameStats, PipelineInfo};
 use crate::resource_cache::ResourceCache;
 #[cfg(feature = "replay")]
@@ -60,6 +65,7 @@ use serde::{Serialize, Deserialize};
 use std::collections::hash_map::Entry::{Occupied, Vacant};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 use std::{mem, u32};
 #[cfg(feature = "capture")]
 use std::path::PathBuf;
@@ -82,6 +88,8 @@ pub struct DocumentView {
 pub struct SceneView {
     pub device_rect: DeviceIntRect,
     pub quality_settings: QualitySettings,
+    /// The color space that the document is composited in.
+    pub output_color_space: RgbColorSpace,
 }
 
 enum RenderBackendStatus {
@@ -229,6 +237,10 @@ impl DataStores {
                 let prim_data = &self.line_decoration[data_handle];
                 &prim_data.common
             }
+            PrimitiveInstanceKind::Path { data_handle, .. } => {
+                let prim_data = &self.path[data_handle];
+                &prim_data.common
+            }
             PrimitiveInstanceKind::LinearGradient { data_handle, .. }
             | PrimitiveInstanceKind::CachedLinearGradient { data_handle, .. } => {
                 let prim_data = &self.linear_grad[data_handle];
@@ -332,6 +344,14 @@ struct Document {
     /// without requiring the scene to be re-built.
     dynamic_properties: SceneProperties,
 
+    /// Keyframe animations evaluated every time a frame is requested, which
+    /// feed their values into `dynamic_properties`.
+    animations: AnimationStore,
+
+    /// True while something in the document changes over time, in which case
+    /// the render backend builds frames without waiting for them to be requested.
+    is_animating: bool,
+
     /// Track whether the last built frame is up to date or if it will need to be re-built
     /// before rendering again.
     frame_is_valid: bool,
@@ -377,6 +397,7 @@ impl Document {
                 scene: SceneView {
                     device_rect: size.into(),
                     quality_settings: QualitySettings::default(),
+                    output_color_space: RgbColorSpace::Srgb,
                 },
             },
             stamp: FrameStamp::first(id),
@@ -385,6 +406,8 @@ impl Document {
             hit_tester: None,
             shared_hit_tester: Arc::new(SharedHitTester::new()),
             dynamic_properties: SceneProperties::new(),
+            animations: AnimationStore::new(),
+            is_animating: false,
             frame_is_valid: false,
             hit_tester_is_valid: false,
             rendered_frame_is_valid: false,
@@ -418,14 +441,14 @@ impl Document {
             FrameMsg::UpdateEpoch(pipeline_id, epoch) => {
                 self.scene.pipeline_epochs.insert(pipeline_id, epoch);
             }
-            FrameMsg::HitTest(point, tx) => {
+            FrameMsg::HitTest(query, tx) => {
                 if !self.hit_tester_is_valid {
                     self.rebuild_hit_tester();
                 }
 
                 let result = match self.hit_tester {
                     Some(ref hit_tester) => {
-                        hit_tester.hit_test(HitTest::new(point))
+                        hit_tester.hit_test(HitTest::new(query))
                     }
                     None => HitTestResult { items: Vec::new() },
                 };
@@ -448,6 +471,19 @@ impl Document {
                     ..DocumentOps::nop()
                 };
             }
+            FrameMsg::Scroll(id, location, phase) => {
+                profile_scope!("Scroll");
+
+                if self.spatial_tree.scroll(id, location, phase, precise_time_ns()) {
+                    self.hit_tester_is_valid = false;
+                    self.frame_is_valid = false;
+                }
+
+                return DocumentOps {
+                    scroll: true,
+                    ..DocumentOps::nop()
+                };
+            }
             FrameMsg::ResetDynamicProperties => {
                 self.dynamic_properties.reset_properties();
             }
@@ -467,6 +503,12 @@ impl Document {
                     }
                 }
             }
+            FrameMsg::AddAnimation(animation, notification) => {
+                self.animations.add(animation, notification);
+            }
+            FrameMsg::RemoveAnimation(id) => {
+                self.animations.remove(id);
+            }
         }
 
         DocumentOps::nop()
@@ -689,14 +731,26 @@ pub struct RenderBackend {
     /// so all control of sequence capture goes through there.
     capture_config: Option<CaptureConfig>,
 
+    #[cfg(feature = "capture")]
+    /// If `Some`, the render task graph of every built frame is written
+    /// into this directory. Set by `DebugCommand::DumpRenderTaskGraphs`.
+    render_task_graph_dump: Option<(PathBuf, RenderTaskGraphFormat)>,
+
     #[cfg(feature = "replay")]
     loaded_resource_sequence_id: u32,
 
     /// A map of tile caches. These are stored in the backend as they are
     /// persisted between both frame and scenes.
     tile_caches: FastHashMap<SliceId, Box<TileCacheInstance>>,
+
+    /// The time animations were last sampled at, from `precise_time_ns`.
+    last_animation_time: u64,
 }
 
+/// How often frames are built while a document is animating, when the
+/// embedder doesn't request them more often.
+const ANIMATION_FRAME_INTERVAL_NS: u64 = 16_666_667;
+
 impl RenderBackend {
     pub fn new(
         api_rx: Receiver<ApiMsg>,
@@ -727,9 +781,12 @@ impl RenderBackend {
             recycler: Recycler::new(),
             #[cfg(feature = "capture")]
             capture_config: None,
+            #[cfg(feature = "capture")]
+            render_task_graph_dump: None,
             #[cfg(feature = "replay")]
             loaded_resource_sequence_id: 0,
             tile_caches: FastHashMap::default(),
+            last_animation_time: 0,
         }
     }
 
@@ -746,11 +803,24 @@ impl RenderBackend {
         }
 
         while let RenderBackendStatus::Continue = status {
-            status = match self.api_rx.recv() {
-                Ok(msg) => {
-                    self.process_api_msg(msg, &mut frame_counter)
-                }
-                Err(..) => { RenderBackendStatus::ShutDown(None) }
+            status = match self.animation_frame_timeout() {
+                // Wait for messages until the next animation frame is due.
+                Some(timeout) => match self.api_rx.recv_timeout(timeout) {
+                    Ok(msg) => {
+                        self.process_api_msg(msg, &mut frame_counter)
+                    }
+                    Err(RecvTimeoutError::Timeout) => {
+                        self.build_animation_frames(&mut frame_counter);
+                        RenderBackendStatus::Continue
+                    }
+                    Err(RecvTimeoutError::Disconnected) => { RenderBackendStatus::ShutDown(None) }
+                },
+                None => match self.api_rx.recv() {
+                    Ok(msg) => {
+                        self.process_api_msg(msg, &mut frame_counter)
+                    }
+                    Err(..) => { RenderBackendStatus::ShutDown(None) }
+                },
             };
         }
 
@@ -989,6 +1059,16 @@ impl RenderBackend {
                         self.stop_capture_sequence();
                         return RenderBackendStatus::Continue;
                     },
+                    #[cfg(feature = "capture")]
+                    DebugCommand::DumpRenderTaskGraphs(dump) => {
+                        if let Some((ref root, _)) = dump {
+                            if let Err(e) = std::fs::create_dir_all(root) {
+                                error!("Unable to create render task graph dir {:?}: {}", root, e);
+                            }
+                        }
+                        self.render_task_graph_dump = dump;
+                        return RenderBackendStatus::Continue;
+                    },
                     #[cfg(feature = "replay")]
                     DebugCommand::LoadCapture(path, ids, tx) => {
                         NEXT_NAMESPACE_ID.fetch_add(1, Ordering::Relaxed);
@@ -1251,6 +1331,53 @@ impl RenderBackend {
         self.bookkeep_after_frames();
     }
 
+    /// Returns how long to wait for the next animation frame, or None if no
+    /// document is animating.
+    fn animation_frame_timeout(&self) -> Option<Duration> {
+        if !self.documents.values().any(|doc| doc.is_animating) {
+            return None;
+        }
+
+        let next_frame_time = self.last_animation_time + ANIMATION_FRAME_INTERVAL_NS;
+        Some(Duration::from_nanos(next_frame_time.saturating_sub(precise_time_ns())))
+    }
+
+    /// Build a frame for each animating document, as if the embedder had sent
+    /// an empty transaction that generates a frame.
+    fn build_animation_frames(&mut self, frame_counter: &mut u32) {
+        let document_ids: Vec<DocumentId> = self.documents
+            .iter()
+            .filter(|(_, doc)| doc.is_animating)
+            .map(|(id, _)| *id)
+            .collect();
+
+        self.prepare_for_frames();
+        self.maybe_force_nop_documents(
+            frame_counter,
+            |document_id| document_ids.contains(&document_id));
+
+        #[allow(unused_variables)]
+        let mut built_frame = false;
+        for document_id in document_ids {
+            built_frame |= self.update_document(
+                document_id,
+                Vec::default(),
+                Vec::default(),
+                Vec::default(),
+                true,
+                RenderReasons::ANIMATED_PROPERTY,
+                None,
+                false,
+                frame_counter,
+                false);
+        }
+        #[cfg(feature = "capture")]
+        if built_frame {
+            self.save_capture_sequence();
+        }
+        self.bookkeep_after_frames();
+    }
+
     /// In certain cases, resources shared by multiple documents have to run
     /// maintenance operations, like cleaning up unused cache items. In those
     /// cases, we are forced to build frames for all documents, however we
@@ -1304,6 +1431,12 @@ impl RenderBackend {
     ) -> bool {
         let requested_frame = render_frame;
 
+        // Everything that changes over time is sampled at the same time.
+        let now = precise_time_ns();
+        if requested_frame {
+            self.last_animation_time = now;
+        }
+
         let requires_frame_build = self.requires_frame_build();
         let doc = self.documents.get_mut(&document_id).unwrap();
 
@@ -1344,6 +1477,37 @@ impl RenderBackend {
             doc.hit_tester_is_valid = false;
         }
 
+        // Keyframe animations are sampled after the embedder's properties
+        // were flushed so that their values take precedence.
+        if requested_frame {
+            let mut finished_animations = Vec::new();
+            let animated_properties = doc.animations.sample(now, &mut finished_animations);
+            if doc.dynamic_properties.set_animated_properties(animated_properties) {
+                doc.frame_is_valid = false;
+                doc.hit_tester_is_valid = false;
+            }
+
+            for notification in finished_animations {
+                notification.notify();
+            }
+        }
+
+        // Scroll frames moving after a scroll gesture advance with the clock too.
+        if requested_frame && doc.spatial_tree.sample_scroll_physics(now) {
+            doc.frame_is_valid = false;
+            doc.hit_tester_is_valid = false;
+        }
+
+        // Animated images advance with the clock rather than with transactions,
+        // so a new frame is needed whenever one of them changes frame.
+        if requested_frame && self.resource_cache.update_image_animations(now) {
+            doc.frame_is_valid = false;
+        }
+
+        doc.is_animating = doc.animations.is_active() ||
+            doc.spatial_tree.is_scroll_physics_animating() ||
+            self.resource_cache.has_active_image_animations(now);
+
         if !doc.can_render() {
             // TODO: this happens if we are building the first scene asynchronously and
             // scroll at the same time. we should keep track of the fact that we skipped
@@ -1449,6 +1613,24 @@ impl RenderBackend {
                 None => {},
             }
 
+            #[cfg(feature = "capture")]
+            if let Some((ref root, format)) = self.render_task_graph_dump {
+                let file_name = format!(
+                    "render-tasks-{}-{}-{:05}.{}",
+                    document_id.namespace_id.0,
+                    document_id.id,
+                    *frame_counter,
+                    format.extension(),
+                );
+                let path = root.join(file_name);
+                let result = std::fs::File::create(&path).and_then(|mut file| {
+                    dump_render_tasks(&rendered_document.frame.render_tasks, format, &mut file)
+                });
+                if let Err(e) = result {
+                    error!("Unable to write the render task graph to {:?}: {}", path, e);
+                }
+            }
+
             let msg = ResultMsg::PublishPipelineInfo(doc.updated_pipeline_info());
             self.result_tx.send(msg).unwrap();
 
@@ -1562,7 +1744,7 @@ impl RenderBackend {
         bits: CaptureBits,
     ) -> DebugOutput {
         use std::fs;
-        use crate::render_task_graph::dump_render_tasks_as_svg;
+        use crate::render_task_graph::{dump_render_tasks_as_dot, dump_render_tasks_as_svg};
 
         debug!("capture: saving {:?}", root);
         if !root.is_dir() {
@@ -1612,6 +1794,13 @@ impl RenderBackend {
                     &rendered_document.frame.render_tasks,
                     &mut render_tasks_file
                 ).unwrap();
+                let file_name = format!("render-tasks-{}-{}.dot", id.namespace_id.0, id.id);
+                let mut render_tasks_file = fs::File::create(&config.file_path_for_frame(file_name, "dot"))
+                    .expect("Failed to open the DOT file.");
+                dump_render_tasks_as_dot(
+                    &rendered_document.frame.render_tasks,
+                    &mut render_tasks_file
+                ).unwrap();
 
                 let file_name = format!("texture-cache-color-linear-{}-{}.svg", id.namespace_id.0, id.id);
                 let mut texture_file = fs::File::create(&config.file_path_for_frame(file_name, "svg"))
@@ -1660,6 +1849,16 @@ impl RenderBackend {
         debug!("\tresource cache");
         let (resources, deferred) = self.resource_cache.save_capture(&config.root);
 
+        if config.bits.contains(CaptureBits::YAML) {
+            // The scene builder thread writes the YAML itself, next to these.
+            debug!("\tYAML images");
+            let yaml_root = config.scene_root().join("yaml");
+            match fs::create_dir_all(&yaml_root) {
+                Ok(()) => self.resource_cache.save_yaml_images(&yaml_root),
+                Err(e) => warn!("Unable to create {}: {:?}", yaml_root.display(), e),
+            }
+        }
+
         info!("\tbackend");
         let backend = PlainRenderBackend {
             frame_config: self.frame_config.clone(),
@@ -1820,6 +2019,8 @@ impl RenderBackend {
                         stamp: FrameStamp::first(id),
                         frame_builder: FrameBuilder::new(),
                         dynamic_properties: properties,
+                        animations: AnimationStore::new(),
+                        is_animating: false,
                         hit_tester: None,
                         shared_hit_tester: Arc::new(SharedHitTester::new()),
                         frame_is_valid: false,
@@ -4,12 +4,12 @@
 
 
 use api::units::*;
-use api::{ColorF, ImageFormat, LineOrientation, BorderStyle};
+use api::{ColorF, ImageFormat, LineOrientation, BorderStyle, MorphologyOperator, RgbColorSpace};
 use crate::batch::{AlphaBatchBuilder, AlphaBatchContainer, BatchTextures};
 use crate::batch::{ClipBatcher, BatchBuilder, CommandBufferList};
 use crate::spatial_tree::SpatialTree;
 use crate::clip::ClipStore;
-use crate::frame_builder::{FrameGlobalResources};
+use crate::frame_builder::{FrameGlobalResources, ToneMapping};
 use crate::gpu_cache::{GpuCache, GpuCacheAddress};
 use crate::gpu_types::{BorderInstance, SvgFilterInstance, BlurDirection, BlurInstance, PrimitiveHeaders, ScalingInstance};
 use crate::gpu_types::{TransformPalette, ZBufferIdGenerator};
@@ -62,6 +62,8 @@ pub struct RenderTargetContext<'a, 'rc> {
     pub globals: &'a FrameGlobalResources,
     pub tile_caches: &'a FastHashMap<SliceId, Box<TileCacheInstance>>,
     pub root_spatial_node_index: SpatialNodeIndex,
+    pub hdr_tone_mapping: Option<ToneMapping>,
+    pub output_color_space: RgbColorSpace,
 }
 
 /// Represents a number of rendering operations on a surface.
@@ -396,7 +398,8 @@ impl RenderTarget for ColorRenderTarget {
             RenderTaskKind::LinearGradient(..) |
             RenderTaskKind::RadialGradient(..) |
             RenderTaskKind::ConicGradient(..) |
-            RenderTaskKind::LineDecoration(..) => {
+            RenderTaskKind::LineDecoration(..) |
+            RenderTaskKind::Path(..) => {
                 panic!("Should not be added to color target!");
             }
             RenderTaskKind::Readback(..) => {}
@@ -488,6 +491,7 @@ impl RenderTarget for AlphaRenderTarget {
             RenderTaskKind::Blit(..) |
             RenderTaskKind::Border(..) |
             RenderTaskKind::LineDecoration(..) |
+            RenderTaskKind::Path(..) |
             RenderTaskKind::FastLinearGradient(..) |
             RenderTaskKind::LinearGradient(..) |
             RenderTaskKind::RadialGradient(..) |
@@ -597,6 +601,7 @@ pub struct TextureCacheRenderTarget {
     pub border_segments_solid: Vec<BorderInstance>,
     pub clears: Vec<DeviceIntRect>,
     pub line_decorations: Vec<LineDecorationJob>,
+    pub paths: Vec<PathJob>,
     pub fast_linear_gradients: Vec<FastLinearGradientInstance>,
     pub linear_gradients: Vec<LinearGradientInstance>,
     pub radial_gradients: Vec<RadialGradientInstance>,
@@ -613,6 +618,7 @@ impl TextureCacheRenderTarget {
             border_segments_solid: vec![],
             clears: vec![],
             line_decorations: vec![],
+            paths: vec![],
             fast_linear_gradients: vec![],
             linear_gradients: vec![],
             radial_gradients: vec![],
@@ -647,6 +653,19 @@ impl TextureCacheRenderTarget {
                     wavy_line_thickness: info.wavy_line_thickness,
                 });
             }
+            RenderTaskKind::Path(ref info) => {
+                self.clears.push(target_rect);
+
+                // A path without edges covers nothing, the clear is enough.
+                if let Some(ref handle) = info.edges_gpu_cache_handle {
+                    self.paths.push(PathJob {
+                        task_rect: target_rect.to_f32(),
+                        edges_address: gpu_cache.get_address(handle),
+                        edge_count: info.edges.len() as i32,
+                        fill_rule: u8::from(info.fill_rule) as i32,
+                    });
+                }
+            }
             RenderTaskKind::HorizontalBlur(..) => {
                 add_blur_instances(
                     &mut self.horizontal_blurs,
@@ -747,7 +766,11 @@ fn add_scaling_instances(
 
     let source = source_task.unwrap().get_texture_source();
 
-    let source_rect = source_task.unwrap().get_target_rect().to_f32();
+    let source_task_rect = source_task.unwrap().get_target_rect();
+    let source_rect = match task.source_rect {
+        Some(rect) => rect.translate(source_task_rect.min.to_vector()),
+        None => source_task_rect,
+    }.to_f32();
 
     instances
         .entry(source)
@@ -789,10 +812,19 @@ fn add_svg_filter_instances(
         SvgFilterInfo::ComponentTransfer(..) => 8,
         SvgFilterInfo::Identity => 9,
         SvgFilterInfo::Composite(..) => 10,
+        SvgFilterInfo::Turbulence(..) => 11,
+        SvgFilterInfo::Morphology(..) => 12,
+        SvgFilterInfo::ConvolveMatrix(..) => 13,
+        SvgFilterInfo::DisplacementMap(..) => 14,
+        SvgFilterInfo::DiffuseLighting(..) => 15,
+        SvgFilterInfo::SpecularLighting(..) => 16,
+        SvgFilterInfo::Tile(..) => 17,
+        SvgFilterInfo::Image(..) => 18,
     };
 
     let input_count = match filter {
-        SvgFilterInfo::Flood(..) => 0,
+        SvgFilterInfo::Flood(..) |
+        SvgFilterInfo::Turbulence(..) => 0,
 
         SvgFilterInfo::LinearToSrgb |
         SvgFilterInfo::SrgbToLinear |
@@ -800,12 +832,19 @@ fn add_svg_filter_instances(
         SvgFilterInfo::ColorMatrix(..) |
         SvgFilterInfo::Offset(..) |
         SvgFilterInfo::ComponentTransfer(..) |
-        SvgFilterInfo::Identity => 1,
+        SvgFilterInfo::Identity |
+        SvgFilterInfo::Morphology(..) |
+        SvgFilterInfo::ConvolveMatrix(..) |
+        SvgFilterInfo::DiffuseLighting(..) |
+        SvgFilterInfo::SpecularLighting(..) |
+        SvgFilterInfo::Tile(..) |
+        SvgFilterInfo::Image(..) => 1,
 
         // Not techincally a 2 input filter, but we have 2 inputs here: original content & blurred content.
         SvgFilterInfo::DropShadow(..) |
         SvgFilterInfo::Blend(..) |
-        SvgFilterInfo::Composite(..) => 2,
+        SvgFilterInfo::Composite(..) |
+        SvgFilterInfo::DisplacementMap(..) => 2,
     };
 
     let generic_int = match filter {
@@ -817,6 +856,12 @@ fn add_svg_filter_instances(
               data.a_func.to_int()) as u16),
         SvgFilterInfo::Composite(operator) =>
             operator.as_int() as u16,
+        SvgFilterInfo::Morphology(operator, _) => match operator {
+            MorphologyOperator::Erode => 0,
+            MorphologyOperator::Dilate => 1,
+        },
+        SvgFilterInfo::DisplacementMap(_, x_channel, y_channel) =>
+            (*x_channel as u16) | (*y_channel as u16) << 4,
         SvgFilterInfo::LinearToSrgb |
         SvgFilterInfo::SrgbToLinear |
         SvgFilterInfo::Flood(..) |
@@ -824,7 +869,13 @@ fn add_svg_filter_instances(
         SvgFilterInfo::ColorMatrix(..) |
         SvgFilterInfo::DropShadow(..) |
         SvgFilterInfo::Offset(..) |
-        SvgFilterInfo::Identity => 0,
+        SvgFilterInfo::Identity |
+        SvgFilterInfo::Turbulence(..) |
+        SvgFilterInfo::ConvolveMatrix(..) |
+        SvgFilterInfo::DiffuseLighting(..) |
+        SvgFilterInfo::SpecularLighting(..) |
+        SvgFilterInfo::Tile(..) |
+        SvgFilterInfo::Image(..) => 0,
     };
 
     let instance = SvgFilterInstance {
@@ -867,3 +918,14 @@ pub struct LineDecorationJob {
     pub style: i32,
     pub axis_select: f32,
 }
+
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+#[derive(Clone, Debug)]
+#[repr(C)]
+pub struct PathJob {
+    pub task_rect: DeviceRect,
+    pub edges_address: GpuCacheAddress,
+    pub edge_count: i32,
+    pub fill_rule: i32,
+}
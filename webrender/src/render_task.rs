@@ -4,16 +4,19 @@
 
 use api::{CompositeOperator, FilterPrimitive, FilterPrimitiveInput, FilterPrimitiveKind};
 use api::{LineStyle, LineOrientation, ClipMode, MixBlendMode, ColorF, ColorSpace};
+use api::{ColorChannel, ConvolveMatrixPrimitive, EdgeMode, LightSource, MorphologyOperator};
+use api::{DistantLight, PointLight, SpotLight, TurbulenceNoiseType, FillRule};
 use api::MAX_RENDER_TASK_SIZE;
 use api::units::*;
 use crate::batch::CommandBufferIndex;
 use crate::clip::{ClipDataStore, ClipItemKind, ClipStore, ClipNodeRange};
 use crate::spatial_tree::SpatialNodeIndex;
-use crate::filterdata::SFilterData;
+use crate::filterdata::{SFilterData, push_turbulence_tables};
 use crate::frame_builder::{FrameBuilderConfig};
 use crate::gpu_cache::{GpuCache, GpuCacheAddress, GpuCacheHandle};
 use crate::gpu_types::{BorderInstance, ImageSource, UvRectKind};
 use crate::internal_types::{CacheTextureId, FastHashMap, TextureSource, Swizzle};
+use crate::path::PathEdge;
 use crate::picture::ResolvedSurfaceTexture;
 use crate::prim_store::ClipData;
 use crate::prim_store::gradient::{
@@ -31,6 +34,44 @@ use smallvec::SmallVec;
 const FLOATS_PER_RENDER_TASK_INFO: usize = 8;
 pub const MAX_BLUR_STD_DEVIATION: f32 = 4.0;
 pub const MIN_DOWNSCALING_RT_SIZE: i32 = 8;
+/// The largest radius, in device pixels, of each morphology filter pass.
+const MAX_MORPHOLOGY_RADIUS: f32 = 100.0;
+const MAX_TURBULENCE_OCTAVES: u32 = 10;
+/// The offset added to turbulence lattice coordinates so they are positive.
+const TURBULENCE_PERLIN_N: f32 = 4096.0;
+
+/// Converts the positions of a light source from the local space of a filter
+/// to the device space of its render tasks.
+fn light_to_task_space(
+    light: LightSource,
+    local_to_task: &dyn Fn(LayoutPoint) -> DevicePoint,
+    device_pixel_scale: DevicePixelScale,
+) -> LightSource {
+    match light {
+        LightSource::Distant(..) => light,
+        LightSource::Point(light) => {
+            let position = local_to_task(LayoutPoint::new(light.x, light.y));
+            LightSource::Point(PointLight {
+                x: position.x,
+                y: position.y,
+                z: light.z * device_pixel_scale.0,
+            })
+        }
+        LightSource::Spot(light) => {
+            let position = local_to_task(LayoutPoint::new(light.x, light.y));
+            let points_at = local_to_task(LayoutPoint::new(light.points_at_x, light.points_at_y));
+            LightSource::Spot(SpotLight {
+                x: position.x,
+                y: position.y,
+                z: light.z * device_pixel_scale.0,
+                points_at_x: points_at.x,
+                points_at_y: points_at.y,
+                points_at_z: light.points_at_z * device_pixel_scale.0,
+                ..light
+            })
+        }
+    }
+}
 
 fn render_task_sanity_check(size: &DeviceIntSize) {
     if size.width > MAX_RENDER_TASK_SIZE ||
@@ -244,6 +285,9 @@ impl BlurTask {
 pub struct ScalingTask {
     pub target_kind: RenderTargetKind,
     pub padding: DeviceIntSideOffsets,
+    /// The part of the source task that is scaled, relative to its origin.
+    /// The whole source task when None.
+    pub source_rect: Option<DeviceIntRect>,
 }
 
 #[derive(Debug)]
@@ -270,6 +314,17 @@ pub struct LineDecorationTask {
     pub local_size: LayoutSize,
 }
 
+#[derive(Debug)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct PathTask {
+    /// The flattened edges of the path, in task pixels relative to the
+    /// origin of the task.
+    pub edges: Vec<PathEdge>,
+    pub fill_rule: FillRule,
+    pub edges_gpu_cache_handle: Option<GpuCacheHandle>,
+}
+
 #[derive(Debug)]
 #[cfg_attr(feature = "capture", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
@@ -286,6 +341,49 @@ pub enum SvgFilterInfo {
     Composite(CompositeOperator),
     // TODO: This is used as a hack to ensure that a blur task's input is always in the blur's previous pass.
     Identity,
+    Turbulence(Box<TurbulenceInfo>),
+    /// The radius is in device pixels.
+    Morphology(MorphologyOperator, DeviceSize),
+    ConvolveMatrix(Box<ConvolveMatrixPrimitive>),
+    /// The scale is in device pixels.
+    DisplacementMap(f32, ColorChannel, ColorChannel),
+    DiffuseLighting(Box<LightingInfo>),
+    SpecularLighting(Box<LightingInfo>),
+    /// The rect of the input to repeat, relative to the task.
+    Tile(DeviceRect),
+    /// The rect to stretch the input image to, relative to the task.
+    Image(DeviceRect),
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct TurbulenceInfo {
+    /// The base frequencies, already adjusted for stitching if needed.
+    pub base_frequency: (f32, f32),
+    pub num_octaves: u32,
+    pub seed: i32,
+    pub noise_type: TurbulenceNoiseType,
+    /// The lattice width, height and wrapping points of the first octave,
+    /// if the tiles are stitched.
+    pub stitch: Option<[f32; 4]>,
+    /// The device space origin of the task, used to find the local
+    /// position of each pixel.
+    pub content_origin: DevicePoint,
+    pub device_pixel_scale: f32,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct LightingInfo {
+    pub surface_scale: f32,
+    /// The diffuse or specular constant.
+    pub constant: f32,
+    pub specular_exponent: f32,
+    pub color: ColorF,
+    /// The light source, with positions relative to the task in device pixels.
+    pub light: LightSource,
 }
 
 #[derive(Debug)]
@@ -328,6 +426,7 @@ pub enum RenderTaskKind {
     Blit(BlitTask),
     Border(BorderTask),
     LineDecoration(LineDecorationTask),
+    Path(PathTask),
     FastLinearGradient(FastLinearGradientTask),
     LinearGradient(LinearGradientTask),
     RadialGradient(RadialGradientTask),
@@ -377,6 +476,7 @@ impl RenderTaskKind {
             RenderTaskKind::Blit(..) => "Blit",
             RenderTaskKind::Border(..) => "Border",
             RenderTaskKind::LineDecoration(..) => "LineDecoration",
+            RenderTaskKind::Path(..) => "Path",
             RenderTaskKind::FastLinearGradient(..) => "FastLinearGradient",
             RenderTaskKind::LinearGradient(..) => "LinearGradient",
             RenderTaskKind::RadialGradient(..) => "RadialGradient",
@@ -391,6 +491,7 @@ impl RenderTaskKind {
         match *self {
             RenderTaskKind::Image(..) |
             RenderTaskKind::LineDecoration(..) |
+            RenderTaskKind::Path(..) |
             RenderTaskKind::Readback(..) |
             RenderTaskKind::Border(..) |
             RenderTaskKind::FastLinearGradient(..) |
@@ -483,6 +584,17 @@ impl RenderTaskKind {
         })
     }
 
+    pub fn new_path(
+        edges: Vec<PathEdge>,
+        fill_rule: FillRule,
+    ) -> Self {
+        RenderTaskKind::Path(PathTask {
+            edges,
+            fill_rule,
+            edges_gpu_cache_handle: None,
+        })
+    }
+
     pub fn new_border_segment(
         instances: Vec<BorderInstance>,
     ) -> Self {
@@ -603,7 +715,8 @@ impl RenderTaskKind {
                 }
                 ClipItemKind::Rectangle { .. } |
                 ClipItemKind::RoundedRectangle { .. } |
-                ClipItemKind::Image { .. } => {}
+                ClipItemKind::Image { .. } |
+                ClipItemKind::Polygon { .. } => {}
             }
         }
 
@@ -666,6 +779,7 @@ impl RenderTaskKind {
             RenderTaskKind::Scaling(..) |
             RenderTaskKind::Border(..) |
             RenderTaskKind::LineDecoration(..) |
+            RenderTaskKind::Path(..) |
             RenderTaskKind::FastLinearGradient(..) |
             RenderTaskKind::LinearGradient(..) |
             RenderTaskKind::RadialGradient(..) |
@@ -679,6 +793,8 @@ impl RenderTaskKind {
                 match task.info {
                     SvgFilterInfo::Opacity(opacity) => [opacity, 0.0, 0.0, 0.0],
                     SvgFilterInfo::Offset(offset) => [offset.x, offset.y, 0.0, 0.0],
+                    SvgFilterInfo::Morphology(_, radius) => [radius.width, radius.height, 0.0, 0.0],
+                    SvgFilterInfo::DisplacementMap(scale, ..) => [scale, 0.0, 0.0, 0.0],
                     _ => [0.0; 4]
                 }
             }
@@ -707,6 +823,17 @@ impl RenderTaskKind {
         &mut self,
         gpu_cache: &mut GpuCache,
     ) {
+        if let RenderTaskKind::Path(ref mut path_task) = self {
+            if !path_task.edges.is_empty() {
+                let handle = path_task.edges_gpu_cache_handle.get_or_insert_with(GpuCacheHandle::new);
+                if let Some(mut request) = gpu_cache.request(handle) {
+                    for edge in &path_task.edges {
+                        request.push(*edge);
+                    }
+                }
+            }
+        }
+
         if let RenderTaskKind::SvgFilter(ref mut filter_task) = self {
             match filter_task.info {
                 SvgFilterInfo::ColorMatrix(ref matrix) => {
@@ -738,6 +865,106 @@ impl RenderTaskKind {
                         }
                     }
                 }
+                SvgFilterInfo::Turbulence(ref info) => {
+                    let handle = filter_task.extra_gpu_cache_handle.get_or_insert_with(GpuCacheHandle::new);
+                    if let Some(mut request) = gpu_cache.request(handle) {
+                        let flags = match info.noise_type {
+                            TurbulenceNoiseType::FractalNoise => 1.0,
+                            TurbulenceNoiseType::Turbulence => 0.0,
+                        } + if info.stitch.is_some() { 2.0 } else { 0.0 };
+                        request.push([
+                            info.base_frequency.0,
+                            info.base_frequency.1,
+                            info.num_octaves as f32,
+                            flags,
+                        ]);
+                        request.push([
+                            info.content_origin.x,
+                            info.content_origin.y,
+                            1.0 / info.device_pixel_scale,
+                            0.0,
+                        ]);
+                        request.push(info.stitch.unwrap_or([0.0; 4]));
+                        push_turbulence_tables(&mut request, info.seed);
+                    }
+                }
+                SvgFilterInfo::ConvolveMatrix(ref info) => {
+                    let handle = filter_task.extra_gpu_cache_handle.get_or_insert_with(GpuCacheHandle::new);
+                    if let Some(mut request) = gpu_cache.request(handle) {
+                        let edge_mode = match info.edge_mode {
+                            EdgeMode::Duplicate => 0.0,
+                            EdgeMode::Wrap => 1.0,
+                            EdgeMode::None => 2.0,
+                        };
+                        request.push([
+                            info.order_x as f32,
+                            info.order_y as f32,
+                            info.target_x as f32,
+                            info.target_y as f32,
+                        ]);
+                        request.push([
+                            info.divisor,
+                            info.bias,
+                            edge_mode,
+                            if info.preserve_alpha { 1.0 } else { 0.0 },
+                        ]);
+                        for values in info.kernel.chunks(4) {
+                            let mut block = [0.0; 4];
+                            block[.. values.len()].copy_from_slice(values);
+                            request.push(block);
+                        }
+                    }
+                }
+                SvgFilterInfo::DiffuseLighting(ref info) |
+                SvgFilterInfo::SpecularLighting(ref info) => {
+                    let handle = filter_task.extra_gpu_cache_handle.get_or_insert_with(GpuCacheHandle::new);
+                    if let Some(mut request) = gpu_cache.request(handle) {
+                        let (light_type, light_data) = match info.light {
+                            LightSource::Distant(DistantLight { azimuth, elevation }) => {
+                                let (azimuth, elevation) = (azimuth.to_radians(), elevation.to_radians());
+                                let direction = [
+                                    azimuth.cos() * elevation.cos(),
+                                    azimuth.sin() * elevation.cos(),
+                                    elevation.sin(),
+                                    0.0,
+                                ];
+                                (0.0, [direction, [0.0; 4]])
+                            }
+                            LightSource::Point(PointLight { x, y, z }) => {
+                                (1.0, [[x, y, z, 0.0], [0.0; 4]])
+                            }
+                            LightSource::Spot(ref light) => {
+                                let direction = [
+                                    light.points_at_x - light.x,
+                                    light.points_at_y - light.y,
+                                    light.points_at_z - light.z,
+                                ];
+                                let length = direction.iter().map(|d| d * d).sum::<f32>().sqrt().max(f32::EPSILON);
+                                // A negative limiting cone angle means the cone is unbounded.
+                                let cos_cone = if light.limiting_cone_angle < 0.0 {
+                                    -2.0
+                                } else {
+                                    light.limiting_cone_angle.to_radians().cos()
+                                };
+                                (2.0, [
+                                    [light.x, light.y, light.z, light.specular_exponent],
+                                    [direction[0] / length, direction[1] / length, direction[2] / length, cos_cone],
+                                ])
+                            }
+                        };
+                        request.push([info.color.r, info.color.g, info.color.b, info.surface_scale]);
+                        request.push([info.constant, info.specular_exponent, light_type, 0.0]);
+                        request.push(light_data[0]);
+                        request.push(light_data[1]);
+                    }
+                }
+                SvgFilterInfo::Tile(rect) |
+                SvgFilterInfo::Image(rect) => {
+                    let handle = filter_task.extra_gpu_cache_handle.get_or_insert_with(GpuCacheHandle::new);
+                    if let Some(mut request) = gpu_cache.request(handle) {
+                        request.push([rect.min.x, rect.min.y, rect.max.x, rect.max.y]);
+                    }
+                }
                 _ => {},
             }
         }
@@ -1023,6 +1250,43 @@ impl RenderTask {
         target_kind: RenderTargetKind,
         padded_size: DeviceIntSize,
         padding: DeviceIntSideOffsets,
+    ) -> RenderTaskId {
+        Self::new_scaling_impl(
+            source,
+            rg_builder,
+            target_kind,
+            padded_size,
+            padding,
+            None,
+        )
+    }
+
+    /// Scale the `source_rect` part of the source task, relative to its
+    /// origin, to `size`.
+    pub fn new_scaling_from_rect(
+        source: RenderTaskId,
+        rg_builder: &mut RenderTaskGraphBuilder,
+        target_kind: RenderTargetKind,
+        size: DeviceIntSize,
+        source_rect: DeviceIntRect,
+    ) -> RenderTaskId {
+        Self::new_scaling_impl(
+            source,
+            rg_builder,
+            target_kind,
+            size,
+            DeviceIntSideOffsets::zero(),
+            Some(source_rect),
+        )
+    }
+
+    fn new_scaling_impl(
+        source: RenderTaskId,
+        rg_builder: &mut RenderTaskGraphBuilder,
+        target_kind: RenderTargetKind,
+        padded_size: DeviceIntSize,
+        padding: DeviceIntSideOffsets,
+        source_rect: Option<DeviceIntRect>,
     ) -> RenderTaskId {
         let uv_rect_kind = rg_builder.get_task(source).uv_rect_kind();
 
@@ -1032,6 +1296,7 @@ impl RenderTask {
                 RenderTaskKind::Scaling(ScalingTask {
                     target_kind,
                     padding,
+                    source_rect,
                 }),
             ).with_uv_rect_kind(uv_rect_kind)
         );
@@ -1045,7 +1310,10 @@ impl RenderTask {
         filter_primitives: &[FilterPrimitive],
         filter_datas: &[SFilterData],
         rg_builder: &mut RenderTaskGraphBuilder,
+        resource_cache: &mut ResourceCache,
+        gpu_cache: &mut GpuCache,
         content_size: DeviceIntSize,
+        content_origin: DevicePoint,
         uv_rect_kind: UvRectKind,
         original_task_id: RenderTaskId,
         device_pixel_scale: DevicePixelScale,
@@ -1055,6 +1323,18 @@ impl RenderTask {
             return original_task_id;
         }
 
+        // Maps a point in the local space of the filter to the device space
+        // of the filter tasks, which all share the same origin.
+        let local_to_task = |point: LayoutPoint| -> DevicePoint {
+            DevicePoint::new(
+                point.x * device_pixel_scale.0 - content_origin.x,
+                point.y * device_pixel_scale.0 - content_origin.y,
+            )
+        };
+        let local_rect_to_task = |rect: &LayoutRect| -> DeviceRect {
+            DeviceRect::new(local_to_task(rect.min), local_to_task(rect.max))
+        };
+
         // Resolves the input to a filter primitive
         let get_task_input = |
             input: &FilterPrimitiveInput,
@@ -1329,6 +1609,290 @@ impl RenderTask {
                         rg_builder,
                     )
                 }
+                FilterPrimitiveKind::Turbulence(ref info) => {
+                    let mut base_frequency = (info.base_frequency_x, info.base_frequency_y);
+
+                    // Adjust the base frequencies so that an integral number of
+                    // lattice cells fit in the stitch tile, as described in
+                    // https://drafts.fxtf.org/filter-effects/#feTurbulenceElement
+                    let stitch = if info.stitch_tiles {
+                        let tile = info.stitch_rect;
+                        let adjust = |frequency: f32, size: f32| {
+                            if frequency == 0.0 || size <= 0.0 {
+                                return frequency;
+                            }
+                            let lo = (size * frequency).floor() / size;
+                            let hi = (size * frequency).ceil() / size;
+                            if frequency / lo < hi / frequency { lo } else { hi }
+                        };
+                        base_frequency.0 = adjust(base_frequency.0, tile.width());
+                        base_frequency.1 = adjust(base_frequency.1, tile.height());
+
+                        let width = (tile.width() * base_frequency.0 + 0.5).floor();
+                        let height = (tile.height() * base_frequency.1 + 0.5).floor();
+                        Some([
+                            width,
+                            height,
+                            (tile.min.x * base_frequency.0 + TURBULENCE_PERLIN_N + width).floor(),
+                            (tile.min.y * base_frequency.1 + TURBULENCE_PERLIN_N + height).floor(),
+                        ])
+                    } else {
+                        None
+                    };
+
+                    RenderTask::new_svg_filter_primitive(
+                        smallvec![],
+                        content_size,
+                        uv_rect_kind,
+                        SvgFilterInfo::Turbulence(Box::new(TurbulenceInfo {
+                            base_frequency,
+                            num_octaves: info.num_octaves.min(MAX_TURBULENCE_OCTAVES),
+                            seed: info.seed,
+                            noise_type: info.noise_type,
+                            stitch,
+                            content_origin,
+                            device_pixel_scale: device_pixel_scale.0,
+                        })),
+                        rg_builder,
+                    )
+                }
+                FilterPrimitiveKind::Morphology(ref info) => {
+                    let input_task_id = get_task_input(
+                        &info.input,
+                        filter_primitives,
+                        rg_builder,
+                        cur_index,
+                        &outputs,
+                        original_task_id,
+                        primitive.color_space
+                    );
+
+                    let radius_x = (info.radius_x * device_pixel_scale.0).min(MAX_MORPHOLOGY_RADIUS).round();
+                    let radius_y = (info.radius_y * device_pixel_scale.0).min(MAX_MORPHOLOGY_RADIUS).round();
+
+                    // The minimum or maximum over a rectangle is separable, so
+                    // apply the horizontal and vertical passes one after the other.
+                    let horizontal_task_id = RenderTask::new_svg_filter_primitive(
+                        smallvec![input_task_id],
+                        content_size,
+                        uv_rect_kind,
+                        SvgFilterInfo::Morphology(info.operator, DeviceSize::new(radius_x, 0.0)),
+                        rg_builder,
+                    );
+
+                    RenderTask::new_svg_filter_primitive(
+                        smallvec![horizontal_task_id],
+                        content_size,
+                        uv_rect_kind,
+                        SvgFilterInfo::Morphology(info.operator, DeviceSize::new(0.0, radius_y)),
+                        rg_builder,
+                    )
+                }
+                FilterPrimitiveKind::ConvolveMatrix(ref info) => {
+                    let input_task_id = get_task_input(
+                        &info.input,
+                        filter_primitives,
+                        rg_builder,
+                        cur_index,
+                        &outputs,
+                        original_task_id,
+                        primitive.color_space
+                    );
+
+                    RenderTask::new_svg_filter_primitive(
+                        smallvec![input_task_id],
+                        content_size,
+                        uv_rect_kind,
+                        SvgFilterInfo::ConvolveMatrix(Box::new(*info)),
+                        rg_builder,
+                    )
+                }
+                FilterPrimitiveKind::DisplacementMap(ref info) => {
+                    let input_1_task_id = get_task_input(
+                        &info.input1,
+                        filter_primitives,
+                        rg_builder,
+                        cur_index,
+                        &outputs,
+                        original_task_id,
+                        primitive.color_space
+                    );
+                    let input_2_task_id = get_task_input(
+                        &info.input2,
+                        filter_primitives,
+                        rg_builder,
+                        cur_index,
+                        &outputs,
+                        original_task_id,
+                        primitive.color_space
+                    );
+
+                    RenderTask::new_svg_filter_primitive(
+                        smallvec![input_1_task_id, input_2_task_id],
+                        content_size,
+                        uv_rect_kind,
+                        SvgFilterInfo::DisplacementMap(
+                            info.scale * device_pixel_scale.0,
+                            info.x_channel,
+                            info.y_channel,
+                        ),
+                        rg_builder,
+                    )
+                }
+                FilterPrimitiveKind::DiffuseLighting(ref info) => {
+                    let input_task_id = get_task_input(
+                        &info.input,
+                        filter_primitives,
+                        rg_builder,
+                        cur_index,
+                        &outputs,
+                        original_task_id,
+                        primitive.color_space
+                    );
+
+                    RenderTask::new_svg_filter_primitive(
+                        smallvec![input_task_id],
+                        content_size,
+                        uv_rect_kind,
+                        SvgFilterInfo::DiffuseLighting(Box::new(LightingInfo {
+                            surface_scale: info.surface_scale * device_pixel_scale.0,
+                            constant: info.diffuse_constant,
+                            specular_exponent: 1.0,
+                            color: info.lighting_color,
+                            light: light_to_task_space(info.light, &local_to_task, device_pixel_scale),
+                        })),
+                        rg_builder,
+                    )
+                }
+                FilterPrimitiveKind::SpecularLighting(ref info) => {
+                    let input_task_id = get_task_input(
+                        &info.input,
+                        filter_primitives,
+                        rg_builder,
+                        cur_index,
+                        &outputs,
+                        original_task_id,
+                        primitive.color_space
+                    );
+
+                    RenderTask::new_svg_filter_primitive(
+                        smallvec![input_task_id],
+                        content_size,
+                        uv_rect_kind,
+                        SvgFilterInfo::SpecularLighting(Box::new(LightingInfo {
+                            surface_scale: info.surface_scale * device_pixel_scale.0,
+                            constant: info.specular_constant,
+                            specular_exponent: info.specular_exponent,
+                            color: info.lighting_color,
+                            light: light_to_task_space(info.light, &local_to_task, device_pixel_scale),
+                        })),
+                        rg_builder,
+                    )
+                }
+                FilterPrimitiveKind::Tile(ref info) => {
+                    let input_task_id = get_task_input(
+                        &info.input,
+                        filter_primitives,
+                        rg_builder,
+                        cur_index,
+                        &outputs,
+                        original_task_id,
+                        primitive.color_space
+                    );
+
+                    RenderTask::new_svg_filter_primitive(
+                        smallvec![input_task_id],
+                        content_size,
+                        uv_rect_kind,
+                        SvgFilterInfo::Tile(local_rect_to_task(&info.source_rect)),
+                        rg_builder,
+                    )
+                }
+                FilterPrimitiveKind::Image(ref info) => {
+                    let request = ImageRequest {
+                        key: info.image_key,
+                        rendering: info.rendering,
+                        tile: None,
+                    };
+
+                    // Tiled images are not supported as filter inputs, they
+                    // produce transparent black like missing images.
+                    let is_valid = resource_cache
+                        .get_image_properties(info.image_key)
+                        .map_or(false, |properties| properties.tiling.is_none());
+
+                    if is_valid {
+                        let size = resource_cache.request_image(request, gpu_cache);
+                        let image_task_id = rg_builder.add().init(
+                            RenderTask::new_image(size, request)
+                        );
+
+                        let mut task_id = RenderTask::new_svg_filter_primitive(
+                            smallvec![image_task_id],
+                            content_size,
+                            uv_rect_kind,
+                            SvgFilterInfo::Image(local_rect_to_task(&info.rect)),
+                            rg_builder,
+                        );
+
+                        // Images are always sRGB.
+                        if primitive.color_space == ColorSpace::LinearRgb {
+                            task_id = RenderTask::new_svg_filter_primitive(
+                                smallvec![task_id],
+                                content_size,
+                                uv_rect_kind,
+                                SvgFilterInfo::SrgbToLinear,
+                                rg_builder,
+                            );
+                        }
+
+                        task_id
+                    } else {
+                        RenderTask::new_svg_filter_primitive(
+                            smallvec![],
+                            content_size,
+                            uv_rect_kind,
+                            SvgFilterInfo::Flood(ColorF::TRANSPARENT),
+                            rg_builder,
+                        )
+                    }
+                }
+                FilterPrimitiveKind::Merge(ref info) => {
+                    // Composite each input over the result of the previous ones.
+                    let mut task_id = None;
+                    for input in info.inputs() {
+                        let input_task_id = get_task_input(
+                            input,
+                            filter_primitives,
+                            rg_builder,
+                            cur_index,
+                            &outputs,
+                            original_task_id,
+                            primitive.color_space
+                        );
+
+                        task_id = Some(match task_id {
+                            Some(backdrop_task_id) => RenderTask::new_svg_filter_primitive(
+                                smallvec![input_task_id, backdrop_task_id],
+                                content_size,
+                                uv_rect_kind,
+                                SvgFilterInfo::Composite(CompositeOperator::Over),
+                                rg_builder,
+                            ),
+                            None => input_task_id,
+                        });
+                    }
+
+                    task_id.unwrap_or_else(|| {
+                        RenderTask::new_svg_filter_primitive(
+                            smallvec![],
+                            content_size,
+                            uv_rect_kind,
+                            SvgFilterInfo::Flood(ColorF::TRANSPARENT),
+                            rg_builder,
+                        )
+                    })
+                }
             };
             outputs.push(render_task_id);
         }
@@ -1336,6 +1900,13 @@ impl RenderTask {
         // The output of a filter is the output of the last primitive in the chain.
         let mut render_task_id = *outputs.last().unwrap();
 
+        // The source graphic is always rendered, even if no primitive reads it
+        // (e.g. a chain made only of images or floods). Keep it as an input of
+        // the output so its surface is returned to the pool after use.
+        if render_task_id != original_task_id && rg_builder.is_root(original_task_id) {
+            rg_builder.add_dependency(render_task_id, original_task_id);
+        }
+
         // Convert to sRGB if needed
         if filter_primitives.last().unwrap().color_space == ColorSpace::LinearRgb {
             render_task_id = RenderTask::new_svg_filter_primitive(
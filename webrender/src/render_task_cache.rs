@@ -12,12 +12,13 @@ use crate::freelist::{FreeList, FreeListHandle, WeakFreeListHandle};
 use crate::gpu_cache::GpuCache;
 use crate::internal_types::FastHashMap;
 use crate::picture::SurfaceIndex;
-use crate::prim_store::image::ImageCacheKey;
+use crate::prim_store::image::{ImageCacheKey, ScaledImageCacheKey};
 use crate::prim_store::gradient::{
     FastLinearGradientCacheKey, LinearGradientCacheKey, RadialGradientCacheKey,
     ConicGradientCacheKey,
 };
 use crate::prim_store::line_dec::LineDecorationCacheKey;
+use crate::prim_store::path::PathCacheKey;
 use crate::resource_cache::CacheItem;
 use std::{mem, usize, f32, i32};
 use crate::surface::SurfaceBuilder;
@@ -46,8 +47,10 @@ pub enum RenderTaskParent {
 pub enum RenderTaskCacheKeyKind {
     BoxShadow(BoxShadowCacheKey),
     Image(ImageCacheKey),
+    ScaledImage(ScaledImageCacheKey),
     BorderSegment(BorderSegmentCacheKey),
     LineDecoration(LineDecorationCacheKey),
+    Path(PathCacheKey),
     FastLinearGradient(FastLinearGradientCacheKey),
     LinearGradient(LinearGradientCacheKey),
     RadialGradient(RadialGradientCacheKey),
@@ -17,6 +17,8 @@ use crate::render_task::{RenderTaskData, RenderTaskKind};
 use crate::resource_cache::ResourceCache;
 use crate::texture_pack::GuillotineAllocator;
 use crate::prim_store::DeferredResolve;
+#[cfg(feature = "capture")]
+use crate::render_api::RenderTaskGraphFormat;
 use crate::image_source::{resolve_image, resolve_cached_render_task};
 use crate::util::VecHelper;
 use smallvec::SmallVec;
@@ -273,6 +275,14 @@ impl RenderTaskGraphBuilder {
         self.roots.remove(&input);
     }
 
+    /// Returns true if no task has been made to depend on `task_id` yet.
+    pub fn is_root(
+        &self,
+        task_id: RenderTaskId,
+    ) -> bool {
+        self.roots.contains(&task_id)
+    }
+
     /// End the graph building phase and produce the immutable task graph for this frame
     pub fn end_frame(
         &mut self,
@@ -830,6 +840,19 @@ impl RenderPass {
     }
 }
 
+/// Dump the render graph in the requested format for debugging purposes.
+#[cfg(feature = "capture")]
+pub fn dump_render_tasks(
+    render_tasks: &RenderTaskGraph,
+    format: RenderTaskGraphFormat,
+    output: &mut dyn std::io::Write,
+) -> std::io::Result<()> {
+    match format {
+        RenderTaskGraphFormat::Dot => dump_render_tasks_as_dot(render_tasks, output),
+        RenderTaskGraphFormat::Svg => dump_render_tasks_as_svg(render_tasks, output),
+    }
+}
+
 // Dump an SVG visualization of the render graph for debugging purposes
 #[cfg(feature = "capture")]
 pub fn dump_render_tasks_as_svg(
@@ -1011,6 +1034,130 @@ fn dump_task_dependency_link(
     }
 }
 
+#[cfg(feature = "capture")]
+fn describe_static_surface(surface: &StaticRenderTaskSurface) -> String {
+    match *surface {
+        StaticRenderTaskSurface::TextureCache { texture, target_kind } => {
+            format!("texture cache #{} ({:?})", texture.0, target_kind)
+        }
+        StaticRenderTaskSurface::ReadOnly { ref source } => {
+            format!("read-only {:?}", source)
+        }
+        StaticRenderTaskSurface::PictureCache { .. } => {
+            "picture cache".to_string()
+        }
+    }
+}
+
+#[cfg(feature = "capture")]
+fn describe_task_location(location: &RenderTaskLocation) -> String {
+    match *location {
+        RenderTaskLocation::Unallocated { .. } => "unallocated".to_string(),
+        RenderTaskLocation::CacheRequest { .. } => "cache request".to_string(),
+        RenderTaskLocation::Existing { parent_task_id, .. } => {
+            format!("shares task {}", parent_task_id.index)
+        }
+        RenderTaskLocation::Dynamic { texture_id, rect } => {
+            format!("target #{} at ({}, {})", texture_id.0, rect.min.x, rect.min.y)
+        }
+        RenderTaskLocation::Static { ref surface, rect } => {
+            format!("{} at ({}, {})", describe_static_surface(surface), rect.min.x, rect.min.y)
+        }
+    }
+}
+
+// Dump a Graphviz DOT description of the render graph for debugging purposes.
+// Passes and their sub-passes (render targets) are emitted as nested clusters,
+// in the order they are drawn, and each edge goes from an input to the task
+// that reads it.
+#[cfg(feature = "capture")]
+pub fn dump_render_tasks_as_dot(
+    render_tasks: &RenderTaskGraph,
+    output: &mut dyn std::io::Write,
+) -> std::io::Result<()> {
+    // DOT accepts the same escapes as Rust's debug formatting of strings,
+    // which also turns line breaks into centered label lines.
+    let node_label = |index: usize| {
+        let task = &render_tasks.tasks[index];
+        let size = task.location.size();
+        format!(
+            "{} {}\n{}x{}\n{}\nrender_on {} free_after {}",
+            index,
+            task.kind.as_str(),
+            size.width,
+            size.height,
+            describe_task_location(&task.location),
+            task.render_on.0,
+            if task.free_after == PassId::INVALID {
+                "never".to_string()
+            } else {
+                task.free_after.0.to_string()
+            },
+        )
+    };
+
+    writeln!(output, "digraph render_tasks {{")?;
+    writeln!(output, "    rankdir=LR;")?;
+    writeln!(output, "    node [shape=box, fontname=\"monospace\", fontsize=10];")?;
+
+    let mut emitted = vec![false; render_tasks.tasks.len()];
+
+    for (p, pass) in render_tasks.passes.iter().enumerate().rev() {
+        writeln!(output, "    subgraph cluster_pass_{} {{", p)?;
+        writeln!(output, "        label=\"pass {}\";", p)?;
+
+        for (s, sub_pass) in pass.sub_passes.iter().enumerate() {
+            let surface = match sub_pass.surface {
+                SubPassSurface::Dynamic { texture_id, target_kind, used_rect } => {
+                    format!(
+                        "{:?} target #{} (used {}x{})",
+                        target_kind,
+                        texture_id.0,
+                        used_rect.width(),
+                        used_rect.height(),
+                    )
+                }
+                SubPassSurface::Persistent { ref surface } => describe_static_surface(surface),
+            };
+
+            writeln!(output, "        subgraph cluster_pass_{}_{} {{", p, s)?;
+            writeln!(output, "            label={:?};", surface)?;
+            for task_id in &sub_pass.task_ids {
+                let index = task_id.index as usize;
+                emitted[index] = true;
+                writeln!(output, "            task_{} [label={:?}];", index, node_label(index))?;
+            }
+            writeln!(output, "        }}")?;
+        }
+
+        // Tasks that were assigned to the pass but not to a target.
+        for task_id in &pass.task_ids {
+            let index = task_id.index as usize;
+            if !emitted[index] {
+                emitted[index] = true;
+                writeln!(output, "        task_{} [label={:?}];", index, node_label(index))?;
+            }
+        }
+
+        writeln!(output, "    }}")?;
+    }
+
+    // Tasks that don't render anything themselves (images, cached tasks).
+    for (index, &was_emitted) in emitted.iter().enumerate() {
+        if !was_emitted {
+            writeln!(output, "    task_{} [label={:?}, style=dashed];", index, node_label(index))?;
+        }
+    }
+
+    for (index, task) in render_tasks.tasks.iter().enumerate() {
+        for dep in &task.children {
+            writeln!(output, "    task_{} -> task_{};", dep.index, index)?;
+        }
+    }
+
+    writeln!(output, "}}")
+}
+
 /// Construct a picture cache render task location for testing
 #[cfg(test)]
 fn pc_target(
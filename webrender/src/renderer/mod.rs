@@ -65,8 +65,10 @@ use crate::device::{UploadMethod, UploadPBOPool, VertexUsageHint};
 use crate::device::query::{GpuSampler, GpuTimer};
 #[cfg(feature = "capture")]
 use crate::device::FBOId;
+#[cfg(feature = "sw_compositor")]
+use crate::compositor::sw_compositor::SwRasterizeWorkers;
 use crate::debug_item::DebugItem;
-use crate::frame_builder::{Frame, ChasePrimitive, FrameBuilderConfig};
+use crate::frame_builder::{Frame, ChasePrimitive, FrameBuilderConfig, ToneMapping};
 use crate::glyph_cache::GlyphCache;
 use crate::glyph_rasterizer::{GlyphFormat, GlyphRasterizer, SharedFontResources};
 use crate::gpu_cache::{GpuCacheUpdate, GpuCacheUpdateList};
@@ -201,6 +203,10 @@ const GPU_TAG_CACHE_LINE_DECORATION: GpuProfileTag = GpuProfileTag {
     label: "C_LineDecoration",
     color: debug_colors::YELLOWGREEN,
 };
+const GPU_TAG_CACHE_PATH: GpuProfileTag = GpuProfileTag {
+    label: "C_Path",
+    color: debug_colors::ORCHID,
+};
 const GPU_TAG_CACHE_FAST_LINEAR_GRADIENT: GpuProfileTag = GpuProfileTag {
     label: "C_FastLinearGradient",
     color: debug_colors::BROWN,
@@ -978,6 +984,9 @@ impl Renderer {
         );
 
         let color_cache_formats = device.preferred_color_formats();
+        let compressed_texture_formats = device.supported_compressed_formats();
+        let high_bit_depth_formats = device.supported_high_bit_depth_formats();
+        let hdr_tone_mapping = options.hdr_tone_mapping;
         let swizzle_settings = device.swizzle_settings();
         let use_dual_source_blending =
             device.get_capabilities().supports_dual_source_blending &&
@@ -1187,6 +1196,7 @@ impl Renderer {
             force_invalidation: false,
             is_software,
             low_quality_pinch_zoom: options.low_quality_pinch_zoom,
+            hdr_tone_mapping: options.hdr_tone_mapping,
         };
         info!("WR {:?}", config);
 
@@ -1211,6 +1221,12 @@ impl Renderer {
                     .build();
                 Arc::new(worker.unwrap())
             });
+        #[cfg(feature = "sw_compositor")]
+        {
+            if let Some(swgl) = options.swgl_context {
+                swgl.set_rasterize_workers(Some(Box::new(SwRasterizeWorkers::new(Arc::clone(&workers)))));
+            }
+        }
         let sampler = options.sampler;
         let namespace_alloc_by_client = options.namespace_alloc_by_client;
 
@@ -1234,6 +1250,7 @@ impl Renderer {
             SceneBuilderThreadChannels::new(api_tx.clone());
 
         let sb_fonts = fonts.clone();
+        let sb_font_contexts = glyph_rasterizer.font_contexts();
 
         thread::Builder::new().name(scene_thread_name.clone()).spawn(move || {
             register_thread_with_profiler(scene_thread_name.clone());
@@ -1242,6 +1259,7 @@ impl Renderer {
             let mut scene_builder = SceneBuilderThread::new(
                 config,
                 sb_fonts,
+                sb_font_contexts,
                 make_size_of_ops(),
                 scene_builder_hooks,
                 scene_builder_channels,
@@ -1321,6 +1339,8 @@ impl Renderer {
             );
 
             resource_cache.enable_multithreading(enable_multithreading);
+            resource_cache.set_native_compressed_formats(compressed_texture_formats);
+            resource_cache.set_native_high_bit_depth_formats(high_bit_depth_formats, hdr_tone_mapping);
 
             let mut backend = RenderBackend::new(
                 api_rx,
@@ -1673,7 +1693,8 @@ impl Renderer {
             DebugCommand::SaveCapture(..) |
             DebugCommand::LoadCapture(..) |
             DebugCommand::StartCaptureSequence(..) |
-            DebugCommand::StopCaptureSequence => {
+            DebugCommand::StopCaptureSequence |
+            DebugCommand::DumpRenderTaskGraphs(..) => {
                 panic!("Capture commands are not welcome here! Did you build with 'capture' feature?")
             }
             DebugCommand::ClearCaches(_)
@@ -1686,6 +1707,17 @@ impl Renderer {
             DebugCommand::SetFlags(flags) => {
                 self.set_debug_flags(flags);
             }
+            DebugCommand::SaveProfilerTrace(path) => {
+                let result = std::fs::File::create(&path).and_then(|file| {
+                    use std::io::Write;
+                    let mut writer = std::io::BufWriter::new(file);
+                    self.profiler.write_chrome_trace(&mut writer)?;
+                    writer.flush()
+                });
+                if let Err(e) = result {
+                    error!("Unable to write the profiler trace to {:?}: {}", path, e);
+                }
+            }
         }
     }
 
@@ -2137,7 +2169,7 @@ impl Renderer {
         self.profiler.set_counters(&mut self.profile);
 
         // Note: profile counters must be set before this or they will count for next frame.
-        self.profiler.update();
+        self.profiler.update(cpu_frame_id);
 
         if self.debug_flags.intersects(DebugFlags::PROFILER_DBG | DebugFlags::PROFILER_CAPTURE) {
             if let Some(device_size) = device_size {
@@ -2224,7 +2256,7 @@ impl Renderer {
             self.gpu_profiles.push_back(GpuProfile::new(gpu_frame_id, &timers));
         }
 
-        self.profiler.set_gpu_time_queries(timers);
+        self.profiler.set_gpu_time_queries(gpu_frame_id, timers);
 
         if !samplers.is_empty() {
             let screen_fraction = 1.0 / device_size.to_f32().area();
@@ -3883,6 +3915,24 @@ impl Renderer {
             );
         }
 
+        // draw polygon clips
+        if !list.polygons.is_empty() {
+            let _gm2 = self.gpu_profiler.start_marker("clip polygons");
+            self.shaders.borrow_mut().cs_clip_polygon.bind(
+                &mut self.device,
+                projection,
+                None,
+                &mut self.renderer_errors,
+                &mut self.profile,
+            );
+            self.draw_instanced_batch(
+                &list.polygons,
+                VertexArrayKind::ClipPolygon,
+                &BatchTextures::empty(),
+                stats,
+            );
+        }
+
         // draw box-shadow clips
         for (mask_texture_id, items) in list.box_shadows.iter() {
             let _gm2 = self.gpu_profiler.start_marker("box-shadows");
@@ -4259,6 +4309,28 @@ impl Renderer {
             self.set_blend(false, FramebufferKind::Other);
         }
 
+        // Draw any path coverage masks for this target.
+        if !target.paths.is_empty() {
+            let _timer = self.gpu_profiler.start_timer(GPU_TAG_CACHE_PATH);
+
+            self.set_blend(false, FramebufferKind::Other);
+
+            self.shaders.borrow_mut().cs_path.bind(
+                &mut self.device,
+                &projection,
+                None,
+                &mut self.renderer_errors,
+                &mut self.profile,
+            );
+
+            self.draw_instanced_batch(
+                &target.paths,
+                VertexArrayKind::Path,
+                &BatchTextures::empty(),
+                stats,
+            );
+        }
+
         // Draw any fast path linear gradients for this target.
         if !target.fast_linear_gradients.is_empty() {
             let _timer = self.gpu_profiler.start_timer(GPU_TAG_CACHE_FAST_LINEAR_GRADIENT);
@@ -5037,6 +5109,9 @@ impl Renderer {
                 self.gpu_profiler.disable_samplers();
             }
         }
+        if let Some(enabled) = flag_changed(self.debug_flags, flags, DebugFlags::PROFILER_TRACE) {
+            self.profiler.set_tracing(enabled);
+        }
 
         self.debug_flags = flags;
     }
@@ -5676,6 +5751,10 @@ pub struct RendererOptions {
     pub upload_pbo_default_size: usize,
     pub batched_upload_threshold: i32,
     pub workers: Option<Arc<ThreadPool>>,
+    /// The SWGL context the renderer draws with, if any. Draw calls then
+    /// rasterize bands of large render targets in parallel on `workers`.
+    #[cfg(feature = "sw_compositor")]
+    pub swgl_context: Option<swgl::Context>,
     pub enable_multithreading: bool,
     pub blob_image_handler: Option<Box<dyn BlobImageHandler>>,
     pub crash_annotator: Option<Box<dyn CrashAnnotator>>,
@@ -5737,6 +5816,10 @@ pub struct RendererOptions {
     /// items, if the zoom factor is relatively small, bilinear filtering should
     /// make the result look quite close to the high-quality zoom, except for glyphs.
     pub low_quality_pinch_zoom: bool,
+    /// If set, colors outside of the displayable range in RGBA16F and RGB10A2
+    /// images are mapped with the given operator when they are drawn.
+    /// Otherwise they are clamped by the 8 bit render targets.
+    pub hdr_tone_mapping: Option<ToneMapping>,
 }
 
 impl RendererOptions {
@@ -5775,6 +5858,8 @@ impl Default for RendererOptions {
             upload_pbo_default_size: 512 * 512 * 4,
             batched_upload_threshold: 512 * 512,
             workers: None,
+            #[cfg(feature = "sw_compositor")]
+            swgl_context: None,
             enable_multithreading: true,
             blob_image_handler: None,
             crash_annotator: None,
@@ -5807,6 +5892,7 @@ impl Default for RendererOptions {
             enable_instancing: true,
             reject_software_rasterizer: false,
             low_quality_pinch_zoom: false,
+            hdr_tone_mapping: None,
         }
     }
 }
@@ -5962,15 +6048,29 @@ impl Renderer {
 
         let short_path = format!("textures/{}.raw", name);
 
-        let bytes_per_pixel = texture.get_format().bytes_per_pixel();
         let read_format = texture.get_format();
         let rect_size = texture.get_dimensions();
 
         let mut file = fs::File::create(root.join(&short_path))
             .expect(&format!("Unable to create {}", short_path));
-        let bytes_per_texture = (rect_size.width * rect_size.height * bytes_per_pixel) as usize;
+        let bytes_per_texture = texture.size_in_bytes();
         let mut data = vec![0; bytes_per_texture];
 
+        // Compressed textures can't be attached to a framebuffer to be read
+        // back, so their contents are left blank.
+        if read_format.is_compressed() {
+            warn!("Unable to read back compressed {:?} texture {}", read_format, name);
+            file.write_all(&data).unwrap();
+            return PlainTexture {
+                data: short_path,
+                size: rect_size,
+                format: read_format,
+                filter: texture.get_filter(),
+                has_depth: texture.supports_depth(),
+                category,
+            };
+        }
+
         //TODO: instead of reading from an FBO with `read_pixels*`, we could
         // read from textures directly with `get_tex_image*`.
 
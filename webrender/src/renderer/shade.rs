@@ -245,6 +245,7 @@ impl LazilyCompiledShader {
             let vertex_descriptor = match vertex_format {
                 VertexArrayKind::Primitive => &desc::PRIM_INSTANCES,
                 VertexArrayKind::LineDecoration => &desc::LINE,
+                VertexArrayKind::Path => &desc::PATH,
                 VertexArrayKind::FastLinearGradient => &desc::FAST_LINEAR_GRADIENT,
                 VertexArrayKind::LinearGradient => &desc::LINEAR_GRADIENT,
                 VertexArrayKind::RadialGradient => &desc::RADIAL_GRADIENT,
@@ -253,6 +254,7 @@ impl LazilyCompiledShader {
                 VertexArrayKind::ClipImage => &desc::CLIP_IMAGE,
                 VertexArrayKind::ClipRect => &desc::CLIP_RECT,
                 VertexArrayKind::ClipBoxShadow => &desc::CLIP_BOX_SHADOW,
+                VertexArrayKind::ClipPolygon => &desc::CLIP_POLYGON,
                 VertexArrayKind::VectorStencil => &desc::VECTOR_STENCIL,
                 VertexArrayKind::VectorCover => &desc::VECTOR_COVER,
                 VertexArrayKind::Border => &desc::BORDER,
@@ -589,6 +591,7 @@ pub struct Shaders {
     pub cs_border_solid: LazilyCompiledShader,
     pub cs_scale: Vec<Option<LazilyCompiledShader>>,
     pub cs_line_decoration: LazilyCompiledShader,
+    pub cs_path: LazilyCompiledShader,
     pub cs_fast_linear_gradient: LazilyCompiledShader,
     pub cs_linear_gradient: LazilyCompiledShader,
     pub cs_radial_gradient: LazilyCompiledShader,
@@ -612,6 +615,7 @@ pub struct Shaders {
     pub cs_clip_rectangle_slow: LazilyCompiledShader,
     pub cs_clip_rectangle_fast: LazilyCompiledShader,
     pub cs_clip_box_shadow: LazilyCompiledShader,
+    pub cs_clip_polygon: LazilyCompiledShader,
     pub cs_clip_image: LazilyCompiledShader,
 
     // The are "primitive shaders". These shaders draw and blend
@@ -813,6 +817,16 @@ impl Shaders {
             profile,
         )?;
 
+        let cs_clip_polygon = LazilyCompiledShader::new(
+            ShaderKind::ClipCache(VertexArrayKind::ClipPolygon),
+            "cs_clip_polygon",
+            &[],
+            device,
+            options.precache_flags,
+            &shader_list,
+            profile,
+        )?;
+
         let cs_clip_image = LazilyCompiledShader::new(
             ShaderKind::ClipCache(VertexArrayKind::ClipImage),
             "cs_clip_image",
@@ -1071,6 +1085,16 @@ impl Shaders {
             profile,
         )?;
 
+        let cs_path = LazilyCompiledShader::new(
+            ShaderKind::Cache(VertexArrayKind::Path),
+            "cs_path",
+            &[],
+            device,
+            options.precache_flags,
+            &shader_list,
+            profile,
+        )?;
+
         let cs_fast_linear_gradient = LazilyCompiledShader::new(
             ShaderKind::Cache(VertexArrayKind::FastLinearGradient),
             "cs_fast_linear_gradient",
@@ -1136,6 +1160,7 @@ impl Shaders {
             cs_blur_rgba8,
             cs_border_segment,
             cs_line_decoration,
+            cs_path,
             cs_fast_linear_gradient,
             cs_linear_gradient,
             cs_radial_gradient,
@@ -1155,6 +1180,7 @@ impl Shaders {
             cs_clip_rectangle_slow,
             cs_clip_rectangle_fast,
             cs_clip_box_shadow,
+            cs_clip_polygon,
             cs_clip_image,
             ps_text_run,
             ps_text_run_dual_source,
@@ -1319,6 +1345,7 @@ impl Shaders {
         self.cs_clip_rectangle_slow.deinit(device);
         self.cs_clip_rectangle_fast.deinit(device);
         self.cs_clip_box_shadow.deinit(device);
+        self.cs_clip_polygon.deinit(device);
         self.cs_clip_image.deinit(device);
         self.ps_text_run.deinit(device);
         if let Some(shader) = self.ps_text_run_dual_source {
@@ -1345,6 +1372,7 @@ impl Shaders {
         self.cs_radial_gradient.deinit(device);
         self.cs_conic_gradient.deinit(device);
         self.cs_line_decoration.deinit(device);
+        self.cs_path.deinit(device);
         self.cs_border_segment.deinit(device);
         self.ps_split_composite.deinit(device);
         self.ps_clear.deinit(device);
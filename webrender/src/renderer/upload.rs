@@ -105,9 +105,9 @@ pub fn upload_to_texture_cache(
                         }
                         ExternalImageSource::Invalid => {
                             // Create a local buffer to fill the pbo.
-                            let bpp = texture.get_format().bytes_per_pixel();
-                            let width = stride.unwrap_or(rect.width() * bpp);
-                            let total_size = width * rect.height();
+                            let format = texture.get_format();
+                            let width = stride.unwrap_or(format.bytes_per_block_row(rect.width()));
+                            let total_size = width * format.block_rows(rect.height());
                             // WR haven't support RGBAF32 format in texture_cache, so
                             // we use u8 type here.
                             dummy_data = vec![0xFFu8; total_size as usize];
@@ -136,8 +136,11 @@ pub fn upload_to_texture_cache(
 
             stats.items_uploaded += 1;
 
+            // The staging textures only cover the 8 bit formats, so high bit
+            // depth atlases are always updated directly.
             let use_batch_upload = renderer.device.use_batched_texture_uploads() &&
                 texture.flags().contains(TextureFlags::IS_SHARED_TEXTURE_CACHE) &&
+                !texture.get_format().is_high_bit_depth_color() &&
                 rect.width() <= BATCH_UPLOAD_TEXTURE_SIZE.width &&
                 rect.height() <= BATCH_UPLOAD_TEXTURE_SIZE.height &&
                 rect.area() < renderer.device.batched_upload_threshold();
@@ -174,6 +177,19 @@ pub fn upload_to_texture_cache(
                     &mut batch_upload_copies,
                     &mut stats,
                 );
+            } else if texture.get_format().is_compressed() {
+                // Compressed textures are never in the shared cache, and are
+                // uploaded directly rather than through a PBO.
+                let upload_start_time = precise_time_ns();
+
+                stats.bytes_uploaded += renderer.device.upload_compressed_texture(
+                    texture,
+                    rect,
+                    stride,
+                    data,
+                );
+
+                stats.upload_time += precise_time_ns() - upload_start_time;
             } else {
                 let upload_start_time = precise_time_ns();
 
@@ -99,6 +99,31 @@ pub mod desc {
         ],
     };
 
+    pub const PATH: VertexDescriptor = VertexDescriptor {
+        vertex_attributes: &[VertexAttribute {
+            name: "aPosition",
+            count: 2,
+            kind: VertexAttributeKind::U8Norm,
+        }],
+        instance_attributes: &[
+            VertexAttribute {
+                name: "aTaskRect",
+                count: 4,
+                kind: VertexAttributeKind::F32,
+            },
+            VertexAttribute {
+                name: "aEdgesAddress",
+                count: 2,
+                kind: VertexAttributeKind::U16,
+            },
+            VertexAttribute {
+                name: "aPathParams",
+                count: 2,
+                kind: VertexAttributeKind::I32,
+            },
+        ],
+    };
+
     pub const FAST_LINEAR_GRADIENT: VertexDescriptor = VertexDescriptor {
         vertex_attributes: &[VertexAttribute {
             name: "aPosition",
@@ -161,6 +186,11 @@ pub mod desc {
                 count: 1,
                 kind: VertexAttributeKind::I32,
             },
+            VertexAttribute {
+                name: "aColorSpace",
+                count: 1,
+                kind: VertexAttributeKind::I32,
+            },
             VertexAttribute {
                 name: "aGradientStopsAddress",
                 count: 1,
@@ -211,6 +241,11 @@ pub mod desc {
                 count: 1,
                 kind: VertexAttributeKind::I32,
             },
+            VertexAttribute {
+                name: "aColorSpace",
+                count: 1,
+                kind: VertexAttributeKind::I32,
+            },
             VertexAttribute {
                 name: "aGradientStopsAddress",
                 count: 1,
@@ -261,6 +296,11 @@ pub mod desc {
                 count: 1,
                 kind: VertexAttributeKind::I32,
             },
+            VertexAttribute {
+                name: "aColorSpace",
+                count: 1,
+                kind: VertexAttributeKind::I32,
+            },
             VertexAttribute {
                 name: "aGradientStopsAddress",
                 count: 1,
@@ -488,6 +528,53 @@ pub mod desc {
         ],
     };
 
+    pub const CLIP_POLYGON: VertexDescriptor = VertexDescriptor {
+        vertex_attributes: &[VertexAttribute {
+            name: "aPosition",
+            count: 2,
+            kind: VertexAttributeKind::U8Norm,
+        }],
+        instance_attributes: &[
+            // common clip attributes
+            VertexAttribute {
+                name: "aClipDeviceArea",
+                count: 4,
+                kind: VertexAttributeKind::F32,
+            },
+            VertexAttribute {
+                name: "aClipOrigins",
+                count: 4,
+                kind: VertexAttributeKind::F32,
+            },
+            VertexAttribute {
+                name: "aDevicePixelScale",
+                count: 1,
+                kind: VertexAttributeKind::F32,
+            },
+            VertexAttribute {
+                name: "aTransformIds",
+                count: 2,
+                kind: VertexAttributeKind::I32,
+            },
+            // specific clip attributes
+            VertexAttribute {
+                name: "aClipLocalRect",
+                count: 4,
+                kind: VertexAttributeKind::F32,
+            },
+            VertexAttribute {
+                name: "aClipPolygonAddress",
+                count: 2,
+                kind: VertexAttributeKind::U16,
+            },
+            VertexAttribute {
+                name: "aClipPolygonParams",
+                count: 2,
+                kind: VertexAttributeKind::I32,
+            },
+        ],
+    };
+
     pub const CLIP_IMAGE: VertexDescriptor = VertexDescriptor {
         vertex_attributes: &[VertexAttribute {
             name: "aPosition",
@@ -792,11 +879,13 @@ pub enum VertexArrayKind {
     ClipImage,
     ClipRect,
     ClipBoxShadow,
+    ClipPolygon,
     VectorStencil,
     VectorCover,
     Border,
     Scale,
     LineDecoration,
+    Path,
     FastLinearGradient,
     LinearGradient,
     RadialGradient,
@@ -1017,9 +1106,11 @@ pub struct RendererVAOs {
     blur_vao: VAO,
     clip_rect_vao: VAO,
     clip_box_shadow_vao: VAO,
+    clip_polygon_vao: VAO,
     clip_image_vao: VAO,
     border_vao: VAO,
     line_vao: VAO,
+    path_vao: VAO,
     scale_vao: VAO,
     fast_linear_gradient_vao: VAO,
     linear_gradient_vao: VAO,
@@ -1064,10 +1155,12 @@ impl RendererVAOs {
             clip_rect_vao: device.create_vao_with_new_instances(&desc::CLIP_RECT, &prim_vao),
             clip_box_shadow_vao: device
                 .create_vao_with_new_instances(&desc::CLIP_BOX_SHADOW, &prim_vao),
+            clip_polygon_vao: device.create_vao_with_new_instances(&desc::CLIP_POLYGON, &prim_vao),
             clip_image_vao: device.create_vao_with_new_instances(&desc::CLIP_IMAGE, &prim_vao),
             border_vao: device.create_vao_with_new_instances(&desc::BORDER, &prim_vao),
             scale_vao: device.create_vao_with_new_instances(&desc::SCALE, &prim_vao),
             line_vao: device.create_vao_with_new_instances(&desc::LINE, &prim_vao),
+            path_vao: device.create_vao_with_new_instances(&desc::PATH, &prim_vao),
             fast_linear_gradient_vao: device.create_vao_with_new_instances(&desc::FAST_LINEAR_GRADIENT, &prim_vao),
             linear_gradient_vao: device.create_vao_with_new_instances(&desc::LINEAR_GRADIENT, &prim_vao),
             radial_gradient_vao: device.create_vao_with_new_instances(&desc::RADIAL_GRADIENT, &prim_vao),
@@ -1086,6 +1179,7 @@ impl RendererVAOs {
         device.delete_vao(self.resolve_vao);
         device.delete_vao(self.clip_rect_vao);
         device.delete_vao(self.clip_box_shadow_vao);
+        device.delete_vao(self.clip_polygon_vao);
         device.delete_vao(self.clip_image_vao);
         device.delete_vao(self.fast_linear_gradient_vao);
         device.delete_vao(self.linear_gradient_vao);
@@ -1093,6 +1187,7 @@ impl RendererVAOs {
         device.delete_vao(self.conic_gradient_vao);
         device.delete_vao(self.blur_vao);
         device.delete_vao(self.line_vao);
+        device.delete_vao(self.path_vao);
         device.delete_vao(self.border_vao);
         device.delete_vao(self.scale_vao);
         device.delete_vao(self.svg_filter_vao);
@@ -1110,11 +1205,13 @@ impl ops::Index<VertexArrayKind> for RendererVAOs {
             VertexArrayKind::ClipImage => &self.clip_image_vao,
             VertexArrayKind::ClipRect => &self.clip_rect_vao,
             VertexArrayKind::ClipBoxShadow => &self.clip_box_shadow_vao,
+            VertexArrayKind::ClipPolygon => &self.clip_polygon_vao,
             VertexArrayKind::Blur => &self.blur_vao,
             VertexArrayKind::VectorStencil | VertexArrayKind::VectorCover => unreachable!(),
             VertexArrayKind::Border => &self.border_vao,
             VertexArrayKind::Scale => &self.scale_vao,
             VertexArrayKind::LineDecoration => &self.line_vao,
+            VertexArrayKind::Path => &self.path_vao,
             VertexArrayKind::FastLinearGradient => &self.fast_linear_gradient_vao,
             VertexArrayKind::LinearGradient => &self.linear_gradient_vao,
             VertexArrayKind::RadialGradient => &self.radial_gradient_vao,
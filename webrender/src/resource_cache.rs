@@ -9,8 +9,12 @@ use api::{DirtyRect, GlyphDimensions, IdNamespace, DEFAULT_TILE_SIZE};
 use api::{ImageData, ImageDescriptor, ImageKey, ImageRendering, TileSize};
 use api::{BlobImageHandler, BlobImageKey, VoidPtrToSizeFn};
 use api::units::*;
-use crate::{render_api::{ClearCache, AddFont, ResourceUpdate, MemoryReport}, util::WeakTable};
+use crate::{render_api::{ClearCache, AddFont, AddAnimatedImage, ResourceUpdate, MemoryReport}, util::WeakTable};
+use crate::compressed_texture::decompress_to_rgba8;
+use crate::frame_builder::ToneMapping;
+use crate::high_bit_depth;
 use crate::image_tiling::{compute_tile_size, compute_tile_range};
+use crate::image_tiling::{align_tile_size_to_blocks, compute_tile_data_offset};
 #[cfg(feature = "capture")]
 use crate::capture::ExternalCaptureImage;
 #[cfg(feature = "replay")]
@@ -43,6 +47,8 @@ use std::{cmp, mem};
 use std::fmt::Debug;
 use std::hash::Hash;
 use std::os::raw::c_void;
+#[cfg(feature = "capture")]
+use std::path::Path;
 #[cfg(any(feature = "capture", feature = "replay"))]
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -199,6 +205,9 @@ pub struct ImageTiling {
 #[derive(Default)]
 struct ImageTemplates {
     images: FastHashMap<ImageKey, ImageResource>,
+    /// The frames of the animated images. The template of an animated image
+    /// holds the data of the frame currently displayed.
+    animations: FastHashMap<ImageKey, ImageAnimation>,
 }
 
 impl ImageTemplates {
@@ -219,6 +228,104 @@ impl ImageTemplates {
     }
 }
 
+struct ImageAnimationFrame {
+    data: CachedImageData,
+    duration_ms: u64,
+    /// The texture cache entries of the frame while another frame is
+    /// displayed, so that they don't need to be uploaded again when the frame
+    /// comes back, unless the texture cache evicted them in the meantime.
+    cached: Option<ImageResult>,
+}
+
+/// The state of an animated image, which selects the frame to display
+/// from the render backend's clock, in nanoseconds from `precise_time_ns`.
+struct ImageAnimation {
+    frames: Vec<ImageAnimationFrame>,
+    loop_count: Option<u32>,
+    /// The time of the first frame built with this animation.
+    start_time: Option<u64>,
+    current_frame: usize,
+}
+
+impl ImageAnimation {
+    fn new(image: &AddAnimatedImage) -> Self {
+        ImageAnimation {
+            frames: image.frames.iter().map(|frame| ImageAnimationFrame {
+                data: frame.data.clone().into(),
+                duration_ms: frame.duration_ms as u64,
+                cached: None,
+            }).collect(),
+            loop_count: image.loop_count,
+            start_time: None,
+            current_frame: 0,
+        }
+    }
+
+    /// Returns the number of bytes of frame data held by the animation.
+    fn size_in_bytes(&self) -> usize {
+        self.frames.iter().map(|frame| match frame.data {
+            CachedImageData::Raw(ref bytes) => bytes.len(),
+            _ => 0,
+        }).sum()
+    }
+
+    /// Returns true if the animation has yet to display another frame.
+    fn is_active(&self, time: u64) -> bool {
+        let total_ms: u64 = self.frames.iter().map(|frame| frame.duration_ms).sum();
+        if self.frames.len() < 2 || total_ms == 0 {
+            return false;
+        }
+
+        match (self.start_time, self.loop_count) {
+            (Some(start_time), Some(loop_count)) => {
+                let elapsed_ms = time.saturating_sub(start_time) / 1_000_000;
+                elapsed_ms / total_ms < loop_count as u64
+            }
+            _ => true,
+        }
+    }
+
+    /// Returns the index of the frame to display at the given time.
+    fn frame_index_at(&self, time: u64) -> usize {
+        let start_time = match self.start_time {
+            Some(start_time) => start_time,
+            None => return 0,
+        };
+
+        let total_ms: u64 = self.frames.iter().map(|frame| frame.duration_ms).sum();
+        if total_ms == 0 {
+            return 0;
+        }
+
+        let elapsed_ms = time.saturating_sub(start_time) / 1_000_000;
+
+        if let Some(loop_count) = self.loop_count {
+            if elapsed_ms / total_ms >= loop_count as u64 {
+                return self.frames.len() - 1;
+            }
+        }
+
+        let mut t = elapsed_ms % total_ms;
+        for (i, frame) in self.frames.iter().enumerate() {
+            if t < frame.duration_ms {
+                return i;
+            }
+            t -= frame.duration_ms;
+        }
+
+        self.frames.len() - 1
+    }
+
+    /// Returns true if displaying the given frame requires different pixels
+    /// than the current frame.
+    fn frame_changes_data(&self, index: usize) -> bool {
+        match (&self.frames[self.current_frame].data, &self.frames[index].data) {
+            (&CachedImageData::Raw(ref a), &CachedImageData::Raw(ref b)) => !Arc::ptr_eq(a, b),
+            _ => index != self.current_frame,
+        }
+    }
+}
+
 #[cfg_attr(feature = "capture", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
 struct CachedImageInfo {
@@ -488,6 +595,16 @@ pub struct ResourceCache {
 
     /// A pool of render targets for use by the render task graph
     render_target_pool: Vec<RenderTarget>,
+
+    /// The block-compressed formats the device can sample from. Images in
+    /// other compressed formats are decoded to RGBA8 when they are added.
+    native_compressed_formats: Vec<ImageFormat>,
+
+    /// The high bit depth formats the device can sample from. Images in other
+    /// high bit depth formats are converted to RGBA8 when they are added,
+    /// using `hdr_tone_mapping`.
+    native_high_bit_depth_formats: Vec<ImageFormat>,
+    hdr_tone_mapping: Option<ToneMapping>,
 }
 
 impl ResourceCache {
@@ -525,6 +642,9 @@ impl ResourceCache {
             image_templates_memory: 0,
             font_templates_memory: 0,
             render_target_pool: Vec::new(),
+            native_compressed_formats: Vec::new(),
+            native_high_bit_depth_formats: Vec::new(),
+            hdr_tone_mapping: None,
         }
     }
 
@@ -570,6 +690,57 @@ impl ResourceCache {
         self.glyph_rasterizer.enable_multithreading(enable);
     }
 
+    pub fn set_native_compressed_formats(&mut self, formats: Vec<ImageFormat>) {
+        self.native_compressed_formats = formats;
+    }
+
+    pub fn set_native_high_bit_depth_formats(
+        &mut self,
+        formats: Vec<ImageFormat>,
+        tone_mapping: Option<ToneMapping>,
+    ) {
+        self.native_high_bit_depth_formats = formats;
+        self.hdr_tone_mapping = tone_mapping;
+    }
+
+    /// Converts block-compressed and high bit depth image data to RGBA8 if
+    /// the device can't sample from its format. External buffers are left
+    /// alone, since their data isn't available until upload.
+    fn convert_if_unsupported(
+        &self,
+        descriptor: &mut ImageDescriptor,
+        data: &mut CachedImageData,
+    ) {
+        let format = descriptor.format;
+        let supported = if format.is_compressed() {
+            self.native_compressed_formats.contains(&format)
+        } else if format.is_high_bit_depth_color() {
+            self.native_high_bit_depth_formats.contains(&format)
+        } else {
+            true
+        };
+        if supported {
+            return;
+        }
+
+        match *data {
+            CachedImageData::Raw(ref bytes) => {
+                let pixels = if format.is_compressed() {
+                    decompress_to_rgba8(descriptor, bytes)
+                } else {
+                    high_bit_depth::convert_to_rgba8(descriptor, bytes, self.hdr_tone_mapping)
+                };
+                *data = CachedImageData::Raw(Arc::new(pixels));
+                descriptor.format = ImageFormat::RGBA8;
+                descriptor.stride = None;
+                descriptor.offset = 0;
+            }
+            CachedImageData::External(..) | CachedImageData::Blob => {
+                warn!("Unable to convert {:?} image data that isn't raw", format);
+            }
+        }
+    }
+
     fn should_tile(limit: i32, descriptor: &ImageDescriptor, data: &CachedImageData) -> bool {
         let size_check = descriptor.size.width > limit || descriptor.size.height > limit;
         match *data {
@@ -645,8 +816,27 @@ impl ResourceCache {
                     profile.set(profiler::IMAGE_TEMPLATES, self.resources.image_templates.images.len());
                 }
                 ResourceUpdate::UpdateImage(img) => {
+                    // Updating an animated image replaces its frames with the
+                    // new still image.
+                    if self.remove_image_animation(img.key) {
+                        profile.set(profiler::IMAGE_TEMPLATES_MEM, bytes_to_mb(self.image_templates_memory));
+                    }
                     self.update_image_template(img.key, img.descriptor, img.data.into(), &img.dirty_rect);
                 }
+                ResourceUpdate::AddAnimatedImage(img) => {
+                    let animation = ImageAnimation::new(&img);
+                    self.image_templates_memory += animation.size_in_bytes();
+                    profile.set(profiler::IMAGE_TEMPLATES_MEM, bytes_to_mb(self.image_templates_memory));
+                    self.add_image_template(
+                        img.key,
+                        img.descriptor,
+                        img.frames[0].data.clone().into(),
+                        &img.descriptor.size.into(),
+                        None,
+                    );
+                    self.resources.image_templates.animations.insert(img.key, animation);
+                    profile.set(profiler::IMAGE_TEMPLATES, self.resources.image_templates.images.len());
+                }
                 ResourceUpdate::AddBlobImage(img) => {
                     self.add_image_template(
                         img.key.as_image(),
@@ -796,14 +986,17 @@ impl ResourceCache {
     pub fn add_image_template(
         &mut self,
         image_key: ImageKey,
-        descriptor: ImageDescriptor,
-        data: CachedImageData,
+        mut descriptor: ImageDescriptor,
+        mut data: CachedImageData,
         visible_rect: &DeviceIntRect,
         mut tiling: Option<TileSize>,
     ) {
+        self.convert_if_unsupported(&mut descriptor, &mut data);
+
         if let Some(ref mut tile_size) = tiling {
             // Sanitize the value since it can be set by a pref.
             *tile_size = (*tile_size).max(16).min(2048);
+            *tile_size = align_tile_size_to_blocks(*tile_size, descriptor.format);
         }
 
         if tiling.is_none() && Self::should_tile(self.tiling_threshold(), &descriptor, &data) {
@@ -826,10 +1019,12 @@ impl ResourceCache {
     pub fn update_image_template(
         &mut self,
         image_key: ImageKey,
-        descriptor: ImageDescriptor,
-        data: CachedImageData,
+        mut descriptor: ImageDescriptor,
+        mut data: CachedImageData,
         dirty_rect: &ImageDirtyRect,
     ) {
+        self.convert_if_unsupported(&mut descriptor, &mut data);
+
         let tiling_threshold = self.tiling_threshold();
         let image = match self.resources.image_templates.get_mut(image_key) {
             Some(res) => res,
@@ -892,6 +1087,7 @@ impl ResourceCache {
 
     pub fn delete_image_template(&mut self, image_key: ImageKey) {
         // Remove the template.
+        self.remove_image_animation(image_key);
         let value = self.resources.image_templates.remove(image_key);
 
         // Release the corresponding texture cache entry, if any.
@@ -916,6 +1112,72 @@ impl ResourceCache {
         }
     }
 
+    /// Drop the frames of an animated image, leaving its template with the
+    /// frame currently displayed. Returns false if the image isn't animated.
+    fn remove_image_animation(&mut self, image_key: ImageKey) -> bool {
+        let animation = match self.resources.image_templates.animations.remove(&image_key) {
+            Some(animation) => animation,
+            None => return false,
+        };
+
+        self.image_templates_memory -= animation.size_in_bytes();
+        for frame in animation.frames {
+            if let Some(mut cached) = frame.cached {
+                cached.drop_from_cache(&mut self.texture_cache);
+            }
+        }
+
+        true
+    }
+
+    /// Returns true if any animated image has yet to display another frame,
+    /// in which case new frames are needed to show it.
+    pub fn has_active_image_animations(&self, time: u64) -> bool {
+        self.resources.image_templates.animations.values().any(|animation| {
+            animation.is_active(time)
+        })
+    }
+
+    /// Select the frame of each animated image at the given time. Returns
+    /// true if any image changed frame, in which case a new frame needs to
+    /// be built.
+    pub fn update_image_animations(&mut self, time: u64) -> bool {
+        let mut did_change = false;
+        let templates = &mut self.resources.image_templates;
+
+        for (key, animation) in templates.animations.iter_mut() {
+            if animation.start_time.is_none() {
+                animation.start_time = Some(time);
+                did_change = true;
+            }
+
+            let index = animation.frame_index_at(time);
+            if !animation.frame_changes_data(index) {
+                animation.current_frame = index;
+                continue;
+            }
+
+            // Set the texture cache entries of the displayed frame aside, and
+            // bring back those of the new frame, if it was displayed before.
+            // Entries that were evicted are uploaded again when requested.
+            let current = animation.current_frame;
+            animation.frames[current].cached = self.cached_images.remove(key);
+            if let Some(cached) = animation.frames[index].cached.take() {
+                self.cached_images.insert(*key, cached);
+            }
+            animation.current_frame = index;
+
+            // Bumping the generation invalidates the picture cache tiles that
+            // display the image.
+            let template = templates.images.get_mut(key).unwrap();
+            template.data = animation.frames[index].data.clone();
+            template.generation = ImageGeneration(template.generation.0 + 1);
+            did_change = true;
+        }
+
+        did_change
+    }
+
     /// Return the current generation of an image template
     pub fn get_image_generation(&self, key: ImageKey) -> ImageGeneration {
         self.resources
@@ -1335,12 +1597,14 @@ impl ResourceCache {
                         // we don't expect to have partial tiles at the top and left of non-blob
                         // images.
                         debug_assert_eq!(image_template.visible_rect.min, point2(0, 0));
-                        let bpp = descriptor.format.bytes_per_pixel();
                         let stride = descriptor.compute_stride();
                         descriptor.stride = Some(stride);
-                        descriptor.offset +=
-                            tile.y as i32 * tile_size as i32 * stride +
-                            tile.x as i32 * tile_size as i32 * bpp;
+                        descriptor.offset += compute_tile_data_offset(
+                            descriptor.format,
+                            stride,
+                            tile_size,
+                            tile,
+                        );
                     }
 
                     descriptor.size = clipped_tile_size;
@@ -1357,7 +1621,7 @@ impl ResourceCache {
                     ImageRendering::Pixelated => {
                         TextureFilter::Nearest
                     }
-                    ImageRendering::Auto | ImageRendering::CrispEdges => {
+                    ImageRendering::Auto | ImageRendering::CrispEdges | ImageRendering::HighQuality => {
                         // If the texture uses linear filtering, enable mipmaps and
                         // trilinear filtering, for better image quality. We only
                         // support this for now on textures that are not placed
@@ -1365,7 +1629,12 @@ impl ResourceCache {
                         // that is > 512 in either dimension, so it should cover
                         // the most important use cases. We may want to support
                         // mip-maps on shared cache items in the future.
+                        // Mipmaps can't be generated for compressed textures, nor
+                        // for half float textures on GLES without
+                        // EXT_color_buffer_float.
                         if descriptor.allow_mipmaps() &&
+                           !descriptor.format.is_compressed() &&
+                           descriptor.format != ImageFormat::RGBA16F &&
                            descriptor.size.width > 512 &&
                            descriptor.size.height > 512 &&
                            !self.texture_cache.is_allowed_in_shared_cache(
@@ -2167,6 +2436,32 @@ impl ResourceCache {
         external_images
     }
 
+    /// Saves the image templates next to a YAML capture of the scene, in the
+    /// format and with the names the YAML refers to. Blob and external images
+    /// don't have their data here, so they are saved as placeholders.
+    #[cfg(feature = "capture")]
+    pub fn save_yaml_images(&self, path: &Path) {
+        info!("saving YAML images");
+        for (&key, template) in self.resources.image_templates.images.iter() {
+            let data = match template.data {
+                CachedImageData::Raw(ref arc) => Some(&arc[..]),
+                CachedImageData::Blob |
+                CachedImageData::External(..) => None,
+            };
+            #[cfg(feature = "png")]
+            crate::yaml_frame_writer::save_image(
+                &path.join(crate::yaml_frame_writer::image_file_name(key)),
+                &template.descriptor,
+                data,
+            );
+            #[cfg(not(feature = "png"))]
+            {
+                let _ = (data, path);
+                warn!("Not saving image {:?}, the png feature is disabled", key);
+            }
+        }
+    }
+
     #[cfg(feature = "capture")]
     pub fn save_capture_sequence(&mut self, config: &mut CaptureConfig) -> Vec<ExternalCaptureImage> {
         if self.capture_dirty {
@@ -2180,3 +2475,39 @@ impl ResourceCache {
         }
     }
 }
+
+#[test]
+fn animated_image_frame_selection() {
+    let frame = |byte: u8, duration_ms| ImageAnimationFrame {
+        data: CachedImageData::Raw(Arc::new(vec![byte; 4])),
+        duration_ms,
+        cached: None,
+    };
+    let start_time = 1_000_000_000;
+    let at = |ms: u64| start_time + ms * 1_000_000;
+
+    let mut animation = ImageAnimation {
+        frames: vec![frame(0, 100), frame(1, 50), frame(2, 100)],
+        loop_count: None,
+        start_time: Some(start_time),
+        current_frame: 0,
+    };
+
+    assert_eq!(animation.frame_index_at(at(0)), 0);
+    assert_eq!(animation.frame_index_at(at(99)), 0);
+    assert_eq!(animation.frame_index_at(at(100)), 1);
+    assert_eq!(animation.frame_index_at(at(175)), 2);
+    assert_eq!(animation.frame_index_at(at(250)), 0);
+    assert!(!animation.frame_changes_data(0));
+    assert!(animation.frame_changes_data(1));
+    assert!(animation.is_active(at(1000)));
+    assert_eq!(animation.size_in_bytes(), 12);
+
+    // Once all loops are played the last frame stays displayed.
+    animation.loop_count = Some(2);
+    assert_eq!(animation.frame_index_at(at(300)), 0);
+    assert_eq!(animation.frame_index_at(at(500)), 2);
+    assert_eq!(animation.frame_index_at(at(1000)), 2);
+    assert!(animation.is_active(at(499)));
+    assert!(!animation.is_active(at(500)));
+}
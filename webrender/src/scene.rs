@@ -2,8 +2,9 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use api::{BuiltDisplayList, DisplayListWithCache, ColorF, DynamicProperties, Epoch, FontRenderMode};
+use api::{BuiltDisplayList, DisplayListWithCache, ColorF, ComplexClipRegion, DynamicProperties, Epoch};
 use api::{PipelineId, PropertyBinding, PropertyBindingId, PropertyValue, MixBlendMode, StackingContext};
+use api::{FontRenderMode, RgbColorSpace};
 use api::units::*;
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use crate::render_api::MemoryReport;
@@ -28,8 +29,12 @@ pub struct SceneProperties {
     transform_properties: FastHashMap<PropertyBindingId, LayoutTransform>,
     float_properties: FastHashMap<PropertyBindingId, f32>,
     color_properties: FastHashMap<PropertyBindingId, ColorF>,
+    clip_region_properties: FastHashMap<PropertyBindingId, ComplexClipRegion>,
     current_properties: DynamicProperties,
     pending_properties: Option<DynamicProperties>,
+    /// Values sampled from the document's keyframe animations, which take
+    /// precedence over the properties supplied by the embedder.
+    animated_properties: DynamicProperties,
 }
 
 impl SceneProperties {
@@ -38,8 +43,10 @@ impl SceneProperties {
             transform_properties: FastHashMap::default(),
             float_properties: FastHashMap::default(),
             color_properties: FastHashMap::default(),
+            clip_region_properties: FastHashMap::default(),
             current_properties: DynamicProperties::default(),
             pending_properties: None,
+            animated_properties: DynamicProperties::default(),
         }
     }
 
@@ -83,26 +90,8 @@ impl SceneProperties {
 
         if let Some(ref pending_properties) = self.pending_properties {
             if *pending_properties != self.current_properties {
-                self.transform_properties.clear();
-                self.float_properties.clear();
-                self.color_properties.clear();
-
-                for property in &pending_properties.transforms {
-                    self.transform_properties
-                        .insert(property.key.id, property.value);
-                }
-
-                for property in &pending_properties.floats {
-                    self.float_properties
-                        .insert(property.key.id, property.value);
-                }
-
-                for property in &pending_properties.colors {
-                    self.color_properties
-                        .insert(property.key.id, property.value);
-                }
-
                 self.current_properties = pending_properties.clone();
+                self.update_property_maps();
                 properties_changed = true;
             }
         }
@@ -110,6 +99,48 @@ impl SceneProperties {
         properties_changed
     }
 
+    /// Replace the values produced by keyframe animations. Returns true if
+    /// they changed.
+    pub fn set_animated_properties(&mut self, properties: DynamicProperties) -> bool {
+        if properties == self.animated_properties {
+            return false;
+        }
+
+        self.animated_properties = properties;
+        self.update_property_maps();
+
+        true
+    }
+
+    fn update_property_maps(&mut self) {
+        self.transform_properties.clear();
+        self.float_properties.clear();
+        self.color_properties.clear();
+        self.clip_region_properties.clear();
+
+        for properties in &[&self.current_properties, &self.animated_properties] {
+            for property in &properties.transforms {
+                self.transform_properties
+                    .insert(property.key.id, property.value);
+            }
+
+            for property in &properties.floats {
+                self.float_properties
+                    .insert(property.key.id, property.value);
+            }
+
+            for property in &properties.colors {
+                self.color_properties
+                    .insert(property.key.id, property.value);
+            }
+
+            for property in &properties.clip_regions {
+                self.clip_region_properties
+                    .insert(property.key.id, property.value);
+            }
+        }
+    }
+
     /// Get the current value for a transform property.
     pub fn resolve_layout_transform(
         &self,
@@ -166,6 +197,10 @@ impl SceneProperties {
         &self.color_properties
     }
 
+    pub fn clip_region_properties(&self) -> &FastHashMap<PropertyBindingId, ComplexClipRegion> {
+        &self.clip_region_properties
+    }
+
 }
 
 /// A representation of the layout within the display port for a given document or iframe.
@@ -282,6 +317,7 @@ pub struct BuiltScene {
     pub pipeline_epochs: FastHashMap<PipelineId, Epoch>,
     pub output_rect: DeviceIntRect,
     pub background_color: Option<ColorF>,
+    pub output_color_space: RgbColorSpace,
     pub prim_store: PrimitiveStore,
     pub clip_store: ClipStore,
     pub config: FrameBuilderConfig,
@@ -301,6 +337,7 @@ impl BuiltScene {
             pipeline_epochs: FastHashMap::default(),
             output_rect: DeviceIntRect::zero(),
             background_color: None,
+            output_color_space: RgbColorSpace::Srgb,
             prim_store: PrimitiveStore::new(&PrimitiveStoreStats::empty()),
             clip_store: ClipStore::new(&ClipStoreStats::empty()),
             hit_testing_scene: Arc::new(HitTestingScene::new(&HitTestingSceneStats::empty())),
@@ -331,6 +368,7 @@ impl BuiltScene {
                 force_invalidation: false,
                 is_software: false,
                 low_quality_pinch_zoom: false,
+                hdr_tone_mapping: None,
             },
         }
     }
@@ -4,18 +4,20 @@
 
 use api::{AsyncBlobImageRasterizer, BlobImageResult, Parameter};
 use api::{DocumentId, PipelineId, ExternalEvent, BlobImageRequest};
-use api::{NotificationRequest, Checkpoint, IdNamespace, QualitySettings};
+use api::{NotificationRequest, Checkpoint, IdNamespace, QualitySettings, RgbColorSpace};
 use api::{PrimitiveKeyKind, GlyphDimensionRequest, GlyphIndexRequest};
 use api::channel::{unbounded_channel, single_msg_channel, Receiver, Sender};
 use api::units::*;
 use crate::render_api::{ApiMsg, FrameMsg, SceneMsg, ResourceUpdate, TransactionMsg, MemoryReport};
 #[cfg(feature = "capture")]
 use crate::capture::CaptureConfig;
+#[cfg(feature = "capture")]
+use crate::yaml_frame_writer::YamlFrameWriter;
 use crate::frame_builder::FrameBuilderConfig;
 use crate::scene_building::SceneBuilder;
 use crate::clip::{ClipIntern, PolygonIntern};
 use crate::filterdata::FilterDataIntern;
-use crate::glyph_rasterizer::SharedFontResources;
+use crate::glyph_rasterizer::{FontContexts, SharedFontResources};
 use crate::intern::{Internable, Interner, UpdateList};
 use crate::internal_types::{FastHashMap, FastHashSet};
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
@@ -24,6 +26,7 @@ use crate::prim_store::borders::{ImageBorder, NormalBorderPrim};
 use crate::prim_store::gradient::{LinearGradient, RadialGradient, ConicGradient};
 use crate::prim_store::image::{Image, YuvImage};
 use crate::prim_store::line_dec::LineDecoration;
+use crate::prim_store::path::Path;
 use crate::prim_store::picture::Picture;
 use crate::prim_store::text_run::TextRun;
 use crate::profiler::{self, TransactionProfile};
@@ -32,6 +35,7 @@ use crate::renderer::{FullFrameStats, PipelineInfo, SceneBuilderHooks};
 use crate::scene::{Scene, BuiltScene, SceneStats};
 use crate::spatial_tree::{SceneSpatialTree, SpatialTreeUpdates};
 use std::iter;
+use std::sync::Arc;
 use time::precise_time_ns;
 use crate::util::drain_filter;
 use std::thread;
@@ -221,6 +225,7 @@ impl Document {
             view: SceneView {
                 device_rect,
                 quality_settings: QualitySettings::default(),
+                output_color_space: RgbColorSpace::Srgb,
             },
         }
     }
@@ -232,6 +237,7 @@ pub struct SceneBuilderThread {
     tx: Sender<ApiMsg>,
     config: FrameBuilderConfig,
     fonts: SharedFontResources,
+    font_contexts: Arc<FontContexts>,
     size_of_ops: Option<MallocSizeOfOps>,
     hooks: Option<Box<dyn SceneBuilderHooks + Send>>,
     simulate_slow_ms: u32,
@@ -264,6 +270,7 @@ impl SceneBuilderThread {
     pub fn new(
         config: FrameBuilderConfig,
         fonts: SharedFontResources,
+        font_contexts: Arc<FontContexts>,
         size_of_ops: Option<MallocSizeOfOps>,
         hooks: Option<Box<dyn SceneBuilderHooks + Send>>,
         channels: SceneBuilderThreadChannels,
@@ -276,6 +283,7 @@ impl SceneBuilderThread {
             tx,
             config,
             fonts,
+            font_contexts,
             size_of_ops,
             hooks,
             simulate_slow_ms: 0,
@@ -411,6 +419,16 @@ impl SceneBuilderThread {
                 let file_name = format!("scene-{}-{}", id.namespace_id.0, id.id);
                 config.serialize_for_scene(&doc.scene, file_name);
             }
+
+            if config.bits.contains(CaptureBits::YAML) {
+                let yaml_root = config.scene_root().join("yaml");
+                if let Err(e) = std::fs::create_dir_all(&yaml_root) {
+                    warn!("Unable to create {}: {:?}", yaml_root.display(), e);
+                    continue;
+                }
+                let file_name = format!("scene-{}-{}", id.namespace_id.0, id.id);
+                YamlFrameWriter::new(&self.fonts, yaml_root).write_scene(&doc.scene, &file_name);
+            }
         }
     }
 
@@ -427,6 +445,7 @@ impl SceneBuilderThread {
                 built_scene = Some(SceneBuilder::build(
                     &item.scene,
                     item.fonts,
+                    &self.font_contexts,
                     &item.view,
                     &self.config,
                     &mut item.interners,
@@ -533,6 +552,14 @@ impl SceneBuilderThread {
                 SceneMsg::SetDocumentView { device_rect } => {
                     doc.view.device_rect = device_rect;
                 }
+                SceneMsg::SetOutputColorSpace { output_color_space } => {
+                    // Solid colors are converted to the output color space
+                    // during scene building.
+                    if doc.view.output_color_space != output_color_space {
+                        doc.view.output_color_space = output_color_space;
+                        rebuild_scene = true;
+                    }
+                }
                 SceneMsg::SetDisplayList {
                     epoch,
                     pipeline_id,
@@ -595,6 +622,7 @@ impl SceneBuilderThread {
             let built = SceneBuilder::build(
                 &scene,
                 self.fonts.clone(),
+                &self.font_contexts,
                 &doc.view,
                 &self.config,
                 &mut doc.interners,
@@ -37,22 +37,25 @@
 
 use api::{AlphaType, BorderDetails, BorderDisplayItem, BuiltDisplayListIter, BuiltDisplayList, PrimitiveFlags};
 use api::{ClipId, ColorF, CommonItemProperties, ComplexClipRegion, ComponentTransferFuncType, RasterSpace};
-use api::{DisplayItem, DisplayItemRef, ExtendMode, ExternalScrollId, FilterData};
-use api::{FilterOp, FilterPrimitive, FontInstanceKey, FontSize, GlyphInstance, GlyphOptions, GradientStop};
+use api::{DisplayItem, DisplayItemRef, ExtendMode, ExternalScrollId, FilterData, GradientInterpolation};
+use api::{FilterOp, FilterPrimitive, FilterPrimitiveKind, FontInstanceKey, FontSize, GlyphInstance, GlyphOptions, GradientStop};
 use api::{IframeDisplayItem, ImageKey, ImageRendering, ItemRange, ColorDepth, QualitySettings};
 use api::{LineOrientation, LineStyle, NinePatchBorderSource, PipelineId, MixBlendMode, StackingContextFlags};
-use api::{PropertyBinding, ReferenceFrameKind, ScrollFrameDescriptor, ReferenceFrameMapper};
+use api::{HitTestShape, PathDisplayItem, PathSegment, RgbColorSpace, TextDecoration, TextDecorationLines};
+use api::{PropertyBinding, PropertyBindingId, ReferenceFrameKind, ScrollFrameDescriptor, ReferenceFrameMapper, ScrollSnapPoint};
 use api::{APZScrollGeneration, HasScrollLinkedEffect, Shadow, SpaceAndClipInfo, SpatialId, StickyFrameDescriptor, ImageMask, ItemTag};
 use api::{ClipMode, PrimitiveKeyKind, TransformStyle, YuvColorSpace, ColorRange, YuvData, TempFilterData};
 use api::{ReferenceTransformBinding, Rotation, FillRule, SpatialTreeItem, ReferenceFrameDescriptor};
+use api::POLYGON_CLIP_VERTEX_MAX;
 use api::units::*;
-use crate::image_tiling::simplify_repeated_primitive;
+use crate::image_tiling::{apply_repeat_modes, simplify_repeated_primitive};
 use crate::clip::{ClipChainId, ClipItemKey, ClipStore, ClipItemKeyKind};
 use crate::clip::{ClipInternData, ClipNodeKind, ClipInstance, SceneClipInstance};
-use crate::clip::{PolygonDataHandle};
+use crate::color_space::convert_color;
+use crate::clip::{PolygonDataHandle, MAX_POLYGON_CLIP_VERTICES};
 use crate::spatial_tree::{SceneSpatialTree, SpatialNodeIndex, get_external_scroll_offset};
 use crate::frame_builder::{ChasePrimitive, FrameBuilderConfig};
-use crate::glyph_rasterizer::{FontInstance, SharedFontResources};
+use crate::glyph_rasterizer::{FontContexts, FontInstance, SharedFontResources};
 use crate::hit_test::HitTestingScene;
 use crate::intern::Interner;
 use crate::internal_types::{FastHashMap, LayoutPrimitiveInfo, Filter, PlaneSplitter, PlaneSplitterIndex, PipelineInstanceId};
@@ -71,12 +74,14 @@ use crate::prim_store::gradient::{
 };
 use crate::prim_store::image::{Image, YuvImage};
 use crate::prim_store::line_dec::{LineDecoration, LineDecorationCacheKey, get_line_decoration_size};
+use crate::prim_store::path::{Path, PathCacheKey, PathSegmentKey, StrokeKey};
 use crate::prim_store::picture::{Picture, PictureCompositeKey, PictureKey};
 use crate::prim_store::text_run::TextRun;
 use crate::render_backend::SceneView;
 use crate::resource_cache::ImageRequest;
 use crate::scene::{Scene, ScenePipeline, BuiltScene, SceneStats, StackingContextHelpers};
 use crate::scene_builder_thread::Interners;
+use crate::scroll_physics::ScrollPhysicsParams;
 use crate::space::SpaceSnapper;
 use crate::spatial_node::{StickyFrameInfo, ScrollFrameKind, SpatialNodeUid};
 use crate::tile_cache::TileCacheBuilder;
@@ -421,6 +426,10 @@ pub struct SceneBuilder<'a> {
     /// The map of all font instances.
     fonts: SharedFontResources,
 
+    /// The font contexts of the glyph rasterizer, to find the ink of glyphs
+    /// for text decorations that skip it.
+    font_contexts: &'a FontContexts,
+
     /// The data structure that converts between ClipId/SpatialId and the various
     /// index types that the SpatialTree uses.
     id_to_index_mapper_stack: Vec<NodeIdToIndexMapper>,
@@ -472,6 +481,9 @@ pub struct SceneBuilder<'a> {
     /// The current quality / performance settings for this scene.
     quality_settings: QualitySettings,
 
+    /// The color space that the document is composited in.
+    output_color_space: RgbColorSpace,
+
     /// Maintains state about the list of tile caches being built for this scene.
     tile_cache_builder: TileCacheBuilder,
 
@@ -514,6 +526,7 @@ impl<'a> SceneBuilder<'a> {
     pub fn build(
         scene: &Scene,
         fonts: SharedFontResources,
+        font_contexts: &FontContexts,
         view: &SceneView,
         frame_builder_config: &FrameBuilderConfig,
         interners: &mut Interners,
@@ -528,7 +541,8 @@ impl<'a> SceneBuilder<'a> {
 
         let background_color = root_pipeline
             .background_color
-            .and_then(|color| if color.a > 0.0 { Some(color) } else { None });
+            .and_then(|color| if color.a > 0.0 { Some(color) } else { None })
+            .map(|color| convert_color(color, RgbColorSpace::Srgb, view.output_color_space));
 
         let root_reference_frame_index = spatial_tree.root_reference_frame_index();
 
@@ -542,6 +556,7 @@ impl<'a> SceneBuilder<'a> {
             scene,
             spatial_tree,
             fonts,
+            font_contexts,
             config: *frame_builder_config,
             id_to_index_mapper_stack: Vec::new(),
             hit_testing_scene: HitTestingScene::new(&stats.hit_test_stats),
@@ -557,6 +572,7 @@ impl<'a> SceneBuilder<'a> {
             iframe_size: Vec::new(),
             root_iframe_clip: None,
             quality_settings: view.quality_settings,
+            output_color_space: view.output_color_space,
             tile_cache_builder: TileCacheBuilder::new(root_reference_frame_index),
             snap_to_device,
             picture_graph: PictureGraph::new(),
@@ -586,6 +602,7 @@ impl<'a> SceneBuilder<'a> {
             pipeline_epochs: scene.pipeline_epochs.clone(),
             output_rect: view.device_rect.size().into(),
             background_color,
+            output_color_space: view.output_color_space,
             hit_testing_scene: Arc::new(builder.hit_testing_scene),
             prim_store: builder.prim_store,
             clip_store: builder.clip_store,
@@ -627,12 +644,19 @@ impl<'a> SceneBuilder<'a> {
         pipeline_id: PipelineId,
         instance_id: PipelineInstanceId,
     ) {
+        // Snap points precede the scroll frame they belong to.
+        let mut snap_points = Vec::new();
+
         dl.iter_spatial_tree(|item| {
             match item {
+                SpatialTreeItem::ScrollSnapPoint(point) => {
+                    snap_points.push(*point);
+                }
                 SpatialTreeItem::ScrollFrame(descriptor) => {
                     let parent_space = self.get_space(descriptor.parent_space);
                     self.build_scroll_frame(
                         descriptor,
+                        mem::take(&mut snap_points),
                         parent_space,
                         pipeline_id,
                         instance_id,
@@ -722,9 +746,12 @@ impl<'a> SceneBuilder<'a> {
                         }
 
                         let composition_operations = CompositeOps::new(
-                            filter_ops_for_compositing(item.filters()),
+                            filter_ops_for_compositing(item.filters(), self.output_color_space),
                             filter_datas_for_compositing(item.filter_datas()),
-                            filter_primitives_for_compositing(item.filter_primitives()),
+                            filter_primitives_for_compositing(
+                                item.filter_primitives(),
+                                self.output_color_space,
+                            ),
                             info.stacking_context.mix_blend_mode_for_compositing(),
                         );
 
@@ -938,6 +965,7 @@ impl<'a> SceneBuilder<'a> {
     fn build_scroll_frame(
         &mut self,
         info: &ScrollFrameDescriptor,
+        snap_points: Vec<ScrollSnapPoint>,
         parent_node_index: SpatialNodeIndex,
         pipeline_id: PipelineId,
         instance_id: PipelineInstanceId,
@@ -958,6 +986,11 @@ impl<'a> SceneBuilder<'a> {
             info.external_scroll_offset,
             info.scroll_offset_generation,
             info.has_scroll_linked_effect,
+            ScrollPhysicsParams {
+                snap: info.physics.snap,
+                overscroll: info.physics.overscroll,
+                snap_points,
+            },
             SpatialNodeUid::external(info.key, pipeline_id, instance_id),
         );
     }
@@ -1042,6 +1075,7 @@ impl<'a> SceneBuilder<'a> {
             LayoutVector2D::zero(),
             APZScrollGeneration::default(),
             HasScrollLinkedEffect::No,
+            ScrollPhysicsParams::default(),
             SpatialNodeUid::root_scroll_frame(iframe_pipeline_id, instance_id),
         );
 
@@ -1172,6 +1206,7 @@ impl<'a> SceneBuilder<'a> {
                     &layout,
                     layout.rect.size(),
                     LayoutSize::zero(),
+                    info.source_rect,
                     info.image_key,
                     info.image_rendering,
                     info.alpha_type,
@@ -1181,23 +1216,35 @@ impl<'a> SceneBuilder<'a> {
             DisplayItem::RepeatingImage(ref info) => {
                 profile_scope!("repeating_image");
 
-                let (layout, unsnapped_rect, spatial_node_index, clip_chain_id) = self.process_common_properties_with_bounds(
+                let (mut layout, unsnapped_rect, spatial_node_index, clip_chain_id) = self.process_common_properties_with_bounds(
                     &info.common,
                     &info.bounds,
                 );
 
-                let stretch_size = process_repeat_size(
+                let mut stretch_size = process_repeat_size(
                     &layout.rect,
                     &unsnapped_rect,
                     info.stretch_size,
                 );
+                let mut tile_spacing = info.tile_spacing;
+
+                if !apply_repeat_modes(
+                    info.repeat_horizontal,
+                    info.repeat_vertical,
+                    &mut stretch_size,
+                    &mut tile_spacing,
+                    &mut layout.rect,
+                ) {
+                    return;
+                }
 
                 self.add_image(
                     spatial_node_index,
                     clip_chain_id,
                     &layout,
                     stretch_size,
-                    info.tile_spacing,
+                    tile_spacing,
+                    info.source_rect,
                     info.image_key,
                     info.image_rendering,
                     info.alpha_type,
@@ -1246,6 +1293,18 @@ impl<'a> SceneBuilder<'a> {
                     item.glyphs(),
                     info.glyph_options,
                 );
+
+                if let Some(ref decoration) = info.decoration {
+                    self.add_text_decorations(
+                        &info.common,
+                        &info.bounds,
+                        &info.font_key,
+                        &info.color,
+                        item.glyphs(),
+                        info.glyph_options,
+                        decoration,
+                    );
+                }
             }
             DisplayItem::Rectangle(ref info) => {
                 profile_scope!("rect");
@@ -1255,13 +1314,23 @@ impl<'a> SceneBuilder<'a> {
                     &info.bounds,
                 );
 
+                // Animated colors are converted to the output color space
+                // when their binding is resolved.
+                let color = match info.color {
+                    PropertyBinding::Value(color) => PropertyBinding::Value(
+                        convert_color(color, info.color_space, self.output_color_space),
+                    ),
+                    binding => binding,
+                };
+
                 self.add_primitive(
                     spatial_node_index,
                     clip_chain_id,
                     &layout,
                     Vec::new(),
                     PrimitiveKeyKind::Rectangle {
-                        color: info.color.into(),
+                        color: color.into(),
+                        color_space: info.color_space,
                     },
                 );
             }
@@ -1278,8 +1347,18 @@ impl<'a> SceneBuilder<'a> {
                 // Don't add transparent rectangles to the draw list,
                 // but do consider them for hit testing. This allows
                 // specifying invisible hit testing areas.
+                let current_offset = self.current_offset(spatial_node_index);
+                let points: Vec<LayoutPoint> = item
+                    .points()
+                    .iter()
+                    .map(|point| point + current_offset)
+                    .collect();
+
                 self.add_primitive_to_hit_testing_list(
                     &layout,
+                    &info.shape,
+                    &points,
+                    info.pass_through,
                     spatial_node_index,
                     info.common.clip_id,
                     info.tag,
@@ -1317,6 +1396,26 @@ impl<'a> SceneBuilder<'a> {
                     info.style,
                 );
             }
+            DisplayItem::Path(ref info) => {
+                profile_scope!("path");
+
+                if info.bounds.is_empty() {
+                    return;
+                }
+
+                let (layout, _, spatial_node_index, clip_chain_id) = self.process_common_properties_with_bounds(
+                    &info.common,
+                    &info.bounds,
+                );
+
+                self.add_path(
+                    spatial_node_index,
+                    clip_chain_id,
+                    &layout,
+                    info,
+                    item.path_segments(),
+                );
+            }
             DisplayItem::Gradient(ref info) => {
                 profile_scope!("gradient");
 
@@ -1357,6 +1456,7 @@ impl<'a> SceneBuilder<'a> {
                             end,
                             stops.to_vec(),
                             ExtendMode::Clamp,
+                            info.gradient.interpolation,
                             rect.size(),
                             LayoutSize::zero(),
                             None,
@@ -1379,6 +1479,7 @@ impl<'a> SceneBuilder<'a> {
                         end,
                         stops,
                         info.gradient.extend_mode,
+                        info.gradient.interpolation,
                         tile_size,
                         info.tile_spacing,
                         None,
@@ -1436,7 +1537,12 @@ impl<'a> SceneBuilder<'a> {
                                 .. layout
                             },
                             Vec::new(),
-                            PrimitiveKeyKind::Rectangle { color: PropertyBinding::Value(color) },
+                            PrimitiveKeyKind::Rectangle {
+                                color: PropertyBinding::Value(
+                                    self.output_color(color.into()).into(),
+                                ),
+                                color_space: RgbColorSpace::Srgb,
+                            },
                         );
                     }
                 );
@@ -1457,6 +1563,7 @@ impl<'a> SceneBuilder<'a> {
                         info.gradient.radius.width / info.gradient.radius.height,
                         stops,
                         info.gradient.extend_mode,
+                        info.gradient.interpolation,
                         tile_size,
                         tile_spacing,
                         None,
@@ -1506,6 +1613,7 @@ impl<'a> SceneBuilder<'a> {
                         info.gradient.end_offset,
                         item.gradient_stops(),
                         info.gradient.extend_mode,
+                        info.gradient.interpolation,
                         tile_size,
                         info.tile_spacing,
                         None,
@@ -1567,6 +1675,16 @@ impl<'a> SceneBuilder<'a> {
                     item.points(),
                 );
             }
+            DisplayItem::PolygonClip(ref info) => {
+                profile_scope!("polygon_clip");
+
+                self.add_polygon_clip_node(
+                    info.id,
+                    &info.parent_space_and_clip,
+                    info.fill_rule,
+                    item.points(),
+                );
+            }
             DisplayItem::RoundedRectClip(ref info) => {
                 profile_scope!("rounded_clip");
 
@@ -1574,6 +1692,7 @@ impl<'a> SceneBuilder<'a> {
                     info.id,
                     &info.parent_space_and_clip,
                     &info.clip,
+                    info.binding.map(|key| (key.id, info.binding_offset)),
                 );
             }
             DisplayItem::RectClip(ref info) => {
@@ -1611,9 +1730,12 @@ impl<'a> SceneBuilder<'a> {
                     None,
                 );
 
-                let _filters = filter_ops_for_compositing(item.filters());
+                let _filters = filter_ops_for_compositing(item.filters(), self.output_color_space);
                 let _filter_datas = filter_datas_for_compositing(item.filter_datas());
-                let _filter_primitives = filter_primitives_for_compositing(item.filter_primitives());
+                let _filter_primitives = filter_primitives_for_compositing(
+                    item.filter_primitives(),
+                    self.output_color_space,
+                );
 
                 /*
                 self.add_backdrop_filter(
@@ -1748,6 +1870,9 @@ impl<'a> SceneBuilder<'a> {
     pub fn add_primitive_to_hit_testing_list(
         &mut self,
         info: &LayoutPrimitiveInfo,
+        shape: &HitTestShape,
+        points: &[LayoutPoint],
+        pass_through: bool,
         spatial_node_index: SpatialNodeIndex,
         clip_id: ClipId,
         tag: ItemTag,
@@ -1755,6 +1880,9 @@ impl<'a> SceneBuilder<'a> {
         self.hit_testing_scene.add_item(
             tag,
             info,
+            shape,
+            points,
+            pass_through,
             spatial_node_index,
             clip_id,
             &self.clip_store,
@@ -2549,6 +2677,7 @@ impl<'a> SceneBuilder<'a> {
             LayoutVector2D::zero(),
             APZScrollGeneration::default(),
             HasScrollLinkedEffect::No,
+            ScrollPhysicsParams::default(),
             SpatialNodeUid::root_scroll_frame(pipeline_id, instance),
         );
     }
@@ -2567,7 +2696,10 @@ impl<'a> SceneBuilder<'a> {
             &image_mask.rect,
             spatial_node_index,
         );
-        let points: Vec<LayoutPoint> = points_range.iter().collect();
+        let points: Vec<LayoutPoint> = points_range
+            .iter()
+            .take(POLYGON_CLIP_VERTEX_MAX)
+            .collect();
 
         // If any points are provided, then intern a polygon with the points and fill rule.
         let mut polygon_handle: Option<PolygonDataHandle> = None;
@@ -2577,7 +2709,7 @@ impl<'a> SceneBuilder<'a> {
             let handle = self
                 .interners
                 .polygon
-                .intern(&item, || item);
+                .intern(&item, || item.clone());
             polygon_handle = Some(handle);
         }
 
@@ -2608,6 +2740,72 @@ impl<'a> SceneBuilder<'a> {
         );
     }
 
+    /// Add a new analytic polygon clip, positioned by the spatial node in the `space_and_clip`.
+    fn add_polygon_clip_node(
+        &mut self,
+        new_node_id: ClipId,
+        space_and_clip: &SpaceAndClipInfo,
+        fill_rule: FillRule,
+        points_range: ItemRange<LayoutPoint>,
+    ) {
+        let spatial_node_index = self.get_space(space_and_clip.spatial_id);
+
+        let mut points: Vec<LayoutPoint> = points_range.iter().collect();
+        if points.len() > MAX_POLYGON_CLIP_VERTICES {
+            warn!("Polygon clip with {} vertices exceeds the maximum of {}, truncating",
+                points.len(), MAX_POLYGON_CLIP_VERTICES);
+            points.truncate(MAX_POLYGON_CLIP_VERTICES);
+        }
+
+        // A polygon needs at least three vertices to enclose any area, so
+        // anything less clips out everything it is applied to.
+        let kind = if points.len() >= 3 {
+            let bounds = LayoutRect::from_points(&points);
+
+            // The polygon key stores points relative to the origin of the bounds,
+            // matching the layout used by polygons attached to image masks.
+            let relative_points: Vec<LayoutPoint> = points
+                .iter()
+                .map(|point| *point - bounds.min.to_vector())
+                .collect();
+            let polygon = PolygonKey::new(&relative_points, fill_rule);
+            let polygon_handle = self
+                .interners
+                .polygon
+                .intern(&polygon, || polygon.clone());
+
+            ClipItemKeyKind::polygon(bounds, polygon_handle)
+        } else {
+            ClipItemKeyKind::rectangle(LayoutRect::zero(), ClipMode::Clip)
+        };
+
+        let item = ClipItemKey {
+            kind,
+            spatial_node_index,
+        };
+
+        let handle = self
+            .interners
+            .clip
+            .intern(&item, || {
+                ClipInternData {
+                    clip_node_kind: item.kind.node_kind(),
+                    spatial_node_index,
+                }
+            });
+
+        let instance = SceneClipInstance {
+            key: item,
+            clip: ClipInstance::new(handle),
+        };
+
+        self.clip_store.register_clip_template(
+            new_node_id,
+            space_and_clip.clip_id,
+            &[instance],
+        );
+    }
+
     /// Add a new rectangle clip, positioned by the spatial node in the `space_and_clip`.
     pub fn add_rect_clip_node(
         &mut self,
@@ -2653,6 +2851,7 @@ impl<'a> SceneBuilder<'a> {
         new_node_id: ClipId,
         space_and_clip: &SpaceAndClipInfo,
         clip: &ComplexClipRegion,
+        binding: Option<(PropertyBindingId, LayoutVector2D)>,
     ) {
         let spatial_node_index = self.get_space(space_and_clip.spatial_id);
 
@@ -2660,12 +2859,23 @@ impl<'a> SceneBuilder<'a> {
             &clip.rect,
             spatial_node_index,
         );
-        let item = ClipItemKey {
-            kind: ClipItemKeyKind::rounded_rect(
+        let kind = match binding {
+            // Animated regions aren't snapped, since they change every frame.
+            Some((id, offset)) => ClipItemKeyKind::animated_rounded_rect(
+                clip.rect,
+                clip.radii,
+                clip.mode,
+                id,
+                offset,
+            ),
+            None => ClipItemKeyKind::rounded_rect(
                 snapped_region_rect,
                 clip.radii,
                 clip.mode,
             ),
+        };
+        let item = ClipItemKey {
+            kind,
             spatial_node_index,
         };
 
@@ -2679,6 +2889,11 @@ impl<'a> SceneBuilder<'a> {
                 }
             });
 
+        if binding.is_some() &&
+           !self.clip_store.animated_clips.iter().any(|h| h.uid() == handle.uid()) {
+            self.clip_store.animated_clips.push(handle);
+        }
+
         let instance = SceneClipInstance {
             key: item,
             clip: ClipInstance::new(handle),
@@ -2703,6 +2918,7 @@ impl<'a> SceneBuilder<'a> {
         external_scroll_offset: LayoutVector2D,
         scroll_offset_generation: APZScrollGeneration,
         has_scroll_linked_effect: HasScrollLinkedEffect,
+        physics: ScrollPhysicsParams,
         uid: SpatialNodeUid,
     ) -> SpatialNodeIndex {
         let node_index = self.spatial_tree.add_scroll_frame(
@@ -2715,12 +2931,18 @@ impl<'a> SceneBuilder<'a> {
             external_scroll_offset,
             scroll_offset_generation,
             has_scroll_linked_effect,
+            physics,
             uid,
         );
         self.id_to_index_mapper_stack.last_mut().unwrap().add_spatial_node(new_node_id, node_index);
         node_index
     }
 
+    /// Converts an sRGB color given by value to the output color space.
+    pub fn output_color(&self, color: ColorF) -> ColorF {
+        convert_color(color, RgbColorSpace::Srgb, self.output_color_space)
+    }
+
     pub fn push_shadow(
         &mut self,
         shadow: Shadow,
@@ -2728,6 +2950,11 @@ impl<'a> SceneBuilder<'a> {
         clip_chain_id: ClipChainId,
         should_inflate: bool,
     ) {
+        let shadow = Shadow {
+            color: self.output_color(shadow.color),
+            .. shadow
+        };
+
         // Store this shadow in the pending list, for processing
         // during pop_all_shadows.
         self.pending_shadow_items.push_back(ShadowItem::Shadow(PendingShadow {
@@ -2769,11 +2996,11 @@ impl<'a> SceneBuilder<'a> {
                     // Add any primitives that come after this shadow in the item
                     // list to this shadow.
                     let mut prim_list = PrimitiveList::empty();
-                    let blur_filter = Filter::Blur {
-                        width: std_deviation,
-                        height: std_deviation,
-                        should_inflate: pending_shadow.should_inflate,
-                    };
+                    let blur_filter = Filter::blur(
+                        std_deviation,
+                        std_deviation,
+                        pending_shadow.should_inflate,
+                    );
                     let blur_is_noop = blur_filter.is_noop();
 
                     for item in &items {
@@ -3053,7 +3280,42 @@ impl<'a> SceneBuilder<'a> {
             Vec::new(),
             LineDecoration {
                 cache_key,
-                color: color.into(),
+                color: self.output_color(color).into(),
+            },
+        );
+    }
+
+    pub fn add_path(
+        &mut self,
+        spatial_node_index: SpatialNodeIndex,
+        clip_chain_id: ClipChainId,
+        info: &LayoutPrimitiveInfo,
+        path_item: &PathDisplayItem,
+        segments: ItemRange<PathSegment>,
+    ) {
+        // Like line decorations, the cache key of the coverage mask doesn't
+        // depend on the device pixel ratio or transform. The segments are
+        // stored relative to the bounds, so that the mask can be shared by
+        // translated copies of the path.
+        let origin = path_item.bounds.min;
+        let cache_key = PathCacheKey {
+            segments: segments
+                .iter()
+                .map(|segment| PathSegmentKey::new(&segment, origin))
+                .collect(),
+            fill_rule: path_item.fill_rule,
+            stroke: path_item.stroke.map(StrokeKey::from),
+            size: info.rect.size().to_au(),
+        };
+
+        self.add_nonshadowable_primitive(
+            spatial_node_index,
+            clip_chain_id,
+            info,
+            Vec::new(),
+            Path {
+                cache_key,
+                color: self.output_color(path_item.color).into(),
             },
         );
     }
@@ -3105,6 +3367,7 @@ impl<'a> SceneBuilder<'a> {
                             gradient.end_point,
                             read_gradient_stops(gradient_stops),
                             gradient.extend_mode,
+                            gradient.interpolation,
                             LayoutSize::new(border.height as f32, border.width as f32),
                             LayoutSize::zero(),
                             Some(Box::new(nine_patch)),
@@ -3130,6 +3393,7 @@ impl<'a> SceneBuilder<'a> {
                             gradient.radius.width / gradient.radius.height,
                             read_gradient_stops(gradient_stops),
                             gradient.extend_mode,
+                            gradient.interpolation,
                             LayoutSize::new(border.height as f32, border.width as f32),
                             LayoutSize::zero(),
                             Some(Box::new(nine_patch)),
@@ -3152,6 +3416,7 @@ impl<'a> SceneBuilder<'a> {
                             gradient.end_offset,
                             gradient_stops,
                             gradient.extend_mode,
+                            gradient.interpolation,
                             LayoutSize::new(border.height as f32, border.width as f32),
                             LayoutSize::zero(),
                             Some(Box::new(nine_patch)),
@@ -3186,6 +3451,7 @@ impl<'a> SceneBuilder<'a> {
         end_point: LayoutPoint,
         stops: Vec<GradientStopKey>,
         extend_mode: ExtendMode,
+        interpolation: GradientInterpolation,
         stretch_size: LayoutSize,
         mut tile_spacing: LayoutSize,
         nine_patch: Option<Box<NinePatchDescriptor>>,
@@ -3201,7 +3467,7 @@ impl<'a> SceneBuilder<'a> {
                 has_hard_stops = true;
             }
             prev_stop = Some(stop.offset);
-            if stop.color.a > 0 {
+            if stop.color.a > 0 || stop.color_binding.is_some() {
                 is_entirely_transparent = false;
             }
         }
@@ -3245,6 +3511,8 @@ impl<'a> SceneBuilder<'a> {
 
         Some(LinearGradient {
             extend_mode,
+            interpolation,
+            output_color_space: self.output_color_space,
             start_point: sp.into(),
             end_point: ep.into(),
             stretch_size: stretch_size.into(),
@@ -3265,6 +3533,7 @@ impl<'a> SceneBuilder<'a> {
         ratio_xy: f32,
         stops: Vec<GradientStopKey>,
         extend_mode: ExtendMode,
+        interpolation: GradientInterpolation,
         stretch_size: LayoutSize,
         mut tile_spacing: LayoutSize,
         nine_patch: Option<Box<NinePatchDescriptor>>,
@@ -3280,6 +3549,8 @@ impl<'a> SceneBuilder<'a> {
 
         RadialGradient {
             extend_mode,
+            interpolation,
+            output_color_space: self.output_color_space,
             center: center.into(),
             params,
             stretch_size: stretch_size.into(),
@@ -3298,6 +3569,7 @@ impl<'a> SceneBuilder<'a> {
         end_offset: f32,
         stops: ItemRange<GradientStop>,
         extend_mode: ExtendMode,
+        interpolation: GradientInterpolation,
         stretch_size: LayoutSize,
         mut tile_spacing: LayoutSize,
         nine_patch: Option<Box<NinePatchDescriptor>>,
@@ -3305,15 +3577,12 @@ impl<'a> SceneBuilder<'a> {
         let mut prim_rect = info.rect;
         simplify_repeated_primitive(&stretch_size, &mut tile_spacing, &mut prim_rect);
 
-        let stops = stops.iter().map(|stop| {
-            GradientStopKey {
-                offset: stop.offset,
-                color: stop.color.into(),
-            }
-        }).collect();
+        let stops = read_gradient_stops(stops);
 
         ConicGradient {
             extend_mode,
+            interpolation,
+            output_color_space: self.output_color_space,
             center: center.into(),
             params: ConicGradientParams { angle, start_offset, end_offset },
             stretch_size: stretch_size.into(),
@@ -3336,40 +3605,11 @@ impl<'a> SceneBuilder<'a> {
         let offset = self.current_offset(spatial_node_index);
 
         let text_run = {
-            let shared_key = self.fonts.instance_keys.map_key(font_instance_key);
-            let font_instance = match self.fonts.instances.get_font_instance(shared_key) {
-                Some(instance) => instance,
-                None => {
-                    warn!("Unknown font instance key");
-                    debug!("key={:?} shared={:?}", font_instance_key, shared_key);
-                    return;
-                }
+            let font = match self.get_text_font(font_instance_key, text_color, glyph_options) {
+                Some(font) => font,
+                None => return,
             };
 
-            // Trivial early out checks
-            if font_instance.size <= FontSize::zero() {
-                return;
-            }
-
-            // TODO(gw): Use a proper algorithm to select
-            // whether this item should be rendered with
-            // subpixel AA!
-            let mut render_mode = self.config
-                .default_font_render_mode
-                .limit_by(font_instance.render_mode);
-            let mut flags = font_instance.flags;
-            if let Some(options) = glyph_options {
-                render_mode = render_mode.limit_by(options.render_mode);
-                flags |= options.flags;
-            }
-
-            let font = FontInstance::new(
-                font_instance,
-                (*text_color).into(),
-                render_mode,
-                flags,
-            );
-
             // TODO(gw): It'd be nice not to have to allocate here for creating
             //           the primitive key, when the common case is that the
             //           hash will match and we won't end up creating a new
@@ -3409,6 +3649,205 @@ impl<'a> SceneBuilder<'a> {
         );
     }
 
+    /// Get the font instance that the glyphs of a text item are rasterized
+    /// with, or None if there is nothing to draw.
+    fn get_text_font(
+        &self,
+        font_instance_key: &FontInstanceKey,
+        text_color: &ColorF,
+        glyph_options: Option<GlyphOptions>,
+    ) -> Option<FontInstance> {
+        let shared_key = self.fonts.instance_keys.map_key(font_instance_key);
+        let font_instance = match self.fonts.instances.get_font_instance(shared_key) {
+            Some(instance) => instance,
+            None => {
+                warn!("Unknown font instance key");
+                debug!("key={:?} shared={:?}", font_instance_key, shared_key);
+                return None;
+            }
+        };
+
+        // Trivial early out checks
+        if font_instance.size <= FontSize::zero() {
+            return None;
+        }
+
+        // TODO(gw): Use a proper algorithm to select
+        // whether this item should be rendered with
+        // subpixel AA!
+        let mut render_mode = self.config
+            .default_font_render_mode
+            .limit_by(font_instance.render_mode);
+        let mut flags = font_instance.flags;
+        if let Some(options) = glyph_options {
+            render_mode = render_mode.limit_by(options.render_mode);
+            flags |= options.flags;
+        }
+
+        Some(FontInstance::new(
+            font_instance,
+            self.output_color(*text_color).into(),
+            render_mode,
+            flags,
+        ))
+    }
+
+    /// Add the underline, overline and line-through of a text item as line
+    /// decorations. A line that skips ink is split into segments around the
+    /// glyphs that cross it. Each segment keeps the rect of the whole line and
+    /// is clipped down to its extent, so that dashes and waves stay in phase.
+    pub fn add_text_decorations(
+        &mut self,
+        common: &CommonItemProperties,
+        bounds: &LayoutRect,
+        font_instance_key: &FontInstanceKey,
+        text_color: &ColorF,
+        glyph_range: ItemRange<GlyphInstance>,
+        glyph_options: Option<GlyphOptions>,
+        decoration: &TextDecoration,
+    ) {
+        // Offsets are relative to the baseline of the first glyph.
+        let baseline = match glyph_range.iter().next() {
+            Some(glyph) => glyph.point.y,
+            None => return,
+        };
+
+        if decoration.thickness <= 0.0 || bounds.width() <= 0.0 {
+            return;
+        }
+
+        let lines = [
+            (TextDecorationLines::UNDERLINE, decoration.underline_offset, decoration.skip_ink),
+            (TextDecorationLines::OVERLINE, decoration.overline_offset, decoration.skip_ink),
+            (TextDecorationLines::LINE_THROUGH, decoration.line_through_offset, false),
+        ];
+
+        // Glyph inks are only looked up if some line skips them. The font
+        // contexts cache them across lines, items and scene builds.
+        let mut font = None;
+
+        for &(line, offset, skip_ink) in &lines {
+            if !decoration.lines.contains(line) {
+                continue;
+            }
+
+            let top = baseline + offset;
+            let bottom = top + decoration.thickness;
+
+            // Wavy lines oscillate around the line, so they need some room
+            // on either side of it.
+            let area = match decoration.style {
+                LineStyle::Wavy => LayoutRect::new(
+                    LayoutPoint::new(bounds.min.x, top - decoration.thickness),
+                    LayoutPoint::new(bounds.max.x, bottom + decoration.thickness),
+                ),
+                LineStyle::Solid | LineStyle::Dotted | LineStyle::Dashed => LayoutRect::new(
+                    LayoutPoint::new(bounds.min.x, top),
+                    LayoutPoint::new(bounds.max.x, bottom),
+                ),
+            };
+
+            let (layout, _, spatial_node_index, clip_chain_id) = self.process_common_properties_with_bounds(
+                common,
+                &area,
+            );
+
+            // The horizontal ranges of the line that are not covered by ink,
+            // relative to the display list.
+            let mut segments = vec![(area.min.x, area.max.x)];
+
+            if skip_ink {
+                if font.is_none() {
+                    font = self.get_text_font(font_instance_key, text_color, glyph_options)
+                        .and_then(|font| {
+                            let template = self.fonts.templates.get_font(&font.font_key)?;
+                            Some((font, template))
+                        });
+                }
+
+                if let Some((ref font, ref template)) = font {
+                    let mut gaps = Vec::new();
+                    for glyph in glyph_range.iter() {
+                        let ink = self.font_contexts.get_glyph_ink(
+                            font,
+                            template,
+                            glyph.glyph_index(),
+                            glyph.orientation(),
+                        );
+                        let extent = ink.and_then(|ink| {
+                            ink.horizontal_extent(area.min.y - glyph.point.y, area.max.y - glyph.point.y)
+                        });
+                        if let Some((start, end)) = extent {
+                            // Leave some room between the line and the ink.
+                            let gap = (
+                                glyph.point.x + start - decoration.thickness,
+                                glyph.point.x + end + decoration.thickness,
+                            );
+                            // Glyphs at non-finite positions aren't drawn, so
+                            // they don't interrupt the line either.
+                            if gap.0.is_finite() && gap.1.is_finite() {
+                                gaps.push(gap);
+                            }
+                        }
+                    }
+
+                    gaps.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+                    segments.clear();
+                    let mut start = area.min.x;
+                    for (gap_start, gap_end) in gaps {
+                        if gap_start > start {
+                            segments.push((start, gap_start.min(area.max.x)));
+                        }
+                        start = start.max(gap_end);
+                    }
+                    if start < area.max.x {
+                        segments.push((start, area.max.x));
+                    }
+                }
+            }
+
+            // Segments are mapped into the same space as the line, which may
+            // have been offset and snapped.
+            let scale = layout.rect.width() / area.width();
+            for (start, end) in segments {
+                if end <= start {
+                    continue;
+                }
+
+                let segment = LayoutRect::new(
+                    LayoutPoint::new(
+                        layout.rect.min.x + (start - area.min.x) * scale,
+                        layout.rect.min.y,
+                    ),
+                    LayoutPoint::new(
+                        layout.rect.min.x + (end - area.min.x) * scale,
+                        layout.rect.max.y,
+                    ),
+                );
+                let clip_rect = match segment.intersection(&layout.clip_rect) {
+                    Some(clip_rect) => clip_rect,
+                    None => continue,
+                };
+
+                let info = LayoutPrimitiveInfo {
+                    clip_rect,
+                    .. layout
+                };
+
+                self.add_line(
+                    spatial_node_index,
+                    clip_chain_id,
+                    &info,
+                    decoration.thickness,
+                    LineOrientation::Horizontal,
+                    decoration.color,
+                    decoration.style,
+                );
+            }
+        }
+    }
+
     pub fn add_image(
         &mut self,
         spatial_node_index: SpatialNodeIndex,
@@ -3416,6 +3855,7 @@ impl<'a> SceneBuilder<'a> {
         info: &LayoutPrimitiveInfo,
         stretch_size: LayoutSize,
         mut tile_spacing: LayoutSize,
+        source_rect: Option<DeviceIntRect>,
         image_key: ImageKey,
         image_rendering: ImageRendering,
         alpha_type: AlphaType,
@@ -3437,9 +3877,10 @@ impl<'a> SceneBuilder<'a> {
                 key: image_key,
                 tile_spacing: tile_spacing.into(),
                 stretch_size: stretch_size.into(),
-                color: color.into(),
+                color: self.output_color(color).into(),
                 image_rendering,
                 alpha_type,
+                source_rect,
             },
         );
     }
@@ -3857,11 +4298,29 @@ fn create_prim_instance(
 
 fn filter_ops_for_compositing(
     input_filters: ItemRange<FilterOp>,
+    output_color_space: RgbColorSpace,
 ) -> Vec<Filter> {
     // TODO(gw): Now that we resolve these later on,
     //           we could probably make it a bit
     //           more efficient than cloning these here.
-    input_filters.iter().map(|filter| filter.into()).collect()
+    input_filters
+        .iter()
+        .map(|filter| {
+            let mut filter: Filter = filter.into();
+            match filter {
+                Filter::DropShadows(ref mut shadows) => {
+                    for shadow in shadows {
+                        shadow.color = convert_color(shadow.color, RgbColorSpace::Srgb, output_color_space);
+                    }
+                }
+                Filter::Flood(ref mut color) => {
+                    *color = convert_color(*color, RgbColorSpace::Srgb, output_color_space);
+                }
+                _ => {}
+            }
+            filter
+        })
+        .collect()
 }
 
 fn filter_datas_for_compositing(
@@ -3890,12 +4349,26 @@ fn filter_datas_for_compositing(
 
 fn filter_primitives_for_compositing(
     input_filter_primitives: ItemRange<FilterPrimitive>,
+    output_color_space: RgbColorSpace,
 ) -> Vec<FilterPrimitive> {
     // Resolve these in the flattener?
     // TODO(gw): Now that we resolve these later on,
     //           we could probably make it a bit
     //           more efficient than cloning these here.
-    input_filter_primitives.iter().map(|primitive| primitive).collect()
+    input_filter_primitives
+        .iter()
+        .map(|mut primitive| {
+            let color = match primitive.kind {
+                FilterPrimitiveKind::Flood(ref mut flood) => &mut flood.color,
+                FilterPrimitiveKind::DropShadow(ref mut drop_shadow) => &mut drop_shadow.shadow.color,
+                FilterPrimitiveKind::DiffuseLighting(ref mut lighting) => &mut lighting.lighting_color,
+                FilterPrimitiveKind::SpecularLighting(ref mut lighting) => &mut lighting.lighting_color,
+                _ => return primitive,
+            };
+            *color = convert_color(*color, RgbColorSpace::Srgb, output_color_space);
+            primitive
+        })
+        .collect()
 }
 
 fn process_repeat_size(
@@ -3926,10 +4399,5 @@ fn process_repeat_size(
 }
 
 fn read_gradient_stops(stops: ItemRange<GradientStop>) -> Vec<GradientStopKey> {
-    stops.iter().map(|stop| {
-        GradientStopKey {
-            offset: stop.offset,
-            color: stop.color.into(),
-        }
-    }).collect()
+    stops.iter().map(|stop| stop.into()).collect()
 }
@@ -0,0 +1,598 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Scroll physics for scroll frames scrolled with `Transaction::scroll`.
+//!
+//! Scroll events move the content directly while a gesture is in progress.
+//! Once the gesture ends, the scroll frame keeps moving with the velocity of
+//! the gesture and decelerates with friction, or springs towards its rest
+//! position when it has to snap or recover from overscroll. The motion is
+//! advanced every time the render backend builds a frame.
+//!
+//! Times are in nanoseconds from the monotonic `precise_time_ns` clock.
+//!
+//! Positions are distances scrolled from the start of the content, between
+//! zero and the scrollable size, except while overscrolled.
+
+use api::{OverscrollMode, ScrollEventPhase, ScrollLocation, ScrollSnapPoint, ScrollSnapStrictness};
+use api::units::{LayoutSize, LayoutVector2D};
+
+/// The velocity multiplier applied every millisecond of momentum scrolling.
+const FRICTION: f32 = 0.997;
+/// Below this speed, in pixels per millisecond, the motion is considered
+/// stopped.
+const MIN_VELOCITY: f32 = 0.02;
+/// The natural frequency of the critically damped spring used for snapping
+/// and overscroll recovery, per millisecond.
+const SPRING_FREQUENCY: f32 = 0.02;
+/// The distance under which a settling scroll frame is considered at rest.
+const REST_DISTANCE: f32 = 0.5;
+/// How hard it is to drag the content past the bounds.
+const RUBBER_BAND_FACTOR: f32 = 0.55;
+/// Gesture events further apart than this, in milliseconds, don't contribute
+/// to the velocity of the gesture.
+const VELOCITY_TIMEOUT_MS: f32 = 100.0;
+/// The distance at which proximity snapping applies, as a fraction of the
+/// size of the viewport.
+const SNAP_PROXIMITY: f32 = 0.3;
+
+/// The snapping and overscroll behavior of a scroll frame, declared in its
+/// display list.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct ScrollPhysicsParams {
+    pub snap: ScrollSnapStrictness,
+    pub overscroll: OverscrollMode,
+    pub snap_points: Vec<ScrollSnapPoint>,
+}
+
+/// The extent a scroll frame can be scrolled within.
+#[derive(Clone, Copy, Debug)]
+pub struct ScrollBounds {
+    /// The largest position on each axis.
+    pub max: LayoutVector2D,
+    /// The size of the scroll frame's viewport.
+    pub viewport: LayoutSize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+enum Motion {
+    /// The scroll frame is at rest.
+    Idle,
+    /// The scroll frame follows a gesture in progress.
+    Tracking,
+    /// The scroll frame decelerates after a gesture ended.
+    Momentum,
+    /// The scroll frame springs towards a rest position.
+    Settling { target: LayoutVector2D },
+}
+
+/// The state of a scroll frame driven by scroll events.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct ScrollPhysicsState {
+    position: LayoutVector2D,
+    /// In pixels per millisecond.
+    velocity: LayoutVector2D,
+    motion: Motion,
+    /// The time of the last event or sample.
+    last_time: u64,
+    /// The time of the last gesture event.
+    last_event_time: u64,
+}
+
+impl ScrollPhysicsState {
+    pub fn new(position: LayoutVector2D, time: u64) -> Self {
+        ScrollPhysicsState {
+            position,
+            velocity: LayoutVector2D::zero(),
+            motion: Motion::Idle,
+            last_time: time,
+            last_event_time: time,
+        }
+    }
+
+    pub fn position(&self) -> LayoutVector2D {
+        self.position
+    }
+
+    pub fn is_animating(&self) -> bool {
+        match self.motion {
+            Motion::Momentum | Motion::Settling { .. } => true,
+            Motion::Idle | Motion::Tracking => false,
+        }
+    }
+
+    /// Apply a scroll event.
+    pub fn handle_event(
+        &mut self,
+        location: ScrollLocation,
+        phase: ScrollEventPhase,
+        time: u64,
+        params: &ScrollPhysicsParams,
+        bounds: &ScrollBounds,
+    ) {
+        // Advance any ongoing motion up to the time of the event first.
+        self.sample(time, params, bounds);
+
+        let delta = match location {
+            ScrollLocation::Delta(delta) => -delta,
+            ScrollLocation::Start | ScrollLocation::End => {
+                let mut target = self.rest_target();
+                target.y = match location {
+                    ScrollLocation::Start => 0.0,
+                    _ => bounds.max.y,
+                };
+                let direction = target - self.position;
+                self.settle_to(snap(target, Some(direction), params, bounds));
+                return;
+            }
+        };
+
+        match phase {
+            ScrollEventPhase::Start => {
+                self.velocity = LayoutVector2D::zero();
+                self.motion = Motion::Tracking;
+                self.last_event_time = time;
+                self.drag(delta, params, bounds);
+            }
+            ScrollEventPhase::Move | ScrollEventPhase::End => {
+                if self.motion != Motion::Tracking {
+                    self.velocity = LayoutVector2D::zero();
+                    self.motion = Motion::Tracking;
+                }
+
+                let elapsed = ms_between(self.last_event_time, time);
+                self.last_event_time = time;
+
+                let old_position = self.position;
+                self.drag(delta, params, bounds);
+
+                if elapsed > VELOCITY_TIMEOUT_MS {
+                    self.velocity = LayoutVector2D::zero();
+                } else if phase == ScrollEventPhase::End && delta == LayoutVector2D::zero() {
+                    // An empty end event only marks the release of the
+                    // gesture and doesn't slow it down.
+                } else if elapsed > 0.0 {
+                    let velocity = (self.position - old_position) / elapsed;
+                    self.velocity = self.velocity.lerp(velocity, 0.6);
+                }
+
+                if phase == ScrollEventPhase::End {
+                    self.release(params, bounds);
+                }
+            }
+            ScrollEventPhase::Discrete => {
+                // Consecutive discrete scrolls accumulate.
+                let target = clamp(self.rest_target() + delta, bounds);
+                self.settle_to(snap(target, Some(delta), params, bounds));
+            }
+        }
+    }
+
+    /// Advance the motion to the given time. Returns true if the position
+    /// changed.
+    pub fn sample(
+        &mut self,
+        time: u64,
+        params: &ScrollPhysicsParams,
+        bounds: &ScrollBounds,
+    ) -> bool {
+        let dt = ms_between(self.last_time, time);
+        self.last_time = time;
+
+        let old_position = self.position;
+
+        match self.motion {
+            Motion::Idle | Motion::Tracking => {}
+            Motion::Momentum => {
+                let decay = FRICTION.powf(dt);
+                // The integral of the exponentially decaying velocity.
+                self.position += self.velocity * ((1.0 - decay) / -FRICTION.ln());
+                self.velocity *= decay;
+
+                let clamped = clamp(self.position, bounds);
+                if clamped != self.position {
+                    match params.overscroll {
+                        // Spring back from the bounds, which also makes the
+                        // content bounce past them.
+                        OverscrollMode::Bounce => {
+                            self.motion = Motion::Settling { target: clamped };
+                        }
+                        OverscrollMode::Clamp => {
+                            if clamped.x != self.position.x {
+                                self.velocity.x = 0.0;
+                            }
+                            if clamped.y != self.position.y {
+                                self.velocity.y = 0.0;
+                            }
+                            self.position = clamped;
+                        }
+                    }
+                }
+
+                if self.motion == Motion::Momentum && is_stopped(self.velocity) {
+                    let target = snap(self.position, None, params, bounds);
+                    self.settle_to(target);
+                }
+            }
+            Motion::Settling { target } => {
+                let (x, vx) = spring_step(self.position.x - target.x, self.velocity.x, dt);
+                let (y, vy) = spring_step(self.position.y - target.y, self.velocity.y, dt);
+                self.position = target + LayoutVector2D::new(x, y);
+                self.velocity = LayoutVector2D::new(vx, vy);
+
+                if params.overscroll == OverscrollMode::Clamp {
+                    self.position = clamp(self.position, bounds);
+                }
+
+                let offset = self.position - target;
+                if offset.x.abs() < REST_DISTANCE && offset.y.abs() < REST_DISTANCE &&
+                   is_stopped(self.velocity) {
+                    self.position = target;
+                    self.velocity = LayoutVector2D::zero();
+                    self.motion = Motion::Idle;
+                }
+            }
+        }
+
+        self.position != old_position
+    }
+
+    /// Bring the scroll frame back within new bounds, e.g. after its content
+    /// changed size.
+    pub fn update_bounds(&mut self, params: &ScrollPhysicsParams, bounds: &ScrollBounds) {
+        if self.motion == Motion::Idle {
+            let target = snap(self.position, None, params, bounds);
+            self.settle_to(target);
+        }
+    }
+
+    /// Move the content with a gesture, resisting past the bounds.
+    fn drag(&mut self, delta: LayoutVector2D, params: &ScrollPhysicsParams, bounds: &ScrollBounds) {
+        self.position = match params.overscroll {
+            OverscrollMode::Clamp => clamp(self.position + delta, bounds),
+            OverscrollMode::Bounce => LayoutVector2D::new(
+                rubber_band(self.position.x, delta.x, bounds.max.x, bounds.viewport.width),
+                rubber_band(self.position.y, delta.y, bounds.max.y, bounds.viewport.height),
+            ),
+        };
+    }
+
+    /// Start the motion that follows the end of a gesture.
+    fn release(&mut self, params: &ScrollPhysicsParams, bounds: &ScrollBounds) {
+        // Where the scroll frame would come to rest with friction alone.
+        let projected = self.position + self.velocity / -FRICTION.ln();
+        let target = snap(projected, None, params, bounds);
+
+        if target != clamp(projected, bounds) || clamp(self.position, bounds) != self.position {
+            self.settle_to(target);
+        } else if is_stopped(self.velocity) {
+            self.velocity = LayoutVector2D::zero();
+            self.motion = Motion::Idle;
+        } else {
+            self.motion = Motion::Momentum;
+        }
+    }
+
+    fn settle_to(&mut self, target: LayoutVector2D) {
+        self.motion = if target == self.position && is_stopped(self.velocity) {
+            self.velocity = LayoutVector2D::zero();
+            Motion::Idle
+        } else {
+            Motion::Settling { target }
+        };
+    }
+
+    /// The position the scroll frame is heading to when settling, or its
+    /// current position otherwise.
+    fn rest_target(&self) -> LayoutVector2D {
+        match self.motion {
+            Motion::Settling { target } => target,
+            _ => self.position,
+        }
+    }
+}
+
+fn ms_between(from: u64, to: u64) -> f32 {
+    to.saturating_sub(from) as f32 / 1_000_000.0
+}
+
+fn is_stopped(velocity: LayoutVector2D) -> bool {
+    velocity.x.abs() < MIN_VELOCITY && velocity.y.abs() < MIN_VELOCITY
+}
+
+fn clamp(position: LayoutVector2D, bounds: &ScrollBounds) -> LayoutVector2D {
+    LayoutVector2D::new(
+        position.x.max(0.0).min(bounds.max.x),
+        position.y.max(0.0).min(bounds.max.y),
+    )
+}
+
+/// Advance a critically damped spring by `dt` milliseconds, given the offset
+/// from its rest position and its velocity.
+fn spring_step(offset: f32, velocity: f32, dt: f32) -> (f32, f32) {
+    let decay = (-SPRING_FREQUENCY * dt).exp();
+    let c = velocity + SPRING_FREQUENCY * offset;
+    (
+        (offset + c * dt) * decay,
+        (velocity - SPRING_FREQUENCY * c * dt) * decay,
+    )
+}
+
+/// Move a position along one axis with a gesture, resisting past the bounds.
+///
+/// The visible overscroll is `x * c * d / (x * c + d)` for a gesture that
+/// went `x` past the bounds, which approaches the viewport size `d` but never
+/// reaches it.
+fn rubber_band(position: f32, delta: f32, max: f32, extent: f32) -> f32 {
+    let extent = extent.max(1.0);
+    let overscroll = |x: f32| x * RUBBER_BAND_FACTOR * extent / (x * RUBBER_BAND_FACTOR + extent);
+    let unresisted = |y: f32| y * extent / (RUBBER_BAND_FACTOR * (extent - y));
+
+    // Find where the gesture would be without resistance.
+    let bound = position.max(0.0).min(max);
+    let visible = (position - bound).abs().min(extent * 0.99);
+    let gesture = bound + unresisted(visible).copysign(position - bound) + delta;
+
+    let bound = gesture.max(0.0).min(max);
+    bound + overscroll((gesture - bound).abs()).copysign(gesture - bound)
+}
+
+/// Select the rest position of a scroll frame that was headed to `position`,
+/// moving in the given direction if any.
+fn snap(
+    position: LayoutVector2D,
+    direction: Option<LayoutVector2D>,
+    params: &ScrollPhysicsParams,
+    bounds: &ScrollBounds,
+) -> LayoutVector2D {
+    let clamped = clamp(position, bounds);
+    LayoutVector2D::new(
+        snap_axis(
+            params.snap_points.iter().filter_map(|point| point.x),
+            clamped.x,
+            direction.map(|d| d.x),
+            params.snap,
+            bounds.max.x,
+            bounds.viewport.width,
+        ),
+        snap_axis(
+            params.snap_points.iter().filter_map(|point| point.y),
+            clamped.y,
+            direction.map(|d| d.y),
+            params.snap,
+            bounds.max.y,
+            bounds.viewport.height,
+        ),
+    )
+}
+
+fn snap_axis(
+    points: impl Iterator<Item = f32> + Clone,
+    position: f32,
+    direction: Option<f32>,
+    strictness: ScrollSnapStrictness,
+    max: f32,
+    extent: f32,
+) -> f32 {
+    // A degenerate offset or velocity from the API has nothing to snap to.
+    if strictness == ScrollSnapStrictness::None || !position.is_finite() {
+        return position;
+    }
+
+    let nearest = |points: &mut dyn Iterator<Item = f32>| {
+        points
+            .map(|point| point.max(0.0).min(max))
+            .filter(|point| point.is_finite())
+            .min_by(|a, b| {
+                let (a, b) = ((a - position).abs(), (b - position).abs());
+                a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
+            })
+    };
+
+    // Prefer snap positions that don't go back against the motion.
+    let forward = match direction {
+        Some(direction) if direction != 0.0 => {
+            let behind = position - direction.signum() * REST_DISTANCE;
+            nearest(&mut points.clone().filter(|point| (point - behind) * direction > 0.0))
+        }
+        _ => None,
+    };
+
+    let snapped = match forward.or_else(|| nearest(&mut points.clone())) {
+        Some(snapped) => snapped,
+        None => return position,
+    };
+
+    match strictness {
+        ScrollSnapStrictness::Proximity if (snapped - position).abs() > extent * SNAP_PROXIMITY => position,
+        _ => snapped,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds() -> ScrollBounds {
+        ScrollBounds {
+            max: LayoutVector2D::new(0.0, 1000.0),
+            viewport: LayoutSize::new(200.0, 200.0),
+        }
+    }
+
+    fn physics(snap: ScrollSnapStrictness, overscroll: OverscrollMode, points: &[f32]) -> ScrollPhysicsParams {
+        ScrollPhysicsParams {
+            snap,
+            overscroll,
+            snap_points: points.iter().map(|y| ScrollSnapPoint { x: None, y: Some(*y) }).collect(),
+        }
+    }
+
+    fn ms(start: u64, ms: u64) -> u64 {
+        start + ms * 1_000_000
+    }
+
+    /// Sample until the scroll frame comes to rest, returning its position.
+    fn settle(
+        state: &mut ScrollPhysicsState,
+        start: u64,
+        params: &ScrollPhysicsParams,
+    ) -> LayoutVector2D {
+        let mut time = 0;
+        while state.is_animating() {
+            time += 16;
+            assert!(time < 10_000, "the motion never stops");
+            state.sample(ms(start, time), params, &bounds());
+        }
+        state.position()
+    }
+
+    fn fling(state: &mut ScrollPhysicsState, start: u64, params: &ScrollPhysicsParams, step: f32) {
+        let up = |y| ScrollLocation::Delta(LayoutVector2D::new(0.0, y));
+        state.handle_event(up(0.0), ScrollEventPhase::Start, start, params, &bounds());
+        for i in 1 .. 5 {
+            state.handle_event(up(-step), ScrollEventPhase::Move, ms(start, i * 10), params, &bounds());
+        }
+        state.handle_event(up(0.0), ScrollEventPhase::End, ms(start, 50), params, &bounds());
+    }
+
+    #[test]
+    fn gesture_follows_deltas() {
+        let start = 0;
+        let params = ScrollPhysicsParams::default();
+        let mut state = ScrollPhysicsState::new(LayoutVector2D::zero(), start);
+
+        let delta = ScrollLocation::Delta(LayoutVector2D::new(0.0, -30.0));
+        state.handle_event(delta, ScrollEventPhase::Start, start, &params, &bounds());
+        state.handle_event(delta, ScrollEventPhase::Move, ms(start, 500), &params, &bounds());
+        assert_eq!(state.position(), LayoutVector2D::new(0.0, 60.0));
+
+        // The gesture paused before being released, so there is no momentum.
+        state.handle_event(delta, ScrollEventPhase::End, ms(start, 1000), &params, &bounds());
+        assert!(!state.is_animating());
+        assert_eq!(state.position(), LayoutVector2D::new(0.0, 90.0));
+    }
+
+    #[test]
+    fn momentum_decelerates() {
+        let start = 0;
+        let params = ScrollPhysicsParams::default();
+        let mut state = ScrollPhysicsState::new(LayoutVector2D::zero(), start);
+
+        fling(&mut state, start, &params, 10.0);
+        let released = state.position().y;
+        assert_eq!(released, 40.0);
+        assert!(state.is_animating());
+
+        state.sample(ms(start, 66), &params, &bounds());
+        let first = state.position().y - released;
+        state.sample(ms(start, 82), &params, &bounds());
+        let second = state.position().y - released - first;
+        assert!(first > 0.0 && second > 0.0 && second < first);
+
+        // At 1px/ms, the fling travels about 1 / -ln(0.997) = 333px.
+        let rest = settle(&mut state, start, &params).y;
+        assert!(rest > released + 300.0 && rest < released + 340.0, "{}", rest);
+    }
+
+    #[test]
+    fn momentum_clamps_or_bounces() {
+        let start = 0;
+        let at_end = LayoutVector2D::new(0.0, 950.0);
+
+        let params = ScrollPhysicsParams::default();
+        let mut state = ScrollPhysicsState::new(at_end, start);
+        fling(&mut state, start, &params, 10.0);
+        assert_eq!(settle(&mut state, start, &params), LayoutVector2D::new(0.0, 1000.0));
+
+        let params = physics(ScrollSnapStrictness::None, OverscrollMode::Bounce, &[]);
+        let mut state = ScrollPhysicsState::new(at_end, start);
+        fling(&mut state, start, &params, 10.0);
+
+        let mut max_position: f32 = 0.0;
+        let mut time = 50;
+        while state.is_animating() {
+            time += 16;
+            state.sample(ms(start, time), &params, &bounds());
+            max_position = max_position.max(state.position().y);
+        }
+        assert!(max_position > 1010.0, "{}", max_position);
+        assert_eq!(state.position(), LayoutVector2D::new(0.0, 1000.0));
+    }
+
+    #[test]
+    fn overscroll_resists_and_springs_back() {
+        let start = 0;
+        let params = physics(ScrollSnapStrictness::None, OverscrollMode::Bounce, &[]);
+        let mut state = ScrollPhysicsState::new(LayoutVector2D::zero(), start);
+
+        let down = ScrollLocation::Delta(LayoutVector2D::new(0.0, 100.0));
+        state.handle_event(down, ScrollEventPhase::Start, start, &params, &bounds());
+        let first = -state.position().y;
+        assert!(first > 0.0 && first < 55.0, "{}", first);
+        state.handle_event(down, ScrollEventPhase::Move, ms(start, 500), &params, &bounds());
+        let second = -state.position().y - first;
+        assert!(second > 0.0 && second < first, "{}", second);
+
+        // Moving back by the same amount undoes the gesture.
+        let up = ScrollLocation::Delta(LayoutVector2D::new(0.0, -100.0));
+        state.handle_event(up, ScrollEventPhase::Move, ms(start, 1000), &params, &bounds());
+        assert!((state.position().y + first).abs() < 0.01, "{:?}", state.position());
+
+        state.handle_event(down, ScrollEventPhase::End, ms(start, 1500), &params, &bounds());
+        assert!(state.is_animating());
+        assert_eq!(settle(&mut state, start, &params), LayoutVector2D::zero());
+    }
+
+    #[test]
+    fn snapping() {
+        let start = 0;
+        let points = [0.0, 300.0, 600.0, 900.0];
+
+        // A fling comes to rest on the snap position nearest to where friction
+        // alone would have stopped it.
+        let params = physics(ScrollSnapStrictness::Mandatory, OverscrollMode::Clamp, &points);
+        let mut state = ScrollPhysicsState::new(LayoutVector2D::zero(), start);
+        fling(&mut state, start, &params, 10.0);
+        assert_eq!(settle(&mut state, start, &params), LayoutVector2D::new(0.0, 300.0));
+
+        // Discrete scrolls move to the next snap position in their direction.
+        let mut state = ScrollPhysicsState::new(LayoutVector2D::zero(), start);
+        let wheel = ScrollLocation::Delta(LayoutVector2D::new(0.0, -40.0));
+        state.handle_event(wheel, ScrollEventPhase::Discrete, start, &params, &bounds());
+        assert_eq!(settle(&mut state, start, &params), LayoutVector2D::new(0.0, 300.0));
+
+        // Proximity snapping only applies near a snap position.
+        let params = physics(ScrollSnapStrictness::Proximity, OverscrollMode::Clamp, &points);
+        assert_eq!(snap(LayoutVector2D::new(0.0, 330.0), None, &params, &bounds()).y, 300.0);
+        assert_eq!(snap(LayoutVector2D::new(0.0, 450.0), None, &params, &bounds()).y, 450.0);
+    }
+
+    #[test]
+    fn snapping_non_finite_positions() {
+        let points = [0.0, 300.0, std::f32::NAN];
+        let snap_y = |position, direction| {
+            snap_axis(points.iter().cloned(), position, direction, ScrollSnapStrictness::Mandatory, 1000.0, 200.0)
+        };
+        assert!(snap_y(std::f32::NAN, None).is_nan());
+        assert_eq!(snap_y(250.0, Some(std::f32::NAN)), 300.0);
+        assert_eq!(snap_y(std::f32::INFINITY, None), std::f32::INFINITY);
+    }
+
+    #[test]
+    fn scroll_to_end() {
+        let start = 0;
+        let params = ScrollPhysicsParams::default();
+        let mut state = ScrollPhysicsState::new(LayoutVector2D::zero(), start);
+
+        state.handle_event(ScrollLocation::End, ScrollEventPhase::Discrete, start, &params, &bounds());
+        assert_eq!(settle(&mut state, start, &params), LayoutVector2D::new(0.0, 1000.0));
+    }
+}
@@ -5,6 +5,7 @@
 
 use api::{ExternalScrollId, PipelineId, PropertyBinding, PropertyBindingId, ReferenceFrameKind};
 use api::{APZScrollGeneration, HasScrollLinkedEffect, SampledScrollOffset};
+use api::{ScrollEventPhase, ScrollLocation};
 use api::{TransformStyle, StickyOffsetBounds, SpatialTreeItemKey};
 use api::units::*;
 use crate::internal_types::PipelineInstanceId;
@@ -12,6 +13,7 @@ use crate::spatial_tree::{CoordinateSystem, SpatialNodeIndex, TransformUpdateSta
 use crate::spatial_tree::{CoordinateSystemId};
 use euclid::{Vector2D, SideOffsets2D};
 use crate::scene::SceneProperties;
+use crate::scroll_physics::{ScrollBounds, ScrollPhysicsParams, ScrollPhysicsState};
 use crate::util::{LayoutFastTransform, MatrixHelpers, ScaleOffset, TransformedRectKind, PointHelpers};
 
 /// The kind of a spatial node uid. These are required because we currently create external
@@ -197,6 +199,7 @@ impl SceneSpatialNode {
         external_scroll_offset: LayoutVector2D,
         offset_generation: APZScrollGeneration,
         has_scroll_linked_effect: HasScrollLinkedEffect,
+        physics: ScrollPhysicsParams,
         is_root_coord_system: bool,
     ) -> Self {
         let node_type = SpatialNodeType::ScrollFrame(ScrollFrameInfo::new(
@@ -210,6 +213,7 @@ impl SceneSpatialNode {
                 external_scroll_offset,
                 offset_generation,
                 has_scroll_linked_effect,
+                physics,
             )
         );
 
@@ -340,6 +344,9 @@ impl SpatialNode {
             element.offset = -element.offset - scrolling.external_scroll_offset;
         }
 
+        // Offsets set by the embedder take over from any ongoing motion.
+        scrolling.physics_state = None;
+
         if scrolling.offsets == offsets {
             return false;
         }
@@ -804,6 +811,13 @@ pub struct ScrollFrameInfo {
     /// Whether the document containing this scroll frame has any scroll-linked
     /// effect or not.
     pub has_scroll_linked_effect: HasScrollLinkedEffect,
+
+    /// How this scroll frame moves when scrolled through `Transaction::scroll`.
+    pub physics: ScrollPhysicsParams,
+
+    /// The motion of the scroll frame since the last `Transaction::scroll`,
+    /// if any. Reset when the offsets are set by the embedder.
+    pub physics_state: Option<ScrollPhysicsState>,
 }
 
 /// Manages scrolling offset.
@@ -816,6 +830,7 @@ impl ScrollFrameInfo {
         external_scroll_offset: LayoutVector2D,
         offset_generation: APZScrollGeneration,
         has_scroll_linked_effect: HasScrollLinkedEffect,
+        physics: ScrollPhysicsParams,
     ) -> ScrollFrameInfo {
         ScrollFrameInfo {
             viewport_rect,
@@ -836,6 +851,8 @@ impl ScrollFrameInfo {
             }],
             offset_generation,
             has_scroll_linked_effect,
+            physics,
+            physics_state: None,
         }
     }
 
@@ -856,6 +873,81 @@ impl ScrollFrameInfo {
             _ => self.offsets.first().map_or(LayoutVector2D::zero(), |sampled| sampled.offset),
         }
     }
+
+    /// Apply a scroll event to this scroll frame. Returns true if the scroll
+    /// frame moved or started moving.
+    pub fn scroll(
+        &mut self,
+        location: ScrollLocation,
+        phase: ScrollEventPhase,
+        time: u64,
+    ) -> bool {
+        let bounds = self.physics_bounds();
+        // The physics work with the distance scrolled from the start of the
+        // content, which is the negated offset.
+        let position = -self.offset();
+        let state = self.physics_state.get_or_insert_with(|| {
+            ScrollPhysicsState::new(position, time)
+        });
+        state.handle_event(location, phase, time, &self.physics, &bounds);
+        let is_animating = state.is_animating();
+
+        self.apply_physics_position() || is_animating
+    }
+
+    /// Advance the motion of this scroll frame, if any. Returns true if the
+    /// scroll frame moved.
+    pub fn sample_physics(&mut self, time: u64) -> bool {
+        let bounds = self.physics_bounds();
+        match self.physics_state {
+            Some(ref mut state) => {
+                state.sample(time, &self.physics, &bounds);
+            }
+            None => return false,
+        }
+
+        self.apply_physics_position()
+    }
+
+    /// Returns true if the scroll frame keeps moving after a scroll gesture.
+    pub fn is_physics_animating(&self) -> bool {
+        self.physics_state.as_ref().map_or(false, |state| state.is_animating())
+    }
+
+    /// Restore the offsets from the motion of the scroll frame, after they
+    /// were reset by a new scene.
+    pub fn restore_physics_state(&mut self) {
+        let bounds = self.physics_bounds();
+        if let Some(ref mut state) = self.physics_state {
+            state.update_bounds(&self.physics, &bounds);
+        }
+        self.apply_physics_position();
+    }
+
+    fn physics_bounds(&self) -> ScrollBounds {
+        ScrollBounds {
+            max: LayoutVector2D::new(self.scrollable_size.width, self.scrollable_size.height),
+            viewport: self.viewport_rect.size(),
+        }
+    }
+
+    fn apply_physics_position(&mut self) -> bool {
+        let position = match self.physics_state {
+            Some(ref state) => state.position(),
+            None => return false,
+        };
+
+        let offsets = vec![SampledScrollOffset {
+            offset: -position,
+            generation: self.offset_generation.clone(),
+        }];
+        if self.offsets == offsets {
+            return false;
+        }
+
+        self.offsets = offsets;
+        true
+    }
 }
 
 /// Contains information about reference frames.
@@ -956,6 +1048,7 @@ fn test_cst_perspective_relative_scroll() {
         LayoutVector2D::zero(),
         APZScrollGeneration::default(),
         HasScrollLinkedEffect::No,
+        ScrollPhysicsParams::default(),
         SpatialNodeUid::external(SpatialTreeItemKey::new(0, 1), PipelineId::dummy(), pid),
     );
 
@@ -969,6 +1062,7 @@ fn test_cst_perspective_relative_scroll() {
         LayoutVector2D::new(0.0, 50.0),
         APZScrollGeneration::default(),
         HasScrollLinkedEffect::No,
+        ScrollPhysicsParams::default(),
         SpatialNodeUid::external(SpatialTreeItemKey::new(0, 3), PipelineId::dummy(), pid),
     );
 
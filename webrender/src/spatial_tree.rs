@@ -4,12 +4,14 @@
 
 use api::{ExternalScrollId, PropertyBinding, ReferenceFrameKind, TransformStyle, PropertyBindingId};
 use api::{APZScrollGeneration, HasScrollLinkedEffect, PipelineId, SampledScrollOffset, SpatialTreeItemKey};
+use api::{ScrollEventPhase, ScrollLocation};
 use api::units::*;
 use euclid::Transform3D;
 use crate::gpu_types::TransformPalette;
 use crate::internal_types::{FastHashMap, FastHashSet, PipelineInstanceId};
 use crate::print_tree::{PrintableTree, PrintTree, PrintTreePrinter};
 use crate::scene::SceneProperties;
+use crate::scroll_physics::ScrollPhysicsParams;
 use crate::spatial_node::{ReferenceFrameInfo, SpatialNode, SpatialNodeType, StickyFrameInfo, SpatialNodeDescriptor};
 use crate::spatial_node::{SpatialNodeUid, ScrollFrameKind, SceneSpatialNode, SpatialNodeInfo, SpatialNodeUidKind};
 use std::{ops, u32};
@@ -534,6 +536,7 @@ impl SceneSpatialTree {
         external_scroll_offset: LayoutVector2D,
         scroll_offset_generation: APZScrollGeneration,
         has_scroll_linked_effect: HasScrollLinkedEffect,
+        physics: ScrollPhysicsParams,
         uid: SpatialNodeUid,
     ) -> SpatialNodeIndex {
         // Scroll frames are only 2d translations - they can't introduce a new static coord system
@@ -549,6 +552,7 @@ impl SceneSpatialTree {
             external_scroll_offset,
             scroll_offset_generation,
             has_scroll_linked_effect,
+            physics,
             is_root_coord_system,
         );
         self.add_spatial_node(node, uid)
@@ -863,8 +867,15 @@ impl SpatialTree {
                     }
 
                     let node = &mut self.spatial_nodes[index];
+                    let mut node_type = descriptor.node_type;
 
-                    node.node_type = descriptor.node_type;
+                    // Keep any ongoing scroll motion of an updated scroll frame.
+                    if let (SpatialNodeType::ScrollFrame(old), SpatialNodeType::ScrollFrame(new)) =
+                        (&node.node_type, &mut node_type) {
+                        new.physics_state = old.physics_state.clone();
+                    }
+
+                    node.node_type = node_type;
                     node.pipeline_id = descriptor.pipeline_id;
                     node.parent = parent;
                 }
@@ -894,6 +905,7 @@ impl SpatialTree {
                         offset: -info.external_scroll_offset,
                         generation: info.offset_generation,
                     }];
+                    info.restore_physics_state();
                 }
                 SpatialNodeType::StickyFrame(ref mut info) => {
                     info.current_offset = LayoutVector2D::zero();
@@ -1090,6 +1102,55 @@ impl SpatialTree {
         did_change
     }
 
+    /// Apply a scroll event to the scroll frames with the given external id.
+    /// Returns true if any of them moved or started moving.
+    pub fn scroll(
+        &mut self,
+        id: ExternalScrollId,
+        location: ScrollLocation,
+        phase: ScrollEventPhase,
+        time: u64,
+    ) -> bool {
+        let mut did_change = false;
+
+        self.visit_nodes_mut(|_, node| {
+            if node.matches_external_id(id) {
+                if let SpatialNodeType::ScrollFrame(ref mut info) = node.node_type {
+                    did_change |= info.scroll(location, phase, time);
+                }
+            }
+        });
+
+        did_change
+    }
+
+    /// Advance the motion of scroll frames scrolled with scroll events.
+    /// Returns true if any of them moved.
+    pub fn sample_scroll_physics(&mut self, time: u64) -> bool {
+        let mut did_change = false;
+
+        self.visit_nodes_mut(|_, node| {
+            if let SpatialNodeType::ScrollFrame(ref mut info) = node.node_type {
+                did_change |= info.sample_physics(time);
+            }
+        });
+
+        did_change
+    }
+
+    /// Returns true if any scroll frame keeps moving after a scroll gesture.
+    pub fn is_scroll_physics_animating(&self) -> bool {
+        let mut is_animating = false;
+
+        self.visit_nodes(|_, node| {
+            if let SpatialNodeType::ScrollFrame(ref info) = node.node_type {
+                is_animating |= info.is_physics_animating();
+            }
+        });
+
+        is_animating
+    }
+
     pub fn update_tree(
         &mut self,
         scene_properties: &SceneProperties,
@@ -1685,6 +1746,7 @@ fn test_find_scroll_root_simple() {
         LayoutVector2D::new(0.0, 0.0),
         APZScrollGeneration::default(),
         HasScrollLinkedEffect::No,
+        ScrollPhysicsParams::default(),
         SpatialNodeUid::external(SpatialTreeItemKey::new(0, 1), PipelineId::dummy(), pid),
     );
 
@@ -1721,6 +1783,7 @@ fn test_find_scroll_root_sub_scroll_frame() {
         LayoutVector2D::new(0.0, 0.0),
         APZScrollGeneration::default(),
         HasScrollLinkedEffect::No,
+        ScrollPhysicsParams::default(),
         SpatialNodeUid::external(SpatialTreeItemKey::new(0, 1), PipelineId::dummy(), pid),
     );
 
@@ -1734,6 +1797,7 @@ fn test_find_scroll_root_sub_scroll_frame() {
         LayoutVector2D::new(0.0, 0.0),
         APZScrollGeneration::default(),
         HasScrollLinkedEffect::No,
+        ScrollPhysicsParams::default(),
         SpatialNodeUid::external(SpatialTreeItemKey::new(0, 2), PipelineId::dummy(), pid),
     );
 
@@ -1770,6 +1834,7 @@ fn test_find_scroll_root_not_scrollable() {
         LayoutVector2D::new(0.0, 0.0),
         APZScrollGeneration::default(),
         HasScrollLinkedEffect::No,
+        ScrollPhysicsParams::default(),
         SpatialNodeUid::external(SpatialTreeItemKey::new(0, 1), PipelineId::dummy(), pid),
     );
 
@@ -1783,6 +1848,7 @@ fn test_find_scroll_root_not_scrollable() {
         LayoutVector2D::new(0.0, 0.0),
         APZScrollGeneration::default(),
         HasScrollLinkedEffect::No,
+        ScrollPhysicsParams::default(),
         SpatialNodeUid::external(SpatialTreeItemKey::new(0, 2), PipelineId::dummy(), pid),
     );
 
@@ -1819,6 +1885,7 @@ fn test_find_scroll_root_too_small() {
         LayoutVector2D::new(0.0, 0.0),
         APZScrollGeneration::default(),
         HasScrollLinkedEffect::No,
+        ScrollPhysicsParams::default(),
         SpatialNodeUid::external(SpatialTreeItemKey::new(0, 1), PipelineId::dummy(), pid),
     );
 
@@ -1832,6 +1899,7 @@ fn test_find_scroll_root_too_small() {
         LayoutVector2D::new(0.0, 0.0),
         APZScrollGeneration::default(),
         HasScrollLinkedEffect::No,
+        ScrollPhysicsParams::default(),
         SpatialNodeUid::external(SpatialTreeItemKey::new(0, 2), PipelineId::dummy(), pid),
     );
 
@@ -1869,6 +1937,7 @@ fn test_find_scroll_root_perspective() {
         LayoutVector2D::new(0.0, 0.0),
         APZScrollGeneration::default(),
         HasScrollLinkedEffect::No,
+        ScrollPhysicsParams::default(),
         SpatialNodeUid::external(SpatialTreeItemKey::new(0, 1), PipelineId::dummy(), pid),
     );
 
@@ -1894,6 +1963,7 @@ fn test_find_scroll_root_perspective() {
         LayoutVector2D::new(0.0, 0.0),
         APZScrollGeneration::default(),
         HasScrollLinkedEffect::No,
+        ScrollPhysicsParams::default(),
         SpatialNodeUid::external(SpatialTreeItemKey::new(0, 3), PipelineId::dummy(), pid),
     );
 
@@ -1931,6 +2001,7 @@ fn test_find_scroll_root_2d_scale() {
         LayoutVector2D::new(0.0, 0.0),
         APZScrollGeneration::default(),
         HasScrollLinkedEffect::No,
+        ScrollPhysicsParams::default(),
         SpatialNodeUid::external(SpatialTreeItemKey::new(0, 1), PipelineId::dummy(), pid),
     );
 
@@ -1958,6 +2029,7 @@ fn test_find_scroll_root_2d_scale() {
         LayoutVector2D::new(0.0, 0.0),
         APZScrollGeneration::default(),
         HasScrollLinkedEffect::No,
+        ScrollPhysicsParams::default(),
         SpatialNodeUid::external(SpatialTreeItemKey::new(0, 3), PipelineId::dummy(), pid),
     );
 
@@ -11,6 +11,7 @@ use crate::device::{TextureFilter, TextureFormatPair};
 use crate::freelist::{FreeList, FreeListHandle, WeakFreeListHandle};
 use crate::gpu_cache::{GpuCache, GpuCacheHandle};
 use crate::gpu_types::{ImageSource, UvRectKind};
+use crate::image_tiling::align_rect_to_blocks;
 use crate::internal_types::{
     CacheTextureId, Swizzle, SwizzleSettings, FrameStamp, FrameId,
     TextureUpdateList, TextureUpdateSource, TextureSource,
@@ -268,11 +269,13 @@ enum BudgetType {
     SharedAlpha8,
     SharedAlpha8Glyphs,
     SharedAlpha16,
+    SharedColor16F,
+    SharedColor10,
     Standalone,
 }
 
 impl BudgetType {
-    pub const COUNT: usize = 7;
+    pub const COUNT: usize = 9;
 
     pub const VALUES: [BudgetType; BudgetType::COUNT] = [
         BudgetType::SharedColor8Linear,
@@ -281,6 +284,8 @@ impl BudgetType {
         BudgetType::SharedAlpha8,
         BudgetType::SharedAlpha8Glyphs,
         BudgetType::SharedAlpha16,
+        BudgetType::SharedColor16F,
+        BudgetType::SharedColor10,
         BudgetType::Standalone,
     ];
 
@@ -291,6 +296,8 @@ impl BudgetType {
         profiler::ATLAS_ALPHA8_PRESSURE,
         profiler::ATLAS_ALPHA8_GLYPHS_PRESSURE,
         profiler::ATLAS_ALPHA16_PRESSURE,
+        profiler::ATLAS_COLOR16F_PRESSURE,
+        profiler::ATLAS_COLOR10_PRESSURE,
         profiler::ATLAS_STANDALONE_PRESSURE,
     ];
 
@@ -310,6 +317,8 @@ struct SharedTextures {
     alpha16_linear: AllocatorList<ShelfAllocator, TextureParameters>,
     color8_linear: AllocatorList<ShelfAllocator, TextureParameters>,
     color8_glyphs: AllocatorList<ShelfAllocator, TextureParameters>,
+    color16f_linear: AllocatorList<ShelfAllocator, TextureParameters>,
+    color10_linear: AllocatorList<ShelfAllocator, TextureParameters>,
     bytes_per_texture_of_type: [i32 ; BudgetType::COUNT],
     next_compaction_idx: usize,
 }
@@ -425,6 +434,41 @@ impl SharedTextures {
             color_formats.internal.bytes_per_pixel() *
             config.color8_nearest_texture_size * config.color8_nearest_texture_size;
 
+        // Used for HDR and wide gamut images, which are uncommon. These are
+        // only sampled with linear filtering, other images get standalone
+        // textures.
+        let color16f_linear = AllocatorList::new(
+            config.color16f_texture_size,
+            ShelfAllocatorOptions {
+                num_columns: if config.color16f_texture_size >= 1024 { 2 } else { 1 },
+                alignment: size2(16, 16),
+                .. ShelfAllocatorOptions::default()
+            },
+            TextureParameters {
+                formats: TextureFormatPair::from(ImageFormat::RGBA16F),
+                filter: TextureFilter::Linear,
+            },
+        );
+        bytes_per_texture_of_type[BudgetType::SharedColor16F as usize] =
+            ImageFormat::RGBA16F.bytes_per_pixel() *
+            config.color16f_texture_size * config.color16f_texture_size;
+
+        let color10_linear = AllocatorList::new(
+            config.color10_texture_size,
+            ShelfAllocatorOptions {
+                num_columns: if config.color10_texture_size >= 1024 { 2 } else { 1 },
+                alignment: size2(16, 16),
+                .. ShelfAllocatorOptions::default()
+            },
+            TextureParameters {
+                formats: TextureFormatPair::from(ImageFormat::RGB10A2),
+                filter: TextureFilter::Linear,
+            },
+        );
+        bytes_per_texture_of_type[BudgetType::SharedColor10 as usize] =
+            ImageFormat::RGB10A2.bytes_per_pixel() *
+            config.color10_texture_size * config.color10_texture_size;
+
         Self {
             alpha8_linear,
             alpha8_glyphs,
@@ -432,6 +476,8 @@ impl SharedTextures {
             color8_linear,
             color8_glyphs,
             color8_nearest,
+            color16f_linear,
+            color10_linear,
             bytes_per_texture_of_type,
             next_compaction_idx: 0,
         }
@@ -449,6 +495,8 @@ impl SharedTextures {
         self.color8_linear.clear(texture_dealloc_cb);
         self.color8_nearest.clear(texture_dealloc_cb);
         self.color8_glyphs.clear(texture_dealloc_cb);
+        self.color16f_linear.clear(texture_dealloc_cb);
+        self.color10_linear.clear(texture_dealloc_cb);
     }
 
     /// Returns a mutable borrow for the shared texture array matching the parameters.
@@ -469,6 +517,14 @@ impl SharedTextures {
                 assert_eq!(filter, TextureFilter::Linear);
                 (&mut self.alpha16_linear, BudgetType::SharedAlpha16)
             }
+            ImageFormat::RGBA16F => {
+                assert_eq!(filter, TextureFilter::Linear);
+                (&mut self.color16f_linear, BudgetType::SharedColor16F)
+            }
+            ImageFormat::RGB10A2 => {
+                assert_eq!(filter, TextureFilter::Linear);
+                (&mut self.color10_linear, BudgetType::SharedColor10)
+            }
             ImageFormat::RGBA8 |
             ImageFormat::BGRA8 => {
                 match (filter, shader) {
@@ -502,6 +558,8 @@ impl SharedTextures {
             BudgetType::SharedAlpha8 => self.alpha8_linear.allocated_textures() > 1,
             BudgetType::SharedAlpha8Glyphs => self.alpha8_glyphs.allocated_textures() > 1,
             BudgetType::SharedAlpha16 => self.alpha16_linear.allocated_textures() > 1,
+            BudgetType::SharedColor16F => self.color16f_linear.allocated_textures() > 1,
+            BudgetType::SharedColor10 => self.color10_linear.allocated_textures() > 1,
             BudgetType::Standalone => false,
         }
     }
@@ -527,6 +585,8 @@ pub struct TextureCacheConfig {
     pub alpha8_texture_size: i32,
     pub alpha8_glyph_texture_size: i32,
     pub alpha16_texture_size: i32,
+    pub color16f_texture_size: i32,
+    pub color10_texture_size: i32,
 }
 
 impl TextureCacheConfig {
@@ -537,6 +597,8 @@ impl TextureCacheConfig {
         alpha8_texture_size: 1024,
         alpha8_glyph_texture_size: 2048,
         alpha16_texture_size: 512,
+        color16f_texture_size: 1024,
+        color10_texture_size: 1024,
     };
 }
 
@@ -722,6 +784,8 @@ impl TextureCache {
         self.shared_textures.color8_linear.release_empty_textures(callback);
         self.shared_textures.color8_nearest.release_empty_textures(callback);
         self.shared_textures.color8_glyphs.release_empty_textures(callback);
+        self.shared_textures.color16f_linear.release_empty_textures(callback);
+        self.shared_textures.color10_linear.release_empty_textures(callback);
 
         for budget in BudgetType::iter() {
             let threshold = self.get_eviction_threshold(budget);
@@ -741,6 +805,10 @@ impl TextureCache {
         profile.set(profiler::ATLAS_RGBA8_NEAREST_TEXTURES, self.shared_textures.color8_nearest.allocated_textures());
         profile.set(profiler::ATLAS_RGBA8_GLYPHS_PIXELS, self.shared_textures.color8_glyphs.allocated_space());
         profile.set(profiler::ATLAS_RGBA8_GLYPHS_TEXTURES, self.shared_textures.color8_glyphs.allocated_textures());
+        profile.set(profiler::ATLAS_RGBA16F_PIXELS, self.shared_textures.color16f_linear.allocated_space());
+        profile.set(profiler::ATLAS_RGBA16F_TEXTURES, self.shared_textures.color16f_linear.allocated_textures());
+        profile.set(profiler::ATLAS_RGB10A2_PIXELS, self.shared_textures.color10_linear.allocated_space());
+        profile.set(profiler::ATLAS_RGB10A2_TEXTURES, self.shared_textures.color10_linear.allocated_textures());
 
         let shared_bytes = [
             BudgetType::SharedColor8Linear,
@@ -749,6 +817,8 @@ impl TextureCache {
             BudgetType::SharedAlpha8,
             BudgetType::SharedAlpha8Glyphs,
             BudgetType::SharedAlpha16,
+            BudgetType::SharedColor16F,
+            BudgetType::SharedColor10,
         ].iter().map(|b| self.bytes_allocated[*b as usize]).sum();
 
         profile.set(profiler::ATLAS_ITEMS_MEM, profiler::bytes_to_mb(shared_bytes));
@@ -766,6 +836,8 @@ impl TextureCache {
             &mut self.shared_textures.alpha8_linear,
             &mut self.shared_textures.alpha8_glyphs,
             &mut self.shared_textures.alpha16_linear,
+            &mut self.shared_textures.color16f_linear,
+            &mut self.shared_textures.color10_linear,
         ];
 
         // Pick a texture type on which to try to run the compaction logic this frame.
@@ -994,7 +1066,8 @@ impl TextureCache {
     // or otherwise indicate the handle is invalid.
     pub fn get_allocated_size(&self, handle: &TextureCacheHandle) -> Option<usize> {
         self.get_entry_opt(handle).map(|entry| {
-            (entry.input_format.bytes_per_pixel() * entry.size.area()) as usize
+            let format = entry.input_format;
+            (format.bytes_per_block_row(entry.size.width) * format.block_rows(entry.size.height)) as usize
         })
     }
 
@@ -1360,6 +1433,11 @@ impl TextureCache {
         filter: TextureFilter,
         descriptor: &ImageDescriptor,
     ) -> bool {
+        // The shared textures can't hold block-compressed data.
+        if descriptor.format.is_compressed() {
+            return false;
+        }
+
         let mut allowed_in_shared_cache = true;
 
         if matches!(descriptor.format, ImageFormat::RGBA8 | ImageFormat::BGRA8)
@@ -1376,11 +1454,11 @@ impl TextureCache {
             allowed_in_shared_cache = false;
         }
 
-        // TODO(gw): For now, alpha formats of the texture cache can only be linearly sampled.
+        // TODO(gw): For now, alpha and high bit depth formats of the texture cache can only be linearly sampled.
         //           Nearest sampling gets a standalone texture.
         //           This is probably rare enough that it can be fixed up later.
         if filter == TextureFilter::Nearest &&
-           descriptor.format.bytes_per_pixel() <= 2
+           (descriptor.format.bytes_per_pixel() <= 2 || descriptor.format.is_high_bit_depth_color())
         {
             allowed_in_shared_cache = false;
         }
@@ -1442,7 +1520,8 @@ impl TextureCache {
             category: TextureCacheCategory::Standalone,
         };
 
-        let size_in_bytes = (info.width * info.height * info.format.bytes_per_pixel()) as usize;
+        let size_in_bytes = (info.format.bytes_per_block_row(info.width) *
+            info.format.block_rows(info.height)) as usize;
         self.bytes_allocated[BudgetType::Standalone as usize] += size_in_bytes;
 
         self.pending_updates.push_alloc(texture_id, info);
@@ -1587,9 +1666,10 @@ impl TextureCacheUpdate {
                 },
             },
             CachedImageData::Raw(bytes) => {
+                let format = descriptor.format;
                 let finish = descriptor.offset +
-                    descriptor.size.width * descriptor.format.bytes_per_pixel() +
-                    (descriptor.size.height - 1) * descriptor.compute_stride();
+                    format.bytes_per_block_row(descriptor.size.width) +
+                    (format.block_rows(descriptor.size.height) - 1) * descriptor.compute_stride();
                 assert!(bytes.len() >= finish as usize);
 
                 TextureUpdateSource::Bytes { data: bytes }
@@ -1604,8 +1684,12 @@ impl TextureCacheUpdate {
         match *dirty_rect {
             DirtyRect::Partial(dirty) => {
                 // the dirty rectangle doesn't have to be within the area but has to intersect it, at least
+                let dirty = align_rect_to_blocks(dirty, descriptor.format);
                 let stride = descriptor.compute_stride();
-                let offset = descriptor.offset + dirty.min.y * stride + dirty.min.x * descriptor.format.bytes_per_pixel();
+                let block_size = descriptor.format.block_size();
+                let offset = descriptor.offset +
+                    dirty.min.y / block_size * stride +
+                    dirty.min.x / block_size * descriptor.format.bytes_per_block();
 
                 TextureCacheUpdate {
                     rect: DeviceIntRect::from_origin_and_size(
@@ -1646,7 +1730,7 @@ mod test_texture_cache {
         use crate::gpu_cache::GpuCache;
         use crate::device::TextureFilter;
         use crate::gpu_types::UvRectKind;
-        use api::{ImageDescriptor, ImageDescriptorFlags, ImageFormat, DirtyRect};
+        use api::{ImageDescriptor, ImageDescriptorFlags, ImageFormat, DirtyRect, RgbColorSpace};
         use api::units::*;
         use euclid::size2;
         let mut texture_cache = TextureCache::new_for_testing(2048, ImageFormat::BGRA8);
@@ -1680,6 +1764,7 @@ mod test_texture_cache {
                     format: ImageFormat::BGRA8,
                     flags: ImageDescriptorFlags::empty(),
                     offset: 0,
+                    color_space: RgbColorSpace::Srgb,
                 },
                 TextureFilter::Linear,
                 None,
@@ -1704,4 +1789,42 @@ mod test_texture_cache {
         let bytes_at_end = texture_cache.total_allocated_bytes_for_testing();
         assert_eq!(bytes_at_end, bytes_at_start);
     }
+
+    #[test]
+    fn high_bit_depth_atlases() {
+        use crate::texture_cache::{TextureCache, TextureCacheHandle, Eviction, TargetShader};
+        use crate::gpu_cache::GpuCache;
+        use crate::device::TextureFilter;
+        use crate::gpu_types::UvRectKind;
+        use api::{ImageDescriptor, ImageDescriptorFlags, ImageFormat, DirtyRect};
+
+        let mut texture_cache = TextureCache::new_for_testing(2048, ImageFormat::RGBA8);
+        let mut gpu_cache = GpuCache::new_for_testing();
+
+        for &format in &[ImageFormat::RGBA16F, ImageFormat::RGB10A2] {
+            let descriptor = ImageDescriptor::new(64, 64, format, ImageDescriptorFlags::empty());
+            assert!(texture_cache.is_allowed_in_shared_cache(TextureFilter::Linear, &descriptor));
+            assert!(!texture_cache.is_allowed_in_shared_cache(TextureFilter::Nearest, &descriptor));
+
+            let mut handle = TextureCacheHandle::invalid();
+            texture_cache.request(&handle, &mut gpu_cache);
+            texture_cache.update(
+                &mut handle,
+                descriptor,
+                TextureFilter::Linear,
+                None,
+                [0.0; 4],
+                DirtyRect::All,
+                &mut gpu_cache,
+                None,
+                UvRectKind::Rect,
+                Eviction::Manual,
+                TargetShader::Default,
+            );
+        }
+
+        assert_eq!(texture_cache.shared_textures.color16f_linear.allocated_textures(), 1);
+        assert_eq!(texture_cache.shared_textures.color10_linear.allocated_textures(), 1);
+        assert_eq!(texture_cache.shared_textures.color8_linear.allocated_textures(), 0);
+    }
 }
@@ -7,7 +7,7 @@
 //! TODO: document what this pass does!
 //!
 
-use api::{DebugFlags};
+use api::{DebugFlags, RgbColorSpace};
 use api::units::*;
 use std::{usize};
 use crate::clip::{ClipStore, ClipChainStack};
@@ -34,6 +34,8 @@ pub struct FrameVisibilityContext<'a> {
     pub scene_properties: &'a SceneProperties,
     pub config: FrameBuilderConfig,
     pub root_spatial_node_index: SpatialNodeIndex,
+    /// The color space that the document is composited in.
+    pub output_color_space: RgbColorSpace,
 }
 
 pub struct FrameVisibilityState<'a> {
@@ -309,6 +311,7 @@ pub fn update_prim_visibility(
                     device_pixel_scale,
                     &world_culling_rect,
                     &mut frame_state.data_stores.clip,
+                    &frame_state.data_stores.polygon,
                     true,
                     prim_instance.is_chased(),
                 );
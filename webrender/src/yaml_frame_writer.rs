@@ -0,0 +1,1541 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Serializes the display lists of a scene into the YAML format read by wrench.
+//!
+//! Captures taken with `CaptureBits::YAML` get a `yaml` directory holding a
+//! `scene-{namespace}-{id}.yaml` file per document, along with the images and
+//! fonts it refers to, so that it can be replayed with `wrench show` or copied
+//! into the reftests as it is.
+//!
+//! The display list builder bakes the stacking context offsets and external
+//! scroll offsets into clips, spatial nodes and iframes, and wrench applies them
+//! again when it rebuilds the display list. The writer tracks the same offsets
+//! as the builder and takes them back out of those items. Scroll and sticky
+//! frames are not part of the item stream, so they are written at the start of
+//! the root and of each reference frame, once their parent has been defined.
+//!
+//! Whatever the YAML format can't express (animated values, scroll physics,
+//! the contents of blob and external images, ...) is written in its static form
+//! or dropped with a warning.
+
+use api::{BorderDetails, BorderRadius, BorderStyle, BoxShadowClipMode, ClipId, ClipMode, ColorF};
+use api::{ColorChannel, ColorSpace, CommonItemProperties, CompositeOperator, DisplayItem, DisplayItemRef};
+use api::{EdgeMode, ExtendMode, FillRule, FilterOp, FilterPrimitive, FilterPrimitiveInput};
+use api::{FilterPrimitiveKind, FontInstanceFlags, FontInstanceKey, FontKey, FontTemplate};
+use api::{GlyphOrientation, GradientColorSpace, GradientInterpolation, GradientStop, HasScrollLinkedEffect};
+use api::{HueInterpolationMethod, ImageKey, ImageRendering, ItemRange, LightSource};
+use api::{LineCap, LineJoin, LineOrientation, LineStyle, MixBlendMode, MorphologyOperator};
+use api::{NinePatchBorderSource, PathSegment, PipelineId, PrimitiveFlags, PropertyBinding, RasterSpace};
+use api::{ReferenceFrameDescriptor, ReferenceFrameKind, ReferenceFrameMapper, ReferenceTransformBinding};
+use api::{RepeatMode, RgbColorSpace, SpatialId, SpatialTreeItem, StackingContextFlags, StrokeStyle};
+use api::{TempFilterData, TransformStyle, TurbulenceNoiseType, YuvData, AlphaType, ComponentTransferFuncType};
+use api::{HitTestShape, SpaceAndClipInfo, TextDecoration, TextDecorationLines};
+use api::units::*;
+use crate::glyph_rasterizer::SharedFontResources;
+use crate::internal_types::{FastHashMap, FastHashSet};
+use crate::scene::{Scene, ScenePipeline};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+/// Numeric ids of clips start here, so that they don't collide with spatial
+/// ids: wrench registers the id of a clip for both.
+const CLIP_ID_BASE: usize = 1 << 24;
+/// Numeric ids of clip chains start here.
+const CLIP_CHAIN_ID_BASE: u64 = 1 << 32;
+/// Non-finite values are clamped to this, since wrench only parses finite numbers.
+const LARGE_VALUE: f32 = 1.0e30;
+
+/// The name of the file an image template is saved to, relative to the YAML file.
+pub fn image_file_name(key: ImageKey) -> String {
+    format!("image-{}-{}.png", (key.0).0, key.1)
+}
+
+fn font_file_name(key: FontKey) -> String {
+    format!("font-{}-{}.raw", (key.0).0, key.1)
+}
+
+/// Saves an image template as a PNG that wrench can load. Color images are
+/// converted to unpremultiplied RGBA, since wrench premultiplies them when
+/// loading. Formats wrench can't load, and images without data, are replaced
+/// by a magenta placeholder of the same size.
+#[cfg(feature = "png")]
+pub fn save_image(path: &std::path::Path, descriptor: &api::ImageDescriptor, data: Option<&[u8]>) {
+    use api::ImageFormat;
+    use png::{BitDepth, ColorType, Encoder};
+    use std::fs::File;
+    use std::io::BufWriter;
+
+    let width = descriptor.size.width.max(0) as usize;
+    let height = descriptor.size.height.max(0) as usize;
+    let format = descriptor.format;
+
+    let converted = data.and_then(|data| {
+        let bpp = match format {
+            ImageFormat::BGRA8 | ImageFormat::RGBA8 | ImageFormat::R8 => format.bytes_per_pixel() as usize,
+            _ => return None,
+        };
+        let stride = descriptor.compute_stride() as usize;
+        let offset = descriptor.offset as usize;
+        if height > 0 && data.len() < offset + (height - 1) * stride + width * bpp {
+            return None;
+        }
+
+        let mut pixels = Vec::with_capacity(width * height * bpp);
+        for y in 0 .. height {
+            let row = &data[offset + y * stride ..][.. width * bpp];
+            match format {
+                ImageFormat::R8 => pixels.extend_from_slice(row),
+                _ => {
+                    for texel in row.chunks(4) {
+                        let (r, g, b, a) = match format {
+                            ImageFormat::BGRA8 => (texel[2], texel[1], texel[0], texel[3]),
+                            _ => (texel[0], texel[1], texel[2], texel[3]),
+                        };
+                        let unpremultiply = |c: u8| match a {
+                            0 => 0,
+                            _ => ((c as u32 * 255 + a as u32 / 2) / a as u32).min(255) as u8,
+                        };
+                        pixels.extend_from_slice(&[unpremultiply(r), unpremultiply(g), unpremultiply(b), a]);
+                    }
+                }
+            }
+        }
+
+        let color_type = match format {
+            ImageFormat::R8 => ColorType::Grayscale,
+            _ => ColorType::RGBA,
+        };
+        Some((color_type, pixels))
+    });
+
+    let (color_type, pixels) = match converted {
+        Some(converted) => converted,
+        None => {
+            warn!("Saving a placeholder for {:?} image {}", format, path.display());
+            let pixels = [255, 0, 255, 255].iter().cloned().cycle().take(width * height * 4).collect();
+            (ColorType::RGBA, pixels)
+        }
+    };
+
+    let file = match File::create(path) {
+        Ok(file) => file,
+        Err(e) => {
+            warn!("Unable to create {}: {:?}", path.display(), e);
+            return;
+        }
+    };
+    let mut encoder = Encoder::new(BufWriter::new(file), width as u32, height as u32);
+    encoder.set_color(color_type);
+    encoder.set_depth(BitDepth::Eight);
+    let result = encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&pixels));
+    if let Err(e) = result {
+        warn!("Unable to save {}: {:?}", path.display(), e);
+    }
+}
+
+fn f32_str(value: f32) -> String {
+    if value.is_nan() {
+        return "0".to_string();
+    }
+    format!("{}", value.max(-LARGE_VALUE).min(LARGE_VALUE))
+}
+
+fn f32_vec_str<I: IntoIterator<Item = f32>>(values: I) -> String {
+    let values: Vec<String> = values.into_iter().map(f32_str).collect();
+    format!("[{}]", values.join(", "))
+}
+
+fn point_str(point: LayoutPoint) -> String {
+    f32_vec_str([point.x, point.y].iter().cloned())
+}
+
+fn vector_str(vector: LayoutVector2D) -> String {
+    f32_vec_str([vector.x, vector.y].iter().cloned())
+}
+
+fn size_str(size: LayoutSize) -> String {
+    f32_vec_str([size.width, size.height].iter().cloned())
+}
+
+fn rect_str(rect: &LayoutRect) -> String {
+    f32_vec_str([rect.min.x, rect.min.y, rect.width(), rect.height()].iter().cloned())
+}
+
+/// Wrench colors have r, g, b in [0, 255] and alpha in [0, 1].
+fn color_str(color: ColorF) -> String {
+    let channel = |c: f32| (c * 255.0).max(0.0).min(255.0);
+    f32_vec_str([
+        channel(color.r),
+        channel(color.g),
+        channel(color.b),
+        color.a.max(0.0).min(1.0),
+    ].iter().cloned())
+}
+
+fn radius_str(radius: &BorderRadius) -> String {
+    format!(
+        "{{top-left: {}, top-right: {}, bottom-left: {}, bottom-right: {}}}",
+        size_str(radius.top_left),
+        size_str(radius.top_right),
+        size_str(radius.bottom_left),
+        size_str(radius.bottom_right),
+    )
+}
+
+fn quoted_str(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+fn pipeline_id_str(id: PipelineId) -> String {
+    format!("[{}, {}]", id.0, id.1)
+}
+
+fn spatial_id_str(id: SpatialId) -> String {
+    match id.0 {
+        0 => "root-reference-frame".to_string(),
+        1 => "root-scroll-node".to_string(),
+        index => index.to_string(),
+    }
+}
+
+fn clip_id_str(id: ClipId) -> String {
+    match id {
+        ClipId::Clip(..) if id.is_root() || !id.is_valid() => "root_clip".to_string(),
+        ClipId::Clip(index, _) => (CLIP_ID_BASE + index).to_string(),
+        ClipId::ClipChain(chain_id) => (CLIP_CHAIN_ID_BASE + chain_id.0).to_string(),
+    }
+}
+
+fn binding_value<T: Copy>(binding: &PropertyBinding<T>) -> T {
+    match *binding {
+        PropertyBinding::Value(value) |
+        PropertyBinding::Binding(_, value) => value,
+    }
+}
+
+fn rgb_color_space_name(color_space: RgbColorSpace) -> &'static str {
+    match color_space {
+        RgbColorSpace::Srgb => "srgb",
+        RgbColorSpace::DisplayP3 => "display-p3",
+        RgbColorSpace::Rec2020 => "rec2020",
+        RgbColorSpace::LinearSrgb => "linear-srgb",
+    }
+}
+
+fn line_style_name(style: LineStyle) -> &'static str {
+    match style {
+        LineStyle::Solid => "solid",
+        LineStyle::Dotted => "dotted",
+        LineStyle::Dashed => "dashed",
+        LineStyle::Wavy => "wavy",
+    }
+}
+
+fn text_decoration_str(decoration: &TextDecoration) -> String {
+    let mut lines = Vec::new();
+    for (line, name) in [
+        (TextDecorationLines::UNDERLINE, "underline"),
+        (TextDecorationLines::OVERLINE, "overline"),
+        (TextDecorationLines::LINE_THROUGH, "line-through"),
+    ] {
+        if decoration.lines.contains(line) {
+            lines.push(name);
+        }
+    }
+    format!(
+        "{{lines: [{}], style: {}, color: {}, thickness: {}, underline-offset: {}, overline-offset: {}, line-through-offset: {}, skip-ink: {}}}",
+        lines.join(", "),
+        line_style_name(decoration.style),
+        color_str(decoration.color),
+        f32_str(decoration.thickness),
+        f32_str(decoration.underline_offset),
+        f32_str(decoration.overline_offset),
+        f32_str(decoration.line_through_offset),
+        decoration.skip_ink,
+    )
+}
+
+fn fill_rule_name(fill_rule: FillRule) -> &'static str {
+    match fill_rule {
+        FillRule::Nonzero => "nonzero",
+        FillRule::Evenodd => "evenodd",
+    }
+}
+
+fn clip_mode_name(mode: ClipMode) -> &'static str {
+    match mode {
+        ClipMode::Clip => "clip",
+        ClipMode::ClipOut => "clip-out",
+    }
+}
+
+fn image_rendering_name(rendering: ImageRendering) -> &'static str {
+    match rendering {
+        ImageRendering::Auto => "auto",
+        ImageRendering::CrispEdges => "crisp-edges",
+        ImageRendering::Pixelated => "pixelated",
+        ImageRendering::HighQuality => "high-quality",
+    }
+}
+
+fn repeat_mode_name(mode: RepeatMode) -> &'static str {
+    match mode {
+        RepeatMode::Stretch => "stretch",
+        RepeatMode::Repeat => "repeat",
+        RepeatMode::Round => "round",
+        RepeatMode::Space => "space",
+    }
+}
+
+fn border_style_name(style: BorderStyle) -> &'static str {
+    match style {
+        BorderStyle::None => "none",
+        BorderStyle::Solid => "solid",
+        BorderStyle::Double => "double",
+        BorderStyle::Dotted => "dotted",
+        BorderStyle::Dashed => "dashed",
+        BorderStyle::Hidden => "hidden",
+        BorderStyle::Groove => "groove",
+        BorderStyle::Ridge => "ridge",
+        BorderStyle::Inset => "inset",
+        BorderStyle::Outset => "outset",
+    }
+}
+
+fn mix_blend_mode_name(mode: MixBlendMode) -> &'static str {
+    match mode {
+        MixBlendMode::Normal => "normal",
+        MixBlendMode::Multiply => "multiply",
+        MixBlendMode::Screen => "screen",
+        MixBlendMode::Overlay => "overlay",
+        MixBlendMode::Darken => "darken",
+        MixBlendMode::Lighten => "lighten",
+        MixBlendMode::ColorDodge => "color-dodge",
+        MixBlendMode::ColorBurn => "color-burn",
+        MixBlendMode::HardLight => "hard-light",
+        MixBlendMode::SoftLight => "soft-light",
+        MixBlendMode::Difference => "difference",
+        MixBlendMode::Exclusion => "exclusion",
+        MixBlendMode::Hue => "hue",
+        MixBlendMode::Saturation => "saturation",
+        MixBlendMode::Color => "color",
+        MixBlendMode::Luminosity => "luminosity",
+        MixBlendMode::PlusLighter => "plus-lighter",
+    }
+}
+
+fn func_type_name(func_type: ComponentTransferFuncType) -> &'static str {
+    match func_type {
+        ComponentTransferFuncType::Identity => "Identity",
+        ComponentTransferFuncType::Table => "Table",
+        ComponentTransferFuncType::Discrete => "Discrete",
+        ComponentTransferFuncType::Linear => "Linear",
+        ComponentTransferFuncType::Gamma => "Gamma",
+    }
+}
+
+fn color_channel_name(channel: ColorChannel) -> &'static str {
+    match channel {
+        ColorChannel::R => "r",
+        ColorChannel::G => "g",
+        ColorChannel::B => "b",
+        ColorChannel::A => "a",
+    }
+}
+
+fn filter_op_str(op: &FilterOp) -> String {
+    let s = match *op {
+        FilterOp::Identity => "identity".to_string(),
+        FilterOp::ComponentTransfer => "component-transfer".to_string(),
+        FilterOp::Blur(ref width, ref height) => format!(
+            "blur({}, {})",
+            f32_str(binding_value(width)),
+            f32_str(binding_value(height)),
+        ),
+        FilterOp::Brightness(ref value) => format!("brightness({})", f32_str(binding_value(value))),
+        FilterOp::Contrast(ref value) => format!("contrast({})", f32_str(binding_value(value))),
+        FilterOp::Grayscale(ref value) => format!("grayscale({})", f32_str(binding_value(value))),
+        FilterOp::HueRotate(ref value) => format!("hue-rotate({})", f32_str(binding_value(value))),
+        FilterOp::Invert(ref value) => format!("invert({})", f32_str(binding_value(value))),
+        FilterOp::Opacity(ref value, _) => format!("opacity({})", f32_str(binding_value(value))),
+        FilterOp::Saturate(ref value) => format!("saturate({})", f32_str(binding_value(value))),
+        FilterOp::Sepia(ref value) => format!("sepia({})", f32_str(binding_value(value))),
+        FilterOp::SrgbToLinear => "srgb-to-linear".to_string(),
+        FilterOp::LinearToSrgb => "linear-to-srgb".to_string(),
+        FilterOp::DropShadow(ref shadow) => format!(
+            "drop-shadow({}, {}, {})",
+            vector_str(shadow.offset),
+            f32_str(shadow.blur_radius),
+            color_str(shadow.color),
+        ),
+        FilterOp::ColorMatrix(ref matrix) => {
+            let values: Vec<String> = matrix.iter().cloned().map(f32_str).collect();
+            format!("color-matrix({})", values.join(", "))
+        }
+        FilterOp::Flood(color) => format!("flood({})", color_str(color)),
+    };
+    quoted_str(&s)
+}
+
+fn filter_data_str(data: &TempFilterData) -> String {
+    let func_types: Vec<&str> = data.func_types.iter().map(func_type_name).collect();
+    format!(
+        "[[{}], {}, {}, {}, {}]",
+        func_types.join(", "),
+        f32_vec_str(data.r_values.iter()),
+        f32_vec_str(data.g_values.iter()),
+        f32_vec_str(data.b_values.iter()),
+        f32_vec_str(data.a_values.iter()),
+    )
+}
+
+fn filter_input_str(input: FilterPrimitiveInput) -> String {
+    match input {
+        FilterPrimitiveInput::Original => "original".to_string(),
+        FilterPrimitiveInput::Previous => "previous".to_string(),
+        FilterPrimitiveInput::OutputOfPrimitiveIndex(index) => index.to_string(),
+    }
+}
+
+fn light_source_str(light: &LightSource) -> String {
+    match *light {
+        LightSource::Distant(ref light) => format!(
+            "{{type: distant, azimuth: {}, elevation: {}}}",
+            f32_str(light.azimuth),
+            f32_str(light.elevation),
+        ),
+        LightSource::Point(ref light) => format!(
+            "{{type: point, position: {}}}",
+            f32_vec_str([light.x, light.y, light.z].iter().cloned()),
+        ),
+        LightSource::Spot(ref light) => format!(
+            "{{type: spot, position: {}, points-at: {}, specular-exponent: {}, limiting-cone-angle: {}}}",
+            f32_vec_str([light.x, light.y, light.z].iter().cloned()),
+            f32_vec_str([light.points_at_x, light.points_at_y, light.points_at_z].iter().cloned()),
+            f32_str(light.specular_exponent),
+            f32_str(light.limiting_cone_angle),
+        ),
+    }
+}
+
+fn filter_primitive_str(primitive: &FilterPrimitive) -> String {
+    fn input(fields: &mut Vec<(&'static str, String)>, key: &'static str, input: FilterPrimitiveInput) {
+        fields.push((key, filter_input_str(input)));
+    }
+
+    let mut fields = Vec::new();
+    match primitive.kind {
+        FilterPrimitiveKind::Identity(ref p) => {
+            fields.push(("type", "identity".to_string()));
+            input(&mut fields, "in", p.input);
+        }
+        FilterPrimitiveKind::Blend(ref p) => {
+            fields.push(("type", "blend".to_string()));
+            input(&mut fields, "in1", p.input1);
+            input(&mut fields, "in2", p.input2);
+            fields.push(("blend-mode", mix_blend_mode_name(p.mode).to_string()));
+        }
+        FilterPrimitiveKind::Flood(ref p) => {
+            fields.push(("type", "flood".to_string()));
+            fields.push(("color", color_str(p.color)));
+        }
+        FilterPrimitiveKind::Blur(ref p) => {
+            fields.push(("type", "blur".to_string()));
+            input(&mut fields, "in", p.input);
+            fields.push(("width", f32_str(p.width)));
+            fields.push(("height", f32_str(p.height)));
+        }
+        FilterPrimitiveKind::Opacity(ref p) => {
+            fields.push(("type", "opacity".to_string()));
+            input(&mut fields, "in", p.input);
+            fields.push(("opacity", f32_str(p.opacity)));
+        }
+        FilterPrimitiveKind::ColorMatrix(ref p) => {
+            fields.push(("type", "color-matrix".to_string()));
+            input(&mut fields, "in", p.input);
+            fields.push(("matrix", f32_vec_str(p.matrix.iter().cloned())));
+        }
+        FilterPrimitiveKind::DropShadow(ref p) => {
+            fields.push(("type", "drop-shadow".to_string()));
+            input(&mut fields, "in", p.input);
+            fields.push(("offset", vector_str(p.shadow.offset)));
+            fields.push(("color", color_str(p.shadow.color)));
+            fields.push(("radius", f32_str(p.shadow.blur_radius)));
+        }
+        FilterPrimitiveKind::ComponentTransfer(ref p) => {
+            fields.push(("type", "component-transfer".to_string()));
+            input(&mut fields, "in", p.input);
+        }
+        FilterPrimitiveKind::Offset(ref p) => {
+            fields.push(("type", "offset".to_string()));
+            input(&mut fields, "in", p.input);
+            fields.push(("offset", vector_str(p.offset)));
+        }
+        FilterPrimitiveKind::Composite(ref p) => {
+            fields.push(("type", "composite".to_string()));
+            input(&mut fields, "in1", p.input1);
+            input(&mut fields, "in2", p.input2);
+            let operator = match p.operator {
+                CompositeOperator::Over => "over",
+                CompositeOperator::In => "in",
+                CompositeOperator::Out => "out",
+                CompositeOperator::Atop => "atop",
+                CompositeOperator::Xor => "xor",
+                CompositeOperator::Lighter => "lighter",
+                CompositeOperator::Arithmetic(ref k) => {
+                    fields.push(("k-values", f32_vec_str(k.iter().cloned())));
+                    "arithmetic"
+                }
+            };
+            fields.push(("operator", operator.to_string()));
+        }
+        FilterPrimitiveKind::Turbulence(ref p) => {
+            fields.push(("type", "turbulence".to_string()));
+            fields.push(("base-frequency", f32_vec_str([p.base_frequency_x, p.base_frequency_y].iter().cloned())));
+            fields.push(("num-octaves", p.num_octaves.to_string()));
+            fields.push(("seed", p.seed.to_string()));
+            if p.stitch_tiles {
+                fields.push(("stitch-tiles", rect_str(&p.stitch_rect)));
+            }
+            let noise_type = match p.noise_type {
+                TurbulenceNoiseType::FractalNoise => "fractal-noise",
+                TurbulenceNoiseType::Turbulence => "turbulence",
+            };
+            fields.push(("noise-type", noise_type.to_string()));
+        }
+        FilterPrimitiveKind::Morphology(ref p) => {
+            fields.push(("type", "morphology".to_string()));
+            input(&mut fields, "in", p.input);
+            let operator = match p.operator {
+                MorphologyOperator::Erode => "erode",
+                MorphologyOperator::Dilate => "dilate",
+            };
+            fields.push(("operator", operator.to_string()));
+            fields.push(("radius", f32_vec_str([p.radius_x, p.radius_y].iter().cloned())));
+        }
+        FilterPrimitiveKind::ConvolveMatrix(ref p) => {
+            fields.push(("type", "convolve-matrix".to_string()));
+            input(&mut fields, "in", p.input);
+            fields.push(("order", format!("[{}, {}]", p.order_x, p.order_y)));
+            let kernel_size = (p.order_x * p.order_y) as usize;
+            fields.push(("kernel", f32_vec_str(p.kernel.iter().cloned().take(kernel_size))));
+            fields.push(("divisor", f32_str(p.divisor)));
+            fields.push(("bias", f32_str(p.bias)));
+            fields.push(("target", format!("[{}, {}]", p.target_x, p.target_y)));
+            let edge_mode = match p.edge_mode {
+                EdgeMode::Duplicate => "duplicate",
+                EdgeMode::Wrap => "wrap",
+                EdgeMode::None => "none",
+            };
+            fields.push(("edge-mode", edge_mode.to_string()));
+            fields.push(("preserve-alpha", p.preserve_alpha.to_string()));
+        }
+        FilterPrimitiveKind::DisplacementMap(ref p) => {
+            fields.push(("type", "displacement-map".to_string()));
+            input(&mut fields, "in1", p.input1);
+            input(&mut fields, "in2", p.input2);
+            fields.push(("scale", f32_str(p.scale)));
+            fields.push(("x-channel", color_channel_name(p.x_channel).to_string()));
+            fields.push(("y-channel", color_channel_name(p.y_channel).to_string()));
+        }
+        FilterPrimitiveKind::DiffuseLighting(ref p) => {
+            fields.push(("type", "diffuse-lighting".to_string()));
+            input(&mut fields, "in", p.input);
+            fields.push(("surface-scale", f32_str(p.surface_scale)));
+            fields.push(("diffuse-constant", f32_str(p.diffuse_constant)));
+            fields.push(("lighting-color", color_str(p.lighting_color)));
+            fields.push(("light", light_source_str(&p.light)));
+        }
+        FilterPrimitiveKind::SpecularLighting(ref p) => {
+            fields.push(("type", "specular-lighting".to_string()));
+            input(&mut fields, "in", p.input);
+            fields.push(("surface-scale", f32_str(p.surface_scale)));
+            fields.push(("specular-constant", f32_str(p.specular_constant)));
+            fields.push(("specular-exponent", f32_str(p.specular_exponent)));
+            fields.push(("lighting-color", color_str(p.lighting_color)));
+            fields.push(("light", light_source_str(&p.light)));
+        }
+        FilterPrimitiveKind::Tile(ref p) => {
+            fields.push(("type", "tile".to_string()));
+            input(&mut fields, "in", p.input);
+            fields.push(("source-rect", rect_str(&p.source_rect)));
+        }
+        FilterPrimitiveKind::Image(ref p) => {
+            fields.push(("type", "image".to_string()));
+            fields.push(("src", quoted_str(&image_file_name(p.image_key))));
+            fields.push(("rendering", image_rendering_name(p.rendering).to_string()));
+            fields.push(("bounds", rect_str(&p.rect)));
+        }
+        FilterPrimitiveKind::Merge(ref p) => {
+            fields.push(("type", "merge".to_string()));
+            let inputs: Vec<String> = p.inputs().iter().cloned().map(filter_input_str).collect();
+            fields.push(("inputs", format!("[{}]", inputs.join(", "))));
+        }
+    }
+    let color_space = match primitive.color_space {
+        ColorSpace::Srgb => "srgb",
+        ColorSpace::LinearRgb => "linear-rgb",
+    };
+    fields.push(("color-space", color_space.to_string()));
+
+    let fields: Vec<String> = fields.iter().map(|(key, value)| format!("{}: {}", key, value)).collect();
+    format!("{{{}}}", fields.join(", "))
+}
+
+fn path_data_str(segments: ItemRange<PathSegment>) -> String {
+    let mut d = String::new();
+    for segment in segments.iter() {
+        if !d.is_empty() {
+            d.push(' ');
+        }
+        let coords = |points: &[LayoutPoint]| {
+            let values: Vec<String> = points
+                .iter()
+                .map(|p| format!("{} {}", f32_str(p.x), f32_str(p.y)))
+                .collect();
+            values.join(" ")
+        };
+        match segment {
+            PathSegment::MoveTo(to) => write!(d, "M {}", coords(&[to])),
+            PathSegment::LineTo(to) => write!(d, "L {}", coords(&[to])),
+            PathSegment::QuadraticTo(ctrl, to) => write!(d, "Q {}", coords(&[ctrl, to])),
+            PathSegment::CubicTo(ctrl1, ctrl2, to) => write!(d, "C {}", coords(&[ctrl1, ctrl2, to])),
+            PathSegment::Close => write!(d, "Z"),
+        }.unwrap();
+    }
+    quoted_str(&d)
+}
+
+fn stroke_str(stroke: &StrokeStyle) -> String {
+    let line_join = match stroke.line_join {
+        LineJoin::Miter => "miter",
+        LineJoin::Round => "round",
+        LineJoin::Bevel => "bevel",
+    };
+    let line_cap = match stroke.line_cap {
+        LineCap::Butt => "butt",
+        LineCap::Round => "round",
+        LineCap::Square => "square",
+    };
+    format!(
+        "{{width: {}, line-join: {}, line-cap: {}, miter-limit: {}, dashes: {}, dash-offset: {}}}",
+        f32_str(stroke.width),
+        line_join,
+        line_cap,
+        f32_str(stroke.miter_limit),
+        f32_vec_str(stroke.dashes().iter().cloned()),
+        f32_str(stroke.dash_offset),
+    )
+}
+
+/// Per spatial node state needed to undo the offsets applied by the builder.
+#[derive(Clone, Copy)]
+struct SpatialNodeInfo {
+    accumulated_external_scroll_offset: LayoutVector2D,
+}
+
+/// Writes the display lists of a scene, and the fonts they use, into a
+/// directory that wrench can replay.
+pub struct YamlFrameWriter<'a> {
+    fonts: &'a SharedFontResources,
+    output_dir: PathBuf,
+    saved_fonts: FastHashSet<FontKey>,
+}
+
+impl<'a> YamlFrameWriter<'a> {
+    pub fn new(fonts: &'a SharedFontResources, output_dir: PathBuf) -> Self {
+        YamlFrameWriter {
+            fonts,
+            output_dir,
+            saved_fonts: FastHashSet::default(),
+        }
+    }
+
+    /// Writes `scene` to `{file_name}.yaml` in the output directory.
+    pub fn write_scene(&mut self, scene: &Scene, file_name: &str) {
+        let root_pipeline_id = match scene.root_pipeline_id {
+            Some(id) => id,
+            None => {
+                warn!("Not writing {} as the scene has no root pipeline", file_name);
+                return;
+            }
+        };
+
+        let mut out = String::new();
+        let mut pipelines: Vec<&ScenePipeline> = scene.pipelines
+            .values()
+            .filter(|pipeline| pipeline.pipeline_id != root_pipeline_id)
+            .collect();
+        pipelines.sort_by_key(|pipeline| (pipeline.pipeline_id.0, pipeline.pipeline_id.1));
+
+        if !pipelines.is_empty() {
+            out.push_str("pipelines:\n");
+            for pipeline in pipelines {
+                writeln!(out, "  - id: {}", pipeline_id_str(pipeline.pipeline_id)).unwrap();
+                out.push_str("    items:\n");
+                self.write_pipeline(&mut out, pipeline, 6);
+            }
+        }
+
+        out.push_str("root:\n");
+        out.push_str("  items:\n");
+        match scene.pipelines.get(&root_pipeline_id) {
+            Some(pipeline) => self.write_pipeline(&mut out, pipeline, 4),
+            None => warn!("The root pipeline of {} has no display list", file_name),
+        }
+
+        let path = self.output_dir.join(file_name).with_extension("yaml");
+        if let Err(e) = std::fs::write(&path, out) {
+            warn!("Unable to write {}: {:?}", path.display(), e);
+        }
+    }
+
+    fn write_pipeline(&mut self, out: &mut String, pipeline: &ScenePipeline, indent: usize) {
+        let mut writer = PipelineWriter::new(self, out, indent, pipeline);
+        writer.write_items(pipeline);
+    }
+
+    /// Saves the font behind a font instance if needed, and returns the text
+    /// item fields describing it.
+    fn font_fields(&mut self, key: FontInstanceKey) -> Option<Vec<(&'static str, String)>> {
+        let shared_key = self.fonts.instance_keys.map_key(&key);
+        let instance = self.fonts.instances.get_font_instance(shared_key)?;
+        let mut fields = Vec::new();
+
+        fields.push(("size", f32_str(instance.size.to_f32_px() * 12.0 / 16.0)));
+        match self.fonts.templates.get_font(&instance.font_key) {
+            Some(FontTemplate::Raw(ref bytes, index)) => {
+                let file_name = font_file_name(instance.font_key);
+                if self.saved_fonts.insert(instance.font_key) {
+                    if let Err(e) = std::fs::write(self.output_dir.join(&file_name), &bytes[..]) {
+                        warn!("Unable to write {}: {:?}", file_name, e);
+                    }
+                }
+                fields.push(("font", quoted_str(&file_name)));
+                fields.push(("font-index", index.to_string()));
+            }
+            #[cfg(not(target_os = "macos"))]
+            Some(FontTemplate::Native(ref handle)) => {
+                fields.push(("font", quoted_str(&handle.path.to_string_lossy())));
+                fields.push(("font-index", handle.index.to_string()));
+            }
+            #[cfg(target_os = "macos")]
+            Some(FontTemplate::Native(ref handle)) => {
+                fields.push(("family", quoted_str(&handle.name)));
+            }
+            None => {
+                warn!("Missing font template for {:?}", instance.font_key);
+            }
+        }
+
+        if instance.bg_color.a != 0 {
+            let bg = instance.bg_color;
+            fields.push(("bg-color", f32_vec_str([
+                bg.r as f32, bg.g as f32, bg.b as f32, bg.a as f32 / 255.0,
+            ].iter().cloned())));
+        }
+        if instance.synthetic_italics.is_enabled() {
+            fields.push(("synthetic-italics", f32_str(instance.synthetic_italics.to_degrees())));
+        }
+        for (key, flag) in [
+            ("synthetic-bold", FontInstanceFlags::SYNTHETIC_BOLD),
+            ("embedded-bitmaps", FontInstanceFlags::EMBEDDED_BITMAPS),
+            ("transpose", FontInstanceFlags::TRANSPOSE),
+            ("flip-x", FontInstanceFlags::FLIP_X),
+            ("flip-y", FontInstanceFlags::FLIP_Y),
+        ].iter() {
+            if instance.flags.contains(*flag) {
+                fields.push((*key, "true".to_string()));
+            }
+        }
+        Some(fields)
+    }
+}
+
+/// Writes the items of a single display list.
+struct PipelineWriter<'a, 'b> {
+    frame_writer: &'b mut YamlFrameWriter<'a>,
+    out: &'b mut String,
+    /// Indentation of the list entries at the current nesting level.
+    indent: usize,
+    spatial_nodes: FastHashMap<usize, SpatialNodeInfo>,
+    /// Reference frame descriptors, in the order of their push items.
+    reference_frames: Vec<ReferenceFrameDescriptor>,
+    next_reference_frame: usize,
+    /// Scroll and sticky frames that haven't been written yet, in tree order.
+    pending_spatial_nodes: Vec<SpatialTreeItem>,
+    defined_spatial_nodes: FastHashSet<usize>,
+    rf_mapper: ReferenceFrameMapper,
+}
+
+impl<'a, 'b> PipelineWriter<'a, 'b> {
+    fn new(
+        frame_writer: &'b mut YamlFrameWriter<'a>,
+        out: &'b mut String,
+        indent: usize,
+        pipeline: &ScenePipeline,
+    ) -> Self {
+        let root_info = SpatialNodeInfo {
+            accumulated_external_scroll_offset: LayoutVector2D::zero(),
+        };
+        let mut spatial_nodes = FastHashMap::default();
+        spatial_nodes.insert(0, root_info);
+        spatial_nodes.insert(1, root_info);
+
+        let mut reference_frames = Vec::new();
+        let mut pending_spatial_nodes = Vec::new();
+        pipeline.display_list.display_list.iter_spatial_tree(|item| {
+            let (id, accumulated_external_scroll_offset) = match *item {
+                SpatialTreeItem::ScrollFrame(ref descriptor) => {
+                    pending_spatial_nodes.push(*item);
+                    let parent = spatial_nodes.get(&descriptor.parent_space.0).unwrap_or(&root_info);
+                    (
+                        descriptor.scroll_frame_id.0,
+                        parent.accumulated_external_scroll_offset + descriptor.external_scroll_offset,
+                    )
+                }
+                SpatialTreeItem::StickyFrame(ref descriptor) => {
+                    pending_spatial_nodes.push(*item);
+                    let parent = spatial_nodes.get(&descriptor.parent_spatial_id.0).unwrap_or(&root_info);
+                    (descriptor.id.0, parent.accumulated_external_scroll_offset)
+                }
+                SpatialTreeItem::ReferenceFrame(ref descriptor) => {
+                    reference_frames.push(*descriptor);
+                    (descriptor.reference_frame.id.0, LayoutVector2D::zero())
+                }
+                SpatialTreeItem::ScrollSnapPoint(..) |
+                SpatialTreeItem::Invalid => return,
+            };
+            spatial_nodes.insert(id, SpatialNodeInfo { accumulated_external_scroll_offset });
+        });
+
+        let mut defined_spatial_nodes = FastHashSet::default();
+        defined_spatial_nodes.insert(0);
+        defined_spatial_nodes.insert(1);
+
+        PipelineWriter {
+            frame_writer,
+            out,
+            indent,
+            spatial_nodes,
+            reference_frames,
+            next_reference_frame: 0,
+            pending_spatial_nodes,
+            defined_spatial_nodes,
+            rf_mapper: ReferenceFrameMapper::new(),
+        }
+    }
+
+    /// The offset the builder added to coordinates defined relative to `spatial_id`.
+    fn current_offset(&self, spatial_id: SpatialId) -> LayoutVector2D {
+        self.rf_mapper.current_offset() + self.accumulated_scroll_offset(spatial_id)
+    }
+
+    fn accumulated_scroll_offset(&self, spatial_id: SpatialId) -> LayoutVector2D {
+        self.spatial_nodes
+            .get(&spatial_id.0)
+            .map_or(LayoutVector2D::zero(), |info| info.accumulated_external_scroll_offset)
+    }
+
+    fn begin_item(&mut self, item_type: &str) {
+        writeln!(self.out, "{:indent$}- type: {}", "", item_type, indent = self.indent).unwrap();
+    }
+
+    fn field<V: AsRef<str>>(&mut self, key: &str, value: V) {
+        writeln!(self.out, "{:indent$}{}: {}", "", key, value.as_ref(), indent = self.indent + 2).unwrap();
+    }
+
+    fn comment(&mut self, text: &str) {
+        writeln!(self.out, "{:indent$}# {}", "", text, indent = self.indent).unwrap();
+    }
+
+    fn begin_children(&mut self) {
+        writeln!(self.out, "{:indent$}items:", "", indent = self.indent + 2).unwrap();
+        self.indent += 4;
+    }
+
+    fn end_children(&mut self) {
+        self.indent -= 4;
+    }
+
+    fn unsupported(&mut self, what: &str) {
+        warn!("YAML capture: {}", what);
+        self.comment(&format!("unsupported: {}", what));
+    }
+
+    fn common(&mut self, common: &CommonItemProperties) {
+        self.field("clip-rect", rect_str(&common.clip_rect));
+        self.field(
+            "clip-and-scroll",
+            format!("[{}, {}]", spatial_id_str(common.spatial_id), clip_id_str(common.clip_id)),
+        );
+        self.prim_flags(common.flags);
+    }
+
+    fn prim_flags(&mut self, flags: PrimitiveFlags) {
+        if !flags.contains(PrimitiveFlags::IS_BACKFACE_VISIBLE) {
+            self.field("backface-visible", "false");
+        }
+        if flags.contains(PrimitiveFlags::IS_SCROLLBAR_CONTAINER) {
+            self.field("scrollbar-container", "true");
+        }
+        if flags.contains(PrimitiveFlags::PREFER_COMPOSITOR_SURFACE) {
+            self.field("prefer-compositor-surface", "true");
+        }
+    }
+
+    fn gradient_interpolation(&mut self, interpolation: &GradientInterpolation) {
+        let color_space = match interpolation.color_space {
+            GradientColorSpace::Srgb => None,
+            GradientColorSpace::SrgbLinear => Some("srgb-linear"),
+            GradientColorSpace::Oklab => Some("oklab"),
+            GradientColorSpace::Oklch => Some("oklch"),
+            GradientColorSpace::Hsl => Some("hsl"),
+        };
+        if let Some(color_space) = color_space {
+            self.field("color-space", color_space);
+        }
+        let hue = match interpolation.hue {
+            HueInterpolationMethod::Shorter => None,
+            HueInterpolationMethod::Longer => Some("longer"),
+            HueInterpolationMethod::Increasing => Some("increasing"),
+            HueInterpolationMethod::Decreasing => Some("decreasing"),
+        };
+        if let Some(hue) = hue {
+            self.field("hue-interpolation", hue);
+        }
+    }
+
+    /// Writes the stops of a gradient, mapping them back from the normalized
+    /// `[0, 1]` range onto `[start_offset, end_offset]`. Wrench normalizes them
+    /// again when creating the gradient.
+    fn gradient_stops(
+        &mut self,
+        stops: ItemRange<GradientStop>,
+        start_offset: f32,
+        end_offset: f32,
+        extend_mode: ExtendMode,
+    ) {
+        let stops: Vec<String> = stops
+            .iter()
+            .map(|stop| format!(
+                "{}, {}",
+                f32_str(start_offset + stop.offset * (end_offset - start_offset)),
+                color_str(stop.color),
+            ))
+            .collect();
+        self.field("stops", format!("[{}]", stops.join(", ")));
+        if extend_mode == ExtendMode::Repeat {
+            self.field("repeat", "true");
+        }
+    }
+
+    fn filters(
+        &mut self,
+        filters: ItemRange<FilterOp>,
+        filter_datas: &[TempFilterData],
+        filter_primitives: ItemRange<FilterPrimitive>,
+    ) {
+        let filters: Vec<String> = filters.iter().map(|op| filter_op_str(&op)).collect();
+        if !filters.is_empty() {
+            self.field("filters", format!("[{}]", filters.join(", ")));
+        }
+        if !filter_datas.is_empty() {
+            let datas: Vec<String> = filter_datas.iter().map(filter_data_str).collect();
+            self.field("filter-datas", format!("[{}]", datas.join(", ")));
+        }
+        let primitives: Vec<String> = filter_primitives.iter().map(|p| filter_primitive_str(&p)).collect();
+        if !primitives.is_empty() {
+            self.field("filter-primitives", format!("[{}]", primitives.join(", ")));
+        }
+    }
+
+    /// Writes the scroll and sticky frames whose parent has been defined.
+    fn write_pending_spatial_nodes(&mut self) {
+        let pending = std::mem::take(&mut self.pending_spatial_nodes);
+        for item in pending {
+            let (id, parent) = match item {
+                SpatialTreeItem::ScrollFrame(ref descriptor) => (descriptor.scroll_frame_id, descriptor.parent_space),
+                SpatialTreeItem::StickyFrame(ref descriptor) => (descriptor.id, descriptor.parent_spatial_id),
+                _ => unreachable!(),
+            };
+            if !self.defined_spatial_nodes.contains(&parent.0) {
+                self.pending_spatial_nodes.push(item);
+                continue;
+            }
+
+            // Frames are written where the reference frame offset is zero, so
+            // only the external scroll offset of the parent needs removing.
+            let offset = self.accumulated_scroll_offset(parent);
+            match item {
+                SpatialTreeItem::ScrollFrame(ref descriptor) => {
+                    self.begin_item("scroll-frame");
+                    self.field("id", spatial_id_str(id));
+                    self.field("clip-and-scroll", format!("[{}, root_clip]", spatial_id_str(parent)));
+                    self.field("bounds", rect_str(&descriptor.frame_rect.translate(-offset)));
+                    self.field("content-size", size_str(descriptor.content_rect.size()));
+                    if descriptor.external_scroll_offset != LayoutVector2D::zero() {
+                        self.field("external-scroll-offset", vector_str(descriptor.external_scroll_offset));
+                    }
+                    self.field("scroll-generation", descriptor.scroll_offset_generation.to_string());
+                    if descriptor.has_scroll_linked_effect == HasScrollLinkedEffect::Yes {
+                        self.field("has-scroll-linked-effect", "true");
+                    }
+                }
+                SpatialTreeItem::StickyFrame(ref descriptor) => {
+                    self.begin_item("sticky-frame");
+                    self.field("id", spatial_id_str(id));
+                    self.field("clip-and-scroll", format!("[{}, root_clip]", spatial_id_str(parent)));
+                    self.field("bounds", rect_str(&descriptor.bounds.translate(-offset)));
+                    let margins = descriptor.margins;
+                    for (key, margin) in [
+                        ("margin-top", margins.top),
+                        ("margin-right", margins.right),
+                        ("margin-bottom", margins.bottom),
+                        ("margin-left", margins.left),
+                    ].iter() {
+                        if let Some(margin) = *margin {
+                            self.field(key, f32_str(margin));
+                        }
+                    }
+                    let bounds = descriptor.vertical_offset_bounds;
+                    self.field("vertical-offset-bounds", f32_vec_str([bounds.min, bounds.max].iter().cloned()));
+                    let bounds = descriptor.horizontal_offset_bounds;
+                    self.field("horizontal-offset-bounds", f32_vec_str([bounds.min, bounds.max].iter().cloned()));
+                    self.field("previously-applied-offset", vector_str(descriptor.previously_applied_offset));
+                }
+                _ => unreachable!(),
+            }
+            self.defined_spatial_nodes.insert(id.0);
+        }
+    }
+
+    fn write_items(&mut self, pipeline: &ScenePipeline) {
+        self.write_pending_spatial_nodes();
+
+        let mut iter = pipeline.display_list.iter();
+        while let Some(item) = iter.next() {
+            self.write_item(&item);
+        }
+
+        for item in std::mem::take(&mut self.pending_spatial_nodes) {
+            let id = match item {
+                SpatialTreeItem::ScrollFrame(ref descriptor) => descriptor.scroll_frame_id,
+                SpatialTreeItem::StickyFrame(ref descriptor) => descriptor.id,
+                _ => unreachable!(),
+            };
+            self.unsupported(&format!("spatial node {} has no defined parent", id.0));
+        }
+    }
+
+    fn write_item(&mut self, item: &DisplayItemRef) {
+        match *item.item() {
+            DisplayItem::Rectangle(ref info) => {
+                self.begin_item("rect");
+                self.common(&info.common);
+                self.field("bounds", rect_str(&info.bounds));
+                self.field("color", color_str(binding_value(&info.color)));
+                if info.color_space != RgbColorSpace::Srgb {
+                    self.field("color-space", rgb_color_space_name(info.color_space));
+                }
+            }
+            DisplayItem::ClearRectangle(ref info) => {
+                self.begin_item("clear-rect");
+                self.common(&info.common);
+                self.field("bounds", rect_str(&info.bounds));
+            }
+            DisplayItem::HitTest(ref info) => {
+                self.begin_item("hit-test");
+                self.common(&info.common);
+                self.field("bounds", rect_str(&info.common.clip_rect));
+                self.field("hit-testing-tag", format!("[{}, {}]", info.tag.0 as i64, info.tag.1));
+                if info.pass_through {
+                    self.field("pass-through", "true");
+                }
+                match info.shape {
+                    HitTestShape::Rect => {}
+                    HitTestShape::RoundedRect(ref radius) => {
+                        self.field("shape", "rounded-rect");
+                        self.field("radius", radius_str(radius));
+                    }
+                    HitTestShape::Ellipse => self.field("shape", "ellipse"),
+                    HitTestShape::Polygon(fill_rule) => {
+                        self.field("shape", "polygon");
+                        let points: Vec<String> = item.points().iter().map(point_str).collect();
+                        self.field("points", format!("[{}]", points.join(", ")));
+                        self.field("fill-rule", fill_rule_name(fill_rule));
+                    }
+                }
+            }
+            DisplayItem::Text(ref info) => {
+                let font_fields = match self.frame_writer.font_fields(info.font_key) {
+                    Some(fields) => fields,
+                    None => {
+                        self.unsupported(&format!("text with unknown font instance {:?}", info.font_key));
+                        return;
+                    }
+                };
+                self.begin_item("text");
+                self.common(&info.common);
+                self.field("bounds", rect_str(&info.bounds));
+                self.field("color", color_str(info.color));
+                for (key, value) in font_fields {
+                    self.field(key, value);
+                }
+                let glyphs = item.glyphs();
+                let indices: Vec<String> = glyphs.iter().map(|glyph| glyph.glyph_index().to_string()).collect();
+                self.field("glyphs", format!("[{}]", indices.join(", ")));
+                self.field(
+                    "offsets",
+                    f32_vec_str(glyphs.iter().flat_map(|glyph| vec![glyph.point.x, glyph.point.y])),
+                );
+                if glyphs.iter().any(|glyph| glyph.orientation() != GlyphOrientation::Upright) {
+                    let orientations: Vec<&str> = glyphs
+                        .iter()
+                        .map(|glyph| match glyph.orientation() {
+                            GlyphOrientation::Upright => "upright",
+                            GlyphOrientation::Sideways => "sideways",
+                        })
+                        .collect();
+                    self.field("orientations", format!("[{}]", orientations.join(", ")));
+                }
+                if let Some(ref decoration) = info.decoration {
+                    self.field("decoration", text_decoration_str(decoration));
+                }
+            }
+            DisplayItem::Path(ref info) => {
+                self.begin_item("path");
+                self.common(&info.common);
+                self.field("bounds", rect_str(&info.bounds));
+                self.field("color", color_str(info.color));
+                self.field("fill-rule", fill_rule_name(info.fill_rule));
+                self.field("d", path_data_str(item.path_segments()));
+                if let Some(ref stroke) = info.stroke {
+                    self.field("stroke", stroke_str(stroke));
+                }
+            }
+            DisplayItem::Line(ref info) => {
+                self.begin_item("line");
+                self.common(&info.common);
+                self.field("bounds", rect_str(&info.area));
+                let orientation = match info.orientation {
+                    LineOrientation::Horizontal => "horizontal",
+                    LineOrientation::Vertical => "vertical",
+                };
+                self.field("orientation", orientation);
+                self.field("style", line_style_name(info.style));
+                if info.style == LineStyle::Wavy {
+                    self.field("thickness", f32_str(info.wavy_line_thickness));
+                }
+                self.field("color", color_str(info.color));
+            }
+            DisplayItem::Border(ref info) => {
+                self.begin_item("border");
+                self.common(&info.common);
+                self.field("bounds", rect_str(&info.bounds));
+                // Wrench reads the widths as top, left, bottom, right.
+                let widths = info.widths;
+                self.field(
+                    "width",
+                    f32_vec_str([widths.top, widths.left, widths.bottom, widths.right].iter().cloned()),
+                );
+                match info.details {
+                    BorderDetails::Normal(ref border) => {
+                        self.field("border-type", "normal");
+                        let sides = [border.top, border.right, border.bottom, border.left];
+                        let colors: Vec<String> = sides.iter().map(|side| color_str(side.color)).collect();
+                        self.field("color", format!("[{}]", colors.join(", ")));
+                        let styles: Vec<&str> = sides.iter().map(|side| border_style_name(side.style)).collect();
+                        self.field("style", format!("[{}]", styles.join(", ")));
+                        self.field("radius", radius_str(&border.radius));
+                        if !border.do_aa {
+                            self.field("do_aa", "false");
+                        }
+                    }
+                    BorderDetails::NinePatch(ref border) => {
+                        match border.source {
+                            NinePatchBorderSource::Image(key, _) => {
+                                self.field("border-type", "image");
+                                self.field("image-source", quoted_str(&image_file_name(key)));
+                            }
+                            NinePatchBorderSource::Gradient(ref gradient) => {
+                                self.field("border-type", "gradient");
+                                self.field("start", point_str(gradient.start_point));
+                                self.field("end", point_str(gradient.end_point));
+                                self.gradient_stops(item.gradient_stops(), 0.0, 1.0, gradient.extend_mode);
+                                self.gradient_interpolation(&gradient.interpolation);
+                            }
+                            NinePatchBorderSource::RadialGradient(ref gradient) => {
+                                self.field("border-type", "radial-gradient");
+                                self.field("center", point_str(gradient.center));
+                                self.field("radius", size_str(gradient.radius));
+                                self.gradient_stops(
+                                    item.gradient_stops(),
+                                    gradient.start_offset,
+                                    gradient.end_offset,
+                                    gradient.extend_mode,
+                                );
+                                self.gradient_interpolation(&gradient.interpolation);
+                            }
+                            NinePatchBorderSource::ConicGradient(ref gradient) => {
+                                self.field("border-type", "conic-gradient");
+                                self.field("center", point_str(gradient.center));
+                                self.field("angle", f32_str(gradient.angle));
+                                self.gradient_stops(
+                                    item.gradient_stops(),
+                                    gradient.start_offset,
+                                    gradient.end_offset,
+                                    gradient.extend_mode,
+                                );
+                                self.gradient_interpolation(&gradient.interpolation);
+                            }
+                        }
+                        self.field("image-width", border.width.to_string());
+                        self.field("image-height", border.height.to_string());
+                        let slice = border.slice;
+                        self.field(
+                            "slice",
+                            format!("[{}, {}, {}, {}]", slice.top, slice.right, slice.bottom, slice.left),
+                        );
+                        if border.fill {
+                            self.field("fill", "true");
+                        }
+                        let outset = border.outset;
+                        self.field(
+                            "outset",
+                            f32_vec_str([outset.top, outset.right, outset.bottom, outset.left].iter().cloned()),
+                        );
+                        self.field("repeat-horizontal", repeat_mode_name(border.repeat_horizontal));
+                        self.field("repeat-vertical", repeat_mode_name(border.repeat_vertical));
+                    }
+                }
+            }
+            DisplayItem::BoxShadow(ref info) => {
+                self.begin_item("box-shadow");
+                self.common(&info.common);
+                self.field("bounds", rect_str(&info.box_bounds));
+                self.field("box-bounds", rect_str(&info.box_bounds));
+                self.field("offset", vector_str(info.offset));
+                self.field("color", color_str(info.color));
+                self.field("blur-radius", f32_str(info.blur_radius));
+                self.field("spread-radius", f32_str(info.spread_radius));
+                self.field("border-radius", radius_str(&info.border_radius));
+                let clip_mode = match info.clip_mode {
+                    BoxShadowClipMode::Outset => "outset",
+                    BoxShadowClipMode::Inset => "inset",
+                };
+                self.field("clip-mode", clip_mode);
+            }
+            DisplayItem::PushShadow(ref info) => {
+                self.begin_item("shadow");
+                self.field(
+                    "clip-and-scroll",
+                    format!(
+                        "[{}, {}]",
+                        spatial_id_str(info.space_and_clip.spatial_id),
+                        clip_id_str(info.space_and_clip.clip_id),
+                    ),
+                );
+                self.field("blur-radius", f32_str(info.shadow.blur_radius));
+                self.field("offset", vector_str(info.shadow.offset));
+                self.field("color", color_str(info.shadow.color));
+            }
+            DisplayItem::PopAllShadows => {
+                self.begin_item("pop-all-shadows");
+            }
+            DisplayItem::Gradient(ref info) => {
+                self.begin_item("gradient");
+                self.common(&info.common);
+                self.field("bounds", rect_str(&info.bounds));
+                self.field("tile-size", size_str(info.tile_size));
+                self.field("tile-spacing", size_str(info.tile_spacing));
+                self.field("start", point_str(info.gradient.start_point));
+                self.field("end", point_str(info.gradient.end_point));
+                self.gradient_stops(item.gradient_stops(), 0.0, 1.0, info.gradient.extend_mode);
+                self.gradient_interpolation(&info.gradient.interpolation);
+            }
+            DisplayItem::RadialGradient(ref info) => {
+                self.begin_item("radial-gradient");
+                self.common(&info.common);
+                self.field("bounds", rect_str(&info.bounds));
+                self.field("tile-size", size_str(info.tile_size));
+                self.field("tile-spacing", size_str(info.tile_spacing));
+                self.field("center", point_str(info.gradient.center));
+                self.field("radius", size_str(info.gradient.radius));
+                self.gradient_stops(
+                    item.gradient_stops(),
+                    info.gradient.start_offset,
+                    info.gradient.end_offset,
+                    info.gradient.extend_mode,
+                );
+                self.gradient_interpolation(&info.gradient.interpolation);
+            }
+            DisplayItem::ConicGradient(ref info) => {
+                self.begin_item("conic-gradient");
+                self.common(&info.common);
+                self.field("bounds", rect_str(&info.bounds));
+                self.field("tile-size", size_str(info.tile_size));
+                self.field("tile-spacing", size_str(info.tile_spacing));
+                self.field("center", point_str(info.gradient.center));
+                self.field("angle", f32_str(info.gradient.angle));
+                self.gradient_stops(
+                    item.gradient_stops(),
+                    info.gradient.start_offset,
+                    info.gradient.end_offset,
+                    info.gradient.extend_mode,
+                );
+                self.gradient_interpolation(&info.gradient.interpolation);
+            }
+            DisplayItem::Image(ref info) => {
+                self.begin_item("image");
+                self.common(&info.common);
+                self.field("src", quoted_str(&image_file_name(info.image_key)));
+                self.field("bounds", rect_str(&info.bounds));
+                if let Some(rect) = info.source_rect {
+                    self.field("source-rect", rect_str(&rect.to_f32().cast_unit()));
+                }
+                self.field("rendering", image_rendering_name(info.image_rendering));
+                if info.alpha_type == AlphaType::Alpha {
+                    self.field("alpha-type", "alpha");
+                }
+                if info.color != ColorF::WHITE {
+                    self.unsupported("tinted image, written without its color");
+                }
+            }
+            DisplayItem::RepeatingImage(ref info) => {
+                self.begin_item("image");
+                self.common(&info.common);
+                self.field("src", quoted_str(&image_file_name(info.image_key)));
+                self.field("bounds", rect_str(&info.bounds));
+                self.field("stretch-size", size_str(info.stretch_size));
+                self.field("tile-spacing", size_str(info.tile_spacing));
+                if let Some(rect) = info.source_rect {
+                    self.field("source-rect", rect_str(&rect.to_f32().cast_unit()));
+                }
+                self.field("repeat-horizontal", repeat_mode_name(info.repeat_horizontal));
+                self.field("repeat-vertical", repeat_mode_name(info.repeat_vertical));
+                self.field("rendering", image_rendering_name(info.image_rendering));
+                if info.alpha_type == AlphaType::Alpha {
+                    self.field("alpha-type", "alpha");
+                }
+                if info.color != ColorF::WHITE {
+                    self.unsupported("tinted image, written without its color");
+                }
+            }
+            DisplayItem::YuvImage(ref info) => {
+                self.begin_item("yuv-image");
+                self.common(&info.common);
+                match info.yuv_data {
+                    YuvData::PlanarYCbCr(y, u, v) => {
+                        self.field("format", "planar");
+                        self.field("src-y", quoted_str(&image_file_name(y)));
+                        self.field("src-u", quoted_str(&image_file_name(u)));
+                        self.field("src-v", quoted_str(&image_file_name(v)));
+                    }
+                    YuvData::NV12(y, uv) => {
+                        self.field("format", "nv12");
+                        self.field("src-y", quoted_str(&image_file_name(y)));
+                        self.field("src-uv", quoted_str(&image_file_name(uv)));
+                    }
+                    YuvData::P010(y, uv) => {
+                        self.field("format", "p010");
+                        self.field("src-y", quoted_str(&image_file_name(y)));
+                        self.field("src-uv", quoted_str(&image_file_name(uv)));
+                    }
+                    YuvData::InterleavedYCbCr(yuv) => {
+                        self.field("format", "interleaved");
+                        self.field("src", quoted_str(&image_file_name(yuv)));
+                    }
+                }
+                self.field("bounds", rect_str(&info.bounds));
+            }
+            DisplayItem::BackdropFilter(ref info) => {
+                self.begin_item("backdrop-filter");
+                self.common(&info.common);
+                self.field("bounds", rect_str(&info.common.clip_rect));
+                self.filters(item.filters(), item.filter_datas(), item.filter_primitives());
+            }
+            DisplayItem::RectClip(ref info) => {
+                let offset = self.current_offset(info.parent_space_and_clip.spatial_id);
+                self.begin_clip(info.id, &info.parent_space_and_clip);
+                self.field("bounds", rect_str(&info.clip_rect.translate(-offset)));
+            }
+            DisplayItem::RoundedRectClip(ref info) => {
+                if info.binding.is_some() {
+                    warn!("YAML capture: writing the default value of an animated clip");
+                }
+                self.begin_clip(info.id, &info.parent_space_and_clip);
+                self.field(
+                    "complex",
+                    format!(
+                        "[{{rect: {}, radius: {}, clip-mode: {}}}]",
+                        rect_str(&info.clip.rect.translate(-info.binding_offset)),
+                        radius_str(&info.clip.radii),
+                        clip_mode_name(info.clip.mode),
+                    ),
+                );
+            }
+            DisplayItem::ImageMaskClip(ref info) => {
+                let offset = self.current_offset(info.parent_space_and_clip.spatial_id);
+                self.begin_clip(info.id, &info.parent_space_and_clip);
+                self.field(
+                    "image-mask",
+                    format!(
+                        "{{image: {}, rect: {}, repeat: {}}}",
+                        quoted_str(&image_file_name(info.image_mask.image)),
+                        rect_str(&info.image_mask.rect.translate(-offset)),
+                        info.image_mask.repeat,
+                    ),
+                );
+            }
+            DisplayItem::PolygonClip(ref info) => {
+                let offset = self.current_offset(info.parent_space_and_clip.spatial_id);
+                self.begin_clip(info.id, &info.parent_space_and_clip);
+                let points: Vec<String> = item.points().iter().map(|point| point_str(point - offset)).collect();
+                self.field("polygon", format!("[{}]", points.join(", ")));
+                self.field("fill-rule", fill_rule_name(info.fill_rule));
+            }
+            DisplayItem::ClipChain(ref info) => {
+                self.begin_item("clip-chain");
+                self.field("id", clip_id_str(ClipId::ClipChain(info.id)));
+                let clips: Vec<String> = item.clip_chain_items()
+                    .iter()
+                    .filter(|id| !id.is_root())
+                    .map(clip_id_str)
+                    .collect();
+                self.field("clips", format!("[{}]", clips.join(", ")));
+                if let Some(parent) = info.parent {
+                    self.field("parent", clip_id_str(ClipId::ClipChain(parent)));
+                }
+            }
+            DisplayItem::Iframe(ref info) => {
+                let offset = self.current_offset(info.space_and_clip.spatial_id);
+                self.begin_item("iframe");
+                self.field(
+                    "clip-and-scroll",
+                    format!(
+                        "[{}, {}]",
+                        spatial_id_str(info.space_and_clip.spatial_id),
+                        clip_id_str(info.space_and_clip.clip_id),
+                    ),
+                );
+                self.field("bounds", rect_str(&info.bounds.translate(-offset)));
+                self.field("clip-rect", rect_str(&info.clip_rect.translate(-offset)));
+                self.field("id", pipeline_id_str(info.pipeline_id));
+                if !info.ignore_missing_pipeline {
+                    self.field("ignore_missing_pipeline", "false");
+                }
+            }
+            DisplayItem::PushReferenceFrame(..) => {
+                self.push_reference_frame();
+            }
+            DisplayItem::PopReferenceFrame => {
+                self.end_children();
+                self.rf_mapper.pop_scope();
+            }
+            DisplayItem::PushStackingContext(ref info) => {
+                self.begin_item("stacking-context");
+                self.field("clip-and-scroll", format!("[{}, root_clip]", spatial_id_str(info.spatial_id)));
+                self.prim_flags(info.prim_flags);
+                self.field("bounds", f32_vec_str([info.origin.x, info.origin.y, 0.0, 0.0].iter().cloned()));
+                let stacking_context = &info.stacking_context;
+                if let Some(clip_id) = stacking_context.clip_id {
+                    self.field("clip-node", clip_id_str(clip_id));
+                }
+                if stacking_context.transform_style == TransformStyle::Preserve3D {
+                    self.field("transform-style", "preserve-3d");
+                }
+                if stacking_context.mix_blend_mode != MixBlendMode::Normal {
+                    self.field("mix-blend-mode", mix_blend_mode_name(stacking_context.mix_blend_mode));
+                }
+                if let RasterSpace::Local(scale) = stacking_context.raster_space {
+                    self.field("raster-space", quoted_str(&format!("local({})", f32_str(scale))));
+                }
+                if stacking_context.flags.contains(StackingContextFlags::IS_BACKDROP_ROOT) {
+                    self.field("backdrop-root", "true");
+                }
+                if stacking_context.flags.contains(StackingContextFlags::IS_BLEND_CONTAINER) {
+                    self.field("blend-container", "true");
+                }
+                self.filters(item.filters(), item.filter_datas(), item.filter_primitives());
+                self.begin_children();
+                self.rf_mapper.push_offset(info.origin.to_vector());
+            }
+            DisplayItem::PopStackingContext => {
+                self.end_children();
+                self.rf_mapper.pop_offset();
+            }
+            DisplayItem::SetGradientStops |
+            DisplayItem::SetFilterOps |
+            DisplayItem::SetFilterData |
+            DisplayItem::SetFilterPrimitives |
+            DisplayItem::SetPoints |
+            DisplayItem::ReuseItems(..) |
+            DisplayItem::RetainedItems(..) => {}
+        }
+    }
+
+    fn begin_clip(&mut self, id: ClipId, parent: &SpaceAndClipInfo) {
+        self.begin_item("clip");
+        self.field("id", clip_id_str(id));
+        self.field(
+            "clip-and-scroll",
+            format!("[{}, {}]", spatial_id_str(parent.spatial_id), clip_id_str(parent.clip_id)),
+        );
+    }
+
+    fn push_reference_frame(&mut self) {
+        let descriptor = self.reference_frames.get(self.next_reference_frame).cloned();
+        self.next_reference_frame += 1;
+
+        self.begin_item("reference-frame");
+        let descriptor = match descriptor {
+            Some(descriptor) => descriptor,
+            None => {
+                self.unsupported("reference frame without a spatial tree entry");
+                self.begin_children();
+                self.rf_mapper.push_scope();
+                return;
+            }
+        };
+        let reference_frame = &descriptor.reference_frame;
+        let parent = descriptor.parent_spatial_id;
+
+        self.field("id", spatial_id_str(reference_frame.id));
+        self.field("clip-and-scroll", format!("[{}, root_clip]", spatial_id_str(parent)));
+        let origin = descriptor.origin - self.current_offset(parent);
+        self.field("bounds", f32_vec_str([origin.x, origin.y, 0.0, 0.0].iter().cloned()));
+        if reference_frame.transform_style == TransformStyle::Preserve3D {
+            self.field("transform-style", "preserve-3d");
+        }
+
+        let transform = match reference_frame.transform {
+            ReferenceTransformBinding::Static { ref binding } => binding_value(binding),
+            ReferenceTransformBinding::Computed { .. } => {
+                self.unsupported("computed reference frame, written as identity");
+                LayoutTransform::identity()
+            }
+        };
+        let matrix = f32_vec_str(transform.to_array().iter().cloned());
+        match reference_frame.kind {
+            ReferenceFrameKind::Transform { paired_with_perspective, .. } => {
+                self.field("transform", matrix);
+                if paired_with_perspective {
+                    self.field("paired-with-perspective", "true");
+                }
+            }
+            ReferenceFrameKind::Perspective { .. } => {
+                self.field("perspective", matrix);
+            }
+        }
+
+        self.begin_children();
+        self.rf_mapper.push_scope();
+        self.defined_spatial_nodes.insert(reference_frame.id.0);
+        self.write_pending_spatial_nodes();
+    }
+}
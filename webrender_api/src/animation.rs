@@ -0,0 +1,192 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Declarative keyframe animations.
+//!
+//! Instead of sampling animated values on the embedder's side and sending them
+//! with every frame through `DynamicProperties`, a `KeyframeAnimation` can be
+//! registered once with a transaction. The render backend then evaluates it
+//! every time a frame is built and applies the result to the animated
+//! property binding, following the CSS animations model.
+
+use crate::{ColorF, IdNamespace, PropertyBindingKey};
+use crate::units::LayoutTransform;
+
+/// An opaque identifier of a keyframe animation registered with WebRender.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize)]
+pub struct AnimationId(pub IdNamespace, pub u32);
+
+impl AnimationId {
+    /// Mints a new AnimationId. The given ID must be unique.
+    pub fn new(namespace: IdNamespace, id: u32) -> Self {
+        AnimationId(namespace, id)
+    }
+}
+
+/// Where the jumps of a `steps()` timing function happen.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize)]
+pub enum StepPosition {
+    /// The first jump happens at the start of the interval.
+    JumpStart,
+    /// The last jump happens at the end of the interval.
+    JumpEnd,
+    /// There is no jump at either end of the interval.
+    JumpNone,
+    /// There are jumps at both ends of the interval.
+    JumpBoth,
+}
+
+/// An easing function, as defined by the CSS easing functions specification.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum TimingFunction {
+    ///
+    Linear,
+    /// Equivalent to `CubicBezier(0.25, 0.1, 0.25, 1.0)`.
+    Ease,
+    /// Equivalent to `CubicBezier(0.42, 0.0, 1.0, 1.0)`.
+    EaseIn,
+    /// Equivalent to `CubicBezier(0.0, 0.0, 0.58, 1.0)`.
+    EaseOut,
+    /// Equivalent to `CubicBezier(0.42, 0.0, 0.58, 1.0)`.
+    EaseInOut,
+    /// A cubic bezier curve going from (0, 0) to (1, 1), with the given
+    /// control points (x1, y1) and (x2, y2). The x coordinates must be in
+    /// the [0, 1] range.
+    CubicBezier(f32, f32, f32, f32),
+    /// A step function with the given number of intervals.
+    Steps(u32, StepPosition),
+}
+
+impl Default for TimingFunction {
+    fn default() -> Self {
+        TimingFunction::Ease
+    }
+}
+
+/// The direction in which the iterations of an animation are played.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize)]
+pub enum AnimationDirection {
+    ///
+    Normal,
+    ///
+    Reverse,
+    /// Even iterations are played forwards and odd ones backwards.
+    Alternate,
+    /// Even iterations are played backwards and odd ones forwards.
+    AlternateReverse,
+}
+
+impl Default for AnimationDirection {
+    fn default() -> Self {
+        AnimationDirection::Normal
+    }
+}
+
+/// Whether an animation applies its values outside of its active interval.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize)]
+pub enum AnimationFillMode {
+    /// The animation only affects the property while it is active.
+    None,
+    /// The final value is kept once the animation has finished.
+    Forwards,
+    /// The initial value is applied during the delay.
+    Backwards,
+    /// Both `Forwards` and `Backwards`.
+    Both,
+}
+
+impl Default for AnimationFillMode {
+    fn default() -> Self {
+        AnimationFillMode::None
+    }
+}
+
+impl AnimationFillMode {
+    /// Returns true if the value is applied during the delay.
+    pub fn fills_backwards(self) -> bool {
+        matches!(self, AnimationFillMode::Backwards | AnimationFillMode::Both)
+    }
+
+    /// Returns true if the value is kept after the animation has finished.
+    pub fn fills_forwards(self) -> bool {
+        matches!(self, AnimationFillMode::Forwards | AnimationFillMode::Both)
+    }
+}
+
+/// A value of an animated property at a given point of an iteration.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Keyframe<T> {
+    /// The position of the keyframe in the iteration, between 0 and 1.
+    pub offset: f32,
+    ///
+    pub value: T,
+    /// The easing applied between this keyframe and the next one.
+    pub easing: TimingFunction,
+}
+
+impl<T> Keyframe<T> {
+    /// Constructor.
+    pub fn new(offset: f32, value: T, easing: TimingFunction) -> Self {
+        Keyframe { offset, value, easing }
+    }
+}
+
+/// The keyframes of an animation, along with the property binding they
+/// animate.
+///
+/// Keyframes must be sorted by offset. If there is no keyframe at offset 0 or
+/// 1, the value of the first or last keyframe is used up to that point.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum AnimatedProperty {
+    ///
+    Transform(PropertyBindingKey<LayoutTransform>, Vec<Keyframe<LayoutTransform>>),
+    ///
+    Float(PropertyBindingKey<f32>, Vec<Keyframe<f32>>),
+    ///
+    Color(PropertyBindingKey<ColorF>, Vec<Keyframe<ColorF>>),
+}
+
+/// A keyframe animation, evaluated by the render backend every time a frame
+/// is built.
+///
+/// The animation starts with the first frame built after it was added.
+/// Values produced by animations take precedence over the ones supplied
+/// through `DynamicProperties` for the same binding.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct KeyframeAnimation {
+    ///
+    pub id: AnimationId,
+    ///
+    pub property: AnimatedProperty,
+    /// The duration of one iteration, in milliseconds.
+    pub duration_ms: f32,
+    /// The time between the start of the animation and its first iteration,
+    /// in milliseconds.
+    pub delay_ms: f32,
+    /// The number of iterations, which can be fractional or infinite.
+    pub iterations: f32,
+    ///
+    pub direction: AnimationDirection,
+    ///
+    pub fill_mode: AnimationFillMode,
+}
+
+impl KeyframeAnimation {
+    /// Creates an animation playing the keyframes once, with no delay.
+    pub fn new(id: AnimationId, property: AnimatedProperty, duration_ms: f32) -> Self {
+        KeyframeAnimation {
+            id,
+            property,
+            duration_ms,
+            delay_ms: 0.0,
+            iterations: 1.0,
+            direction: AnimationDirection::Normal,
+            fill_mode: AnimationFillMode::None,
+        }
+    }
+}
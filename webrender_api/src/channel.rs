@@ -11,10 +11,10 @@ use std::mem;
 pub use crossbeam_channel as crossbeam;
 
 #[cfg(not(target_os = "windows"))]
-pub use crossbeam_channel::{Sender, Receiver};
+pub use crossbeam_channel::{Sender, Receiver, RecvTimeoutError};
 
 #[cfg(target_os = "windows")]
-pub use std::sync::mpsc::{Sender, Receiver};
+pub use std::sync::mpsc::{Sender, Receiver, RecvTimeoutError};
 
 #[derive(Clone)]
 pub struct Payload {
@@ -107,6 +107,30 @@ impl Hash for PremultipliedColorF {
     }
 }
 
+/// The RGB color space that a color or an image is expressed in.
+///
+/// Colors and images default to sRGB. When their color space differs from
+/// the output color space of the document, WebRender converts them before
+/// compositing.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize, PeekPoke)]
+pub enum RgbColorSpace {
+    /// sRGB primaries with the sRGB transfer function.
+    Srgb = 0,
+    /// Display-P3 primaries with the sRGB transfer function.
+    DisplayP3 = 1,
+    /// ITU-R BT.2020 primaries with the BT.2020 transfer function.
+    Rec2020 = 2,
+    /// sRGB primaries without any transfer function.
+    LinearSrgb = 3,
+}
+
+impl Default for RgbColorSpace {
+    fn default() -> Self {
+        RgbColorSpace::Srgb
+    }
+}
+
 /// Represents RGBA screen colors with one byte per channel.
 ///
 /// If the alpha value `a` is 255 the color is opaque.
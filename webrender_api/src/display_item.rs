@@ -7,8 +7,8 @@ use peek_poke::PeekPoke;
 use std::ops::Not;
 // local imports
 use crate::font;
-use crate::{APZScrollGeneration, HasScrollLinkedEffect, PipelineId, PropertyBinding};
-use crate::color::ColorF;
+use crate::{APZScrollGeneration, HasScrollLinkedEffect, PipelineId, PropertyBinding, PropertyBindingKey};
+use crate::color::{ColorF, RgbColorSpace};
 use crate::image::{ColorDepth, ImageKey};
 use crate::units::*;
 use std::hash::{Hash, Hasher};
@@ -143,6 +143,8 @@ pub enum SpatialTreeItem {
     ScrollFrame(ScrollFrameDescriptor),
     ReferenceFrame(ReferenceFrameDescriptor),
     StickyFrame(StickyFrameDescriptor),
+    /// A snap position of the next scroll frame item.
+    ScrollSnapPoint(ScrollSnapPoint),
     Invalid,
 }
 
@@ -154,6 +156,7 @@ pub enum DisplayItem {
     ClearRectangle(ClearRectangleDisplayItem),
     HitTest(HitTestDisplayItem),
     Text(TextDisplayItem),
+    Path(PathDisplayItem),
     Line(LineDisplayItem),
     Border(BorderDisplayItem),
     BoxShadow(BoxShadowDisplayItem),
@@ -170,6 +173,7 @@ pub enum DisplayItem {
     RectClip(RectClipDisplayItem),
     RoundedRectClip(RoundedRectClipDisplayItem),
     ImageMaskClip(ImageMaskClipDisplayItem),
+    PolygonClip(PolygonClipDisplayItem),
     ClipChain(ClipChainItem),
 
     // Spaces and Frames that content can be scoped under.
@@ -204,6 +208,7 @@ pub enum DebugDisplayItem {
     ClearRectangle(ClearRectangleDisplayItem),
     HitTest(HitTestDisplayItem),
     Text(TextDisplayItem, Vec<font::GlyphInstance>),
+    Path(PathDisplayItem, Vec<PathSegment>),
     Line(LineDisplayItem),
     Border(BorderDisplayItem),
     BoxShadow(BoxShadowDisplayItem),
@@ -219,6 +224,7 @@ pub enum DebugDisplayItem {
     ImageMaskClip(ImageMaskClipDisplayItem),
     RoundedRectClip(RoundedRectClipDisplayItem),
     RectClip(RectClipDisplayItem),
+    PolygonClip(PolygonClipDisplayItem),
     ClipChain(ClipChainItem, Vec<ClipId>),
 
     Iframe(IframeDisplayItem),
@@ -255,9 +261,27 @@ pub struct RectClipDisplayItem {
 pub struct RoundedRectClipDisplayItem {
     pub id: ClipId,
     pub parent_space_and_clip: SpaceAndClipInfo,
+    /// The clip region, or the default value of the binding if the region
+    /// is animated.
     pub clip: ComplexClipRegion,
+    /// The animated property the region is read from, if any.
+    pub binding: Option<PropertyBindingKey<ComplexClipRegion>>,
+    /// The offset applied by the display list builder to `clip`, which also
+    /// applies to the animated values.
+    pub binding_offset: LayoutVector2D,
 }
 
+/// An analytic polygon clip, e.g. for CSS `clip-path: polygon()`. Unlike the
+/// polygon attached to an `ImageMaskClipDisplayItem`, which is only used for
+/// hit testing, this is rasterized by WebRender itself and is not limited to
+/// `POLYGON_CLIP_VERTEX_MAX` vertices.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct PolygonClipDisplayItem {
+    pub id: ClipId,
+    pub parent_space_and_clip: SpaceAndClipInfo,
+    pub fill_rule: FillRule,
+} // IMPLICIT points: Vec<LayoutPoint>
+
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
 pub struct ClipDisplayItem {
     pub id: ClipId,
@@ -342,6 +366,48 @@ pub struct ScrollFrameDescriptor {
     pub has_scroll_linked_effect: HasScrollLinkedEffect,
     /// A unique (per-pipeline) key for this spatial that is stable across display lists.
     pub key: SpatialTreeItemKey,
+    /// How the scroll frame behaves when it is scrolled by `Transaction::scroll`.
+    /// Its snap positions are declared by the `ScrollSnapPoint` items that
+    /// precede this one.
+    pub physics: ScrollPhysics,
+}
+
+/// Whether a scroll frame snaps, following CSS `scroll-snap-type`.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize, PeekPoke)]
+pub enum ScrollSnapStrictness {
+    /// The scroll frame doesn't snap.
+    None,
+    /// The scroll frame comes to rest on a snap position if it is close enough.
+    Proximity,
+    /// The scroll frame always comes to rest on a snap position.
+    Mandatory,
+}
+
+/// What happens when a scroll frame is scrolled past its bounds.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize, PeekPoke)]
+pub enum OverscrollMode {
+    /// The scroll offset stops at the bounds.
+    Clamp,
+    /// The content can be dragged past the bounds with some resistance, and
+    /// springs back once released.
+    Bounce,
+}
+
+/// Parameters of the scroll physics applied by the render backend.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct ScrollPhysics {
+    pub snap: ScrollSnapStrictness,
+    pub overscroll: OverscrollMode,
+}
+
+/// A snap position of a scroll frame, expressed as the distance scrolled from
+/// the start of the content. An axis without a position doesn't snap.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct ScrollSnapPoint {
+    pub x: Option<f32>,
+    pub y: Option<f32>,
 }
 
 /// A solid or an animating color to draw (may not actually be a rectangle due to complex clips)
@@ -350,6 +416,8 @@ pub struct RectangleDisplayItem {
     pub common: CommonItemProperties,
     pub bounds: LayoutRect,
     pub color: PropertyBinding<ColorF>,
+    /// The color space that `color` is expressed in.
+    pub color_space: RgbColorSpace,
 }
 
 /// Clears all colors from the area, making it possible to cut holes in the window.
@@ -367,6 +435,27 @@ pub struct ClearRectangleDisplayItem {
 pub struct HitTestDisplayItem {
     pub common: CommonItemProperties,
     pub tag: ItemTag,
+    /// The shape of the hit-testable region within the clip rect.
+    pub shape: HitTestShape,
+    /// If true, hit-test queries for the topmost item keep looking for items
+    /// behind this one, after returning it.
+    pub pass_through: bool,
+}
+
+/// The shape of the hit-testable region of a `HitTestDisplayItem`, relative
+/// to the clip rect of the item.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub enum HitTestShape {
+    /// The whole clip rect.
+    Rect,
+    /// The clip rect, with rounded corners.
+    RoundedRect(BorderRadius),
+    /// The ellipse inscribed in the clip rect.
+    Ellipse,
+    /// A polygon, whose points are supplied with the item. Only the part of
+    /// the polygon inside the clip rect is hit-testable.
+    Polygon(FillRule),
 }
 
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
@@ -406,6 +495,36 @@ pub enum LineStyle {
     Wavy,
 }
 
+bitflags! {
+    /// The lines drawn by a `TextDecoration`.
+    #[repr(C)]
+    #[derive(Default, Deserialize, MallocSizeOf, Serialize, PeekPoke)]
+    pub struct TextDecorationLines: u8 {
+        const UNDERLINE = 1 << 0;
+        const OVERLINE = 1 << 1;
+        const LINE_THROUGH = 1 << 2;
+    }
+}
+
+/// Lines drawn along a text item, as for CSS `text-decoration`. The lines span
+/// the bounds of the item horizontally, and are positioned relative to the
+/// baseline of its first glyph.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct TextDecoration {
+    pub lines: TextDecorationLines,
+    pub style: LineStyle,
+    pub color: ColorF,
+    pub thickness: f32,
+    /// The offsets from the baseline to the top of each line, with y pointing
+    /// down. These usually come from the font metrics.
+    pub underline_offset: f32,
+    pub overline_offset: f32,
+    pub line_through_offset: f32,
+    /// Whether underlines and overlines are interrupted where they cross glyph
+    /// outlines, as for CSS `text-decoration-skip-ink`. Line-throughs never are.
+    pub skip_ink: bool,
+}
+
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
 pub struct TextDisplayItem {
     pub common: CommonItemProperties,
@@ -420,8 +539,100 @@ pub struct TextDisplayItem {
     pub font_key: font::FontInstanceKey,
     pub color: ColorF,
     pub glyph_options: Option<font::GlyphOptions>,
+    pub decoration: Option<TextDecoration>,
 } // IMPLICIT: glyphs: Vec<font::GlyphInstance>
 
+/// A vector shape made of lines and Bézier curves, rasterized by WebRender
+/// into a coverage mask that is then tinted with `color`. The path is either
+/// filled according to `fill_rule`, or stroked if `stroke` is set.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct PathDisplayItem {
+    pub common: CommonItemProperties,
+    /// The area covered by the path, including the stroke if there is one.
+    /// Anything outside of it is clipped out.
+    pub bounds: LayoutRect,
+    pub color: ColorF,
+    pub fill_rule: FillRule,
+    pub stroke: Option<StrokeStyle>,
+} // IMPLICIT: segments: Vec<PathSegment>
+
+/// A path command. Coordinates are in the local space of the path item.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub enum PathSegment {
+    MoveTo(LayoutPoint),
+    LineTo(LayoutPoint),
+    /// A quadratic Bézier curve with a control point and an end point.
+    QuadraticTo(LayoutPoint, LayoutPoint),
+    /// A cubic Bézier curve with two control points and an end point.
+    CubicTo(LayoutPoint, LayoutPoint, LayoutPoint),
+    Close,
+}
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize, Eq, Hash, PeekPoke)]
+pub enum LineJoin {
+    Miter,
+    Round,
+    Bevel,
+}
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize, Eq, Hash, PeekPoke)]
+pub enum LineCap {
+    Butt,
+    Round,
+    Square,
+}
+
+/// The maximum number of entries in a stroke dash array.
+pub const MAX_STROKE_DASHES: usize = 8;
+
+/// How to stroke a path, following the SVG stroke properties.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct StrokeStyle {
+    pub width: f32,
+    pub line_join: LineJoin,
+    pub line_cap: LineCap,
+    pub miter_limit: f32,
+    pub dash_offset: f32,
+    /// The number of valid entries in `dashes`. Zero means a solid stroke.
+    pub dash_count: u32,
+    /// Alternating dash and gap lengths.
+    pub dashes: [f32; MAX_STROKE_DASHES],
+}
+
+impl StrokeStyle {
+    pub fn new(width: f32, line_join: LineJoin, line_cap: LineCap) -> Self {
+        StrokeStyle {
+            width,
+            line_join,
+            line_cap,
+            miter_limit: 4.0,
+            dash_offset: 0.0,
+            dash_count: 0,
+            dashes: [0.0; MAX_STROKE_DASHES],
+        }
+    }
+
+    /// Set the dash pattern. An odd number of entries is repeated to make it
+    /// even, as in SVG, and the result is truncated to `MAX_STROKE_DASHES`.
+    pub fn with_dashes(mut self, dashes: &[f32], dash_offset: f32) -> Self {
+        let repeat = if dashes.len() % 2 == 1 { 2 } else { 1 };
+        let count = (dashes.len() * repeat).min(MAX_STROKE_DASHES);
+        for (dst, src) in self.dashes.iter_mut().zip(dashes.iter().cycle()).take(count) {
+            *dst = *src;
+        }
+        self.dash_count = count as u32;
+        self.dash_offset = dash_offset;
+        self
+    }
+
+    pub fn dashes(&self) -> &[f32] {
+        &self.dashes[.. self.dash_count as usize]
+    }
+}
+
 #[derive(Clone, Copy, Debug, Default, Deserialize, MallocSizeOf, PartialEq, Serialize, PeekPoke)]
 pub struct NormalBorder {
     pub left: BorderSide,
@@ -645,11 +856,62 @@ pub enum ExtendMode {
     Repeat,
 }
 
+/// The color space that gradient stops are interpolated in.
+///
+/// See https://drafts.csswg.org/css-color-4/#interpolation-space
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, Hash, Eq, MallocSizeOf, PartialEq, Serialize, Deserialize, PeekPoke)]
+pub enum GradientColorSpace {
+    /// Premultiplied, gamma encoded sRGB.
+    Srgb = 0,
+    /// Premultiplied sRGB without the transfer function.
+    SrgbLinear = 1,
+    Oklab = 2,
+    /// The polar form of OKLab. The hue is interpolated according to the
+    /// `HueInterpolationMethod`.
+    Oklch = 3,
+    /// The hue is interpolated according to the `HueInterpolationMethod`.
+    Hsl = 4,
+}
+
+impl GradientColorSpace {
+    /// Whether this color space has a hue component.
+    pub fn is_polar(self) -> bool {
+        match self {
+            GradientColorSpace::Oklch | GradientColorSpace::Hsl => true,
+            GradientColorSpace::Srgb |
+            GradientColorSpace::SrgbLinear |
+            GradientColorSpace::Oklab => false,
+        }
+    }
+}
+
+/// How the hue of polar color spaces is interpolated between two stops.
+///
+/// See https://drafts.csswg.org/css-color-4/#hue-interpolation
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, Hash, Eq, MallocSizeOf, PartialEq, Serialize, Deserialize, PeekPoke)]
+pub enum HueInterpolationMethod {
+    Shorter = 0,
+    Longer = 1,
+    Increasing = 2,
+    Decreasing = 3,
+}
+
+/// The interpolation color space of a gradient.
+#[derive(Debug, Copy, Clone, Default, Hash, Eq, MallocSizeOf, PartialEq, Serialize, Deserialize, PeekPoke)]
+pub struct GradientInterpolation {
+    pub color_space: GradientColorSpace,
+    /// Ignored unless the color space is polar.
+    pub hue: HueInterpolationMethod,
+}
+
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
 pub struct Gradient {
     pub start_point: LayoutPoint,
     pub end_point: LayoutPoint,
     pub extend_mode: ExtendMode,
+    pub interpolation: GradientInterpolation,
 } // IMPLICIT: stops: Vec<GradientStop>
 
 impl Gradient {
@@ -681,7 +943,11 @@ pub struct GradientDisplayItem {
 #[derive(Clone, Copy, Debug, Default, Deserialize, MallocSizeOf, PartialEq, Serialize, PeekPoke)]
 pub struct GradientStop {
     pub offset: f32,
+    /// The color of the stop, or the default value of the binding if the
+    /// color is animated.
     pub color: ColorF,
+    /// The animated color property the stop is read from, if any.
+    pub color_binding: Option<PropertyBindingKey<ColorF>>,
 }
 
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
@@ -691,6 +957,7 @@ pub struct RadialGradient {
     pub start_offset: f32,
     pub end_offset: f32,
     pub extend_mode: ExtendMode,
+    pub interpolation: GradientInterpolation,
 } // IMPLICIT stops: Vec<GradientStop>
 
 impl RadialGradient {
@@ -709,6 +976,7 @@ pub struct ConicGradient {
     pub start_offset: f32,
     pub end_offset: f32,
     pub extend_mode: ExtendMode,
+    pub interpolation: GradientInterpolation,
 } // IMPLICIT stops: Vec<GradientStop>
 
 impl ConicGradient {
@@ -1125,6 +1393,245 @@ pub struct CompositePrimitive {
     pub operator: CompositeOperator,
 }
 
+/// The kind of noise generated by the turbulence filter primitive.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize, PeekPoke)]
+pub enum TurbulenceNoiseType {
+    FractalNoise,
+    Turbulence,
+}
+
+/// See https://drafts.fxtf.org/filter-effects/#feTurbulenceElement
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct TurbulencePrimitive {
+    pub base_frequency_x: f32,
+    pub base_frequency_y: f32,
+    pub num_octaves: u32,
+    pub seed: i32,
+    /// If true, the noise tiles seamlessly across `stitch_rect`.
+    pub stitch_tiles: bool,
+    pub stitch_rect: LayoutRect,
+    pub noise_type: TurbulenceNoiseType,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize, PeekPoke)]
+pub enum MorphologyOperator {
+    Erode,
+    Dilate,
+}
+
+/// See https://drafts.fxtf.org/filter-effects/#feMorphologyElement
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct MorphologyPrimitive {
+    pub input: FilterPrimitiveInput,
+    pub operator: MorphologyOperator,
+    pub radius_x: f32,
+    pub radius_y: f32,
+}
+
+impl MorphologyPrimitive {
+    pub fn sanitize(&mut self) {
+        self.radius_x = self.radius_x.max(0.0);
+        self.radius_y = self.radius_y.max(0.0);
+    }
+}
+
+/// How pixels outside of the input are sampled by the convolve matrix
+/// filter primitive.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize, PeekPoke)]
+pub enum EdgeMode {
+    Duplicate,
+    Wrap,
+    None,
+}
+
+/// The maximum number of columns or rows of a convolve matrix kernel.
+pub const MAX_CONVOLVE_MATRIX_ORDER: u32 = 5;
+
+/// See https://drafts.fxtf.org/filter-effects/#feConvolveMatrixElement
+///
+/// The kernel is stored row by row in the first `order_x * order_y` entries
+/// of `kernel`, so orders are limited to `MAX_CONVOLVE_MATRIX_ORDER`.
+/// cbindgen:derive-eq=false
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct ConvolveMatrixPrimitive {
+    pub input: FilterPrimitiveInput,
+    pub order_x: u32,
+    pub order_y: u32,
+    pub kernel: [f32; 25],
+    pub divisor: f32,
+    pub bias: f32,
+    pub target_x: u32,
+    pub target_y: u32,
+    pub edge_mode: EdgeMode,
+    pub preserve_alpha: bool,
+}
+
+impl ConvolveMatrixPrimitive {
+    pub fn sanitize(&mut self) {
+        self.order_x = self.order_x.min(MAX_CONVOLVE_MATRIX_ORDER).max(1);
+        self.order_y = self.order_y.min(MAX_CONVOLVE_MATRIX_ORDER).max(1);
+        self.target_x = self.target_x.min(self.order_x - 1);
+        self.target_y = self.target_y.min(self.order_y - 1);
+        if self.divisor == 0.0 {
+            self.divisor = 1.0;
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize, PeekPoke)]
+pub enum ColorChannel {
+    R,
+    G,
+    B,
+    A,
+}
+
+/// See https://drafts.fxtf.org/filter-effects/#feDisplacementMapElement
+///
+/// `input1` is the image to displace and `input2` the displacement map.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct DisplacementMapPrimitive {
+    pub input1: FilterPrimitiveInput,
+    pub input2: FilterPrimitiveInput,
+    pub scale: f32,
+    pub x_channel: ColorChannel,
+    pub y_channel: ColorChannel,
+}
+
+/// A light infinitely far away, with angles in degrees.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct DistantLight {
+    pub azimuth: f32,
+    pub elevation: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct PointLight {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+/// A spot light, with `limiting_cone_angle` in degrees. A negative cone
+/// angle means the cone is unbounded.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct SpotLight {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub points_at_x: f32,
+    pub points_at_y: f32,
+    pub points_at_z: f32,
+    pub specular_exponent: f32,
+    pub limiting_cone_angle: f32,
+}
+
+/// The light source of a lighting filter primitive. Positions are in the
+/// local space of the filtered stacking context.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub enum LightSource {
+    Distant(DistantLight),
+    Point(PointLight),
+    Spot(SpotLight),
+}
+
+/// See https://drafts.fxtf.org/filter-effects/#feDiffuseLightingElement
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct DiffuseLightingPrimitive {
+    pub input: FilterPrimitiveInput,
+    pub surface_scale: f32,
+    pub diffuse_constant: f32,
+    pub lighting_color: ColorF,
+    pub light: LightSource,
+}
+
+/// See https://drafts.fxtf.org/filter-effects/#feSpecularLightingElement
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct SpecularLightingPrimitive {
+    pub input: FilterPrimitiveInput,
+    pub surface_scale: f32,
+    pub specular_constant: f32,
+    pub specular_exponent: f32,
+    pub lighting_color: ColorF,
+    pub light: LightSource,
+}
+
+impl SpecularLightingPrimitive {
+    pub fn sanitize(&mut self) {
+        self.specular_exponent = self.specular_exponent.min(128.0).max(1.0);
+    }
+}
+
+/// See https://drafts.fxtf.org/filter-effects/#feTileElement
+///
+/// Since primitive subregions are not supported, the region of the input
+/// that is repeated is given explicitly by `source_rect`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct TilePrimitive {
+    pub input: FilterPrimitiveInput,
+    pub source_rect: LayoutRect,
+}
+
+/// See https://drafts.fxtf.org/filter-effects/#feImageElement
+///
+/// Draws the image stretched to `rect`. Only images are supported, not
+/// references to other elements.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct ImagePrimitive {
+    pub image_key: ImageKey,
+    pub rendering: ImageRendering,
+    pub rect: LayoutRect,
+}
+
+/// The maximum number of inputs of a merge filter primitive.
+pub const MAX_MERGE_INPUTS: usize = 8;
+
+/// See https://drafts.fxtf.org/filter-effects/#feMergeElement
+///
+/// The first `input_count` entries of `inputs` are composited on top of
+/// each other, in order.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, PeekPoke)]
+pub struct MergePrimitive {
+    pub input_count: u32,
+    pub inputs: [FilterPrimitiveInput; MAX_MERGE_INPUTS],
+}
+
+impl MergePrimitive {
+    pub fn new(inputs: &[FilterPrimitiveInput]) -> Self {
+        let mut primitive = MergePrimitive::default();
+        for (src, dest) in inputs.iter().zip(primitive.inputs.iter_mut()) {
+            *dest = *src;
+            primitive.input_count += 1;
+        }
+        primitive
+    }
+
+    pub fn inputs(&self) -> &[FilterPrimitiveInput] {
+        &self.inputs[.. self.input_count as usize]
+    }
+
+    pub fn sanitize(&mut self) {
+        self.input_count = self.input_count.min(MAX_MERGE_INPUTS as u32);
+    }
+}
+
 /// See: https://github.com/eqrion/cbindgen/issues/9
 /// cbindgen:derive-eq=false
 #[repr(C)]
@@ -1142,6 +1649,16 @@ pub enum FilterPrimitiveKind {
     ComponentTransfer(ComponentTransferPrimitive),
     Offset(OffsetPrimitive),
     Composite(CompositePrimitive),
+    Turbulence(TurbulencePrimitive),
+    Morphology(MorphologyPrimitive),
+    /// cbindgen:derive-eq=false
+    ConvolveMatrix(ConvolveMatrixPrimitive),
+    DisplacementMap(DisplacementMapPrimitive),
+    DiffuseLighting(DiffuseLightingPrimitive),
+    SpecularLighting(SpecularLightingPrimitive),
+    Tile(TilePrimitive),
+    Image(ImagePrimitive),
+    Merge(MergePrimitive),
 }
 
 impl Default for FilterPrimitiveKind {
@@ -1155,6 +1672,10 @@ impl FilterPrimitiveKind {
         match self {
             FilterPrimitiveKind::Flood(flood) => flood.sanitize(),
             FilterPrimitiveKind::Opacity(opacity) => opacity.sanitize(),
+            FilterPrimitiveKind::Morphology(morphology) => morphology.sanitize(),
+            FilterPrimitiveKind::ConvolveMatrix(convolve) => convolve.sanitize(),
+            FilterPrimitiveKind::SpecularLighting(lighting) => lighting.sanitize(),
+            FilterPrimitiveKind::Merge(merge) => merge.sanitize(),
 
             // No sanitization needed.
             FilterPrimitiveKind::Identity(..) |
@@ -1164,6 +1685,11 @@ impl FilterPrimitiveKind {
             FilterPrimitiveKind::Composite(..) |
             FilterPrimitiveKind::Blur(..) |
             FilterPrimitiveKind::DropShadow(..) |
+            FilterPrimitiveKind::Turbulence(..) |
+            FilterPrimitiveKind::DisplacementMap(..) |
+            FilterPrimitiveKind::DiffuseLighting(..) |
+            FilterPrimitiveKind::Tile(..) |
+            FilterPrimitiveKind::Image(..) |
             // Component transfer's filter data is sanitized separately.
             FilterPrimitiveKind::ComponentTransfer(..) => {}
         }
@@ -1187,21 +1713,25 @@ impl FilterPrimitive {
 }
 
 /// CSS filter.
+///
+/// The arguments of the blur and color adjustment filters can be bound to
+/// animated float properties, in which case they are updated by
+/// `DynamicProperties` without rebuilding the scene.
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize, PeekPoke)]
 pub enum FilterOp {
     /// Filter that does no transformation of the colors, needed for
     /// debug purposes only.
     Identity,
-    Blur(f32, f32),
-    Brightness(f32),
-    Contrast(f32),
-    Grayscale(f32),
-    HueRotate(f32),
-    Invert(f32),
+    Blur(PropertyBinding<f32>, PropertyBinding<f32>),
+    Brightness(PropertyBinding<f32>),
+    Contrast(PropertyBinding<f32>),
+    Grayscale(PropertyBinding<f32>),
+    HueRotate(PropertyBinding<f32>),
+    Invert(PropertyBinding<f32>),
     Opacity(PropertyBinding<f32>, f32),
-    Saturate(f32),
-    Sepia(f32),
+    Saturate(PropertyBinding<f32>),
+    Sepia(PropertyBinding<f32>),
     DropShadow(Shadow),
     ColorMatrix([f32; 20]),
     SrgbToLinear,
@@ -1332,6 +1862,9 @@ pub struct ImageDisplayItem {
     pub alpha_type: AlphaType,
     /// A hack used by gecko to color a simple bitmap font used for tofu glyphs
     pub color: ColorF,
+    /// The part of the image to draw, in texels (common case: None, the whole
+    /// image). This lets several sprites share one image atlas.
+    pub source_rect: Option<DeviceIntRect>,
 }
 
 /// This describes a background-image and its tiling. It repeats in a grid to fill
@@ -1352,6 +1885,16 @@ pub struct RepeatingImageDisplayItem {
     pub alpha_type: AlphaType,
     /// A hack used by gecko to color a simple bitmap font used for tofu glyphs
     pub color: ColorF,
+    /// The part of the image to repeat, in texels (common case: None, the
+    /// whole image).
+    pub source_rect: Option<DeviceIntRect>,
+    /// How the tiles fit the bounds horizontally. `Round` and `Space` adjust
+    /// `stretch_size` and `tile_spacing` respectively so that a whole number
+    /// of tiles fits, as for CSS `background-repeat`; `tile_spacing` is then
+    /// the minimum spacing. `Stretch` draws a single tile over the bounds.
+    pub repeat_horizontal: RepeatMode,
+    /// How the tiles fit the bounds vertically.
+    pub repeat_vertical: RepeatMode,
 }
 
 #[repr(u8)]
@@ -1360,6 +1903,13 @@ pub enum ImageRendering {
     Auto = 0,
     CrispEdges = 1,
     Pixelated = 2,
+    /// Like `Auto`, but images drawn at less than half of their size are
+    /// first downscaled to the nearest level of a mip chain in render tasks,
+    /// each halving the previous level with a box filter. This avoids the
+    /// aliasing of minified photos, at the cost of the extra render tasks the
+    /// first time an image is drawn at a given size. Images with tile spacing
+    /// are drawn as with `Auto`.
+    HighQuality = 3,
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize, PeekPoke)]
@@ -1601,6 +2151,8 @@ impl ComplexClipRegion {
     }
 }
 
+/// The maximum number of vertices of the hit-testing polygon that can be
+/// attached to an image mask clip. Polygon clip items are not bound by this.
 pub const POLYGON_CLIP_VERTEX_MAX: usize = 32;
 
 #[repr(u8)]
@@ -1731,6 +2283,7 @@ impl DisplayItem {
             DisplayItem::RectClip(..) => "rect_clip",
             DisplayItem::RoundedRectClip(..) => "rounded_rect_clip",
             DisplayItem::ImageMaskClip(..) => "image_mask_clip",
+            DisplayItem::PolygonClip(..) => "polygon_clip",
             DisplayItem::ClipChain(..) => "clip_chain",
             DisplayItem::ConicGradient(..) => "conic_gradient",
             DisplayItem::Gradient(..) => "gradient",
@@ -1738,6 +2291,7 @@ impl DisplayItem {
             DisplayItem::Image(..) => "image",
             DisplayItem::RepeatingImage(..) => "repeating_image",
             DisplayItem::Line(..) => "line",
+            DisplayItem::Path(..) => "path",
             DisplayItem::PopAllShadows => "pop_all_shadows",
             DisplayItem::PopReferenceFrame => "pop_reference_frame",
             DisplayItem::PopStackingContext => "pop_stacking_context",
@@ -1787,6 +2341,9 @@ impl_default_for_enums! {
     ComponentTransferFuncType => Identity,
     ClipMode => Clip,
     FillRule => Nonzero,
+    PathSegment => Close,
+    LineJoin => Miter,
+    LineCap => Butt,
     ClipId => ClipId::invalid(),
     ReferenceFrameKind => Transform {
         is_2d_scale_translation: false,
@@ -1806,5 +2363,15 @@ impl_default_for_enums! {
     YuvFormat => NV12,
     FilterPrimitiveInput => Original,
     ColorSpace => Srgb,
-    CompositeOperator => Over
+    CompositeOperator => Over,
+    TurbulenceNoiseType => Turbulence,
+    MorphologyOperator => Erode,
+    EdgeMode => Duplicate,
+    ColorChannel => A,
+    LightSource => Distant(DistantLight::default()),
+    ScrollSnapStrictness => None,
+    OverscrollMode => Clamp,
+    HitTestShape => Rect,
+    HueInterpolationMethod => Shorter,
+    GradientColorSpace => Srgb
 }
@@ -37,6 +37,10 @@ impl From<DisplayItemRef<'_, '_>> for CachedDisplayItem {
                 item: *item,
                 data: item_ref.glyphs().bytes().to_vec(),
             },
+            DisplayItem::Path(..) => CachedDisplayItem {
+                item: *item,
+                data: item_ref.path_segments().bytes().to_vec(),
+            },
             _ => CachedDisplayItem {
                 item: *item,
                 data: Vec::new(),
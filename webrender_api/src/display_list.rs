@@ -22,7 +22,7 @@ use crate::display_item as di;
 use crate::display_item_cache::*;
 use crate::{APZScrollGeneration, HasScrollLinkedEffect, PipelineId, PropertyBinding};
 use crate::gradient_builder::GradientBuilder;
-use crate::color::ColorF;
+use crate::color::{ColorF, RgbColorSpace};
 use crate::font::{FontInstanceKey, GlyphInstance, GlyphOptions};
 use crate::image::{ColorDepth, ImageKey};
 use crate::units::*;
@@ -312,6 +312,10 @@ impl<'de> Deserialize<'de> for DisplayListWithCache {
                     DisplayListBuilder::push_iter_impl(&mut temp, glyphs);
                     Real::Text(v)
                 },
+                Debug::Path(v, segments) => {
+                    DisplayListBuilder::push_iter_impl(&mut temp, segments);
+                    Real::Path(v)
+                },
                 Debug::Iframe(v) => {
                     Real::Iframe(v)
                 }
@@ -350,6 +354,7 @@ impl<'de> Deserialize<'de> for DisplayListWithCache {
                 Debug::RectClip(v) => Real::RectClip(v),
                 Debug::RoundedRectClip(v) => Real::RoundedRectClip(v),
                 Debug::ImageMaskClip(v) => Real::ImageMaskClip(v),
+                Debug::PolygonClip(v) => Real::PolygonClip(v),
                 Debug::Rectangle(v) => Real::Rectangle(v),
                 Debug::ClearRectangle(v) => Real::ClearRectangle(v),
                 Debug::HitTest(v) => Real::HitTest(v),
@@ -402,6 +407,7 @@ pub struct BuiltDisplayListIter<'a> {
     cur_item: di::DisplayItem,
     cur_stops: ItemRange<'a, di::GradientStop>,
     cur_glyphs: ItemRange<'a, GlyphInstance>,
+    cur_path_segments: ItemRange<'a, di::PathSegment>,
     cur_filters: ItemRange<'a, di::FilterOp>,
     cur_filter_data: Vec<TempFilterData<'a>>,
     cur_filter_primitives: ItemRange<'a, di::FilterPrimitive>,
@@ -504,6 +510,10 @@ impl<'a, 'b> DisplayItemRef<'a, 'b> {
         self.iter.glyphs()
     }
 
+    pub fn path_segments(&self) -> ItemRange<di::PathSegment> {
+        self.iter.path_segments()
+    }
+
     pub fn gradient_stops(&self) -> ItemRange<di::GradientStop> {
         self.iter.gradient_stops()
     }
@@ -636,6 +646,10 @@ impl BuiltDisplayList {
                     v,
                     item.iter.cur_glyphs.iter().collect()
                 ),
+                Real::Path(v) => Debug::Path(
+                    v,
+                    item.iter.cur_path_segments.iter().collect()
+                ),
                 Real::SetFilterOps => Debug::SetFilterOps(
                     item.iter.cur_filters.iter().collect()
                 ),
@@ -671,6 +685,7 @@ impl BuiltDisplayList {
                 Real::RectClip(v) => Debug::RectClip(v),
                 Real::RoundedRectClip(v) => Debug::RoundedRectClip(v),
                 Real::ImageMaskClip(v) => Debug::ImageMaskClip(v),
+                Real::PolygonClip(v) => Debug::PolygonClip(v),
                 Real::Rectangle(v) => Debug::Rectangle(v),
                 Real::ClearRectangle(v) => Debug::ClearRectangle(v),
                 Real::HitTest(v) => Debug::HitTest(v),
@@ -730,6 +745,7 @@ impl<'a> BuiltDisplayListIter<'a> {
             cur_item: di::DisplayItem::PopStackingContext,
             cur_stops: ItemRange::default(),
             cur_glyphs: ItemRange::default(),
+            cur_path_segments: ItemRange::default(),
             cur_filters: ItemRange::default(),
             cur_filter_data: Vec::new(),
             cur_filter_primitives: ItemRange::default(),
@@ -772,6 +788,10 @@ impl<'a> BuiltDisplayListIter<'a> {
         self.cached_item_range_or(self.cur_glyphs)
     }
 
+    pub fn path_segments(&self) -> ItemRange<di::PathSegment> {
+        self.cached_item_range_or(self.cur_path_segments)
+    }
+
     pub fn gradient_stops(&self) -> ItemRange<di::GradientStop> {
         self.cached_item_range_or(self.cur_stops)
     }
@@ -884,6 +904,10 @@ impl<'a> BuiltDisplayListIter<'a> {
                 self.cur_glyphs = skip_slice::<GlyphInstance>(&mut self.data);
                 self.debug_stats.log_slice("text.glyphs", &self.cur_glyphs);
             }
+            Path(_) => {
+                self.cur_path_segments = skip_slice::<di::PathSegment>(&mut self.data);
+                self.debug_stats.log_slice("path.segments", &self.cur_path_segments);
+            }
             ReuseItems(key) => {
                 match self.cache {
                     Some(cache) => {
@@ -1317,6 +1341,25 @@ impl DisplayListBuilder {
         let item = di::DisplayItem::Rectangle(di::RectangleDisplayItem {
             common: *common,
             color: PropertyBinding::Value(color),
+            color_space: RgbColorSpace::Srgb,
+            bounds,
+        });
+        self.push_item(&item);
+    }
+
+    /// Pushes a solid color rectangle whose color is expressed in the given
+    /// color space rather than sRGB.
+    pub fn push_rect_with_color_space(
+        &mut self,
+        common: &di::CommonItemProperties,
+        bounds: LayoutRect,
+        color: ColorF,
+        color_space: RgbColorSpace,
+    ) {
+        let item = di::DisplayItem::Rectangle(di::RectangleDisplayItem {
+            common: *common,
+            color: PropertyBinding::Value(color),
+            color_space,
             bounds,
         });
         self.push_item(&item);
@@ -1331,6 +1374,7 @@ impl DisplayListBuilder {
         let item = di::DisplayItem::Rectangle(di::RectangleDisplayItem {
             common: *common,
             color,
+            color_space: RgbColorSpace::Srgb,
             bounds,
         });
         self.push_item(&item);
@@ -1353,13 +1397,51 @@ impl DisplayListBuilder {
         common: &di::CommonItemProperties,
         tag: di::ItemTag,
     ) {
+        self.push_hit_test_shape(common, tag, di::HitTestShape::Rect, false);
+    }
+
+    /// Push a hit-test item with a rounded rect or elliptical region. Use
+    /// `push_hit_test_polygon` for polygonal regions.
+    pub fn push_hit_test_shape(
+        &mut self,
+        common: &di::CommonItemProperties,
+        tag: di::ItemTag,
+        shape: di::HitTestShape,
+        pass_through: bool,
+    ) {
+        debug_assert!(!matches!(shape, di::HitTestShape::Polygon(..)));
+
         let item = di::DisplayItem::HitTest(di::HitTestDisplayItem {
             common: *common,
             tag,
+            shape,
+            pass_through,
         });
         self.push_item(&item);
     }
 
+    /// Push a hit-test item with a polygonal region. The points are in the
+    /// local space of `common.spatial_id`.
+    pub fn push_hit_test_polygon(
+        &mut self,
+        common: &di::CommonItemProperties,
+        tag: di::ItemTag,
+        points: &[LayoutPoint],
+        fill_rule: di::FillRule,
+        pass_through: bool,
+    ) {
+        let item = di::DisplayItem::HitTest(di::HitTestDisplayItem {
+            common: *common,
+            tag,
+            shape: di::HitTestShape::Polygon(fill_rule),
+            pass_through,
+        });
+
+        self.push_item(&di::DisplayItem::SetPoints);
+        self.push_iter(points);
+        self.push_item(&item);
+    }
+
     pub fn push_line(
         &mut self,
         common: &di::CommonItemProperties,
@@ -1381,6 +1463,29 @@ impl DisplayListBuilder {
         self.push_item(&item);
     }
 
+    /// Push a vector path. Segments are in the same coordinate space as
+    /// `bounds`, which must enclose the path including its stroke.
+    pub fn push_path(
+        &mut self,
+        common: &di::CommonItemProperties,
+        bounds: LayoutRect,
+        segments: &[di::PathSegment],
+        color: ColorF,
+        fill_rule: di::FillRule,
+        stroke: Option<di::StrokeStyle>,
+    ) {
+        let item = di::DisplayItem::Path(di::PathDisplayItem {
+            common: *common,
+            bounds,
+            color,
+            fill_rule,
+            stroke,
+        });
+
+        self.push_item(&item);
+        self.push_iter(segments);
+    }
+
     pub fn push_image(
         &mut self,
         common: &di::CommonItemProperties,
@@ -1397,6 +1502,32 @@ impl DisplayListBuilder {
             image_rendering,
             alpha_type,
             color,
+            source_rect: None,
+        });
+
+        self.push_item(&item);
+    }
+
+    /// Push an image that draws the `source_rect` part of the image, in
+    /// texels, stretched over `bounds`.
+    pub fn push_image_with_source_rect(
+        &mut self,
+        common: &di::CommonItemProperties,
+        bounds: LayoutRect,
+        source_rect: DeviceIntRect,
+        image_rendering: di::ImageRendering,
+        alpha_type: di::AlphaType,
+        key: ImageKey,
+        color: ColorF,
+    ) {
+        let item = di::DisplayItem::Image(di::ImageDisplayItem {
+            common: *common,
+            bounds,
+            image_key: key,
+            image_rendering,
+            alpha_type,
+            color,
+            source_rect: Some(source_rect),
         });
 
         self.push_item(&item);
@@ -1422,6 +1553,43 @@ impl DisplayListBuilder {
             image_rendering,
             alpha_type,
             color,
+            source_rect: None,
+            repeat_horizontal: di::RepeatMode::Repeat,
+            repeat_vertical: di::RepeatMode::Repeat,
+        });
+
+        self.push_item(&item);
+    }
+
+    /// Push a repeating image with an optional source rect and the given
+    /// repeat modes. See `RepeatingImageDisplayItem` for how the modes
+    /// adjust `stretch_size` and `tile_spacing`.
+    pub fn push_repeating_image_with_modes(
+        &mut self,
+        common: &di::CommonItemProperties,
+        bounds: LayoutRect,
+        stretch_size: LayoutSize,
+        tile_spacing: LayoutSize,
+        source_rect: Option<DeviceIntRect>,
+        repeat_horizontal: di::RepeatMode,
+        repeat_vertical: di::RepeatMode,
+        image_rendering: di::ImageRendering,
+        alpha_type: di::AlphaType,
+        key: ImageKey,
+        color: ColorF,
+    ) {
+        let item = di::DisplayItem::RepeatingImage(di::RepeatingImageDisplayItem {
+            common: *common,
+            bounds,
+            image_key: key,
+            stretch_size,
+            tile_spacing,
+            image_rendering,
+            alpha_type,
+            color,
+            source_rect,
+            repeat_horizontal,
+            repeat_vertical,
         });
 
         self.push_item(&item);
@@ -1459,17 +1627,48 @@ impl DisplayListBuilder {
         color: ColorF,
         glyph_options: Option<GlyphOptions>,
     ) {
-        let item = di::DisplayItem::Text(di::TextDisplayItem {
+        self.push_text_impl(common, bounds, glyphs, font_key, color, glyph_options, None);
+    }
+
+    /// Pushes a text run along with its underline, overline and line-through.
+    /// If the run is split into several items, the decoration is attached to
+    /// the first one and only its glyphs are considered when skipping ink.
+    pub fn push_text_with_decoration(
+        &mut self,
+        common: &di::CommonItemProperties,
+        bounds: LayoutRect,
+        glyphs: &[GlyphInstance],
+        font_key: FontInstanceKey,
+        color: ColorF,
+        glyph_options: Option<GlyphOptions>,
+        decoration: di::TextDecoration,
+    ) {
+        self.push_text_impl(common, bounds, glyphs, font_key, color, glyph_options, Some(decoration));
+    }
+
+    fn push_text_impl(
+        &mut self,
+        common: &di::CommonItemProperties,
+        bounds: LayoutRect,
+        glyphs: &[GlyphInstance],
+        font_key: FontInstanceKey,
+        color: ColorF,
+        glyph_options: Option<GlyphOptions>,
+        decoration: Option<di::TextDecoration>,
+    ) {
+        let mut item = di::TextDisplayItem {
             common: *common,
             bounds,
             color,
             font_key,
             glyph_options,
-        });
+            decoration,
+        };
 
         for split_glyphs in glyphs.chunks(MAX_TEXT_RUN_LENGTH) {
-            self.push_item(&item);
+            self.push_item(&di::DisplayItem::Text(item));
             self.push_iter(split_glyphs);
+            item.decoration = None;
         }
     }
 
@@ -1886,6 +2085,35 @@ impl DisplayListBuilder {
         scroll_offset_generation: APZScrollGeneration,
         has_scroll_linked_effect: HasScrollLinkedEffect,
         key: di::SpatialTreeItemKey,
+    ) -> di::SpatialId {
+        self.define_scroll_frame_with_physics(
+            parent_space,
+            external_id,
+            content_rect,
+            frame_rect,
+            external_scroll_offset,
+            scroll_offset_generation,
+            has_scroll_linked_effect,
+            key,
+            di::ScrollPhysics::default(),
+            &[],
+        )
+    }
+
+    /// Define a scroll frame along with the snapping and overscroll behavior
+    /// it has when it is scrolled by `Transaction::scroll`.
+    pub fn define_scroll_frame_with_physics(
+        &mut self,
+        parent_space: di::SpatialId,
+        external_id: di::ExternalScrollId,
+        content_rect: LayoutRect,
+        frame_rect: LayoutRect,
+        external_scroll_offset: LayoutVector2D,
+        scroll_offset_generation: APZScrollGeneration,
+        has_scroll_linked_effect: HasScrollLinkedEffect,
+        key: di::SpatialTreeItemKey,
+        physics: di::ScrollPhysics,
+        snap_points: &[di::ScrollSnapPoint],
     ) -> di::SpatialId {
         let scroll_frame_id = self.generate_spatial_index();
         let current_offset = self.current_offset(parent_space);
@@ -1907,8 +2135,12 @@ impl DisplayListBuilder {
             scroll_offset_generation,
             has_scroll_linked_effect,
             key,
+            physics,
         });
 
+        for snap_point in snap_points {
+            self.push_spatial_tree_item(&di::SpatialTreeItem::ScrollSnapPoint(*snap_point));
+        }
         self.push_spatial_tree_item(&descriptor);
 
         scroll_frame_id
@@ -1964,6 +2196,31 @@ impl DisplayListBuilder {
         id
     }
 
+    /// Define an analytic polygon clip. The points are in the local space of
+    /// `parent_space_and_clip.spatial_id`, and there is no upper limit on
+    /// their count other than the size of the GPU cache rows (2048 points).
+    pub fn define_clip_polygon(
+        &mut self,
+        parent_space_and_clip: &di::SpaceAndClipInfo,
+        points: &[LayoutPoint],
+        fill_rule: di::FillRule,
+    ) -> di::ClipId {
+        let id = self.generate_clip_index();
+
+        let current_offset = self.current_offset(parent_space_and_clip.spatial_id);
+
+        let item = di::DisplayItem::PolygonClip(di::PolygonClipDisplayItem {
+            id,
+            parent_space_and_clip: *parent_space_and_clip,
+            fill_rule,
+        });
+
+        self.push_item(&di::DisplayItem::SetPoints);
+        self.push_iter(points.iter().map(|point| *point + current_offset));
+        self.push_item(&item);
+        id
+    }
+
     pub fn define_clip_rect(
         &mut self,
         parent_space_and_clip: &di::SpaceAndClipInfo,
@@ -1988,11 +2245,30 @@ impl DisplayListBuilder {
         &mut self,
         parent_space_and_clip: &di::SpaceAndClipInfo,
         clip: di::ComplexClipRegion,
+    ) -> di::ClipId {
+        self.define_animated_clip_rounded_rect(
+            parent_space_and_clip,
+            PropertyBinding::Value(clip),
+        )
+    }
+
+    /// Define a rounded rect clip whose region can be animated through
+    /// `DynamicProperties::clip_regions`. Hit testing always uses the
+    /// default region.
+    pub fn define_animated_clip_rounded_rect(
+        &mut self,
+        parent_space_and_clip: &di::SpaceAndClipInfo,
+        clip: PropertyBinding<di::ComplexClipRegion>,
     ) -> di::ClipId {
         let id = self.generate_clip_index();
 
         let current_offset = self.current_offset(parent_space_and_clip.spatial_id);
 
+        let (clip, binding) = match clip {
+            PropertyBinding::Value(clip) => (clip, None),
+            PropertyBinding::Binding(key, clip) => (clip, Some(key)),
+        };
+
         let clip = di::ComplexClipRegion {
             rect: clip.rect.translate(current_offset),
             ..clip
@@ -2002,6 +2278,8 @@ impl DisplayListBuilder {
             id,
             parent_space_and_clip: *parent_space_and_clip,
             clip,
+            binding,
+            binding_offset: current_offset,
         });
 
         self.push_item(&item);
@@ -406,6 +406,24 @@ pub struct FontInstanceData {
 
 pub type GlyphIndex = u32;
 
+/// How a glyph is drawn relative to the font instance it is rendered with.
+/// Vertical text with `text-orientation: mixed` keeps CJK glyphs upright and
+/// turns the glyphs of horizontal scripts sideways.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, MallocSizeOf, PartialEq, Serialize, PeekPoke)]
+pub enum GlyphOrientation {
+    /// The glyph is drawn as the font instance lays it out.
+    Upright,
+    /// The glyph is rotated 90 degrees clockwise about its origin.
+    Sideways,
+}
+
+impl Default for GlyphOrientation {
+    fn default() -> Self {
+        GlyphOrientation::Upright
+    }
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize, PeekPoke)]
 pub struct GlyphInstance {
@@ -413,6 +431,39 @@ pub struct GlyphInstance {
     pub point: LayoutPoint,
 }
 
+impl GlyphInstance {
+    /// Set in `index` for sideways glyphs. Glyph ids are 16 bits, so the top
+    /// bit of the index is free to carry the orientation of the glyph without
+    /// growing this struct, which is stored for every glyph of a display list
+    /// and shared with C++.
+    pub const SIDEWAYS: GlyphIndex = 1 << 31;
+
+    pub fn new(index: GlyphIndex, point: LayoutPoint, orientation: GlyphOrientation) -> Self {
+        let flag = match orientation {
+            GlyphOrientation::Upright => 0,
+            GlyphOrientation::Sideways => Self::SIDEWAYS,
+        };
+
+        GlyphInstance {
+            index: index | flag,
+            point,
+        }
+    }
+
+    /// The index of the glyph in its font, without the orientation.
+    pub fn glyph_index(&self) -> GlyphIndex {
+        self.index & !Self::SIDEWAYS
+    }
+
+    pub fn orientation(&self) -> GlyphOrientation {
+        if self.index & Self::SIDEWAYS != 0 {
+            GlyphOrientation::Sideways
+        } else {
+            GlyphOrientation::Upright
+        }
+    }
+}
+
 impl Default for GlyphInstance {
     fn default() -> Self {
         GlyphInstance {
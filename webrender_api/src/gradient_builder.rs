@@ -8,7 +8,8 @@ use crate::units::*;
 
 /// Construct a gradient to be used in display lists.
 ///
-/// Each gradient needs at least two stops.
+/// Each gradient needs at least two stops. The produced gradients interpolate
+/// in sRGB, their `interpolation` can be changed afterwards.
 pub struct GradientBuilder {
     stops: Vec<di::GradientStop>,
 }
@@ -55,6 +56,7 @@ impl GradientBuilder {
             start_point: start_point + start_to_end * start_offset,
             end_point: start_point + start_to_end * end_offset,
             extend_mode,
+            interpolation: di::GradientInterpolation::default(),
         }
     }
 
@@ -72,11 +74,11 @@ impl GradientBuilder {
             // The shader cannot handle a non positive radius. So
             // reuse the stops vector and construct an equivalent
             // gradient.
-            let last_color = self.stops.last().unwrap().color;
+            let last = *self.stops.last().unwrap();
 
             self.stops.clear();
-            self.stops.push(di::GradientStop { offset: 0.0, color: last_color, });
-            self.stops.push(di::GradientStop { offset: 1.0, color: last_color, });
+            self.stops.push(di::GradientStop { offset: 0.0, ..last });
+            self.stops.push(di::GradientStop { offset: 1.0, ..last });
 
             return di::RadialGradient {
                 center,
@@ -84,6 +86,7 @@ impl GradientBuilder {
                 start_offset: 0.0,
                 end_offset: 1.0,
                 extend_mode,
+                interpolation: di::GradientInterpolation::default(),
             };
         }
 
@@ -96,6 +99,7 @@ impl GradientBuilder {
             start_offset,
             end_offset,
             extend_mode,
+            interpolation: di::GradientInterpolation::default(),
         }
     }
 
@@ -115,6 +119,7 @@ impl GradientBuilder {
             start_offset,
             end_offset,
             extend_mode,
+            interpolation: di::GradientInterpolation::default(),
         }
     }
 
@@ -155,10 +160,10 @@ impl GradientBuilder {
                     // This gradient is two colors split at the offset of the stops,
                     // so create a gradient with two colors split at 0.5 and adjust
                     // the gradient line so 0.5 is at the offset of the stops
-                    stops.push(di::GradientStop { color: first.color, offset: 0.0, });
-                    stops.push(di::GradientStop { color: first.color, offset: 0.5, });
-                    stops.push(di::GradientStop { color: last.color, offset: 0.5, });
-                    stops.push(di::GradientStop { color: last.color, offset: 1.0, });
+                    stops.push(di::GradientStop { offset: 0.0, ..first });
+                    stops.push(di::GradientStop { offset: 0.5, ..first });
+                    stops.push(di::GradientStop { offset: 0.5, ..last });
+                    stops.push(di::GradientStop { offset: 1.0, ..last });
 
                     let offset = last.offset;
 
@@ -169,8 +174,8 @@ impl GradientBuilder {
                     // position should just display the last color. I believe the
                     // spec says that it should be the average color of the gradient,
                     // but this matches what Gecko and Blink does
-                    stops.push(di::GradientStop { color: last.color, offset: 0.0, });
-                    stops.push(di::GradientStop { color: last.color, offset: 1.0, });
+                    stops.push(di::GradientStop { offset: 0.0, ..last });
+                    stops.push(di::GradientStop { offset: 1.0, ..last });
 
                     (0.0, 1.0)
                 }
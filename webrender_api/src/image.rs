@@ -9,7 +9,7 @@ use peek_poke::PeekPoke;
 use std::ops::{Add, Sub};
 use std::sync::Arc;
 // local imports
-use crate::{IdNamespace, TileSize};
+use crate::{IdNamespace, RgbColorSpace, TileSize};
 use crate::display_item::ImageRendering;
 use crate::font::{FontInstanceKey, FontInstanceData, FontKey, FontTemplate};
 use crate::units::*;
@@ -168,12 +168,43 @@ pub enum ImageFormat {
     RGBAI32 = 7,
     /// Four channels, byte storage.
     RGBA8 = 8,
+
+    /// S3TC/DXT1 compressed RGB with 1-bit alpha, in 8 byte 4x4 blocks.
+    BC1 = 9,
+    /// S3TC/DXT5 compressed RGBA, in 16 byte 4x4 blocks.
+    BC3 = 10,
+    /// BPTC compressed RGBA, in 16 byte 4x4 blocks.
+    BC7 = 11,
+    /// ETC2 compressed RGB, in 8 byte 4x4 blocks.
+    ETC2RGB8 = 12,
+    /// ETC2 compressed RGB with EAC alpha, in 16 byte 4x4 blocks.
+    ETC2RGBA8 = 13,
+    /// ASTC LDR compressed RGBA, in 16 byte 4x4 blocks.
+    ASTC4X4 = 14,
+
+    /// Four channels, half-float storage. Values may exceed 1.0.
+    RGBA16F = 15,
+    /// Four channels packed into 32 bits, with 10 bits per color channel and
+    /// 2 bits of alpha.
+    RGB10A2 = 16,
 }
 
 impl ImageFormat {
     /// Returns the number of bytes per pixel for the given format.
+    ///
+    /// Block-compressed formats don't have a whole number of bytes per pixel,
+    /// so this panics for them. Use `bytes_per_block_row` and `block_rows` for
+    /// anything that addresses or measures their data.
     pub fn bytes_per_pixel(self) -> i32 {
         match self {
+            ImageFormat::BC1 |
+            ImageFormat::BC3 |
+            ImageFormat::BC7 |
+            ImageFormat::ETC2RGB8 |
+            ImageFormat::ETC2RGBA8 |
+            ImageFormat::ASTC4X4 => {
+                panic!("{:?} has no whole number of bytes per pixel", self)
+            }
             ImageFormat::R8 => 1,
             ImageFormat::R16 => 2,
             ImageFormat::BGRA8 => 4,
@@ -182,8 +213,61 @@ impl ImageFormat {
             ImageFormat::RG16 => 4,
             ImageFormat::RGBAI32 => 16,
             ImageFormat::RGBA8 => 4,
+            ImageFormat::RGBA16F => 8,
+            ImageFormat::RGB10A2 => 4,
         }
     }
+
+    /// Returns true for the high bit depth color formats that images can be
+    /// stored in, `RGBA16F` and `RGB10A2`.
+    pub fn is_high_bit_depth_color(self) -> bool {
+        matches!(self, ImageFormat::RGBA16F | ImageFormat::RGB10A2)
+    }
+
+    /// Returns true if the data is stored as blocks of compressed pixels.
+    pub fn is_compressed(self) -> bool {
+        self.block_size() != 1
+    }
+
+    /// Returns the width and height in pixels of the blocks the data is
+    /// stored in. Uncompressed formats use 1x1 blocks.
+    pub fn block_size(self) -> i32 {
+        match self {
+            ImageFormat::BC1 |
+            ImageFormat::BC3 |
+            ImageFormat::BC7 |
+            ImageFormat::ETC2RGB8 |
+            ImageFormat::ETC2RGBA8 |
+            ImageFormat::ASTC4X4 => 4,
+            _ => 1,
+        }
+    }
+
+    /// Returns the number of bytes in each block of the data.
+    pub fn bytes_per_block(self) -> i32 {
+        match self {
+            ImageFormat::BC1 | ImageFormat::ETC2RGB8 => 8,
+            ImageFormat::BC3 |
+            ImageFormat::BC7 |
+            ImageFormat::ETC2RGBA8 |
+            ImageFormat::ASTC4X4 => 16,
+            _ => self.bytes_per_pixel(),
+        }
+    }
+
+    /// Returns the number of bytes needed to store a row of blocks spanning
+    /// `width` pixels.
+    pub fn bytes_per_block_row(self, width: i32) -> i32 {
+        let block_size = self.block_size();
+        (width + block_size - 1) / block_size * self.bytes_per_block()
+    }
+
+    /// Returns the number of rows of blocks needed to store `height` rows of
+    /// pixels.
+    pub fn block_rows(self, height: i32) -> i32 {
+        let block_size = self.block_size();
+        (height + block_size - 1) / block_size
+    }
 }
 
 /// Specifies the color depth of an image. Currently only used for YUV images.
@@ -264,6 +348,10 @@ pub struct ImageDescriptor {
     pub offset: i32,
     /// Various bool flags related to this descriptor.
     pub flags: ImageDescriptorFlags,
+    /// The color space of the image data. Images that aren't in the output
+    /// color space of the document are converted when they are drawn.
+    #[serde(default)]
+    pub color_space: RgbColorSpace,
 }
 
 impl ImageDescriptor {
@@ -280,18 +368,20 @@ impl ImageDescriptor {
             stride: None,
             offset: 0,
             flags,
+            color_space: RgbColorSpace::Srgb,
         }
     }
 
     /// Returns the stride, either via an explicit stride stashed on the object
-    /// or by the default computation.
+    /// or by the default computation. For block-compressed formats this is the
+    /// distance between rows of blocks rather than rows of pixels.
     pub fn compute_stride(&self) -> i32 {
-        self.stride.unwrap_or(self.size.width * self.format.bytes_per_pixel())
+        self.stride.unwrap_or(self.format.bytes_per_block_row(self.size.width))
     }
 
     /// Computes the total size of the image, in bytes.
     pub fn compute_total_size(&self) -> i32 {
-        self.compute_stride() * self.size.height
+        self.compute_stride() * self.format.block_rows(self.size.height)
     }
 
     /// Computes the bounding rectangle for the image, rooted at (0, 0).
@@ -36,6 +36,7 @@ extern crate malloc_size_of;
 extern crate peek_poke;
 
 pub mod channel;
+mod animation;
 mod color;
 mod display_item;
 mod display_item_cache;
@@ -45,6 +46,7 @@ mod gradient_builder;
 mod image;
 pub mod units;
 
+pub use crate::animation::*;
 pub use crate::color::*;
 pub use crate::display_item::*;
 pub use crate::display_item_cache::DisplayItemCache;
@@ -246,6 +248,9 @@ pub enum Checkpoint {
     /// NotificationRequests get notified with this if they get dropped without having been
     /// notified. This provides the guarantee that if a request is created it will get notified.
     TransactionDropped,
+    /// Requests passed to `Transaction::add_animation` get notified with this when the
+    /// animation finishes playing.
+    AnimationFinished,
 }
 
 /// A handler to notify when a transaction reaches certain stages of the rendering
@@ -291,7 +296,130 @@ pub trait ApiHitTester: Send + Sync {
     /// Does a hit test on display items in the specified document, at the given
     /// point. The vector of hit results will contain all display items that match,
     /// ordered from front to back.
-    fn hit_test(&self, point: WorldPoint) -> HitTestResult;
+    fn hit_test(&self, point: WorldPoint) -> HitTestResult {
+        self.hit_test_query(&HitTestQuery::point(point))
+    }
+
+    /// Does a hit test on display items in the specified document, as described
+    /// by the query. The vector of hit results is ordered from front to back.
+    fn hit_test_query(&self, query: &HitTestQuery) -> HitTestResult;
+}
+
+/// The area covered by a hit-test query, in world space.
+///
+/// Rects and circles are intersected exactly with the bounds, shapes and
+/// clips of items that are only scaled and offset in world space, however
+/// thin the part of an item left inside the area is. For items or clips with
+/// any other transform, only a grid of points over the overlap is sampled
+/// when the point of the item nearest to the center of the area is clipped
+/// out, so parts thinner than the grid spacing may be missed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HitTestArea {
+    ///
+    Point(WorldPoint),
+    /// Items overlapping the rectangle are hit.
+    Rect(WorldRect),
+    /// Items overlapping the circle are hit.
+    Circle {
+        ///
+        center: WorldPoint,
+        ///
+        radius: f32,
+    },
+}
+
+impl HitTestArea {
+    /// Returns true if the area contains the given point.
+    pub fn contains(&self, point: WorldPoint) -> bool {
+        match *self {
+            HitTestArea::Point(p) => p == point,
+            HitTestArea::Rect(rect) => rect.contains(point),
+            HitTestArea::Circle { center, radius } => {
+                (point - center).square_length() <= radius * radius
+            }
+        }
+    }
+
+    /// The smallest rectangle containing the area.
+    pub fn bounds(&self) -> WorldRect {
+        match *self {
+            HitTestArea::Point(p) => WorldRect::new(p, p),
+            HitTestArea::Rect(rect) => rect,
+            HitTestArea::Circle { center, radius } => {
+                let radius = WorldVector2D::new(radius, radius);
+                WorldRect::new(center - radius, center + radius)
+            }
+        }
+    }
+
+    /// The point of the area that hits are expected to be nearest to.
+    pub fn center(&self) -> WorldPoint {
+        match *self {
+            HitTestArea::Point(p) => p,
+            HitTestArea::Rect(rect) => rect.center(),
+            HitTestArea::Circle { center, .. } => center,
+        }
+    }
+}
+
+/// Which of the items matching a hit-test query are returned.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HitTestMode {
+    /// All items, from front to back.
+    All,
+    /// Only the frontmost item, preceded by any pass-through item in front
+    /// of it.
+    Topmost,
+}
+
+/// Describes a hit-test query.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HitTestQuery {
+    ///
+    pub area: HitTestArea,
+    ///
+    pub mode: HitTestMode,
+    /// If set, only items with a tag within the range are considered. Other
+    /// items don't hide the items behind them, even in `HitTestMode::Topmost`.
+    pub tag_range: Option<std::ops::RangeInclusive<ItemTag>>,
+}
+
+impl HitTestQuery {
+    /// A query returning all items at the given point.
+    pub fn point(point: WorldPoint) -> Self {
+        HitTestQuery::new(HitTestArea::Point(point))
+    }
+
+    /// A query returning all items overlapping the given rectangle.
+    pub fn rect(rect: WorldRect) -> Self {
+        HitTestQuery::new(HitTestArea::Rect(rect))
+    }
+
+    /// A query returning all items overlapping the given circle.
+    pub fn circle(center: WorldPoint, radius: f32) -> Self {
+        HitTestQuery::new(HitTestArea::Circle { center, radius })
+    }
+
+    fn new(area: HitTestArea) -> Self {
+        HitTestQuery {
+            area,
+            mode: HitTestMode::All,
+            tag_range: None,
+        }
+    }
+
+    /// Only return the frontmost item.
+    pub fn topmost(mut self) -> Self {
+        self.mode = HitTestMode::Topmost;
+        self
+    }
+
+    /// Only consider items with a tag within the given range.
+    pub fn with_tag_range(mut self, tag_range: std::ops::RangeInclusive<ItemTag>) -> Self {
+        self.tag_range = Some(tag_range);
+        self
+    }
 }
 
 /// A hit tester requested to the render backend thread but not necessarily ready yet.
@@ -317,6 +445,15 @@ pub struct HitTestItem {
 
     /// The tag of the hit display item.
     pub tag: ItemTag,
+
+    /// The point that hit the item, in the local space of the item. For area
+    /// queries, this is the point of the item within the area that is closest
+    /// to the center of the area.
+    pub point_in_local_space: LayoutPoint,
+
+    /// The bounds of the item transformed to world space, or an empty rect if
+    /// the item can't be projected to world space.
+    pub item_rect: WorldRect,
 }
 
 /// Returned by `RenderApi::hit_test`.
@@ -485,6 +622,8 @@ pub struct DynamicProperties {
     pub floats: Vec<PropertyValue<f32>>,
     /// background color
     pub colors: Vec<PropertyValue<ColorF>>,
+    /// rounded rect clip regions
+    pub clip_regions: Vec<PropertyValue<ComplexClipRegion>>,
 }
 
 impl DynamicProperties {
@@ -493,6 +632,7 @@ impl DynamicProperties {
         self.transforms.extend(other.transforms);
         self.floats.extend(other.floats);
         self.colors.extend(other.colors);
+        self.clip_regions.extend(other.clip_regions);
     }
 }
 
@@ -636,6 +776,9 @@ bitflags! {
         const SMART_PROFILER        = 1 << 22;
         /// If set, dump picture cache invalidation debug to console.
         const INVALIDATION_DBG = 1 << 23;
+        /// Record the profiler counters and timings of the recent frames, to
+        /// save them with `DebugCommand::SaveProfilerTrace`.
+        const PROFILER_TRACE = 1 << 24;
         /// Collect and dump profiler statistics to captures.
         const PROFILER_CAPTURE = (1 as u32) << 25; // need "as u32" until we have cbindgen#556
         /// Invalidate picture tiles every frames (useful when inspecting GPU work in external tools).
@@ -655,6 +798,9 @@ pub enum PrimitiveKeyKind {
     Rectangle {
         ///
         color: PropertyBinding<ColorU>,
+        /// The color space that an animated color is converted from when its
+        /// binding is resolved. Colors given by value are already converted.
+        color_space: RgbColorSpace,
     },
 }
 
@@ -669,6 +815,22 @@ pub enum ScrollLocation {
     End,
 }
 
+/// The stage of the user interaction that produced a scroll event.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ScrollEventPhase {
+    /// The user started a scroll gesture, e.g. put two fingers on a touchpad.
+    /// This stops any ongoing momentum or snapping.
+    Start,
+    /// The gesture moved, and the content follows it directly.
+    Move,
+    /// The gesture ended. The scroll frame keeps moving with the velocity of
+    /// the gesture, then snaps and springs back from any overscroll.
+    End,
+    /// A scroll that isn't part of a gesture, such as a mouse wheel notch or
+    /// a key press, which is animated smoothly.
+    Discrete,
+}
+
 /// Crash annotations included in crash reports.
 #[repr(C)]
 #[derive(Clone, Copy)]
@@ -67,12 +67,14 @@ pub fn get_shader_features(flags: ShaderFeatureFlags) -> ShaderFeatures {
     shaders.insert("cs_clip_rectangle", vec![String::new(), "FAST_PATH".to_string()]);
     shaders.insert("cs_clip_image", vec!["TEXTURE_2D".to_string()]);
     shaders.insert("cs_clip_box_shadow", vec!["TEXTURE_2D".to_string()]);
+    shaders.insert("cs_clip_polygon", vec![String::new()]);
 
     // Cache shaders
     shaders.insert("cs_blur", vec!["ALPHA_TARGET".to_string(), "COLOR_TARGET".to_string()]);
 
     for name in &[
         "cs_line_decoration",
+        "cs_path",
         "cs_fast_linear_gradient",
         "cs_border_segment",
         "cs_border_solid",
@@ -0,0 +1,396 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Structural comparison of two WebRender captures.
+//!
+//! The capture files are pretty-printed RON, so rather than deserializing
+//! WebRender's internal types we diff them line by line and use the
+//! indentation to recover the path of the structure each change lives in.
+//! Files are grouped by what they describe (display lists, spatial tree,
+//! clip store, ...) so the report points at the stage where two builds
+//! start to diverge.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const COLOR_DEFAULT: &str = "\x1b[0m";
+const COLOR_RED: &str = "\x1b[31m";
+const COLOR_GREEN: &str = "\x1b[32m";
+const COLOR_MAGENTA: &str = "\x1b[95m";
+
+/// Maximum number of changed regions printed for a single file.
+const MAX_HUNKS_PER_FILE: usize = 10;
+/// Maximum number of lines printed for each side of a changed region.
+const MAX_LINES_PER_HUNK: usize = 6;
+/// Upper bound on the size of the LCS table used to align a changed region.
+/// Larger regions are reported as a single replacement.
+const MAX_LCS_CELLS: usize = 16 * 1024 * 1024;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Category {
+    DisplayLists,
+    SpatialTree,
+    ClipStore,
+    ResourceTemplates,
+    RenderTaskGraph,
+    Other,
+}
+
+impl Category {
+    fn from_file_name(name: &str) -> Category {
+        // Order matters: several names share a prefix with a more general one.
+        if name.starts_with("scene-spatial-tree-") ||
+            name.starts_with("frame-spatial-tree-") ||
+            name.starts_with("spatial-") {
+            Category::SpatialTree
+        } else if name.starts_with("scene-") {
+            Category::DisplayLists
+        } else if name.starts_with("built-clips-") {
+            Category::ClipStore
+        } else if name.starts_with("interners-") ||
+            name.starts_with("data-stores-") ||
+            name.starts_with("plain-resources") {
+            Category::ResourceTemplates
+        } else if name.starts_with("frame-") || name.starts_with("render-tasks-") {
+            Category::RenderTaskGraph
+        } else {
+            Category::Other
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Category::DisplayLists => "display lists",
+            Category::SpatialTree => "spatial tree",
+            Category::ClipStore => "clip store",
+            Category::ResourceTemplates => "resource templates",
+            Category::RenderTaskGraph => "render task graph",
+            Category::Other => "other",
+        }
+    }
+}
+
+/// A region where the two files disagree, as line ranges in each file.
+#[derive(Debug, PartialEq)]
+struct Hunk {
+    a: (usize, usize),
+    b: (usize, usize),
+}
+
+enum FileDiff {
+    Same,
+    OnlyInA,
+    OnlyInB,
+    Binary,
+    Text {
+        text_a: String,
+        text_b: String,
+        hunks: Vec<Hunk>,
+    },
+}
+
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    let read_error = |e: io::Error| format!("Unable to read capture directory {}: {}", dir.display(), e);
+    for entry in fs::read_dir(dir).map_err(read_error)? {
+        let path = entry.map_err(read_error)?.path();
+        if path.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            files.push(relative.to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+/// Computes the changed regions between two sequences of lines.
+///
+/// The common prefix and suffix are trimmed first, since captures of the
+/// same page usually differ in a handful of places. The remainder is aligned
+/// with a longest common subsequence when it is small enough.
+fn diff_lines(a: &[&str], b: &[&str]) -> Vec<Hunk> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix ..].iter().rev()
+        .zip(b[prefix ..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let a_mid = &a[prefix .. a.len() - suffix];
+    let b_mid = &b[prefix .. b.len() - suffix];
+
+    if a_mid.is_empty() && b_mid.is_empty() {
+        return Vec::new();
+    }
+    if a_mid.is_empty() || b_mid.is_empty() ||
+        (a_mid.len() + 1) * (b_mid.len() + 1) > MAX_LCS_CELLS {
+        return vec![Hunk {
+            a: (prefix, prefix + a_mid.len()),
+            b: (prefix, prefix + b_mid.len()),
+        }];
+    }
+
+    // lcs[i][j] is the length of the LCS of a_mid[i..] and b_mid[j..].
+    let width = b_mid.len() + 1;
+    let mut lcs = vec![0u32; (a_mid.len() + 1) * width];
+    for i in (0 .. a_mid.len()).rev() {
+        for j in (0 .. b_mid.len()).rev() {
+            lcs[i * width + j] = if a_mid[i] == b_mid[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut hunks = Vec::new();
+    let mut current: Option<Hunk> = None;
+    let (mut i, mut j) = (0, 0);
+    while i < a_mid.len() || j < b_mid.len() {
+        if i < a_mid.len() && j < b_mid.len() && a_mid[i] == b_mid[j] {
+            hunks.extend(current.take());
+            i += 1;
+            j += 1;
+            continue;
+        }
+        let hunk = current.get_or_insert(Hunk {
+            a: (prefix + i, prefix + i),
+            b: (prefix + j, prefix + j),
+        });
+        if j == b_mid.len() ||
+            (i < a_mid.len() && lcs[(i + 1) * width + j] >= lcs[i * width + j + 1]) {
+            i += 1;
+            hunk.a.1 = prefix + i;
+        } else {
+            j += 1;
+            hunk.b.1 = prefix + j;
+        }
+    }
+    hunks.extend(current);
+    hunks
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Describes where `line` sits in the pretty-printed structure by walking
+/// back to each less-indented line that opens a nested value.
+fn structure_path(lines: &[&str], line: usize) -> String {
+    let mut path = Vec::new();
+    let mut indent = match lines.get(line) {
+        Some(l) => indentation(l),
+        None => return String::new(),
+    };
+    for l in lines[.. line].iter().rev() {
+        if indent == 0 {
+            break;
+        }
+        let trimmed = l.trim();
+        if trimmed.is_empty() || indentation(l) >= indent {
+            continue;
+        }
+        indent = indentation(l);
+        let label = trimmed.trim_end_matches(|c| "([{: ".contains(c));
+        path.push(if label.is_empty() { "_" } else { label });
+    }
+    path.reverse();
+    path.join(" > ")
+}
+
+fn print_lines(prefix: &str, color: &str, lines: &[&str]) {
+    for line in lines.iter().take(MAX_LINES_PER_HUNK) {
+        println!("    {}{} {}{}", color, prefix, line.trim(), COLOR_DEFAULT);
+    }
+    if lines.len() > MAX_LINES_PER_HUNK {
+        println!("    {}{} ... {} more lines{}", color, prefix, lines.len() - MAX_LINES_PER_HUNK, COLOR_DEFAULT);
+    }
+}
+
+/// Reads a capture file, or returns `None` if it doesn't exist.
+fn read_file(path: &Path) -> Result<Option<Vec<u8>>, String> {
+    match fs::read(path) {
+        Ok(data) => Ok(Some(data)),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Unable to read {}: {}", path.display(), e)),
+    }
+}
+
+fn compare_file(a: &Path, b: &Path) -> Result<FileDiff, String> {
+    let (data_a, data_b) = match (read_file(a)?, read_file(b)?) {
+        (Some(data_a), Some(data_b)) => (data_a, data_b),
+        (Some(_), None) => return Ok(FileDiff::OnlyInA),
+        (None, Some(_)) => return Ok(FileDiff::OnlyInB),
+        (None, None) => return Err(format!("Unable to read {}: file vanished", a.display())),
+    };
+    if data_a == data_b {
+        return Ok(FileDiff::Same);
+    }
+    Ok(match (String::from_utf8(data_a), String::from_utf8(data_b)) {
+        (Ok(text_a), Ok(text_b)) => {
+            let hunks = {
+                let lines_a: Vec<&str> = text_a.lines().collect();
+                let lines_b: Vec<&str> = text_b.lines().collect();
+                diff_lines(&lines_a, &lines_b)
+            };
+            FileDiff::Text { text_a, text_b, hunks }
+        }
+        _ => FileDiff::Binary,
+    })
+}
+
+fn print_text_diff(text_a: &str, text_b: &str, hunks: &[Hunk]) {
+    let lines_a: Vec<&str> = text_a.lines().collect();
+    let lines_b: Vec<&str> = text_b.lines().collect();
+
+    for hunk in hunks.iter().take(MAX_HUNKS_PER_FILE) {
+        let path = if hunk.a.0 < lines_a.len() {
+            structure_path(&lines_a, hunk.a.0)
+        } else {
+            structure_path(&lines_b, hunk.b.0)
+        };
+        println!("  @ line {} / {}: {}", hunk.a.0 + 1, hunk.b.0 + 1, path);
+        print_lines("-", COLOR_RED, &lines_a[hunk.a.0 .. hunk.a.1]);
+        print_lines("+", COLOR_GREEN, &lines_b[hunk.b.0 .. hunk.b.1]);
+    }
+    if hunks.len() > MAX_HUNKS_PER_FILE {
+        println!("  ... {} more changed regions", hunks.len() - MAX_HUNKS_PER_FILE);
+    }
+}
+
+/// Compares the captures in `first` and `second` and prints the differences
+/// grouped by category. Returns true if the captures differ, or a message
+/// if one of them can't be read.
+pub fn compare(first: &Path, second: &Path) -> Result<bool, String> {
+    let mut files = Vec::new();
+    collect_files(first, first, &mut files)?;
+    collect_files(second, second, &mut files)?;
+    files.sort();
+    files.dedup();
+
+    let mut by_category: BTreeMap<Category, Vec<PathBuf>> = BTreeMap::new();
+    for file in files {
+        let name = file.file_name().unwrap().to_string_lossy().into_owned();
+        by_category.entry(Category::from_file_name(&name)).or_default().push(file);
+    }
+
+    let mut any_difference = false;
+    for (category, files) in &by_category {
+        let mut same = 0;
+        let mut reports = Vec::new();
+        for file in files {
+            match compare_file(&first.join(file), &second.join(file))? {
+                FileDiff::Same => same += 1,
+                diff => reports.push((file, diff)),
+            }
+        }
+
+        println!(
+            "{}== {} =={} {} identical, {} different",
+            COLOR_MAGENTA,
+            category.name(),
+            COLOR_DEFAULT,
+            same,
+            reports.len(),
+        );
+        for (file, diff) in reports {
+            any_difference = true;
+            match diff {
+                FileDiff::Same => unreachable!(),
+                FileDiff::OnlyInA => println!("{} only in {}", file.display(), first.display()),
+                FileDiff::OnlyInB => println!("{} only in {}", file.display(), second.display()),
+                FileDiff::Binary => println!("{} differs (binary)", file.display()),
+                FileDiff::Text { text_a, text_b, hunks } => {
+                    println!("{} differs in {} places", file.display(), hunks.len());
+                    print_text_diff(&text_a, &text_b, &hunks);
+                }
+            }
+        }
+    }
+
+    Ok(any_difference)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn hunk(a: (usize, usize), b: (usize, usize)) -> Hunk {
+        Hunk { a, b }
+    }
+
+    #[test]
+    fn diff_lines_identical() {
+        let lines = ["a", "b", "c"];
+        assert_eq!(diff_lines(&lines, &lines), vec![]);
+        assert_eq!(diff_lines(&[], &[]), vec![]);
+    }
+
+    #[test]
+    fn diff_lines_changes() {
+        // Replaced, inserted and removed lines.
+        assert_eq!(diff_lines(&["a", "b", "c"], &["a", "x", "c"]), vec![hunk((1, 2), (1, 2))]);
+        assert_eq!(diff_lines(&["a", "c"], &["a", "b", "c"]), vec![hunk((1, 1), (1, 2))]);
+        assert_eq!(diff_lines(&["a", "b", "c"], &["a", "c"]), vec![hunk((1, 2), (1, 1))]);
+        assert_eq!(diff_lines(&["a"], &[]), vec![hunk((0, 1), (0, 0))]);
+
+        // Separate changes are aligned on the common lines between them.
+        assert_eq!(
+            diff_lines(&["a", "b", "c", "d", "e"], &["a", "x", "c", "d", "y", "e"]),
+            vec![hunk((1, 2), (1, 2)), hunk((4, 4), (4, 5))],
+        );
+    }
+
+    #[test]
+    fn structure_path_follows_indentation() {
+        let lines = [
+            "(",
+            "    scene: (",
+            "        pipelines: {",
+            "            (1, 0): (",
+            "                epoch: 1,",
+            "                display_list: [",
+            "                    Rectangle(",
+            "                        color: 1,",
+            "                    ),",
+            "                ],",
+            "            ),",
+            "        },",
+            "    ),",
+            ")",
+        ];
+        assert_eq!(
+            structure_path(&lines, 7),
+            "_ > scene > pipelines > (1, 0) > display_list > Rectangle",
+        );
+        assert_eq!(structure_path(&lines, 4), "_ > scene > pipelines > (1, 0)");
+        assert_eq!(structure_path(&lines, 0), "");
+        assert_eq!(structure_path(&lines, lines.len()), "");
+    }
+
+    #[test]
+    fn category_from_file_name() {
+        let expected = [
+            ("scene-1-1.ron", Category::DisplayLists),
+            ("scene-spatial-tree-1-1.ron", Category::SpatialTree),
+            ("frame-spatial-tree-1-1.ron", Category::SpatialTree),
+            ("spatial-1-1.ron", Category::SpatialTree),
+            ("built-clips-1-1.ron", Category::ClipStore),
+            ("interners-1-1.ron", Category::ResourceTemplates),
+            ("data-stores-1-1.ron", Category::ResourceTemplates),
+            ("plain-resources.ron", Category::ResourceTemplates),
+            ("frame-1-1.ron", Category::RenderTaskGraph),
+            ("render-tasks-1-1.ron", Category::RenderTaskGraph),
+            ("backend.ron", Category::Other),
+        ];
+        for &(name, category) in &expected {
+            assert_eq!(Category::from_file_name(name), category, "{}", name);
+        }
+    }
+
+    #[test]
+    fn missing_capture_is_an_error() {
+        let missing = Path::new("this-capture-does-not-exist");
+        assert!(compare(missing, missing).is_err());
+    }
+}
@@ -13,6 +13,7 @@ extern crate tracy_rs;
 
 mod angle;
 mod blob;
+mod capture_diff;
 mod egl;
 mod parse_function;
 mod perf;
@@ -63,7 +64,7 @@ pub static mut CURRENT_FRAME_NUMBER: u32 = 0;
 pub struct HeadlessContext {
     width: i32,
     height: i32,
-    _context: osmesa_sys::OSMesaContext,
+    _context: Option<osmesa_sys::OSMesaContext>,
     _buffer: Vec<u32>,
 }
 
@@ -109,7 +110,7 @@ impl HeadlessContext {
         HeadlessContext {
             width,
             height,
-            _context: context,
+            _context: Some(context),
             _buffer: buffer,
         }
     }
@@ -119,6 +120,23 @@ impl HeadlessContext {
         HeadlessContext { width, height }
     }
 
+    /// A headless context for software rendering, which renders into the
+    /// swgl framebuffer and so needs no native GL context at all.
+    #[cfg(feature = "headless")]
+    fn new_software(width: i32, height: i32) -> Self {
+        HeadlessContext {
+            width,
+            height,
+            _context: None,
+            _buffer: Vec::new(),
+        }
+    }
+
+    #[cfg(not(feature = "headless"))]
+    fn new_software(width: i32, height: i32) -> Self {
+        HeadlessContext { width, height }
+    }
+
     #[cfg(feature = "headless")]
     fn get_proc_address(s: &str) -> *const c_void {
         let c_str = CString::new(s).expect("Unable to create CString");
@@ -387,6 +405,12 @@ fn make_window(
             WindowWrapper::WindowedContext(windowed_context, gl, sw_ctx)
         }
     } else {
+        let context = if software {
+            HeadlessContext::new_software(size.width, size.height)
+        } else {
+            HeadlessContext::new(size.width, size.height)
+        };
+
         #[cfg_attr(not(feature = "software"), allow(unused_variables))]
         let gl = if let Some(sw_ctx) = sw_ctx {
             #[cfg(feature = "software")]
@@ -411,7 +435,7 @@ fn make_window(
                 },
             }
         };
-        WindowWrapper::Headless(HeadlessContext::new(size.width, size.height), gl, sw_ctx)
+        WindowWrapper::Headless(context, gl, sw_ctx)
     };
 
     let gl = wrapper.gl();
@@ -503,6 +527,10 @@ fn reftest<'a>(
         reftest_options.allow_max_difference = allow_max_diff.parse().unwrap_or(1);
         reftest_options.allow_num_differences = dim.width as usize * dim.height as usize;
     }
+    reftest_options.exact = subargs.is_present("exact");
+    reftest_options.output_dir = subargs.value_of("output_dir").map(PathBuf::from);
+    reftest_options.json_report = subargs.value_of("json_report").map(PathBuf::from);
+    reftest_options.junit_report = subargs.value_of("junit_report").map(PathBuf::from);
     let num_failures = ReftestHarness::new(&mut wrench, window, &rx)
         .run(base_manifest, specific_reftest, &reftest_options);
     wrench.shut_down(rx);
@@ -559,6 +587,20 @@ fn main() {
         clap.get_matches()
     };
 
+    // Comparing captures only reads files, so do it before creating a window.
+    if let Some(subargs) = args.subcommand_matches("capture-diff") {
+        let first = Path::new(subargs.value_of("first_capture").unwrap());
+        let second = Path::new(subargs.value_of("second_capture").unwrap());
+        // Like diff(1): 0 if the captures match, 1 if they differ, 2 on errors.
+        match capture_diff::compare(first, second) {
+            Ok(differ) => process::exit(if differ { 1 } else { 0 }),
+            Err(e) => {
+                eprintln!("capture-diff: {}", e);
+                process::exit(2);
+            }
+        }
+    }
+
     // handle some global arguments
     let res_path = args.value_of("shaders").map(PathBuf::from);
     let size = args.value_of("size")
@@ -597,9 +639,23 @@ fn main() {
         None => webrender::ChasePrimitive::Nothing,
     };
 
+    let hdr_tone_mapping = match args.value_of("hdr_tone_mapping") {
+        Some("clamp") => Some(webrender::ToneMapping::Clamp),
+        Some("reinhard") => Some(webrender::ToneMapping::Reinhard),
+        Some(operator) => panic!("Unexpected tone mapping operator {}", operator),
+        None => None,
+    };
+
     let dump_shader_source = args.value_of("dump_shader_source").map(String::from);
 
-    let mut events_loop = if args.is_present("headless") {
+    // Headless software rendering needs neither a window system nor a
+    // native GL driver, so it behaves the same on any machine.
+    let headless_sw = args.is_present("headless_sw");
+    if headless_sw && !cfg!(feature = "software") {
+        panic!("--headless-sw requires wrench to be built with the software feature");
+    }
+
+    let mut events_loop = if args.is_present("headless") || headless_sw {
         None
     } else {
         Some(winit::EventsLoop::new())
@@ -623,7 +679,7 @@ fn main() {
         }
     };
 
-    let software = args.is_present("software");
+    let software = args.is_present("software") || headless_sw;
 
     let mut window = make_window(
         size,
@@ -659,6 +715,7 @@ fn main() {
         args.is_present("precache"),
         args.is_present("slow_subpixel"),
         chase_primitive,
+        hdr_tone_mapping,
         dump_shader_source,
         notifier,
     );
@@ -667,6 +724,17 @@ fn main() {
         wrench.renderer.set_profiler_ui(ui_str);
     }
 
+    if let Some(dir) = args.value_of("dump_render_task_graphs") {
+        let format = match args.value_of("render_task_graph_format") {
+            Some("dot") | None => RenderTaskGraphFormat::Dot,
+            Some("svg") => RenderTaskGraphFormat::Svg,
+            Some(format) => panic!("Unexpected render task graph format {}", format),
+        };
+        wrench.api.send_debug_cmd(
+            DebugCommand::DumpRenderTaskGraphs(Some((PathBuf::from(dir), format)))
+        );
+    }
+
     window.update(&mut wrench);
 
     if let Some(window_title) = wrench.take_title() {
@@ -915,6 +983,16 @@ fn render<'a>(
                             wrench.api.save_capture(path, CaptureBits::all());
                             do_render = false;
                         }
+                        VirtualKeyCode::E => {
+                            // Start recording, or save what has been recorded.
+                            if debug_flags.contains(DebugFlags::PROFILER_TRACE) {
+                                let path = PathBuf::from("../captures/wrench-trace.json");
+                                wrench.api.send_debug_cmd(DebugCommand::SaveProfilerTrace(path));
+                            }
+                            debug_flags.toggle(DebugFlags::PROFILER_TRACE);
+                            wrench.api.send_debug_cmd(DebugCommand::SetFlags(debug_flags));
+                            do_render = false;
+                        }
                         VirtualKeyCode::X => {
                             let results = wrench.api.hit_test(
                                 wrench.document_id,
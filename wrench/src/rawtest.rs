@@ -3,16 +3,39 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use euclid::{point2, size2, rect, Box2D};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicIsize, Ordering};
 use std::sync::mpsc::Receiver;
+#[cfg(feature = "software")]
+use webrender::DebugFlags;
 use webrender::api::*;
 use webrender::render_api::*;
 use webrender::api::units::*;
 use crate::{WindowWrapper, NotifierEvent};
 use crate::blob;
 use crate::reftest::{ReftestImage, ReftestImageComparison};
-use crate::wrench::Wrench;
+use crate::wrench::{Wrench, WrenchThing};
+use crate::yaml_frame_reader::YamlFrameReader;
+
+/// Rasterizes the bands of SWGL draw calls on scoped threads.
+#[cfg(feature = "software")]
+struct ThreadRasterizeWorkers(usize);
+
+#[cfg(feature = "software")]
+impl swgl::RasterizeWorkers for ThreadRasterizeWorkers {
+    fn max_bands(&self) -> usize {
+        self.0
+    }
+
+    fn run_bands(&self, num_bands: usize, band: &(dyn Fn(usize) + Sync)) {
+        crossbeam::scope(|scope| {
+            for index in 0..num_bands {
+                scope.spawn(move || band(index));
+            }
+        });
+    }
+}
 
 pub struct RawtestHarness<'a> {
     wrench: &'a mut Wrench,
@@ -34,7 +57,10 @@ impl<'a> RawtestHarness<'a> {
 
     pub fn run(mut self) {
         self.test_hit_testing();
+        self.test_hit_test_shapes();
+        self.test_hit_test_area_slivers();
         self.test_resize_image();
+        self.test_animated_image();
         self.test_retained_blob_images_test();
         self.test_blob_update_test();
         self.test_blob_update_epoch_test();
@@ -45,9 +71,13 @@ impl<'a> RawtestHarness<'a> {
         self.test_offscreen_blob();
         self.test_save_restore();
         self.test_blur_cache();
+        self.test_animated_properties();
         self.test_capture();
+        self.test_yaml_capture();
         self.test_zero_height_window();
         self.test_clear_cache();
+        #[cfg(feature = "software")]
+        self.test_swgl_raster_bands();
     }
 
     fn render_and_get_pixels(&mut self, window_rect: FramebufferIntRect) -> Vec<u8> {
@@ -224,6 +254,73 @@ impl<'a> RawtestHarness<'a> {
         self.wrench.api.send_transaction(self.wrench.document_id, txn);
     }
 
+    fn test_animated_image(&mut self) {
+        println!("\tanimated image...");
+        // This test checks that the render backend builds frames on its own
+        // while an animated image plays, and that it stops on the last frame.
+
+        let window_size = self.window.get_inner_size();
+        let test_size = FramebufferIntSize::new(100, 100);
+        let window_rect = FramebufferIntRect::from_origin_and_size(
+            FramebufferIntPoint::new(0, window_size.height - test_size.height),
+            test_size,
+        );
+        let layout_size = LayoutSize::new(100., 100.);
+
+        let mut txn = Transaction::new();
+        let img = self.wrench.api.generate_image_key();
+        let frame = |bgra: [u8; 4]| AnimatedImageFrame {
+            data: ImageData::new(bgra.iter().cloned().cycle().take(64 * 64 * 4).collect()),
+            duration_ms: 200,
+        };
+
+        txn.add_animated_image(
+            img,
+            ImageDescriptor::new(64, 64, ImageFormat::BGRA8, ImageDescriptorFlags::IS_OPAQUE),
+            vec![frame([0, 0, 255, 255]), frame([0, 255, 0, 255])],
+            Some(1),
+        );
+
+        let mut builder = DisplayListBuilder::new(self.wrench.root_pipeline_id);
+        builder.begin();
+        let info = self.make_common_properties(rect(0.0, 0.0, 64.0, 64.0).to_box2d());
+
+        builder.push_image(
+            &info,
+            info.clip_rect,
+            ImageRendering::Auto,
+            AlphaType::PremultipliedAlpha,
+            img,
+            ColorF::WHITE,
+        );
+
+        let mut epoch = Epoch(0);
+        self.submit_dl(&mut epoch, layout_size, builder, txn);
+
+        let w = window_rect.width() as usize;
+        let h = window_rect.height() as usize;
+        let p1 = (32 + (h - 32) * w) * 4;
+
+        let pixels = self.render_and_get_pixels(window_rect);
+        assert_eq!(&pixels[p1..p1 + 4], &[255, 0, 0, 255]);
+
+        // Let the animation play to its end, and drop the notifications of
+        // the frames the render backend built meanwhile.
+        std::thread::sleep(std::time::Duration::from_millis(600));
+        while self.rx.try_recv().is_ok() {}
+
+        let mut txn = Transaction::new();
+        txn.generate_frame(0, RenderReasons::TESTING);
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+
+        let pixels = self.render_and_get_pixels(window_rect);
+        assert_eq!(&pixels[p1..p1 + 4], &[0, 255, 0, 255]);
+
+        let mut txn = Transaction::new();
+        txn.delete_image(img);
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+    }
+
     fn test_tile_decomposition(&mut self) {
         println!("\ttile decomposition...");
         // This exposes a crash in tile decomposition
@@ -1205,6 +1302,125 @@ impl<'a> RawtestHarness<'a> {
         assert_ne!(first, second);
     }
 
+    fn test_animated_properties(&mut self) {
+        println!("\tanimated properties...");
+        // Changing an animated filter argument, clip radius or gradient stop
+        // color must invalidate the cached tiles without a new display list.
+
+        let window_size = self.window.get_inner_size();
+        let test_size = FramebufferIntSize::new(300, 100);
+        let window_rect = FramebufferIntRect::from_origin_and_size(
+            point2(0, window_size.height - test_size.height),
+            test_size,
+        );
+        let layout_size = LayoutSize::new(300., 100.);
+        let space_and_clip = SpaceAndClipInfo::root_scroll(self.wrench.root_pipeline_id);
+
+        let invert_key = self.wrench.api.generate_property_binding_key();
+        let clip_key = self.wrench.api.generate_property_binding_key();
+        let color_key = self.wrench.api.generate_property_binding_key();
+
+        let red = ColorF::new(1.0, 0.0, 0.0, 1.0);
+        let green = ColorF::new(0.0, 1.0, 0.0, 1.0);
+        let clip_rect = rect(110., 10., 80., 80.).to_box2d();
+
+        let mut builder = DisplayListBuilder::new(self.wrench.root_pipeline_id);
+        builder.begin();
+
+        // A red rect under an animated invert filter.
+        builder.push_simple_stacking_context_with_filters(
+            LayoutPoint::zero(),
+            space_and_clip.spatial_id,
+            PrimitiveFlags::default(),
+            &[FilterOp::Invert(PropertyBinding::Binding(invert_key, 0.0))],
+            &[],
+            &[],
+        );
+        let info = self.make_common_properties(rect(10., 10., 80., 80.).to_box2d());
+        builder.push_rect(&info, info.clip_rect, red);
+        builder.pop_stacking_context();
+
+        // A green rect under an animated rounded clip.
+        let clip_id = builder.define_animated_clip_rounded_rect(
+            &space_and_clip,
+            PropertyBinding::Binding(
+                clip_key,
+                ComplexClipRegion::new(clip_rect, BorderRadius::zero(), ClipMode::Clip),
+            ),
+        );
+        builder.push_rect(
+            &CommonItemProperties {
+                clip_rect,
+                clip_id,
+                spatial_id: space_and_clip.spatial_id,
+                flags: PrimitiveFlags::default(),
+            },
+            clip_rect,
+            green,
+        );
+
+        // A gradient whose stops both read an animated color.
+        let info = self.make_common_properties(rect(210., 10., 80., 80.).to_box2d());
+        let gradient = builder.create_gradient(
+            point2(0., 0.),
+            point2(80., 0.),
+            vec![
+                GradientStop { offset: 0.0, color: red, color_binding: Some(color_key) },
+                GradientStop { offset: 1.0, color: red, color_binding: Some(color_key) },
+            ],
+            ExtendMode::Clamp,
+        );
+        builder.push_gradient(
+            &info,
+            info.clip_rect,
+            gradient,
+            info.clip_rect.size(),
+            LayoutSize::zero(),
+        );
+
+        let properties = |invert, radius, color| DynamicProperties {
+            transforms: Vec::new(),
+            floats: vec![PropertyValue { key: invert_key, value: invert }],
+            colors: vec![PropertyValue { key: color_key, value: color }],
+            clip_regions: vec![PropertyValue {
+                key: clip_key,
+                value: ComplexClipRegion::new(
+                    clip_rect,
+                    BorderRadius::uniform(radius),
+                    ClipMode::Clip,
+                ),
+            }],
+        };
+
+        let mut txn = Transaction::new();
+        txn.append_dynamic_properties(properties(0.0, 0.0, red));
+        self.submit_dl(&mut Epoch(0), layout_size, builder, txn);
+
+        let w = window_rect.width() as usize;
+        let h = window_rect.height() as usize;
+        let pixel = |pixels: &[u8], x: usize, y: usize| -> [u8; 4] {
+            let p = (x + (h - y) * w) * 4;
+            [pixels[p], pixels[p + 1], pixels[p + 2], pixels[p + 3]]
+        };
+
+        let pixels = self.render_and_get_pixels(window_rect);
+        assert_eq!(pixel(&pixels, 50, 50), [255, 0, 0, 255]);
+        assert_eq!(pixel(&pixels, 112, 88), [0, 255, 0, 255]);
+        assert_eq!(pixel(&pixels, 250, 50), [255, 0, 0, 255]);
+
+        // Only update the property values, the scene is not rebuilt.
+        let mut txn = Transaction::new();
+        txn.reset_dynamic_properties();
+        txn.append_dynamic_properties(properties(1.0, 40.0, green));
+        txn.generate_frame(0, RenderReasons::TESTING);
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+
+        let pixels = self.render_and_get_pixels(window_rect);
+        assert_eq!(pixel(&pixels, 50, 50), [0, 255, 255, 255]);
+        assert_ne!(pixel(&pixels, 112, 88), [0, 255, 0, 255]);
+        assert_eq!(pixel(&pixels, 250, 50), [0, 255, 0, 255]);
+    }
+
     fn test_capture(&mut self) {
         println!("\tcapture...");
         let path = "../captures/test";
@@ -1288,6 +1504,164 @@ impl<'a> RawtestHarness<'a> {
         self.compare_pixels(pixels0, pixels2, window_rect.size());
     }
 
+    fn test_yaml_capture(&mut self) {
+        println!("\tyaml capture...");
+        // The YAML written with a capture must read back in wrench as the
+        // same scene.
+        let path = PathBuf::from("../captures/test-yaml");
+        let layout_size = LayoutSize::new(400., 400.);
+        let window_size = self.window.get_inner_size();
+        let window_rect = FramebufferIntRect::from_origin_and_size(
+            point2(0, window_size.height - layout_size.height as i32),
+            size2(layout_size.width as i32, layout_size.height as i32),
+        );
+
+        // 1. render a scene using the common kinds of items
+        let mut txn = Transaction::new();
+        let image = self.wrench.api.generate_image_key();
+        txn.add_image(
+            image,
+            ImageDescriptor::new(2, 2, ImageFormat::BGRA8, ImageDescriptorFlags::IS_OPAQUE),
+            ImageData::new(vec![
+                0, 0, 255, 255,   0, 255, 0, 255,
+                255, 0, 0, 255,   0, 0, 0, 255,
+            ]),
+            None,
+        );
+
+        let mut builder = DisplayListBuilder::new(self.wrench.root_pipeline_id);
+        builder.begin();
+        let space_and_clip = SpaceAndClipInfo::root_scroll(self.wrench.root_pipeline_id);
+
+        let info = self.make_common_properties(rect(10., 10., 100., 100.).to_box2d());
+        builder.push_rect(&info, info.clip_rect, ColorF::new(0.0, 0.0, 1.0, 1.0));
+
+        let info = self.make_common_properties(rect(120., 10., 100., 100.).to_box2d());
+        let gradient = builder.create_gradient(
+            point2(0., 0.),
+            point2(100., 100.),
+            vec![
+                GradientStop { offset: 0.0, color: ColorF::new(1.0, 0.0, 0.0, 1.0), color_binding: None },
+                GradientStop { offset: 1.0, color: ColorF::new(0.0, 1.0, 0.0, 0.5), color_binding: None },
+            ],
+            ExtendMode::Repeat,
+        );
+        builder.push_gradient(&info, info.clip_rect, gradient, size2(50., 50.), LayoutSize::zero());
+
+        let info = self.make_common_properties(rect(230., 10., 100., 100.).to_box2d());
+        builder.push_image(
+            &info,
+            info.clip_rect,
+            ImageRendering::Pixelated,
+            AlphaType::PremultipliedAlpha,
+            image,
+            ColorF::WHITE,
+        );
+
+        let clip_rect = rect(10., 120., 100., 100.).to_box2d();
+        let clip_id = builder.define_clip_rounded_rect(
+            &space_and_clip,
+            ComplexClipRegion::new(clip_rect, BorderRadius::uniform(30.), ClipMode::Clip),
+        );
+        builder.push_rect(
+            &CommonItemProperties {
+                clip_rect,
+                clip_id,
+                spatial_id: space_and_clip.spatial_id,
+                flags: PrimitiveFlags::default(),
+            },
+            clip_rect,
+            ColorF::new(0.0, 0.5, 0.5, 1.0),
+        );
+
+        let info = self.make_common_properties(rect(120., 120., 100., 100.).to_box2d());
+        let side = BorderSide {
+            color: ColorF::new(0.5, 0.0, 0.5, 1.0),
+            style: BorderStyle::Solid,
+        };
+        builder.push_border(
+            &info,
+            info.clip_rect,
+            LayoutSideOffsets::new_all_same(8.0),
+            BorderDetails::Normal(NormalBorder {
+                top: side,
+                right: side,
+                bottom: side,
+                left: side,
+                radius: BorderRadius::uniform(16.0),
+                do_aa: true,
+            }),
+        );
+
+        let info = self.make_common_properties(rect(220., 110., 120., 120.).to_box2d());
+        builder.push_box_shadow(
+            &info,
+            rect(240., 130., 80., 80.).to_box2d(),
+            LayoutVector2D::new(5., 5.),
+            ColorF::new(0.0, 0.0, 0.0, 0.5),
+            4.0,
+            2.0,
+            BorderRadius::uniform(10.0),
+            BoxShadowClipMode::Outset,
+        );
+
+        let spatial_id = builder.push_reference_frame(
+            LayoutPoint::new(10., 240.),
+            space_and_clip.spatial_id,
+            TransformStyle::Flat,
+            PropertyBinding::Value(LayoutTransform::scale(1.5, 1.5, 1.0)),
+            ReferenceFrameKind::Transform {
+                is_2d_scale_translation: true,
+                should_snap: false,
+                paired_with_perspective: false,
+            },
+            SpatialTreeItemKey::new(0, 0),
+        );
+        builder.push_simple_stacking_context_with_filters(
+            LayoutPoint::zero(),
+            spatial_id,
+            PrimitiveFlags::default(),
+            &[FilterOp::Opacity(PropertyBinding::Value(0.5), 0.5)],
+            &[],
+            &[],
+        );
+        let info = CommonItemProperties {
+            clip_rect: rect(0., 0., 80., 80.).to_box2d(),
+            clip_id: ClipId::root(self.wrench.root_pipeline_id),
+            spatial_id,
+            flags: PrimitiveFlags::default(),
+        };
+        builder.push_rect(&info, info.clip_rect, ColorF::new(1.0, 0.5, 0.0, 1.0));
+        builder.pop_stacking_context();
+        builder.pop_reference_frame();
+
+        self.submit_dl(&mut Epoch(0), layout_size, builder, txn);
+        let pixels0 = self.render_and_get_pixels(window_rect);
+
+        // 2. capture it as YAML
+        self.wrench.api.save_capture(path.clone(), CaptureBits::SCENE | CaptureBits::YAML);
+        self.wrench.api.flush_scene_builder();
+
+        // 3. read the YAML back and compare
+        let document_id = self.wrench.document_id;
+        let yaml_path = path.join("yaml").join(format!(
+            "scene-{}-{}.yaml",
+            document_id.namespace_id.0,
+            document_id.id,
+        ));
+        let mut reader = YamlFrameReader::new(&yaml_path);
+        reader.do_frame(self.wrench);
+        self.wrench.api.flush_scene_builder();
+
+        let pixels1 = self.render_and_get_pixels(window_rect);
+        self.compare_pixels(pixels0, pixels1, window_rect.size());
+
+        reader.deinit(self.wrench);
+        let mut txn = Transaction::new();
+        txn.delete_image(image);
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+    }
+
     fn test_zero_height_window(&mut self) {
         println!("\tzero height test...");
 
@@ -1390,6 +1764,27 @@ impl<'a> RawtestHarness<'a> {
             (0, 5),
         );
 
+        // Add a rectangle that is clipped by a triangular polygon clip.
+        let rect = LayoutRect::from_origin_and_size(LayoutPoint::new(0., 200.), LayoutSize::new(100., 100.));
+        let clip_id = builder.define_clip_polygon(
+            &space_and_clip,
+            &[
+                LayoutPoint::new(0., 200.),
+                LayoutPoint::new(100., 200.),
+                LayoutPoint::new(0., 300.),
+            ],
+            FillRule::Nonzero,
+        );
+        builder.push_hit_test(
+            &CommonItemProperties {
+                clip_rect: rect,
+                clip_id,
+                spatial_id: space_and_clip.spatial_id,
+                flags: PrimitiveFlags::default(),
+            },
+            (0, 6),
+        );
+
         let mut epoch = Epoch(0);
         let txn = Transaction::new();
         self.submit_dl(&mut epoch, layout_size, builder, txn);
@@ -1444,6 +1839,207 @@ impl<'a> RawtestHarness<'a> {
 
         test_rounded_rectangle(WorldPoint::new(100., 100.), WorldSize::new(100., 100.), (0, 4));
         test_rounded_rectangle(WorldPoint::new(200., 100.), WorldSize::new(100., 100.), (0, 5));
+
+        // Only the half of the rectangle inside the triangle should be hit.
+        assert_hit_test(WorldPoint::new(20., 220.), vec![(0, 6), (0, 1)]);
+        assert_hit_test(WorldPoint::new(80., 280.), vec![(0, 1)]);
+
+        let hit_test_query = |query: HitTestQuery| -> Vec<ItemTag> {
+            self.wrench.api.hit_test_query(self.wrench.document_id, query)
+                .items
+                .iter()
+                .map(|item| item.tag)
+                .collect()
+        };
+
+        // Area queries hit every item overlapping the area.
+        let area = WorldRect::new(WorldPoint::new(90., 50.), WorldPoint::new(110., 60.));
+        assert_eq!(hit_test_query(HitTestQuery::rect(area)), vec![(0, 2), (0, 1)]);
+
+        // Only the parts of items that aren't clipped out are hit.
+        assert_eq!(hit_test_query(HitTestQuery::circle(WorldPoint::new(98., 98.), 6.)), vec![(0, 1)]);
+        assert_eq!(hit_test_query(HitTestQuery::circle(WorldPoint::new(98., 98.), 30.)), vec![(0, 4), (0, 1)]);
+
+        assert_eq!(hit_test_query(HitTestQuery::point(WorldPoint::new(150., 50.)).topmost()), vec![(0, 2)]);
+
+        let scene = WorldRect::from_size(WorldSize::new(400., 400.));
+        assert_eq!(
+            hit_test_query(HitTestQuery::rect(scene).with_tag_range((0, 4) ..= (0, 5))),
+            vec![(0, 5), (0, 4)],
+        );
+
+        let result = self.wrench.api.hit_test_query(
+            self.wrench.document_id,
+            HitTestQuery::circle(WorldPoint::new(160., 40.), 5.).topmost(),
+        );
+        assert_eq!(result.items[0].point_in_local_space, LayoutPoint::new(160., 40.));
+        assert_eq!(
+            result.items[0].item_rect,
+            WorldRect::new(WorldPoint::new(100., 0.), WorldPoint::new(200., 100.)),
+        );
+    }
+
+    fn test_hit_test_shapes(&mut self) {
+        println!("\thit test shapes test...");
+
+        let layout_size = LayoutSize::new(400., 400.);
+        let mut builder = DisplayListBuilder::new(self.wrench.root_pipeline_id);
+        builder.begin();
+
+        let info = self.make_common_properties(LayoutRect::from_size(layout_size));
+        builder.push_hit_test(&info, (0, 1));
+
+        // A button with rounded corners.
+        let info = self.make_common_properties(LayoutRect::from_origin_and_size(
+            LayoutPoint::new(0., 0.),
+            LayoutSize::new(100., 100.),
+        ));
+        let radius = BorderRadius::uniform_size(LayoutSize::new(30., 30.));
+        builder.push_hit_test_shape(&info, (0, 2), HitTestShape::RoundedRect(radius), false);
+
+        // A round button.
+        let info = self.make_common_properties(LayoutRect::from_origin_and_size(
+            LayoutPoint::new(200., 0.),
+            LayoutSize::new(200., 100.),
+        ));
+        builder.push_hit_test_shape(&info, (0, 3), HitTestShape::Ellipse, false);
+
+        // A triangular control.
+        let info = self.make_common_properties(LayoutRect::from_origin_and_size(
+            LayoutPoint::new(0., 200.),
+            LayoutSize::new(100., 100.),
+        ));
+        builder.push_hit_test_polygon(
+            &info,
+            (0, 4),
+            &[
+                LayoutPoint::new(0., 200.),
+                LayoutPoint::new(100., 200.),
+                LayoutPoint::new(0., 300.),
+            ],
+            FillRule::Nonzero,
+            false,
+        );
+
+        // A pass-through overlay on top of the triangle.
+        let info = self.make_common_properties(LayoutRect::from_origin_and_size(
+            LayoutPoint::new(0., 200.),
+            LayoutSize::new(200., 200.),
+        ));
+        builder.push_hit_test_shape(&info, (0, 5), HitTestShape::Rect, true);
+
+        let mut epoch = Epoch(0);
+        let txn = Transaction::new();
+        self.submit_dl(&mut epoch, layout_size, builder, txn);
+
+        // We render to ensure that the hit tester is up to date with the current scene.
+        self.rx.recv().unwrap();
+        self.wrench.render();
+
+        let hit_test = |query: HitTestQuery| -> Vec<ItemTag> {
+            self.wrench.api.hit_test_query(self.wrench.document_id, query)
+                .items
+                .iter()
+                .map(|item| item.tag)
+                .collect()
+        };
+        let point = |x, y| HitTestQuery::point(WorldPoint::new(x, y));
+
+        // The rounded corners aren't hit-testable.
+        assert_eq!(hit_test(point(50., 50.)), vec![(0, 2), (0, 1)]);
+        assert_eq!(hit_test(point(3., 3.)), vec![(0, 1)]);
+        assert_eq!(hit_test(point(97., 3.)), vec![(0, 1)]);
+
+        // Only the inside of the ellipse is.
+        assert_eq!(hit_test(point(300., 50.)), vec![(0, 3), (0, 1)]);
+        assert_eq!(hit_test(point(205., 50.)), vec![(0, 3), (0, 1)]);
+        assert_eq!(hit_test(point(210., 10.)), vec![(0, 1)]);
+
+        // Only the inside of the triangle is.
+        assert_eq!(hit_test(point(20., 220.)), vec![(0, 5), (0, 4), (0, 1)]);
+        assert_eq!(hit_test(point(80., 280.)), vec![(0, 5), (0, 1)]);
+
+        // Pass-through items don't hide the items behind them from topmost
+        // queries.
+        assert_eq!(hit_test(point(20., 220.).topmost()), vec![(0, 5), (0, 4)]);
+        assert_eq!(hit_test(point(150., 250.).topmost()), vec![(0, 5), (0, 1)]);
+        assert_eq!(hit_test(point(50., 50.).topmost()), vec![(0, 2)]);
+    }
+
+    fn test_hit_test_area_slivers(&mut self) {
+        println!("\thit test area slivers test...");
+
+        let layout_size = LayoutSize::new(400., 400.);
+        let mut builder = DisplayListBuilder::new(self.wrench.root_pipeline_id);
+        builder.begin();
+
+        let space_and_clip = SpaceAndClipInfo::root_scroll(self.wrench.root_pipeline_id);
+
+        // A square clipped to a circle, so that a corner of it is left with
+        // only a sliver at the far corner of a small area.
+        let rect = LayoutRect::from_size(LayoutSize::new(200., 200.));
+        let clip_id = builder.define_clip_rounded_rect(
+            &space_and_clip,
+            ComplexClipRegion::new(rect, BorderRadius::uniform(100.), ClipMode::Clip),
+        );
+        builder.push_hit_test(
+            &self.make_common_properties_with_clip_and_spatial(rect, clip_id, space_and_clip.spatial_id),
+            (0, 1),
+        );
+
+        // A long, thin spike.
+        let info = self.make_common_properties(LayoutRect::from_origin_and_size(
+            LayoutPoint::new(200., 250.),
+            LayoutSize::new(200., 100.),
+        ));
+        builder.push_hit_test_polygon(
+            &info,
+            (0, 2),
+            &[
+                LayoutPoint::new(200., 299.),
+                LayoutPoint::new(400., 300.),
+                LayoutPoint::new(200., 301.),
+            ],
+            FillRule::Nonzero,
+            false,
+        );
+
+        let mut epoch = Epoch(0);
+        let txn = Transaction::new();
+        self.submit_dl(&mut epoch, layout_size, builder, txn);
+
+        // We render to ensure that the hit tester is up to date with the current scene.
+        self.rx.recv().unwrap();
+        self.wrench.render();
+
+        let hit_test = |query: HitTestQuery| -> Vec<ItemTag> {
+            self.wrench.api.hit_test_query(self.wrench.document_id, query)
+                .items
+                .iter()
+                .map(|item| item.tag)
+                .collect()
+        };
+        let rect_area = |x0, y0, x1, y1| {
+            HitTestQuery::rect(WorldRect::new(WorldPoint::new(x0, y0), WorldPoint::new(x1, y1)))
+        };
+        let circle_area = |x, y, radius| HitTestQuery::circle(WorldPoint::new(x, y), radius);
+
+        // The circle only reaches into the area in a sliver a few pixels
+        // across at its far corner, but that is still hit.
+        assert_eq!(hit_test(rect_area(0., 0., 31., 31.)), vec![(0, 1)]);
+        assert_eq!(hit_test(rect_area(0., 0., 29., 29.)), Vec::<ItemTag>::new());
+
+        // The spike is a fraction of a pixel tall where the area overlaps it,
+        // away from the center of the area.
+        assert_eq!(hit_test(rect_area(370., 260., 400., 320.)), vec![(0, 2)]);
+        assert_eq!(hit_test(rect_area(370., 260., 400., 295.)), Vec::<ItemTag>::new());
+        assert_eq!(hit_test(circle_area(385., 290., 11.)), vec![(0, 2)]);
+        assert_eq!(hit_test(circle_area(385., 290., 9.)), Vec::<ItemTag>::new());
+
+        // The hit is at the point of the spike closest to the center.
+        let result = self.wrench.api.hit_test_query(self.wrench.document_id, rect_area(370., 260., 400., 320.));
+        let point = result.items[0].point_in_local_space;
+        assert!((point.x - 385.).abs() < 0.1 && (point.y - 300.).abs() < 0.2);
     }
 
     fn test_clear_cache(&mut self) {
@@ -1462,4 +2058,90 @@ impl<'a> RawtestHarness<'a> {
         self.rx.recv().unwrap();
         self.wrench.render();
     }
+
+    #[cfg(feature = "software")]
+    fn test_swgl_raster_bands(&mut self) {
+        println!("\tswgl raster bands...");
+        // Draw calls split into bands that are rasterized in parallel must
+        // produce exactly the same pixels as when they are rasterized whole.
+
+        let swgl = match self.window.software_gl() {
+            Some(swgl) => *swgl,
+            None => return,
+        };
+
+        let window_size = self.window.get_inner_size();
+        let test_size = FramebufferIntSize::new(400, 400);
+        let window_rect = FramebufferIntRect::from_origin_and_size(
+            point2(0, window_size.height - test_size.height),
+            test_size,
+        );
+        let layout_size = LayoutSize::new(400., 400.);
+
+        // Make sure every frame rasterizes the whole scene again.
+        let debug_flags = self.wrench.renderer.get_debug_flags();
+        self.wrench.api.send_debug_cmd(
+            DebugCommand::SetFlags(debug_flags | DebugFlags::FORCE_PICTURE_INVALIDATION)
+        );
+
+        let mut do_test = |num_bands| {
+            swgl.set_rasterize_workers(Some(Box::new(ThreadRasterizeWorkers(num_bands))));
+
+            let mut builder = DisplayListBuilder::new(self.wrench.root_pipeline_id);
+            builder.begin();
+            let space_and_clip = SpaceAndClipInfo::root_scroll(self.wrench.root_pipeline_id);
+
+            let info = self.make_common_properties(rect(0., 0., 400., 400.).to_box2d());
+            let gradient = builder.create_gradient(
+                point2(0., 0.),
+                point2(400., 400.),
+                vec![
+                    GradientStop { offset: 0.0, color: ColorF::new(1.0, 0.0, 0.0, 1.0), color_binding: None },
+                    GradientStop { offset: 0.5, color: ColorF::new(0.0, 1.0, 0.0, 0.5), color_binding: None },
+                    GradientStop { offset: 1.0, color: ColorF::new(0.0, 0.0, 1.0, 1.0), color_binding: None },
+                ],
+                ExtendMode::Clamp,
+            );
+            builder.push_gradient(
+                &info,
+                info.clip_rect,
+                gradient,
+                layout_size,
+                LayoutSize::zero(),
+            );
+
+            // A rounded clip crossing the band boundaries.
+            let clip_rect = rect(50., 30., 300., 340.).to_box2d();
+            let clip_id = builder.define_clip_rounded_rect(
+                &space_and_clip,
+                ComplexClipRegion::new(
+                    clip_rect,
+                    BorderRadius::uniform_size(LayoutSize::new(80., 80.)),
+                    ClipMode::Clip,
+                ),
+            );
+            builder.push_rect(
+                &CommonItemProperties {
+                    clip_rect,
+                    clip_id,
+                    spatial_id: space_and_clip.spatial_id,
+                    flags: PrimitiveFlags::default(),
+                },
+                clip_rect,
+                ColorF::new(1.0, 1.0, 0.0, 0.75),
+            );
+
+            let txn = Transaction::new();
+            self.submit_dl(&mut Epoch(0), layout_size, builder, txn);
+
+            self.render_and_get_pixels(window_rect)
+        };
+
+        let whole = do_test(1);
+        let banded = do_test(4);
+
+        self.compare_pixels(whole, banded, test_size);
+
+        self.wrench.api.send_debug_cmd(DebugCommand::SetFlags(debug_flags));
+    }
 }
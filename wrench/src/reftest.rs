@@ -7,14 +7,16 @@ use image::load as load_piston_image;
 use image::png::PNGEncoder;
 use image::{ColorType, ImageFormat};
 use crate::parse_function::parse_function;
-use crate::png::save_flipped;
-use std::{cmp, env};
+use crate::png::{save, save_flipped, SaveSettings};
+use std::{cmp, env, fs};
+use std::collections::BTreeMap;
 use std::fmt::{Display, Error, Formatter};
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::mpsc::Receiver;
+use std::time::Instant;
 use webrender::RenderResults;
 use webrender::api::*;
 use webrender::render_api::*;
@@ -32,6 +34,14 @@ pub struct ReftestOptions {
     // These override values that are lower.
     pub allow_max_difference: usize,
     pub allow_num_differences: usize,
+    /// Ignore the fuzzy annotations of the manifest and require exact matches.
+    pub exact: bool,
+    /// Directory in which the test, reference and diff images of failing tests are saved.
+    pub output_dir: Option<PathBuf>,
+    /// Path of the JSON summary of all results.
+    pub json_report: Option<PathBuf>,
+    /// Path of the JUnit XML summary of all results.
+    pub junit_report: Option<PathBuf>,
 }
 
 impl ReftestOptions {
@@ -39,6 +49,10 @@ impl ReftestOptions {
         ReftestOptions {
             allow_max_difference: 0,
             allow_num_differences: 0,
+            exact: false,
+            output_dir: None,
+            json_report: None,
+            junit_report: None,
         }
     }
 }
@@ -115,12 +129,20 @@ impl Reftest {
         comparison: ReftestImageComparison,
         test: &ReftestImage,
         reference: &ReftestImage,
+        result: &mut ReftestResult,
     ) -> bool {
         match comparison {
             ReftestImageComparison::Equal => {
                 true
             }
-            ReftestImageComparison::NotEqual { difference_histogram, max_difference, count_different } => {
+            ReftestImageComparison::NotEqual {
+                difference_histogram,
+                channel_histograms,
+                max_difference,
+                count_different,
+            } => {
+                result.add_differences(max_difference, count_different, &channel_histograms);
+
                 // Each entry in the sorted self.fuzziness list represents a bucket which
                 // allows at most num_differences pixels with a difference of at most
                 // max_difference -- but with the caveat that a difference which is small
@@ -213,6 +235,18 @@ impl Reftest {
                         count_different,
                         fail_text,
                     );
+                    for (name, histogram) in CHANNEL_NAMES.iter().zip(channel_histograms.iter()) {
+                        let channel_count: usize = histogram.iter().sum();
+                        if channel_count != 0 {
+                            let channel_max = histogram.iter().rposition(|&n| n != 0).unwrap();
+                            println!(
+                                "REFTEST   CHANNEL {}: max difference: {}, number of differing pixels: {}",
+                                name,
+                                channel_max,
+                                channel_count,
+                            );
+                        }
+                    }
                     println!("REFTEST   IMAGE 1 (TEST): {}", test.clone().create_data_uri());
                     println!(
                         "REFTEST   IMAGE 2 (REFERENCE): {}",
@@ -220,8 +254,15 @@ impl Reftest {
                     );
                     println!("REFTEST TEST-END | {}", self);
 
+                    result.failure = Some(format!(
+                        "image comparison, max difference: {}, number of differing pixels: {} | {}",
+                        max_difference,
+                        count_different,
+                        fail_text,
+                    ));
                     false
                 } else {
+                    result.fuzzy_match = true;
                     true
                 }
             }
@@ -229,10 +270,156 @@ impl Reftest {
     }
 
     /// Report details of the negative case
-    fn report_unexpected_equality(&self) {
+    fn report_unexpected_equality(&self, result: &mut ReftestResult) {
         println!("REFTEST TEST-UNEXPECTED-FAIL | {} | image comparison", self);
         println!("REFTEST TEST-END | {}", self);
+        result.failure = Some("image comparison, images are equal".to_string());
+    }
+
+    /// A name for this test that is usable as a file name.
+    fn file_stem(&self) -> String {
+        let mut stem = String::new();
+        for c in self.to_string().chars() {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                stem.push(c);
+            } else if !stem.ends_with('_') {
+                stem.push('_');
+            }
+        }
+        stem.trim_matches('_').to_string()
+    }
+}
+
+const CHANNEL_NAMES: [&str; 4] = ["R", "G", "B", "A"];
+
+/// The outcome of a single reftest, as written to the JSON and JUnit summaries.
+#[derive(Serialize)]
+pub struct ReftestResult {
+    pub test: String,
+    pub passed: bool,
+    /// The test only passed thanks to its fuzzy annotations.
+    pub fuzzy_match: bool,
+    pub failure: Option<String>,
+    pub duration_ms: f64,
+    pub max_difference: usize,
+    pub count_different: usize,
+    /// Per-channel (R, G, B, A) histograms of the non-zero differences,
+    /// mapping a difference to the number of pixels with that difference.
+    pub channel_histograms: BTreeMap<&'static str, BTreeMap<usize, usize>>,
+    /// The test, reference and diff images saved for a failing test.
+    pub images: Vec<PathBuf>,
+}
+
+impl ReftestResult {
+    fn new(reftest: &Reftest) -> Self {
+        ReftestResult {
+            test: reftest.to_string(),
+            passed: false,
+            fuzzy_match: false,
+            failure: None,
+            duration_ms: 0.0,
+            max_difference: 0,
+            count_different: 0,
+            channel_histograms: BTreeMap::new(),
+            images: Vec::new(),
+        }
+    }
+
+    fn add_differences(
+        &mut self,
+        max_difference: usize,
+        count_different: usize,
+        channel_histograms: &[Vec<usize>; 4],
+    ) {
+        self.max_difference = cmp::max(self.max_difference, max_difference);
+        self.count_different = cmp::max(self.count_different, count_different);
+        for (name, histogram) in CHANNEL_NAMES.iter().zip(channel_histograms.iter()) {
+            let entry = self.channel_histograms.entry(*name).or_insert_with(BTreeMap::new);
+            for (difference, &count) in histogram.iter().enumerate() {
+                if count != 0 {
+                    let total = entry.entry(difference).or_insert(0);
+                    *total = cmp::max(*total, count);
+                }
+            }
+        }
+    }
+}
+
+fn write_json_report(path: &Path, results: &[ReftestResult]) {
+    #[derive(Serialize)]
+    struct Report<'a> {
+        passing: usize,
+        failing: usize,
+        results: &'a [ReftestResult],
     }
+
+    let passing = results.iter().filter(|r| r.passed).count();
+    let report = Report {
+        passing,
+        failing: results.len() - passing,
+        results,
+    };
+
+    let mut file = File::create(path).unwrap();
+    let s = serde_json::to_string_pretty(&report).unwrap();
+    file.write_all(&s.into_bytes()).unwrap();
+    file.write_all(b"\n").unwrap();
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn write_junit_report(path: &Path, results: &[ReftestResult]) {
+    let failing = results.iter().filter(|r| !r.passed).count();
+    let total_ms: f64 = results.iter().map(|r| r.duration_ms).sum();
+
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuite name=\"wrench-reftests\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+        results.len(),
+        failing,
+        total_ms / 1000.0,
+    ));
+    for result in results {
+        xml.push_str(&format!(
+            "  <testcase classname=\"reftest\" name=\"{}\" time=\"{:.3}\"",
+            escape_xml(&result.test),
+            result.duration_ms / 1000.0,
+        ));
+        if result.passed {
+            xml.push_str("/>\n");
+            continue;
+        }
+        xml.push_str(">\n");
+        let message = result.failure.as_ref().map_or("unexpected result", |f| f.as_str());
+        xml.push_str(&format!("    <failure message=\"{}\">", escape_xml(message)));
+        for (name, histogram) in &result.channel_histograms {
+            let bins: Vec<String> = histogram.iter().map(|(d, n)| format!("{}:{}", d, n)).collect();
+            xml.push_str(&escape_xml(&format!("channel {} differences {}\n", name, bins.join(" "))));
+        }
+        for image in &result.images {
+            xml.push_str(&escape_xml(&format!("image {}\n", image.display())));
+        }
+        xml.push_str("</failure>\n");
+        xml.push_str("  </testcase>\n");
+    }
+    xml.push_str("</testsuite>\n");
+
+    let mut file = File::create(path).unwrap();
+    file.write_all(xml.as_bytes()).unwrap();
 }
 
 impl Display for Reftest {
@@ -260,6 +447,9 @@ pub enum ReftestImageComparison {
     NotEqual {
         /// entry[j] = number of pixels with a difference of exactly j
         difference_histogram: Vec<usize>,
+        /// The same, for the difference of each of the R, G, B and A
+        /// channels of the pixels that differ.
+        channel_histograms: [Vec<usize>; 4],
         max_difference: usize,
         count_different: usize,
     },
@@ -272,16 +462,20 @@ impl ReftestImage {
         assert_eq!(self.data.len() % 4, 0);
 
         let mut histogram = [0usize; 256];
+        let mut channel_histograms = [[0usize; 256]; 4];
         let mut count = 0;
         let mut max = 0;
 
         for (a, b) in self.data.chunks(4).zip(other.data.chunks(4)) {
             if a != b {
-                let pixel_max = a.iter()
-                    .zip(b.iter())
-                    .map(|(x, y)| (*x as isize - *y as isize).abs() as usize)
-                    .max()
-                    .unwrap();
+                let mut pixel_max = 0;
+                for (channel, (x, y)) in a.iter().zip(b.iter()).enumerate() {
+                    let difference = (*x as isize - *y as isize).abs() as usize;
+                    if difference != 0 {
+                        channel_histograms[channel][difference] += 1;
+                    }
+                    pixel_max = cmp::max(pixel_max, difference);
+                }
 
                 count += 1;
                 assert!(pixel_max < 256, "pixel values are not 8 bit, update the histogram binning code");
@@ -297,6 +491,12 @@ impl ReftestImage {
         if count != 0 {
             ReftestImageComparison::NotEqual {
                 difference_histogram: histogram.to_vec(),
+                channel_histograms: [
+                    channel_histograms[0].to_vec(),
+                    channel_histograms[1].to_vec(),
+                    channel_histograms[2].to_vec(),
+                    channel_histograms[3].to_vec(),
+                ],
                 max_difference: max,
                 count_different: count,
             }
@@ -305,6 +505,37 @@ impl ReftestImage {
         }
     }
 
+    /// Create an image highlighting the pixels that differ from `other`.
+    ///
+    /// Matching pixels are drawn as a faded grayscale copy of this image,
+    /// differing pixels are drawn in red, brighter for larger differences.
+    pub fn create_diff_image(&self, other: &ReftestImage) -> ReftestImage {
+        assert_eq!(self.size, other.size);
+        assert_eq!(self.data.len(), other.data.len());
+
+        let mut data = Vec::with_capacity(self.data.len());
+        for (a, b) in self.data.chunks(4).zip(other.data.chunks(4)) {
+            let pixel_max = a.iter()
+                .zip(b.iter())
+                .map(|(x, y)| (*x as i32 - *y as i32).abs())
+                .max()
+                .unwrap();
+            if pixel_max == 0 {
+                let luma = (a[0] as u32 * 54 + a[1] as u32 * 183 + a[2] as u32 * 19) >> 8;
+                let faded = (192 + luma / 4) as u8;
+                data.extend_from_slice(&[faded, faded, faded, 255]);
+            } else {
+                let intensity = (128 + pixel_max / 2) as u8;
+                data.extend_from_slice(&[intensity, 0, 0, 255]);
+            }
+        }
+
+        ReftestImage {
+            data,
+            size: self.size,
+        }
+    }
+
     pub fn create_data_uri(mut self) -> String {
         let width = self.size.width;
         let height = self.size.height;
@@ -511,6 +742,10 @@ impl ReftestManifest {
                 fuzziness.push(RefTestFuzzy { max_difference: 2, num_differences: std::usize::MAX });
             }
 
+            if options.exact {
+                fuzziness.clear();
+            }
+
             // to avoid changing the meaning of existing tests, the case of
             // only a single (or no) 'fuzzy' keyword means we use the max
             // of that fuzzy and options.allow_.. (we don't want that to
@@ -564,6 +799,27 @@ impl ReftestManifest {
     }
 }
 
+/// Save the test, reference and diff images of a failing comparison.
+fn save_failure_images(
+    output_dir: &Path,
+    stem: &str,
+    test: &ReftestImage,
+    reference: &ReftestImage,
+    result: &mut ReftestResult,
+) {
+    let diff = test.create_diff_image(reference);
+    let images = [("test", test), ("reference", reference), ("diff", &diff)];
+    for &(suffix, image) in &images {
+        let path = output_dir.join(format!("{}-{}.png", stem, suffix));
+        save(&path, image.data.clone(), image.size, SaveSettings {
+            flip_vertical: true,
+            try_crop: false,
+        });
+        println!("REFTEST   IMAGE {}: {}", suffix.to_uppercase(), path.display());
+        result.images.push(path);
+    }
+}
+
 struct YamlRenderOutput {
     image: ReftestImage,
     results: RenderResults,
@@ -703,13 +959,31 @@ impl<'a> ReftestHarness<'a> {
 
         let mut total_passing = 0;
         let mut failing = Vec::new();
+        let mut results = Vec::new();
+
+        if let Some(ref output_dir) = options.output_dir {
+            fs::create_dir_all(output_dir).unwrap();
+        }
 
         for t in reftests {
-            if self.run_reftest(t) {
+            let mut result = ReftestResult::new(t);
+            let start = Instant::now();
+            result.passed = self.run_reftest(t, &mut result, options.output_dir.as_deref());
+            result.duration_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+            if result.passed {
                 total_passing += 1;
             } else {
                 failing.push(t);
             }
+            results.push(result);
+        }
+
+        if let Some(ref path) = options.json_report {
+            write_json_report(path, &results);
+        }
+        if let Some(ref path) = options.junit_report {
+            write_junit_report(path, &results);
         }
 
         println!(
@@ -729,7 +1003,12 @@ impl<'a> ReftestHarness<'a> {
         failing.len()
     }
 
-    fn run_reftest(&mut self, t: &Reftest) -> bool {
+    fn run_reftest(
+        &mut self,
+        t: &Reftest,
+        result: &mut ReftestResult,
+        output_dir: Option<&Path>,
+    ) -> bool {
         let test_name = t.to_string();
         println!("REFTEST {}", test_name);
         profile_scope!("wrench reftest", text: &test_name);
@@ -868,6 +1147,7 @@ impl<'a> ReftestHarness<'a> {
                     results,
                 );
                 println!("REFTEST TEST-END | {}", t);
+                result.failure = Some(format!("Failing Check: {:?}", extra_check));
                 return false;
             }
         }
@@ -877,11 +1157,18 @@ impl<'a> ReftestHarness<'a> {
                 // Ensure that the final image matches the reference
                 let test = images.pop().unwrap();
                 let comparison = test.compare(&reference);
-                t.check_and_report_equality_failure(
+                let passed = t.check_and_report_equality_failure(
                     comparison,
                     &test,
                     &reference,
-                )
+                    result,
+                );
+                if !passed {
+                    if let Some(output_dir) = output_dir {
+                        save_failure_images(output_dir, &t.file_stem(), &test, &reference, result);
+                    }
+                }
+                passed
             }
             ReftestOp::NotEqual => {
                 // Ensure that the final image *doesn't* match the reference
@@ -889,7 +1176,7 @@ impl<'a> ReftestHarness<'a> {
                 let comparison = test.compare(&reference);
                 match comparison {
                     ReftestImageComparison::Equal => {
-                        t.report_unexpected_equality();
+                        t.report_unexpected_equality(result);
                         false
                     }
                     ReftestImageComparison::NotEqual { .. } => {
@@ -899,14 +1186,19 @@ impl<'a> ReftestHarness<'a> {
             }
             ReftestOp::Accurate => {
                 // Ensure that *all* images match the reference
-                for test in images.drain(..) {
+                for (i, test) in images.drain(..).enumerate() {
                     let comparison = test.compare(&reference);
 
                     if !t.check_and_report_equality_failure(
                         comparison,
                         &test,
                         &reference,
+                        result,
                     ) {
+                        if let Some(output_dir) = output_dir {
+                            let stem = format!("{}-{}", t.file_stem(), i);
+                            save_failure_images(output_dir, &stem, &test, &reference, result);
+                        }
                         return false;
                     }
                 }
@@ -923,7 +1215,7 @@ impl<'a> ReftestHarness<'a> {
                 });
 
                 if all_same {
-                    t.report_unexpected_equality();
+                    t.report_unexpected_equality(result);
                 }
 
                 !all_same
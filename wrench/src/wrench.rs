@@ -195,6 +195,7 @@ impl WrenchThing for CapturedSequence {
 
 pub struct Wrench {
     window_size: DeviceIntSize,
+    output_color_space: RgbColorSpace,
 
     pub renderer: webrender::Renderer,
     pub api: RenderApi,
@@ -229,6 +230,7 @@ impl Wrench {
         precache_shaders: bool,
         disable_dual_source_blending: bool,
         chase_primitive: webrender::ChasePrimitive,
+        hdr_tone_mapping: Option<webrender::ToneMapping>,
         dump_shader_source: Option<String>,
         notifier: Option<Box<dyn RenderNotifier>>,
     ) -> Self {
@@ -254,6 +256,7 @@ impl Wrench {
             precache_flags,
             blob_image_handler: Some(Box::new(blob::CheckerboardRenderer::new(callbacks.clone()))),
             chase_primitive,
+            hdr_tone_mapping,
             testing: true,
             max_internal_texture_size: Some(8196), // Needed for rawtest::test_resize_image.
             allow_dual_source_blending: !disable_dual_source_blending,
@@ -262,6 +265,8 @@ impl Wrench {
             // SWGL doesn't support the GL_ALWAYS depth comparison function used by
             // `clear_caches_with_quads`, but scissored clears work well.
             clear_caches_with_quads: !window.is_software(),
+            #[cfg(feature = "software")]
+            swgl_context: window.software_gl().copied(),
             ..Default::default()
         };
 
@@ -291,6 +296,7 @@ impl Wrench {
 
         let mut wrench = Wrench {
             window_size: size,
+            output_color_space: RgbColorSpace::Srgb,
 
             renderer,
             api,
@@ -322,6 +328,17 @@ impl Wrench {
         self.api.send_transaction(self.document_id, txn);
     }
 
+    pub fn set_output_color_space(&mut self, color_space: RgbColorSpace) {
+        if color_space == self.output_color_space {
+            return;
+        }
+        self.output_color_space = color_space;
+
+        let mut txn = Transaction::new();
+        txn.set_output_color_space(color_space);
+        self.api.send_transaction(self.document_id, txn);
+    }
+
     pub fn layout_simple_ascii(
         &mut self,
         font_key: FontKey,
@@ -614,6 +631,7 @@ impl Wrench {
             "M - Trigger memory pressure event",
             "T - Save CPU profile to a file",
             "C - Save a capture to captures/wrench/",
+            "E - Toggle recording a profiler trace to captures/wrench-trace.json",
             "X - Do a hit test at the current cursor position",
             "Y - Clear all caches",
         ];
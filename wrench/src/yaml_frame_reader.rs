@@ -286,7 +286,105 @@ fn generate_solid_color_image(
     )
 }
 
+/// Generates a solid color image in a block-compressed format, so that the
+/// compressed upload paths and their CPU fallback can be tested.
+fn generate_compressed_solid_color_image(
+    format: &str,
+    r: u8,
+    g: u8,
+    b: u8,
+    a: u8,
+    w: u32,
+    h: u32,
+) -> (ImageDescriptor, ImageData) {
+    let (format, block) = match format {
+        "bc1" => {
+            // Both endpoints are the color, so every index decodes to it.
+            let color = (r as u16 >> 3) << 11 | (g as u16 >> 2) << 5 | b as u16 >> 3;
+            let mut block = Vec::with_capacity(8);
+            block.extend_from_slice(&color.to_le_bytes());
+            block.extend_from_slice(&color.to_le_bytes());
+            block.extend_from_slice(&[0; 4]);
+            (ImageFormat::BC1, block)
+        }
+        "astc" => {
+            // A void-extent block, which stores a single 16-bit per channel color.
+            let mut block = vec![0xfc, 0xfd, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+            for channel in &[r, g, b, a] {
+                block.extend_from_slice(&(*channel as u16 * 257).to_le_bytes());
+            }
+            (ImageFormat::ASTC4X4, block)
+        }
+        _ => panic!("Unsupported compressed format {}", format),
+    };
+
+    let mut flags = ImageDescriptorFlags::empty();
+    if a == 255 {
+        flags |= ImageDescriptorFlags::IS_OPAQUE;
+    }
+
+    let descriptor = ImageDescriptor::new(w as i32, h as i32, format, flags);
+    let num_blocks = (descriptor.compute_total_size() as usize) / block.len();
+
+    (descriptor, ImageData::new(block.repeat(num_blocks)))
+}
+
+/// Converts an f32 to an IEEE 754 binary16 value. The mantissa is truncated,
+/// and values too small to be a normal half float are flushed to zero.
+fn f32_to_half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    if value.is_nan() {
+        return sign | 0x7e00;
+    }
 
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    if exponent >= 0x1f {
+        sign | 0x7c00
+    } else if exponent <= 0 {
+        sign
+    } else {
+        sign | ((exponent as u16) << 10) | ((bits & 0x7f_ffff) >> 13) as u16
+    }
+}
+
+fn generate_hdr_solid_color_image(
+    format: &str,
+    r: f32,
+    g: f32,
+    b: f32,
+    a: f32,
+    w: u32,
+    h: u32,
+) -> (ImageDescriptor, ImageData) {
+    let (format, pixel) = match format {
+        "rgba16f" => {
+            let mut pixel = Vec::with_capacity(8);
+            for channel in &[r, g, b, a] {
+                pixel.extend_from_slice(&f32_to_half(*channel).to_ne_bytes());
+            }
+            (ImageFormat::RGBA16F, pixel)
+        }
+        "rgb10a2" => {
+            let unorm = |value: f32, max: f32| (value.clamp(0.0, 1.0) * max).round() as u32;
+            let bits = unorm(r, 1023.0) |
+                (unorm(g, 1023.0) << 10) |
+                (unorm(b, 1023.0) << 20) |
+                (unorm(a, 3.0) << 30);
+            (ImageFormat::RGB10A2, bits.to_ne_bytes().to_vec())
+        }
+        _ => panic!("Unsupported high bit depth format {}", format),
+    };
+
+    let mut flags = ImageDescriptorFlags::empty();
+    if a >= 1.0 {
+        flags |= ImageDescriptorFlags::IS_OPAQUE;
+    }
+
+    let descriptor = ImageDescriptor::new(w as i32, h as i32, format, flags);
+
+    (descriptor, ImageData::new(pixel.repeat((w * h) as usize)))
+}
 
 fn is_image_opaque(format: ImageFormat, bytes: &[u8]) -> bool {
     match format {
@@ -306,7 +404,15 @@ fn is_image_opaque(format: ImageFormat, bytes: &[u8]) -> bool {
         ImageFormat::R8 => false,
         ImageFormat::R16 => false,
         ImageFormat::RGBAF32 |
-        ImageFormat::RGBAI32 => unreachable!(),
+        ImageFormat::RGBAI32 |
+        ImageFormat::RGBA16F |
+        ImageFormat::RGB10A2 |
+        ImageFormat::BC1 |
+        ImageFormat::BC3 |
+        ImageFormat::BC7 |
+        ImageFormat::ETC2RGB8 |
+        ImageFormat::ETC2RGBA8 |
+        ImageFormat::ASTC4X4 => unreachable!(),
     }
 }
 
@@ -327,7 +433,7 @@ pub struct YamlFrameReader {
     scroll_offsets: HashMap<ExternalScrollId, Vec<SampledScrollOffset>>,
     next_external_scroll_id: u64,
 
-    image_map: HashMap<(PathBuf, Option<i64>), (ImageKey, LayoutSize)>,
+    image_map: HashMap<(PathBuf, Option<i64>, RgbColorSpace), (ImageKey, LayoutSize)>,
 
     fonts: HashMap<FontDescriptor, FontKey>,
     font_instances: HashMap<(FontKey, FontSize, FontInstanceFlags, Option<ColorU>, SyntheticItalics), FontInstanceKey>,
@@ -441,6 +547,10 @@ impl YamlFrameReader {
 
         self.reset();
 
+        wrench.set_output_color_space(
+            yaml["output-color-space"].as_rgb_color_space().unwrap_or(RgbColorSpace::Srgb),
+        );
+
         if let Some(pipelines) = yaml["pipelines"].as_vec() {
             for pipeline in pipelines {
                 self.build_pipeline(wrench, pipeline["id"].as_pipeline_id().unwrap(), pipeline);
@@ -588,13 +698,14 @@ impl YamlFrameReader {
         item: &Yaml,
         wrench: &mut Wrench,
     ) -> (ImageKey, LayoutSize) {
-        let key = (file.to_owned(), tiling);
+        let color_space = item["color-space"].as_rgb_color_space().unwrap_or(RgbColorSpace::Srgb);
+        let key = (file.to_owned(), tiling, color_space);
         if let Some(k) = self.image_map.get(&key) {
             return *k;
         }
 
         if self.list_resources { println!("{}", file.to_string_lossy()); }
-        let (descriptor, image_data) = match image::open(file) {
+        let (mut descriptor, image_data) = match image::open(file) {
             Ok(image) => {
                 let (image_width, image_height) = image.dimensions();
                 let (format, bytes) = match image {
@@ -660,6 +771,24 @@ impl YamlFrameReader {
                         args.get(4).unwrap_or(&"1000").parse::<u32>().unwrap(),
                         args.get(5).unwrap_or(&"1000").parse::<u32>().unwrap(),
                     ),
+                    ("compressed-solid-color", args, _) => generate_compressed_solid_color_image(
+                        args.get(0).unwrap_or(&"bc1"),
+                        args.get(1).unwrap_or(&"255").parse::<u8>().unwrap(),
+                        args.get(2).unwrap_or(&"255").parse::<u8>().unwrap(),
+                        args.get(3).unwrap_or(&"255").parse::<u8>().unwrap(),
+                        args.get(4).unwrap_or(&"255").parse::<u8>().unwrap(),
+                        args.get(5).unwrap_or(&"1000").parse::<u32>().unwrap(),
+                        args.get(6).unwrap_or(&"1000").parse::<u32>().unwrap(),
+                    ),
+                    ("hdr-solid-color", args, _) => generate_hdr_solid_color_image(
+                        args.get(0).unwrap_or(&"rgba16f"),
+                        args.get(1).unwrap_or(&"1.0").parse::<f32>().unwrap(),
+                        args.get(2).unwrap_or(&"1.0").parse::<f32>().unwrap(),
+                        args.get(3).unwrap_or(&"1.0").parse::<f32>().unwrap(),
+                        args.get(4).unwrap_or(&"1.0").parse::<f32>().unwrap(),
+                        args.get(5).unwrap_or(&"1000").parse::<u32>().unwrap(),
+                        args.get(6).unwrap_or(&"1000").parse::<u32>().unwrap(),
+                    ),
                     (name @ "transparent-checkerboard", args, _) |
                     (name @ "checkerboard", args, _) => {
                         let border = args.get(0).unwrap_or(&"4").parse::<u32>().unwrap();
@@ -703,6 +832,7 @@ impl YamlFrameReader {
                 }
             }
         };
+        descriptor.color_space = color_space;
         let tiling = tiling.map(|tile_size| tile_size as u16);
         let image_key = wrench.api.generate_image_key();
         let mut txn = Transaction::new();
@@ -841,7 +971,10 @@ impl YamlFrameReader {
 
         let bounds = self.resolve_rect(&item[bounds_key]);
         let color = self.resolve_colorf(&item["color"]).unwrap_or(ColorF::BLACK);
-        dl.push_rect(info, bounds, color);
+        match item["color-space"].as_rgb_color_space() {
+            Some(color_space) => dl.push_rect_with_color_space(info, bounds, color, color_space),
+            None => dl.push_rect(info, bounds, color),
+        }
     }
 
     fn handle_clear_rect(
@@ -865,14 +998,57 @@ impl YamlFrameReader {
             &info.clip_rect
         );
 
-        if let Some(tag) = self.to_hit_testing_tag(&item["hit-testing-tag"]) {
-            dl.push_hit_test(
-                info,
-                tag,
-            );
+        let tag = match self.to_hit_testing_tag(&item["hit-testing-tag"]) {
+            Some(tag) => tag,
+            None => return,
+        };
+        let pass_through = item["pass-through"].as_bool().unwrap_or(false);
+
+        match item["shape"].as_str() {
+            None | Some("rect") => dl.push_hit_test_shape(info, tag, HitTestShape::Rect, pass_through),
+            Some("rounded-rect") => {
+                let radius = item["radius"]
+                    .as_border_radius()
+                    .expect("rounded-rect hit-test shape must have a radius");
+                dl.push_hit_test_shape(info, tag, HitTestShape::RoundedRect(radius), pass_through);
+            }
+            Some("ellipse") => dl.push_hit_test_shape(info, tag, HitTestShape::Ellipse, pass_through),
+            Some("polygon") => {
+                let points: Vec<LayoutPoint> = item["points"]
+                    .as_vec()
+                    .expect("polygon hit-test shape must have points")
+                    .iter()
+                    .map(|point| point.as_point().expect("polygon points must be points"))
+                    .collect();
+                let fill_rule = item["fill-rule"].as_fill_rule().unwrap_or(FillRule::Nonzero);
+                dl.push_hit_test_polygon(info, tag, &points, fill_rule, pass_through);
+            }
+            Some(shape) => panic!("Unknown hit-test shape '{}'", shape),
         }
     }
 
+    fn handle_path(
+        &mut self,
+        dl: &mut DisplayListBuilder,
+        item: &Yaml,
+        info: &mut CommonItemProperties,
+    ) {
+        let bounds = item["bounds"].as_rect().expect("path must have bounds");
+        let segments = item["d"].as_path_segments().expect("path must have valid path data");
+        let color = item["color"].as_colorf().unwrap_or(ColorF::BLACK);
+        let fill_rule = item["fill-rule"].as_fill_rule().unwrap_or(FillRule::Nonzero);
+        let stroke = item["stroke"].as_stroke_style();
+
+        dl.push_path(
+            info,
+            bounds,
+            &segments,
+            color,
+            fill_rule,
+            stroke,
+        );
+    }
+
     fn handle_line(
         &mut self,
         dl: &mut DisplayListBuilder,
@@ -1318,6 +1494,14 @@ impl YamlFrameReader {
         let (image_key, image_dims) =
             self.add_or_get_image(&file, tiling, item, wrench);
 
+        let source_rect: Option<DeviceIntRect> = item["source-rect"]
+            .as_rect()
+            .map(|rect| rect.round().to_i32().cast_unit());
+        let image_dims = match source_rect {
+            Some(rect) => rect.size().to_f32().cast_unit(),
+            None => image_dims,
+        };
+
         let bounds_raws = item["bounds"].as_vec_f32().unwrap();
         let bounds = if bounds_raws.len() == 2 {
             LayoutRect::from_origin_and_size(LayoutPoint::new(bounds_raws[0], bounds_raws[1]), image_dims)
@@ -1336,8 +1520,9 @@ impl YamlFrameReader {
             Some("auto") | None => ImageRendering::Auto,
             Some("crisp-edges") => ImageRendering::CrispEdges,
             Some("pixelated") => ImageRendering::Pixelated,
+            Some("high-quality") => ImageRendering::HighQuality,
             Some(_) => panic!(
-                "ImageRendering can be auto, crisp-edges, or pixelated -- got {:?}",
+                "ImageRendering can be auto, crisp-edges, pixelated, or high-quality -- got {:?}",
                 item
             ),
         };
@@ -1351,21 +1536,45 @@ impl YamlFrameReader {
         };
         let stretch_size = item["stretch-size"].as_size();
         let tile_spacing = item["tile-spacing"].as_size();
-        if stretch_size.is_none() && tile_spacing.is_none() {
-            dl.push_image(
-                info,
-                bounds,
-                rendering,
-                alpha_type,
-                image_key,
-                ColorF::WHITE,
-           );
+        let repeat_horizontal = item["repeat-horizontal"].as_str();
+        let repeat_vertical = item["repeat-vertical"].as_str();
+        if stretch_size.is_none() && tile_spacing.is_none() &&
+            repeat_horizontal.is_none() && repeat_vertical.is_none() {
+            match source_rect {
+                Some(source_rect) => dl.push_image_with_source_rect(
+                    info,
+                    bounds,
+                    source_rect,
+                    rendering,
+                    alpha_type,
+                    image_key,
+                    ColorF::WHITE,
+                ),
+                None => dl.push_image(
+                    info,
+                    bounds,
+                    rendering,
+                    alpha_type,
+                    image_key,
+                    ColorF::WHITE,
+                ),
+            }
         } else {
-            dl.push_repeating_image(
+            let repeat_mode = |mode: Option<&str>| match mode.unwrap_or("repeat") {
+                "stretch" => RepeatMode::Stretch,
+                "repeat" => RepeatMode::Repeat,
+                "round" => RepeatMode::Round,
+                "space" => RepeatMode::Space,
+                s => panic!("Unknown image repeat mode {}", s),
+            };
+            dl.push_repeating_image_with_modes(
                 info,
                 bounds,
                 stretch_size.unwrap_or(image_dims),
                 tile_spacing.unwrap_or_else(LayoutSize::zero),
+                source_rect,
+                repeat_mode(repeat_horizontal),
+                repeat_mode(repeat_vertical),
                 rendering,
                 alpha_type,
                 image_key,
@@ -1437,19 +1646,31 @@ impl YamlFrameReader {
             let glyph_indices = item["glyphs"].as_vec_u32().unwrap();
             let glyph_offsets = item["offsets"].as_vec_f32().unwrap();
             assert_eq!(glyph_offsets.len(), glyph_indices.len() * 2);
+            let glyph_orientations = item["orientations"].as_vec_string().map(|orientations| {
+                assert_eq!(orientations.len(), glyph_indices.len());
+                orientations
+                    .iter()
+                    .map(|orientation| {
+                        GlyphOrientation::from_str(orientation).expect("unknown glyph orientation")
+                    })
+                    .collect::<Vec<_>>()
+            });
 
             let glyphs = glyph_indices
                 .iter()
                 .enumerate()
                 .map(|k| {
-                    GlyphInstance {
-                        index: *k.1,
+                    GlyphInstance::new(
+                        *k.1,
                         // In the future we want to change the API to be relative, eliminating this
-                        point: LayoutPoint::new(
+                        LayoutPoint::new(
                             origin.x + glyph_offsets[k.0 * 2],
                             origin.y + glyph_offsets[k.0 * 2 + 1],
                         ),
-                    }
+                        glyph_orientations
+                            .as_ref()
+                            .map_or(GlyphOrientation::Upright, |orientations| orientations[k.0]),
+                    )
                 })
                 .collect::<Vec<_>>();
             // TODO(gw): We could optionally use the WR API to query glyph dimensions
@@ -1485,14 +1706,25 @@ impl YamlFrameReader {
             (glyphs, bounds)
         };
 
-        dl.push_text(
-            info,
-            rect,
-            &glyphs,
-            font_instance_key,
-            color,
-            None,
-        );
+        match item["decoration"].as_text_decoration() {
+            Some(decoration) => dl.push_text_with_decoration(
+                info,
+                rect,
+                &glyphs,
+                font_instance_key,
+                color,
+                None,
+                decoration,
+            ),
+            None => dl.push_text(
+                info,
+                rect,
+                &glyphs,
+                font_instance_key,
+                color,
+                None,
+            ),
+        }
     }
 
     fn handle_iframe(
@@ -1616,6 +1848,7 @@ impl YamlFrameReader {
                 "hit-test" => self.handle_hit_test(dl, item, &mut info),
                 "clear-rect" => self.handle_clear_rect(dl, item, &info),
                 "line" => self.handle_line(dl, item, &mut info),
+                "path" => self.handle_path(dl, item, &mut info),
                 "image" => self.handle_image(dl, wrench, item, &mut info),
                 "yuv-image" => self.handle_yuv_image(dl, wrench, item, &mut info),
                 "text" | "glyphs" => self.handle_text(dl, wrench, item, &mut info),
@@ -1635,7 +1868,7 @@ impl YamlFrameReader {
                 "reference-frame" => self.handle_reference_frame(dl, wrench, item),
                 "shadow" => self.handle_push_shadow(dl, item, &mut info),
                 "pop-all-shadows" => self.handle_pop_all_shadows(dl),
-                "backdrop-filter" => self.handle_backdrop_filter(dl, item, &mut info),
+                "backdrop-filter" => self.handle_backdrop_filter(dl, wrench, item, &mut info),
                 _ => println!("Skipping unknown item type: {:?}", item),
             }
 
@@ -1893,6 +2126,19 @@ impl YamlFrameReader {
             );
         }
 
+        if let Some(points) = yaml["polygon"].as_vec() {
+            let points: Vec<LayoutPoint> = points
+                .iter()
+                .map(|point| point.as_point().expect("polygon points must be points"))
+                .collect();
+            let fill_rule = yaml["fill-rule"].as_fill_rule().unwrap_or(FillRule::Nonzero);
+            space_and_clip.clip_id = dl.define_clip_polygon(
+                &space_and_clip,
+                &points,
+                fill_rule,
+            );
+        }
+
         if let Some(numeric_id) = numeric_id {
             self.add_clip_id_mapping(numeric_id as u64, space_and_clip.clip_id);
             self.add_spatial_id_mapping(numeric_id as u64, space_and_clip.spatial_id);
@@ -2042,7 +2288,8 @@ impl YamlFrameReader {
 
         let filters = yaml["filters"].as_vec_filter_op().unwrap_or_default();
         let filter_datas = yaml["filter-datas"].as_vec_filter_data().unwrap_or_default();
-        let filter_primitives = yaml["filter-primitives"].as_vec_filter_primitive().unwrap_or_default();
+        let mut filter_primitives = yaml["filter-primitives"].as_vec_filter_primitive().unwrap_or_default();
+        self.add_filter_primitive_images(&mut filter_primitives, &yaml["filter-primitives"], wrench);
 
         let mut flags = StackingContextFlags::empty();
         flags.set(StackingContextFlags::IS_BACKDROP_ROOT, is_backdrop_root);
@@ -2077,6 +2324,7 @@ impl YamlFrameReader {
     fn handle_backdrop_filter(
         &mut self,
         dl: &mut DisplayListBuilder,
+        wrench: &mut Wrench,
         item: &Yaml,
         info: &mut CommonItemProperties,
     ) {
@@ -2087,7 +2335,8 @@ impl YamlFrameReader {
 
         let filters = item["filters"].as_vec_filter_op().unwrap_or_default();
         let filter_datas = item["filter-datas"].as_vec_filter_data().unwrap_or_default();
-        let filter_primitives = item["filter-primitives"].as_vec_filter_primitive().unwrap_or_default();
+        let mut filter_primitives = item["filter-primitives"].as_vec_filter_primitive().unwrap_or_default();
+        self.add_filter_primitive_images(&mut filter_primitives, &item["filter-primitives"], wrench);
 
         dl.push_backdrop_filter(
             info,
@@ -2096,6 +2345,28 @@ impl YamlFrameReader {
             &filter_primitives,
         );
     }
+
+    /// Image filter primitives refer to an image file, which has to be added
+    /// as a resource before the primitive can be pushed.
+    fn add_filter_primitive_images(
+        &mut self,
+        filter_primitives: &mut [FilterPrimitive],
+        yaml: &Yaml,
+        wrench: &mut Wrench,
+    ) {
+        let yaml_primitives = match yaml.as_vec() {
+            Some(v) => v.as_slice(),
+            None => std::slice::from_ref(yaml),
+        };
+
+        for (primitive, item) in filter_primitives.iter_mut().zip(yaml_primitives) {
+            if let FilterPrimitiveKind::Image(ref mut image) = primitive.kind {
+                let file = rsrc_path(&item["src"], &self.aux_dir);
+                let (image_key, _) = self.add_or_get_image(&file, None, item, wrench);
+                image.image_key = image_key;
+            }
+        }
+    }
 }
 
 impl WrenchThing for YamlFrameReader {
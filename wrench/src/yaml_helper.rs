@@ -33,6 +33,7 @@ pub trait YamlHelper {
     fn as_transform_style(&self) -> Option<TransformStyle>;
     fn as_raster_space(&self) -> Option<RasterSpace>;
     fn as_clip_mode(&self) -> Option<ClipMode>;
+    fn as_fill_rule(&self) -> Option<FillRule>;
     fn as_mix_blend_mode(&self) -> Option<MixBlendMode>;
     fn as_filter_op(&self) -> Option<FilterOp>;
     fn as_vec_filter_op(&self) -> Option<Vec<FilterOp>>;
@@ -41,9 +42,15 @@ pub trait YamlHelper {
     fn as_filter_input(&self) -> Option<FilterPrimitiveInput>;
     fn as_filter_primitive(&self) -> Option<FilterPrimitive>;
     fn as_vec_filter_primitive(&self) -> Option<Vec<FilterPrimitive>>;
+    fn as_light_source(&self) -> Option<LightSource>;
+    fn as_path_segments(&self) -> Option<Vec<PathSegment>>;
+    fn as_stroke_style(&self) -> Option<StrokeStyle>;
+    fn as_text_decoration(&self) -> Option<TextDecoration>;
     fn as_color_space(&self) -> Option<ColorSpace>;
+    fn as_rgb_color_space(&self) -> Option<RgbColorSpace>;
     fn as_complex_clip_region(&self) -> ComplexClipRegion;
     fn as_sticky_offset_bounds(&self) -> StickyOffsetBounds;
+    fn as_gradient_interpolation(&self) -> GradientInterpolation;
     fn as_gradient(&self, dl: &mut DisplayListBuilder) -> Gradient;
     fn as_radial_gradient(&self, dl: &mut DisplayListBuilder) -> RadialGradient;
     fn as_conic_gradient(&self, dl: &mut DisplayListBuilder) -> ConicGradient;
@@ -154,6 +161,14 @@ define_string_enum!(
 
 define_string_enum!(ClipMode, [Clip = "clip", ClipOut = "clip-out"]);
 
+define_string_enum!(GlyphOrientation, [Upright = "upright", Sideways = "sideways"]);
+
+define_string_enum!(LineJoin, [Miter = "miter", Round = "round", Bevel = "bevel"]);
+
+define_string_enum!(LineCap, [Butt = "butt", Round = "round", Square = "square"]);
+
+define_string_enum!(FillRule, [Nonzero = "nonzero", Evenodd = "evenodd"]);
+
 define_string_enum!(
     ComponentTransferFuncType,
     [
@@ -165,6 +180,17 @@ define_string_enum!(
     ]
 );
 
+define_string_enum!(
+    TurbulenceNoiseType,
+    [FractalNoise = "fractal-noise", Turbulence = "turbulence"]
+);
+
+define_string_enum!(MorphologyOperator, [Erode = "erode", Dilate = "dilate"]);
+
+define_string_enum!(EdgeMode, [Duplicate = "duplicate", Wrap = "wrap", None = "none"]);
+
+define_string_enum!(ColorChannel, [R = "r", G = "g", B = "b", A = "a"]);
+
 define_string_enum!(
     ColorSpace,
     [
@@ -173,6 +199,37 @@ define_string_enum!(
     ]
 );
 
+define_string_enum!(
+    RgbColorSpace,
+    [
+        Srgb = "srgb",
+        DisplayP3 = "display-p3",
+        Rec2020 = "rec2020",
+        LinearSrgb = "linear-srgb"
+    ]
+);
+
+define_string_enum!(
+    GradientColorSpace,
+    [
+        Srgb = "srgb",
+        SrgbLinear = "srgb-linear",
+        Oklab = "oklab",
+        Oklch = "oklch",
+        Hsl = "hsl"
+    ]
+);
+
+define_string_enum!(
+    HueInterpolationMethod,
+    [
+        Shorter = "shorter",
+        Longer = "longer",
+        Increasing = "increasing",
+        Decreasing = "decreasing"
+    ]
+);
+
 // Rotate around `axis` by `degrees` angle
 fn make_rotation(
     origin: &LayoutPoint,
@@ -536,6 +593,10 @@ impl YamlHelper for Yaml {
         self.as_str().and_then(StringEnum::from_str)
     }
 
+    fn as_fill_rule(&self) -> Option<FillRule> {
+        self.as_str().and_then(StringEnum::from_str)
+    }
+
     fn as_filter_op(&self) -> Option<FilterOp> {
         if let Some(s) = self.as_str() {
             match parse_function(s) {
@@ -546,32 +607,35 @@ impl YamlHelper for Yaml {
                     Some(FilterOp::ComponentTransfer)
                 }
                 ("blur", ref args, _) if args.len() == 2 => {
-                    Some(FilterOp::Blur(args[0].parse().unwrap(), args[1].parse().unwrap()))
+                    Some(FilterOp::Blur(
+                        args[0].parse::<f32>().unwrap().into(),
+                        args[1].parse::<f32>().unwrap().into(),
+                    ))
                 }
                 ("brightness", ref args, _) if args.len() == 1 => {
-                    Some(FilterOp::Brightness(args[0].parse().unwrap()))
+                    Some(FilterOp::Brightness(args[0].parse::<f32>().unwrap().into()))
                 }
                 ("contrast", ref args, _) if args.len() == 1 => {
-                    Some(FilterOp::Contrast(args[0].parse().unwrap()))
+                    Some(FilterOp::Contrast(args[0].parse::<f32>().unwrap().into()))
                 }
                 ("grayscale", ref args, _) if args.len() == 1 => {
-                    Some(FilterOp::Grayscale(args[0].parse().unwrap()))
+                    Some(FilterOp::Grayscale(args[0].parse::<f32>().unwrap().into()))
                 }
                 ("hue-rotate", ref args, _) if args.len() == 1 => {
-                    Some(FilterOp::HueRotate(args[0].parse().unwrap()))
+                    Some(FilterOp::HueRotate(args[0].parse::<f32>().unwrap().into()))
                 }
                 ("invert", ref args, _) if args.len() == 1 => {
-                    Some(FilterOp::Invert(args[0].parse().unwrap()))
+                    Some(FilterOp::Invert(args[0].parse::<f32>().unwrap().into()))
                 }
                 ("opacity", ref args, _) if args.len() == 1 => {
                     let amount: f32 = args[0].parse().unwrap();
                     Some(FilterOp::Opacity(amount.into(), amount))
                 }
                 ("saturate", ref args, _) if args.len() == 1 => {
-                    Some(FilterOp::Saturate(args[0].parse().unwrap()))
+                    Some(FilterOp::Saturate(args[0].parse::<f32>().unwrap().into()))
                 }
                 ("sepia", ref args, _) if args.len() == 1 => {
-                    Some(FilterOp::Sepia(args[0].parse().unwrap()))
+                    Some(FilterOp::Sepia(args[0].parse::<f32>().unwrap().into()))
                 }
                 ("srgb-to-linear", _, _)  => Some(FilterOp::SrgbToLinear),
                 ("linear-to-srgb", _, _)  => Some(FilterOp::LinearToSrgb),
@@ -764,6 +828,123 @@ impl YamlHelper for Yaml {
                         operator,
                     })
                 }
+                "turbulence" => {
+                    let base_frequency = self["base-frequency"].as_vec_f32().unwrap();
+                    let stitch_rect = self["stitch-tiles"].as_rect();
+                    FilterPrimitiveKind::Turbulence(TurbulencePrimitive {
+                        base_frequency_x: base_frequency[0],
+                        base_frequency_y: *base_frequency.get(1).unwrap_or(&base_frequency[0]),
+                        num_octaves: self["num-octaves"].as_i64().unwrap_or(1) as u32,
+                        seed: self["seed"].as_i64().unwrap_or(0) as i32,
+                        stitch_tiles: stitch_rect.is_some(),
+                        stitch_rect: stitch_rect.unwrap_or_else(LayoutRect::zero),
+                        noise_type: self["noise-type"]
+                            .as_str()
+                            .map(|s| StringEnum::from_str(s).unwrap())
+                            .unwrap_or(TurbulenceNoiseType::Turbulence),
+                    })
+                }
+                "morphology" => {
+                    let radius = self["radius"].as_vec_f32().unwrap();
+                    FilterPrimitiveKind::Morphology(MorphologyPrimitive {
+                        input: self["in"].as_filter_input().unwrap(),
+                        operator: StringEnum::from_str(self["operator"].as_str().unwrap()).unwrap(),
+                        radius_x: radius[0],
+                        radius_y: *radius.get(1).unwrap_or(&radius[0]),
+                    })
+                }
+                "convolve-matrix" => {
+                    let order = self["order"].as_vec_u32().unwrap();
+                    let (order_x, order_y) = (order[0], *order.get(1).unwrap_or(&order[0]));
+                    let values = self["kernel"].as_vec_f32().unwrap();
+                    assert_eq!(values.len() as u32, order_x * order_y, "Invalid convolve matrix kernel size");
+                    let mut kernel = [0.0; 25];
+                    kernel[.. values.len()].copy_from_slice(&values);
+                    let target = self["target"]
+                        .as_vec_u32()
+                        .unwrap_or_else(|| vec![order_x / 2, order_y / 2]);
+                    let sum: f32 = values.iter().sum();
+                    FilterPrimitiveKind::ConvolveMatrix(ConvolveMatrixPrimitive {
+                        input: self["in"].as_filter_input().unwrap(),
+                        order_x,
+                        order_y,
+                        kernel,
+                        divisor: self["divisor"].as_f32().unwrap_or(if sum == 0.0 { 1.0 } else { sum }),
+                        bias: self["bias"].as_f32().unwrap_or(0.0),
+                        target_x: target[0],
+                        target_y: target[1],
+                        edge_mode: self["edge-mode"]
+                            .as_str()
+                            .map(|s| StringEnum::from_str(s).unwrap())
+                            .unwrap_or(EdgeMode::Duplicate),
+                        preserve_alpha: self["preserve-alpha"].as_bool().unwrap_or(false),
+                    })
+                }
+                "displacement-map" => {
+                    FilterPrimitiveKind::DisplacementMap(DisplacementMapPrimitive {
+                        input1: self["in1"].as_filter_input().unwrap(),
+                        input2: self["in2"].as_filter_input().unwrap(),
+                        scale: self["scale"].as_f32().unwrap_or(0.0),
+                        x_channel: self["x-channel"]
+                            .as_str()
+                            .map(|s| StringEnum::from_str(s).unwrap())
+                            .unwrap_or(ColorChannel::A),
+                        y_channel: self["y-channel"]
+                            .as_str()
+                            .map(|s| StringEnum::from_str(s).unwrap())
+                            .unwrap_or(ColorChannel::A),
+                    })
+                }
+                "diffuse-lighting" => {
+                    FilterPrimitiveKind::DiffuseLighting(DiffuseLightingPrimitive {
+                        input: self["in"].as_filter_input().unwrap(),
+                        surface_scale: self["surface-scale"].as_f32().unwrap_or(1.0),
+                        diffuse_constant: self["diffuse-constant"].as_f32().unwrap_or(1.0),
+                        lighting_color: self["lighting-color"].as_colorf().unwrap_or(ColorF::WHITE),
+                        light: self["light"].as_light_source().unwrap(),
+                    })
+                }
+                "specular-lighting" => {
+                    FilterPrimitiveKind::SpecularLighting(SpecularLightingPrimitive {
+                        input: self["in"].as_filter_input().unwrap(),
+                        surface_scale: self["surface-scale"].as_f32().unwrap_or(1.0),
+                        specular_constant: self["specular-constant"].as_f32().unwrap_or(1.0),
+                        specular_exponent: self["specular-exponent"].as_f32().unwrap_or(1.0),
+                        lighting_color: self["lighting-color"].as_colorf().unwrap_or(ColorF::WHITE),
+                        light: self["light"].as_light_source().unwrap(),
+                    })
+                }
+                "tile" => {
+                    FilterPrimitiveKind::Tile(TilePrimitive {
+                        input: self["in"].as_filter_input().unwrap(),
+                        source_rect: self["source-rect"].as_rect().unwrap(),
+                    })
+                }
+                "image" => {
+                    // The image key is resolved by the frame reader, which
+                    // owns the image resources.
+                    FilterPrimitiveKind::Image(ImagePrimitive {
+                        image_key: ImageKey::DUMMY,
+                        rendering: match self["rendering"].as_str() {
+                            Some("auto") | None => ImageRendering::Auto,
+                            Some("crisp-edges") => ImageRendering::CrispEdges,
+                            Some("pixelated") => ImageRendering::Pixelated,
+                            Some("high-quality") => ImageRendering::HighQuality,
+                            Some(other) => panic!("Invalid image rendering {:?}", other),
+                        },
+                        rect: self["bounds"].as_rect().unwrap(),
+                    })
+                }
+                "merge" => {
+                    let inputs: Vec<FilterPrimitiveInput> = self["inputs"]
+                        .as_vec()
+                        .unwrap()
+                        .iter()
+                        .map(|input| input.as_filter_input().unwrap())
+                        .collect();
+                    assert!(inputs.len() <= MAX_MERGE_INPUTS, "Too many merge inputs");
+                    FilterPrimitiveKind::Merge(MergePrimitive::new(&inputs))
+                }
                 _ => return None,
             };
 
@@ -788,6 +969,175 @@ impl YamlHelper for Yaml {
         self.as_str().and_then(StringEnum::from_str)
     }
 
+    fn as_rgb_color_space(&self) -> Option<RgbColorSpace> {
+        self.as_str().and_then(StringEnum::from_str)
+    }
+
+    fn as_light_source(&self) -> Option<LightSource> {
+        let light = match self["type"].as_str()? {
+            "distant" => LightSource::Distant(DistantLight {
+                azimuth: self["azimuth"].as_f32().unwrap_or(0.0),
+                elevation: self["elevation"].as_f32().unwrap_or(0.0),
+            }),
+            "point" => {
+                let position = self["position"].as_vec_f32().unwrap();
+                LightSource::Point(PointLight {
+                    x: position[0],
+                    y: position[1],
+                    z: position[2],
+                })
+            }
+            "spot" => {
+                let position = self["position"].as_vec_f32().unwrap();
+                let points_at = self["points-at"].as_vec_f32().unwrap();
+                LightSource::Spot(SpotLight {
+                    x: position[0],
+                    y: position[1],
+                    z: position[2],
+                    points_at_x: points_at[0],
+                    points_at_y: points_at[1],
+                    points_at_z: points_at[2],
+                    specular_exponent: self["specular-exponent"].as_f32().unwrap_or(1.0),
+                    limiting_cone_angle: self["limiting-cone-angle"].as_f32().unwrap_or(-1.0),
+                })
+            }
+            _ => return None,
+        };
+        Some(light)
+    }
+
+    /// Parse an SVG path data string. Only the M, L, H, V, Q, C and Z commands
+    /// (and their relative variants) are supported.
+    fn as_path_segments(&self) -> Option<Vec<PathSegment>> {
+        let data = self.as_str()?;
+
+        // Split the data into command letters and numbers.
+        let mut tokens = Vec::new();
+        let mut number = String::new();
+        for c in data.chars() {
+            if c.is_ascii_alphabetic() && c != 'e' {
+                if !number.is_empty() {
+                    tokens.push(number.clone());
+                    number.clear();
+                }
+                tokens.push(c.to_string());
+            } else if c.is_whitespace() || c == ',' {
+                if !number.is_empty() {
+                    tokens.push(number.clone());
+                    number.clear();
+                }
+            } else {
+                number.push(c);
+            }
+        }
+        if !number.is_empty() {
+            tokens.push(number);
+        }
+
+        let mut segments = Vec::new();
+        let mut command = 'M';
+        let mut current = LayoutPoint::zero();
+        let mut start = LayoutPoint::zero();
+        let mut i = 0;
+        while i < tokens.len() {
+            if let Some(c) = tokens[i].chars().next().filter(|c| c.is_ascii_alphabetic()) {
+                command = c;
+                i += 1;
+                if c == 'Z' || c == 'z' {
+                    segments.push(PathSegment::Close);
+                    current = start;
+                    continue;
+                }
+            }
+
+            let relative = command.is_ascii_lowercase();
+            let arg_count = match command.to_ascii_uppercase() {
+                'M' | 'L' => 2,
+                'H' | 'V' => 1,
+                'Q' => 4,
+                'C' => 6,
+                _ => panic!("Unsupported path command {:?}", command),
+            };
+            let args = tokens.get(i .. i + arg_count)?
+                .iter()
+                .map(|token| f32::from_str(token).ok())
+                .collect::<Option<Vec<f32>>>()?;
+            i += arg_count;
+
+            let origin = if relative { current.to_vector() } else { LayoutVector2D::zero() };
+            let point = |j: usize| LayoutPoint::new(args[j], args[j + 1]) + origin;
+            let segment = match command.to_ascii_uppercase() {
+                'M' => {
+                    start = point(0);
+                    // Subsequent coordinate pairs are implicit line-to commands.
+                    command = if relative { 'l' } else { 'L' };
+                    PathSegment::MoveTo(start)
+                }
+                'L' => PathSegment::LineTo(point(0)),
+                'H' => PathSegment::LineTo(LayoutPoint::new(args[0] + origin.x, current.y)),
+                'V' => PathSegment::LineTo(LayoutPoint::new(current.x, args[0] + origin.y)),
+                'Q' => PathSegment::QuadraticTo(point(0), point(2)),
+                _ => PathSegment::CubicTo(point(0), point(2), point(4)),
+            };
+            current = match segment {
+                PathSegment::MoveTo(to) |
+                PathSegment::LineTo(to) |
+                PathSegment::QuadraticTo(_, to) |
+                PathSegment::CubicTo(_, _, to) => to,
+                PathSegment::Close => start,
+            };
+            segments.push(segment);
+        }
+
+        Some(segments)
+    }
+
+    fn as_stroke_style(&self) -> Option<StrokeStyle> {
+        let width = self["width"].as_f32()?;
+        let line_join = self["line-join"]
+            .as_str()
+            .and_then(StringEnum::from_str)
+            .unwrap_or(LineJoin::Miter);
+        let line_cap = self["line-cap"]
+            .as_str()
+            .and_then(StringEnum::from_str)
+            .unwrap_or(LineCap::Butt);
+        let mut stroke = StrokeStyle::new(width, line_join, line_cap)
+            .with_dashes(
+                &self["dashes"].as_vec_f32().unwrap_or_default(),
+                self["dash-offset"].as_f32().unwrap_or(0.0),
+            );
+        if let Some(miter_limit) = self["miter-limit"].as_f32() {
+            stroke.miter_limit = miter_limit;
+        }
+        Some(stroke)
+    }
+
+    fn as_text_decoration(&self) -> Option<TextDecoration> {
+        let mut lines = TextDecorationLines::empty();
+        for line in self["lines"].as_vec_string()? {
+            lines |= match line.as_str() {
+                "underline" => TextDecorationLines::UNDERLINE,
+                "overline" => TextDecorationLines::OVERLINE,
+                "line-through" => TextDecorationLines::LINE_THROUGH,
+                _ => panic!("unknown text decoration line {}", line),
+            };
+        }
+        Some(TextDecoration {
+            lines,
+            style: self["style"]
+                .as_str()
+                .and_then(StringEnum::from_str)
+                .unwrap_or(LineStyle::Solid),
+            color: self["color"].as_colorf().unwrap_or(ColorF::BLACK),
+            thickness: self["thickness"].as_f32().unwrap_or(1.0),
+            underline_offset: self["underline-offset"].as_f32().unwrap_or(0.0),
+            overline_offset: self["overline-offset"].as_f32().unwrap_or(0.0),
+            line_through_offset: self["line-through-offset"].as_f32().unwrap_or(0.0),
+            skip_ink: self["skip-ink"].as_bool().unwrap_or(false),
+        })
+    }
+
     fn as_complex_clip_region(&self) -> ComplexClipRegion {
         let rect = self["rect"]
             .as_rect()
@@ -811,6 +1161,19 @@ impl YamlHelper for Yaml {
         }
     }
 
+    fn as_gradient_interpolation(&self) -> GradientInterpolation {
+        GradientInterpolation {
+            color_space: self["color-space"]
+                .as_str()
+                .map(|s| StringEnum::from_str(s).expect("unknown gradient color space"))
+                .unwrap_or_default(),
+            hue: self["hue-interpolation"]
+                .as_str()
+                .map(|s| StringEnum::from_str(s).expect("unknown hue interpolation method"))
+                .unwrap_or_default(),
+        }
+    }
+
     fn as_gradient(&self, dl: &mut DisplayListBuilder) -> Gradient {
         let start = self["start"].as_point().expect("gradient must have start");
         let end = self["end"].as_point().expect("gradient must have end");
@@ -826,6 +1189,7 @@ impl YamlHelper for Yaml {
                     color: chunk[1]
                         .as_colorf()
                         .expect("gradient stop color is not color"),
+                    color_binding: None,
                 }
             })
             .collect::<Vec<_>>();
@@ -835,7 +1199,9 @@ impl YamlHelper for Yaml {
             ExtendMode::Clamp
         };
 
-        dl.create_gradient(start, end, stops, extend_mode)
+        let mut gradient = dl.create_gradient(start, end, stops, extend_mode);
+        gradient.interpolation = self.as_gradient_interpolation();
+        gradient
     }
 
     fn as_radial_gradient(&self, dl: &mut DisplayListBuilder) -> RadialGradient {
@@ -853,6 +1219,7 @@ impl YamlHelper for Yaml {
                     color: chunk[1]
                         .as_colorf()
                         .expect("gradient stop color is not color"),
+                    color_binding: None,
                 }
             })
             .collect::<Vec<_>>();
@@ -862,7 +1229,9 @@ impl YamlHelper for Yaml {
             ExtendMode::Clamp
         };
 
-        dl.create_radial_gradient(center, radius, stops, extend_mode)
+        let mut gradient = dl.create_radial_gradient(center, radius, stops, extend_mode);
+        gradient.interpolation = self.as_gradient_interpolation();
+        gradient
     }
 
     fn as_conic_gradient(&self, dl: &mut DisplayListBuilder) -> ConicGradient {
@@ -880,6 +1249,7 @@ impl YamlHelper for Yaml {
                     color: chunk[1]
                         .as_colorf()
                         .expect("gradient stop color is not color"),
+                    color_binding: None,
                 }
             })
             .collect::<Vec<_>>();
@@ -889,7 +1259,9 @@ impl YamlHelper for Yaml {
             ExtendMode::Clamp
         };
 
-        dl.create_conic_gradient(center, angle, stops, extend_mode)
+        let mut gradient = dl.create_conic_gradient(center, angle, stops, extend_mode);
+        gradient.interpolation = self.as_gradient_interpolation();
+        gradient
     }
 
     fn as_complex_clip_regions(&self) -> Vec<ComplexClipRegion> {